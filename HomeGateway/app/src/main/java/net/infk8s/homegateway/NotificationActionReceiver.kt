@@ -0,0 +1,78 @@
+package net.infk8s.homegateway
+
+import android.app.NotificationManager
+import android.content.BroadcastReceiver
+import android.content.Context
+import android.content.Intent
+import android.util.Log
+import androidx.core.content.getSystemService
+import okhttp3.MediaType.Companion.toMediaType
+import okhttp3.OkHttpClient
+import okhttp3.Request
+import okhttp3.RequestBody.Companion.toRequestBody
+import org.json.JSONObject
+import java.util.concurrent.TimeUnit
+
+/// Reports a tapped notification button back to the gateway, which publishes it
+/// as a `notification_action` event. Mirrors the auth/URL pattern used by [AlarmWorker].
+class NotificationActionReceiver : BroadcastReceiver() {
+    override fun onReceive(context: Context, intent: Intent) {
+        val notificationId = intent.getStringExtra(EXTRA_NOTIFICATION_ID) ?: return
+        val action = intent.getStringExtra(EXTRA_ACTION) ?: return
+
+        context.getSystemService<NotificationManager>()
+            ?.cancel(intent.getIntExtra(EXTRA_TAG, 0))
+
+        val pending = goAsync()
+        Thread {
+            try {
+                send(notificationId, action)
+            } finally {
+                pending.finish()
+            }
+        }.start()
+    }
+
+    private fun send(notificationId: String, action: String) {
+        val payload = mapOf(
+            "notification_id" to notificationId,
+            "action" to action,
+        )
+
+        val client = OkHttpClient.Builder()
+            .callTimeout(30, TimeUnit.SECONDS)
+            .build()
+
+        val apiKey = BuildConfig.HOME_GATEWAY_INGEST_API_SECRET
+        var url = "https://home.anurag.sh/v1/ingest/home/notification-action"
+        if (BuildConfig.DEBUG) {
+            url = "http://192.168.0.104:8000/v1/ingest/home/notification-action"
+        }
+
+        val request = Request.Builder()
+            .post(
+                JSONObject(payload).toString().toRequestBody("application/json".toMediaType())
+            )
+            .header("X-Webhook-Secret", apiKey)
+            .url(url)
+            .build()
+
+        try {
+            client.newCall(request).execute().use { response ->
+                if (response.isSuccessful) {
+                    Log.i("NotificationAction", "sent action $action")
+                } else {
+                    Log.e("NotificationAction", "failed to send action ${response.code}")
+                }
+            }
+        } catch (e: Exception) {
+            Log.e("NotificationAction", "error sending action", e)
+        }
+    }
+
+    companion object {
+        const val EXTRA_NOTIFICATION_ID = "notification_id"
+        const val EXTRA_ACTION = "action"
+        const val EXTRA_TAG = "tag"
+    }
+}
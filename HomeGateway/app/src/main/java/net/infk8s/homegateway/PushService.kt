@@ -2,11 +2,13 @@ package net.infk8s.homegateway
 
 import android.app.NotificationChannel
 import android.app.NotificationManager
-import android.content.Context
+import android.app.PendingIntent
+import android.content.Intent
 import androidx.core.app.NotificationCompat
 import androidx.core.content.getSystemService
 import com.google.firebase.messaging.FirebaseMessagingService
 import com.google.firebase.messaging.RemoteMessage
+import org.json.JSONArray
 
 class PushService : FirebaseMessagingService() {
     override fun onNewToken(token: String) {
@@ -15,7 +17,9 @@ class PushService : FirebaseMessagingService() {
     }
 
     override fun onMessageReceived(message: RemoteMessage) {
-        val notification = message.notification ?: return
+        // Actionable notifications arrive data-only so they reach us in the background.
+        val title = message.notification?.title ?: message.data["title"] ?: return
+        val body = message.notification?.body ?: message.data["body"]
         val manager = getSystemService<NotificationManager>() ?: return
 
         // Channels are required since Android 8; creating an existing one is a no-op.
@@ -29,12 +33,42 @@ class PushService : FirebaseMessagingService() {
 
         val builder = NotificationCompat.Builder(this, CHANNEL_ID)
             .setSmallIcon(android.R.drawable.ic_dialog_info)
-            .setContentTitle(notification.title)
-            .setContentText(notification.body)
+            .setContentTitle(title)
+            .setContentText(body)
             .setPriority(NotificationCompat.PRIORITY_HIGH)
             .setAutoCancel(true)
 
-        manager.notify(System.currentTimeMillis().toInt(), builder.build())
+        val tag = System.currentTimeMillis().toInt()
+        addActions(builder, message.data, tag)
+
+        manager.notify(tag, builder.build())
+    }
+
+    /// Reply buttons arrive as data: the id the gateway recorded the notification
+    /// under, and a JSON array of `{id, title}`.
+    private fun addActions(
+        builder: NotificationCompat.Builder,
+        data: Map<String, String>,
+        tag: Int,
+    ) {
+        val notificationId = data["notification_id"] ?: return
+        val actions = JSONArray(data["actions"] ?: return)
+
+        for (i in 0 until actions.length()) {
+            val action = actions.getJSONObject(i)
+            val intent = Intent(this, NotificationActionReceiver::class.java)
+                .putExtra(NotificationActionReceiver.EXTRA_NOTIFICATION_ID, notificationId)
+                .putExtra(NotificationActionReceiver.EXTRA_ACTION, action.getString("id"))
+                .putExtra(NotificationActionReceiver.EXTRA_TAG, tag)
+            val pending = PendingIntent.getBroadcast(
+                this,
+                tag + i,
+                intent,
+                PendingIntent.FLAG_IMMUTABLE or PendingIntent.FLAG_UPDATE_CURRENT,
+            )
+
+            builder.addAction(0, action.getString("title"), pending)
+        }
     }
 
     companion object {
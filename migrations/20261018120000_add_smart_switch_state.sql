@@ -0,0 +1,5 @@
+CREATE TABLE smart_switch_state (
+    address TEXT PRIMARY KEY,
+    state TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1 @@
+SELECT pgmq.create('workflow_delays');
@@ -0,0 +1,9 @@
+CREATE TABLE notification_actions (
+    id UUID PRIMARY KEY,
+    event_id UUID NOT NULL,
+    source TEXT NOT NULL,
+    actions TEXT[] NOT NULL,
+    sent_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    action TEXT,
+    responded_at TIMESTAMPTZ
+);
@@ -0,0 +1,19 @@
+CREATE TABLE notification_suppressions (
+    id UUID PRIMARY KEY,
+    target TEXT NOT NULL,
+    channel TEXT NOT NULL,
+    source TEXT NOT NULL,
+    event_id UUID NOT NULL,
+    message TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    digest BOOLEAN NOT NULL,
+    suppressed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    digested_at TIMESTAMPTZ
+);
+
+CREATE INDEX notification_suppressions_suppressed_at_idx
+    ON notification_suppressions (suppressed_at DESC);
+
+CREATE INDEX notification_suppressions_held_idx
+    ON notification_suppressions (suppressed_at)
+    WHERE digest AND digested_at IS NULL;
@@ -0,0 +1,7 @@
+CREATE TABLE eink_album_pins (
+    object_key TEXT PRIMARY KEY,
+    album TEXT NOT NULL,
+    pinned_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX eink_album_pins_album_idx ON eink_album_pins (album);
@@ -0,0 +1,19 @@
+CREATE TABLE eink_render_history (
+    id BIGSERIAL PRIMARY KEY,
+    device_id TEXT NOT NULL,
+    render_hash TEXT NOT NULL,
+    previous_hash TEXT,
+    image_key TEXT NOT NULL,
+    image_content_hash TEXT NOT NULL,
+    sleep_label TEXT,
+    fingerprint TEXT[] NOT NULL,
+    clear_screen BOOLEAN NOT NULL,
+    partial_x INTEGER,
+    partial_y INTEGER,
+    partial_width INTEGER,
+    partial_height INTEGER,
+    rendered_at TIMESTAMPTZ,
+    served_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX eink_render_history_device_idx ON eink_render_history (device_id, served_at DESC);
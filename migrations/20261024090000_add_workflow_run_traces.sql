@@ -0,0 +1 @@
+ALTER TABLE workflow_runs ADD COLUMN trace JSONB;
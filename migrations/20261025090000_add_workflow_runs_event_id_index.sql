@@ -0,0 +1 @@
+CREATE INDEX workflow_runs_event_id_idx ON workflow_runs (event_id);
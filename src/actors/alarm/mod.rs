@@ -39,10 +39,9 @@ impl Actor for AlarmActor {
         myself: ractor::ActorRef<Self::Msg>,
         _args: Self::Arguments,
     ) -> Result<Self::State, ractor::ActorProcessingErr> {
-        let offset = self.shared_actor_state.settings.alarm.offset;
-        let poll_interval = self
-            .shared_actor_state
-            .settings
+        let settings = self.shared_actor_state.settings();
+        let offset = settings.alarm.offset;
+        let poll_interval = settings
             .alarm
             .poll_interval
             .to_std()
@@ -88,7 +87,8 @@ impl Actor for AlarmActor {
                         now.timestamp_millis() >= (alarm_time - offset).timestamp_millis();
 
                     if should_trigger {
-                        let workflow_name = &self.shared_actor_state.settings.alarm.workflow;
+                        let settings = self.shared_actor_state.settings();
+                        let workflow_name = &settings.alarm.workflow;
                         tracing::info!("triggering `{workflow_name}` workflow");
                         let Some(workflow_actor) = ractor::registry::where_is(WorkflowWorker::NAME)
                         else {
@@ -96,12 +96,7 @@ impl Actor for AlarmActor {
                             return Ok(());
                         };
 
-                        let Some(workflow) = self
-                            .shared_actor_state
-                            .settings
-                            .workflows
-                            .get(workflow_name)
-                        else {
+                        let Some(workflow) = settings.workflows.get(workflow_name) else {
                             tracing::warn!("alarm workflow `{workflow_name}` not configured");
                             return Ok(());
                         };
@@ -114,6 +109,7 @@ impl Actor for AlarmActor {
                                 event_id,
                                 workflow,
                                 vars: HashMap::new(),
+                                reply: None,
                             },
                             options: JobOptions::default(),
                             accepted: None,
@@ -1,4 +1,8 @@
-use crate::{event_bus::EventBusMessage, state::SharedActorState};
+use crate::{
+    device_metric::{DeviceMetric, MetricValue},
+    event_bus::EventBusMessage,
+    state::SharedActorState,
+};
 use ractor::{
     ActorProcessingErr, ActorRef,
     factory::{FactoryMessage, Job, Worker, WorkerBuilder, WorkerId},
@@ -29,10 +33,30 @@ pub struct ControlSwitchHandler {
 impl ControlSwitchHandler {
     pub const NAME: &str = "control-switch";
 
+    /// The metric a switch's presses are recorded under; the latest one is the
+    /// switch's last action.
+    pub const ACTION_METRIC: &str = "action";
+
     async fn handle(&self, message: ControlSwitchMessage) -> Result<(), anyhow::Error> {
         match message {
             ControlSwitchMessage::NewEvent(event) => match &event.entity {
                 Entity::Zigbee { address, action } => {
+                    let record = DeviceMetric {
+                        event_id: event.event_id,
+                        address: address.clone(),
+                        device_id: self
+                            .shared_actor_state
+                            .devices()
+                            .id_for_address(address)
+                            .map(str::to_owned),
+                        metric: Self::ACTION_METRIC.to_owned(),
+                        value: MetricValue::Text(action.clone()),
+                    };
+
+                    if let Err(e) = record.save(&self.shared_actor_state.db).await {
+                        tracing::error!("failed to save control switch action for {address}: {e}");
+                    }
+
                     self.shared_actor_state
                         .event_bus
                         .publish(EventBusMessage::SwitchAction {
@@ -1,6 +1,6 @@
 use super::{DoorEvents, DoorEventsMessage, DoorEventsType};
 use crate::{
-    integrations::notify::notify,
+    integrations::notify::{Origin, notify},
     settings::{ArmedDoorStates, IEEEAddress},
     state::SharedActorState,
 };
@@ -8,6 +8,7 @@ use chrono::{DateTime, Utc};
 use ractor::Actor;
 use std::collections::HashMap;
 use tracing::Level;
+use uuid::Uuid;
 
 pub enum DoorState {
     Open,
@@ -28,10 +29,11 @@ impl ArmedDoor {
 }
 
 impl ArmedDoor {
-    pub fn trigger_action(&self, ieee_addr: &IEEEAddress) {
-        if let Some(settings) = self.shared_actor_state.devices.door(ieee_addr) {
+    pub fn trigger_action(&self, event_id: Uuid, ieee_addr: &IEEEAddress) {
+        if let Some(settings) = self.shared_actor_state.devices().door(ieee_addr) {
             let message = format!("{} has been left open.", settings.name);
-            notify(&settings.notify, message);
+            let origin = Origin::new(event_id, format!("door:{}", settings.id));
+            notify(&settings.notify, message, origin, &settings.actions);
         }
     }
 }
@@ -73,7 +75,7 @@ impl Actor for ArmedDoor {
         match event {
             DoorEventsType::Opened => {
                 state.map.insert(ieee_addr.clone(), DoorState::Open);
-                if let Some(value) = self.shared_actor_state.devices.door(&ieee_addr)
+                if let Some(value) = self.shared_actor_state.devices().door(&ieee_addr)
                     && let ArmedDoorStates::Armed { timeout } = value.armed
                 {
                     let duration = timeout.to_std()?;
@@ -100,10 +102,10 @@ impl Actor for ArmedDoor {
                                 if difference.num_seconds() <= 60 {
                                     tracing::info!("de-duped event for door trigger: {ieee_addr}");
                                 } else {
-                                    self.trigger_action(&ieee_addr);
+                                    self.trigger_action(event_id, &ieee_addr);
                                 }
                             } else {
-                                self.trigger_action(&ieee_addr);
+                                self.trigger_action(event_id, &ieee_addr);
                             }
 
                             state.last_trigger.insert(ieee_addr, now);
@@ -142,7 +142,7 @@ impl EnvironmentSensorHandler {
                 } => {
                     let Some(metric) = self
                         .shared_actor_state
-                        .devices
+                        .devices()
                         .environment(&node)
                         .and_then(|s| s.entities.get(&object_id).copied())
                     else {
@@ -174,7 +174,7 @@ impl EnvironmentSensorHandler {
 
                     let friendly_name = self
                         .shared_actor_state
-                        .devices
+                        .devices()
                         .friendly_name(&node)
                         .await
                         .unwrap_or_else(|| node.clone());
@@ -215,11 +215,8 @@ impl EnvironmentSensorHandler {
         lux: Option<f64>,
         uv_index: Option<f64>,
     ) -> Result<(), anyhow::Error> {
-        let id = self
-            .shared_actor_state
-            .devices
-            .environment(&ieee_addr)
-            .map(|s| &s.id);
+        let devices = self.shared_actor_state.devices();
+        let id = devices.environment(&ieee_addr).map(|s| &s.id);
         let now = Utc::now();
 
         sqlx::query!(
@@ -21,9 +21,10 @@ pub struct NewEvent {
 }
 
 pub enum LightHandlerMessage {
+    /// `None` until the light has reported a state.
     QueryPowerState {
         ieee_addr: IEEEAddress,
-        reply: RpcReplyPort<bool>,
+        reply: RpcReplyPort<Option<bool>>,
     },
     NewEvent(Box<NewEvent>),
     TurnOn {
@@ -99,11 +100,22 @@ pub async fn record_light_state(
     ieee_addr: IEEEAddress,
     state: String,
 ) -> Result<(), anyhow::Error> {
-    sqlx::query!(
-        "INSERT INTO light_state (ieee_address, state) VALUES ($1, $2) ON CONFLICT (ieee_address) DO UPDATE SET state = EXCLUDED.state",
+    let previous = sqlx::query_scalar!(
+        "WITH prior AS (SELECT state FROM light_state WHERE ieee_address = $1) \
+         INSERT INTO light_state (ieee_address, state) VALUES ($1, $2) \
+         ON CONFLICT (ieee_address) DO UPDATE SET state = EXCLUDED.state \
+         RETURNING (SELECT state FROM prior) AS previous",
         ieee_addr,
         state,
-    ).execute(&shared_actor_state.db).await?;
+    )
+    .fetch_one(&shared_actor_state.db)
+    .await?;
+
+    // zigbee2mqtt republishes the whole state on every brightness/colour
+    // change; only the power edges go on the bus
+    if previous.as_deref() == Some(state.as_str()) {
+        return Ok(());
+    }
 
     shared_actor_state
         .event_bus
@@ -142,7 +154,7 @@ impl LightHandler {
     fn warn_if_unsupported(&self, ieee_addr: &str, capability: Capability) {
         if !self
             .shared_actor_state
-            .devices
+            .devices()
             .capabilities(ieee_addr)
             .contains(&capability)
         {
@@ -171,7 +183,7 @@ impl LightHandler {
             LightHandlerMessage::Toggle { ieee_addr } => {
                 let state = if self
                     .shared_actor_state
-                    .devices
+                    .devices()
                     .esphome_light(&ieee_addr)
                     .is_some()
                 {
@@ -227,10 +239,10 @@ impl LightHandler {
                     "SELECT state FROM light_state WHERE ieee_address = $1",
                     ieee_addr
                 )
-                .fetch_one(&self.shared_actor_state.db)
+                .fetch_optional(&self.shared_actor_state.db)
                 .await?;
 
-                let is_on = light_state.state == "ON";
+                let is_on = light_state.map(|light_state| light_state.state == "ON");
 
                 reply.send(is_on)?;
             }
@@ -244,7 +256,7 @@ impl LightHandler {
         ieee_addr: String,
         state: serde_json::Value,
     ) -> Result<(), anyhow::Error> {
-        if let Some(object_id) = self.shared_actor_state.devices.esphome_light(&ieee_addr) {
+        if let Some(object_id) = self.shared_actor_state.devices().esphome_light(&ieee_addr) {
             let topic = light_command_topic(&ieee_addr, object_id);
             let Some(state) = esphome_command(&state) else {
                 tracing::warn!("esphome light {ieee_addr} does not support command: {state}");
@@ -259,7 +271,7 @@ impl LightHandler {
 
         let target = self
             .shared_actor_state
-            .devices
+            .devices()
             .friendly_name(&ieee_addr)
             .await
             .unwrap_or_else(|| ieee_addr.clone());
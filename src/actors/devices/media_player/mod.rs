@@ -44,7 +44,7 @@ impl MediaPlayerHandler {
             attributes,
         } = update;
 
-        let devices = &self.shared_actor_state.devices;
+        let devices = self.shared_actor_state.devices();
         let Some(settings) = devices.media_player(&address) else {
             tracing::warn!("media player update for unregistered entity {address}");
             return Ok(());
@@ -88,6 +88,19 @@ pub fn edges(prior: Option<&Prior>, now: &Prior) -> Vec<PlaybackState> {
     }
 }
 
+/// The edge that left a player in `state`, judged from the state alone, for
+/// when there's no prior to compare against. A playing player counts as
+/// `started`; a resume can't be told apart from it.
+pub fn settled(state: &str) -> PlaybackState {
+    if !is_active(state) {
+        PlaybackState::Stopped
+    } else if is_paused(state) {
+        PlaybackState::Paused
+    } else {
+        PlaybackState::Started
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -171,4 +184,12 @@ mod tests {
             .is_empty()
         );
     }
+
+    #[test]
+    fn a_settled_state_is_the_edge_that_left_it() {
+        assert_eq!(settled("playing"), PlaybackState::Started);
+        assert_eq!(settled("buffering"), PlaybackState::Started);
+        assert_eq!(settled("paused"), PlaybackState::Paused);
+        assert_eq!(settled("off"), PlaybackState::Stopped);
+    }
 }
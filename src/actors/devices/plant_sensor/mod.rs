@@ -1,4 +1,5 @@
 use crate::{
+    device_metric::{DeviceMetric, MetricValue},
     event_bus::{EventBusMessage, SensorReading},
     state::SharedActorState,
 };
@@ -30,12 +31,29 @@ pub struct PlantSensorHandler {
 impl PlantSensorHandler {
     pub const NAME: &str = "plant-sensor";
 
-    /// Publish each reading onto the bus as an `Environment` event keyed by node
-    /// and object_id. Thresholds and rising-edge handling live in the dispatcher
+    /// Record each reading in the device metric sink for the plant's history,
+    /// then publish it onto the bus as an `Environment` event keyed by node and
+    /// object_id. Thresholds and rising-edge handling live in the dispatcher
     /// (driven by `triggers:`).
-    fn handle(&self, message: Message) -> Result<(), anyhow::Error> {
+    async fn handle(&self, message: Message) -> Result<(), anyhow::Error> {
         let Message::NewEvent(event) = message;
 
+        let record = DeviceMetric {
+            event_id: event.event_id,
+            address: event.node.clone(),
+            device_id: self
+                .shared_actor_state
+                .devices()
+                .id_for_address(&event.node)
+                .map(str::to_owned),
+            metric: event.object_id.clone(),
+            value: MetricValue::Numeric(event.value),
+        };
+
+        if let Err(e) = record.save(&self.shared_actor_state.db).await {
+            tracing::error!("failed to save plant reading for {}: {e}", event.node);
+        }
+
         self.shared_actor_state
             .event_bus
             .publish(EventBusMessage::Environment {
@@ -70,7 +88,7 @@ impl Worker for PlantSensorHandler {
         Job { msg, .. }: Job<(), Message>,
         _state: &mut Self::State,
     ) -> Result<(), ActorProcessingErr> {
-        if let Err(e) = Self::handle(self, msg) {
+        if let Err(e) = Self::handle(self, msg).await {
             tracing::error!("error while handling message: {e}")
         }
 
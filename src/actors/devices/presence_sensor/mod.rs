@@ -1,6 +1,11 @@
 use std::collections::HashMap;
 
-use crate::{event_bus::EventBusMessage, state::SharedActorState};
+use crate::{
+    device_metric::{DeviceMetric, MetricValue},
+    event_bus::EventBusMessage,
+    history::PRESENCE_METRIC,
+    state::SharedActorState,
+};
 use ractor::{
     ActorProcessingErr, ActorRef, RpcReplyPort,
     factory::{FactoryMessage, Job, Worker, WorkerBuilder, WorkerId},
@@ -48,7 +53,7 @@ pub struct PresenceSensorHandler {
 impl PresenceSensorHandler {
     pub const NAME: &str = "presence-sensor";
 
-    fn process_presence(
+    async fn process_presence(
         &self,
         event_id: Uuid,
         key: String,
@@ -69,8 +74,24 @@ impl PresenceSensorHandler {
             .or_insert(presence);
 
         // edge-detected here so triggers receive discrete transitions, not every
-        // sensor ping
+        // sensor ping; only the transitions are kept for history too
         if was_state_changed {
+            let record = DeviceMetric {
+                event_id,
+                address: key.clone(),
+                device_id: self
+                    .shared_actor_state
+                    .devices()
+                    .id_for_address(&key)
+                    .map(str::to_owned),
+                metric: PRESENCE_METRIC.to_owned(),
+                value: MetricValue::Numeric(if presence { 1.0 } else { 0.0 }),
+            };
+
+            if let Err(e) = record.save(&self.shared_actor_state.db).await {
+                tracing::error!("failed to save presence transition for {key}: {e}");
+            }
+
             self.shared_actor_state
                 .event_bus
                 .publish(EventBusMessage::Presence {
@@ -94,7 +115,8 @@ impl PresenceSensorHandler {
             }
             Message::NewEvent(event) => match event.entity {
                 Entity::Zigbee { address, presence } => {
-                    self.process_presence(event.event_id, address, presence, state)?
+                    self.process_presence(event.event_id, address, presence, state)
+                        .await?
                 }
                 Entity::Esphome {
                     node,
@@ -104,7 +126,8 @@ impl PresenceSensorHandler {
                     let entities = state.esphome_entities.entry(node.clone()).or_default();
                     entities.insert(object_id, motion);
                     let present = entities.values().any(|&on| on);
-                    self.process_presence(event.event_id, node, present, state)?
+                    self.process_presence(event.event_id, node, present, state)
+                        .await?
                 }
             },
         }
@@ -1,4 +1,5 @@
 use crate::actors::system::battery::BatteryActor;
+use crate::event_bus::EventBusMessage;
 use crate::settings::RoborockField;
 use crate::state::SharedActorState;
 use ractor::{
@@ -65,7 +66,7 @@ impl RobotVacuumHandler {
     pub const NAME: &str = "robot_vacuum";
 
     fn device_name(&self, device_id: &str) -> String {
-        let devices = &self.shared_actor_state.devices;
+        let devices = self.shared_actor_state.devices();
         let address = devices.address_or_self(device_id);
 
         devices
@@ -94,27 +95,33 @@ impl RobotVacuumHandler {
         .execute(&self.shared_actor_state.db)
         .await?;
 
-        sqlx::query!(
-            "INSERT INTO latest_robot_vacuum_state (device_id, source, state, battery_level, fan_speed) \
+        let row = sqlx::query!(
+            "WITH prior AS (SELECT state FROM latest_robot_vacuum_state WHERE device_id = $1) \
+             INSERT INTO latest_robot_vacuum_state (device_id, source, state, battery_level, fan_speed) \
              VALUES ($1, 'valetudo', $2, $3, $4) \
              ON CONFLICT (device_id) DO UPDATE SET \
                source = EXCLUDED.source, \
                state = COALESCE(EXCLUDED.state, latest_robot_vacuum_state.state), \
                battery_level = COALESCE(EXCLUDED.battery_level, latest_robot_vacuum_state.battery_level), \
                fan_speed = COALESCE(EXCLUDED.fan_speed, latest_robot_vacuum_state.fan_speed), \
-               updated_at = now()",
+               updated_at = now() \
+             RETURNING state, (SELECT state FROM prior) AS previous",
             device_id,
             parsed.state,
             parsed.battery_level,
             parsed.fan_speed,
         )
-        .execute(&self.shared_actor_state.db)
+        .fetch_one(&self.shared_actor_state.db)
         .await?;
 
         if let Some(level) = parsed.battery_level {
             self.report_battery(device_id, level);
         }
 
+        if let Some(state) = row.state {
+            self.publish_state(event_id, device_id, state, row.previous);
+        }
+
         Ok(())
     }
 
@@ -165,16 +172,20 @@ impl RobotVacuumHandler {
                 .execute(&self.shared_actor_state.db)
                 .await?;
 
-                sqlx::query!(
-                    "INSERT INTO latest_robot_vacuum_state (device_id, source, state) \
+                let previous = sqlx::query_scalar!(
+                    "WITH prior AS (SELECT state FROM latest_robot_vacuum_state WHERE device_id = $1) \
+                     INSERT INTO latest_robot_vacuum_state (device_id, source, state) \
                      VALUES ($1, 'roborock', $2) \
                      ON CONFLICT (device_id) DO UPDATE SET \
-                       source = EXCLUDED.source, state = EXCLUDED.state, updated_at = now()",
+                       source = EXCLUDED.source, state = EXCLUDED.state, updated_at = now() \
+                     RETURNING (SELECT state FROM prior) AS previous",
                     device_id,
                     value,
                 )
-                .execute(&self.shared_actor_state.db)
+                .fetch_one(&self.shared_actor_state.db)
                 .await?;
+
+                self.publish_state(event_id, &device_id, value, previous);
             }
 
             RoborockField::Battery => {
@@ -223,6 +234,35 @@ impl RobotVacuumHandler {
         Ok(())
     }
 
+    /// Publish a state change to the bus. Valetudo republishes its status
+    /// alongside every battery/fan update, so an unchanged state is dropped.
+    fn publish_state(
+        &self,
+        event_id: Uuid,
+        device_id: &str,
+        state: String,
+        previous: Option<String>,
+    ) {
+        if previous.as_ref() == Some(&state) {
+            return;
+        }
+
+        tracing::info!(
+            "robot vacuum {device_id} {} -> {state}",
+            previous.as_deref().unwrap_or("unknown")
+        );
+
+        self.shared_actor_state
+            .event_bus
+            .publish(EventBusMessage::RobotVacuum {
+                event_id,
+                device_id: device_id.to_owned(),
+                name: self.device_name(device_id),
+                state,
+                previous,
+            });
+    }
+
     fn report_battery(&self, device_id: &str, level: i32) {
         let name = self.device_name(device_id);
 
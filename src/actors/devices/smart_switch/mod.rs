@@ -1,4 +1,7 @@
-use crate::{actors::devices::light::record_light_state, state::SharedActorState};
+use crate::{
+    actors::devices::light::record_light_state, integrations::mqtt::ZIGBEE2MQTT_BASE,
+    settings::SwitchControl, state::SharedActorState,
+};
 use ractor::{
     ActorProcessingErr, ActorRef,
     factory::{FactoryMessage, Job, Worker, WorkerBuilder, WorkerId},
@@ -24,8 +27,17 @@ pub struct NewEvent {
     pub entity: Entity,
 }
 
+pub struct HomeAssistantUpdate {
+    pub address: String,
+    pub state: String,
+}
+
 pub enum Message {
     NewEvent(NewEvent),
+    HomeAssistant(HomeAssistantUpdate),
+    TurnOn { address: String },
+    TurnOff { address: String },
+    Toggle { address: String },
 }
 
 pub struct SmartSwitchHandler {
@@ -60,8 +72,91 @@ impl SmartSwitchHandler {
         Ok(())
     }
 
+    async fn record_state(&self, address: &str, state: &str) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            r#"INSERT INTO smart_switch_state (address, state, updated_at) VALUES ($1, $2, now())
+            ON CONFLICT (address)
+            DO UPDATE SET
+                state = EXCLUDED.state,
+                updated_at = EXCLUDED.updated_at
+            "#,
+            address,
+            state,
+        )
+        .execute(&self.shared_actor_state.db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// zigbee2mqtt reports `ON`/`OFF` while Home Assistant uses `on`/`off`, so
+    /// the stored value is compared case-insensitively.
+    async fn stored_power_state(&self, address: &str) -> Result<Option<bool>, anyhow::Error> {
+        let row = sqlx::query!(
+            "SELECT state FROM smart_switch_state WHERE address = $1",
+            address
+        )
+        .fetch_optional(&self.shared_actor_state.db)
+        .await?;
+
+        Ok(row.map(|row| row.state.eq_ignore_ascii_case("on")))
+    }
+
+    async fn set_power(&self, address: String, on: bool) -> Result<(), anyhow::Error> {
+        let devices = self.shared_actor_state.devices();
+        let Some(settings) = devices.smart_switch(&address) else {
+            return Err(anyhow::anyhow!("unknown smart switch {address}"));
+        };
+
+        match &settings.control {
+            SwitchControl::Zigbee => {
+                let target = devices
+                    .friendly_name(&address)
+                    .await
+                    .unwrap_or_else(|| address.clone());
+
+                let topic = format!("{ZIGBEE2MQTT_BASE}/{target}/set");
+                self.shared_actor_state
+                    .mqtt
+                    .send_event(
+                        topic,
+                        serde_json::json!({"state": if on { "ON" } else { "OFF" }}),
+                    )
+                    .await?;
+            }
+            SwitchControl::HomeAssistant { domain } => {
+                let Some(home_assistant) = &self.shared_actor_state.home_assistant else {
+                    return Err(anyhow::anyhow!(
+                        "home assistant is not configured, cannot switch {address}"
+                    ));
+                };
+
+                let service = if on { "turn_on" } else { "turn_off" };
+                home_assistant
+                    .call_service(domain, service, serde_json::json!({ "entity_id": address }))
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
     async fn handle(&self, message: Message) -> Result<(), anyhow::Error> {
         match message {
+            Message::TurnOn { address } => self.set_power(address, true).await?,
+            Message::TurnOff { address } => self.set_power(address, false).await?,
+            Message::Toggle { address } => {
+                let Some(on) = self.stored_power_state(&address).await? else {
+                    return Err(anyhow::anyhow!(
+                        "no known state for smart switch {address}, cannot toggle"
+                    ));
+                };
+
+                self.set_power(address, !on).await?;
+            }
+            Message::HomeAssistant(update) => {
+                self.record_state(&update.address, &update.state).await?;
+            }
             Message::NewEvent(event) => match event.entity {
                 Entity::Zigbee {
                     address,
@@ -83,7 +178,11 @@ impl SmartSwitchHandler {
                     )
                     .await?;
 
-                    let is_light = self.shared_actor_state.devices.light(&address).is_some();
+                    if let Some(state) = &state {
+                        self.record_state(&address, state).await?;
+                    }
+
+                    let is_light = self.shared_actor_state.devices().light(&address).is_some();
 
                     match (is_light, state) {
                         (true, Some(state)) => {
@@ -190,7 +190,8 @@ impl Actor for EInkDisplayActor {
                 battery_chemistry,
                 battery_kind,
             } => {
-                let Some(display) = self.shared_actor_state.devices.eink_display(&device_id) else {
+                let devices = self.shared_actor_state.devices();
+                let Some(display) = devices.eink_display(&device_id) else {
                     tracing::warn!(
                         "battery report from unregistered eink display '{device_id}', dropping"
                     );
@@ -215,7 +216,8 @@ impl Actor for EInkDisplayActor {
                 );
             }
             EInkDisplayMessage::ConfigRequest { device_id } => {
-                let Some(display) = self.shared_actor_state.devices.eink_display(&device_id) else {
+                let devices = self.shared_actor_state.devices();
+                let Some(display) = devices.eink_display(&device_id) else {
                     tracing::warn!(
                         "config request from unregistered eink display '{device_id}', dropping"
                     );
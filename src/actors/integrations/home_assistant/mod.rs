@@ -13,6 +13,7 @@ use uuid::Uuid;
 use crate::{
     actors::devices::media_player::{self, MediaPlayerHandler},
     actors::devices::robot_vacuum::{self, RobotVacuumHandler},
+    actors::devices::smart_switch::{self, SmartSwitchHandler},
     event_bus::EventBusMessage,
     integrations::home_assistant::HomeAssistant,
     settings::EntitySettings,
@@ -121,7 +122,7 @@ impl HomeAssistantActor {
                 Some("result")
                     if payload.get("id").and_then(Value::as_u64) == Some(Self::GET_STATES_ID) =>
                 {
-                    self.seed_media_players(&payload);
+                    self.seed_from_states(&payload);
                 }
                 _ => {}
             }
@@ -148,7 +149,7 @@ impl HomeAssistantActor {
         let event_id = Uuid::new_v4();
         let entity = self
             .shared_actor_state
-            .settings
+            .settings()
             .home_assistant
             .for_entity(entity_id);
 
@@ -171,6 +172,7 @@ impl HomeAssistantActor {
         }
 
         self.forward_roborock(event_id, entity_id, &state);
+        self.forward_smart_switch(entity_id, &state);
         self.forward_media_player(
             event_id,
             entity_id,
@@ -188,8 +190,8 @@ impl HomeAssistantActor {
     }
 
     fn forward_roborock(&self, event_id: Uuid, entity_id: &str, state: &str) {
-        let Some((device_id, field)) = self.shared_actor_state.devices.roborock_entity(entity_id)
-        else {
+        let devices = self.shared_actor_state.devices();
+        let Some((device_id, field)) = devices.roborock_entity(entity_id) else {
             return;
         };
 
@@ -215,10 +217,41 @@ impl HomeAssistantActor {
         }
     }
 
+    fn forward_smart_switch(&self, entity_id: &str, state: &str) {
+        if self
+            .shared_actor_state
+            .devices()
+            .smart_switch(entity_id)
+            .is_none()
+        {
+            return;
+        }
+
+        let Some(actor) = ractor::registry::where_is(SmartSwitchHandler::NAME) else {
+            tracing::error!("no smart switch actor found for home assistant update");
+            return;
+        };
+
+        let job = FactoryMessage::Dispatch(Job {
+            key: (),
+            msg: smart_switch::Message::HomeAssistant(smart_switch::HomeAssistantUpdate {
+                address: entity_id.to_owned(),
+                state: state.to_owned(),
+            }),
+            options: JobOptions::default(),
+            accepted: None,
+        });
+
+        if let Err(e) = actor.send_message(job) {
+            tracing::error!("failed to forward smart switch update: {e}");
+        }
+    }
+
     /// `state_changed` only fires on a transition, so a freshly connected gateway
-    /// knows nothing about what is already playing. The `get_states` reply fills
-    /// that gap for every registered media player.
-    fn seed_media_players(&self, payload: &Value) {
+    /// knows nothing about what is already playing or switched on. The
+    /// `get_states` reply fills that gap for every registered media player and
+    /// smart switch.
+    fn seed_from_states(&self, payload: &Value) {
         let Some(states) = payload.get("result").and_then(Value::as_array) else {
             tracing::warn!("home assistant get_states reply carried no result array");
             return;
@@ -229,20 +262,22 @@ impl HomeAssistantActor {
                 continue;
             };
 
+            let state = entity
+                .get("state")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+
+            self.forward_smart_switch(entity_id, state);
+
             if self
                 .shared_actor_state
-                .devices
+                .devices()
                 .media_player(entity_id)
                 .is_none()
             {
                 continue;
             }
 
-            let state = entity
-                .get("state")
-                .and_then(Value::as_str)
-                .unwrap_or_default();
-
             tracing::info!("seeding media player {entity_id} from get_states ({state})");
             self.forward_media_player(Uuid::new_v4(), entity_id, state, &entity["attributes"]);
         }
@@ -257,7 +292,7 @@ impl HomeAssistantActor {
     ) {
         if self
             .shared_actor_state
-            .devices
+            .devices()
             .media_player(entity_id)
             .is_none()
         {
@@ -338,7 +338,8 @@ impl Actor for JellyfinActor {
         myself: ractor::ActorRef<Self::Msg>,
         _args: Self::Arguments,
     ) -> Result<Self::State, ractor::ActorProcessingErr> {
-        let Some(settings) = self.shared_actor_state.settings.jellyfin.as_ref() else {
+        let settings = self.shared_actor_state.settings();
+        let Some(settings) = settings.jellyfin.as_ref() else {
             return Err(anyhow::anyhow!("jellyfin is not configured").into());
         };
 
@@ -85,7 +85,7 @@ impl Actor for SolarActor {
     ) -> Result<Self::State, ractor::ActorProcessingErr> {
         let refresh = self
             .shared_actor_state
-            .settings
+            .settings()
             .solar
             .as_ref()
             .and_then(|s| s.refresh.to_std().ok())
@@ -1,6 +1,6 @@
 use crate::{
     integrations::trmnl::{Trmnl, types::TrmnlDevice},
-    settings::TrmnlDeviceSettings,
+    settings::{TrmnlDeviceSettings, trmnl::normalize_address},
     state::SharedActorState,
 };
 use ractor::Actor;
@@ -19,22 +19,14 @@ impl TrmnlActor {
     pub const NAME: &str = "trmnl";
 }
 
-fn normalize(value: &str) -> String {
-    value
-        .chars()
-        .filter(|c| c.is_ascii_alphanumeric())
-        .collect::<String>()
-        .to_ascii_lowercase()
-}
-
 fn match_device<'a>(
     device: &TrmnlDevice,
     registry: &'a std::collections::HashMap<String, TrmnlDeviceSettings>,
 ) -> Option<(&'a String, &'a TrmnlDeviceSettings)> {
-    let friendly = normalize(&device.friendly_id);
-    let mac = normalize(&device.mac_address);
+    let friendly = normalize_address(&device.friendly_id);
+    let mac = normalize_address(&device.mac_address);
     registry.iter().find(|(address, _)| {
-        let key = normalize(address);
+        let key = normalize_address(address);
         key == friendly || key == mac
     })
 }
@@ -51,7 +43,7 @@ impl Actor for TrmnlActor {
     ) -> Result<Self::State, ractor::ActorProcessingErr> {
         let refresh = self
             .shared_actor_state
-            .settings
+            .settings()
             .trmnl
             .refresh
             .to_std()
@@ -70,7 +62,8 @@ impl Actor for TrmnlActor {
     ) -> Result<(), ractor::ActorProcessingErr> {
         match message {
             TrmnlMessage::CheckBattery => {
-                let registry = self.shared_actor_state.devices.trmnl_devices();
+                let configured = self.shared_actor_state.devices();
+                let registry = configured.trmnl_devices();
                 let devices = self.trmnl.list_devices().await?;
 
                 for device in devices {
@@ -136,6 +129,7 @@ mod tests {
             TrmnlDeviceSettings {
                 id: "fridge-trmnl".to_owned(),
                 name: "Fridge TRMNL".to_owned(),
+                byos: None,
             },
         )])
     }
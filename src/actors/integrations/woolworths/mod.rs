@@ -51,7 +51,7 @@ impl Actor for WoolworthsActor {
 
         let refresh = self
             .shared_actor_state
-            .settings
+            .settings()
             .woolworths
             .refresh
             .to_std()
@@ -3,7 +3,9 @@ use crate::{
         eink_display::EInkDisplayActor,
         integrations::{solar::SolarActor, trmnl::TrmnlActor, woolworths::WoolworthsActor},
         sun::SunActor,
-        system::{battery::BatteryActor, watchdog::WatchdogActor},
+        system::{
+            battery::BatteryActor, config_reload::ConfigReloadActor, watchdog::WatchdogActor,
+        },
     },
     integrations::{
         solar::{goodwe::GoodWeSemsAPI, weather::WeatherAPI},
@@ -38,9 +40,10 @@ use super::{
         adhoc::AdhocTaskActor,
         cron::CronActor,
         mqtt_ingest::{self, MqttIngest},
+        notify::{self, NotifyWorker, policy::NotifyPolicyActor},
         push::{self, PushWorker},
     },
-    workflows::{self, WorkflowWorker, dispatcher::WorkflowDispatcher},
+    workflows::{self, WorkflowWorker, delayed::DelayedRunActor, dispatcher::WorkflowDispatcher},
 };
 
 const RESTART_BACKOFF_BASE: Duration = Duration::from_secs(1);
@@ -98,7 +101,9 @@ impl RootSupervisor {
             SolarActor::NAME => self.start_solar_actor(myself).await?,
             SunActor::NAME => self.start_sun_actor(myself).await?,
             WatchdogActor::NAME => self.start_watchdog_actor(myself).await?,
+            ConfigReloadActor::NAME => self.start_config_reload_actor(myself).await?,
             WorkflowDispatcher::NAME => self.start_workflow_dispatcher(myself).await?,
+            DelayedRunActor::NAME => self.start_delayed_run_actor(myself).await?,
 
             MqttIngest::NAME => {
                 mqtt_ingest::spawn::spawn_mqtt_ingest(myself, state).await?;
@@ -153,6 +158,12 @@ impl RootSupervisor {
             PushWorker::NAME => {
                 push::spawn::spawn_push(myself, state).await?;
             }
+            NotifyWorker::NAME => {
+                notify::spawn::spawn_notify(myself, state).await?;
+            }
+            NotifyPolicyActor::NAME => {
+                notify::spawn::spawn_notify_policy(myself, state).await?;
+            }
 
             name => tracing::error!(actor = %name, "no restart hook for this actor"),
         }
@@ -329,7 +340,7 @@ impl RootSupervisor {
         &self,
         myself: &ractor::ActorRef<RootMessage>,
     ) -> Result<(), ractor::ActorProcessingErr> {
-        let Some(api_key) = self.shared_actor_state.settings.trmnl_api_key.clone() else {
+        let Some(api_key) = self.shared_actor_state.settings().trmnl_api_key.clone() else {
             return Ok(());
         };
 
@@ -340,7 +351,7 @@ impl RootSupervisor {
                     shared_actor_state: self.shared_actor_state.clone(),
                     trmnl: Trmnl::new(
                         api_key,
-                        self.shared_actor_state.settings.trmnl.base_url.clone(),
+                        self.shared_actor_state.settings().trmnl.base_url.clone(),
                     ),
                 },
                 (),
@@ -371,7 +382,7 @@ impl RootSupervisor {
         &self,
         myself: &ractor::ActorRef<RootMessage>,
     ) -> Result<(), ractor::ActorProcessingErr> {
-        let Some(settings) = self.shared_actor_state.settings.solar.clone() else {
+        let Some(settings) = self.shared_actor_state.settings().solar.clone() else {
             tracing::info!("no solar config; skipping solar actor");
             return Ok(());
         };
@@ -429,6 +440,23 @@ impl RootSupervisor {
         Ok(())
     }
 
+    async fn start_config_reload_actor(
+        &self,
+        myself: &ractor::ActorRef<RootMessage>,
+    ) -> Result<(), ractor::ActorProcessingErr> {
+        myself
+            .spawn_linked(
+                Some(ConfigReloadActor::NAME.to_owned()),
+                ConfigReloadActor {
+                    shared_actor_state: self.shared_actor_state.clone(),
+                },
+                (),
+            )
+            .await?;
+
+        Ok(())
+    }
+
     async fn start_workflow_dispatcher(
         &self,
         myself: &ractor::ActorRef<RootMessage>,
@@ -445,6 +473,23 @@ impl RootSupervisor {
 
         Ok(())
     }
+
+    async fn start_delayed_run_actor(
+        &self,
+        myself: &ractor::ActorRef<RootMessage>,
+    ) -> Result<(), ractor::ActorProcessingErr> {
+        myself
+            .spawn_linked(
+                Some(DelayedRunActor::NAME.to_owned()),
+                DelayedRunActor {
+                    shared_actor_state: self.shared_actor_state.clone(),
+                },
+                (),
+            )
+            .await?;
+
+        Ok(())
+    }
 }
 
 impl Actor for RootSupervisor {
@@ -469,6 +514,8 @@ impl Actor for RootSupervisor {
 
         door_sensor::spawn::spawn_door_handler(&myself, shared_actor_state.clone()).await?;
         push::spawn::spawn_push(&myself, self.shared_actor_state.clone()).await?;
+        notify::spawn::spawn_notify(&myself, self.shared_actor_state.clone()).await?;
+        notify::spawn::spawn_notify_policy(&myself, self.shared_actor_state.clone()).await?;
 
         light::spawn::spawn_light_handler(&myself, shared_actor_state.clone()).await?;
         environment_sensor::spawn::spawn_environment_sensor_handler(
@@ -517,7 +564,9 @@ impl Actor for RootSupervisor {
         self.start_solar_actor(&myself).await?;
         self.start_sun_actor(&myself).await?;
         self.start_watchdog_actor(&myself).await?;
+        self.start_config_reload_actor(&myself).await?;
         self.start_workflow_dispatcher(&myself).await?;
+        self.start_delayed_run_actor(&myself).await?;
         self.start_adhoc_task_actor(&myself).await;
 
         Ok(RootState::default())
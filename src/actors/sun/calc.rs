@@ -67,6 +67,16 @@ pub fn current_period(loc: LocationSettings, now: DateTime<Utc>, offset: TimeDel
     }
 }
 
+/// Today's (local) sunrise or sunset.
+pub fn transition_today(
+    loc: LocationSettings,
+    now: DateTime<Utc>,
+    transition: SunTransition,
+) -> DateTime<Utc> {
+    let today = now.with_timezone(&Perth).date_naive();
+    event_time(loc, today, transition, TimeDelta::zero())
+}
+
 pub fn next_transition_at(
     loc: LocationSettings,
     now: DateTime<Utc>,
@@ -25,7 +25,12 @@ pub enum SunActorMessage {
     Fire {
         transition: SunTransition,
         offset: TimeDelta,
+        generation: u64,
     },
+    /// The config was reloaded: drop every armed timer and schedule the
+    /// current set of sun triggers from scratch. No catch-up is replayed, since
+    /// nothing was missed while the process kept running.
+    Rearm,
 }
 
 pub struct SunActor {
@@ -40,14 +45,36 @@ impl SunActor {
         myself: &ractor::ActorRef<SunActorMessage>,
         transition: SunTransition,
         offset: TimeDelta,
+        generation: u64,
     ) {
-        let location = self.shared_actor_state.settings.location;
+        let location = self.shared_actor_state.settings().location;
         let delay = calc::next_transition(location, Utc::now(), transition, offset);
         tracing::info!(
             "next sun {transition:?} (offset {}) in {delay:?}",
             crate::timedelta_format::humanize(offset)
         );
-        myself.send_after(delay, move || SunActorMessage::Fire { transition, offset });
+        myself.send_after(delay, move || SunActorMessage::Fire {
+            transition,
+            offset,
+            generation,
+        });
+    }
+
+    /// Every distinct (transition, offset) pair across the enabled workflows.
+    fn configured_pairs(&self) -> Vec<(SunTransition, TimeDelta)> {
+        let mut pairs: Vec<(SunTransition, TimeDelta)> = Vec::new();
+        for workflow in self.shared_actor_state.settings().workflows.values() {
+            if !workflow.enabled {
+                continue;
+            }
+            if let Some(TriggerMatcher::Sun { transition, offset }) = workflow.on() {
+                let pair = (*transition, *offset);
+                if !pairs.contains(&pair) {
+                    pairs.push(pair);
+                }
+            }
+        }
+        pairs
     }
 
     async fn publish(
@@ -99,14 +126,10 @@ impl SunActor {
         offset: TimeDelta,
     ) -> Result<(), ractor::ActorProcessingErr> {
         let now = Utc::now();
-        let previous = calc::previous_transition_at(
-            self.shared_actor_state.settings.location,
-            now,
-            transition,
-            offset,
-        );
+        let settings = self.shared_actor_state.settings();
+        let previous = calc::previous_transition_at(settings.location, now, transition, offset);
         let last_fired = self.last_fired(transition, offset).await?;
-        let grace = self.shared_actor_state.settings.sun.catch_up_within;
+        let grace = settings.sun.catch_up_within;
 
         if calc::should_catch_up(previous, last_fired, now, grace) {
             tracing::info!(
@@ -127,7 +150,9 @@ impl SunActor {
 
 impl Actor for SunActor {
     type Msg = SunActorMessage;
-    type State = ();
+    /// Bumped on every [`SunActorMessage::Rearm`], so timers from before a
+    /// reload are recognised as stale when they come due.
+    type State = u64;
     type Arguments = ();
 
     async fn pre_start(
@@ -135,34 +160,40 @@ impl Actor for SunActor {
         myself: ractor::ActorRef<Self::Msg>,
         _args: Self::Arguments,
     ) -> Result<Self::State, ractor::ActorProcessingErr> {
-        let mut scheduled: Vec<(SunTransition, TimeDelta)> = Vec::new();
-        for workflow in self.shared_actor_state.settings.workflows.values() {
-            if !workflow.enabled {
-                continue;
-            }
-            if let Some(TriggerMatcher::Sun { transition, offset }) = workflow.on() {
-                let pair = (*transition, *offset);
-                if !scheduled.contains(&pair) {
-                    scheduled.push(pair);
-                    self.catch_up(*transition, *offset).await?;
-                    self.schedule(&myself, *transition, *offset);
-                }
-            }
+        for (transition, offset) in self.configured_pairs() {
+            self.catch_up(transition, offset).await?;
+            self.schedule(&myself, transition, offset, 0);
         }
-        Ok(())
+        Ok(0)
     }
 
-    #[tracing::instrument(name = "sun-actor", skip(self, myself, message, _state))]
+    #[tracing::instrument(name = "sun-actor", skip(self, myself, message, state))]
     async fn handle(
         &self,
         myself: ractor::ActorRef<Self::Msg>,
         message: Self::Msg,
-        _state: &mut Self::State,
+        state: &mut Self::State,
     ) -> Result<(), ractor::ActorProcessingErr> {
         match message {
-            SunActorMessage::Fire { transition, offset } => {
+            SunActorMessage::Fire {
+                transition,
+                offset,
+                generation,
+            } => {
+                if generation != *state {
+                    tracing::debug!("dropping sun {transition:?} armed before the last reload");
+                    return Ok(());
+                }
+
                 self.publish(transition, offset, Utc::now()).await?;
-                self.schedule(&myself, transition, offset);
+                self.schedule(&myself, transition, offset, generation);
+            }
+            SunActorMessage::Rearm => {
+                *state += 1;
+                tracing::info!("re-arming sun triggers after config reload");
+                for (transition, offset) in self.configured_pairs() {
+                    self.schedule(&myself, transition, offset, *state);
+                }
             }
         }
         Ok(())
@@ -123,7 +123,7 @@ impl Actor for AdhocTaskActor {
     ) -> Result<Self::State, ractor::ActorProcessingErr> {
         let interval = self
             .shared_actor_state
-            .settings
+            .settings()
             .adhoc
             .recheck_interval
             .to_std()
@@ -73,7 +73,7 @@ impl Actor for BatteryActor {
             battery_percent,
             battery_chemistry,
         } = message;
-        let devices = &self.shared_actor_state.devices;
+        let devices = self.shared_actor_state.devices();
         let address = devices.address_or_self(&device_id);
 
         if devices.battery(address).is_none() {
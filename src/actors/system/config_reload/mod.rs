@@ -0,0 +1,196 @@
+//! Config hot-reload.
+//!
+//! Polls the config dir for changes and also serves explicit reload requests
+//! from the admin REST route and GraphQL mutation. A reload re-reads the dir
+//! through the same validation as startup and swaps it into the shared
+//! [`LiveConfig`]; an invalid config is rejected and the running one kept.
+//!
+//! Most consumers read the config per message, so the swap alone is enough for
+//! them. The exceptions are schedules and subscriptions derived from the
+//! config, which are re-armed here, along with the dispatcher's `for:` timers.
+//! Rising-edge latches live in the dispatcher and are untouched; parked delays
+//! look their workflow up again when they come due, so they pick up the new
+//! config on their own, or expire if the steps they parked in have changed.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use ractor::{Actor, RpcReplyPort};
+use tracing::Level;
+
+use crate::actors::sun::{SunActor, SunActorMessage};
+use crate::actors::system::cron::{CronActor, CronActorMessage};
+use crate::actors::workflows::dispatcher::{WorkflowDispatcher, WorkflowDispatcherMessage};
+use crate::settings::ConfigChange;
+use crate::state::SharedActorState;
+
+const WATCH_INTERVAL: Duration = Duration::from_secs(10);
+
+pub enum ConfigReloadMessage {
+    /// Periodic check of the config dir; reloads if anything changed on disk.
+    Poll,
+    /// Reload now, regardless of whether the files changed.
+    Reload {
+        reply: RpcReplyPort<Result<ConfigChange, String>>,
+    },
+}
+
+/// Path, size and mtime of every file under the config dir. Symlinks are
+/// followed, so a Kubernetes ConfigMap swapping its `..data` link shows up too.
+type Fingerprint = Vec<(PathBuf, u64, Option<SystemTime>)>;
+
+fn fingerprint(dir: &Path) -> Fingerprint {
+    fn walk(dir: &Path, out: &mut Fingerprint) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(metadata) = std::fs::metadata(&path) else {
+                continue;
+            };
+
+            if metadata.is_dir() {
+                walk(&path, out);
+            } else {
+                out.push((path, metadata.len(), metadata.modified().ok()));
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    walk(dir, &mut out);
+    out.sort();
+    out
+}
+
+pub struct ConfigReloadActor {
+    pub shared_actor_state: SharedActorState,
+}
+
+impl ConfigReloadActor {
+    pub const NAME: &str = "config-reload";
+
+    fn reload(&self) -> Result<ConfigChange, String> {
+        let change = self
+            .shared_actor_state
+            .config
+            .reload()
+            .map_err(|e| e.to_string())?;
+
+        tracing::info!(
+            version = %change.version,
+            added_workflows = ?change.added_workflows,
+            removed_workflows = ?change.removed_workflows,
+            "reloaded config"
+        );
+
+        self.rearm(&change);
+
+        Ok(change)
+    }
+
+    fn rearm(&self, change: &ConfigChange) {
+        match ractor::registry::where_is(CronActor::NAME) {
+            Some(actor) => {
+                if let Err(e) = actor.send_message(CronActorMessage::Rearm) {
+                    tracing::error!("failed to re-arm cron triggers: {e}");
+                }
+            }
+            None => tracing::error!("cron actor not found, triggers not re-armed"),
+        }
+
+        match ractor::registry::where_is(SunActor::NAME) {
+            Some(actor) => {
+                if let Err(e) = actor.send_message(SunActorMessage::Rearm) {
+                    tracing::error!("failed to re-arm sun triggers: {e}");
+                }
+            }
+            None => tracing::error!("sun actor not found, triggers not re-armed"),
+        }
+
+        match ractor::registry::where_is(WorkflowDispatcher::NAME) {
+            Some(actor) => {
+                if let Err(e) = actor.send_message(WorkflowDispatcherMessage::Rearm) {
+                    tracing::error!("failed to re-arm held triggers: {e}");
+                }
+            }
+            None => tracing::error!("workflow dispatcher not found, held triggers not re-armed"),
+        }
+
+        let mqtt = self.shared_actor_state.mqtt.clone();
+        let added = change.added_topics.clone();
+        let removed = change.removed_topics.clone();
+        tokio::spawn(async move {
+            for topic in added {
+                tracing::info!("subscribing to esphome state topic: {topic}");
+                if let Err(e) = mqtt.subscribe(topic.clone()).await {
+                    tracing::error!("failed to subscribe to {topic}: {e}");
+                }
+            }
+
+            for topic in removed {
+                tracing::info!("unsubscribing from esphome state topic: {topic}");
+                if let Err(e) = mqtt.unsubscribe(topic.clone()).await {
+                    tracing::error!("failed to unsubscribe from {topic}: {e}");
+                }
+            }
+        });
+    }
+}
+
+impl Actor for ConfigReloadActor {
+    type Msg = ConfigReloadMessage;
+    /// The config dir as of the last poll.
+    type State = Fingerprint;
+    type Arguments = ();
+
+    async fn pre_start(
+        &self,
+        myself: ractor::ActorRef<Self::Msg>,
+        _args: Self::Arguments,
+    ) -> Result<Self::State, ractor::ActorProcessingErr> {
+        let _join_handle = myself.send_interval(WATCH_INTERVAL, || ConfigReloadMessage::Poll);
+
+        Ok(fingerprint(self.shared_actor_state.config.dir()))
+    }
+
+    #[tracing::instrument(name = "config-reload-actor", skip(self, _myself, message, state), level = Level::TRACE)]
+    async fn handle(
+        &self,
+        _myself: ractor::ActorRef<Self::Msg>,
+        message: Self::Msg,
+        state: &mut Self::State,
+    ) -> Result<(), ractor::ActorProcessingErr> {
+        match message {
+            ConfigReloadMessage::Poll => {
+                let current = fingerprint(self.shared_actor_state.config.dir());
+                if current == *state {
+                    return Ok(());
+                }
+
+                // Only try each on-disk version once; a broken edit is logged
+                // and left alone until the files change again.
+                *state = current;
+                tracing::info!("config dir changed, reloading");
+                if let Err(e) = self.reload() {
+                    tracing::error!("rejected config reload, keeping the running config: {e}");
+                }
+            }
+            ConfigReloadMessage::Reload { reply } => {
+                *state = fingerprint(self.shared_actor_state.config.dir());
+                let result = self.reload();
+                if let Err(e) = &result {
+                    tracing::error!("rejected config reload, keeping the running config: {e}");
+                }
+
+                if reply.send(result).is_err() {
+                    tracing::warn!("config reload requester went away before the reply");
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -21,7 +21,11 @@ pub enum CronActorMessage {
     Fire {
         name: String,
         schedule: CronSchedule,
+        generation: u64,
     },
+    /// The config was reloaded: drop every armed timer and schedule the
+    /// current set of cron triggers from scratch.
+    Rearm,
 }
 
 pub struct CronActor {
@@ -38,21 +42,47 @@ impl CronActor {
         myself: &ractor::ActorRef<CronActorMessage>,
         name: String,
         schedule: CronSchedule,
+        generation: u64,
     ) {
         match schedule.time_until_next() {
             Ok(delay) => {
-                myself.send_after(delay, move || CronActorMessage::Fire { name, schedule });
+                myself.send_after(delay, move || CronActorMessage::Fire {
+                    name,
+                    schedule,
+                    generation,
+                });
             }
             Err(e) => {
                 tracing::error!("cron trigger '{name}' has no next occurrence: {e}");
             }
         }
     }
+
+    /// Schedule every enabled cron trigger in the current config under
+    /// `generation`; timers armed under an older generation fire into the void.
+    fn schedule_all(&self, myself: &ractor::ActorRef<CronActorMessage>, generation: u64) {
+        let settings = self.shared_actor_state.settings();
+        for workflow in settings.workflows.values() {
+            if !workflow.enabled {
+                continue;
+            }
+            if let Some(TriggerMatcher::Cron { schedule }) = workflow.on() {
+                Self::schedule_next(
+                    myself,
+                    workflow.name.clone(),
+                    schedule.as_ref().clone(),
+                    generation,
+                );
+            }
+        }
+    }
 }
 
 impl Actor for CronActor {
     type Msg = CronActorMessage;
-    type State = ();
+    /// Bumped on every [`CronActorMessage::Rearm`], so timers from before a
+    /// reload are recognised as stale when they come due.
+    type State = u64;
     type Arguments = ();
 
     async fn pre_start(
@@ -60,28 +90,29 @@ impl Actor for CronActor {
         myself: ractor::ActorRef<Self::Msg>,
         _args: Self::Arguments,
     ) -> Result<Self::State, ractor::ActorProcessingErr> {
-        let settings = &self.shared_actor_state.settings;
-        for workflow in settings.workflows.values() {
-            if !workflow.enabled {
-                continue;
-            }
-            if let Some(TriggerMatcher::Cron { schedule }) = workflow.on() {
-                Self::schedule_next(&myself, workflow.name.clone(), schedule.as_ref().clone());
-            }
-        }
+        self.schedule_all(&myself, 0);
 
-        Ok(())
+        Ok(0)
     }
 
-    #[tracing::instrument(name = "cron-actor", skip(self, myself, message, _state))]
+    #[tracing::instrument(name = "cron-actor", skip(self, myself, message, state))]
     async fn handle(
         &self,
         myself: ractor::ActorRef<Self::Msg>,
         message: Self::Msg,
-        _state: &mut Self::State,
+        state: &mut Self::State,
     ) -> Result<(), ractor::ActorProcessingErr> {
         match message {
-            CronActorMessage::Fire { name, schedule } => {
+            CronActorMessage::Fire {
+                name,
+                schedule,
+                generation,
+            } => {
+                if generation != *state {
+                    tracing::debug!("dropping cron '{name}' armed before the last reload");
+                    return Ok(());
+                }
+
                 self.shared_actor_state
                     .event_bus
                     .publish(EventBusMessage::Cron {
@@ -89,7 +120,12 @@ impl Actor for CronActor {
                         name: name.clone(),
                     });
 
-                Self::schedule_next(&myself, name, schedule);
+                Self::schedule_next(&myself, name, schedule, generation);
+            }
+            CronActorMessage::Rearm => {
+                *state += 1;
+                tracing::info!("re-arming cron triggers after config reload");
+                self.schedule_all(&myself, *state);
             }
         }
 
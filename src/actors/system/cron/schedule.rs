@@ -38,6 +38,11 @@ impl CronSchedule {
         Ok((next - now).to_std().unwrap_or(Duration::ZERO))
     }
 
+    /// The next occurrence strictly after `from`.
+    pub fn next_after(&self, from: DateTime<Tz>) -> Result<DateTime<Tz>, CronError> {
+        self.0.find_next_occurrence(&from, false)
+    }
+
     pub fn secs_until_next_from(
         &self,
         from: DateTime<Tz>,
@@ -1,7 +1,9 @@
 pub mod adhoc;
 pub mod battery;
+pub mod config_reload;
 pub mod cron;
 pub mod mqtt_ingest;
+pub mod notify;
 pub mod push;
 pub mod rpc;
 pub mod watchdog;
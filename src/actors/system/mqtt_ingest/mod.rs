@@ -163,7 +163,7 @@ impl MqttIngest {
 
         let event_id = uuid::Uuid::new_v4();
 
-        if self.shared_actor_state.devices.plant(node).is_some() {
+        if self.shared_actor_state.devices().plant(node).is_some() {
             match ractor::registry::where_is(plant_sensor::PlantSensorHandler::NAME) {
                 Some(actor_cell) => {
                     actor_cell.send_message(FactoryMessage::Dispatch(Job {
@@ -182,7 +182,12 @@ impl MqttIngest {
             }
         }
 
-        if self.shared_actor_state.devices.environment(node).is_some() {
+        if self
+            .shared_actor_state
+            .devices()
+            .environment(node)
+            .is_some()
+        {
             match ractor::registry::where_is(EnvironmentSensorHandler::NAME) {
                 Some(actor_cell) => {
                     actor_cell.send_message(FactoryMessage::Dispatch(Job {
@@ -209,7 +214,7 @@ impl MqttIngest {
     }
 
     fn record_battery(&self, address: &str, percent: f64) {
-        let devices = &self.shared_actor_state.devices;
+        let devices = self.shared_actor_state.devices();
 
         let Some(settings) = devices.battery(address) else {
             return;
@@ -238,7 +243,7 @@ impl MqttIngest {
         payload: &Map<String, Value>,
     ) -> Result<(), anyhow::Error> {
         let address = device.address.clone();
-        let devices = &self.shared_actor_state.devices;
+        let devices = self.shared_actor_state.devices();
 
         if devices.battery(&address).is_some()
             && let Some(percent) = role::battery(device, payload)
@@ -246,12 +251,12 @@ impl MqttIngest {
             self.record_battery(&address, percent as f64);
         }
 
-        role::run::<door_sensor::Entity>(event_id, devices, device, friendly_name, payload);
-        role::run::<environment_sensor::Entity>(event_id, devices, device, friendly_name, payload);
-        role::run::<light::Entity>(event_id, devices, device, friendly_name, payload);
-        role::run::<smart_switch::Entity>(event_id, devices, device, friendly_name, payload);
-        role::run::<presence_sensor::Entity>(event_id, devices, device, friendly_name, payload);
-        role::run::<control_switch::Entity>(event_id, devices, device, friendly_name, payload);
+        role::run::<door_sensor::Entity>(event_id, &devices, device, friendly_name, payload);
+        role::run::<environment_sensor::Entity>(event_id, &devices, device, friendly_name, payload);
+        role::run::<light::Entity>(event_id, &devices, device, friendly_name, payload);
+        role::run::<smart_switch::Entity>(event_id, &devices, device, friendly_name, payload);
+        role::run::<presence_sensor::Entity>(event_id, &devices, device, friendly_name, payload);
+        role::run::<control_switch::Entity>(event_id, &devices, device, friendly_name, payload);
 
         let device_id = devices.id_for_address(&address).map(str::to_owned);
 
@@ -301,7 +306,7 @@ impl MqttIngest {
                             .execute(&self.shared_actor_state.db)
                             .await?;
                     self.shared_actor_state
-                        .devices
+                        .devices()
                         .record_friendly_name(ieee_address, friendly_name)
                         .await;
                 }
@@ -317,13 +322,13 @@ impl MqttIngest {
                 );
 
                 self.shared_actor_state
-                    .devices
+                    .devices()
                     .record_friendly_name(discovery.name.clone(), discovery.friendly_name)
                     .await;
 
                 let topics: Vec<String> = self
                     .shared_actor_state
-                    .devices
+                    .devices()
                     .esphome_topics_for(&discovery.name)
                     .map(|(topic, _)| topic.clone())
                     .collect();
@@ -338,7 +343,7 @@ impl MqttIngest {
                 // topics declared in the sensor registry; look the target up exactly
                 let target = self
                     .shared_actor_state
-                    .devices
+                    .devices()
                     .esphome_target(&topic)
                     .cloned();
 
@@ -378,7 +383,7 @@ impl MqttIngest {
 
                 let device_id = self
                     .shared_actor_state
-                    .devices
+                    .devices()
                     .id_for_address(&identifier)
                     .unwrap_or(&identifier)
                     .to_owned();
@@ -409,7 +414,7 @@ impl MqttIngest {
                     return Ok(());
                 };
 
-                let devices = &self.shared_actor_state.devices;
+                let devices = self.shared_actor_state.devices();
                 let address = match object
                     .get("device")
                     .and_then(|device| device.get("ieee_addr"))
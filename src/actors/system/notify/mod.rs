@@ -0,0 +1,113 @@
+//! Delivery for every notify target except the Android app, which has its own
+//! FCM [`PushWorker`](crate::actors::system::push::PushWorker). Each job is one
+//! message to one target; failures are logged and counted, not retried.
+//! Messages reach the workers through the [`policy::NotifyPolicyActor`].
+
+use open_feature::EvaluationContext;
+use ractor::{
+    ActorRef,
+    factory::{FactoryMessage, Job, Worker, WorkerBuilder, WorkerId},
+};
+
+use crate::{integrations::notify::deliver, settings::NotifySource, state::SharedActorState};
+
+pub mod policy;
+pub mod spawn;
+
+pub enum NotifyMessage {
+    Send {
+        source: NotifySource,
+        message: String,
+    },
+}
+
+pub struct NotifyWorker {
+    client: reqwest_middleware::ClientWithMiddleware,
+    shared_actor_state: SharedActorState,
+}
+
+impl NotifyWorker {
+    pub const NAME: &str = "notify";
+}
+
+impl Worker for NotifyWorker {
+    type Key = ();
+    type Message = NotifyMessage;
+    type State = ();
+    type Arguments = ();
+
+    async fn pre_start(
+        &self,
+        _wid: WorkerId,
+        _factory: &ActorRef<FactoryMessage<(), NotifyMessage>>,
+        _startup_context: Self::Arguments,
+    ) -> Result<Self::State, ractor::ActorProcessingErr> {
+        Ok(())
+    }
+
+    async fn handle(
+        &self,
+        _wid: WorkerId,
+        _factory: &ActorRef<FactoryMessage<(), NotifyMessage>>,
+        Job { msg, .. }: Job<(), NotifyMessage>,
+        _state: &mut Self::State,
+    ) -> Result<(), ractor::ActorProcessingErr> {
+        match msg {
+            NotifyMessage::Send { source, message } => {
+                let channel = source.channel();
+
+                let evaluation_context = EvaluationContext::default()
+                    .with_custom_field("message", message.clone())
+                    .with_custom_field("channel", channel.to_owned());
+                if self
+                    .shared_actor_state
+                    .feature_flag_client
+                    .is_feature_enabled(
+                        "home-gateway-notification-killswitch",
+                        false,
+                        evaluation_context,
+                    )
+                    .await
+                {
+                    tracing::warn!("notification kill switch is enabled, not sending: {message}");
+                    crate::metrics::record_notification(channel, "skipped");
+                    return Ok(());
+                }
+
+                match deliver(
+                    &self.client,
+                    &self.shared_actor_state.mqtt,
+                    &source,
+                    &message,
+                )
+                .await
+                {
+                    Ok(()) => crate::metrics::record_notification(channel, "delivered"),
+                    Err(e) => {
+                        tracing::error!("{channel} notification failed: {e}");
+                        crate::metrics::record_notification(channel, "failed");
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub struct NotifyWorkerBuilder {
+    pub client: reqwest_middleware::ClientWithMiddleware,
+    pub shared_actor_state: SharedActorState,
+}
+
+impl WorkerBuilder<NotifyWorker, ()> for NotifyWorkerBuilder {
+    fn build(&mut self, _wid: usize) -> (NotifyWorker, ()) {
+        (
+            NotifyWorker {
+                client: self.client.clone(),
+                shared_actor_state: self.shared_actor_state.clone(),
+            },
+            (),
+        )
+    }
+}
@@ -0,0 +1,225 @@
+//! Sits between [`notify`](crate::integrations::notify::notify) and the
+//! delivery workers, applying each target's [`NotifyPolicy`]. One actor sees
+//! every message, so duplicate and rate-limit decisions never race.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::Utc;
+use chrono_tz::Australia::Perth;
+use ractor::Actor;
+use tracing::Level;
+
+use crate::{
+    integrations::notify::{
+        Actionable, Origin, dispatch,
+        policy::{self, Now, PolicyState, Suppressed},
+    },
+    mode::Mode,
+    settings::{
+        NotifySource,
+        notify::{NotifyAction, NotifyPolicy},
+    },
+    state::SharedActorState,
+};
+
+/// How often held messages are checked for a digest.
+const DIGEST_INTERVAL: Duration = Duration::from_secs(60);
+
+pub enum NotifyPolicyMessage {
+    Notify {
+        targets: Vec<NotifySource>,
+        message: String,
+        origin: Origin,
+        actions: Vec<NotifyAction>,
+    },
+    FlushDigests,
+}
+
+pub struct NotifyPolicyState {
+    policy: PolicyState,
+    /// Targets that have held a message, by [`NotifySource::key`], so a digest
+    /// can reach an inline target. Named targets are also found in settings.
+    digest_targets: HashMap<String, NotifySource>,
+}
+
+pub struct NotifyPolicyActor {
+    pub shared_actor_state: SharedActorState,
+}
+
+impl NotifyPolicyActor {
+    pub const NAME: &str = "notify-policy";
+
+    /// Night mode is only read when some policy asks for it.
+    async fn now<'a>(&self, mut policies: impl Iterator<Item = &'a NotifyPolicy>) -> Now {
+        let wants_night = policies.any(|policy| {
+            policy
+                .quiet_hours
+                .as_ref()
+                .is_some_and(|quiet| quiet.night_mode)
+        });
+        let night = wants_night
+            && self
+                .shared_actor_state
+                .workflows
+                .mode_active(Mode::Night)
+                .await;
+
+        let at = Utc::now();
+        Now {
+            at,
+            local: at.with_timezone(&Perth).time(),
+            night,
+        }
+    }
+
+    async fn notify(
+        &self,
+        state: &mut NotifyPolicyState,
+        targets: Vec<NotifySource>,
+        message: String,
+        origin: Origin,
+        actions: Vec<NotifyAction>,
+    ) {
+        let now = self
+            .now(targets.iter().map(|target| &target.options().policy))
+            .await;
+        let actionable = Actionable::new(&origin, &actions);
+
+        for target in targets {
+            let policy = &target.options().policy;
+            let key = target.key();
+
+            let Some(reason) = state
+                .policy
+                .check(policy, &key, &origin.source, &message, now)
+            else {
+                dispatch(&target, message.clone(), actionable.clone());
+                continue;
+            };
+
+            let digest = policy.digest && reason.digestible();
+            tracing::info!(
+                "suppressed {} notification from {} ({}): {message}",
+                target.channel(),
+                origin.source,
+                reason.as_str()
+            );
+            crate::metrics::record_notification(target.channel(), "suppressed");
+
+            if let Err(e) = policy::record(
+                &self.shared_actor_state.db,
+                Suppressed {
+                    target: &key,
+                    channel: target.channel(),
+                    origin: &origin,
+                    message: &message,
+                    reason,
+                    digest,
+                },
+            )
+            .await
+            {
+                tracing::error!("failed to record notification suppression: {e}");
+            }
+
+            if digest {
+                state.digest_targets.insert(key, target);
+            }
+        }
+    }
+
+    async fn flush_digests(&self, state: &mut NotifyPolicyState) -> Result<(), sqlx::Error> {
+        let held = policy::held(&self.shared_actor_state.db).await?;
+        if held.is_empty() {
+            return Ok(());
+        }
+
+        let settings = self.shared_actor_state.settings();
+        let mut by_target: HashMap<String, Vec<policy::Held>> = HashMap::new();
+        for message in held {
+            by_target
+                .entry(message.target.clone())
+                .or_default()
+                .push(message);
+        }
+
+        for (key, messages) in by_target {
+            // an inline target is forgotten by a restart, and a named one can be
+            // renamed or removed; either way there's nowhere to send the digest
+            let Some(target) = state.digest_targets.get(&key).cloned().or_else(|| {
+                settings
+                    .notify_targets
+                    .values()
+                    .find(|target| target.key() == key)
+                    .cloned()
+            }) else {
+                tracing::warn!(
+                    "dropping {} held notifications for {key}, the target can't be found",
+                    messages.len()
+                );
+                let ids: Vec<_> = messages.iter().map(|m| m.id).collect();
+                policy::mark_digested(&self.shared_actor_state.db, &ids).await?;
+                continue;
+            };
+
+            let policy = &target.options().policy;
+            let now = self.now(std::iter::once(policy)).await;
+            if !PolicyState::digest_due(policy, messages[0].suppressed_at, now) {
+                continue;
+            }
+
+            let ids: Vec<_> = messages.iter().map(|m| m.id).collect();
+            let lines: Vec<_> = messages.into_iter().map(|m| m.message).collect();
+            policy::mark_digested(&self.shared_actor_state.db, &ids).await?;
+            dispatch(&target, policy::digest(&lines), None);
+            state.digest_targets.remove(&key);
+        }
+
+        Ok(())
+    }
+}
+
+impl Actor for NotifyPolicyActor {
+    type Msg = NotifyPolicyMessage;
+    type State = NotifyPolicyState;
+    type Arguments = ();
+
+    async fn pre_start(
+        &self,
+        myself: ractor::ActorRef<Self::Msg>,
+        _args: Self::Arguments,
+    ) -> Result<Self::State, ractor::ActorProcessingErr> {
+        let _join_handle =
+            myself.send_interval(DIGEST_INTERVAL, || NotifyPolicyMessage::FlushDigests);
+
+        Ok(NotifyPolicyState {
+            policy: PolicyState::default(),
+            digest_targets: HashMap::new(),
+        })
+    }
+
+    #[tracing::instrument(name = "notify-policy", skip(self, _myself, message, state), level = Level::TRACE)]
+    async fn handle(
+        &self,
+        _myself: ractor::ActorRef<Self::Msg>,
+        message: Self::Msg,
+        state: &mut Self::State,
+    ) -> Result<(), ractor::ActorProcessingErr> {
+        match message {
+            NotifyPolicyMessage::Notify {
+                targets,
+                message,
+                origin,
+                actions,
+            } => self.notify(state, targets, message, origin, actions).await,
+            NotifyPolicyMessage::FlushDigests => {
+                if let Err(e) = self.flush_digests(state).await {
+                    tracing::error!("failed to flush notification digests: {e}");
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+use crate::{http::get_traced_http_client, state::SharedActorState};
+use ractor::{
+    ActorRef,
+    factory::{Factory, FactoryArguments, FactoryMessage, queues, routing},
+};
+
+use super::{
+    NotifyMessage, NotifyWorker, NotifyWorkerBuilder,
+    policy::{NotifyPolicyActor, NotifyPolicyMessage},
+};
+
+pub async fn spawn_notify(
+    root_supervisor_ref: &ActorRef<crate::actors::root::RootMessage>,
+    shared_actor_state: SharedActorState,
+) -> anyhow::Result<ActorRef<FactoryMessage<(), NotifyMessage>>> {
+    let notify_factory_def = Factory::<
+        (),
+        NotifyMessage,
+        (),
+        NotifyWorker,
+        routing::QueuerRouting<(), NotifyMessage>,
+        queues::DefaultQueue<(), NotifyMessage>,
+    >::default();
+
+    let client = get_traced_http_client()?;
+
+    // a slow SMTP server or webhook shouldn't hold up every other channel
+    let notify_factory_args = FactoryArguments::builder()
+        .worker_builder(Box::new(NotifyWorkerBuilder {
+            client,
+            shared_actor_state,
+        }))
+        .queue(Default::default())
+        .router(Default::default())
+        .num_initial_workers(2)
+        .build();
+
+    let (actor_ref, _) = root_supervisor_ref
+        .spawn_linked(
+            Some(NotifyWorker::NAME.to_string()),
+            notify_factory_def,
+            notify_factory_args,
+        )
+        .await?;
+
+    Ok(actor_ref)
+}
+
+pub async fn spawn_notify_policy(
+    root_supervisor_ref: &ActorRef<crate::actors::root::RootMessage>,
+    shared_actor_state: SharedActorState,
+) -> anyhow::Result<ActorRef<NotifyPolicyMessage>> {
+    let (actor_ref, _) = root_supervisor_ref
+        .spawn_linked(
+            Some(NotifyPolicyActor::NAME.to_string()),
+            NotifyPolicyActor { shared_actor_state },
+            (),
+        )
+        .await?;
+
+    Ok(actor_ref)
+}
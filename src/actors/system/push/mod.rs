@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use gcp_auth::TokenProvider;
 use http::{Method, StatusCode};
@@ -7,18 +7,28 @@ use ractor::{
     ActorRef,
     factory::{FactoryMessage, Job, Worker, WorkerBuilder, WorkerId},
 };
-use types::{FcmMessage, FcmNotification, FcmSendRequest};
+use types::{FcmAndroidConfig, FcmMessage, FcmNotification, FcmSendRequest};
+use uuid::Uuid;
 
-use crate::state::SharedActorState;
+use crate::{
+    integrations::notify::{Actionable, actions},
+    state::SharedActorState,
+};
 
 pub mod spawn;
 pub mod types;
 
 /// OAuth2 scope required to call the FCM HTTP v1 API.
 const FCM_SCOPE: &str = "https://www.googleapis.com/auth/firebase.messaging";
+/// Metrics label, matching [`crate::settings::NotifySource::channel`].
+const CHANNEL: &str = "android_app";
 
 pub enum PushMessage {
-    Send { title: String, body: String },
+    Send {
+        title: String,
+        body: String,
+        actionable: Option<Actionable>,
+    },
 }
 
 pub struct PushWorker {
@@ -38,15 +48,25 @@ impl PushWorker {
         device_token: String,
         title: &str,
         body: &str,
-    ) {
+        data: Option<&HashMap<String, String>>,
+    ) -> bool {
         let url = format!("https://fcm.googleapis.com/v1/projects/{project_id}/messages:send");
         let payload = FcmSendRequest {
             message: FcmMessage {
                 token: device_token.clone(),
-                notification: FcmNotification {
+                notification: data.is_none().then(|| FcmNotification {
                     title: title.to_string(),
                     body: body.to_string(),
-                },
+                }),
+                data: data.map(|data| {
+                    let mut data = data.clone();
+                    data.insert("title".to_owned(), title.to_owned());
+                    data.insert("body".to_owned(), body.to_owned());
+                    data
+                }),
+                android: data
+                    .is_some()
+                    .then_some(FcmAndroidConfig { priority: "high" }),
             },
         };
 
@@ -61,6 +81,10 @@ impl PushWorker {
         match response {
             Ok(resp) => {
                 let status = resp.status();
+                if status.is_success() {
+                    return true;
+                }
+
                 // FCM returns 404 NOT_FOUND for tokens that are no longer valid
                 // (app uninstalled / token rotated). Prune them so we stop trying.
                 if status == StatusCode::NOT_FOUND {
@@ -72,13 +96,44 @@ impl PushWorker {
                     {
                         tracing::error!("failed to prune push token: {e}");
                     }
-                } else if !status.is_success() {
+                } else {
                     let text = resp.text().await.unwrap_or_default();
                     tracing::error!("fcm send failed ({status}): {text}");
                 }
             }
             Err(e) => tracing::error!("error sending fcm request: {e}"),
         }
+
+        false
+    }
+}
+
+impl PushWorker {
+    /// Record the notification so its callback can be checked, and build the
+    /// FCM data carrying its actions. Without a record a tap could never be
+    /// accepted, so the notification goes out plain instead.
+    async fn record_actions(&self, actionable: &Actionable) -> Option<HashMap<String, String>> {
+        let notification_id = Uuid::new_v4();
+        if let Err(e) =
+            actions::record(&self.shared_actor_state.db, notification_id, actionable).await
+        {
+            tracing::error!("failed to record notification actions, sending without them: {e}");
+            return None;
+        }
+
+        let actions = match serde_json::to_string(&actionable.actions) {
+            Ok(actions) => actions,
+            Err(e) => {
+                tracing::error!("failed to encode notification actions: {e}");
+                return None;
+            }
+        };
+
+        Some(HashMap::from([
+            ("notification_id".to_owned(), notification_id.to_string()),
+            ("event_id".to_owned(), actionable.event_id.to_string()),
+            ("actions".to_owned(), actions),
+        ]))
     }
 }
 
@@ -105,9 +160,14 @@ impl Worker for PushWorker {
         _state: &mut Self::State,
     ) -> Result<(), ractor::ActorProcessingErr> {
         match msg {
-            PushMessage::Send { title, body } => {
+            PushMessage::Send {
+                title,
+                body,
+                actionable,
+            } => {
                 let Some(token_provider) = &self.token_provider else {
                     tracing::warn!("no fcm service account configured, skipping push: {title}");
+                    crate::metrics::record_notification(CHANNEL, "skipped");
                     return Ok(());
                 };
 
@@ -124,15 +184,17 @@ impl Worker for PushWorker {
                     .await
                 {
                     tracing::warn!("notification kill switch is enabled, not sending: {title}");
+                    crate::metrics::record_notification(CHANNEL, "skipped");
                     return Ok(());
                 }
 
-                let project_id = self.shared_actor_state.settings.fcm_project_id.clone();
+                let project_id = self.shared_actor_state.settings().fcm_project_id.clone();
 
                 let access_token = match token_provider.token(&[FCM_SCOPE]).await {
                     Ok(t) => t,
                     Err(e) => {
                         tracing::error!("failed to mint fcm access token: {e}");
+                        crate::metrics::record_notification(CHANNEL, "failed");
                         return Ok(());
                     }
                 };
@@ -145,25 +207,41 @@ impl Worker for PushWorker {
                         Ok(tokens) => tokens,
                         Err(e) => {
                             tracing::error!("failed to load push tokens: {e}");
+                            crate::metrics::record_notification(CHANNEL, "failed");
                             return Ok(());
                         }
                     };
 
                 if device_tokens.is_empty() {
                     tracing::info!("no registered push tokens, skipping push");
+                    crate::metrics::record_notification(CHANNEL, "skipped");
                     return Ok(());
                 }
 
+                let data = match actionable {
+                    Some(actionable) => self.record_actions(&actionable).await,
+                    None => None,
+                };
+
+                let mut delivered = false;
                 for device_token in device_tokens {
-                    self.send_to_token(
-                        access_token.as_str(),
-                        &project_id,
-                        device_token,
-                        &title,
-                        &body,
-                    )
-                    .await;
+                    delivered |= self
+                        .send_to_token(
+                            access_token.as_str(),
+                            &project_id,
+                            device_token,
+                            &title,
+                            &body,
+                            data.as_ref(),
+                        )
+                        .await;
                 }
+
+                // delivered if it reached at least one device
+                crate::metrics::record_notification(
+                    CHANNEL,
+                    if delivered { "delivered" } else { "failed" },
+                );
             }
         }
 
@@ -27,7 +27,10 @@ pub async fn spawn_push(
     // Load the FCM service account once at startup. The secret holds the raw
     // service-account JSON; a missing/invalid value is logged rather than fatal
     // so the rest of the gateway still comes up.
-    let sa_json = shared_actor_state.settings.fcm_service_account_json.clone();
+    let sa_json = shared_actor_state
+        .settings()
+        .fcm_service_account_json
+        .clone();
     let token_provider: Option<Arc<dyn TokenProvider>> = if sa_json.is_empty() {
         tracing::warn!("fcm_service_account_json not set, android push disabled");
         None
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use serde::Serialize;
 
 /// FCM HTTP v1 send payload: `{ "message": { "token", "notification": { ... } } }`.
@@ -9,7 +11,25 @@ pub struct FcmSendRequest {
 #[derive(Serialize)]
 pub struct FcmMessage {
     pub token: String,
-    pub notification: FcmNotification,
+    /// Left off for actionable notifications: Android only hands a message to
+    /// the app (which adds the buttons) when it carries no `notification`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notification: Option<FcmNotification>,
+    /// String-valued data for the app. Actionable notifications carry `title`,
+    /// `body`, `notification_id`, `event_id` and `actions` (a JSON array of
+    /// `{ id, title }`); the app posts the id and the tapped action's `id` back
+    /// to `/v1/ingest/home/notification-action`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<HashMap<String, String>>,
+    /// Data-only messages are delivered at normal priority (and may wait out
+    /// Doze) unless asked otherwise.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub android: Option<FcmAndroidConfig>,
+}
+
+#[derive(Serialize)]
+pub struct FcmAndroidConfig {
+    pub priority: &'static str,
 }
 
 #[derive(Serialize)]
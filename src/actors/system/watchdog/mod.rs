@@ -3,15 +3,38 @@ use std::time::Duration;
 
 use chrono::{DateTime, Utc};
 use ractor::Actor;
+use sqlx::{Pool, Postgres};
 use tracing::Level;
+use uuid::Uuid;
 
-use crate::{integrations::notify::notify, settings::NotifySource, state::SharedActorState};
+use crate::{
+    event_bus::EventBusMessage,
+    integrations::notify::{Origin, notify},
+    settings::NotifySource,
+    state::SharedActorState,
+};
 
 struct LastSeen {
     last_seen: DateTime<Utc>,
     alerted_at: Option<DateTime<Utc>>,
 }
 
+/// When the device behind `address` was last heard from, whether it reported
+/// under its address or (zigbee) its friendly name. `None` if never.
+pub async fn last_seen(
+    db: &Pool<Postgres>,
+    address: &str,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    sqlx::query_scalar!(
+        "SELECT max(dls.last_seen) FROM device_last_seen dls \
+         LEFT JOIN known_devices kd ON kd.name = dls.device_key \
+         WHERE dls.device_key = $1 OR kd.ieee_addr = $1",
+        address
+    )
+    .fetch_one(db)
+    .await
+}
+
 pub enum WatchdogMessage {
     Check,
 }
@@ -23,8 +46,32 @@ pub struct WatchdogActor {
 impl WatchdogActor {
     pub const NAME: &str = "watchdog";
 
+    /// Publish a device going offline or coming back, for workflows and the
+    /// subscription stream. Returns the event id so alerts can be correlated.
+    fn publish(&self, address: &str, available: bool, last_seen: DateTime<Utc>) -> Uuid {
+        let device_id = self
+            .shared_actor_state
+            .devices()
+            .id_for_address(address)
+            .unwrap_or(address)
+            .to_owned();
+
+        let event_id = Uuid::new_v4();
+        self.shared_actor_state
+            .event_bus
+            .publish(EventBusMessage::DeviceAvailability {
+                event_id,
+                device_id,
+                address: address.to_owned(),
+                available,
+                last_seen,
+            });
+
+        event_id
+    }
+
     async fn check(&self) -> Result<(), anyhow::Error> {
-        let watchdog = &self.shared_actor_state.settings.watchdog;
+        let watchdog = &self.shared_actor_state.settings().watchdog;
         let now = Utc::now();
         let realert_before = now - watchdog.realert_after;
 
@@ -44,20 +91,28 @@ impl WatchdogActor {
             })
             .collect();
 
-        for (device_key, device) in self.shared_actor_state.devices.watchdog_devices() {
+        let android_app = [NotifySource::android_app()];
+        for (device_key, device) in self.shared_actor_state.devices().watchdog_devices() {
             let Some(state) = seen.get(device_key) else {
                 continue;
             };
 
-            let timeout = device.timeout.unwrap_or(watchdog.timeout);
             let targets: &[NotifySource] = if device.notify.is_empty() {
-                std::slice::from_ref(&NotifySource::AndroidApp)
+                &android_app
             } else {
                 &device.notify
             };
-            let is_stale = state.last_seen < now - timeout;
+            let is_stale = !watchdog.available(Some(device), state.last_seen, now);
 
             if is_stale {
+                // `alerted_at` doubles as the offline flag, so only the first
+                // alert is an edge; re-alerts are just reminders
+                let event_id = if state.alerted_at.is_none() {
+                    self.publish(device_key, false, state.last_seen)
+                } else {
+                    Uuid::new_v4()
+                };
+
                 let should_alert = state
                     .alerted_at
                     .is_none_or(|alerted_at| alerted_at < realert_before);
@@ -65,6 +120,8 @@ impl WatchdogActor {
                     notify(
                         targets,
                         format!("Sensor offline: {device_key} has stopped reporting"),
+                        Origin::new(event_id, format!("watchdog:{device_key}")),
+                        &device.actions,
                     );
                     sqlx::query!(
                         "UPDATE device_last_seen SET alerted_at = $1 WHERE device_key = $2",
@@ -75,9 +132,12 @@ impl WatchdogActor {
                     .await?;
                 }
             } else if state.alerted_at.is_some() {
+                let event_id = self.publish(device_key, true, state.last_seen);
                 notify(
                     targets,
                     format!("Sensor back online: {device_key} is reporting again"),
+                    Origin::new(event_id, format!("watchdog:{device_key}")),
+                    &[],
                 );
                 sqlx::query!(
                     "UPDATE device_last_seen SET alerted_at = NULL WHERE device_key = $1",
@@ -102,7 +162,7 @@ impl Actor for WatchdogActor {
         myself: ractor::ActorRef<Self::Msg>,
         _args: Self::Arguments,
     ) -> Result<Self::State, ractor::ActorProcessingErr> {
-        let watchdog = &self.shared_actor_state.settings.watchdog;
+        let watchdog = &self.shared_actor_state.settings().watchdog;
         if !watchdog.enabled {
             tracing::info!("sensor offline watchdog is disabled, not arming checks");
             return Ok(());
@@ -4,7 +4,8 @@
 //!
 //! Both need to answer the same boolean predicates against live device/sensor
 //! state, so the actor-query RPC logic lives here once and takes a plain
-//! [`SharedActorState`] rather than being tied to the workflow worker.
+//! [`SharedActorState`] rather than being tied to the workflow worker. Template
+//! lookups ([`super::scope`]) read the same state through it too.
 
 use super::WorkflowError;
 use crate::actors::sun::calc;
@@ -17,6 +18,7 @@ use crate::{
         devices::light::{LightHandler, LightHandlerMessage},
         devices::presence_sensor::{Message as PresenceMessage, PresenceSensorHandler},
         system::rpc::{self, RpcError},
+        system::watchdog,
     },
     db::DoorState,
     settings::workflow::{Combinator, Comparison, Condition, EnvMetric, LeafCondition},
@@ -72,18 +74,18 @@ async fn eval_combinator(
 async fn eval_leaf(state: &SharedActorState, cond: &LeafCondition) -> Result<bool, WorkflowError> {
     match cond {
         LeafCondition::Light { ieee_addr, on } => {
-            Ok(query_light_on(state.devices.address_or_self(ieee_addr)).await? == *on)
+            Ok(query_light_on(state.devices().address_or_self(ieee_addr)).await? == *on)
         }
         LeafCondition::Environment {
             sensor,
             metric,
             cmp,
-        } => eval_environment(state.devices.address_or_self(sensor), *metric, *cmp).await,
+        } => eval_environment(state.devices().address_or_self(sensor), *metric, *cmp).await,
         LeafCondition::Door { ieee_addr, open } => {
-            Ok(query_door_open(state.devices.address_or_self(ieee_addr)).await? == *open)
+            Ok(query_door_open(state.devices().address_or_self(ieee_addr)).await? == *open)
         }
         LeafCondition::Presence { sensor, present } => {
-            Ok(query_presence(state.devices.address_or_self(sensor)).await? == *present)
+            Ok(query_presence(state.devices().address_or_self(sensor)).await? == *present)
         }
         LeafCondition::TimeOfDay { after, before } => {
             let now = Local::now().time();
@@ -96,24 +98,95 @@ async fn eval_leaf(state: &SharedActorState, cond: &LeafCondition) -> Result<boo
             })
         }
         LeafCondition::Sun { is, offset } => {
-            Ok(calc::current_period(state.settings.location, Utc::now(), *offset) == *is)
+            Ok(calc::current_period(state.settings().location, Utc::now(), *offset) == *is)
         }
         LeafCondition::Mode { mode, active } => {
             Ok(state.workflows.mode_active(*mode).await == *active)
         }
+        LeafCondition::Variable { variable, cmp } => Ok(variable_number(state, variable)
+            .await?
+            .is_some_and(|value| cmp.matches(value))),
+        LeafCondition::Available { device, available } => {
+            Ok(
+                device_available(state, state.devices().address_or_self(device)).await?
+                    == *available,
+            )
+        }
+        LeafCondition::RoomOccupied { room, occupied } => {
+            Ok(room_occupied(state, room).await? == *occupied)
+        }
+    }
+}
+
+/// Whether any presence sensor in `room` currently detects someone. A room
+/// without presence sensors is never occupied.
+async fn room_occupied(state: &SharedActorState, room: &str) -> Result<bool, WorkflowError> {
+    let devices = state.devices();
+
+    for address in devices.room_members(room) {
+        if devices.presence(address).is_some() && query_presence(address).await? {
+            return Ok(true);
+        }
     }
+
+    Ok(false)
+}
+
+/// Whether the device has been heard from within its watchdog timeout. A
+/// device that has never reported counts as unavailable.
+async fn device_available(state: &SharedActorState, address: &str) -> Result<bool, WorkflowError> {
+    let last_seen = watchdog::last_seen(&state.db, address)
+        .await
+        .map_err(|e| WorkflowError::Other(e.into()))?;
+
+    let devices = state.devices();
+    let watchdog = &state.settings().watchdog;
+
+    Ok(last_seen.is_some_and(|last_seen| {
+        watchdog.available(devices.watchdog(address), last_seen, Utc::now())
+    }))
+}
+
+/// A number or boolean variable's current value as a number, `None` when it is
+/// no longer declared (a config reload can drop it) or has no value.
+async fn variable_number(
+    state: &SharedActorState,
+    name: &str,
+) -> Result<Option<f64>, WorkflowError> {
+    let settings = state.settings();
+    let Some(variable) = settings.variables.get(name) else {
+        tracing::warn!("condition reads undeclared variable `{name}`");
+        return Ok(None);
+    };
+
+    let value = state
+        .workflows
+        .variable(name, variable)
+        .await
+        .map_err(|e| WorkflowError::Other(e.into()))?;
+
+    Ok(value.and_then(|value| variable.number(&value)))
 }
 
 async fn query_light_on(ieee_addr: &str) -> Result<bool, WorkflowError> {
-    Ok(
-        rpc::query_factory(LightHandler::NAME, QUERY_TIMEOUT, |reply| {
-            LightHandlerMessage::QueryPowerState {
-                ieee_addr: ieee_addr.to_owned(),
-                reply,
-            }
-        })
-        .await?,
-    )
+    Ok(light_on(ieee_addr).await?.unwrap_or(false))
+}
+
+/// `None` (and a warning) when the light hasn't reported a state yet.
+pub(super) async fn light_on(ieee_addr: &str) -> Result<Option<bool>, WorkflowError> {
+    let on: Option<bool> = rpc::query_factory(LightHandler::NAME, QUERY_TIMEOUT, |reply| {
+        LightHandlerMessage::QueryPowerState {
+            ieee_addr: ieee_addr.to_owned(),
+            reply,
+        }
+    })
+    .await?;
+
+    if on.is_none() {
+        tracing::warn!("no power state for light {ieee_addr}");
+    }
+
+    Ok(on)
 }
 
 async fn eval_environment(
@@ -121,6 +194,17 @@ async fn eval_environment(
     metric: EnvMetric,
     cmp: Comparison,
 ) -> Result<bool, WorkflowError> {
+    Ok(environment_value(sensor, metric)
+        .await?
+        .is_some_and(|value| cmp.matches(value)))
+}
+
+/// The sensor's latest reading for `metric`, `None` (and a warning) when it
+/// hasn't reported one yet.
+pub(super) async fn environment_value(
+    sensor: &str,
+    metric: EnvMetric,
+) -> Result<Option<f64>, WorkflowError> {
     let reading: Option<LatestReading> =
         rpc::query_factory(EnvironmentSensorHandler::NAME, QUERY_TIMEOUT, |reply| {
             EnvironmentMessage::QueryLatest {
@@ -132,7 +216,7 @@ async fn eval_environment(
 
     let Some(reading) = reading else {
         tracing::warn!("no readings for environment sensor {sensor}");
-        return Ok(false);
+        return Ok(None);
     };
 
     let value = match metric {
@@ -143,15 +227,19 @@ async fn eval_environment(
         EnvMetric::UvIndex => reading.uv_index,
     };
 
-    let Some(value) = value else {
+    if value.is_none() {
         tracing::warn!("environment sensor {sensor} has no reading for {metric:?}");
-        return Ok(false);
-    };
+    }
+
+    Ok(value)
+}
 
-    Ok(cmp.matches(value))
+pub(crate) async fn query_presence(sensor: &str) -> Result<bool, WorkflowError> {
+    Ok(latest_presence(sensor).await?.unwrap_or(false))
 }
 
-async fn query_presence(sensor: &str) -> Result<bool, WorkflowError> {
+/// `None` (and a warning) when the sensor hasn't reported yet.
+pub(super) async fn latest_presence(sensor: &str) -> Result<Option<bool>, WorkflowError> {
     let present: Option<bool> =
         rpc::query_factory(PresenceSensorHandler::NAME, QUERY_TIMEOUT, |reply| {
             PresenceMessage::QueryLatest {
@@ -161,16 +249,19 @@ async fn query_presence(sensor: &str) -> Result<bool, WorkflowError> {
         })
         .await?;
 
-    match present {
-        Some(present) => Ok(present),
-        None => {
-            tracing::warn!("no presence reading for sensor {sensor}");
-            Ok(false)
-        }
+    if present.is_none() {
+        tracing::warn!("no presence reading for sensor {sensor}");
     }
+
+    Ok(present)
 }
 
 async fn query_door_open(ieee_addr: &str) -> Result<bool, WorkflowError> {
+    Ok(door_open(ieee_addr).await?.unwrap_or(false))
+}
+
+/// `None` (and a warning) when the door's state isn't known yet.
+pub(super) async fn door_open(ieee_addr: &str) -> Result<Option<bool>, WorkflowError> {
     let state: Option<DoorState> = rpc::query(DerivedDoorEvents::NAME, QUERY_TIMEOUT, |reply| {
         DoorEventsMessage::QueryState {
             ieee_addr: ieee_addr.to_owned(),
@@ -179,11 +270,9 @@ async fn query_door_open(ieee_addr: &str) -> Result<bool, WorkflowError> {
     })
     .await?;
 
-    match state {
-        Some(state) => Ok(matches!(state, DoorState::Open)),
-        None => {
-            tracing::warn!("no door state for {ieee_addr}");
-            Ok(false)
-        }
+    if state.is_none() {
+        tracing::warn!("no door state for {ieee_addr}");
     }
+
+    Ok(state.map(|state| matches!(state, DoorState::Open)))
 }
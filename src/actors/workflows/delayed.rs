@@ -0,0 +1,361 @@
+//! Durable delays: delayed triggers, and the rest of a workflow after a `delay`
+//! step, parked in the `workflow_delays` pgmq queue so a restart doesn't lose
+//! them.
+//!
+//! Parking sends a [`DelayedRun`] with the delay as its visibility timeout, so
+//! it only becomes readable once due. [`DelayedRunActor`] polls the queue,
+//! hands what it reads to the `WorkflowWorker` factory and only then deletes
+//! it, looking the workflow up by slug in whatever config is current at that
+//! point.
+//!
+//! After downtime the backlog comes due at once on the first poll. Anything
+//! overdue by more than `workflow.resume_within` is expired instead of run,
+//! the same way `SunActor` only catches up within `sun.catch_up_within`.
+//!
+//! Delayed triggers remember the [`EventSubject`] that armed them, and the
+//! dispatcher deletes them from the queue when a fresh event for that subject
+//! arrives, before they ever fire.
+
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{DateTime, Utc};
+use ractor::{
+    Actor, ActorProcessingErr, ActorRef,
+    factory::{FactoryMessage, Job, JobOptions},
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Postgres, types::Json};
+use tracing::Level;
+use uuid::Uuid;
+
+use crate::{
+    actors::workflows::{
+        Continuation, WorkflowWorker, WorkflowWorkerMessage, manager::WorkflowRun, trace::RunTrace,
+    },
+    state::SharedActorState,
+};
+
+pub const QUEUE: &str = "workflow_delays";
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// How long a read message stays invisible before pgmq hands it out again.
+/// Only matters if its run isn't handed off: the process dies in between, or
+/// dispatching it fails.
+const VISIBILITY_TIMEOUT: i32 = 30;
+const BATCH_SIZE: i32 = 32;
+
+/// `(event kind, entity)` of the event that armed a delayed trigger.
+pub type EventSubject = (String, String);
+
+/// A parked workflow run, as stored in the queue.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DelayedRun {
+    pub event_id: Uuid,
+    pub slug: String,
+    pub name: String,
+    pub due_at: DateTime<Utc>,
+    pub vars: HashMap<String, String>,
+    #[serde(flatten)]
+    pub resume: Resume,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "resume", rename_all = "snake_case")]
+pub enum Resume {
+    /// A delayed trigger: run the workflow from the top.
+    Trigger {
+        subject_kind: String,
+        subject_entity: String,
+    },
+    /// Carry on after the `delay` step at `path`, its index at each level of
+    /// `scene` / `run_workflow` nesting, if the workflow's steps still have
+    /// the [`Step::digest`](crate::settings::workflow::Step::digest) `digest`.
+    Step {
+        path: Vec<usize>,
+        #[serde(default)]
+        digest: Option<String>,
+    },
+}
+
+/// Park `run` until `delay` has passed.
+pub async fn park(
+    db: &Pool<Postgres>,
+    run: &DelayedRun,
+    delay: Duration,
+) -> Result<(), sqlx::Error> {
+    let delay = i32::try_from(delay.as_secs()).unwrap_or(i32::MAX);
+
+    sqlx::query!(
+        r#"SELECT pgmq.send($1::text, $2::jsonb, $3::integer) AS "msg_id!""#,
+        QUEUE,
+        Json(run) as _,
+        delay
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(())
+}
+
+/// Drop every delayed trigger armed for `subject`, returning the names of the
+/// workflows they would have run. Parked `delay` continuations are left alone.
+pub async fn cancel_for(
+    db: &Pool<Postgres>,
+    subject: &EventSubject,
+) -> Result<Vec<String>, sqlx::Error> {
+    let (kind, entity) = subject;
+
+    sqlx::query_scalar!(
+        r#"DELETE FROM pgmq.q_workflow_delays
+           WHERE message->>'resume' = 'trigger'
+             AND message->>'subject_kind' = $1
+             AND message->>'subject_entity' = $2
+           RETURNING message->>'name' AS "name!""#,
+        kind,
+        entity
+    )
+    .fetch_all(db)
+    .await
+}
+
+/// Subjects with a delayed trigger armed for them, the only ones worth a
+/// [`cancel_for`].
+pub async fn armed_subjects(db: &Pool<Postgres>) -> Result<Vec<EventSubject>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"SELECT DISTINCT message->>'subject_kind' AS "kind!",
+                  message->>'subject_entity' AS "entity!"
+           FROM pgmq.q_workflow_delays
+           WHERE message->>'resume' = 'trigger'"#
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(rows.into_iter().map(|row| (row.kind, row.entity)).collect())
+}
+
+/// Event ids of the runs of `slug` parked behind a `delay`.
+pub async fn parked(db: &Pool<Postgres>, slug: &str) -> Result<Vec<Uuid>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"SELECT (message->>'event_id')::uuid AS "event_id!"
+           FROM pgmq.q_workflow_delays
+           WHERE message->>'resume' = 'step' AND message->>'slug' = $1"#,
+        slug
+    )
+    .fetch_all(db)
+    .await
+}
+
+/// Drop the runs of `slug` parked behind a `delay`, returning their event ids.
+/// Delayed triggers are left alone; they haven't started a run yet.
+pub async fn cancel_parked(db: &Pool<Postgres>, slug: &str) -> Result<Vec<Uuid>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"DELETE FROM pgmq.q_workflow_delays
+           WHERE message->>'resume' = 'step' AND message->>'slug' = $1
+           RETURNING (message->>'event_id')::uuid AS "event_id!""#,
+        slug
+    )
+    .fetch_all(db)
+    .await
+}
+
+pub enum DelayedRunMessage {
+    Poll,
+}
+
+pub struct DelayedRunActor {
+    pub shared_actor_state: SharedActorState,
+}
+
+impl DelayedRunActor {
+    pub const NAME: &str = "workflow-delayed";
+
+    async fn poll(&self) -> Result<(), ActorProcessingErr> {
+        let db = &self.shared_actor_state.db;
+
+        let due = sqlx::query!(
+            r#"SELECT msg_id AS "msg_id!", message AS "message!"
+               FROM pgmq.read($1::text, $2::integer, $3::integer)"#,
+            QUEUE,
+            VISIBILITY_TIMEOUT,
+            BATCH_SIZE
+        )
+        .fetch_all(db)
+        .await?;
+
+        for row in due {
+            // hold the message until its run is handed off, so a crash or a
+            // failed dispatch leaves it to be read again once it's visible
+            let mut tx = db.begin().await?;
+            let claimed = sqlx::query_scalar!(
+                "SELECT msg_id FROM pgmq.q_workflow_delays WHERE msg_id = $1 FOR UPDATE",
+                row.msg_id
+            )
+            .fetch_optional(&mut *tx)
+            .await?;
+
+            // cancelled by a fresh event since the read
+            if claimed.is_none() {
+                continue;
+            }
+
+            match DelayedRun::deserialize(&row.message) {
+                Ok(run) => self.resume(run).await?,
+                Err(e) => {
+                    tracing::error!("dropping unreadable delayed run {}: {e}", row.msg_id);
+                    // release its run if enough of it can be read to name one
+                    if let Some(slug) = row.message["slug"].as_str()
+                        && let Some(Ok(event_id)) =
+                            row.message["event_id"].as_str().map(Uuid::parse_str)
+                    {
+                        self.release(slug, event_id);
+                    }
+                }
+            }
+
+            sqlx::query_scalar!(
+                r#"SELECT pgmq.delete($1::text, $2::bigint) AS "deleted!""#,
+                QUEUE,
+                row.msg_id
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            tx.commit().await?;
+        }
+
+        Ok(())
+    }
+
+    async fn resume(&self, run: DelayedRun) -> Result<(), ActorProcessingErr> {
+        let DelayedRun {
+            event_id,
+            slug,
+            name,
+            due_at,
+            vars,
+            resume,
+        } = run;
+        let settings = self.shared_actor_state.settings();
+
+        let overdue = Utc::now() - due_at;
+        if overdue > settings.workflow.resume_within {
+            tracing::warn!(
+                "[{event_id}] expiring delayed run of '{name}', overdue by {}",
+                crate::timedelta_format::humanize(overdue)
+            );
+            self.release(&slug, event_id);
+            crate::metrics::record_workflow("expired", Duration::ZERO);
+            self.shared_actor_state
+                .workflows
+                .record_run(&WorkflowRun {
+                    slug,
+                    name,
+                    event_id,
+                    outcome: "expired".to_owned(),
+                    dry_run: false,
+                    duration: Duration::ZERO,
+                    error: None,
+                    trace: RunTrace::with_vars(&vars),
+                })
+                .await;
+            return Ok(());
+        }
+
+        let Some(workflow) = settings.workflows.values().find(|w| w.slug == slug) else {
+            tracing::warn!(
+                "[{event_id}] dropping delayed run of '{name}', workflow no longer configured"
+            );
+            self.release(&slug, event_id);
+            return Ok(());
+        };
+
+        let workflow = workflow.clone();
+        let msg = match resume {
+            Resume::Trigger { .. } => {
+                tracing::info!("[{event_id}] delayed trigger '{name}' firing");
+                WorkflowWorkerMessage::Execute {
+                    event_id,
+                    workflow,
+                    vars,
+                    reply: None,
+                }
+            }
+            Resume::Step { path, digest } => {
+                tracing::info!("[{event_id}] resuming '{name}' after delay");
+                WorkflowWorkerMessage::Resume {
+                    event_id,
+                    workflow,
+                    vars,
+                    continuation: Continuation { path, digest },
+                }
+            }
+        };
+
+        let Some(actor) = ractor::registry::where_is(WorkflowWorker::NAME) else {
+            tracing::warn!("[{event_id}] workflow factory not found, dropping delayed run");
+            self.release(&slug, event_id);
+            return Ok(());
+        };
+
+        actor.send_message(FactoryMessage::Dispatch(Job {
+            key: (),
+            msg,
+            options: JobOptions::default(),
+            accepted: None,
+        }))?;
+
+        Ok(())
+    }
+
+    /// Release a parked run that won't resume from its workflow's runs in
+    /// flight, so its `mode:` stops counting it, and start whatever was
+    /// queued behind it.
+    fn release(&self, slug: &str, event_id: Uuid) {
+        let next = self
+            .shared_actor_state
+            .workflows
+            .runs()
+            .forget(slug, event_id);
+
+        if let Some(next) = next
+            && let Err(e) = WorkflowWorker::dispatch_queued(next)
+        {
+            tracing::error!("[{event_id}] failed to start the run queued behind it: {e}");
+        }
+    }
+}
+
+impl Actor for DelayedRunActor {
+    type Msg = DelayedRunMessage;
+    type State = ();
+    type Arguments = ();
+
+    async fn pre_start(
+        &self,
+        myself: ActorRef<Self::Msg>,
+        _args: Self::Arguments,
+    ) -> Result<Self::State, ActorProcessingErr> {
+        // poll straight away so anything that came due while down resumes now
+        myself.send_message(DelayedRunMessage::Poll)?;
+        let _join_handle = myself.send_interval(POLL_INTERVAL, || DelayedRunMessage::Poll);
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "workflow-delayed-actor", skip(self, _myself, message, _state), level = Level::TRACE)]
+    async fn handle(
+        &self,
+        _myself: ActorRef<Self::Msg>,
+        message: Self::Msg,
+        _state: &mut Self::State,
+    ) -> Result<(), ActorProcessingErr> {
+        match message {
+            DelayedRunMessage::Poll => {
+                if let Err(e) = self.poll().await {
+                    tracing::error!("error polling delayed workflow runs: {e}");
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -6,9 +6,10 @@
 //! the optional `when` condition, and forwards matching workflows to the
 //! `WorkflowWorker` factory. It deliberately does **no** workflow execution
 //! itself — it only matches and dispatches, so the factory's worker pool keeps
-//! providing the parallelism.
+//! providing the parallelism. Triggers held `for:` a duration are timed here
+//! too; see [`super::held`].
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use ractor::{
     Actor, ActorProcessingErr, ActorRef,
@@ -18,7 +19,12 @@ use tokio::sync::broadcast::error::RecvError;
 use tracing::Instrument;
 
 use crate::{
-    actors::workflows::{WorkflowWorker, WorkflowWorkerMessage, conditions},
+    actors::workflows::{
+        WorkflowWorker, WorkflowWorkerMessage, conditions,
+        delayed::{self, DelayedRun, EventSubject, Resume},
+        held::{self, Elapsed, Held},
+    },
+    device_registry::DeviceRegistry,
     event_bus::{EventBusMessage, SensorMetric, SolarMetric},
     integrations::solar::{queries, types::SolarCurrentStatisticsAverages},
     settings::{TriggerMatcher, Workflow},
@@ -29,6 +35,15 @@ pub struct WorkflowDispatcher {
     pub shared_actor_state: SharedActorState,
 }
 
+pub enum WorkflowDispatcherMessage {
+    Event(EventBusMessage),
+    /// A `for:` trigger's timer ran out.
+    Held(Elapsed),
+    /// The config was reloaded: drop the `for:` timers of triggers that
+    /// changed and arm the current ones from their state.
+    Rearm,
+}
+
 #[derive(Default)]
 pub struct WorkflowDispatcherState {
     /// `(trigger name, sensor, metric) -> comparison satisfied at last reading`.
@@ -38,27 +53,68 @@ pub struct WorkflowDispatcherState {
     /// `(trigger name, metric) -> comparison satisfied at last poll`, the solar
     /// counterpart to `last_satisfied` (there is only one plant, so no subject).
     last_solar_satisfied: HashMap<(String, SolarMetric), bool>,
-    pending_delays: HashMap<(EventSubject, String), tokio::task::JoinHandle<()>>,
+    /// `room -> presence sensors currently detecting someone`, seen since
+    /// start-up. A room is occupied while its set is non-empty, so `room`
+    /// triggers fire on the first sensor in and the last sensor out.
+    room_presence: HashMap<String, HashSet<String>>,
+    /// `(workflow slug, subject entity) -> for: timer`, for triggers held
+    /// `for:` a duration whose state is in effect.
+    held: HashMap<(String, String), Held>,
+    /// `workflow slug -> its for: trigger when armed`, so a reload can tell
+    /// which timers were armed under a trigger that has since changed.
+    held_triggers: HashMap<String, String>,
+    /// Subjects a delayed trigger may be armed for, loaded from the queue on
+    /// start-up and added to as triggers are deferred. Events for any other
+    /// subject have nothing to cancel, so skip the round-trip.
+    delayed_subjects: HashSet<EventSubject>,
 }
 
-type EventSubject = (String, String);
+/// A room's occupancy flipped on this event.
+struct RoomChange {
+    room: String,
+    occupied: bool,
+}
 
 impl WorkflowDispatcherState {
-    fn cancel_pending_for(&mut self, subject: &EventSubject) -> Vec<String> {
-        let mut cancelled = Vec::new();
-
-        self.pending_delays.retain(|(s, name), handle| {
-            if s == subject {
-                handle.abort();
-                cancelled.push(name.clone());
-                false
-            } else {
-                true
-            }
-        });
+    /// Fold a presence reading into its room's present sensors, returning the
+    /// room's new occupancy if the reading flipped it.
+    fn track_room(
+        &mut self,
+        devices: &DeviceRegistry,
+        msg: &EventBusMessage,
+    ) -> Option<RoomChange> {
+        let EventBusMessage::Presence {
+            sensor, present, ..
+        } = msg
+        else {
+            return None;
+        };
+        let room = devices.room(sensor)?;
+        let present_sensors = self.room_presence.entry(room.to_owned()).or_default();
+
+        occupancy_flip(present_sensors, sensor, *present).map(|occupied| RoomChange {
+            room: room.to_owned(),
+            occupied,
+        })
+    }
+}
 
-        cancelled
+fn occupancy_flip(
+    present_sensors: &mut HashSet<String>,
+    sensor: &str,
+    present: bool,
+) -> Option<bool> {
+    let was_occupied = !present_sensors.is_empty();
+
+    if present {
+        present_sensors.insert(sensor.to_owned());
+    } else {
+        present_sensors.remove(sensor);
     }
+
+    let occupied = !present_sensors.is_empty();
+
+    (occupied != was_occupied).then_some(occupied)
 }
 
 enum PendingLatch {
@@ -107,17 +163,218 @@ fn solar_fires(
     true
 }
 
+/// Whether `msg` is an event `on` matches, for the triggers that need nothing
+/// but the event itself. Rooms, cron and the rising-edge environment and solar
+/// triggers carry dispatcher state and never match here. A `wait_for` step's
+/// `event` is matched through this too.
+pub(super) fn event_matches(
+    devices: &DeviceRegistry,
+    on: &TriggerMatcher,
+    msg: &EventBusMessage,
+) -> bool {
+    match (on, msg) {
+        (
+            TriggerMatcher::Presence {
+                sensor, present, ..
+            },
+            EventBusMessage::Presence {
+                sensor: s,
+                present: p,
+                ..
+            },
+        ) => devices.address_or_self(sensor) == s.as_str() && present == p,
+        (
+            TriggerMatcher::Door {
+                ieee_addr, open, ..
+            },
+            EventBusMessage::Door {
+                ieee_addr: a,
+                open: o,
+                ..
+            },
+        ) => devices.address_or_self(ieee_addr) == a.as_str() && open == o,
+        (
+            TriggerMatcher::Switch { ieee_addr, action },
+            EventBusMessage::SwitchAction {
+                ieee_addr: a,
+                action: ac,
+                ..
+            },
+        ) => devices.address_or_self(ieee_addr) == a.as_str() && action == ac,
+        (
+            TriggerMatcher::Sun { transition, offset },
+            EventBusMessage::Sun {
+                transition: t,
+                offset: o,
+                ..
+            },
+        ) => transition == t && offset == o,
+        (
+            TriggerMatcher::Mode { mode, active },
+            EventBusMessage::Mode {
+                mode: m, active: a, ..
+            },
+        ) => mode == m && active == a,
+        (
+            TriggerMatcher::HomeAssistant {
+                entity_id, state, ..
+            },
+            EventBusMessage::HomeAssistant {
+                entity_id: e,
+                state: s,
+                ..
+            },
+        ) => entity_id == e && state.as_ref().is_none_or(|state| state == s),
+        (
+            TriggerMatcher::Woolworths {
+                product_id,
+                min_drop,
+            },
+            EventBusMessage::Woolworths {
+                product_id: id,
+                old_price,
+                new_price,
+                ..
+            },
+        ) => {
+            product_id.is_none_or(|p| p == *id)
+                && min_drop.is_none_or(|min| old_price - new_price >= min)
+        }
+        (
+            TriggerMatcher::DeviceBattery {
+                device_id,
+                kind,
+                below,
+            },
+            EventBusMessage::DeviceBattery {
+                device_id: id,
+                kind: k,
+                battery_voltage,
+                battery_percent,
+                ..
+            },
+        ) => {
+            let level = battery_percent.or(*battery_voltage);
+            device_id.as_ref().is_none_or(|d| d == id)
+                && kind.as_ref().is_none_or(|want| want == k)
+                && below.is_none_or(|threshold| level.is_some_and(|l| l < threshold))
+        }
+        (
+            TriggerMatcher::Jellyfin {
+                state,
+                user,
+                device,
+                item_type,
+            },
+            EventBusMessage::Jellyfin {
+                state: s,
+                user: u,
+                device: d,
+                item_type: t,
+                ..
+            },
+        ) => {
+            state.is_none_or(|state| state == *s)
+                && user.as_ref().is_none_or(|user| user == u)
+                && device.as_ref().is_none_or(|device| device == d)
+                && item_type.as_ref().is_none_or(|item_type| item_type == t)
+        }
+        (
+            TriggerMatcher::MediaPlayer {
+                device, state, app, ..
+            },
+            EventBusMessage::MediaPlayer {
+                device_id: d,
+                state: s,
+                app_name: a,
+                ..
+            },
+        ) => {
+            state.is_none_or(|state| state == *s)
+                && device.as_ref().is_none_or(|device| device == d)
+                && app
+                    .as_ref()
+                    .is_none_or(|app| a.as_ref().is_some_and(|actual| actual == app))
+        }
+        (TriggerMatcher::Variable { variable }, EventBusMessage::Variable { name, .. }) => {
+            variable == name
+        }
+        (
+            TriggerMatcher::Light { ieee_addr, on, .. },
+            EventBusMessage::Light {
+                ieee_addr: a,
+                on: o,
+                ..
+            },
+        ) => devices.address_or_self(ieee_addr) == a.as_str() && on == o,
+        (
+            TriggerMatcher::Unifi {
+                client,
+                mac_address,
+                connected,
+            },
+            EventBusMessage::Unifi {
+                mac_address: m,
+                client: c,
+                connected: co,
+                ..
+            },
+        ) => {
+            connected == co
+                && client.as_ref().is_none_or(|client| client == c)
+                && mac_address
+                    .as_ref()
+                    .is_none_or(|mac| mac.eq_ignore_ascii_case(m))
+        }
+        (
+            TriggerMatcher::RobotVacuum { device, from, to },
+            EventBusMessage::RobotVacuum {
+                device_id,
+                state,
+                previous,
+                ..
+            },
+        ) => {
+            device.as_ref().is_none_or(|device| device == device_id)
+                && to.as_ref().is_none_or(|to| to == state)
+                && from
+                    .as_ref()
+                    .is_none_or(|from| previous.as_ref().is_some_and(|p| p == from))
+        }
+        (
+            TriggerMatcher::DeviceAvailability { device, available },
+            EventBusMessage::DeviceAvailability {
+                device_id,
+                available: a,
+                ..
+            },
+        ) => available == a && device.as_ref().is_none_or(|device| device == device_id),
+        (
+            TriggerMatcher::NotificationAction { action, source },
+            EventBusMessage::NotificationAction {
+                action: a,
+                source: s,
+                ..
+            },
+        ) => action == a && source.as_ref().is_none_or(|source| source == s),
+
+        _ => false,
+    }
+}
+
 impl WorkflowDispatcher {
     pub const NAME: &str = "workflow-dispatcher";
 
     /// Decide whether `trigger.on` matches `msg`. The matcher's device/sensor
     /// references are registry ids, resolved to addresses here to compare against
     /// the event (which carries addresses). For environment triggers this also
-    /// updates rising-edge state, so it takes `&mut state`.
+    /// updates rising-edge state, so it takes `&mut state`. Room triggers match
+    /// the occupancy change `msg` caused, if any, rather than `msg` itself.
     fn matches(
         &self,
         workflow: &Workflow,
         msg: &EventBusMessage,
+        room_change: Option<&RoomChange>,
         averages: Option<&SolarCurrentStatisticsAverages>,
         state: &mut WorkflowDispatcherState,
         pending: &mut Option<PendingLatch>,
@@ -125,37 +382,19 @@ impl WorkflowDispatcher {
         let Some(on) = workflow.on() else {
             return false;
         };
-        let devices = &self.shared_actor_state.devices;
+        if let TriggerMatcher::Room { room, occupied } = on {
+            return room_change
+                .is_some_and(|change| &change.room == room && change.occupied == *occupied);
+        }
+
+        let devices = self.shared_actor_state.devices();
         match (on, msg) {
-            (
-                TriggerMatcher::Presence { sensor, present },
-                EventBusMessage::Presence {
-                    sensor: s,
-                    present: p,
-                    ..
-                },
-            ) => devices.address_or_self(sensor) == s.as_str() && present == p,
-            (
-                TriggerMatcher::Door { ieee_addr, open },
-                EventBusMessage::Door {
-                    ieee_addr: a,
-                    open: o,
-                    ..
-                },
-            ) => devices.address_or_self(ieee_addr) == a.as_str() && open == o,
-            (
-                TriggerMatcher::Switch { ieee_addr, action },
-                EventBusMessage::SwitchAction {
-                    ieee_addr: a,
-                    action: ac,
-                    ..
-                },
-            ) => devices.address_or_self(ieee_addr) == a.as_str() && action == ac,
             (
                 TriggerMatcher::Environment {
                     sensor,
                     metric,
                     cmp,
+                    ..
                 },
                 EventBusMessage::Environment {
                     sensor: s,
@@ -188,97 +427,6 @@ impl WorkflowDispatcher {
             (TriggerMatcher::Cron { .. }, EventBusMessage::Cron { name, .. }) => {
                 &workflow.name == name
             }
-            (
-                TriggerMatcher::Sun { transition, offset },
-                EventBusMessage::Sun {
-                    transition: t,
-                    offset: o,
-                    ..
-                },
-            ) => transition == t && offset == o,
-            (
-                TriggerMatcher::Mode { mode, active },
-                EventBusMessage::Mode {
-                    mode: m, active: a, ..
-                },
-            ) => mode == m && active == a,
-            (
-                TriggerMatcher::HomeAssistant { entity_id, state },
-                EventBusMessage::HomeAssistant {
-                    entity_id: e,
-                    state: s,
-                    ..
-                },
-            ) => entity_id == e && state.as_ref().is_none_or(|state| state == s),
-            (
-                TriggerMatcher::Woolworths {
-                    product_id,
-                    min_drop,
-                },
-                EventBusMessage::Woolworths {
-                    product_id: id,
-                    old_price,
-                    new_price,
-                    ..
-                },
-            ) => {
-                product_id.is_none_or(|p| p == *id)
-                    && min_drop.is_none_or(|min| old_price - new_price >= min)
-            }
-            (
-                TriggerMatcher::DeviceBattery {
-                    device_id,
-                    kind,
-                    below,
-                },
-                EventBusMessage::DeviceBattery {
-                    device_id: id,
-                    kind: k,
-                    battery_voltage,
-                    battery_percent,
-                    ..
-                },
-            ) => {
-                let level = battery_percent.or(*battery_voltage);
-                device_id.as_ref().is_none_or(|d| d == id)
-                    && kind.as_ref().is_none_or(|want| want == k)
-                    && below.is_none_or(|threshold| level.is_some_and(|l| l < threshold))
-            }
-            (
-                TriggerMatcher::Jellyfin {
-                    state,
-                    user,
-                    device,
-                    item_type,
-                },
-                EventBusMessage::Jellyfin {
-                    state: s,
-                    user: u,
-                    device: d,
-                    item_type: t,
-                    ..
-                },
-            ) => {
-                state.is_none_or(|state| state == *s)
-                    && user.as_ref().is_none_or(|user| user == u)
-                    && device.as_ref().is_none_or(|device| device == d)
-                    && item_type.as_ref().is_none_or(|item_type| item_type == t)
-            }
-            (
-                TriggerMatcher::MediaPlayer { device, state, app },
-                EventBusMessage::MediaPlayer {
-                    device_id: d,
-                    state: s,
-                    app_name: a,
-                    ..
-                },
-            ) => {
-                state.is_none_or(|state| state == *s)
-                    && device.as_ref().is_none_or(|device| device == d)
-                    && app
-                        .as_ref()
-                        .is_none_or(|app| a.as_ref().is_some_and(|actual| actual == app))
-            }
             (TriggerMatcher::Solar { metric, cmp }, EventBusMessage::Solar { current_wh, .. }) => {
                 solar_fires(
                     &workflow.name,
@@ -290,7 +438,7 @@ impl WorkflowDispatcher {
                     pending,
                 )
             }
-            _ => false,
+            (on, msg) => event_matches(&devices, on, msg),
         }
     }
 
@@ -329,12 +477,13 @@ impl WorkflowDispatcher {
 
     async fn handle(
         &self,
+        myself: &ActorRef<WorkflowDispatcherMessage>,
         msg: EventBusMessage,
         state: &mut WorkflowDispatcherState,
     ) -> Result<(), ActorProcessingErr> {
         let event_id = msg.event_id();
         crate::metrics::record_event(msg.kind());
-        let settings = self.shared_actor_state.settings.clone();
+        let settings = self.shared_actor_state.settings();
         let mut vars = msg.vars();
 
         let averages = self.solar_averages(&msg, &settings).await;
@@ -345,15 +494,44 @@ impl WorkflowDispatcher {
             vars.insert("avg_3h".to_owned(), watts(averages.last_3_hours));
         }
 
+        let room_change = state.track_room(&self.shared_actor_state.devices(), &msg);
+        if let Some(change) = &room_change {
+            vars.insert("room".to_owned(), change.room.clone());
+            vars.insert("occupied".to_owned(), change.occupied.to_string());
+        }
+
         let subject: EventSubject = (msg.kind().to_string(), msg.entity());
-        for name in state.cancel_pending_for(&subject) {
-            tracing::info!("[{event_id}] cancelled pending delayed trigger '{name}'");
+        if state.delayed_subjects.remove(&subject) {
+            match delayed::cancel_for(&self.shared_actor_state.db, &subject).await {
+                Ok(cancelled) => {
+                    for name in cancelled {
+                        tracing::info!("[{event_id}] cancelled pending delayed trigger '{name}'");
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("[{event_id}] failed to cancel pending delayed triggers: {e}");
+                    // still armed, so the subject's next event tries again
+                    state.delayed_subjects.insert(subject.clone());
+                }
+            }
         }
 
         for workflow in settings.workflows.values() {
+            if workflow.on().and_then(TriggerMatcher::held_for).is_some() {
+                self.track_held(myself, workflow, &msg, &subject, &vars, state);
+                continue;
+            }
+
             let mut pending = None;
 
-            if !self.matches(workflow, &msg, averages.as_ref(), state, &mut pending) {
+            if !self.matches(
+                workflow,
+                &msg,
+                room_change.as_ref(),
+                averages.as_ref(),
+                state,
+                &mut pending,
+            ) {
                 continue;
             }
             if !self
@@ -379,6 +557,197 @@ impl WorkflowDispatcher {
         Ok(())
     }
 
+    /// Arm, keep or cancel `workflow`'s `for:` timer for the subject of `msg`.
+    /// A timer already armed, or already fired, is left be while the state
+    /// keeps holding.
+    fn track_held(
+        &self,
+        myself: &ActorRef<WorkflowDispatcherMessage>,
+        workflow: &Workflow,
+        msg: &EventBusMessage,
+        subject: &EventSubject,
+        vars: &HashMap<String, String>,
+        state: &mut WorkflowDispatcherState,
+    ) {
+        let Some(on) = workflow.on() else {
+            return;
+        };
+        let Some(held_for) = on.held_for() else {
+            return;
+        };
+        let Some(holds) = held::holds(&self.shared_actor_state.devices(), on, msg) else {
+            return;
+        };
+        let event_id = msg.event_id();
+        let key = (workflow.slug.clone(), subject.1.clone());
+
+        if !holds {
+            if let Some(Held::Armed { timer, .. }) = state.held.remove(&key) {
+                timer.abort();
+                tracing::info!(
+                    "[{event_id}] trigger '{}' no longer holds, timer cancelled",
+                    workflow.name
+                );
+            }
+            return;
+        }
+
+        if state.held.contains_key(&key) {
+            return;
+        }
+        let Ok(wait) = held_for.to_std() else {
+            return;
+        };
+
+        tracing::info!(
+            "[{event_id}] trigger '{}' holds, firing in {}s unless it flips back",
+            workflow.name,
+            wait.as_secs()
+        );
+
+        let elapsed = Elapsed {
+            event_id,
+            slug: workflow.slug.clone(),
+            subject: subject.clone(),
+            vars: vars.clone(),
+        };
+        let timer = myself.send_after(wait, move || {
+            WorkflowDispatcherMessage::Held(elapsed.clone())
+        });
+        state.held.insert(
+            key,
+            Held::Armed {
+                event_id,
+                timer: timer.abort_handle(),
+            },
+        );
+    }
+
+    /// A `for:` timer ran out: the trigger fires through the usual `when`,
+    /// cooldown and `delay` handling. It won't arm again until its state flips
+    /// back, even if `when` held it back this time.
+    async fn held_elapsed(
+        &self,
+        elapsed: Elapsed,
+        state: &mut WorkflowDispatcherState,
+    ) -> Result<(), ActorProcessingErr> {
+        let Elapsed {
+            event_id,
+            slug,
+            subject,
+            vars,
+        } = elapsed;
+        let key = (slug, subject.1.clone());
+
+        // cancelled after it had already sent, or re-armed since
+        if !matches!(
+            state.held.get(&key),
+            Some(Held::Armed { event_id: armed, .. }) if *armed == event_id
+        ) {
+            return Ok(());
+        }
+
+        // a reload may have pointed the trigger elsewhere before it was re-armed
+        let settings = self.shared_actor_state.settings();
+        let devices = self.shared_actor_state.devices();
+        let Some(workflow) = settings.workflows.values().find(|w| {
+            w.slug == key.0
+                && w.on().is_some_and(|on| {
+                    on.held_for().is_some() && held::watches(&devices, on, &subject)
+                })
+        }) else {
+            state.held.remove(&key);
+            return Ok(());
+        };
+        state.held.insert(key, Held::Fired);
+        if !self
+            .shared_actor_state
+            .workflows
+            .enabled(&workflow.slug, workflow.enabled)
+            .await
+        {
+            return Ok(());
+        }
+
+        let trigger_span = tracing::info_span!(
+            "trigger.evaluate",
+            otel.name = format!("trigger: {}", workflow.name),
+            trigger = workflow.name,
+            event_kind = subject.0,
+        );
+        self.evaluate_trigger(event_id, workflow, &subject, &vars, state, None)
+            .instrument(trigger_span)
+            .await
+    }
+
+    /// Arm the `for:` timers of triggers whose state already holds, read back
+    /// from the devices since the timers themselves don't survive a restart.
+    async fn rearm_held(
+        &self,
+        myself: &ActorRef<WorkflowDispatcherMessage>,
+        state: &mut WorkflowDispatcherState,
+    ) {
+        let settings = self.shared_actor_state.settings();
+
+        for workflow in settings.workflows.values() {
+            let Some(on) = workflow.on().filter(|on| on.held_for().is_some()) else {
+                continue;
+            };
+            state
+                .held_triggers
+                .insert(workflow.slug.clone(), held::fingerprint(on));
+
+            match held::current_state(&self.shared_actor_state, on).await {
+                Ok(Some(msg)) => {
+                    let subject: EventSubject = (msg.kind().to_string(), msg.entity());
+                    let vars = msg.vars();
+                    self.track_held(myself, workflow, &msg, &subject, &vars, state);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    tracing::warn!(
+                        "failed to read the current state of trigger '{}': {e}",
+                        workflow.name
+                    );
+                }
+            }
+        }
+    }
+
+    /// The config was reloaded: drop the `for:` timers of workflows whose
+    /// trigger changed or went away, then arm the current triggers from their
+    /// state. A timer under an unchanged trigger keeps counting.
+    async fn reload_held(
+        &self,
+        myself: &ActorRef<WorkflowDispatcherMessage>,
+        state: &mut WorkflowDispatcherState,
+    ) {
+        let settings = self.shared_actor_state.settings();
+        let current: HashMap<&str, String> = settings
+            .workflows
+            .values()
+            .filter_map(|w| {
+                let on = w.on().filter(|on| on.held_for().is_some())?;
+                Some((w.slug.as_str(), held::fingerprint(on)))
+            })
+            .collect();
+
+        state
+            .held_triggers
+            .retain(|slug, armed| current.get(slug.as_str()) == Some(&*armed));
+        state.held.retain(|(slug, _), held| {
+            if state.held_triggers.contains_key(slug) {
+                return true;
+            }
+            if let Held::Armed { timer, .. } = held {
+                timer.abort();
+            }
+            false
+        });
+
+        self.rearm_held(myself, state).await;
+    }
+
     /// Evaluate a single matched trigger: gate on `when`, honour the cooldown,
     /// and dispatch its workflow. Recorded as one `trigger.evaluate` span by the
     /// caller via [`Instrument`].
@@ -447,49 +816,52 @@ impl WorkflowDispatcher {
         crate::metrics::record_trigger(&workflow.name, crate::metrics::TriggerOutcome::Fired);
 
         match workflow.delay() {
-            Some(delay) => self.schedule_delayed(event_id, workflow, delay, subject, vars, state),
+            Some(delay) => {
+                self.schedule_delayed(event_id, workflow, delay, subject, vars)
+                    .await?;
+                state.delayed_subjects.insert(subject.clone());
+            }
             None => self.dispatch_workflow(event_id, workflow.clone(), vars.clone())?,
         }
 
         Ok(())
     }
 
-    fn schedule_delayed(
+    /// Park the workflow in the `workflow_delays` queue, keyed by the subject
+    /// that armed it so the next event for that subject can cancel it.
+    async fn schedule_delayed(
         &self,
         event_id: uuid::Uuid,
         workflow: &Workflow,
         delay: chrono::TimeDelta,
         subject: &EventSubject,
         vars: &HashMap<String, String>,
-        state: &mut WorkflowDispatcherState,
-    ) {
-        let Ok(delay) = delay.to_std() else {
-            let _ = self.dispatch_workflow(event_id, workflow.clone(), vars.clone());
-            return;
+    ) -> Result<(), ActorProcessingErr> {
+        let Ok(wait) = delay.to_std() else {
+            return self.dispatch_workflow(event_id, workflow.clone(), vars.clone());
         };
 
-        let name = workflow.name.clone();
-        let workflow = workflow.clone();
         tracing::info!(
-            "[{event_id}] trigger '{name}' deferred by {}s",
-            delay.as_secs()
+            "[{event_id}] trigger '{}' deferred by {}s",
+            workflow.name,
+            wait.as_secs()
         );
 
-        let task_name = name.clone();
-        let vars = vars.clone();
-        let handle = tokio::spawn(async move {
-            tokio::time::sleep(delay).await;
-            tracing::info!("[{event_id}] delayed trigger '{task_name}' firing");
-            if let Err(e) = Self::send_to_factory(event_id, workflow, vars) {
-                tracing::error!(
-                    "[{event_id}] failed to dispatch delayed trigger '{task_name}': {e}"
-                );
-            }
-        });
+        let (subject_kind, subject_entity) = subject.clone();
+        let run = DelayedRun {
+            event_id,
+            slug: workflow.slug.clone(),
+            name: workflow.name.clone(),
+            due_at: chrono::Utc::now() + delay,
+            vars: vars.clone(),
+            resume: Resume::Trigger {
+                subject_kind,
+                subject_entity,
+            },
+        };
+        delayed::park(&self.shared_actor_state.db, &run, wait).await?;
 
-        if let Some(prev) = state.pending_delays.insert((subject.clone(), name), handle) {
-            prev.abort();
-        }
+        Ok(())
     }
 
     /// Returns `true` if the trigger is allowed to fire now (no record, or the
@@ -533,14 +905,6 @@ impl WorkflowDispatcher {
         event_id: uuid::Uuid,
         workflow: Workflow,
         vars: HashMap<String, String>,
-    ) -> Result<(), ActorProcessingErr> {
-        Self::send_to_factory(event_id, workflow, vars)
-    }
-
-    fn send_to_factory(
-        event_id: uuid::Uuid,
-        workflow: Workflow,
-        vars: HashMap<String, String>,
     ) -> Result<(), ActorProcessingErr> {
         let Some(actor) = ractor::registry::where_is(WorkflowWorker::NAME) else {
             tracing::warn!("[{event_id}] workflow factory not found, dropping trigger");
@@ -553,6 +917,7 @@ impl WorkflowDispatcher {
                 event_id,
                 workflow,
                 vars,
+                reply: None,
             },
             options: JobOptions::default(),
             accepted: None,
@@ -563,7 +928,7 @@ impl WorkflowDispatcher {
 }
 
 impl Actor for WorkflowDispatcher {
-    type Msg = EventBusMessage;
+    type Msg = WorkflowDispatcherMessage;
     type State = WorkflowDispatcherState;
     type Arguments = ();
 
@@ -579,7 +944,10 @@ impl Actor for WorkflowDispatcher {
             loop {
                 match rx.recv().await {
                     Ok(msg) => {
-                        if myself.send_message(msg).is_err() {
+                        if myself
+                            .send_message(WorkflowDispatcherMessage::Event(msg))
+                            .is_err()
+                        {
                             // dispatcher stopped; nothing left to feed
                             break;
                         }
@@ -595,26 +963,59 @@ impl Actor for WorkflowDispatcher {
         Ok(WorkflowDispatcherState::default())
     }
 
+    async fn post_start(
+        &self,
+        myself: ActorRef<Self::Msg>,
+        state: &mut Self::State,
+    ) -> Result<(), ActorProcessingErr> {
+        match delayed::armed_subjects(&self.shared_actor_state.db).await {
+            Ok(subjects) => state.delayed_subjects.extend(subjects),
+            Err(e) => tracing::error!("failed to load subjects with delayed triggers: {e}"),
+        }
+        self.rearm_held(&myself, state).await;
+        Ok(())
+    }
+
     async fn handle(
         &self,
-        _myself: ActorRef<Self::Msg>,
+        myself: ActorRef<Self::Msg>,
         message: Self::Msg,
         state: &mut Self::State,
     ) -> Result<(), ActorProcessingErr> {
-        // Root span per event: `parent: None` detaches from any span ractor
-        // re-enters from the `send_message` call site, so each dispatched event
-        // is its own trace rather than all sharing the bridge task's context.
-        let span = tracing::info_span!(
-            parent: None,
-            "dispatch_event",
-            event_kind = message.kind(),
-            event_id = %message.event_id(),
-        );
+        let result = match message {
+            WorkflowDispatcherMessage::Event(message) => {
+                // Root span per event: `parent: None` detaches from any span ractor
+                // re-enters from the `send_message` call site, so each dispatched event
+                // is its own trace rather than all sharing the bridge task's context.
+                let span = tracing::info_span!(
+                    parent: None,
+                    "dispatch_event",
+                    event_kind = message.kind(),
+                    event_id = %message.event_id(),
+                );
 
-        if let Err(e) = WorkflowDispatcher::handle(self, message, state)
-            .instrument(span)
-            .await
-        {
+                WorkflowDispatcher::handle(self, &myself, message, state)
+                    .instrument(span)
+                    .await
+            }
+            WorkflowDispatcherMessage::Held(elapsed) => {
+                let span = tracing::info_span!(
+                    parent: None,
+                    "dispatch_held",
+                    event_kind = elapsed.subject.0,
+                    event_id = %elapsed.event_id,
+                );
+
+                self.held_elapsed(elapsed, state).instrument(span).await
+            }
+            WorkflowDispatcherMessage::Rearm => {
+                tracing::info!("re-arming held triggers after config reload");
+                self.reload_held(&myself, state).await;
+                Ok(())
+            }
+        };
+
+        if let Err(e) = result {
             tracing::error!("error while dispatching event: {e}");
         }
 
@@ -626,7 +1027,6 @@ impl Actor for WorkflowDispatcher {
 mod tests {
     use super::*;
     use crate::settings::workflow::{CompareOp, Comparison};
-    use std::time::Duration;
 
     fn averages(last_15_mins: Option<f64>) -> SolarCurrentStatisticsAverages {
         SolarCurrentStatisticsAverages {
@@ -669,53 +1069,6 @@ mod tests {
         assert!(fires(3500.0, &mut last), "expected a re-arm and re-fire");
     }
 
-    fn armed(state: &mut WorkflowDispatcherState, subject: &EventSubject, name: &str) {
-        let handle = tokio::spawn(async { tokio::time::sleep(Duration::from_secs(3600)).await });
-        state
-            .pending_delays
-            .insert((subject.clone(), name.to_owned()), handle);
-    }
-
-    #[tokio::test]
-    async fn a_matching_subject_cancels_every_delay_armed_for_it() {
-        let mut state = WorkflowDispatcherState::default();
-        let presence: EventSubject = ("presence".to_owned(), "livingroom-motion".to_owned());
-        let door: EventSubject = ("door".to_owned(), "front-door".to_owned());
-
-        armed(&mut state, &presence, "lamp off");
-        armed(&mut state, &presence, "heater off");
-        armed(&mut state, &door, "porch light off");
-
-        let mut cancelled = state.cancel_pending_for(&presence);
-        cancelled.sort();
-
-        assert_eq!(cancelled, vec!["heater off", "lamp off"]);
-        assert_eq!(
-            state.pending_delays.len(),
-            1,
-            "a delay armed for a different subject survives"
-        );
-        assert!(
-            state
-                .pending_delays
-                .contains_key(&(door, "porch light off".to_owned())),
-            "the surviving delay is the door one"
-        );
-    }
-
-    #[tokio::test]
-    async fn cancelling_an_unarmed_subject_is_a_no_op() {
-        let mut state = WorkflowDispatcherState::default();
-        let presence: EventSubject = ("presence".to_owned(), "livingroom-motion".to_owned());
-
-        armed(&mut state, &presence, "lamp off");
-
-        let other: EventSubject = ("presence".to_owned(), "closet-presence".to_owned());
-
-        assert!(state.cancel_pending_for(&other).is_empty());
-        assert_eq!(state.pending_delays.len(), 1);
-    }
-
     #[test]
     fn a_crossing_rejected_by_a_guard_still_fires_when_the_guard_opens() {
         let cmp = Comparison {
@@ -789,6 +1142,24 @@ mod tests {
         assert!(last.is_empty(), "edge state should be untouched");
     }
 
+    #[test]
+    fn a_room_is_occupied_from_the_first_sensor_in_to_the_last_out() {
+        let mut present = HashSet::new();
+
+        assert_eq!(occupancy_flip(&mut present, "desk", true), Some(true));
+        assert_eq!(occupancy_flip(&mut present, "sofa", true), None);
+        assert_eq!(occupancy_flip(&mut present, "desk", false), None);
+        assert_eq!(occupancy_flip(&mut present, "desk", false), None);
+        assert_eq!(occupancy_flip(&mut present, "sofa", false), Some(false));
+    }
+
+    #[test]
+    fn a_clear_from_an_unseen_sensor_does_not_empty_an_empty_room() {
+        let mut present = HashSet::new();
+
+        assert_eq!(occupancy_flip(&mut present, "desk", false), None);
+    }
+
     #[test]
     fn the_current_metric_reads_the_event_not_the_averages() {
         let cmp = Comparison {
@@ -0,0 +1,416 @@
+//! Triggers held `for:` a duration. The dispatcher arms a timer per workflow
+//! and subject when an event puts the trigger's state into effect, cancels it
+//! when an event for the same subject takes it out, and fires the workflow if
+//! the timer runs out first. Timers live in memory; on startup each trigger's
+//! current state is read back and fed through the same path, so a state that
+//! held across a restart is timed again from startup, firing late rather than
+//! early. A config reload drops the timers of triggers that changed and arms
+//! the current ones the same way.
+
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use super::{WorkflowError, conditions, delayed::EventSubject};
+use crate::{
+    actors::devices::media_player::state::settled,
+    device_registry::DeviceRegistry,
+    event_bus::{EventBusMessage, SensorMetric, SensorReading, metric_var_name},
+    settings::{TriggerMatcher, workflow::EnvMetric},
+    state::SharedActorState,
+};
+
+/// Where a `for:` trigger stands for one subject.
+pub(super) enum Held {
+    /// Counting down, armed by `event_id`.
+    Armed {
+        event_id: Uuid,
+        timer: tokio::task::AbortHandle,
+    },
+    /// Its timer ran out; it won't arm again until the state flips back.
+    Fired,
+}
+
+/// A `for:` timer that ran out, sent back to the dispatcher.
+#[derive(Debug, Clone)]
+pub struct Elapsed {
+    /// The event that armed the timer, which the run carries.
+    pub event_id: Uuid,
+    pub slug: String,
+    /// The arming event's subject.
+    pub subject: EventSubject,
+    pub vars: HashMap<String, String>,
+}
+
+/// Whether `on`'s state holds after `msg`, or `None` when `msg` isn't about
+/// the subject `on` watches. Unlike a plain match, an event for the subject
+/// that doesn't match is a `Some(false)`, which cancels the timer.
+pub(super) fn holds(
+    devices: &DeviceRegistry,
+    on: &TriggerMatcher,
+    msg: &EventBusMessage,
+) -> Option<bool> {
+    match (on, msg) {
+        (
+            TriggerMatcher::Presence {
+                sensor, present, ..
+            },
+            EventBusMessage::Presence {
+                sensor: s,
+                present: p,
+                ..
+            },
+        ) if devices.address_or_self(sensor) == s.as_str() => Some(present == p),
+        (
+            TriggerMatcher::Door {
+                ieee_addr, open, ..
+            },
+            EventBusMessage::Door {
+                ieee_addr: a,
+                open: o,
+                ..
+            },
+        ) if devices.address_or_self(ieee_addr) == a.as_str() => Some(open == o),
+        (
+            TriggerMatcher::Light { ieee_addr, on, .. },
+            EventBusMessage::Light {
+                ieee_addr: a,
+                on: o,
+                ..
+            },
+        ) if devices.address_or_self(ieee_addr) == a.as_str() => Some(on == o),
+        (
+            TriggerMatcher::Environment {
+                sensor,
+                metric,
+                cmp,
+                ..
+            },
+            EventBusMessage::Environment {
+                sensor: s,
+                readings,
+                ..
+            },
+        ) if devices.address_or_self(sensor) == s.as_str() => readings
+            .iter()
+            .find(|r| r.metric() == *metric)
+            .map(|reading| cmp.matches(reading.value())),
+        (
+            TriggerMatcher::HomeAssistant {
+                entity_id, state, ..
+            },
+            EventBusMessage::HomeAssistant {
+                entity_id: e,
+                state: s,
+                ..
+            },
+        ) if entity_id == e => Some(state.as_ref().is_none_or(|state| state == s)),
+        (
+            TriggerMatcher::MediaPlayer {
+                device, state, app, ..
+            },
+            EventBusMessage::MediaPlayer {
+                device_id,
+                state: s,
+                app_name,
+                ..
+            },
+        ) if device.as_ref().is_none_or(|device| device == device_id) => Some(
+            state.is_none_or(|state| state == *s)
+                && app
+                    .as_ref()
+                    .is_none_or(|app| app_name.as_ref().is_some_and(|actual| actual == app)),
+        ),
+        _ => None,
+    }
+}
+
+/// Whether `subject` is one `on` watches, so a timer armed for it under a
+/// trigger since reloaded to watch something else can be told apart.
+pub(super) fn watches(
+    devices: &DeviceRegistry,
+    on: &TriggerMatcher,
+    (kind, entity): &EventSubject,
+) -> bool {
+    let address = |name: &str| devices.address_or_self(name) == entity.as_str();
+
+    match on {
+        TriggerMatcher::Presence { sensor, .. } => kind == "presence" && address(sensor),
+        TriggerMatcher::Door { ieee_addr, .. } => kind == "door" && address(ieee_addr),
+        TriggerMatcher::Light { ieee_addr, .. } => kind == "light" && address(ieee_addr),
+        TriggerMatcher::Environment { sensor, .. } => kind == "environment" && address(sensor),
+        TriggerMatcher::HomeAssistant { entity_id, .. } => {
+            kind == "home_assistant" && entity_id == entity
+        }
+        TriggerMatcher::MediaPlayer { device, .. } => {
+            kind == "media_player" && device.as_ref().is_none_or(|device| device == entity)
+        }
+        _ => false,
+    }
+}
+
+/// `on` as configured, to compare across a reload.
+pub(super) fn fingerprint(on: &TriggerMatcher) -> String {
+    format!("{on:?}")
+}
+
+/// The subject `on` watches as it stands now, as the event that would have
+/// put it there, or `None` when its state isn't known yet.
+pub(super) async fn current_state(
+    state: &SharedActorState,
+    on: &TriggerMatcher,
+) -> Result<Option<EventBusMessage>, WorkflowError> {
+    let devices = state.devices();
+    let event_id = Uuid::new_v4();
+
+    let event = match on {
+        TriggerMatcher::Presence { sensor, .. } => {
+            let sensor = devices.address_or_self(sensor);
+            conditions::latest_presence(sensor)
+                .await?
+                .map(|present| EventBusMessage::Presence {
+                    event_id,
+                    sensor: sensor.to_owned(),
+                    present,
+                })
+        }
+        TriggerMatcher::Door { ieee_addr, .. } => {
+            let ieee_addr = devices.address_or_self(ieee_addr);
+            conditions::door_open(ieee_addr)
+                .await?
+                .map(|open| EventBusMessage::Door {
+                    event_id,
+                    ieee_addr: ieee_addr.to_owned(),
+                    open,
+                })
+        }
+        TriggerMatcher::Light { ieee_addr, .. } => {
+            let ieee_addr = devices.address_or_self(ieee_addr);
+            conditions::light_on(ieee_addr)
+                .await?
+                .map(|on| EventBusMessage::Light {
+                    event_id,
+                    ieee_addr: ieee_addr.to_owned(),
+                    on,
+                })
+        }
+        TriggerMatcher::Environment { sensor, metric, .. } => {
+            let sensor = devices.address_or_self(sensor);
+            let value = match env_metric(metric) {
+                Some(env) if devices.environment(sensor).is_some() => {
+                    conditions::environment_value(sensor, env).await?
+                }
+                _ => sqlx::query_scalar!(
+                    "SELECT value FROM latest_device_metric WHERE address = $1 AND metric = $2",
+                    sensor,
+                    metric_var_name(metric)
+                )
+                .fetch_optional(&state.db)
+                .await
+                .map_err(|e| WorkflowError::Other(e.into()))?
+                .flatten(),
+            };
+
+            value.map(|value| EventBusMessage::Environment {
+                event_id,
+                sensor: sensor.to_owned(),
+                readings: vec![SensorReading::new(metric.clone(), value)],
+            })
+        }
+        TriggerMatcher::HomeAssistant { entity_id, .. } => sqlx::query_scalar!(
+            "SELECT state FROM latest_home_assistant_state WHERE entity_id = $1",
+            entity_id
+        )
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|e| WorkflowError::Other(e.into()))?
+        .map(|entity_state| EventBusMessage::HomeAssistant {
+            event_id,
+            entity_id: entity_id.clone(),
+            state: entity_state,
+        }),
+        TriggerMatcher::MediaPlayer {
+            device: Some(device),
+            ..
+        } => media_player_state(state, device, event_id).await?,
+        _ => None,
+    };
+
+    Ok(event)
+}
+
+fn env_metric(metric: &SensorMetric) -> Option<EnvMetric> {
+    match metric {
+        SensorMetric::Temperature => Some(EnvMetric::Temperature),
+        SensorMetric::Humidity => Some(EnvMetric::Humidity),
+        SensorMetric::Pressure => Some(EnvMetric::Pressure),
+        SensorMetric::Lux => Some(EnvMetric::Lux),
+        SensorMetric::UvIndex => Some(EnvMetric::UvIndex),
+        SensorMetric::SoilMoisture | SensorMetric::Other(_) => None,
+    }
+}
+
+/// A media player's stored state, as the edge that [`settled`] it there.
+async fn media_player_state(
+    state: &SharedActorState,
+    device_id: &str,
+    event_id: Uuid,
+) -> Result<Option<EventBusMessage>, WorkflowError> {
+    let devices = state.devices();
+    let Some((address, settings)) = devices
+        .media_players()
+        .find(|(_, settings)| settings.id == device_id)
+    else {
+        return Ok(None);
+    };
+
+    let row = sqlx::query!(
+        r#"SELECT state, app_name, source, media_title, media_series_title, media_content_type,
+                  media_season, media_episode, position_seconds, duration_seconds,
+                  volume_level, muted, artwork_url
+           FROM media_player_state
+           WHERE device_id = $1"#,
+        device_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| WorkflowError::Other(e.into()))?;
+
+    Ok(row.map(|row| EventBusMessage::MediaPlayer {
+        event_id,
+        device_id: device_id.to_owned(),
+        name: settings.name.clone(),
+        room: devices.room(address).map(str::to_owned),
+        state: settled(&row.state),
+        entity_state: row.state,
+        app_name: row.app_name,
+        source: row.source,
+        media_title: row.media_title,
+        media_series_title: row.media_series_title,
+        media_content_type: row.media_content_type,
+        season: row.media_season,
+        episode: row.media_episode,
+        position_seconds: row.position_seconds,
+        duration_seconds: row.duration_seconds,
+        volume_level: row.volume_level,
+        muted: row.muted,
+        artwork_url: row.artwork_url,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_bus::PlaybackState;
+
+    fn trigger(yaml: &str) -> TriggerMatcher {
+        serde_yaml::from_str(yaml).expect("trigger yaml")
+    }
+
+    fn door(open: bool) -> EventBusMessage {
+        EventBusMessage::Door {
+            event_id: Uuid::new_v4(),
+            ieee_addr: "0x2".to_owned(),
+            open,
+        }
+    }
+
+    fn humidity(sensor: &str, value: f64) -> EventBusMessage {
+        EventBusMessage::Environment {
+            event_id: Uuid::new_v4(),
+            sensor: sensor.to_owned(),
+            readings: vec![SensorReading::Humidity { value }],
+        }
+    }
+
+    #[test]
+    fn an_event_for_the_subject_either_holds_or_flips_back() {
+        let devices = DeviceRegistry::default();
+        let open = trigger("type: door\ndevice: \"0x2\"\nopen: true\nfor: 10m");
+
+        assert_eq!(holds(&devices, &open, &door(true)), Some(true));
+        assert_eq!(holds(&devices, &open, &door(false)), Some(false));
+
+        let other = EventBusMessage::Door {
+            event_id: Uuid::new_v4(),
+            ieee_addr: "0x3".to_owned(),
+            open: true,
+        };
+        assert_eq!(holds(&devices, &open, &other), None);
+    }
+
+    #[test]
+    fn a_timer_is_only_for_the_subject_its_trigger_watches() {
+        let devices = DeviceRegistry::default();
+        let open = trigger("type: door\ndevice: \"0x2\"\nopen: true\nfor: 10m");
+        let subject = |kind: &str, entity: &str| (kind.to_owned(), entity.to_owned());
+
+        assert!(watches(&devices, &open, &subject("door", "0x2")));
+        assert!(!watches(&devices, &open, &subject("door", "0x3")));
+        assert!(!watches(&devices, &open, &subject("light", "0x2")));
+
+        let longer = trigger("type: door\ndevice: \"0x2\"\nopen: true\nfor: 20m");
+        assert_ne!(fingerprint(&open), fingerprint(&longer));
+    }
+
+    #[test]
+    fn an_environment_trigger_holds_while_its_comparison_does() {
+        let devices = DeviceRegistry::default();
+        let humid = trigger(
+            "type: environment\nsensor: bathroom\nmetric: humidity\nop: gt\nvalue: 70\nfor: 15m",
+        );
+
+        assert_eq!(
+            holds(&devices, &humid, &humidity("bathroom", 75.0)),
+            Some(true)
+        );
+        assert_eq!(
+            holds(&devices, &humid, &humidity("bathroom", 60.0)),
+            Some(false)
+        );
+        assert_eq!(holds(&devices, &humid, &humidity("kitchen", 75.0)), None);
+
+        let no_humidity = EventBusMessage::Environment {
+            event_id: Uuid::new_v4(),
+            sensor: "bathroom".to_owned(),
+            readings: vec![SensorReading::Temperature { value: 21.0 }],
+        };
+        assert_eq!(holds(&devices, &humid, &no_humidity), None);
+    }
+
+    #[test]
+    fn a_media_player_holds_until_its_next_edge() {
+        let devices = DeviceRegistry::default();
+        let paused = trigger("type: media_player\ndevice: lounge_tv\nstate: paused\nfor: 10m");
+
+        let edge = |state: PlaybackState| EventBusMessage::MediaPlayer {
+            event_id: Uuid::new_v4(),
+            device_id: "lounge_tv".to_owned(),
+            name: "Lounge TV".to_owned(),
+            room: None,
+            state,
+            entity_state: "paused".to_owned(),
+            app_name: None,
+            source: None,
+            media_title: None,
+            media_series_title: None,
+            media_content_type: None,
+            season: None,
+            episode: None,
+            position_seconds: None,
+            duration_seconds: None,
+            volume_level: None,
+            muted: None,
+            artwork_url: None,
+        };
+
+        assert_eq!(
+            holds(&devices, &paused, &edge(PlaybackState::Paused)),
+            Some(true)
+        );
+        assert_eq!(
+            holds(&devices, &paused, &edge(PlaybackState::Resumed)),
+            Some(false)
+        );
+    }
+}
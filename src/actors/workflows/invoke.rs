@@ -0,0 +1,152 @@
+//! Running a configured workflow on request, by slug, rather than from a
+//! trigger. The REST `/workflow/{slug}/run` endpoint and the `executeWorkflow`
+//! mutation both come through here.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use async_graphql::SimpleObject;
+use ractor::factory::{FactoryMessage, Job, JobOptions};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    actors::{
+        system::rpc::{self, RpcError},
+        workflows::{WorkflowWorker, WorkflowWorkerMessage, manager::WorkflowRun, trace::RunTrace},
+    },
+    auth::{AuthContext, scope::Scope},
+    settings::Settings,
+};
+
+/// How long a caller is held waiting on a run. One still going by then is
+/// returned as queued, to be polled like any other.
+const WAIT_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(thiserror::Error, Debug)]
+pub enum InvokeError {
+    #[error("unknown workflow slug: {0}")]
+    UnknownWorkflow(String),
+    #[error("insufficient scope")]
+    Forbidden,
+    #[error("{0}")]
+    InvalidInputs(String),
+    #[error(transparent)]
+    Rpc(#[from] RpcError),
+}
+
+pub struct Invocation {
+    pub slug: String,
+    /// Template vars, checked with [`Workflow::inputs`](crate::settings::Workflow::inputs).
+    pub inputs: HashMap<String, String>,
+    /// Run in dry-run even when the workflow isn't configured to.
+    pub dry_run: bool,
+    /// Hold the caller until the run finishes or parks behind a `delay`,
+    /// rather than returning as soon as it's queued.
+    pub wait: bool,
+}
+
+/// A requested run, either queued or as recorded once it ended.
+#[derive(Debug, Serialize, SimpleObject)]
+pub struct WorkflowExecution {
+    /// The run's event id, which its `workflowRuns` entry carries as `eventId`.
+    pub run_id: Uuid,
+    /// Null while the run is queued.
+    pub outcome: Option<String>,
+    pub dry_run: bool,
+    pub duration_ms: Option<i64>,
+    pub error: Option<String>,
+    pub trace: Option<RunTrace>,
+}
+
+impl WorkflowExecution {
+    fn queued(run_id: Uuid, dry_run: bool) -> Self {
+        Self {
+            run_id,
+            outcome: None,
+            dry_run,
+            duration_ms: None,
+            error: None,
+            trace: None,
+        }
+    }
+}
+
+impl From<WorkflowRun> for WorkflowExecution {
+    fn from(run: WorkflowRun) -> Self {
+        Self {
+            run_id: run.event_id,
+            outcome: Some(run.outcome),
+            dry_run: run.dry_run,
+            duration_ms: Some(i64::try_from(run.duration.as_millis()).unwrap_or(i64::MAX)),
+            error: run.error,
+            trace: Some(run.trace),
+        }
+    }
+}
+
+/// Run the workflow named by `invocation` if `auth` holds `scope` for its
+/// tags and the inputs fit it.
+pub async fn invoke(
+    settings: &Settings,
+    auth: &AuthContext,
+    scope: &Scope,
+    invocation: Invocation,
+) -> Result<WorkflowExecution, InvokeError> {
+    let Some(workflow) = settings
+        .workflows
+        .values()
+        .find(|w| w.slug == invocation.slug)
+    else {
+        return Err(InvokeError::UnknownWorkflow(invocation.slug));
+    };
+
+    if !auth.has_for_tags(scope, &workflow.tags) {
+        return Err(InvokeError::Forbidden);
+    }
+
+    let vars = workflow
+        .inputs(invocation.inputs)
+        .map_err(InvokeError::InvalidInputs)?;
+    let mut workflow = workflow.clone();
+    workflow.dry_run |= invocation.dry_run;
+    let dry_run = workflow.dry_run;
+    let event_id = Uuid::new_v4();
+
+    if invocation.wait {
+        let run = rpc::query_factory(WorkflowWorker::NAME, WAIT_TIMEOUT, |reply| {
+            WorkflowWorkerMessage::Execute {
+                event_id,
+                workflow,
+                vars,
+                reply: Some(reply),
+            }
+        });
+
+        return match tokio::time::timeout(WAIT_TIMEOUT, run).await {
+            Ok(run) => Ok(run?.into()),
+            Err(_) => {
+                tracing::info!("[{event_id}] stopped waiting on '{}'", invocation.slug);
+                Ok(WorkflowExecution::queued(event_id, dry_run))
+            }
+        };
+    }
+
+    let actor = ractor::registry::where_is(WorkflowWorker::NAME)
+        .ok_or(RpcError::ActorNotFound(WorkflowWorker::NAME))?;
+    actor
+        .send_message(FactoryMessage::Dispatch(Job {
+            key: (),
+            msg: WorkflowWorkerMessage::Execute {
+                event_id,
+                workflow,
+                vars,
+                reply: None,
+            },
+            options: JobOptions::default(),
+            accepted: None,
+        }))
+        .map_err(|e| RpcError::Messaging(e.to_string()))?;
+
+    Ok(WorkflowExecution::queued(event_id, dry_run))
+}
@@ -3,12 +3,17 @@ use sqlx::{Pool, Postgres};
 use std::time::Duration;
 use uuid::Uuid;
 
+use crate::actors::workflows::runs::ActiveRuns;
+use crate::actors::workflows::trace::RunTrace;
+use crate::event_bus::EventBusMessage;
 use crate::mode::Mode;
+use crate::settings::Variable;
 
 #[derive(Clone)]
 pub struct WorkflowManager {
     db: Pool<Postgres>,
     enabled_cache: Cache<String, Option<bool>>,
+    runs: ActiveRuns,
 }
 
 pub struct WorkflowRun {
@@ -19,6 +24,25 @@ pub struct WorkflowRun {
     pub dry_run: bool,
     pub duration: Duration,
     pub error: Option<String>,
+    pub trace: RunTrace,
+}
+
+/// A variable write that changed the stored value.
+pub struct VariableChange {
+    pub name: String,
+    pub value: String,
+    pub previous: Option<String>,
+}
+
+impl VariableChange {
+    pub fn into_event(self) -> EventBusMessage {
+        EventBusMessage::Variable {
+            event_id: Uuid::new_v4(),
+            name: self.name,
+            value: self.value,
+            previous: self.previous,
+        }
+    }
 }
 
 impl WorkflowManager {
@@ -27,7 +51,16 @@ impl WorkflowManager {
             .max_capacity(1024)
             .time_to_live(Duration::from_secs(300))
             .build();
-        Self { db, enabled_cache }
+        Self {
+            db,
+            enabled_cache,
+            runs: ActiveRuns::default(),
+        }
+    }
+
+    /// The runs of each workflow in flight, shared by every worker.
+    pub fn runs(&self) -> &ActiveRuns {
+        &self.runs
     }
 
     pub async fn enabled(&self, slug: &str, config_default: bool) -> bool {
@@ -111,6 +144,82 @@ impl WorkflowManager {
         Ok(())
     }
 
+    /// Current value of a declared variable, falling back to its default.
+    pub async fn variable(
+        &self,
+        name: &str,
+        variable: &Variable,
+    ) -> Result<Option<String>, sqlx::Error> {
+        let stored = sqlx::query_scalar!(
+            "SELECT value FROM state WHERE key = $1",
+            Variable::state_key(name)
+        )
+        .fetch_optional(&self.db)
+        .await?;
+
+        Ok(stored.or_else(|| variable.initial()))
+    }
+
+    /// Store `value`, already parsed by [`Variable::parse`]. Returns the change
+    /// so the caller can publish it, or `None` when the value was already set.
+    pub async fn set_variable(
+        &self,
+        name: &str,
+        variable: &Variable,
+        value: String,
+    ) -> Result<Option<VariableChange>, sqlx::Error> {
+        let previous = self.variable(name, variable).await?;
+        if previous.as_ref() == Some(&value) {
+            return Ok(None);
+        }
+
+        sqlx::query!(
+            "INSERT INTO state (key, value) VALUES ($1, $2) \
+             ON CONFLICT (key) DO UPDATE SET value = EXCLUDED.value",
+            Variable::state_key(name),
+            value
+        )
+        .execute(&self.db)
+        .await?;
+
+        Ok(Some(VariableChange {
+            name: name.to_owned(),
+            value,
+            previous,
+        }))
+    }
+
+    /// Add `by` to a number variable in one statement, so concurrent workflow
+    /// runs counting the same thing don't lose updates.
+    pub async fn increment_variable(
+        &self,
+        name: &str,
+        variable: &Variable,
+        by: f64,
+    ) -> Result<VariableChange, sqlx::Error> {
+        let start = match variable {
+            Variable::Number { default } => *default,
+            _ => 0.0,
+        };
+
+        let value: f64 = sqlx::query_scalar!(
+            r#"INSERT INTO state (key, value) VALUES ($1, ($2::float8 + $3::float8)::text)
+               ON CONFLICT (key) DO UPDATE SET value = (state.value::float8 + $3::float8)::text
+               RETURNING value::float8 AS "value!""#,
+            Variable::state_key(name),
+            start,
+            by
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(VariableChange {
+            name: name.to_owned(),
+            value: value.to_string(),
+            previous: Some((value - by).to_string()),
+        })
+    }
+
     pub async fn set_enabled(&self, slug: &str, enabled: bool) -> Result<(), sqlx::Error> {
         sqlx::query!(
             "INSERT INTO workflows (slug, enabled, updated_at) VALUES ($1, $2, now()) \
@@ -127,12 +236,19 @@ impl WorkflowManager {
         Ok(())
     }
 
-    pub async fn record_run(&self, run: WorkflowRun) {
+    pub async fn record_run(&self, run: &WorkflowRun) {
         let duration_ms = i64::try_from(run.duration.as_millis()).unwrap_or(i64::MAX);
+        let trace = match serde_json::to_value(&run.trace) {
+            Ok(trace) => Some(trace),
+            Err(err) => {
+                tracing::warn!("failed to serialize the trace of '{}': {err}", run.slug);
+                None
+            }
+        };
         if let Err(err) = sqlx::query!(
             "INSERT INTO workflow_runs \
-             (slug, name, event_id, outcome, dry_run, duration_ms, error) \
-             VALUES ($1, $2, $3, $4, $5, $6, $7)",
+             (slug, name, event_id, outcome, dry_run, duration_ms, error, trace) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
             run.slug,
             run.name,
             run.event_id,
@@ -140,6 +256,7 @@ impl WorkflowManager {
             run.dry_run,
             duration_ms,
             run.error,
+            trace,
         )
         .execute(&self.db)
         .await
@@ -1,31 +1,56 @@
 use crate::{
     actors::devices::light::{LightHandler, LightHandlerMessage},
+    actors::devices::smart_switch::{self, SmartSwitchHandler},
+    actors::workflows::delayed::{DelayedRun, Resume},
     actors::workflows::manager::WorkflowRun,
+    actors::workflows::runs::{Admission, PendingRun},
+    actors::workflows::trace::{RunTrace, Tracer},
     event_bus::EventBusMessage,
-    integrations::notify::notify,
-    settings::workflow::{EnableState, LightState, Step, Workflow},
+    integrations::notify::{Origin, notify},
+    settings::{
+        NotifyRef, TemplateString, TriggerMatcher,
+        notify::NotifyAction,
+        template::render_json,
+        workflow::{
+            ChooseOption, Condition, EnableState, LightState, MAX_REPEAT, MAX_WAIT, RunMode, Step,
+            SwitchState, Workflow,
+        },
+    },
     state::SharedActorState,
     timer::timed_async,
 };
 use ractor::{
-    ActorRef,
+    ActorRef, RpcReplyPort,
     factory::{FactoryMessage, Job, JobOptions, Worker, WorkerBuilder, WorkerId},
 };
 use std::collections::HashMap;
 use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio_util::sync::CancellationToken;
 use tracing::Instrument;
 use uuid::Uuid;
 
 pub mod conditions;
+pub mod delayed;
 pub mod dispatcher;
+pub mod held;
+pub mod invoke;
 pub mod manager;
 pub mod plan;
+pub mod runs;
+pub mod scope;
 pub mod spawn;
+pub mod trace;
 
 /// Maximum nesting depth for `run_workflow` expansion, guarding against
-/// workflows that (directly or transitively) reference themselves.
+/// workflows that (directly or transitively) reference themselves. Control
+/// flow steps don't add to it, but a `run_workflow` inside one still counts.
 const MAX_DEPTH: u8 = 8;
 
+/// How often a `wait_for` condition is re-checked between bus events, for
+/// time and sun conditions that change without one.
+const WAIT_RECHECK: Duration = Duration::from_secs(30);
+
 #[derive(thiserror::Error, Debug)]
 pub enum WorkflowError {
     #[error("actor `{0}` not found")]
@@ -34,16 +59,40 @@ pub enum WorkflowError {
     DepthExceeded,
     #[error("messaging error: {0}")]
     Messaging(String),
-    #[error("not implemented: {0}")]
-    NotImplemented(&'static str),
     #[error("home assistant is not configured")]
     HomeAssistantNotConfigured,
+    #[error("workflow changed since the `delay` at step {0:?} was parked")]
+    StaleContinuation(Vec<usize>),
+    #[error("template error: {0}")]
+    Template(String),
+    #[error("unknown variable `{0}`")]
+    UnknownVariable(String),
+    #[error("variable `{0}`: {1}")]
+    InvalidVariable(String, String),
+    #[error("{0}")]
+    NotifyTarget(String),
+    #[error("repeat stopped after {MAX_REPEAT} passes")]
+    RepeatLimit,
+    #[error("wait_for timed out after {0}s")]
+    WaitTimedOut(i64),
+    #[error("held the worker in place for over {}m", MAX_WAIT.num_minutes())]
+    HeldTooLong,
+    #[error("cancelled by a newer run")]
+    Cancelled,
     #[error(transparent)]
     HomeAssistant(#[from] crate::integrations::home_assistant::HomeAssistantError),
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
 
+/// How a run of steps ended: all the way through, or with the remainder
+/// parked behind a durable `delay`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Flow {
+    Done,
+    Parked,
+}
+
 /// Per-execution context threaded through the recursive executor.
 #[derive(Clone, Copy)]
 struct WorkflowContext<'a> {
@@ -53,9 +102,19 @@ struct WorkflowContext<'a> {
     /// Slug of the workflow that was triggered, preserved across `run_workflow`
     /// nesting so a `set_workflows_enabled` step never disables its own origin.
     origin_slug: &'a str,
-    /// Template variables carried from the triggering event, substituted into
-    /// `notify` messages.
+    /// Name of the workflow that was triggered; a parked `delay` resumes it.
+    origin_name: &'a str,
+    /// Steps of the workflow that was triggered, which a parked `delay`
+    /// records a [`Step::digest`] of.
+    origin_steps: &'a [Step],
+    /// Template variables carried from the triggering event, the base of every
+    /// step's template [`scope`].
     vars: &'a HashMap<String, String>,
+    /// Where each step reached is recorded for the run's trace.
+    trace: &'a Tracer,
+    /// Set inside `parallel` and `repeat`, where a parked continuation
+    /// couldn't resume, so a `delay` sleeps in place instead.
+    inline_delays: bool,
 }
 
 pub enum WorkflowWorkerMessage {
@@ -63,9 +122,27 @@ pub enum WorkflowWorkerMessage {
         event_id: Uuid,
         workflow: Workflow,
         vars: HashMap<String, String>,
+        /// Answered with the run as recorded, for a caller waiting on it.
+        reply: Option<RpcReplyPort<WorkflowRun>>,
+    },
+    /// Carry on with `workflow` after a parked `delay` step.
+    Resume {
+        event_id: Uuid,
+        workflow: Workflow,
+        vars: HashMap<String, String>,
+        continuation: Continuation,
     },
 }
 
+/// Where a parked run carries on: just after the `delay` at `path`, in steps
+/// that had the [`Step::digest`] `digest` when it parked.
+#[derive(Debug, Clone)]
+pub struct Continuation {
+    pub path: Vec<usize>,
+    /// Absent for runs parked before digests were recorded.
+    pub digest: Option<String>,
+}
+
 pub struct WorkflowWorker {
     shared_actor_state: SharedActorState,
 }
@@ -73,12 +150,19 @@ pub struct WorkflowWorker {
 impl WorkflowWorker {
     pub const NAME: &str = "workflow";
 
+    /// Run `workflow` from the top, or with `resume_after` set, from just
+    /// after the parked `delay` step it points at. Returns the run as it was
+    /// recorded; a failed step shows up in its `outcome` and `error`, a run
+    /// stopped by `cancel` as `cancelled`, and one whose steps were edited
+    /// while it was parked as `expired`.
     pub async fn execute_workflow(
         &self,
         event_id: Uuid,
         workflow: Workflow,
         vars: &HashMap<String, String>,
-    ) -> Result<(), WorkflowError> {
+        resume_after: Option<&Continuation>,
+        cancel: &CancellationToken,
+    ) -> WorkflowRun {
         if !self
             .shared_actor_state
             .workflows
@@ -87,70 +171,380 @@ impl WorkflowWorker {
         {
             tracing::warn!("[{event_id}] workflow not executed as it's disabled");
             crate::metrics::record_workflow("disabled", Duration::ZERO);
-            self.shared_actor_state
-                .workflows
-                .record_run(WorkflowRun {
-                    slug: workflow.slug.clone(),
-                    name: workflow.name.clone(),
-                    event_id,
-                    outcome: "disabled".to_owned(),
-                    dry_run: workflow.dry_run,
-                    duration: Duration::ZERO,
-                    error: None,
-                })
-                .await;
-            return Ok(());
+            let run = WorkflowRun {
+                slug: workflow.slug.clone(),
+                name: workflow.name.clone(),
+                event_id,
+                outcome: "disabled".to_owned(),
+                dry_run: workflow.dry_run,
+                duration: Duration::ZERO,
+                error: None,
+                trace: RunTrace::with_vars(vars),
+            };
+            self.shared_actor_state.workflows.record_run(&run).await;
+            return run;
         }
 
         tracing::info!("executing workflow for: {event_id}");
         if workflow.dry_run {
             tracing::info!("[{event_id}] workflow running in dry-run (shadow) mode");
         }
+        let tracer = Tracer::default();
         let ctx = WorkflowContext {
             event_id,
             depth: 0,
             dry_run: workflow.dry_run,
             origin_slug: &workflow.slug,
+            origin_name: &workflow.name,
+            origin_steps: &workflow.run,
             vars,
+            trace: &tracer,
+            inline_delays: false,
         };
         let start = std::time::Instant::now();
-        let result = self.run_steps(ctx, &workflow.run).await;
+        let steps = async {
+            match resume_after {
+                Some(Continuation { path, digest })
+                    if digest
+                        .as_ref()
+                        .is_some_and(|digest| *digest != Step::digest(&workflow.run)) =>
+                {
+                    // edited since it parked, so `path` may point anywhere
+                    Err(WorkflowError::StaleContinuation(path.clone()))
+                }
+                Some(Continuation { path, .. }) => {
+                    self.resume_steps(ctx, &workflow.run, &[], path).await
+                }
+                None => self.run_steps(ctx, &workflow.run, &[]).await,
+            }
+        };
+        // dropping the steps mid-way is the cancellation; nothing is rolled back
+        let result = tokio::select! {
+            result = steps => result,
+            () = cancel.cancelled() => Err(WorkflowError::Cancelled),
+        };
         let elapsed = start.elapsed();
-        let outcome = if result.is_ok() { "success" } else { "error" };
+        let outcome = match result {
+            Ok(Flow::Done) => "success",
+            Ok(Flow::Parked) => "delayed",
+            Err(WorkflowError::Cancelled) => "cancelled",
+            Err(WorkflowError::StaleContinuation(_)) => "expired",
+            Err(_) => "error",
+        };
         crate::metrics::record_workflow(outcome, elapsed);
-        self.shared_actor_state
+        let run = WorkflowRun {
+            slug: workflow.slug.clone(),
+            name: workflow.name.clone(),
+            event_id,
+            outcome: outcome.to_owned(),
+            dry_run: workflow.dry_run,
+            duration: elapsed,
+            error: result.err().map(|e| e.to_string()),
+            trace: tracer.into_trace(vars),
+        };
+        self.shared_actor_state.workflows.record_run(&run).await;
+        run
+    }
+
+    /// Admit a new run by its workflow's `mode:`, against the runs of it in
+    /// flight here and parked in the delay queue.
+    async fn start(&self, pending: PendingRun) -> Result<(), ractor::ActorProcessingErr> {
+        let event_id = pending.event_id;
+        let slug = pending.workflow.slug.clone();
+        let mode = pending.workflow.mode;
+
+        let parked = delayed::parked(&self.shared_actor_state.db, &slug)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!("[{event_id}] failed to read the parked runs of '{slug}': {e}");
+                Vec::new()
+            });
+
+        let admission = self
+            .shared_actor_state
             .workflows
-            .record_run(WorkflowRun {
-                slug: workflow.slug.clone(),
-                name: workflow.name.clone(),
-                event_id,
-                outcome: outcome.to_owned(),
-                dry_run: workflow.dry_run,
-                duration: elapsed,
-                error: result.as_ref().err().map(|e| e.to_string()),
-            })
-            .await;
-        result
+            .runs()
+            .admit(pending, &parked);
+        crate::metrics::record_admission(mode.as_str(), admission.as_str());
+
+        match admission {
+            Admission::Run(pending, cancel) => {
+                if mode == RunMode::Restart && !parked.is_empty() {
+                    self.cancel_parked(&pending.workflow).await;
+                }
+                self.run(pending, None, cancel).await
+            }
+            Admission::Queued => {
+                tracing::info!("[{event_id}] queued behind the runs of '{slug}' in flight");
+                Ok(())
+            }
+            Admission::Dropped(pending) => {
+                self.drop_run(pending).await;
+                Ok(())
+            }
+        }
+    }
+
+    /// Execute an admitted run, then hand the next one queued behind it, if
+    /// any, back to the factory.
+    async fn run(
+        &self,
+        pending: PendingRun,
+        resume_after: Option<Continuation>,
+        cancel: CancellationToken,
+    ) -> Result<(), ractor::ActorProcessingErr> {
+        let PendingRun {
+            event_id,
+            workflow,
+            vars,
+            reply,
+        } = pending;
+        let slug = workflow.slug.clone();
+
+        let run = timed_async(|| async {
+            Ok::<_, anyhow::Error>(
+                self.execute_workflow(event_id, workflow, &vars, resume_after.as_ref(), &cancel)
+                    .await,
+            )
+        })
+        .await?;
+
+        if let Some(e) = &run.error {
+            match resume_after {
+                Some(_) => tracing::error!("[{event_id}] resumed workflow execution failed: {e}"),
+                None => tracing::error!("[{event_id}] workflow execution failed: {e}"),
+            }
+        }
+
+        let next = self.shared_actor_state.workflows.runs().finish(
+            &slug,
+            event_id,
+            run.outcome == "delayed",
+        );
+        if let Some(reply) = reply
+            && reply.send(run).is_err()
+        {
+            tracing::warn!("[{event_id}] caller stopped waiting for the run");
+        }
+
+        if let Some(next) = next {
+            Self::dispatch_queued(next)?;
+        }
+
+        Ok(())
     }
 
+    /// Hand a run taken off its workflow's queue back to the factory.
+    pub fn dispatch_queued(next: PendingRun) -> Result<(), ractor::ActorProcessingErr> {
+        tracing::info!(
+            "[{}] starting queued run of '{}'",
+            next.event_id,
+            next.workflow.slug
+        );
+        let actor = ractor::registry::where_is(Self::NAME)
+            .ok_or(WorkflowError::ActorNotFound(Self::NAME))?;
+        actor.send_message(FactoryMessage::Dispatch(Job {
+            key: (),
+            msg: WorkflowWorkerMessage::Execute {
+                event_id: next.event_id,
+                workflow: next.workflow,
+                vars: next.vars,
+                reply: next.reply,
+            },
+            options: JobOptions::default(),
+            accepted: None,
+        }))?;
+
+        Ok(())
+    }
+
+    /// Record a run turned away by its workflow's `mode:`.
+    async fn drop_run(&self, pending: PendingRun) {
+        let PendingRun {
+            event_id,
+            workflow,
+            vars,
+            reply,
+        } = pending;
+        tracing::info!(
+            "[{event_id}] dropping run of '{}', already in flight (mode: {})",
+            workflow.name,
+            workflow.mode.as_str()
+        );
+
+        crate::metrics::record_workflow("dropped", Duration::ZERO);
+        let run = WorkflowRun {
+            slug: workflow.slug,
+            name: workflow.name,
+            event_id,
+            outcome: "dropped".to_owned(),
+            dry_run: workflow.dry_run,
+            duration: Duration::ZERO,
+            error: Some(format!(
+                "already in flight (mode: {})",
+                workflow.mode.as_str()
+            )),
+            trace: RunTrace::with_vars(&vars),
+        };
+        self.shared_actor_state.workflows.record_run(&run).await;
+
+        if let Some(reply) = reply
+            && reply.send(run).is_err()
+        {
+            tracing::warn!("[{event_id}] caller stopped waiting for the run");
+        }
+    }
+
+    /// Drop the runs of a `restart` workflow parked behind a `delay`,
+    /// recording each as cancelled.
+    async fn cancel_parked(&self, workflow: &Workflow) {
+        let cancelled =
+            match delayed::cancel_parked(&self.shared_actor_state.db, &workflow.slug).await {
+                Ok(cancelled) => cancelled,
+                Err(e) => {
+                    tracing::warn!(
+                        "failed to cancel the parked runs of '{}': {e}",
+                        workflow.slug
+                    );
+                    return;
+                }
+            };
+
+        for event_id in cancelled {
+            tracing::info!("[{event_id}] cancelled parked run of '{}'", workflow.name);
+            crate::metrics::record_workflow("cancelled", Duration::ZERO);
+            self.shared_actor_state
+                .workflows
+                .record_run(&WorkflowRun {
+                    slug: workflow.slug.clone(),
+                    name: workflow.name.clone(),
+                    event_id,
+                    outcome: "cancelled".to_owned(),
+                    dry_run: workflow.dry_run,
+                    duration: Duration::ZERO,
+                    error: Some(WorkflowError::Cancelled.to_string()),
+                    trace: RunTrace::with_vars(&HashMap::new()),
+                })
+                .await;
+        }
+    }
+
+    /// Run `steps`, which sit at `prefix` in the workflow, from the top.
     async fn run_steps(
         &self,
         ctx: WorkflowContext<'_>,
         steps: &[Step],
-    ) -> Result<(), WorkflowError> {
-        for step in steps {
-            self.run_step(ctx, step).await?;
+        prefix: &[usize],
+    ) -> Result<Flow, WorkflowError> {
+        self.run_steps_from(ctx, steps, prefix, 0).await
+    }
+
+    async fn run_steps_from(
+        &self,
+        ctx: WorkflowContext<'_>,
+        steps: &[Step],
+        prefix: &[usize],
+        start: usize,
+    ) -> Result<Flow, WorkflowError> {
+        for (index, step) in steps.iter().enumerate().skip(start) {
+            let mut at = prefix.to_vec();
+            at.push(index);
+            if self.run_step(ctx, step, &at).await? == Flow::Parked {
+                return Ok(Flow::Parked);
+            }
         }
-        Ok(())
+        Ok(Flow::Done)
+    }
+
+    /// Walk back down to the parked `delay` at `rest` (relative to `prefix`)
+    /// and run whatever follows it at each level on the way back out. Fails
+    /// if the config changed so that `rest` no longer points at a `delay`.
+    async fn resume_steps(
+        &self,
+        ctx: WorkflowContext<'_>,
+        steps: &[Step],
+        prefix: &[usize],
+        rest: &[usize],
+    ) -> Result<Flow, WorkflowError> {
+        let stale = || WorkflowError::StaleContinuation([prefix, rest].concat());
+        let Some((&index, deeper)) = rest.split_first() else {
+            return Err(stale());
+        };
+        let step = steps.get(index).ok_or_else(stale)?;
+        let mut at = prefix.to_vec();
+        at.push(index);
+
+        if deeper.is_empty() {
+            if !matches!(step, Step::Delay { .. }) {
+                return Err(stale());
+            }
+        } else {
+            let flow = match step {
+                Step::Scene { run, .. } => {
+                    Box::pin(self.resume_steps(ctx, run, &at, deeper)).await?
+                }
+                // the option taken sits between the `choose` and its steps
+                Step::Choose { .. } => {
+                    let (&option, deeper) = deeper.split_first().ok_or_else(stale)?;
+                    let run = *step.children().get(option).ok_or_else(stale)?;
+                    at.push(option);
+                    Box::pin(self.resume_steps(ctx, run, &at, deeper)).await?
+                }
+                Step::RunWorkflow { workflow, .. } => {
+                    match self.nested_workflow(ctx, workflow).await? {
+                        Some(nested) => {
+                            let child = WorkflowContext {
+                                depth: ctx.depth + 1,
+                                dry_run: ctx.dry_run || nested.dry_run,
+                                ..ctx
+                            };
+                            Box::pin(self.resume_steps(child, &nested.run, &at, deeper)).await?
+                        }
+                        None => Flow::Done,
+                    }
+                }
+                _ => return Err(stale()),
+            };
+            if flow == Flow::Parked {
+                return Ok(Flow::Parked);
+            }
+        }
+
+        Box::pin(self.run_steps_from(ctx, steps, prefix, index + 1)).await
     }
 
-    async fn run_step(&self, ctx: WorkflowContext<'_>, step: &Step) -> Result<(), WorkflowError> {
+    async fn run_step(
+        &self,
+        ctx: WorkflowContext<'_>,
+        step: &Step,
+        at: &[usize],
+    ) -> Result<Flow, WorkflowError> {
+        let entry = ctx.trace.begin(step, at, ctx.depth, ctx.dry_run);
+        let start = std::time::Instant::now();
+        let result = self.guarded_step(ctx, step, at, entry).await;
+        ctx.trace.finish(
+            entry,
+            start.elapsed(),
+            result.as_ref().err().map(|e| e.to_string()),
+        );
+        result
+    }
+
+    async fn guarded_step(
+        &self,
+        ctx: WorkflowContext<'_>,
+        step: &Step,
+        at: &[usize],
+        entry: usize,
+    ) -> Result<Flow, WorkflowError> {
         // a failed guard skips only this step, not the rest of the workflow
-        if let Some(when) = step.guard()
-            && !conditions::eval(&self.shared_actor_state, when).await?
-        {
-            tracing::info!("[{}] skipping step, guard not satisfied", ctx.event_id);
-            return Ok(());
+        if let Some(when) = step.guard() {
+            let passed = conditions::eval(&self.shared_actor_state, when).await?;
+            ctx.trace.guard(entry, passed);
+            if !passed {
+                tracing::info!("[{}] skipping step, guard not satisfied", ctx.event_id);
+                return Ok(Flow::Done);
+            }
         }
 
         let span = tracing::info_span!(
@@ -160,7 +554,7 @@ impl WorkflowWorker {
             event_id = %ctx.event_id,
         );
         let start = std::time::Instant::now();
-        let result = self.dispatch_step(ctx, step).instrument(span).await;
+        let result = self.dispatch_step(ctx, step, at).instrument(span).await;
         crate::metrics::record_step(step.kind(), result.is_ok(), start.elapsed());
         result
     }
@@ -169,7 +563,8 @@ impl WorkflowWorker {
         &self,
         ctx: WorkflowContext<'_>,
         step: &Step,
-    ) -> Result<(), WorkflowError> {
+        at: &[usize],
+    ) -> Result<Flow, WorkflowError> {
         if ctx.dry_run
             && let Some(detail) = step.describe_action()
         {
@@ -178,38 +573,289 @@ impl WorkflowWorker {
                 ctx.event_id,
                 step.kind()
             );
-            return Ok(());
+            return Ok(Flow::Done);
         }
 
-        match step {
+        let lookups: Vec<String> = step
+            .templates()
+            .iter()
+            .flat_map(TemplateString::lookups)
+            .collect();
+        let resolved;
+        let vars = if lookups.is_empty() {
+            ctx.vars
+        } else {
+            resolved =
+                scope::resolve(&self.shared_actor_state, ctx.event_id, ctx.vars, &lookups).await;
+            &resolved
+        };
+
+        let result = match step {
             Step::Light {
                 ieee_addr, state, ..
-            } => self.run_light(ieee_addr.clone(), state.clone()).await,
-            Step::Switch { ieee_addr, .. } => {
-                // implemented in a later phase (needs a smart-switch control path)
-                let _ = ieee_addr;
-                Err(WorkflowError::NotImplemented("switch action"))
+            } => self.run_light(ieee_addr.clone(), state.clone(), vars).await,
+            Step::RoomLights { room, state, .. } => {
+                self.run_room_lights(ctx, room, state, vars).await
             }
-            Step::Scene { run, .. } => Box::pin(self.run_steps(ctx, run)).await,
+            Step::Switch {
+                ieee_addr, state, ..
+            } => self.run_switch(ieee_addr, *state).await,
+            Step::Scene { run, .. } => return Box::pin(self.run_steps(ctx, run, at)).await,
             Step::Notify {
-                notify: n, message, ..
-            } => {
-                notify(std::slice::from_ref(n), message.render(ctx.vars));
-                Ok(())
-            }
-            Step::Delay { seconds, .. } => {
+                notify: target,
+                message,
+                actions,
+                ..
+            } => self.run_notify(ctx, target, message.render(vars), actions),
+            Step::Delay { seconds, .. } if ctx.inline_delays => {
                 tokio::time::sleep(Duration::from_secs(*seconds)).await;
                 Ok(())
             }
-            Step::RunWorkflow { workflow, .. } => self.run_named_workflow(ctx, workflow).await,
+            Step::Delay { seconds, .. } => return self.park_rest(ctx, at, *seconds).await,
+            Step::RunWorkflow { workflow, .. } => {
+                return self.run_named_workflow(ctx, workflow, at).await;
+            }
             Step::SetMode { mode, active, .. } => self.run_set_mode(*mode, *active).await,
             Step::SetWorkflowsEnabled { tag, state, .. } => {
                 self.run_set_workflows_enabled(ctx, tag, *state).await
             }
+            Step::SetVariable {
+                variable, value, ..
+            } => {
+                self.run_set_variable(ctx, variable, &value.render(vars))
+                    .await
+            }
+            Step::IncrementVariable { variable, by, .. } => {
+                self.run_increment_variable(ctx, variable, *by).await
+            }
             Step::HomeAssistant {
                 call_service, data, ..
-            } => self.run_home_assistant(call_service, data.clone()).await,
+            } => {
+                self.run_home_assistant(call_service, render_json(data, vars))
+                    .await
+            }
+            Step::Parallel { branches, .. } => {
+                return Self::in_place(ctx, self.run_parallel(ctx, branches, at)).await;
+            }
+            Step::Choose {
+                options, otherwise, ..
+            } => return self.run_choose(ctx, options, otherwise, at).await,
+            Step::Repeat {
+                count,
+                repeat_while,
+                until,
+                run,
+                ..
+            } => {
+                let repeat =
+                    self.run_repeat(ctx, *count, repeat_while.as_ref(), until.as_ref(), run, at);
+                return Self::in_place(ctx, repeat).await;
+            }
+            Step::WaitFor {
+                condition,
+                event,
+                timeout,
+                continue_on_timeout,
+                ..
+            } => {
+                self.run_wait_for(
+                    ctx,
+                    condition.as_ref(),
+                    event.as_ref(),
+                    *timeout,
+                    *continue_on_timeout,
+                )
+                .await
+            }
+        };
+
+        result.map(|()| Flow::Done)
+    }
+
+    /// Run a `parallel` or `repeat`, whose `delay`s sleep on the worker rather
+    /// than park. The outermost one is cut off after [`MAX_WAIT`]: config load
+    /// holds its own steps to that, but not workflows it runs by name. Like
+    /// any other step, it stops when the run is cancelled.
+    async fn in_place(
+        ctx: WorkflowContext<'_>,
+        step: impl Future<Output = Result<Flow, WorkflowError>>,
+    ) -> Result<Flow, WorkflowError> {
+        if ctx.inline_delays {
+            return step.await;
+        }
+
+        tokio::time::timeout(MAX_WAIT.to_std().unwrap_or_default(), step)
+            .await
+            .unwrap_or(Err(WorkflowError::HeldTooLong))
+    }
+
+    /// Run every branch at once, each under its index in `at`. All of them
+    /// finish before the first error, if any, fails the step.
+    async fn run_parallel(
+        &self,
+        ctx: WorkflowContext<'_>,
+        branches: &[Vec<Step>],
+        at: &[usize],
+    ) -> Result<Flow, WorkflowError> {
+        let ctx = WorkflowContext {
+            inline_delays: true,
+            ..ctx
+        };
+        let runs = branches.iter().enumerate().map(|(branch, steps)| {
+            let prefix = [at, &[branch]].concat();
+            async move { Box::pin(self.run_steps(ctx, steps, &prefix)).await }
+        });
+
+        for result in futures::future::join_all(runs).await {
+            result?;
         }
+        Ok(Flow::Done)
+    }
+
+    /// Run the first option whose `if` holds, or `otherwise`, under the
+    /// option's index in `at` (`otherwise` comes after the last option).
+    async fn run_choose(
+        &self,
+        ctx: WorkflowContext<'_>,
+        options: &[ChooseOption],
+        otherwise: &[Step],
+        at: &[usize],
+    ) -> Result<Flow, WorkflowError> {
+        for (index, option) in options.iter().enumerate() {
+            if conditions::eval(&self.shared_actor_state, &option.condition).await? {
+                tracing::info!("[{}] choose took option {index}", ctx.event_id);
+                return Box::pin(self.run_steps(ctx, &option.run, &[at, &[index]].concat())).await;
+            }
+        }
+
+        tracing::info!("[{}] choose took else", ctx.event_id);
+        Box::pin(self.run_steps(ctx, otherwise, &[at, &[options.len()]].concat())).await
+    }
+
+    /// Run `run` until `count` passes, `repeat_while` stops holding or `until`
+    /// holds. A conditional loop still going after [`MAX_REPEAT`] passes
+    /// fails; in dry-run it makes one pass, since nothing it does would
+    /// change the condition.
+    async fn run_repeat(
+        &self,
+        ctx: WorkflowContext<'_>,
+        count: Option<u32>,
+        repeat_while: Option<&Condition>,
+        until: Option<&Condition>,
+        run: &[Step],
+        at: &[usize],
+    ) -> Result<Flow, WorkflowError> {
+        let ctx = WorkflowContext {
+            inline_delays: true,
+            ..ctx
+        };
+        let passes = count.unwrap_or(if ctx.dry_run { 1 } else { MAX_REPEAT });
+
+        for _ in 0..passes {
+            if let Some(condition) = repeat_while
+                && !conditions::eval(&self.shared_actor_state, condition).await?
+            {
+                return Ok(Flow::Done);
+            }
+            Box::pin(self.run_steps(ctx, run, at)).await?;
+            if let Some(condition) = until
+                && conditions::eval(&self.shared_actor_state, condition).await?
+            {
+                return Ok(Flow::Done);
+            }
+        }
+
+        if count.is_some() || ctx.dry_run {
+            Ok(Flow::Done)
+        } else {
+            Err(WorkflowError::RepeatLimit)
+        }
+    }
+
+    /// Hold until `condition` holds or an event `event` matches arrives. The
+    /// condition is re-checked on every bus event and every [`WAIT_RECHECK`].
+    async fn run_wait_for(
+        &self,
+        ctx: WorkflowContext<'_>,
+        condition: Option<&Condition>,
+        event: Option<&TriggerMatcher>,
+        timeout: chrono::TimeDelta,
+        continue_on_timeout: bool,
+    ) -> Result<(), WorkflowError> {
+        // subscribe before the first check so nothing slips in between
+        let mut rx = self.shared_actor_state.event_bus.subscribe();
+        let devices = self.shared_actor_state.devices();
+        let closed = || WorkflowError::Messaging("event bus closed".to_owned());
+
+        let wait = async {
+            match (condition, event) {
+                (Some(condition), _) => loop {
+                    if conditions::eval(&self.shared_actor_state, condition).await? {
+                        break Ok(());
+                    }
+                    tokio::select! {
+                        _ = tokio::time::sleep(WAIT_RECHECK) => {}
+                        received = rx.recv() => {
+                            if let Err(RecvError::Closed) = received {
+                                break Err(closed());
+                            }
+                        }
+                    }
+                },
+                (None, Some(event)) => loop {
+                    match rx.recv().await {
+                        Ok(msg) if dispatcher::event_matches(&devices, event, &msg) => {
+                            break Ok(());
+                        }
+                        Ok(_) | Err(RecvError::Lagged(_)) => {}
+                        Err(RecvError::Closed) => break Err(closed()),
+                    }
+                },
+                (None, None) => Ok(()),
+            }
+        };
+
+        match tokio::time::timeout(timeout.to_std().unwrap_or_default(), wait).await {
+            Ok(result) => result,
+            Err(_) if continue_on_timeout => {
+                tracing::info!("[{}] wait_for timed out, carrying on", ctx.event_id);
+                Ok(())
+            }
+            Err(_) => Err(WorkflowError::WaitTimedOut(timeout.num_seconds())),
+        }
+    }
+
+    /// Park the rest of the origin workflow behind a `delay` step, to be picked
+    /// back up by the [`delayed::DelayedRunActor`] once it comes due.
+    async fn park_rest(
+        &self,
+        ctx: WorkflowContext<'_>,
+        at: &[usize],
+        seconds: u64,
+    ) -> Result<Flow, WorkflowError> {
+        let delay = Duration::from_secs(seconds);
+        let run = DelayedRun {
+            event_id: ctx.event_id,
+            slug: ctx.origin_slug.to_owned(),
+            name: ctx.origin_name.to_owned(),
+            due_at: chrono::Utc::now() + delay,
+            vars: ctx.vars.clone(),
+            resume: Resume::Step {
+                path: at.to_vec(),
+                digest: Some(Step::digest(ctx.origin_steps)),
+            },
+        };
+
+        delayed::park(&self.shared_actor_state.db, &run, delay)
+            .await
+            .map_err(|e| WorkflowError::Other(e.into()))?;
+
+        tracing::info!(
+            "[{}] parked the rest of '{}' for {seconds}s",
+            ctx.event_id,
+            ctx.origin_name
+        );
+        Ok(Flow::Parked)
     }
 
     async fn run_home_assistant(
@@ -244,7 +890,7 @@ impl WorkflowWorker {
         tag: &str,
         state: EnableState,
     ) -> Result<(), WorkflowError> {
-        let settings = self.shared_actor_state.settings.clone();
+        let settings = self.shared_actor_state.settings();
         let manager = &self.shared_actor_state.workflows;
 
         let targets = settings
@@ -287,6 +933,26 @@ impl WorkflowWorker {
         Ok(())
     }
 
+    fn run_notify(
+        &self,
+        ctx: WorkflowContext<'_>,
+        target: &NotifyRef,
+        message: String,
+        actions: &[NotifyAction],
+    ) -> Result<(), WorkflowError> {
+        // named targets were checked at load, but a reload may have dropped one
+        let source = target
+            .resolve(&self.shared_actor_state.settings().notify_targets)
+            .map_err(WorkflowError::NotifyTarget)?;
+        notify(
+            std::slice::from_ref(&source),
+            message,
+            Origin::new(ctx.event_id, format!("workflow:{}", ctx.origin_slug)),
+            actions,
+        );
+        Ok(())
+    }
+
     async fn run_set_mode(
         &self,
         mode: crate::mode::Mode,
@@ -311,14 +977,99 @@ impl WorkflowWorker {
         Ok(())
     }
 
+    async fn run_set_variable(
+        &self,
+        ctx: WorkflowContext<'_>,
+        name: &str,
+        value: &str,
+    ) -> Result<(), WorkflowError> {
+        let settings = self.shared_actor_state.settings();
+        let variable = settings
+            .variables
+            .get(name)
+            .ok_or_else(|| WorkflowError::UnknownVariable(name.to_owned()))?;
+        let value = variable
+            .parse(value)
+            .map_err(|e| WorkflowError::InvalidVariable(name.to_owned(), e))?;
+
+        let change = self
+            .shared_actor_state
+            .workflows
+            .set_variable(name, variable, value)
+            .await
+            .map_err(|e| WorkflowError::Other(e.into()))?;
+
+        if let Some(change) = change {
+            tracing::info!(
+                "[{}] set variable `{name}` to {}",
+                ctx.event_id,
+                change.value
+            );
+            self.shared_actor_state
+                .event_bus
+                .publish(change.into_event());
+        }
+        Ok(())
+    }
+
+    async fn run_increment_variable(
+        &self,
+        ctx: WorkflowContext<'_>,
+        name: &str,
+        by: f64,
+    ) -> Result<(), WorkflowError> {
+        let settings = self.shared_actor_state.settings();
+        let variable = settings
+            .variables
+            .get(name)
+            .ok_or_else(|| WorkflowError::UnknownVariable(name.to_owned()))?;
+
+        let change = self
+            .shared_actor_state
+            .workflows
+            .increment_variable(name, variable, by)
+            .await
+            .map_err(|e| WorkflowError::Other(e.into()))?;
+
+        tracing::info!(
+            "[{}] variable `{name}` is now {}",
+            ctx.event_id,
+            change.value
+        );
+        self.shared_actor_state
+            .event_bus
+            .publish(change.into_event());
+        Ok(())
+    }
+
     /// Expand a `run_workflow` step: look the named workflow up in settings and
-    /// run its steps with an incremented depth. `MAX_DEPTH` bounds transitive
-    /// self-reference so a workflow cycle can't recurse forever.
+    /// run its steps with an incremented depth.
     async fn run_named_workflow(
         &self,
         ctx: WorkflowContext<'_>,
         name: &str,
-    ) -> Result<(), WorkflowError> {
+        at: &[usize],
+    ) -> Result<Flow, WorkflowError> {
+        let Some(workflow) = self.nested_workflow(ctx, name).await? else {
+            return Ok(Flow::Done);
+        };
+
+        let child = WorkflowContext {
+            depth: ctx.depth + 1,
+            dry_run: ctx.dry_run || workflow.dry_run,
+            ..ctx
+        };
+        Box::pin(self.run_steps(child, &workflow.run, at)).await
+    }
+
+    /// The workflow a `run_workflow` step names, or `None` when it's unknown or
+    /// disabled and should be skipped. `MAX_DEPTH` bounds transitive
+    /// self-reference so a workflow cycle can't recurse forever.
+    async fn nested_workflow(
+        &self,
+        ctx: WorkflowContext<'_>,
+        name: &str,
+    ) -> Result<Option<Workflow>, WorkflowError> {
         if ctx.depth >= MAX_DEPTH {
             tracing::error!(
                 "[{}] workflow recursion depth exceeded at `{name}`",
@@ -327,13 +1078,13 @@ impl WorkflowWorker {
             return Err(WorkflowError::DepthExceeded);
         }
 
-        let settings = self.shared_actor_state.settings.clone();
+        let settings = self.shared_actor_state.settings();
         let Some(workflow) = settings.workflows.get(name) else {
             tracing::warn!(
                 "[{}] run_workflow references unknown workflow `{name}`",
                 ctx.event_id
             );
-            return Ok(());
+            return Ok(None);
         };
 
         if !self
@@ -343,26 +1094,24 @@ impl WorkflowWorker {
             .await
         {
             tracing::info!("[{}] skipping disabled workflow `{name}`", ctx.event_id);
-            return Ok(());
+            return Ok(None);
         }
 
-        let child = WorkflowContext {
-            event_id: ctx.event_id,
-            depth: ctx.depth + 1,
-            dry_run: ctx.dry_run || workflow.dry_run,
-            origin_slug: ctx.origin_slug,
-            vars: ctx.vars,
-        };
-        Box::pin(self.run_steps(child, &workflow.run)).await
+        Ok(Some(workflow.clone()))
     }
 
-    async fn run_light(&self, device: String, state: LightState) -> Result<(), WorkflowError> {
+    async fn run_light(
+        &self,
+        device: String,
+        state: LightState,
+        vars: &HashMap<String, String>,
+    ) -> Result<(), WorkflowError> {
         let actor = ractor::registry::where_is(LightHandler::NAME)
             .ok_or(WorkflowError::ActorNotFound(LightHandler::NAME))?;
 
         let ieee_addr = self
             .shared_actor_state
-            .devices
+            .devices()
             .address_or_self(&device)
             .to_owned();
 
@@ -370,10 +1119,12 @@ impl WorkflowWorker {
             LightState::On => LightHandlerMessage::TurnOn { ieee_addr },
             LightState::Off => LightHandlerMessage::TurnOff { ieee_addr },
             LightState::Toggle => LightHandlerMessage::Toggle { ieee_addr },
-            LightState::SetBrightness { value } => {
-                LightHandlerMessage::SetBrightness { ieee_addr, value }
-            }
+            LightState::SetBrightness { value } => LightHandlerMessage::SetBrightness {
+                ieee_addr,
+                value: value.resolve(vars).map_err(WorkflowError::Template)?,
+            },
             LightState::IncreaseBrightness { value, on_off } => {
+                let value = value.resolve(vars).map_err(WorkflowError::Template)?;
                 LightHandlerMessage::BrightnessMove {
                     ieee_addr,
                     value: value.try_into().map_err(anyhow::Error::from)?,
@@ -381,6 +1132,7 @@ impl WorkflowWorker {
                 }
             }
             LightState::DecreaseBrightness { value, on_off } => {
+                let value = value.resolve(vars).map_err(WorkflowError::Template)?;
                 LightHandlerMessage::BrightnessMove {
                     ieee_addr,
                     value: -TryInto::<i64>::try_into(value).map_err(anyhow::Error::from)?,
@@ -420,6 +1172,60 @@ impl WorkflowWorker {
             .send_message(message)
             .map_err(|e| WorkflowError::Messaging(e.to_string()))
     }
+
+    /// Apply `state` to every light the registry places in `room` as the step
+    /// runs, so moving a light between rooms needs no workflow change.
+    async fn run_room_lights(
+        &self,
+        ctx: WorkflowContext<'_>,
+        room: &str,
+        state: &LightState,
+        vars: &HashMap<String, String>,
+    ) -> Result<(), WorkflowError> {
+        let devices = self.shared_actor_state.devices();
+        let lights: Vec<String> = devices
+            .room_members(room)
+            .filter(|address| devices.light(address).is_some())
+            .cloned()
+            .collect();
+
+        if lights.is_empty() {
+            tracing::warn!("[{}] no lights in room `{room}`", ctx.event_id);
+            return Ok(());
+        }
+
+        for address in lights {
+            self.run_light(address, state.clone(), vars).await?;
+        }
+        Ok(())
+    }
+
+    async fn run_switch(&self, device: &str, state: SwitchState) -> Result<(), WorkflowError> {
+        let actor = ractor::registry::where_is(SmartSwitchHandler::NAME)
+            .ok_or(WorkflowError::ActorNotFound(SmartSwitchHandler::NAME))?;
+
+        let address = self
+            .shared_actor_state
+            .devices()
+            .address_or_self(device)
+            .to_owned();
+
+        let switch_actor_message = match state {
+            SwitchState::On => smart_switch::Message::TurnOn { address },
+            SwitchState::Off => smart_switch::Message::TurnOff { address },
+            SwitchState::Toggle => smart_switch::Message::Toggle { address },
+        };
+
+        let message = FactoryMessage::Dispatch(Job {
+            key: (),
+            msg: switch_actor_message,
+            options: JobOptions::default(),
+            accepted: None,
+        });
+        actor
+            .send_message(message)
+            .map_err(|e| WorkflowError::Messaging(e.to_string()))
+    }
 }
 
 impl Worker for WorkflowWorker {
@@ -450,17 +1256,35 @@ impl Worker for WorkflowWorker {
                 event_id,
                 workflow,
                 vars,
+                reply,
             } => {
-                let result = timed_async(|| async {
-                    self.execute_workflow(event_id, workflow, &vars)
-                        .await
-                        .map_err(anyhow::Error::from)
+                self.start(PendingRun {
+                    event_id,
+                    workflow,
+                    vars,
+                    reply,
                 })
-                .await;
-
-                if let Err(e) = result {
-                    tracing::error!("[{event_id}] workflow execution failed: {e}");
-                }
+                .await?;
+            }
+            // a continuation was admitted when its run started
+            WorkflowWorkerMessage::Resume {
+                event_id,
+                workflow,
+                vars,
+                continuation,
+            } => {
+                let cancel = self
+                    .shared_actor_state
+                    .workflows
+                    .runs()
+                    .resume(&workflow.slug, event_id);
+                let pending = PendingRun {
+                    event_id,
+                    workflow,
+                    vars,
+                    reply: None,
+                };
+                self.run(pending, Some(continuation), cancel).await?;
             }
         }
 
@@ -7,7 +7,11 @@ use crate::settings::workflow::{Step, Workflow};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PlannedAction {
+    /// How many `run_workflow` steps deep the action sits.
     pub depth: u8,
+    /// How far it's indented: `depth`, plus the `parallel` and `repeat`
+    /// steps it's nested in.
+    pub level: u8,
     pub kind: &'static str,
     pub detail: String,
     pub guards: Vec<String>,
@@ -15,7 +19,7 @@ pub struct PlannedAction {
 
 pub fn plan(workflows: &HashMap<String, Workflow>, steps: &[Step]) -> Vec<PlannedAction> {
     let mut out = Vec::new();
-    plan_steps(workflows, steps, 0, &[], &mut out);
+    plan_steps(workflows, steps, 0, 0, &[], &mut out);
     out
 }
 
@@ -23,6 +27,7 @@ fn plan_steps(
     workflows: &HashMap<String, Workflow>,
     steps: &[Step],
     depth: u8,
+    level: u8,
     guards: &[String],
     out: &mut Vec<PlannedAction>,
 ) {
@@ -31,46 +36,83 @@ fn plan_steps(
         if let Some(when) = step.guard() {
             guards.push(when.describe());
         }
+        let marker = |kind, detail: String, level| PlannedAction {
+            depth,
+            level,
+            kind,
+            detail,
+            guards: guards.clone(),
+        };
 
         match step {
-            Step::Scene { run, .. } => plan_steps(workflows, run, depth, &guards, out),
+            Step::Scene { run, .. } => plan_steps(workflows, run, depth, level, &guards, out),
             Step::RunWorkflow { workflow, .. } => {
                 if depth >= MAX_DEPTH {
-                    out.push(marker("depth_exceeded", workflow, depth, &guards));
+                    out.push(marker("depth_exceeded", workflow.clone(), level));
                     continue;
                 }
                 match workflows.get(workflow) {
-                    None => out.push(marker("unknown_workflow", workflow, depth, &guards)),
+                    None => out.push(marker("unknown_workflow", workflow.clone(), level)),
                     Some(wf) if !wf.enabled => {
-                        out.push(marker("disabled", workflow, depth, &guards))
+                        out.push(marker("disabled", workflow.clone(), level))
                     }
-                    Some(wf) => plan_steps(workflows, &wf.run, depth + 1, &guards, out),
+                    Some(wf) => plan_steps(workflows, &wf.run, depth + 1, level + 1, &guards, out),
                 }
             }
-            leaf => out.push(PlannedAction {
-                depth,
-                kind: leaf.kind(),
-                detail: leaf.describe_action().unwrap_or_default(),
-                guards,
-            }),
+            Step::Parallel { branches, .. } => {
+                out.push(marker(
+                    "parallel",
+                    format!("{} branches", branches.len()),
+                    level,
+                ));
+                for (index, branch) in branches.iter().enumerate() {
+                    out.push(marker("branch", (index + 1).to_string(), level + 1));
+                    plan_steps(workflows, branch, depth, level + 2, &guards, out);
+                }
+            }
+            // each option is guarded by its own `if` and every earlier one failing
+            Step::Choose {
+                options, otherwise, ..
+            } => {
+                let mut ruled_out = guards.clone();
+                for option in options {
+                    let condition = option.condition.describe();
+                    let taken = [ruled_out.as_slice(), &[condition.clone()]].concat();
+                    plan_steps(workflows, &option.run, depth, level, &taken, out);
+                    ruled_out.push(format!("not({condition})"));
+                }
+                plan_steps(workflows, otherwise, depth, level, &ruled_out, out);
+            }
+            Step::Repeat {
+                count,
+                repeat_while,
+                until,
+                run,
+                ..
+            } => {
+                let detail = match (count, repeat_while, until) {
+                    (Some(count), _, _) => format!("{count} times"),
+                    (None, Some(condition), _) => format!("while {}", condition.describe()),
+                    (None, None, Some(condition)) => format!("until {}", condition.describe()),
+                    (None, None, None) => String::new(),
+                };
+                out.push(marker("repeat", detail, level));
+                plan_steps(workflows, run, depth, level + 1, &guards, out);
+            }
+            leaf => out.push(marker(
+                leaf.kind(),
+                leaf.describe_action().unwrap_or_default(),
+                level,
+            )),
         }
     }
 }
 
-fn marker(kind: &'static str, name: &str, depth: u8, guards: &[String]) -> PlannedAction {
-    PlannedAction {
-        depth,
-        kind,
-        detail: name.to_string(),
-        guards: guards.to_vec(),
-    }
-}
-
 pub fn render(actions: &[PlannedAction]) -> String {
     actions
         .iter()
         .map(|a| {
-            let indent = "  ".repeat(a.depth as usize);
+            let indent = "  ".repeat(a.level as usize);
             let guard = if a.guards.is_empty() {
                 String::new()
             } else {
@@ -205,6 +247,84 @@ mod tests {
         insta::assert_snapshot!(rendered(&all, &entry));
     }
 
+    #[test]
+    fn choose_options_are_guarded_by_earlier_ones_failing() {
+        let wf = workflow(
+            r#"
+            run:
+              - type: choose
+                options:
+                  - if: { type: mode, mode: guest, active: true }
+                    run:
+                      - type: light
+                        device: "0x1"
+                        state: "ON"
+                  - if: { type: presence, sensor: hallway, present: true }
+                    run:
+                      - type: notify
+                        notify: { type: android_app }
+                        message: "someone home"
+                else:
+                  - type: light
+                    device: "0x1"
+                    state: "OFF"
+            "#,
+        );
+        insta::assert_snapshot!(rendered(&HashMap::new(), &wf));
+    }
+
+    #[test]
+    fn parallel_repeat_and_wait_for() {
+        let wf = workflow(
+            r#"
+            run:
+              - type: parallel
+                branches:
+                  - - type: light
+                      device: "0x1"
+                      state: "ON"
+                  - - type: repeat
+                      count: 3
+                      run:
+                        - type: notify
+                          notify: { type: android_app }
+                          message: "ping"
+                        - type: delay
+                          seconds: 5
+              - type: wait_for
+                event: { type: door, device: "0x2", open: false }
+                timeout: 10m
+                when: { type: mode, mode: night, active: true }
+              - type: repeat
+                until: { type: light, device: "0x1", on: false }
+                run:
+                  - type: light
+                    device: "0x1"
+                    state: "OFF"
+            "#,
+        );
+        insta::assert_snapshot!(rendered(&HashMap::new(), &wf));
+    }
+
+    #[test]
+    fn run_workflow_in_a_branch_keeps_the_depth_cap() {
+        let all = workflows(
+            r#"
+            loop:
+              run:
+                - type: parallel
+                  branches:
+                    - - type: run_workflow
+                        workflow: loop
+            "#,
+        );
+        let entry = all.get("loop").unwrap().clone();
+        let actions = plan(&all, &entry.run);
+        let capped = actions.last().unwrap();
+        assert_eq!(capped.kind, "depth_exceeded");
+        assert_eq!(capped.depth, MAX_DEPTH);
+    }
+
     #[rstest::rstest]
     fn real_workflows(
         #[files("config/workflows/*.yaml")]
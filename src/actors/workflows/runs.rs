@@ -0,0 +1,258 @@
+//! Which runs of each workflow are in flight, so its `mode:` can decide what a
+//! new run does. A run stays in flight while it's parked behind a `delay`,
+//! until its continuation finishes. Continuations parked before a restart
+//! aren't known here, so the caller passes in what the delay queue holds.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use ractor::RpcReplyPort;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::actors::workflows::manager::WorkflowRun;
+use crate::settings::workflow::{RunMode, Workflow};
+
+/// A run waiting to be admitted, carried through a `queued` workflow's queue
+/// with the caller's reply port.
+pub struct PendingRun {
+    pub event_id: Uuid,
+    pub workflow: Workflow,
+    pub vars: HashMap<String, String>,
+    pub reply: Option<RpcReplyPort<WorkflowRun>>,
+}
+
+pub enum Admission {
+    /// Start now; the run stops when the token is cancelled.
+    Run(PendingRun, CancellationToken),
+    /// Held until the runs ahead of it finish, then dispatched again.
+    Queued,
+    /// Turned away: a `single` run in flight, a full queue, or `parallel` at
+    /// its `max`.
+    Dropped(PendingRun),
+}
+
+impl Admission {
+    /// Label for metrics.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Admission::Run(..) => "run",
+            Admission::Queued => "queued",
+            Admission::Dropped(_) => "dropped",
+        }
+    }
+}
+
+#[derive(Default)]
+struct SlugRuns {
+    running: HashMap<Uuid, CancellationToken>,
+    queue: VecDeque<PendingRun>,
+}
+
+#[derive(Clone, Default)]
+pub struct ActiveRuns(Arc<Mutex<HashMap<String, SlugRuns>>>);
+
+impl ActiveRuns {
+    /// Decide what `pending` does given the runs of its workflow in flight
+    /// here and `parked` in the delay queue. A run taken off the queue was
+    /// registered when it was, so it's let straight through. `restart`
+    /// cancels the runs in flight here; the caller drops the parked ones.
+    pub fn admit(&self, pending: PendingRun, parked: &[Uuid]) -> Admission {
+        let mut runs = self.0.lock().expect("runs lock poisoned");
+        let (mode, max) = (pending.workflow.mode, pending.workflow.max as usize);
+        let slug = runs.entry(pending.workflow.slug.clone()).or_default();
+
+        if let Some(token) = slug.running.get(&pending.event_id) {
+            let token = token.clone();
+            return Admission::Run(pending, token);
+        }
+
+        let in_flight = slug.running.len()
+            + parked
+                .iter()
+                .filter(|id| !slug.running.contains_key(id))
+                .count();
+
+        let admit = match mode {
+            _ if in_flight == 0 => true,
+            RunMode::Single => false,
+            RunMode::Restart => {
+                for (_, token) in slug.running.drain() {
+                    token.cancel();
+                }
+                true
+            }
+            RunMode::Queued if slug.queue.len() < max => {
+                slug.queue.push_back(pending);
+                return Admission::Queued;
+            }
+            RunMode::Queued => false,
+            RunMode::Parallel => in_flight < max,
+        };
+
+        if !admit {
+            return Admission::Dropped(pending);
+        }
+
+        let token = CancellationToken::new();
+        slug.running.insert(pending.event_id, token.clone());
+        Admission::Run(pending, token)
+    }
+
+    /// The token of a run carrying on after a `delay`, registering it again
+    /// if it was parked before a restart.
+    pub fn resume(&self, slug: &str, event_id: Uuid) -> CancellationToken {
+        let mut runs = self.0.lock().expect("runs lock poisoned");
+        runs.entry(slug.to_owned())
+            .or_default()
+            .running
+            .entry(event_id)
+            .or_default()
+            .clone()
+    }
+
+    /// Mark a run as having stopped executing. One that `parked` stays in
+    /// flight. Once nothing of the workflow is left running, the next queued
+    /// run is registered and returned to be dispatched.
+    pub fn finish(&self, slug: &str, event_id: Uuid, parked: bool) -> Option<PendingRun> {
+        if parked {
+            return None;
+        }
+
+        let mut runs = self.0.lock().expect("runs lock poisoned");
+        let entry = runs.get_mut(slug)?;
+        entry.running.remove(&event_id);
+
+        if !entry.running.is_empty() {
+            return None;
+        }
+        let Some(next) = entry.queue.pop_front() else {
+            runs.remove(slug);
+            return None;
+        };
+        entry
+            .running
+            .insert(next.event_id, CancellationToken::new());
+        Some(next)
+    }
+
+    /// Release a run parked behind a `delay` that will never resume, because
+    /// it expired, its workflow is gone or its message couldn't be read. Like
+    /// [`finish`](Self::finish), returns the next queued run to dispatch. A
+    /// run that isn't in flight here, such as one parked before a restart,
+    /// is left as is.
+    pub fn forget(&self, slug: &str, event_id: Uuid) -> Option<PendingRun> {
+        self.finish(slug, event_id, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workflow(mode: &str, max: u32) -> Workflow {
+        serde_yaml::from_str(&format!(
+            "slug: hall\nname: Hall\nmode: {mode}\nmax: {max}\nrun: []"
+        ))
+        .expect("workflow yaml")
+    }
+
+    fn pending(workflow: &Workflow) -> PendingRun {
+        PendingRun {
+            event_id: Uuid::new_v4(),
+            workflow: workflow.clone(),
+            vars: HashMap::new(),
+            reply: None,
+        }
+    }
+
+    fn started(admission: Admission) -> (Uuid, CancellationToken) {
+        match admission {
+            Admission::Run(run, token) => (run.event_id, token),
+            other => panic!("expected the run to start, got {}", other.as_str()),
+        }
+    }
+
+    #[test]
+    fn single_drops_while_a_run_is_in_flight_or_parked() {
+        let runs = ActiveRuns::default();
+        let single = workflow("single", 1);
+
+        let (first, _) = started(runs.admit(pending(&single), &[]));
+        assert_eq!(runs.admit(pending(&single), &[]).as_str(), "dropped");
+
+        runs.finish("hall", first, false);
+        assert_eq!(
+            runs.admit(pending(&single), &[Uuid::new_v4()]).as_str(),
+            "dropped"
+        );
+        assert_eq!(runs.admit(pending(&single), &[]).as_str(), "run");
+    }
+
+    #[test]
+    fn a_parked_run_that_expires_stops_blocking_single() {
+        let runs = ActiveRuns::default();
+        let single = workflow("single", 1);
+
+        let (first, _) = started(runs.admit(pending(&single), &[]));
+        runs.finish("hall", first, true);
+        assert_eq!(runs.admit(pending(&single), &[]).as_str(), "dropped");
+
+        assert!(runs.forget("hall", first).is_none());
+        assert_eq!(runs.admit(pending(&single), &[]).as_str(), "run");
+    }
+
+    #[test]
+    fn forgetting_a_parked_run_starts_the_next_queued_one() {
+        let runs = ActiveRuns::default();
+        let queued = workflow("queued", 1);
+
+        let (first, _) = started(runs.admit(pending(&queued), &[]));
+        let waiting = pending(&queued);
+        let waiting_id = waiting.event_id;
+        runs.admit(waiting, &[]);
+        runs.finish("hall", first, true);
+
+        let next = runs.forget("hall", first).expect("the queued run");
+        assert_eq!(next.event_id, waiting_id);
+    }
+
+    #[test]
+    fn restart_cancels_the_run_in_flight() {
+        let runs = ActiveRuns::default();
+        let restart = workflow("restart", 1);
+
+        let (_, first) = started(runs.admit(pending(&restart), &[]));
+        let (_, second) = started(runs.admit(pending(&restart), &[]));
+        assert!(first.is_cancelled());
+        assert!(!second.is_cancelled());
+    }
+
+    #[test]
+    fn queued_runs_wait_their_turn_up_to_max() {
+        let runs = ActiveRuns::default();
+        let queued = workflow("queued", 1);
+
+        let (first, _) = started(runs.admit(pending(&queued), &[]));
+        let waiting = pending(&queued);
+        let waiting_id = waiting.event_id;
+        assert_eq!(runs.admit(waiting, &[]).as_str(), "queued");
+        assert_eq!(runs.admit(pending(&queued), &[]).as_str(), "dropped");
+
+        // parking keeps the first run in flight
+        assert!(runs.finish("hall", first, true).is_none());
+        let next = runs.finish("hall", first, false).expect("the queued run");
+        assert_eq!(next.event_id, waiting_id);
+        assert_eq!(started(runs.admit(next, &[])).0, waiting_id);
+    }
+
+    #[test]
+    fn parallel_runs_up_to_max() {
+        let runs = ActiveRuns::default();
+        let parallel = workflow("parallel", 2);
+
+        started(runs.admit(pending(&parallel), &[]));
+        started(runs.admit(pending(&parallel), &[]));
+        assert_eq!(runs.admit(pending(&parallel), &[]).as_str(), "dropped");
+    }
+}
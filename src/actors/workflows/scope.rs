@@ -0,0 +1,81 @@
+//! Template scope for a step: the triggering event's vars, plus whatever live
+//! state the step's templates look up by dotted path ([`Lookup`]).
+//!
+//! Lookups resolve right before the step renders, so a step after a `delay`
+//! sees current values. One that fails or has nothing to report yet is left
+//! out of the scope, which renders as missing and so falls through to a
+//! `default` filter.
+
+use std::collections::HashMap;
+
+use chrono::Utc;
+use chrono_tz::Australia::Perth;
+use uuid::Uuid;
+
+use super::{WorkflowError, conditions};
+use crate::{actors::sun::calc, settings::template::Lookup, state::SharedActorState};
+
+/// `vars` extended with the value of every path in `lookups`.
+pub async fn resolve(
+    state: &SharedActorState,
+    event_id: Uuid,
+    vars: &HashMap<String, String>,
+    lookups: &[String],
+) -> HashMap<String, String> {
+    let mut scope = vars.clone();
+
+    for path in lookups {
+        if scope.contains_key(path) {
+            continue;
+        }
+
+        let value = match Lookup::parse(path) {
+            Ok(lookup) => lookup_value(state, lookup).await,
+            Err(e) => Err(WorkflowError::Other(anyhow::anyhow!(e))),
+        };
+
+        match value {
+            Ok(Some(value)) => {
+                scope.insert(path.clone(), value);
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("[{event_id}] template lookup `{path}` failed: {e}"),
+        }
+    }
+
+    scope
+}
+
+async fn lookup_value(
+    state: &SharedActorState,
+    lookup: Lookup<'_>,
+) -> Result<Option<String>, WorkflowError> {
+    let devices = state.devices();
+
+    Ok(match lookup {
+        Lookup::Sensor { sensor, metric } => {
+            conditions::environment_value(devices.address_or_self(sensor), metric)
+                .await?
+                .map(|value| value.to_string())
+        }
+        Lookup::Presence { sensor } => conditions::latest_presence(devices.address_or_self(sensor))
+            .await?
+            .map(|present| present.to_string()),
+        Lookup::Door { device } => conditions::door_open(devices.address_or_self(device))
+            .await?
+            .map(|open| open.to_string()),
+        Lookup::Mode(mode) => Some(state.workflows.mode_active(mode).await.to_string()),
+        Lookup::Sun(transition) => {
+            let at = calc::transition_today(state.settings().location, Utc::now(), transition);
+            Some(at.with_timezone(&Perth).format("%H:%M").to_string())
+        }
+        Lookup::Variable(name) => match state.settings().variables.get(name) {
+            Some(variable) => state
+                .workflows
+                .variable(name, variable)
+                .await
+                .map_err(|e| WorkflowError::Other(e.into()))?,
+            None => None,
+        },
+    })
+}
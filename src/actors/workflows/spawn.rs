@@ -18,7 +18,7 @@ pub async fn spawn_workflows(
         queues::DefaultQueue<(), WorkflowWorkerMessage>,
     >::default();
 
-    let workers = shared_actor_state.settings.workflow.workers;
+    let workers = shared_actor_state.settings().workflow.workers;
 
     let door_handler_factory_args = FactoryArguments::builder()
         .worker_builder(Box::new(WorkflowWorkerBuilder { shared_actor_state }))
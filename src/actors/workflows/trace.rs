@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_graphql::SimpleObject;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::workflow::Step;
+
+/// What a run did, step by step, stored with its `workflow_runs` row.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, SimpleObject)]
+pub struct RunTrace {
+    /// The triggering event's template variables, by name.
+    pub vars: Vec<TraceVar>,
+    /// Every step that was reached, in the order it started; a step that
+    /// nests others is followed by the steps it ran, which for `parallel`
+    /// branches interleave, and for `repeat` appear once per pass.
+    pub steps: Vec<StepTrace>,
+}
+
+impl RunTrace {
+    /// A trace of a run that never reached its steps.
+    pub fn with_vars(vars: &HashMap<String, String>) -> Self {
+        let mut vars: Vec<TraceVar> = vars
+            .iter()
+            .map(|(name, value)| TraceVar {
+                name: name.clone(),
+                value: value.clone(),
+            })
+            .collect();
+        vars.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Self {
+            vars,
+            steps: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, SimpleObject)]
+pub struct TraceVar {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, SimpleObject)]
+pub struct StepTrace {
+    /// Where the step sits, by index at each level of nesting.
+    pub path: Vec<u32>,
+    /// How many `run_workflow` steps deep the step ran, `0` for the triggered
+    /// workflow's own.
+    pub depth: u8,
+    pub kind: String,
+    /// The step's `describe_action()`, or the workflow a `run_workflow` names.
+    pub detail: Option<String>,
+    /// The step's `when:` guard.
+    pub guard: Option<String>,
+    /// What the guard evaluated to; a `false` skipped the step.
+    pub guard_passed: Option<bool>,
+    pub dry_run: bool,
+    pub duration_ms: i64,
+    pub error: Option<String>,
+}
+
+impl StepTrace {
+    fn new(step: &Step, at: &[usize], depth: u8, dry_run: bool) -> Self {
+        let detail = match step {
+            Step::RunWorkflow { workflow, .. } => Some(workflow.clone()),
+            step => step.describe_action(),
+        };
+
+        Self {
+            path: at.iter().map(|&index| index as u32).collect(),
+            depth,
+            kind: step.kind().to_owned(),
+            detail,
+            guard: step.guard().map(|when| when.describe()),
+            guard_passed: None,
+            dry_run,
+            duration_ms: 0,
+            error: None,
+        }
+    }
+}
+
+/// Collects a run's [`StepTrace`]s as the executor recurses. Entries are
+/// opened when a step starts so nested steps land after their parent.
+#[derive(Debug, Default)]
+pub struct Tracer(Mutex<Vec<StepTrace>>);
+
+impl Tracer {
+    pub(super) fn begin(&self, step: &Step, at: &[usize], depth: u8, dry_run: bool) -> usize {
+        let mut steps = self.0.lock().expect("trace lock poisoned");
+        steps.push(StepTrace::new(step, at, depth, dry_run));
+        steps.len() - 1
+    }
+
+    pub(super) fn guard(&self, entry: usize, passed: bool) {
+        self.0.lock().expect("trace lock poisoned")[entry].guard_passed = Some(passed);
+    }
+
+    pub(super) fn finish(&self, entry: usize, elapsed: Duration, error: Option<String>) {
+        let step = &mut self.0.lock().expect("trace lock poisoned")[entry];
+        step.duration_ms = i64::try_from(elapsed.as_millis()).unwrap_or(i64::MAX);
+        step.error = error;
+    }
+
+    pub fn into_trace(self, vars: &HashMap<String, String>) -> RunTrace {
+        RunTrace {
+            steps: self.0.into_inner().expect("trace lock poisoned"),
+            ..RunTrace::with_vars(vars)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn step(yaml: &str) -> Step {
+        serde_yaml::from_str(yaml).expect("step yaml")
+    }
+
+    #[test]
+    fn nested_steps_follow_their_parent_in_start_order() {
+        let tracer = Tracer::default();
+        let scene = step("type: scene\nrun: []");
+        let delay = step("type: delay\nseconds: 5");
+
+        let parent = tracer.begin(&scene, &[0], 0, false);
+        let child = tracer.begin(&delay, &[0, 0], 0, false);
+        tracer.finish(child, Duration::from_millis(5), None);
+        tracer.finish(parent, Duration::from_millis(7), Some("boom".to_owned()));
+
+        let trace = tracer.into_trace(&HashMap::new());
+        let kinds: Vec<(&str, &[u32])> = trace
+            .steps
+            .iter()
+            .map(|s| (s.kind.as_str(), s.path.as_slice()))
+            .collect();
+        assert_eq!(kinds, vec![("scene", &[0][..]), ("delay", &[0, 0][..])]);
+        assert_eq!(trace.steps[0].duration_ms, 7);
+        assert_eq!(trace.steps[0].error.as_deref(), Some("boom"));
+        assert_eq!(trace.steps[1].detail.as_deref(), Some("delay 5s"));
+    }
+
+    #[test]
+    fn a_guard_records_its_expression_and_result() {
+        let tracer = Tracer::default();
+        let guarded = step(
+            "type: run_workflow\nworkflow: evening\nwhen: { type: mode, mode: guest, active: true }",
+        );
+
+        let entry = tracer.begin(&guarded, &[1], 1, true);
+        tracer.guard(entry, false);
+
+        let trace = tracer.into_trace(&HashMap::from([
+            ("sensor".to_owned(), "hall".to_owned()),
+            ("present".to_owned(), "true".to_owned()),
+        ]));
+        let step = &trace.steps[0];
+        assert_eq!(step.detail.as_deref(), Some("evening"));
+        assert!(step.guard.is_some());
+        assert_eq!(step.guard_passed, Some(false));
+        assert!(step.dry_run);
+        assert_eq!(step.depth, 1);
+        assert_eq!(
+            trace
+                .vars
+                .iter()
+                .map(|v| v.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["present", "sensor"]
+        );
+    }
+}
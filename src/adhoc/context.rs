@@ -9,8 +9,8 @@ use crate::state::SharedActorState;
 
 pub struct AdhocTaskContext<'a> {
     pub tx: &'a mut Transaction<'static, Postgres>,
-    pub devices: &'a DeviceRegistry,
-    pub settings: &'a SettingsContainer,
+    pub devices: DeviceRegistry,
+    pub settings: SettingsContainer,
     pub s3: &'a S3,
     pub home_assistant: Option<&'a HomeAssistant>,
     pub jellyfin: Option<&'a Jellyfin>,
@@ -20,8 +20,8 @@ impl<'a> AdhocTaskContext<'a> {
     pub fn new(state: &'a SharedActorState, tx: &'a mut Transaction<'static, Postgres>) -> Self {
         Self {
             tx,
-            devices: &state.devices,
-            settings: &state.settings,
+            devices: state.devices(),
+            settings: state.settings(),
             s3: &state.s3,
             home_assistant: state.home_assistant.as_ref(),
             jellyfin: state.jellyfin.as_ref(),
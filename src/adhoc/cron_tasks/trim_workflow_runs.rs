@@ -29,6 +29,18 @@ impl AdhocCronTask for TrimWorkflowRuns {
 async fn trim(tx: &mut Transaction<'static, Postgres>) -> Result<u64, sqlx::Error> {
     let mut deleted = 0;
 
+    // step traces are the bulk of a run row and only useful while a run is
+    // fresh, so they go well before the row itself
+    sqlx::query!(
+        r#"
+        UPDATE workflow_runs
+        SET trace = NULL
+        WHERE trace IS NOT NULL AND started_at < now() - INTERVAL '14 days'
+        "#,
+    )
+    .execute(&mut **tx)
+    .await?;
+
     loop {
         let batch = sqlx::query!(
             r#"
@@ -1,10 +1,10 @@
 use async_graphql::{Schema, dataloader::DataLoader};
 use axum::{
     Router,
-    extract::Request,
+    extract::{DefaultBodyLimit, Request},
     middleware::{Next, from_fn, from_fn_with_state},
     response::Response,
-    routing::{delete, get, post},
+    routing::{delete, get, post, put},
 };
 use axum_tracing_opentelemetry::middleware::OtelAxumLayer;
 use http::Method;
@@ -29,18 +29,25 @@ use crate::graphql::{
 };
 use crate::routes::{
     self,
-    admin::keys::{create_key, list_keys, regenerate_key, revoke_key, update_key},
+    admin::{
+        config::reload_config,
+        keys::{create_key, list_keys, regenerate_key, revoke_key, update_key},
+    },
     control::light::light_control,
     epd,
     health::{actor_health, health},
     ingest::{
-        home::{alarm::alarm, push_token::push_token},
+        home::{alarm::alarm, notification_action::notification_action, push_token::push_token},
         synergy::synergy,
         unifi::unifi,
     },
     push::notify as push_notify,
     schema::schema as schema_route,
-    workflow::execute::workflow_execute,
+    trmnl,
+    workflow::{
+        execute::workflow_execute,
+        run::{workflow_run, workflow_run_status},
+    },
 };
 use crate::state::{ApiState, SharedActorState};
 
@@ -128,8 +135,7 @@ pub fn build_schema(state: &SharedActorState, http_client: ClientWithMiddleware)
     .data(http_client)
     .data(state.mqtt.clone())
     .data(state.db.clone())
-    .data(state.settings.clone())
-    .data(state.devices.clone())
+    .data(state.config.clone())
     .data(state.feature_flag_client.clone())
     .data(state.event_bus.clone())
     .data(state.workflows.clone())
@@ -146,28 +152,59 @@ pub fn build_router(api_state: ApiState, metrics_registry: Registry) -> Router {
         .allow_headers(AllowHeaders::any());
 
     let api_routes = Router::new()
-        .route("/graphql", get(graphiql).post(graphql_handler))
+        .route(
+            "/graphql",
+            get(graphiql)
+                .post(graphql_handler)
+                .layer(DefaultBodyLimit::max(epd::album::MAX_UPLOAD_BYTES)),
+        )
         .route("/schema", get(schema_route))
         .route("/control/light", post(light_control))
         .route("/workflow/execute", post(workflow_execute))
+        .route("/workflow/{slug}/run", post(workflow_run))
+        .route("/workflow/runs/{run_id}", get(workflow_run_status))
         .route("/ingest/synergy", post(synergy))
         .route("/epd/config", post(epd::config))
         .route("/epd/image/{hash}", get(epd::image))
+        .route("/epd/frames/{hash}", get(epd::frame_preview))
         .route("/epd/firmware", get(epd::firmware))
         .route("/epd/take-screenshot", post(epd::take_screenshot))
+        .route(
+            "/epd/albums/{album}/items",
+            get(epd::album::list)
+                .post(epd::album::upload)
+                .layer(DefaultBodyLimit::max(epd::album::MAX_UPLOAD_BYTES)),
+        )
+        .route(
+            "/epd/albums/{album}/items/{*name}",
+            delete(epd::album::delete),
+        )
+        .route(
+            "/epd/albums/{album}/pins/{*name}",
+            put(epd::album::pin).delete(epd::album::unpin),
+        )
         .route("/push/notify", post(push_notify))
         .route("/ingest/home/alarm", post(alarm))
         .route("/ingest/home/push-token", post(push_token))
+        .route(
+            "/ingest/home/notification-action",
+            post(notification_action),
+        )
         .route("/ingest/unifi", post(unifi))
         .route("/admin/keys", post(create_key).get(list_keys))
         .route("/admin/keys/{id}", delete(revoke_key).patch(update_key))
         .route("/admin/keys/{id}/regenerate", post(regenerate_key))
+        .route("/admin/config/reload", post(reload_config))
         .route_layer(from_fn_with_state(api_state.clone(), auth_middleware))
         .layer(OtelAxumLayer::default())
         .route("/solar/current", get(routes::solar::current))
         .route("/solar/history", get(routes::solar::history))
         .route("/solar/history/range", get(routes::solar::history_since))
         .route("/solar/health", get(routes::solar::health))
+        .route("/trmnl/api/setup", get(trmnl::setup))
+        .route("/trmnl/api/display", get(trmnl::display))
+        .route("/trmnl/api/log", post(trmnl::log))
+        .route("/trmnl/image/{file}", get(trmnl::image))
         .route("/graphql/ws", get(graphql_ws_handler))
         .route("/health", get(health))
         .route("/health/actors", get(actor_health))
@@ -47,6 +47,12 @@ impl AuthContext {
         self.scopes.iter().any(|s| s.matches(required))
     }
 
+    /// [`has`](Self::has) for a workflow, where a `:<tag>` scope counts
+    /// when the workflow carries that tag.
+    pub fn has_for_tags(&self, required: &Scope, tags: &[String]) -> bool {
+        self.scopes.iter().any(|s| s.matches_tagged(required, tags))
+    }
+
     pub fn require(&self, required: &Scope) -> Result<(), StatusCode> {
         if self.has(required) {
             Ok(())
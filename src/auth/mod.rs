@@ -33,7 +33,7 @@ async fn resolve_api_key(
     api_key: &str,
     state: &ApiState,
 ) -> Result<Option<AuthContext>, StatusCode> {
-    let settings = &state.settings;
+    let settings = state.settings();
 
     if !settings.api_key.is_empty() && api_key == settings.api_key {
         return Ok(Some(AuthContext::full_access(true)));
@@ -92,6 +92,25 @@ pub async fn resolve_ws_auth(
     Err(StatusCode::UNAUTHORIZED)
 }
 
+/// For devices whose firmware sends an api key in a header of its own, like
+/// the TRMNL `Access-Token`, rather than `X-Api-Key`.
+pub async fn resolve_device_token(
+    token: Option<&str>,
+    state: &ApiState,
+) -> Result<AuthContext, StatusCode> {
+    if dev_bypass_enabled() {
+        return Ok(AuthContext::full_access(false));
+    }
+
+    let Some(token) = token.map(|s| s.trim()).filter(|s| !s.is_empty()) else {
+        return Err(StatusCode::UNAUTHORIZED);
+    };
+
+    resolve_api_key(token, state)
+        .await?
+        .ok_or(StatusCode::UNAUTHORIZED)
+}
+
 pub async fn resolve_auth(
     headers: &HeaderMap,
     state: &ApiState,
@@ -100,7 +119,7 @@ pub async fn resolve_auth(
         return Ok(AuthContext::full_access(false));
     }
 
-    let settings = &state.settings;
+    let settings = state.settings();
 
     let api_key = headers
         .get("X-Api-Key")
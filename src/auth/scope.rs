@@ -38,7 +38,11 @@ pub enum Resource {
     RobotVacuum,
     Jellyfin,
     MediaPlayer,
+    SmartSwitch,
+    Plant,
     AdhocTask,
+    Config,
+    Variable,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -93,7 +97,11 @@ impl Resource {
             "robot_vacuum" => Self::RobotVacuum,
             "jellyfin" => Self::Jellyfin,
             "media_player" => Self::MediaPlayer,
+            "smart_switch" => Self::SmartSwitch,
+            "plant" => Self::Plant,
             "adhoc_task" => Self::AdhocTask,
+            "config" => Self::Config,
+            "variable" => Self::Variable,
             _ => return None,
         })
     }
@@ -154,7 +162,11 @@ impl Resource {
             Self::RobotVacuum => "robot_vacuum",
             Self::Jellyfin => "jellyfin",
             Self::MediaPlayer => "media_player",
+            Self::SmartSwitch => "smart_switch",
+            Self::Plant => "plant",
             Self::AdhocTask => "adhoc_task",
+            Self::Config => "config",
+            Self::Variable => "variable",
         }
     }
 
@@ -175,6 +187,10 @@ impl Resource {
             "jellyfin" => Self::Jellyfin,
             "media_player" => Self::MediaPlayer,
             "solar" => Self::Solar,
+            "variable" => Self::Variable,
+            "robot_vacuum" => Self::RobotVacuum,
+            "device_availability" => Self::Entity,
+            "notification_action" => Self::Push,
             _ => return None,
         })
     }
@@ -239,13 +255,16 @@ impl Scope {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ScopePattern {
     Global,
     Parts {
         domain: Segment<Domain>,
         resource: Segment<Resource>,
         action: Segment<Action>,
+        /// A fourth `:<tag>` segment, only on workflow scopes, limiting the
+        /// pattern to workflows carrying that tag.
+        tag: Option<String>,
     },
 }
 
@@ -258,30 +277,47 @@ impl ScopePattern {
 
         let mut segments = raw.split(':');
         let domain = segments.next()?;
-        let resource = segments.next()?;
+        let resource = Segment::parse(segments.next()?, Resource::from_segment)?;
         let action = segments.next()?;
+        let tag = match segments.next() {
+            Some(tag) if resource == Segment::Exact(Resource::Workflow) && !tag.is_empty() => {
+                Some(tag.to_owned())
+            }
+            Some(_) => return None,
+            None => None,
+        };
         if segments.next().is_some() {
             return None;
         }
 
         Some(ScopePattern::Parts {
             domain: Segment::parse(domain, Domain::from_segment)?,
-            resource: Segment::parse(resource, Resource::from_segment)?,
+            resource,
             action: Segment::parse(action, Action::from_segment)?,
+            tag,
         })
     }
 
+    /// A tag-limited pattern never matches here, only through
+    /// [`matches_tagged`](Self::matches_tagged) for a workflow it covers.
     pub fn matches(&self, required: &Scope) -> bool {
+        self.matches_tagged(required, &[])
+    }
+
+    /// Whether the pattern grants `required` for a workflow tagged `tags`.
+    pub fn matches_tagged(&self, required: &Scope, tags: &[String]) -> bool {
         match self {
             ScopePattern::Global => true,
             ScopePattern::Parts {
                 domain,
                 resource,
                 action,
+                tag,
             } => {
                 domain.matches(&required.domain)
                     && resource.matches(&required.resource)
                     && action.matches(&required.action)
+                    && tag.as_ref().is_none_or(|tag| tags.contains(tag))
             }
         }
     }
@@ -295,13 +331,20 @@ impl std::fmt::Display for ScopePattern {
                 domain,
                 resource,
                 action,
-            } => write!(
-                f,
-                "{}:{}:{}",
-                domain.as_str(Domain::as_str),
-                resource.as_str(Resource::as_str),
-                action.as_str(Action::as_str)
-            ),
+                tag,
+            } => {
+                write!(
+                    f,
+                    "{}:{}:{}",
+                    domain.as_str(Domain::as_str),
+                    resource.as_str(Resource::as_str),
+                    action.as_str(Action::as_str)
+                )?;
+                match tag {
+                    Some(tag) => write!(f, ":{tag}"),
+                    None => Ok(()),
+                }
+            }
         }
     }
 }
@@ -339,6 +382,15 @@ pub mod required {
         Scope::new(Domain::Graphql, Resource::AdhocTask, Action::Read);
     pub const GRAPHQL_MEDIA_PLAYER_READ: Scope =
         Scope::new(Domain::Graphql, Resource::MediaPlayer, Action::Read);
+    pub const GRAPHQL_SMART_SWITCH_READ: Scope =
+        Scope::new(Domain::Graphql, Resource::SmartSwitch, Action::Read);
+    pub const GRAPHQL_PLANT_READ: Scope =
+        Scope::new(Domain::Graphql, Resource::Plant, Action::Read);
+    pub const GRAPHQL_SWITCH_READ: Scope =
+        Scope::new(Domain::Graphql, Resource::Switch, Action::Read);
+    pub const GRAPHQL_VARIABLE_READ: Scope =
+        Scope::new(Domain::Graphql, Resource::Variable, Action::Read);
+    pub const GRAPHQL_PUSH_READ: Scope = Scope::new(Domain::Graphql, Resource::Push, Action::Read);
 
     pub const GRAPHQL_LIGHT_WRITE: Scope =
         Scope::new(Domain::Graphql, Resource::Light, Action::Write);
@@ -346,17 +398,27 @@ pub mod required {
         Scope::new(Domain::Graphql, Resource::RobotVacuum, Action::Write);
     pub const GRAPHQL_MEDIA_PLAYER_WRITE: Scope =
         Scope::new(Domain::Graphql, Resource::MediaPlayer, Action::Write);
+    pub const GRAPHQL_SMART_SWITCH_WRITE: Scope =
+        Scope::new(Domain::Graphql, Resource::SmartSwitch, Action::Write);
     pub const GRAPHQL_WORKFLOW_WRITE: Scope =
         Scope::new(Domain::Graphql, Resource::Workflow, Action::Write);
     pub const GRAPHQL_EPD_WRITE: Scope = Scope::new(Domain::Graphql, Resource::Epd, Action::Write);
+    pub const GRAPHQL_CONFIG_WRITE: Scope =
+        Scope::new(Domain::Graphql, Resource::Config, Action::Write);
+    pub const GRAPHQL_VARIABLE_WRITE: Scope =
+        Scope::new(Domain::Graphql, Resource::Variable, Action::Write);
 
     pub const GRAPHQL_ADHOC_TASK_EXECUTE: Scope =
         Scope::new(Domain::Graphql, Resource::AdhocTask, Action::Execute);
+    pub const GRAPHQL_WORKFLOW_EXECUTE: Scope =
+        Scope::new(Domain::Graphql, Resource::Workflow, Action::Execute);
 
     pub const REST_CONTROL_WRITE: Scope =
         Scope::new(Domain::Rest, Resource::Control, Action::Write);
     pub const REST_WORKFLOW_EXECUTE: Scope =
         Scope::new(Domain::Rest, Resource::Workflow, Action::Execute);
+    pub const REST_WORKFLOW_READ: Scope =
+        Scope::new(Domain::Rest, Resource::Workflow, Action::Read);
     pub const REST_PUSH_WRITE: Scope = Scope::new(Domain::Rest, Resource::Push, Action::Write);
     pub const REST_EPD_READ: Scope = Scope::new(Domain::Rest, Resource::Epd, Action::Read);
     pub const REST_EPD_WRITE: Scope = Scope::new(Domain::Rest, Resource::Epd, Action::Write);
@@ -370,6 +432,8 @@ pub mod required {
 
     pub const ADMIN_KEYS_READ: Scope = Scope::new(Domain::Admin, Resource::Keys, Action::Read);
     pub const ADMIN_KEYS_WRITE: Scope = Scope::new(Domain::Admin, Resource::Keys, Action::Write);
+    pub const ADMIN_CONFIG_WRITE: Scope =
+        Scope::new(Domain::Admin, Resource::Config, Action::Write);
 }
 
 #[cfg(test)]
@@ -429,5 +493,23 @@ mod tests {
         assert!(ScopePattern::parse("bogus:solar:read").is_none());
         assert!(ScopePattern::parse("graphql:bogus:read").is_none());
         assert!(ScopePattern::parse("graphql:solar:bogus").is_none());
+        assert!(ScopePattern::parse("rest:workflow:execute:").is_none());
+        assert!(ScopePattern::parse("rest:workflow:execute:lounge:extra").is_none());
+        assert!(ScopePattern::parse("rest:*:execute:lounge").is_none());
+    }
+
+    #[test]
+    fn a_tagged_workflow_scope_covers_only_that_tag() {
+        let tagged = ScopePattern::parse("rest:workflow:execute:living-room").unwrap();
+        let tags = ["living-room".to_owned(), "lights".to_owned()];
+
+        assert!(tagged.matches_tagged(&REST_WORKFLOW_EXECUTE, &tags));
+        assert!(!tagged.matches_tagged(&REST_WORKFLOW_EXECUTE, &["bedroom".to_owned()]));
+        assert!(!tagged.matches(&REST_WORKFLOW_EXECUTE));
+        assert_eq!(tagged.to_string(), "rest:workflow:execute:living-room");
+
+        let untagged = ScopePattern::parse("rest:workflow:execute").unwrap();
+        assert!(untagged.matches_tagged(&REST_WORKFLOW_EXECUTE, &tags));
+        assert!(untagged.matches_tagged(&REST_WORKFLOW_EXECUTE, &[]));
     }
 }
@@ -10,14 +10,15 @@ use crate::integrations::esphome::{
 };
 use crate::settings::door::RawDoorSettings;
 use crate::settings::notify::NotifyTargets;
+use crate::settings::trmnl::normalize_address;
 use crate::settings::{
     BatterySettings, DeviceAliases, DeviceWatchdog, DoorSettings, EinkDisplaySettings,
     EnvironmentSensorSettings, EnvironmentSensorType, IEEEAddress, MediaPlayerSettings,
     PlantSensorSettings, PresenceSensorType, PresenceSettings, RawDeviceWatchdog,
     RawEinkDisplayBlock, RawEnvironmentBlock, RawLightBlock, RawMediaPlayerBlock, RawPlantBlock,
     RawPresenceBlock, RawRoborockBlock, RawSmartSwitchBlock, RawTrmnlBlock, RawValetudoBlock,
-    RawZigbeeModelProfile, RoborockField, RoborockSettings, SwitchRole, TrmnlDeviceSettings,
-    ValetudoSettings, ZigbeeModelProfile,
+    RawZigbeeModelProfile, RoborockField, RoborockSettings, SmartSwitchSettings, SwitchControl,
+    SwitchRole, TrmnlDeviceSettings, ValetudoSettings, ZigbeeModelProfile,
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
@@ -144,7 +145,7 @@ pub struct DeviceRegistryInner {
     esphome_topics: HashMap<String, EsphomeTarget>,
     zigbee_devices: HashMap<String, ZigbeeDevice>,
     doors: HashMap<String, DoorSettings>,
-    smart_switches: HashMap<String, String>,
+    smart_switches: HashMap<String, SmartSwitchSettings>,
     control_switches: HashSet<String>,
     environment: HashMap<String, EnvironmentSensorSettings>,
     presence: HashMap<String, PresenceSettings>,
@@ -155,13 +156,15 @@ pub struct DeviceRegistryInner {
     plant: HashMap<String, PlantSensorSettings>,
     eink_displays: HashMap<String, EinkDisplaySettings>,
     trmnl_devices: HashMap<String, TrmnlDeviceSettings>,
+    /// Self-served (`byos`) TRMNLs, by device id, rendered like any other
+    /// eink display.
+    trmnl_displays: HashMap<String, EinkDisplaySettings>,
     roborocks: HashMap<String, RoborockSettings>,
     roborock_entities: HashMap<String, (String, RoborockField)>,
     media_players: HashMap<String, MediaPlayerSettings>,
     valetudos: HashMap<String, ValetudoSettings>,
     battery: HashMap<String, BatterySettings>,
     watchdog: HashMap<String, DeviceWatchdog>,
-    known_devices: RwLock<HashMap<IEEEAddress, String>>,
 }
 
 /// Cheap to clone: the resolved device data lives behind a shared `Arc`, so
@@ -170,6 +173,9 @@ pub struct DeviceRegistryInner {
 #[derive(Debug, Clone, Default)]
 pub struct DeviceRegistry {
     inner: Arc<DeviceRegistryInner>,
+    /// Friendly names learned from the bridge at runtime rather than config,
+    /// kept apart from `inner` so they survive a config reload.
+    known_devices: Arc<RwLock<HashMap<IEEEAddress, String>>>,
 }
 
 impl std::ops::Deref for DeviceRegistry {
@@ -254,8 +260,33 @@ impl DeviceRegistry {
 
         Ok(Self {
             inner: Arc::new(reg),
+            known_devices: Default::default(),
         })
     }
+
+    /// Share the friendly names already learned by `previous`, so a rebuilt
+    /// registry doesn't forget them until the bridge next announces.
+    pub fn keep_friendly_names(mut self, previous: &DeviceRegistry) -> Self {
+        self.known_devices = previous.known_devices.clone();
+        self
+    }
+
+    pub async fn record_friendly_name(&self, address: IEEEAddress, name: String) {
+        self.known_devices.write().await.insert(address, name);
+    }
+
+    pub async fn friendly_name(&self, address: &str) -> Option<String> {
+        self.known_devices.read().await.get(address).cloned()
+    }
+
+    pub async fn address_for_friendly_name(&self, name: &str) -> Option<String> {
+        self.known_devices
+            .read()
+            .await
+            .iter()
+            .find(|(_, known)| known.as_str() == name)
+            .map(|(address, _)| address.clone())
+    }
 }
 
 fn validate_zigbee_roles(
@@ -357,13 +388,20 @@ impl DeviceRegistryInner {
                 "device {id}: `trmnl` transport is only valid with the `trmnl` kind, and vice versa"
             ));
         }
-        let is_home_assistant = matches!(
+        let home_assistant_only = matches!(
             config,
             DeviceConfig::Roborock(_) | DeviceConfig::MediaPlayer(_)
         );
-        if (transport == Transport::HomeAssistant) != is_home_assistant {
+        let home_assistant_capable =
+            home_assistant_only || matches!(config, DeviceConfig::SmartSwitch(_));
+        if transport == Transport::HomeAssistant && !home_assistant_capable {
+            return Err(format!(
+                "device {id}: `home_assistant` transport is only valid with the `roborock`, `media_player` and `smart_switch` kinds"
+            ));
+        }
+        if transport != Transport::HomeAssistant && home_assistant_only {
             return Err(format!(
-                "device {id}: `home_assistant` transport is only valid with the `roborock` and `media_player` kinds, and vice versa"
+                "device {id}: the `roborock` and `media_player` kinds require the `home_assistant` transport"
             ));
         }
         let is_valetudo = matches!(config, DeviceConfig::Valetudo(_));
@@ -456,8 +494,37 @@ impl DeviceRegistryInner {
                 self.lights.insert(address.to_owned(), light.name);
             }
             DeviceConfig::SmartSwitch(switch) => {
-                self.smart_switches
-                    .insert(address.to_owned(), switch.name.clone());
+                let control = match transport {
+                    Transport::Zigbee => SwitchControl::Zigbee,
+                    Transport::HomeAssistant => {
+                        let Some((domain, _)) = address.split_once('.') else {
+                            return Err(format!(
+                                "device {id}: `smart_switch` address `{address}` must be a home assistant entity id"
+                            ));
+                        };
+                        if switch.role.is_some() {
+                            return Err(format!(
+                                "device {id}: a home assistant `smart_switch` cannot be declared `as:` another kind"
+                            ));
+                        }
+                        SwitchControl::HomeAssistant {
+                            domain: domain.to_owned(),
+                        }
+                    }
+                    _ => {
+                        return Err(format!(
+                            "device {id}: `smart_switch` is only valid with the `zigbee` and `home_assistant` transports"
+                        ));
+                    }
+                };
+
+                self.smart_switches.insert(
+                    address.to_owned(),
+                    SmartSwitchSettings {
+                        name: switch.name.clone(),
+                        control,
+                    },
+                );
                 if switch.role == Some(SwitchRole::Light) {
                     self.lights.insert(address.to_owned(), switch.name);
                 }
@@ -470,11 +537,21 @@ impl DeviceRegistryInner {
                     .insert(address.to_owned(), display.resolve(id)?);
             }
             DeviceConfig::Trmnl(trmnl) => {
+                let byos = match trmnl.byos {
+                    Some(byos) => {
+                        let (display, byos) = byos.resolve(id, &trmnl.name, address)?;
+                        self.trmnl_displays.insert(id.to_owned(), display);
+                        Some(byos)
+                    }
+                    None => None,
+                };
+
                 self.trmnl_devices.insert(
                     address.to_owned(),
                     TrmnlDeviceSettings {
                         id: id.to_owned(),
                         name: trmnl.name,
+                        byos,
                     },
                 );
             }
@@ -516,7 +593,9 @@ impl DeviceRegistryInner {
     }
 
     pub fn eink_display(&self, id: &str) -> Option<&EinkDisplaySettings> {
-        self.eink_displays.get(id)
+        self.eink_displays
+            .get(id)
+            .or_else(|| self.trmnl_displays.get(id))
     }
 
     pub fn eink_displays(&self) -> &HashMap<String, EinkDisplaySettings> {
@@ -527,6 +606,18 @@ impl DeviceRegistryInner {
         &self.trmnl_devices
     }
 
+    pub fn trmnl_displays(&self) -> &HashMap<String, EinkDisplaySettings> {
+        &self.trmnl_displays
+    }
+
+    /// The self-served TRMNL sending `mac` in its `ID` header.
+    pub fn byos_trmnl(&self, mac: &str) -> Option<&TrmnlDeviceSettings> {
+        let mac = normalize_address(mac);
+        self.trmnl_devices
+            .values()
+            .find(|trmnl| trmnl.byos.as_ref().is_some_and(|byos| byos.mac == mac))
+    }
+
     pub fn roborock(&self, address: &str) -> Option<&RoborockSettings> {
         self.roborocks.get(address)
     }
@@ -591,23 +682,6 @@ impl DeviceRegistryInner {
             .map(|(id, _)| id.as_str())
     }
 
-    pub async fn record_friendly_name(&self, address: IEEEAddress, name: String) {
-        self.known_devices.write().await.insert(address, name);
-    }
-
-    pub async fn friendly_name(&self, address: &str) -> Option<String> {
-        self.known_devices.read().await.get(address).cloned()
-    }
-
-    pub async fn address_for_friendly_name(&self, name: &str) -> Option<String> {
-        self.known_devices
-            .read()
-            .await
-            .iter()
-            .find(|(_, known)| known.as_str() == name)
-            .map(|(address, _)| address.clone())
-    }
-
     pub fn zigbee_device(&self, address: &str) -> Option<&ZigbeeDevice> {
         self.zigbee_devices.get(address)
     }
@@ -638,17 +712,16 @@ impl DeviceRegistryInner {
         self.doors.get(address)
     }
 
-    #[allow(unused)]
     pub fn control_switch(&self, address: &str) -> bool {
         self.control_switches.contains(address)
     }
 
-    pub fn smart_switch(&self, address: &str) -> Option<&String> {
+    pub fn smart_switch(&self, address: &str) -> Option<&SmartSwitchSettings> {
         self.smart_switches.get(address)
     }
 
-    #[allow(unused)]
-    pub fn smart_switches(&self) -> impl Iterator<Item = (&String, &String)> {
+    /// Every configured smart switch, keyed by address, for entity enumeration.
+    pub fn smart_switches(&self) -> impl Iterator<Item = (&String, &SmartSwitchSettings)> {
         self.smart_switches.iter()
     }
 
@@ -665,6 +738,16 @@ impl DeviceRegistryInner {
         self.plant.get(address)
     }
 
+    /// Every configured plant sensor, keyed by esphome node, for entity enumeration.
+    pub fn plants(&self) -> impl Iterator<Item = (&String, &PlantSensorSettings)> {
+        self.plant.iter()
+    }
+
+    /// Every configured control switch's address, for entity enumeration.
+    pub fn control_switches(&self) -> impl Iterator<Item = &String> {
+        self.control_switches.iter()
+    }
+
     pub fn battery(&self, address: &str) -> Option<&BatterySettings> {
         self.battery.get(address)
     }
@@ -685,6 +768,23 @@ impl DeviceRegistryInner {
         self.rooms.get(address).map(String::as_str)
     }
 
+    /// Every device placed in a room, as `(address, room)`.
+    pub fn device_rooms(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.rooms.iter()
+    }
+
+    /// Addresses of the devices placed in `room`.
+    pub fn room_members<'a>(&'a self, room: &'a str) -> impl Iterator<Item = &'a String> {
+        self.rooms
+            .iter()
+            .filter(move |(_, r)| r.as_str() == room)
+            .map(|(address, _)| address)
+    }
+
+    pub fn watchdog(&self, address: &str) -> Option<&DeviceWatchdog> {
+        self.watchdog.get(address)
+    }
+
     pub fn watchdog_devices(&self) -> impl Iterator<Item = (&String, &DeviceWatchdog)> {
         self.watchdog.iter()
     }
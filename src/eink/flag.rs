@@ -2,7 +2,7 @@ use crate::{
     actors::system::cron::schedule::CronSchedule,
     device_registry::DeviceRegistry,
     integrations::feature_flag::FeatureFlagClient,
-    settings::{EinkMode, PaletteColor, RedditTimespan},
+    settings::{ColorSpace, DitherAlgorithm, DitherConfig, EinkMode, PaletteColor, RedditTimespan},
 };
 use open_feature::EvaluationContext;
 
@@ -16,6 +16,7 @@ pub struct EpdFlagConfig {
     pub refresh: Option<CronSchedule>,
     pub mode: Option<EinkMode>,
     pub dashboard_view: Option<String>,
+    pub layout: Option<String>,
     pub album: Option<String>,
     pub subreddit: Option<String>,
     pub timespan: Option<RedditTimespan>,
@@ -56,12 +57,14 @@ impl From<open_feature::StructValue> for EpdFlagConfig {
                 "dashboard" => Some(EinkMode::Dashboard),
                 "album" => Some(EinkMode::Album),
                 "reddit" => Some(EinkMode::Reddit),
+                "native" => Some(EinkMode::Native),
                 other => {
                     tracing::warn!("unknown epd mode `{other}` in flag, ignoring");
                     None
                 }
             }),
             dashboard_view: string_field("dashboard_view"),
+            layout: string_field("layout"),
             album: string_field("album"),
             subreddit: string_field("subreddit"),
             timespan: string_field("timespan").and_then(|s| match s.as_str() {
@@ -124,12 +127,57 @@ pub async fn epd_flag_config(
     }
 }
 
-pub async fn epd_palette(
+/// What the dithering flag sets for a display: calibrated palette colours,
+/// and dithering that beats the display's and its mode's config.
+#[derive(Debug, Clone, Default)]
+pub struct EpdDithering {
+    pub palette: Vec<(f32, f32, f32, u8)>,
+    pub dither: DitherConfig,
+}
+
+fn dither_config(value: &open_feature::StructValue) -> DitherConfig {
+    let string_field = |key: &str| value.fields.get(key).and_then(|v| v.as_str());
+    let number_field = |key: &str| {
+        value
+            .fields
+            .get(key)
+            .and_then(|v| v.as_f64().or_else(|| v.as_i64().map(|n| n as f64)))
+            .map(|n| n as f32)
+    };
+
+    DitherConfig {
+        algorithm: string_field("algorithm").and_then(|s| match s {
+            "floyd_steinberg" => Some(DitherAlgorithm::FloydSteinberg),
+            "atkinson" => Some(DitherAlgorithm::Atkinson),
+            "stucki" => Some(DitherAlgorithm::Stucki),
+            "jarvis_judice_ninke" => Some(DitherAlgorithm::JarvisJudiceNinke),
+            "bayer" => Some(DitherAlgorithm::Bayer),
+            "blue_noise" => Some(DitherAlgorithm::BlueNoise),
+            other => {
+                tracing::warn!("unknown dither algorithm `{other}` in flag, ignoring");
+                None
+            }
+        }),
+        color_space: string_field("color_space").and_then(|s| match s {
+            "rgb" => Some(ColorSpace::Rgb),
+            "cielab" => Some(ColorSpace::Cielab),
+            "oklab" => Some(ColorSpace::Oklab),
+            other => {
+                tracing::warn!("unknown color space `{other}` in flag, ignoring");
+                None
+            }
+        }),
+        contrast: number_field("contrast"),
+        saturation: number_field("saturation"),
+    }
+}
+
+pub async fn epd_dithering(
     feature_flag_client: &FeatureFlagClient,
     devices: &DeviceRegistry,
     base: &[PaletteColor],
     device_id: &str,
-) -> Vec<(f32, f32, f32, u8)> {
+) -> EpdDithering {
     let config = feature_flag_client
         .get_struct(
             EPD_DITHERING_CONFIG_FLAG,
@@ -138,7 +186,8 @@ pub async fn epd_palette(
         .await
         .ok();
 
-    base.iter()
+    let palette = base
+        .iter()
         .map(|color| {
             let (r, g, b) = config
                 .as_ref()
@@ -165,5 +214,10 @@ pub async fn epd_palette(
                 .unwrap_or((color.r, color.g, color.b));
             (r, g, b, color.index)
         })
-        .collect()
+        .collect();
+
+    EpdDithering {
+        palette,
+        dither: config.as_ref().map(dither_config).unwrap_or_default(),
+    }
 }
@@ -1,5 +1,8 @@
 use sha2::{Digest, Sha256};
 
+/// Face for text drawn onto frames: the sleep label and native dashboards.
+pub(crate) const FONT: &[u8] = include_bytes!("../../assets/LiberationSans-Bold.ttf");
+
 pub fn content_hash(bytes: &[u8]) -> String {
     let mut hasher = Sha256::new();
     hasher.update(bytes);
@@ -36,6 +39,20 @@ pub fn center_crop_cover(img: &image::RgbImage, target_w: u32, target_h: u32) ->
     image::imageops::crop_imm(&scaled, x, y, target_w, target_h).to_image()
 }
 
+/// The largest centred window of `img` in the `target_w`:`target_h` aspect
+/// ratio, at the source's own resolution.
+pub fn crop_to_aspect(img: &image::RgbImage, target_w: u32, target_h: u32) -> image::RgbImage {
+    let (w, h) = img.dimensions();
+    let (crop_w, crop_h) = if w as u64 * target_h as u64 > h as u64 * target_w as u64 {
+        ((h as u64 * target_w as u64 / target_h as u64) as u32, h)
+    } else {
+        (w, (w as u64 * target_h as u64 / target_w as u64) as u32)
+    };
+    let (crop_w, crop_h) = (crop_w.max(1), crop_h.max(1));
+
+    image::imageops::crop_imm(img, (w - crop_w) / 2, (h - crop_h) / 2, crop_w, crop_h).to_image()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -50,6 +67,15 @@ mod tests {
         assert_eq!(out.dimensions(), (1600, 1200));
     }
 
+    #[test]
+    fn crop_to_aspect_keeps_the_source_resolution() {
+        let src = image::RgbImage::from_pixel(800, 400, image::Rgb([10, 20, 30]));
+
+        assert_eq!(crop_to_aspect(&src, 1200, 1600).dimensions(), (300, 400));
+        assert_eq!(crop_to_aspect(&src, 1600, 1200).dimensions(), (533, 400));
+        assert_eq!(crop_to_aspect(&src, 1600, 400).dimensions(), (800, 200));
+    }
+
     #[test]
     fn content_hash_is_stable() {
         assert_eq!(content_hash(b"hello"), content_hash(b"hello"));
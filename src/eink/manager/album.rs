@@ -0,0 +1,211 @@
+use super::EinkDisplayManager;
+use super::sources::CACHE_PREFIX;
+use crate::eink::image::content_hash;
+use crate::eink::upload::{self, UploadError};
+use crate::settings::Album;
+use serde::Serialize;
+use sqlx::{Pool, Postgres};
+use std::collections::{HashMap, HashSet};
+
+/// Enough of the content hash to name an upload; the same photo uploaded
+/// twice lands on the same key.
+const UPLOAD_NAME_LEN: usize = 16;
+
+#[derive(thiserror::Error, Debug)]
+pub enum AlbumError {
+    #[error("album `{0}` is not configured")]
+    UnknownAlbum(String),
+    #[error("album `{album}` has no `{name}`")]
+    UnknownItem { album: String, name: String },
+    #[error("display `{0}` is not registered")]
+    UnknownDisplay(String),
+    #[error(transparent)]
+    Upload(#[from] UploadError),
+    #[error(transparent)]
+    Storage(#[from] anyhow::Error),
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+}
+
+#[derive(Debug, Clone, Serialize, async_graphql::SimpleObject)]
+pub struct EinkAlbumItem {
+    pub album: String,
+    /// The object key under the album's prefix.
+    pub name: String,
+    pub key: String,
+    /// Pinned items are all the album shows while any are left.
+    pub pinned: bool,
+}
+
+impl EinkAlbumItem {
+    fn new(album: &Album, key: String, pinned: bool) -> Self {
+        Self {
+            album: album.name.clone(),
+            name: key.strip_prefix(&album.prefix).unwrap_or(&key).to_owned(),
+            key,
+            pinned,
+        }
+    }
+}
+
+pub async fn pinned_keys(db: &Pool<Postgres>, album: &str) -> Result<HashSet<String>, sqlx::Error> {
+    let keys = sqlx::query_scalar!(
+        "SELECT object_key FROM eink_album_pins WHERE album = $1",
+        album
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(keys.into_iter().collect())
+}
+
+impl EinkDisplayManager {
+    fn album(&self, name: &str) -> Result<Album, AlbumError> {
+        let settings = self.config.settings();
+
+        match settings.eink_display.album(name) {
+            Some(album) => Ok(album.clone()),
+            None if name == settings.eink_display.default_album().name => {
+                Ok(settings.eink_display.default_album())
+            }
+            None => Err(AlbumError::UnknownAlbum(name.to_owned())),
+        }
+    }
+
+    /// The album's photos as the album source sees them, uploads and
+    /// objects put there by hand alike.
+    pub async fn album_items(&self, album: &str) -> Result<Vec<EinkAlbumItem>, AlbumError> {
+        let album = self.album(album)?;
+        let pinned = pinned_keys(&self.db, &album.name).await?;
+
+        Ok(self
+            .s3
+            .list_objects(&album.prefix)
+            .await?
+            .into_iter()
+            .filter(|key| !key.starts_with(CACHE_PREFIX))
+            .map(|key| {
+                let is_pinned = pinned.contains(&key);
+                EinkAlbumItem::new(&album, key, is_pinned)
+            })
+            .collect())
+    }
+
+    async fn album_item(&self, album: &str, name: &str) -> Result<EinkAlbumItem, AlbumError> {
+        self.album_items(album)
+            .await?
+            .into_iter()
+            .find(|item| item.name == name)
+            .ok_or_else(|| AlbumError::UnknownItem {
+                album: album.to_owned(),
+                name: name.to_owned(),
+            })
+    }
+
+    /// Store a photo in `album`, cropped for `device_id` when given and
+    /// otherwise for the first display showing the album, if any is.
+    pub async fn upload_to_album(
+        &self,
+        album: &str,
+        image: Vec<u8>,
+        device_id: Option<&str>,
+    ) -> Result<EinkAlbumItem, AlbumError> {
+        let album = self.album(album)?;
+        let crop_to = self.crop_for(&album, device_id)?;
+
+        let png = tokio::task::spawn_blocking(move || upload::normalize(&image, crop_to))
+            .await
+            .map_err(|e| anyhow::anyhow!("join error: {e}"))??;
+
+        let hash = content_hash(&png);
+        let key = format!("{}{}.png", album.prefix, &hash[..UPLOAD_NAME_LEN]);
+
+        let mut metadata = HashMap::new();
+        metadata.insert("hash".to_owned(), hash);
+
+        self.s3
+            .put_object_with_metadata(&key, &png, Some("image/png"), &metadata)
+            .await?;
+        tracing::info!(album = album.name.as_str(), "uploaded {key} to the album");
+
+        let pinned = pinned_keys(&self.db, &album.name).await?.contains(&key);
+
+        Ok(EinkAlbumItem::new(&album, key, pinned))
+    }
+
+    fn crop_for(
+        &self,
+        album: &Album,
+        device_id: Option<&str>,
+    ) -> Result<Option<(u32, u32)>, AlbumError> {
+        let devices = self.config.devices();
+
+        if let Some(device_id) = device_id {
+            return match devices.eink_display(device_id) {
+                Some(display) => Ok(Some(display.target_dims())),
+                None => Err(AlbumError::UnknownDisplay(device_id.to_owned())),
+            };
+        }
+
+        let mut device_ids = self.device_ids();
+        device_ids.sort();
+
+        Ok(device_ids
+            .iter()
+            .filter_map(|id| devices.eink_display(id))
+            .find(|display| {
+                display
+                    .mode
+                    .sources()
+                    .iter()
+                    .any(|source| source.album() == Some(album.name.as_str()))
+            })
+            .map(|display| display.target_dims()))
+    }
+
+    pub async fn delete_album_item(&self, album: &str, name: &str) -> Result<(), AlbumError> {
+        let item = self.album_item(album, name).await?;
+
+        self.s3.delete_object(&item.key).await?;
+        sqlx::query!(
+            "DELETE FROM eink_album_pins WHERE object_key = $1",
+            item.key
+        )
+        .execute(&self.db)
+        .await?;
+
+        tracing::info!(album, "deleted {} from the album", item.key);
+
+        Ok(())
+    }
+
+    pub async fn pin_album_item(
+        &self,
+        album: &str,
+        name: &str,
+        pinned: bool,
+    ) -> Result<EinkAlbumItem, AlbumError> {
+        let mut item = self.album_item(album, name).await?;
+
+        if pinned {
+            sqlx::query!(
+                "INSERT INTO eink_album_pins (object_key, album) VALUES ($1, $2) \
+                 ON CONFLICT (object_key) DO UPDATE SET album = EXCLUDED.album",
+                item.key,
+                item.album
+            )
+            .execute(&self.db)
+            .await?;
+        } else {
+            sqlx::query!(
+                "DELETE FROM eink_album_pins WHERE object_key = $1",
+                item.key
+            )
+            .execute(&self.db)
+            .await?;
+        }
+
+        item.pinned = pinned;
+        Ok(item)
+    }
+}
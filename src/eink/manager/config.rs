@@ -1,6 +1,8 @@
 use super::EinkDisplayManager;
+use super::plan::{Cached, RenderPlan};
 use super::resolve::ResolvedDisplay;
 use crate::actors::system::cron::schedule::CronSchedule;
+use crate::eink::panel::PartialWindow;
 use crate::eink::partial::resolve_partial_window;
 use crate::routes::epd::{DeviceReport, EpdConfig};
 use chrono::{DateTime, TimeDelta};
@@ -15,18 +17,59 @@ const HOST: &str = "http://192.168.0.149:8000/v1/epd";
 #[cfg(not(debug_assertions))]
 const HOST: &str = "https://home.anurag.sh/v1/epd";
 
+/// A frame the config pointed a display at, for the render history.
+struct Served {
+    plan: RenderPlan,
+    cached: Cached,
+    partial: Option<PartialWindow>,
+}
+
 impl EinkDisplayManager {
+    /// The config a display would be sent if it woke now.
     pub async fn epd_config(
         &self,
         resolved: &ResolvedDisplay,
         report: DeviceReport<'_>,
     ) -> EpdConfig {
-        let now = chrono::Utc::now().with_timezone(&Perth);
+        self.plan_config(resolved, report).await.0
+    }
 
-        let refresh_secs = match resolved.sleep {
-            Some(sleep) => sleep.secs_until_end(now.time()),
-            None => drift_biased_refresh_secs(&resolved.refresh, resolved.grace, now),
-        };
+    /// The config for a display's wake, with the frame it is sent recorded in
+    /// its render history.
+    pub async fn serve_epd_config(
+        &self,
+        resolved: &ResolvedDisplay,
+        report: DeviceReport<'_>,
+    ) -> EpdConfig {
+        let (config, served) = self.plan_config(resolved, report).await;
+
+        if let Some(served) = served
+            && let Err(e) = self
+                .record_render(
+                    &resolved.device_id,
+                    &served.plan,
+                    report.current_image_hash,
+                    resolved.clear_screen,
+                    served.partial,
+                    served.cached,
+                )
+                .await
+        {
+            tracing::warn!(
+                device_id = resolved.device_id,
+                "failed to record the render: {e}"
+            );
+        }
+
+        config
+    }
+
+    async fn plan_config(
+        &self,
+        resolved: &ResolvedDisplay,
+        report: DeviceReport<'_>,
+    ) -> (EpdConfig, Option<Served>) {
+        let refresh_secs = refresh_secs(resolved);
 
         let mut config = EpdConfig {
             refresh_interval_mins: Some(refresh_secs.div_ceil(60)),
@@ -51,12 +94,12 @@ impl EinkDisplayManager {
                 device_id = resolved.device_id,
                 "no image to serve, skipping this cycle"
             );
-            return config;
+            return (config, None);
         };
 
-        if !self.ensure_packed(&plan).await {
-            return config;
-        }
+        let Some(cached) = self.ensure_packed(&plan).await else {
+            return (config, None);
+        };
 
         let partial = match resolved.clear_screen || plan.sleep.is_some() {
             true => None,
@@ -72,23 +115,53 @@ impl EinkDisplayManager {
             }
         };
 
-        let mut url = format!(
-            "{HOST}/image/{}?device_id={}",
-            plan.hash, resolved.device_id
-        );
+        config.image_url = Some(format!(
+            "{HOST}/image/{}?device_id={}{}",
+            plan.hash,
+            resolved.device_id,
+            window_query(partial)
+        ));
+        config.image_hash = Some(plan.hash.clone());
+        config.partial = partial;
 
-        if let Some(window) = partial {
-            url.push_str(&format!(
+        (
+            config,
+            Some(Served {
+                plan,
+                cached,
+                partial,
+            }),
+        )
+    }
+}
+
+/// Where a frame can be seen decoded, with `window` outlined.
+pub(super) fn preview_url(device_id: &str, hash: &str, window: Option<PartialWindow>) -> String {
+    format!(
+        "{HOST}/frames/{hash}?device_id={device_id}{}",
+        window_query(window)
+    )
+}
+
+fn window_query(window: Option<PartialWindow>) -> String {
+    window
+        .map(|window| {
+            format!(
                 "&x={}&y={}&width={}&height={}",
                 window.x, window.y, window.width, window.height
-            ));
-        }
+            )
+        })
+        .unwrap_or_default()
+}
 
-        config.image_url = Some(url);
-        config.image_hash = Some(plan.hash);
-        config.partial = partial;
+/// Seconds until the display should next wake: the end of its sleep, or just
+/// before its next refresh slot.
+pub(super) fn refresh_secs(resolved: &ResolvedDisplay) -> u32 {
+    let now = chrono::Utc::now().with_timezone(&Perth);
 
-        config
+    match resolved.sleep {
+        Some(sleep) => sleep.secs_until_end(now.time()),
+        None => drift_biased_refresh_secs(&resolved.refresh, resolved.grace, now),
     }
 }
 
@@ -1,9 +1,13 @@
+use crate::eink::panel::PanelProfile;
+use crate::settings::Dither;
 use image::RgbImage;
 
 pub struct FrameContext {
     pub crop_to: Option<(u32, u32)>,
     pub sleep_label: Option<String>,
     pub palette: Vec<(f32, f32, f32, u8)>,
+    pub panel: &'static PanelProfile,
+    pub dither: Dither,
 }
 
 pub trait FrameStep: Send + Sync {
@@ -79,23 +83,25 @@ impl FramePipeline {
 mod tests {
     use super::*;
     use crate::eink::manager::steps::{
-        CropCover, FloydSteinbergPacked, RotateToPortrait, SleepLabel,
+        CropCover, DitheredPacked, Enhance, RotateToPanel, SleepLabel,
     };
-    use crate::eink::panel::PACKED_FRAME_SIZE;
-    use crate::settings::eink::PALETTE_COLORS;
+    use crate::eink::panel::{BWR_7IN5, SPECTRA6_13IN3};
+    use crate::settings::{ColorSpace, DitherAlgorithm};
 
     const GOLDEN_CROPPED: &str = "dc86f400cd03697fd84b27d5cd1306185720d73d25c4ab85f79dbed746cbcf3c";
     const GOLDEN_ROTATED: &str = "68ed8c71e335465d1157d1d0a6d8bed26d38d997912a776a66e2a2cd99051918";
 
     fn pipeline() -> FramePipeline {
-        FramePipeline::new(FloydSteinbergPacked)
+        FramePipeline::new(DitheredPacked)
             .register(CropCover)
+            .register(Enhance)
             .register(SleepLabel)
-            .register(RotateToPortrait)
+            .register(RotateToPanel)
     }
 
     fn palette() -> Vec<(f32, f32, f32, u8)> {
-        PALETTE_COLORS
+        SPECTRA6_13IN3
+            .palette
             .iter()
             .map(|&(_, r, g, b, index)| (r, g, b, index))
             .collect()
@@ -126,6 +132,8 @@ mod tests {
                     crop_to: Some((1200, 1600)),
                     sleep_label: Some("zzz till 08:00".to_owned()),
                     palette: palette(),
+                    panel: &SPECTRA6_13IN3,
+                    dither: Dither::default(),
                 },
             )
             .unwrap();
@@ -137,12 +145,14 @@ mod tests {
                     crop_to: None,
                     sleep_label: None,
                     palette: palette(),
+                    panel: &SPECTRA6_13IN3,
+                    dither: Dither::default(),
                 },
             )
             .unwrap();
 
-        assert_eq!(cropped.len(), PACKED_FRAME_SIZE);
-        assert_eq!(rotated.len(), PACKED_FRAME_SIZE);
+        assert_eq!(cropped.len(), SPECTRA6_13IN3.frame_size());
+        assert_eq!(rotated.len(), SPECTRA6_13IN3.frame_size());
         assert_eq!(crate::eink::image::content_hash(&cropped), GOLDEN_CROPPED);
         assert_eq!(crate::eink::image::content_hash(&rotated), GOLDEN_ROTATED);
     }
@@ -153,11 +163,14 @@ mod tests {
             crop_to: None,
             sleep_label: None,
             palette: palette(),
+            panel: &SPECTRA6_13IN3,
+            dither: Dither::default(),
         };
 
         let fingerprint = pipeline().fingerprint(&bare);
 
         assert!(!fingerprint.iter().any(|part| part.starts_with("crop=")));
+        assert!(!fingerprint.iter().any(|part| part.starts_with("enhance=")));
         assert!(
             !fingerprint
                 .iter()
@@ -175,16 +188,43 @@ mod tests {
             crop_to: Some((1200, 1600)),
             sleep_label: Some("zzz till 08:00".to_owned()),
             palette: palette(),
+            panel: &SPECTRA6_13IN3,
+            dither: Dither::default(),
         };
         let relabelled = FrameContext {
             crop_to: base.crop_to,
             sleep_label: Some("zzz till 06:00".to_owned()),
             palette: base.palette.clone(),
+            panel: base.panel,
+            dither: base.dither,
         };
         let repainted = FrameContext {
             crop_to: base.crop_to,
             sleep_label: base.sleep_label.clone(),
             palette: vec![(0.0, 0.0, 0.0, 0), (250.0, 250.0, 250.0, 1)],
+            panel: base.panel,
+            dither: base.dither,
+        };
+        let redithered = FrameContext {
+            crop_to: base.crop_to,
+            sleep_label: base.sleep_label.clone(),
+            palette: base.palette.clone(),
+            panel: base.panel,
+            dither: Dither {
+                algorithm: DitherAlgorithm::Atkinson,
+                color_space: ColorSpace::Oklab,
+                ..Dither::default()
+            },
+        };
+        let boosted = FrameContext {
+            crop_to: base.crop_to,
+            sleep_label: base.sleep_label.clone(),
+            palette: base.palette.clone(),
+            panel: base.panel,
+            dither: Dither {
+                saturation: 1.3,
+                ..Dither::default()
+            },
         };
 
         assert_eq!(pipeline.fingerprint(&base), pipeline.fingerprint(&base));
@@ -196,6 +236,11 @@ mod tests {
             pipeline.fingerprint(&base),
             pipeline.fingerprint(&repainted)
         );
+        assert_ne!(
+            pipeline.fingerprint(&base),
+            pipeline.fingerprint(&redithered)
+        );
+        assert_ne!(pipeline.fingerprint(&base), pipeline.fingerprint(&boosted));
     }
 
     #[test]
@@ -211,9 +256,41 @@ mod tests {
                 crop_to: None,
                 sleep_label: None,
                 palette: palette(),
+                panel: &SPECTRA6_13IN3,
+                dither: Dither::default(),
             },
         );
 
         assert!(result.is_err());
     }
+
+    #[test]
+    fn a_smaller_panel_is_rotated_and_packed_to_its_own_geometry() {
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgb8(image::RgbImage::new(480, 800))
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+
+        let ctx = FrameContext {
+            crop_to: None,
+            sleep_label: None,
+            palette: BWR_7IN5
+                .palette
+                .iter()
+                .map(|&(_, r, g, b, index)| (r, g, b, index))
+                .collect(),
+            panel: &BWR_7IN5,
+            dither: Dither::default(),
+        };
+
+        let packed = pipeline().run(&png, &ctx).unwrap();
+
+        assert_eq!(packed.len(), BWR_7IN5.frame_size());
+        assert!(packed.iter().all(|&byte| byte == 0));
+        assert!(
+            pipeline()
+                .fingerprint(&ctx)
+                .contains(&"rotate=800x480".to_owned())
+        );
+    }
 }
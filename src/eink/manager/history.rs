@@ -0,0 +1,129 @@
+use super::EinkDisplayManager;
+use super::config::preview_url;
+use super::plan::{Cached, RenderPlan};
+use crate::eink::panel::PartialWindow;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Frames kept per display; older ones are dropped as new ones are served.
+const HISTORY_PER_DISPLAY: i64 = 200;
+
+/// A frame served to a display on one wake.
+#[derive(Debug, Clone, Serialize, async_graphql::SimpleObject)]
+#[graphql(rename_fields = "camelCase")]
+pub struct EinkRender {
+    pub hash: String,
+    /// The frame the display said it was showing when it woke.
+    pub previous_hash: Option<String>,
+    pub image_key: String,
+    pub image_content_hash: String,
+    pub sleep_label: Option<String>,
+    /// The frame pipeline's part of the hash, as `step=fingerprint`.
+    pub fingerprint: Vec<String>,
+    pub clear_screen: bool,
+    /// The window a partial refresh redrew, if it was one.
+    pub partial: Option<PartialWindow>,
+    /// When the frame was rendered, if this wake rendered it rather than
+    /// finding it in the frame cache.
+    pub rendered_at: Option<DateTime<Utc>>,
+    pub served_at: DateTime<Utc>,
+    /// The frame decoded to a PNG, with the partial window outlined.
+    pub preview_url: String,
+}
+
+impl EinkDisplayManager {
+    pub(super) async fn record_render(
+        &self,
+        device_id: &str,
+        plan: &RenderPlan,
+        previous_hash: Option<&str>,
+        clear_screen: bool,
+        partial: Option<PartialWindow>,
+        cached: Cached,
+    ) -> Result<(), sqlx::Error> {
+        let rendered_at = (cached == Cached::Rendered).then(Utc::now);
+
+        sqlx::query!(
+            "INSERT INTO eink_render_history (device_id, render_hash, previous_hash, image_key, image_content_hash, \
+             sleep_label, fingerprint, clear_screen, partial_x, partial_y, partial_width, partial_height, rendered_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)",
+            device_id,
+            plan.hash,
+            previous_hash,
+            plan.image_key,
+            plan.content_hash,
+            plan.frame.sleep_label,
+            &plan.fingerprint,
+            clear_screen,
+            partial.map(|w| w.x as i32),
+            partial.map(|w| w.y as i32),
+            partial.map(|w| w.width as i32),
+            partial.map(|w| w.height as i32),
+            rendered_at,
+        )
+        .execute(&self.db)
+        .await?;
+
+        sqlx::query!(
+            "DELETE FROM eink_render_history WHERE device_id = $1 AND id NOT IN \
+             (SELECT id FROM eink_render_history WHERE device_id = $1 ORDER BY served_at DESC LIMIT $2)",
+            device_id,
+            HISTORY_PER_DISPLAY,
+        )
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// The frames last served to `device_id`, newest first.
+    pub async fn render_history(
+        &self,
+        device_id: &str,
+        limit: i64,
+    ) -> Result<Vec<EinkRender>, sqlx::Error> {
+        let rows = sqlx::query!(
+            "SELECT render_hash, previous_hash, image_key, image_content_hash, sleep_label, fingerprint, \
+             clear_screen, partial_x, partial_y, partial_width, partial_height, rendered_at, served_at \
+             FROM eink_render_history WHERE device_id = $1 ORDER BY served_at DESC LIMIT $2",
+            device_id,
+            limit.clamp(0, HISTORY_PER_DISPLAY),
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let partial = match (
+                    row.partial_x,
+                    row.partial_y,
+                    row.partial_width,
+                    row.partial_height,
+                ) {
+                    (Some(x), Some(y), Some(width), Some(height)) => Some(PartialWindow {
+                        x: x as u32,
+                        y: y as u32,
+                        width: width as u32,
+                        height: height as u32,
+                    }),
+                    _ => None,
+                };
+
+                EinkRender {
+                    preview_url: preview_url(device_id, &row.render_hash, partial),
+                    hash: row.render_hash,
+                    previous_hash: row.previous_hash,
+                    image_key: row.image_key,
+                    image_content_hash: row.image_content_hash,
+                    sleep_label: row.sleep_label,
+                    fingerprint: row.fingerprint,
+                    clear_screen: row.clear_screen,
+                    partial,
+                    rendered_at: row.rendered_at,
+                    served_at: row.served_at,
+                }
+            })
+            .collect())
+    }
+}
@@ -1,3 +1,4 @@
+pub mod album;
 pub mod frame;
 pub mod plan;
 pub mod resolve;
@@ -6,37 +7,44 @@ pub mod sources;
 pub mod steps;
 
 mod config;
+mod history;
+mod playlist;
 mod store;
+mod trmnl;
 
-use crate::device_registry::DeviceRegistry;
-use crate::eink::flag::{epd_flag_config, epd_palette};
+use crate::actors::workflows::manager::WorkflowManager;
+use crate::eink::flag::{epd_dithering, epd_flag_config};
 use crate::eink::panel::packed_cache_key;
 use crate::error::AppError;
 use crate::integrations::feature_flag::FeatureFlagClient;
 use crate::integrations::reddit::Reddit;
 use crate::integrations::s3::S3;
-use crate::settings::SettingsContainer;
+use crate::settings::LiveConfig;
 use frame::{FrameContext, FramePipeline};
-use plan::{RenderPlan, render_hash};
+use plan::{Cached, RenderPlan, render_hash};
 use resolve::ResolvedDisplay;
 use source::{
     ImageSource, Prepared, ScreenshotBackend, SourceContext, SourceImage, SourceRegistry,
 };
-use sources::{AlbumSource, DashboardSource, RedditSource, SleepSource};
+use sources::{AlbumSource, DashboardSource, NativeSource, RedditSource, SleepSource};
 use std::sync::Arc;
-use steps::{CropCover, FloydSteinbergPacked, RotateToPortrait, SleepLabel};
+use steps::{CropCover, DitheredPacked, Enhance, GreyscalePng, RotateToPanel, SleepLabel};
+
+pub use history::EinkRender;
+pub use trmnl::{TrmnlDisplay, trmnl_cache_key};
 
 #[derive(Clone)]
 pub struct EinkDisplayManager {
     db: sqlx::Pool<sqlx::Postgres>,
     s3: S3,
     feature_flag_client: FeatureFlagClient,
-    devices: DeviceRegistry,
-    settings: SettingsContainer,
+    config: LiveConfig,
     reddit: Reddit,
+    workflows: WorkflowManager,
     sources: Arc<SourceRegistry>,
     sleep: Arc<SleepSource>,
     frames: Arc<FramePipeline>,
+    trmnl_frames: Arc<FramePipeline>,
 }
 
 impl EinkDisplayManager {
@@ -44,56 +52,75 @@ impl EinkDisplayManager {
         db: sqlx::Pool<sqlx::Postgres>,
         s3: S3,
         feature_flag_client: FeatureFlagClient,
-        devices: DeviceRegistry,
-        settings: SettingsContainer,
+        config: LiveConfig,
         reddit: Reddit,
     ) -> Self {
         let sources = SourceRegistry::new()
             .register(DashboardSource::default())
             .register(AlbumSource)
-            .register(RedditSource);
+            .register(RedditSource)
+            .register(NativeSource::default());
 
-        let frames = FramePipeline::new(FloydSteinbergPacked)
+        let frames = FramePipeline::new(DitheredPacked)
             .register(CropCover)
+            .register(Enhance)
             .register(SleepLabel)
-            .register(RotateToPortrait);
+            .register(RotateToPanel);
+
+        let trmnl_frames = FramePipeline::new(GreyscalePng)
+            .register(CropCover)
+            .register(Enhance)
+            .register(SleepLabel);
 
         Self {
+            workflows: WorkflowManager::new(db.clone()),
             db,
             s3,
             feature_flag_client,
-            devices,
-            settings,
+            config,
             reddit,
             sources: Arc::new(sources),
             sleep: Arc::new(SleepSource),
             frames: Arc::new(frames),
+            trmnl_frames: Arc::new(trmnl_frames),
         }
     }
 
     pub fn device_ids(&self) -> Vec<String> {
-        self.devices.eink_displays().keys().cloned().collect()
+        let devices = self.config.devices();
+
+        devices
+            .eink_displays()
+            .keys()
+            .chain(devices.trmnl_displays().keys())
+            .cloned()
+            .collect()
     }
 
     pub async fn resolve(&self, device_id: &str) -> Option<ResolvedDisplay> {
-        let display = self.devices.eink_display(device_id)?.clone();
+        let devices = self.config.devices();
+        let settings = self.config.settings();
+        let display = devices.eink_display(device_id)?.clone();
 
-        let flag = epd_flag_config(&self.feature_flag_client, &self.devices, device_id).await;
+        let flag = epd_flag_config(&self.feature_flag_client, &devices, device_id).await;
 
-        let palette = epd_palette(
+        let dithering = epd_dithering(
             &self.feature_flag_client,
-            &self.devices,
-            &self.settings.eink_display.palette,
+            &devices,
+            &settings.eink_display.palette(display.panel.profile()),
             device_id,
         )
         .await;
 
+        let playlist = self.playlist_context(&display).await;
+
         Some(ResolvedDisplay::resolve(
             device_id,
             &display,
-            &self.settings.eink_display,
+            &settings.eink_display,
             &flag,
-            palette,
+            dithering,
+            &playlist,
         ))
     }
 
@@ -116,6 +143,7 @@ impl EinkDisplayManager {
         SourceContext {
             display,
             db: &self.db,
+            config: &self.config,
             s3: &self.s3,
             reddit: &self.reddit,
             screenshots,
@@ -171,6 +199,20 @@ impl EinkDisplayManager {
     }
 
     pub async fn plan(&self, display: &ResolvedDisplay) -> Option<RenderPlan> {
+        self.plan_frame(display, &self.frames, None, display.palette.clone())
+            .await
+    }
+
+    /// Plan a frame through `frames`. A fixed `crop_to` size crops every image
+    /// to it; otherwise only the sleep image is cropped, to the display's
+    /// target size.
+    async fn plan_frame(
+        &self,
+        display: &ResolvedDisplay,
+        frames: &FramePipeline,
+        crop_to: Option<(u32, u32)>,
+        palette: Vec<(f32, f32, f32, u8)>,
+    ) -> Option<RenderPlan> {
         let sleep_image = match display.sleep {
             Some(sleep) => self.sleep.acquire(&self.s3, display, sleep).await,
             None => None,
@@ -183,9 +225,11 @@ impl EinkDisplayManager {
                     image_key,
                 },
                 FrameContext {
-                    crop_to: Some(display.target_dims()),
+                    crop_to: crop_to.or(Some(display.target_dims())),
                     sleep_label: display.sleep_label(),
-                    palette: display.palette.clone(),
+                    palette,
+                    panel: display.profile(),
+                    dither: display.dither,
                 },
             ),
             None => {
@@ -199,68 +243,89 @@ impl EinkDisplayManager {
                 (
                     image,
                     FrameContext {
-                        crop_to: None,
+                        crop_to,
                         sleep_label: None,
-                        palette: display.palette.clone(),
+                        palette,
+                        panel: display.profile(),
+                        dither: display.dither,
                     },
                 )
             }
         };
 
+        let fingerprint = frames.fingerprint(&frame);
         let mut parts = vec![
             image.image_key.clone(),
             image.content_hash.clone(),
             display.clear_screen.to_string(),
         ];
-        parts.extend(self.frames.fingerprint(&frame));
+        parts.extend(fingerprint.iter().cloned());
 
         let hash = render_hash(&parts.iter().map(String::as_str).collect::<Vec<_>>());
 
         Some(RenderPlan {
             image_key: image.image_key,
+            content_hash: image.content_hash,
             sleep: display.sleep,
             hash,
+            fingerprint,
             frame,
         })
     }
 
-    pub async fn ensure_packed(&self, plan: &RenderPlan) -> bool {
+    pub async fn ensure_packed(&self, plan: &RenderPlan) -> Option<Cached> {
         let Some(key) = packed_cache_key(&plan.hash) else {
             tracing::error!(hash = %plan.hash, "render plan hash is not a frame hash");
-            return false;
+            return None;
         };
 
-        match self.s3.get_object_metadata(&key).await {
-            Ok(Some(_)) => return true,
+        self.ensure_rendered(plan, &key, self.frames.clone()).await
+    }
+
+    /// Render `plan` into the frame cache at `key` unless it is already there;
+    /// `None` when the frame could not be had.
+    async fn ensure_rendered(
+        &self,
+        plan: &RenderPlan,
+        key: &str,
+        frames: Arc<FramePipeline>,
+    ) -> Option<Cached> {
+        match self.s3.get_object_metadata(key).await {
+            Ok(Some(_)) => return Some(Cached::Hit),
             Ok(None) => {}
             Err(e) => {
-                tracing::warn!(key = %key, "failed to check the packed cache: {e}");
-                return false;
+                tracing::warn!(key = %key, "failed to check the frame cache: {e}");
+                return None;
             }
         }
 
-        match self.render_packed(plan).await {
+        match self.render(plan, frames).await {
             Ok(packed) => {
-                if let Err(e) = self.s3.put_object(&key, &packed, None).await {
-                    tracing::warn!(key = %key, "failed to cache packed frame: {e}");
+                if let Err(e) = self.s3.put_object(key, &packed, None).await {
+                    tracing::warn!(key = %key, "failed to cache the frame: {e}");
                 }
-                true
+                Some(Cached::Rendered)
             }
             Err(e) => {
-                tracing::warn!(key = %key, "failed to prepare the packed frame: {}", e.message());
-                false
+                tracing::warn!(key = %key, "failed to prepare the frame: {}", e.message());
+                None
             }
         }
     }
 
-    async fn render_packed(&self, plan: &RenderPlan) -> Result<Vec<u8>, AppError> {
+    async fn render(
+        &self,
+        plan: &RenderPlan,
+        frames: Arc<FramePipeline>,
+    ) -> Result<Vec<u8>, AppError> {
         let source = self.s3.get_object(&plan.image_key).await?;
 
-        let frames = self.frames.clone();
         let frame = FrameContext {
             crop_to: plan.frame.crop_to,
             sleep_label: plan.frame.sleep_label.clone(),
             palette: plan.frame.palette.clone(),
+            panel: plan.frame.panel,
+            dither: plan.frame.dither,
         };
 
         let packed = tokio::task::spawn_blocking(move || frames.run(&source, &frame))
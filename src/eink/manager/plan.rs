@@ -4,11 +4,23 @@ use sha2::{Digest, Sha256};
 
 pub struct RenderPlan {
     pub image_key: String,
+    pub content_hash: String,
     pub sleep: Option<SleepWindow>,
     pub hash: String,
+    /// The frame pipeline's part of the hash, as `step=fingerprint`.
+    pub fingerprint: Vec<String>,
     pub frame: FrameContext,
 }
 
+/// How a planned frame came to be in the frame cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cached {
+    /// An earlier wake rendered it.
+    Hit,
+    /// It was rendered for this one.
+    Rendered,
+}
+
 pub fn render_hash(parts: &[&str]) -> String {
     let mut hasher = Sha256::new();
 
@@ -0,0 +1,47 @@
+use super::EinkDisplayManager;
+use super::resolve::PlaylistContext;
+use crate::actors::workflows::conditions::query_presence;
+use crate::settings::{EinkDisplaySettings, EinkModeConfig, PlaylistCondition};
+
+impl EinkDisplayManager {
+    /// The clock and house state a display's playlist is picked against.
+    pub(super) async fn playlist_context(&self, display: &EinkDisplaySettings) -> PlaylistContext {
+        let EinkModeConfig::Playlist { entries } = &display.mode else {
+            return PlaylistContext::now(Vec::new());
+        };
+
+        let mut conditions = Vec::with_capacity(entries.len());
+        for entry in entries {
+            conditions.push(self.conditions_hold(&entry.when).await);
+        }
+
+        PlaylistContext::now(conditions)
+    }
+
+    /// A condition that can't be checked counts as not holding.
+    async fn conditions_hold(&self, when: &[PlaylistCondition]) -> bool {
+        for condition in when {
+            let holds = match condition {
+                PlaylistCondition::Mode { mode, active } => {
+                    self.workflows.mode_active(*mode).await == *active
+                }
+                PlaylistCondition::Presence { sensor, present } => {
+                    let devices = self.config.devices();
+                    match query_presence(devices.address_or_self(sensor)).await {
+                        Ok(value) => value == *present,
+                        Err(e) => {
+                            tracing::warn!("playlist presence check for {sensor} failed: {e}");
+                            false
+                        }
+                    }
+                }
+            };
+
+            if !holds {
+                return false;
+            }
+        }
+
+        true
+    }
+}
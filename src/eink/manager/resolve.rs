@@ -1,10 +1,14 @@
 use crate::actors::system::cron::schedule::CronSchedule;
-use crate::eink::flag::EpdFlagConfig;
+use crate::eink::flag::{EpdDithering, EpdFlagConfig};
+use crate::eink::panel::PanelProfile;
 use crate::settings::{
-    Album, DashboardView, EinkDisplaySettings, EinkGlobalSettings, EinkMode, Orientation,
-    PartialRefresh, RedditFeed, RedditTimespan, SleepWindow,
+    Album, DashboardLayout, DashboardView, Dither, EinkDisplaySettings, EinkGlobalSettings,
+    EinkMode, EinkModeConfig, Orientation, Panel, PartialRefresh, RedditFeed, RedditTimespan,
+    SleepWindow,
 };
+use chrono::DateTime;
 use chrono_tz::Australia::Perth;
+use chrono_tz::Tz;
 use std::time::Duration;
 
 const DEFAULT_REDDIT_TIMESPAN: RedditTimespan = RedditTimespan::Day;
@@ -19,17 +23,39 @@ pub struct ResolvedDisplay {
     pub refresh: CronSchedule,
     pub grace: chrono::TimeDelta,
     pub orientation: Orientation,
+    pub panel: Panel,
     pub view: Option<DashboardView>,
+    pub layout: Option<DashboardLayout>,
     pub album: Album,
     pub feed: Option<RedditFeed>,
     pub sleep: Option<SleepWindow>,
     pub lead: Option<chrono::TimeDelta>,
     pub settle: Duration,
     pub palette: Vec<(f32, f32, f32, u8)>,
+    pub dither: Dither,
     pub partial: PartialRefresh,
     pub partial_enabled: bool,
     pub clear_screen: bool,
     pub firmware_version: String,
+    /// The playlist entry this wake shows, for a display in playlist mode.
+    pub playlist_entry: Option<usize>,
+}
+
+/// What a playlist's entry is picked against on one wake.
+#[derive(Debug, Clone)]
+pub struct PlaylistContext {
+    pub now: DateTime<Tz>,
+    /// Whether each entry's `when` conditions hold, by position.
+    pub conditions: Vec<bool>,
+}
+
+impl PlaylistContext {
+    pub fn now(conditions: Vec<bool>) -> Self {
+        Self {
+            now: chrono::Utc::now().with_timezone(&Perth),
+            conditions,
+        }
+    }
 }
 
 impl ResolvedDisplay {
@@ -38,9 +64,11 @@ impl ResolvedDisplay {
         display: &EinkDisplaySettings,
         global: &EinkGlobalSettings,
         flag: &EpdFlagConfig,
-        palette: Vec<(f32, f32, f32, u8)>,
+        dithering: EpdDithering,
+        playlist: &PlaylistContext,
     ) -> Self {
-        let mode = flag.mode.unwrap_or_else(|| display.mode.name());
+        let (source, playlist_entry) = playing(&display.mode, playlist);
+        let mode = flag.mode.unwrap_or_else(|| source.name());
 
         Self {
             device_id: device_id.to_owned(),
@@ -49,17 +77,19 @@ impl ResolvedDisplay {
             refresh: refresh(flag, display),
             grace: display.grace,
             orientation: display.orientation,
-            view: view(flag, global, display).cloned(),
-            album: album(flag, global, display),
-            feed: feed(flag, display),
+            panel: display.panel,
+            view: view(flag, global, source).cloned(),
+            layout: layout(flag, global, source).cloned(),
+            album: album(flag, global, source),
+            feed: feed(flag, source),
             sleep: active_sleep(display, flag.force_sleep, device_id),
-            lead: display.mode.lead(),
-            settle: display
-                .mode
+            lead: source.lead(),
+            settle: source
                 .settle()
                 .and_then(|settle| settle.to_std().ok())
                 .unwrap_or(DEFAULT_SETTLE),
-            palette,
+            palette: dithering.palette,
+            dither: dither(&dithering, global, display, mode),
             partial: display.partial,
             partial_enabled: flag.partial_refresh.unwrap_or(display.partial.enabled),
             clear_screen: flag.clear_screen,
@@ -67,11 +97,16 @@ impl ResolvedDisplay {
                 .firmware_version
                 .clone()
                 .unwrap_or_else(|| display.firmware_version.clone()),
+            playlist_entry,
         }
     }
 
+    pub fn profile(&self) -> &'static PanelProfile {
+        self.panel.profile()
+    }
+
     pub fn target_dims(&self) -> (u32, u32) {
-        self.orientation.target_dims()
+        self.profile().target_dims(self.orientation)
     }
 
     pub fn orientation_str(&self) -> &'static str {
@@ -90,15 +125,82 @@ pub fn refresh(flag: &EpdFlagConfig, display: &EinkDisplaySettings) -> CronSched
         .unwrap_or_else(|| display.refresh.clone())
 }
 
+/// The source a display shows this wake, and for a playlist the position of
+/// the entry it came from. Scheduled entries that are in their window, on
+/// their day and whose conditions hold take turns, each for its `duration`;
+/// only when none of them can play do the unscheduled ones. The turn is a
+/// function of the clock, so every resolve within it agrees and a frame
+/// planned in one is reused by the next.
+pub fn playing<'a>(
+    mode: &'a EinkModeConfig,
+    playlist: &PlaylistContext,
+) -> (&'a EinkModeConfig, Option<usize>) {
+    let EinkModeConfig::Playlist { entries } = mode else {
+        return (mode, None);
+    };
+
+    let local = playlist.now.naive_local();
+    let playable = |scheduled: bool| -> Vec<usize> {
+        entries
+            .iter()
+            .enumerate()
+            .filter(|(index, entry)| {
+                entry.is_scheduled() == scheduled
+                    && entry.in_schedule(local)
+                    && playlist.conditions.get(*index).copied().unwrap_or(true)
+            })
+            .map(|(index, _)| index)
+            .collect()
+    };
+
+    let mut candidates = playable(true);
+    if candidates.is_empty() {
+        candidates = playable(false);
+    }
+
+    if candidates.is_empty() {
+        tracing::warn!("no playlist entry can play now, showing the first");
+        return (&entries[0].source, Some(0));
+    }
+
+    let turn = |index: &usize| entries[*index].duration.num_seconds().max(1);
+    let total: i64 = candidates.iter().map(turn).sum();
+    let mut position = playlist.now.timestamp().rem_euclid(total);
+    let index = candidates
+        .iter()
+        .copied()
+        .find(|index| {
+            position -= turn(index);
+            position < 0
+        })
+        .unwrap_or(candidates[0]);
+
+    (&entries[index].source, Some(index))
+}
+
+/// The flag, then the display, then its mode's default.
+fn dither(
+    dithering: &EpdDithering,
+    global: &EinkGlobalSettings,
+    display: &EinkDisplaySettings,
+    mode: EinkMode,
+) -> Dither {
+    dithering
+        .dither
+        .or(display.dither)
+        .or(global.dither.for_mode(mode))
+        .resolve()
+}
+
 fn view<'a>(
     flag: &EpdFlagConfig,
     global: &'a EinkGlobalSettings,
-    display: &EinkDisplaySettings,
+    source: &EinkModeConfig,
 ) -> Option<&'a DashboardView> {
     let name = flag
         .dashboard_view
         .clone()
-        .or_else(|| display.mode.view().map(str::to_owned))?;
+        .or_else(|| source.view().map(str::to_owned))?;
 
     global.view(&name).or_else(|| {
         tracing::warn!("dashboard view `{name}` not configured, ignoring");
@@ -106,15 +208,27 @@ fn view<'a>(
     })
 }
 
-fn album(
+fn layout<'a>(
     flag: &EpdFlagConfig,
-    global: &EinkGlobalSettings,
-    display: &EinkDisplaySettings,
-) -> Album {
+    global: &'a EinkGlobalSettings,
+    source: &EinkModeConfig,
+) -> Option<&'a DashboardLayout> {
+    let name = flag
+        .layout
+        .clone()
+        .or_else(|| source.layout().map(str::to_owned))?;
+
+    global.layout(&name).or_else(|| {
+        tracing::warn!("dashboard layout `{name}` not configured, ignoring");
+        None
+    })
+}
+
+fn album(flag: &EpdFlagConfig, global: &EinkGlobalSettings, source: &EinkModeConfig) -> Album {
     let name = flag
         .album
         .clone()
-        .or_else(|| display.mode.album().map(str::to_owned));
+        .or_else(|| source.album().map(str::to_owned));
 
     let Some(name) = name else {
         return global.default_album();
@@ -126,8 +240,8 @@ fn album(
     })
 }
 
-fn feed(flag: &EpdFlagConfig, display: &EinkDisplaySettings) -> Option<RedditFeed> {
-    let configured = display.mode.feed();
+fn feed(flag: &EpdFlagConfig, source: &EinkModeConfig) -> Option<RedditFeed> {
+    let configured = source.feed();
 
     let subreddit = flag
         .subreddit
@@ -179,8 +293,12 @@ fn active_sleep(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::settings::EinkModeConfig;
+    use crate::settings::{
+        ColorSpace, DitherAlgorithm, DitherConfig, PlaylistCondition, PlaylistEntry, TimeWindow,
+    };
+    use chrono::{NaiveTime, TimeZone};
     use pretty_assertions::assert_eq;
+    use std::collections::HashMap;
 
     const HOURLY: &str = "0 * * * *";
 
@@ -194,6 +312,7 @@ mod tests {
                 lead: chrono::TimeDelta::minutes(15),
             },
             orientation: Orientation::Portrait,
+            panel: Panel::default(),
             refresh: CronSchedule::parse(refresh).unwrap(),
             grace: chrono::TimeDelta::minutes(10),
             sleep: None,
@@ -202,6 +321,7 @@ mod tests {
                 max_area_pct: 30,
                 max_consecutive: 5,
             },
+            dither: DitherConfig::default(),
         }
     }
 
@@ -223,7 +343,7 @@ mod tests {
         assert_eq!(
             feed(
                 &EpdFlagConfig::default(),
-                &display_with_feed(configured.clone())
+                &display_with_feed(configured.clone()).mode
             ),
             Some(configured)
         );
@@ -243,7 +363,7 @@ mod tests {
         });
 
         assert_eq!(
-            feed(&flag, &display),
+            feed(&flag, &display.mode),
             Some(RedditFeed {
                 subreddit: "ImaginaryLandscapes".to_owned(),
                 timespan: RedditTimespan::Day,
@@ -255,7 +375,10 @@ mod tests {
     #[test]
     fn reddit_feed_without_a_subreddit_is_skipped() {
         assert_eq!(
-            feed(&EpdFlagConfig::default(), &display_with_refresh(HOURLY)),
+            feed(
+                &EpdFlagConfig::default(),
+                &display_with_refresh(HOURLY).mode
+            ),
             None
         );
     }
@@ -268,7 +391,7 @@ mod tests {
         };
 
         assert_eq!(
-            feed(&flag, &display_with_refresh(HOURLY)),
+            feed(&flag, &display_with_refresh(HOURLY).mode),
             Some(RedditFeed {
                 subreddit: "EarthPorn".to_owned(),
                 timespan: DEFAULT_REDDIT_TIMESPAN,
@@ -277,6 +400,193 @@ mod tests {
         );
     }
 
+    #[test]
+    fn dither_layers_the_flag_over_the_display_over_the_mode() {
+        let mut global = EinkGlobalSettings::default();
+        global.dither.album = DitherConfig {
+            algorithm: Some(DitherAlgorithm::Atkinson),
+            color_space: Some(ColorSpace::Oklab),
+            saturation: Some(1.3),
+            ..DitherConfig::default()
+        };
+        let display = EinkDisplaySettings {
+            dither: DitherConfig {
+                color_space: Some(ColorSpace::Cielab),
+                contrast: Some(9.0),
+                ..DitherConfig::default()
+            },
+            ..display_with_refresh(HOURLY)
+        };
+        let dithering = EpdDithering {
+            palette: Vec::new(),
+            dither: DitherConfig {
+                algorithm: Some(DitherAlgorithm::BlueNoise),
+                ..DitherConfig::default()
+            },
+        };
+
+        assert_eq!(
+            dither(&dithering, &global, &display, EinkMode::Album),
+            Dither {
+                algorithm: DitherAlgorithm::BlueNoise,
+                color_space: ColorSpace::Cielab,
+                contrast: 3.0,
+                saturation: 1.3,
+            }
+        );
+        assert_eq!(
+            dither(
+                &EpdDithering::default(),
+                &global,
+                &display_with_refresh(HOURLY),
+                EinkMode::Dashboard
+            ),
+            Dither::default()
+        );
+    }
+
+    #[test]
+    fn layout_prefers_the_flag_and_ignores_an_unknown_name() {
+        let mut global = EinkGlobalSettings::default();
+        for name in ["kitchen", "hallway"] {
+            global.layouts.insert(
+                name.to_owned(),
+                DashboardLayout {
+                    name: name.to_owned(),
+                    columns: 1,
+                    rows: 1,
+                    widgets: Vec::new(),
+                },
+            );
+        }
+        let display = EinkDisplaySettings {
+            mode: EinkModeConfig::Native {
+                layout: Some("kitchen".to_owned()),
+            },
+            ..display_with_refresh(HOURLY)
+        };
+        let flagged = |name: &str| EpdFlagConfig {
+            layout: Some(name.to_owned()),
+            ..EpdFlagConfig::default()
+        };
+
+        let name = |flag: &EpdFlagConfig| {
+            layout(flag, &global, &display.mode).map(|layout| layout.name.clone())
+        };
+
+        assert_eq!(name(&EpdFlagConfig::default()), Some("kitchen".to_owned()));
+        assert_eq!(name(&flagged("hallway")), Some("hallway".to_owned()));
+        assert_eq!(name(&flagged("attic")), None);
+    }
+
+    fn playlist() -> EinkModeConfig {
+        let entry = |source: EinkModeConfig| PlaylistEntry {
+            source,
+            between: None,
+            days: Vec::new(),
+            duration: chrono::TimeDelta::hours(1),
+            when: Vec::new(),
+        };
+        let album = |name: &str| EinkModeConfig::Album {
+            album: Some(name.to_owned()),
+        };
+
+        EinkModeConfig::Playlist {
+            entries: vec![
+                PlaylistEntry {
+                    between: Some(TimeWindow {
+                        start: NaiveTime::from_hms_opt(6, 0, 0).unwrap(),
+                        end: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+                    }),
+                    ..entry(display_with_refresh(HOURLY).mode)
+                },
+                PlaylistEntry {
+                    days: vec![chrono::Weekday::Sat, chrono::Weekday::Sun],
+                    when: vec![PlaylistCondition::Mode {
+                        mode: crate::mode::Mode::Away,
+                        active: false,
+                    }],
+                    ..entry(
+                        display_with_feed(RedditFeed {
+                            subreddit: "EarthPorn".to_owned(),
+                            timespan: RedditTimespan::Day,
+                            limit: 25,
+                        })
+                        .mode,
+                    )
+                },
+                PlaylistEntry {
+                    duration: chrono::TimeDelta::hours(2),
+                    ..entry(album("cats"))
+                },
+                entry(album("world")),
+            ],
+        }
+    }
+
+    fn wake(day: u32, hour: u32, minute: u32, conditions: [bool; 4]) -> PlaylistContext {
+        // 2026-10-19 is a Monday
+        PlaylistContext {
+            now: Perth
+                .with_ymd_and_hms(2026, 10, day, hour, minute, 0)
+                .unwrap(),
+            conditions: conditions.to_vec(),
+        }
+    }
+
+    #[test]
+    fn a_scheduled_entry_plays_over_the_fallbacks() {
+        let playlist = playlist();
+
+        let (source, entry) = playing(&playlist, &wake(19, 7, 0, [true; 4]));
+        assert_eq!(entry, Some(0));
+        assert_eq!(source.name(), EinkMode::Dashboard);
+
+        let (source, entry) = playing(&playlist, &wake(24, 12, 0, [true; 4]));
+        assert_eq!(entry, Some(1));
+        assert_eq!(
+            source.feed().map(|feed| feed.subreddit.as_str()),
+            Some("EarthPorn")
+        );
+    }
+
+    #[test]
+    fn an_entry_whose_conditions_fail_is_skipped() {
+        let (_, entry) = playing(&playlist(), &wake(24, 12, 0, [true, false, true, true]));
+
+        assert!(matches!(entry, Some(2 | 3)), "{entry:?}");
+    }
+
+    #[test]
+    fn fallbacks_take_turns_for_their_duration() {
+        let playlist = playlist();
+        let mut shown = HashMap::new();
+
+        for minute in 0..180 {
+            let (_, entry) = playing(
+                &playlist,
+                &wake(19, 10 + minute / 60, minute % 60, [true; 4]),
+            );
+            *shown.entry(entry).or_insert(0) += 1;
+        }
+
+        assert_eq!(shown, HashMap::from([(Some(2), 120), (Some(3), 60)]));
+        assert_eq!(
+            playing(&playlist, &wake(19, 12, 0, [true; 4])).1,
+            playing(&playlist, &wake(19, 12, 0, [true; 4])).1
+        );
+    }
+
+    #[test]
+    fn a_plain_mode_is_its_own_source() {
+        let display = display_with_refresh(HOURLY);
+
+        let (source, entry) = playing(&display.mode, &wake(19, 7, 0, [true; 4]));
+
+        assert_eq!(entry, None);
+        assert_eq!(source.name(), EinkMode::Dashboard);
+    }
+
     #[test]
     fn refresh_prefers_the_flag() {
         let flag = EpdFlagConfig {
@@ -2,7 +2,7 @@ use super::resolve::ResolvedDisplay;
 use crate::error::AppError;
 use crate::integrations::reddit::Reddit;
 use crate::integrations::s3::S3;
-use crate::settings::EinkMode;
+use crate::settings::{EinkMode, LiveConfig};
 use std::time::Duration;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -31,6 +31,7 @@ pub trait ScreenshotBackend: Send + Sync {
 pub struct SourceContext<'a> {
     pub display: &'a ResolvedDisplay,
     pub db: &'a sqlx::Pool<sqlx::Postgres>,
+    pub config: &'a LiveConfig,
     pub s3: &'a S3,
     pub reddit: &'a Reddit,
     pub screenshots: &'a dyn ScreenshotBackend,
@@ -87,20 +88,26 @@ impl Default for SourceRegistry {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::eink::manager::sources::{AlbumSource, DashboardSource, RedditSource};
+    use crate::eink::manager::sources::{AlbumSource, DashboardSource, NativeSource, RedditSource};
 
     fn registry() -> SourceRegistry {
         SourceRegistry::new()
             .register(DashboardSource::default())
             .register(AlbumSource)
             .register(RedditSource)
+            .register(NativeSource::default())
     }
 
     #[test]
     fn every_mode_has_a_registered_source() {
         let registry = registry();
 
-        for mode in [EinkMode::Dashboard, EinkMode::Album, EinkMode::Reddit] {
+        for mode in [
+            EinkMode::Dashboard,
+            EinkMode::Album,
+            EinkMode::Reddit,
+            EinkMode::Native,
+        ] {
             assert_eq!(
                 registry.select(mode).map(|source| source.mode()),
                 Some(mode)
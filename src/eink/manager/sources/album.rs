@@ -1,4 +1,5 @@
 use super::cache::{CACHE_PREFIX, cache_processed_image, ensure_source_hash};
+use crate::eink::manager::album::pinned_keys;
 use crate::eink::manager::source::{ImageSource, SourceContext, SourceImage};
 use crate::error::AppError;
 use crate::settings::EinkMode;
@@ -27,6 +28,19 @@ impl ImageSource for AlbumSource {
             .filter(|key| !key.starts_with(CACHE_PREFIX))
             .collect();
 
+        // Pins narrow the rotation to themselves, until none of them is left.
+        let pinned = pinned_keys(ctx.db, &album.name).await?;
+        let pinned_images: Vec<String> = images
+            .iter()
+            .filter(|key| pinned.contains(*key))
+            .cloned()
+            .collect();
+        let images = if pinned_images.is_empty() {
+            images
+        } else {
+            pinned_images
+        };
+
         let Some(source_key) = images.choose(&mut rand::rng()).cloned() else {
             tracing::warn!(
                 "album `{}` at `{}` has no images, skipping render",
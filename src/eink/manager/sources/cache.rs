@@ -14,7 +14,7 @@ pub async fn cache_processed_image(
     source_label: &str,
 ) -> Result<String, AppError> {
     let (target_w, target_h) = display.target_dims();
-    let cache_key = format!("{CACHE_PREFIX}{hash}-{}.png", display.orientation_str());
+    let cache_key = format!("{CACHE_PREFIX}{hash}-{target_w}x{target_h}.png");
 
     if s3.get_object_metadata(&cache_key).await?.is_some() {
         tracing::info!("image cache hit for {source_label} -> {cache_key}");
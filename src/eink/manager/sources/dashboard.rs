@@ -23,12 +23,12 @@ pub struct DashboardSource {
 }
 
 impl DashboardSource {
-    pub fn key(view_name: &str, orientation: &str) -> String {
-        format!("eink-display/dashboard/{view_name}-{orientation}.png")
+    pub fn key(view_name: &str, (width, height): (u32, u32)) -> String {
+        format!("eink-display/dashboard/{view_name}-{width}x{height}.png")
     }
 
     pub fn key_for(display: &ResolvedDisplay) -> String {
-        Self::key(Self::view_name(display), display.orientation_str())
+        Self::key(Self::view_name(display), display.target_dims())
     }
 
     fn view_name(display: &ResolvedDisplay) -> &str {
@@ -1,10 +1,13 @@
 mod album;
 mod cache;
 mod dashboard;
+mod native;
 mod reddit;
 mod sleep;
 
 pub use album::AlbumSource;
+pub use cache::CACHE_PREFIX;
 pub use dashboard::DashboardSource;
+pub use native::NativeSource;
 pub use reddit::RedditSource;
 pub use sleep::SleepSource;
@@ -0,0 +1,304 @@
+use crate::actors::sun::calc::{self, SunTransition};
+use crate::battery::{BatteryChemistry, voltage_to_percentage};
+use crate::integrations::bom::{self, Forecast};
+use crate::integrations::solar::queries;
+use crate::integrations::solar::types::SolarCurrentResponse;
+use crate::settings::{DashboardLayout, LocationSettings, TriggerMatcher, Widget, Workflow};
+use chrono::{DateTime, Utc};
+use chrono_tz::Australia::Perth;
+use chrono_tz::Tz;
+use reqwest_middleware::ClientWithMiddleware;
+use sqlx::{Pool, Postgres};
+use std::collections::HashMap;
+use tracing::Instrument;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AgendaEntry {
+    pub at: DateTime<Tz>,
+    pub title: String,
+    pub trigger: &'static str,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SensorReading {
+    pub name: String,
+    pub temperature: f64,
+    pub humidity: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatteryLevel {
+    pub name: String,
+    pub percent: Option<f64>,
+    pub voltage: Option<f64>,
+}
+
+/// Everything a layout's widgets draw, fetched up front so rendering is a
+/// pure function of it. A source that could not be reached is left empty
+/// and its widget says so, rather than failing the whole dashboard.
+#[derive(Debug)]
+pub struct WidgetData {
+    pub now: DateTime<Tz>,
+    pub forecasts: HashMap<String, Forecast>,
+    pub solar: Option<SolarCurrentResponse>,
+    pub agenda: Vec<AgendaEntry>,
+    pub sensors: HashMap<String, SensorReading>,
+    pub batteries: Vec<BatteryLevel>,
+}
+
+impl WidgetData {
+    pub fn empty(now: DateTime<Tz>) -> Self {
+        Self {
+            now,
+            forecasts: HashMap::new(),
+            solar: None,
+            agenda: Vec::new(),
+            sensors: HashMap::new(),
+            batteries: Vec::new(),
+        }
+    }
+}
+
+pub struct Sources<'a> {
+    pub db: &'a Pool<Postgres>,
+    pub http: &'a ClientWithMiddleware,
+    pub bom_url: &'a str,
+    pub location: LocationSettings,
+    pub workflows: Vec<&'a Workflow>,
+}
+
+pub async fn gather(
+    sources: Sources<'_>,
+    layout: &DashboardLayout,
+    now: DateTime<Utc>,
+) -> WidgetData {
+    let mut data = WidgetData::empty(now.with_timezone(&Perth));
+
+    for placed in &layout.widgets {
+        match &placed.widget {
+            Widget::Clock => {}
+            Widget::Weather { location, .. } => {
+                if data.forecasts.contains_key(location) {
+                    continue;
+                }
+
+                match bom::forecast(sources.http, sources.bom_url, location).await {
+                    Ok(forecast) => {
+                        data.forecasts.insert(location.clone(), forecast);
+                    }
+                    Err(e) => tracing::warn!("no forecast for `{location}` on the dashboard: {e}"),
+                }
+            }
+            Widget::Solar => match queries::current(sources.db).await {
+                Ok(solar) => data.solar = Some(solar),
+                Err(e) => tracing::warn!("no solar reading for the dashboard: {e}"),
+            },
+            Widget::Agenda { limit } => {
+                let entries = agenda(
+                    sources.workflows.iter().copied(),
+                    sources.location,
+                    now,
+                    *limit,
+                );
+                if entries.len() > data.agenda.len() {
+                    data.agenda = entries;
+                }
+            }
+            Widget::Sensors { sensors } => {
+                let missing: Vec<String> = sensors
+                    .iter()
+                    .filter(|id| !data.sensors.contains_key(*id))
+                    .cloned()
+                    .collect();
+
+                match sensor_readings(sources.db, &missing).await {
+                    Ok(readings) => data.sensors.extend(readings),
+                    Err(e) => tracing::warn!("no sensor readings for the dashboard: {e}"),
+                }
+            }
+            Widget::Batteries { .. } => {
+                if !data.batteries.is_empty() {
+                    continue;
+                }
+
+                match battery_levels(sources.db).await {
+                    Ok(levels) => data.batteries = levels,
+                    Err(e) => tracing::warn!("no battery levels for the dashboard: {e}"),
+                }
+            }
+        }
+    }
+
+    data
+}
+
+/// The next firing of each enabled workflow on a `cron` or `sun` trigger,
+/// soonest first.
+pub fn agenda<'a>(
+    workflows: impl Iterator<Item = &'a Workflow>,
+    location: LocationSettings,
+    now: DateTime<Utc>,
+    limit: usize,
+) -> Vec<AgendaEntry> {
+    let local = now.with_timezone(&Perth);
+
+    let mut entries: Vec<AgendaEntry> = workflows
+        .filter(|workflow| workflow.enabled)
+        .filter_map(|workflow| {
+            let (at, trigger) = match workflow.on()? {
+                TriggerMatcher::Cron { schedule } => (schedule.next_after(local).ok()?, "cron"),
+                TriggerMatcher::Sun { transition, offset } => (
+                    calc::next_transition_at(location, now, *transition, *offset)
+                        .with_timezone(&Perth),
+                    match transition {
+                        SunTransition::Sunrise => "sunrise",
+                        SunTransition::Sunset => "sunset",
+                    },
+                ),
+                _ => return None,
+            };
+
+            Some(AgendaEntry {
+                at,
+                title: workflow.name.clone(),
+                trigger,
+            })
+        })
+        .collect();
+
+    entries.sort_by(|a, b| a.at.cmp(&b.at).then_with(|| a.title.cmp(&b.title)));
+    entries.truncate(limit);
+    entries
+}
+
+async fn sensor_readings(
+    db: &Pool<Postgres>,
+    entity_ids: &[String],
+) -> Result<HashMap<String, SensorReading>, sqlx::Error> {
+    if entity_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let rows = sqlx::query!(
+        "SELECT entity_id, name, temperature, humidity FROM latest_temperature_sensor \
+         WHERE entity_id = ANY($1)",
+        entity_ids
+    )
+    .fetch_all(db)
+    .instrument(tracing::info_span!("dashboard_sensor_readings"))
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            (
+                row.entity_id,
+                SensorReading {
+                    name: row.name,
+                    temperature: row.temperature,
+                    humidity: row.humidity,
+                },
+            )
+        })
+        .collect())
+}
+
+async fn battery_levels(db: &Pool<Postgres>) -> Result<Vec<BatteryLevel>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"SELECT name, battery_percent, battery_voltage,
+                  battery_chemistry as "battery_chemistry: BatteryChemistry"
+           FROM device_battery_latest"#
+    )
+    .fetch_all(db)
+    .instrument(tracing::info_span!("dashboard_battery_levels"))
+    .await?;
+
+    let mut levels: Vec<BatteryLevel> = rows
+        .into_iter()
+        .map(|row| {
+            let chemistry = row.battery_chemistry.unwrap_or(BatteryChemistry::Unknown);
+
+            BatteryLevel {
+                name: row.name,
+                percent: row.battery_percent.or_else(|| {
+                    row.battery_voltage
+                        .and_then(|v| voltage_to_percentage(chemistry, v))
+                }),
+                voltage: row.battery_voltage,
+            }
+        })
+        .collect();
+
+    levels.sort_by(|a, b| {
+        let key = |level: &BatteryLevel| level.percent.unwrap_or(f64::INFINITY);
+        key(a).total_cmp(&key(b)).then_with(|| a.name.cmp(&b.name))
+    });
+
+    Ok(levels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use pretty_assertions::assert_eq;
+
+    fn workflow(yaml: &str) -> Workflow {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn the_agenda_lists_the_next_cron_and_sun_firings_soonest_first() {
+        let workflows = [
+            workflow(
+                "name: bins\nslug: bins\non: { type: cron, schedule: \"0 20 * * THU\" }\nrun: []\n",
+            ),
+            workflow(
+                "name: porch light\nslug: porch-light\non: { type: sun, transition: sunset }\nrun: []\n",
+            ),
+            workflow(
+                "name: coffee\nslug: coffee\non: { type: cron, schedule: \"30 6 * * *\" }\nrun: []\n",
+            ),
+            workflow(
+                "name: off\nslug: off\nenabled: false\non: { type: cron, schedule: \"* * * * *\" }\nrun: []\n",
+            ),
+            workflow(
+                "name: door\nslug: door\non: { type: presence, sensor: hall, present: true }\nrun: []\n",
+            ),
+        ];
+        let perth = LocationSettings {
+            latitude: -31.95,
+            longitude: 115.86,
+        };
+        // Tuesday 14:00 in Perth
+        let now = Perth
+            .with_ymd_and_hms(2026, 10, 20, 14, 0, 0)
+            .unwrap()
+            .with_timezone(&Utc);
+
+        let agenda = agenda(workflows.iter(), perth, now, 10);
+
+        let titles: Vec<(&str, &str)> = agenda
+            .iter()
+            .map(|entry| (entry.title.as_str(), entry.trigger))
+            .collect();
+        assert_eq!(
+            titles,
+            vec![
+                ("porch light", "sunset"),
+                ("coffee", "cron"),
+                ("bins", "cron")
+            ]
+        );
+        assert_eq!(
+            agenda[1].at,
+            Perth.with_ymd_and_hms(2026, 10, 21, 6, 30, 0).unwrap()
+        );
+        assert_eq!(
+            agenda[2].at,
+            Perth.with_ymd_and_hms(2026, 10, 22, 20, 0, 0).unwrap()
+        );
+
+        assert_eq!(super::agenda(workflows.iter(), perth, now, 1).len(), 1);
+    }
+}
@@ -0,0 +1,91 @@
+mod data;
+mod widgets;
+
+use crate::eink::image::content_hash;
+use crate::eink::manager::source::{ImageSource, SourceContext, SourceImage};
+use crate::error::AppError;
+use crate::http::get_traced_http_client;
+use crate::settings::EinkMode;
+use data::Sources;
+use reqwest_middleware::ClientWithMiddleware;
+
+/// Draws a display's layout in-process from live gateway data, an
+/// alternative to screenshotting the web dashboard that needs no browser.
+pub struct NativeSource {
+    http: ClientWithMiddleware,
+}
+
+impl Default for NativeSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NativeSource {
+    pub fn new() -> Self {
+        Self {
+            http: get_traced_http_client().unwrap(),
+        }
+    }
+
+    /// Per display, not per layout: the data is read at render time, so two
+    /// displays on one layout can still differ.
+    pub fn key(device_id: &str, layout: &str, (width, height): (u32, u32)) -> String {
+        format!("eink-display/native/{device_id}-{layout}-{width}x{height}.png")
+    }
+}
+
+#[async_trait::async_trait]
+impl ImageSource for NativeSource {
+    fn name(&self) -> &'static str {
+        "native"
+    }
+
+    fn mode(&self) -> EinkMode {
+        EinkMode::Native
+    }
+
+    async fn acquire(&self, ctx: &SourceContext<'_>) -> Result<Option<SourceImage>, AppError> {
+        let Some(layout) = ctx.display.layout.clone() else {
+            tracing::warn!(
+                "native mode selected for `{}` but no layout is configured, skipping render",
+                ctx.display.name
+            );
+            return Ok(None);
+        };
+
+        let settings = ctx.config.settings();
+        let sources = Sources {
+            db: ctx.db,
+            http: &self.http,
+            bom_url: &settings.bom.url,
+            location: settings.location,
+            workflows: settings.workflows.values().collect(),
+        };
+
+        let data = data::gather(sources, &layout, chrono::Utc::now()).await;
+        let dims = ctx.display.target_dims();
+        let image_key = Self::key(&ctx.display.device_id, &layout.name, dims);
+
+        let image = tokio::task::spawn_blocking(move || {
+            let img = widgets::render(&layout, &data, dims)?;
+
+            let mut out = Vec::new();
+            image::DynamicImage::ImageRgb8(img)
+                .write_to(&mut std::io::Cursor::new(&mut out), image::ImageFormat::Png)?;
+
+            Ok::<_, anyhow::Error>(out)
+        })
+        .await
+        .map_err(|e| anyhow::anyhow!("join error: {e}"))??;
+
+        ctx.s3
+            .put_object(&image_key, &image, Some("image/png"))
+            .await?;
+
+        Ok(Some(SourceImage {
+            content_hash: content_hash(&image),
+            image_key,
+        }))
+    }
+}
@@ -0,0 +1,530 @@
+use super::data::{AgendaEntry, BatteryLevel, WidgetData};
+use crate::eink::image::FONT;
+use crate::settings::{DashboardLayout, GridCell, Widget};
+use ab_glyph::{FontRef, PxScale};
+use chrono::{DateTime, NaiveDate};
+use chrono_tz::Tz;
+use image::{Rgb, RgbImage};
+use imageproc::drawing::{draw_filled_rect_mut, draw_hollow_rect_mut, draw_text_mut, text_size};
+use imageproc::rect::Rect;
+
+const BLACK: Rgb<u8> = Rgb([0, 0, 0]);
+const WHITE: Rgb<u8> = Rgb([255, 255, 255]);
+const ACCENT: Rgb<u8> = Rgb([255, 0, 0]);
+
+/// Body text is a thirtieth of the frame's short side: 40px on a 13.3"
+/// Spectra, 16px on a 7.5" panel.
+const UNITS_PER_SIDE: f32 = 30.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Area {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Where `cell` lands on a `width` × `height` frame: the grid is split evenly
+/// and every widget keeps `margin` clear of its neighbours and the edges.
+pub fn area(
+    layout: &DashboardLayout,
+    cell: GridCell,
+    (width, height): (u32, u32),
+    margin: u32,
+) -> Area {
+    let edge =
+        |index: u32, cells: u32, span: u32| margin + index * span.saturating_sub(margin) / cells;
+
+    let left = edge(cell.column, layout.columns, width);
+    let right = edge(cell.column + cell.width, layout.columns, width).saturating_sub(margin);
+    let top = edge(cell.row, layout.rows, height);
+    let bottom = edge(cell.row + cell.height, layout.rows, height).saturating_sub(margin);
+
+    Area {
+        x: left as i32,
+        y: top as i32,
+        width: right.saturating_sub(left).max(1),
+        height: bottom.saturating_sub(top).max(1),
+    }
+}
+
+pub fn render(
+    layout: &DashboardLayout,
+    data: &WidgetData,
+    (width, height): (u32, u32),
+) -> anyhow::Result<RgbImage> {
+    let font = FontRef::try_from_slice(FONT)?;
+    let unit = width.min(height) as f32 / UNITS_PER_SIDE;
+
+    let mut img = RgbImage::from_pixel(width, height, WHITE);
+
+    for placed in &layout.widgets {
+        let mut card = Card {
+            img: &mut img,
+            font: &font,
+            area: area(layout, placed.cell, (width, height), unit as u32 / 2),
+            unit,
+            cursor: 0,
+        };
+
+        match &placed.widget {
+            Widget::Clock => clock(&mut card, data),
+            Widget::Weather { location, days } => weather(&mut card, data, location, *days),
+            Widget::Solar => solar(&mut card, data),
+            Widget::Agenda { limit } => agenda(&mut card, data, *limit),
+            Widget::Sensors { sensors } => sensors_tile(&mut card, data, sensors),
+            Widget::Batteries { limit, below } => batteries(&mut card, data, *limit, *below),
+        }
+    }
+
+    Ok(img)
+}
+
+struct Card<'a> {
+    img: &'a mut RgbImage,
+    font: &'a FontRef<'a>,
+    area: Area,
+    unit: f32,
+    /// How far down the card the next line goes.
+    cursor: i32,
+}
+
+impl Card<'_> {
+    fn padding(&self) -> i32 {
+        (self.unit / 2.0) as i32
+    }
+
+    fn inner_width(&self) -> u32 {
+        self.area.width.saturating_sub(2 * self.padding() as u32)
+    }
+
+    fn border(&mut self) {
+        let thickness = (self.unit / 16.0).max(1.0) as u32;
+        for inset in 0..thickness {
+            if self.area.width <= 2 * inset || self.area.height <= 2 * inset {
+                break;
+            }
+
+            draw_hollow_rect_mut(
+                self.img,
+                Rect::at(self.area.x + inset as i32, self.area.y + inset as i32)
+                    .of_size(self.area.width - 2 * inset, self.area.height - 2 * inset),
+                BLACK,
+            );
+        }
+        self.cursor = self.padding();
+    }
+
+    fn title(&mut self, title: &str) {
+        self.border();
+
+        let scale = PxScale::from(self.unit * 0.7);
+        let (_, text_h) = text_size(scale, self.font, title);
+        let x = self.area.x + self.padding();
+        draw_text_mut(
+            self.img,
+            BLACK,
+            x,
+            self.area.y + self.cursor,
+            scale,
+            self.font,
+            title,
+        );
+        self.cursor += text_h as i32 + (self.unit / 4.0) as i32;
+
+        let rule = (self.unit / 12.0).max(1.0) as u32;
+        draw_filled_rect_mut(
+            self.img,
+            Rect::at(x, self.area.y + self.cursor).of_size(self.inner_width().max(1), rule),
+            BLACK,
+        );
+        self.cursor += rule as i32 + (self.unit / 3.0) as i32;
+    }
+
+    /// A line of `left` text with `right` set flush against the card's right
+    /// edge; `false` once the card is full.
+    fn line(&mut self, left: &str, right: &str, size: f32, color: Rgb<u8>) -> bool {
+        let scale = PxScale::from(self.unit * size);
+        let line_h = text_size(scale, self.font, "Ag").1 as i32;
+
+        if self.cursor + line_h > self.area.height as i32 - self.padding() {
+            return false;
+        }
+
+        let x = self.area.x + self.padding();
+        let y = self.area.y + self.cursor;
+        let inner = self.inner_width();
+
+        let right = fit(self.font, scale, right, inner);
+        let right_w = text_size(scale, self.font, &right).0;
+        let gap = if right.is_empty() {
+            0
+        } else {
+            (self.unit / 2.0) as u32
+        };
+        let left = fit(self.font, scale, left, inner.saturating_sub(right_w + gap));
+
+        draw_text_mut(self.img, color, x, y, scale, self.font, &left);
+        if !right.is_empty() {
+            let right_x = x + inner.saturating_sub(right_w) as i32;
+            draw_text_mut(self.img, color, right_x, y, scale, self.font, &right);
+        }
+
+        self.cursor += line_h + (self.unit / 3.0) as i32;
+        true
+    }
+
+    /// `text` as large as fits the card's width at up to `max_size` units,
+    /// centred across it.
+    fn headline(&mut self, text: &str, max_size: f32) {
+        let max_h = (self.area.height as i32 - self.cursor - self.padding()).max(1) as f32;
+        let mut scale = PxScale::from((self.unit * max_size).min(max_h));
+        let (text_w, _) = text_size(scale, self.font, text);
+        let inner = self.inner_width().max(1) as f32;
+        if text_w as f32 > inner {
+            let shrink = inner / text_w as f32;
+            scale = PxScale::from(scale.y * shrink);
+        }
+
+        let (text_w, text_h) = text_size(scale, self.font, text);
+        let x = self.area.x + (self.area.width as i32 - text_w as i32) / 2;
+        draw_text_mut(
+            self.img,
+            BLACK,
+            x,
+            self.area.y + self.cursor,
+            scale,
+            self.font,
+            text,
+        );
+        self.cursor += text_h as i32 + (self.unit / 2.0) as i32;
+    }
+
+    fn unavailable(&mut self) {
+        self.line("unavailable", "", 1.0, BLACK);
+    }
+}
+
+/// `text`, cut short with an ellipsis if it is wider than `max_width`.
+fn fit(font: &FontRef, scale: PxScale, text: &str, max_width: u32) -> String {
+    if text_size(scale, font, text).0 <= max_width {
+        return text.to_owned();
+    }
+
+    let mut chars: Vec<char> = text.chars().collect();
+    while !chars.is_empty() {
+        chars.pop();
+        let candidate = format!("{}…", chars.iter().collect::<String>().trim_end());
+        if text_size(scale, font, &candidate).0 <= max_width {
+            return candidate;
+        }
+    }
+
+    String::new()
+}
+
+fn clock(card: &mut Card, data: &WidgetData) {
+    card.cursor = card.padding();
+    let time = data.now.format("%H:%M").to_string();
+    let date = data.now.format("%A %-d %B").to_string();
+
+    // leave room under the time for the date line
+    let reserve = card.unit * 1.6;
+    let height = card.area.height as f32 - reserve - 2.0 * card.padding() as f32;
+    card.headline(&time, (height / card.unit).max(1.0));
+    card.line(&date, "", 1.0, BLACK);
+}
+
+pub fn day_label(date_time: &str, index: usize) -> String {
+    if index == 0 {
+        return "Today".to_owned();
+    }
+
+    DateTime::parse_from_rfc3339(date_time)
+        .map(|at| at.date_naive())
+        .or_else(|_| {
+            NaiveDate::parse_from_str(date_time.get(..10).unwrap_or(date_time), "%Y-%m-%d")
+        })
+        .map(|date| date.format("%a").to_string())
+        .unwrap_or_else(|_| date_time.to_owned())
+}
+
+fn weather(card: &mut Card, data: &WidgetData, location: &str, days: usize) {
+    card.title("WEATHER");
+
+    let Some(forecast) = data.forecasts.get(location) else {
+        card.unavailable();
+        return;
+    };
+
+    for (index, day) in forecast.days.iter().take(days).enumerate() {
+        let left = format!("{}  {}", day_label(&day.date_time, index), day.description);
+        let right = format!("{}°–{}°", day.min, day.max);
+        if !card.line(&left, &right, 1.0, BLACK) {
+            break;
+        }
+    }
+}
+
+fn solar(card: &mut Card, data: &WidgetData) {
+    card.title("SOLAR");
+
+    let Some(solar) = &data.solar else {
+        card.unavailable();
+        return;
+    };
+
+    card.headline(
+        &format!("{:.1} kW", solar.current_production_wh / 1000.0),
+        3.0,
+    );
+
+    for (label, kwh) in [
+        ("Today", solar.today_production_kwh),
+        ("Yesterday", solar.yesterday_production_kwh),
+        ("This month", solar.month_production_kwh),
+    ] {
+        if !card.line(label, &format!("{kwh:.1} kWh"), 1.0, BLACK) {
+            break;
+        }
+    }
+}
+
+pub fn when_label(at: DateTime<Tz>, now: DateTime<Tz>) -> String {
+    if at.date_naive() == now.date_naive() {
+        at.format("%H:%M").to_string()
+    } else {
+        at.format("%a %H:%M").to_string()
+    }
+}
+
+fn agenda(card: &mut Card, data: &WidgetData, limit: usize) {
+    card.title("UP NEXT");
+
+    if data.agenda.is_empty() {
+        card.line("nothing scheduled", "", 1.0, BLACK);
+        return;
+    }
+
+    for AgendaEntry { at, title, trigger } in data.agenda.iter().take(limit) {
+        let left = format!("{}  {title}", when_label(*at, data.now));
+        if !card.line(&left, trigger, 1.0, BLACK) {
+            break;
+        }
+    }
+}
+
+fn sensors_tile(card: &mut Card, data: &WidgetData, sensors: &[String]) {
+    card.title("SENSORS");
+
+    for id in sensors {
+        let (left, right) = match data.sensors.get(id) {
+            Some(reading) => (
+                reading.name.clone(),
+                match reading.humidity {
+                    Some(humidity) => format!("{:.1}°  {humidity:.0}%", reading.temperature),
+                    None => format!("{:.1}°", reading.temperature),
+                },
+            ),
+            None => (id.clone(), "—".to_owned()),
+        };
+
+        if !card.line(&left, &right, 1.0, BLACK) {
+            break;
+        }
+    }
+}
+
+pub fn battery_label(level: &BatteryLevel) -> String {
+    match (level.percent, level.voltage) {
+        (Some(percent), _) => format!("{percent:.0}%"),
+        (None, Some(voltage)) => format!("{voltage:.2} V"),
+        (None, None) => "—".to_owned(),
+    }
+}
+
+fn batteries(card: &mut Card, data: &WidgetData, limit: usize, below: Option<f64>) {
+    card.title("BATTERIES");
+
+    if data.batteries.is_empty() {
+        card.unavailable();
+        return;
+    }
+
+    for level in data.batteries.iter().take(limit) {
+        let low = matches!((level.percent, below), (Some(percent), Some(below)) if percent < below);
+        let color = if low { ACCENT } else { BLACK };
+
+        if !card.line(&level.name, &battery_label(level), 1.0, color) {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eink::manager::sources::native::data::SensorReading;
+    use crate::settings::PlacedWidget;
+    use chrono::TimeZone;
+    use chrono_tz::Australia::Perth;
+    use pretty_assertions::assert_eq;
+
+    fn layout(columns: u32, rows: u32, widgets: Vec<(GridCell, Widget)>) -> DashboardLayout {
+        DashboardLayout {
+            name: "test".to_owned(),
+            columns,
+            rows,
+            widgets: widgets
+                .into_iter()
+                .map(|(cell, widget)| PlacedWidget { cell, widget })
+                .collect(),
+        }
+    }
+
+    fn cell(column: u32, row: u32, width: u32, height: u32) -> GridCell {
+        GridCell {
+            column,
+            row,
+            width,
+            height,
+        }
+    }
+
+    fn data() -> WidgetData {
+        WidgetData::empty(Perth.with_ymd_and_hms(2026, 10, 20, 14, 5, 0).unwrap())
+    }
+
+    fn inked(img: &RgbImage, area: Area) -> bool {
+        (area.y..area.y + area.height as i32).any(|y| {
+            (area.x..area.x + area.width as i32)
+                .any(|x| *img.get_pixel(x as u32, y as u32) != WHITE)
+        })
+    }
+
+    #[test]
+    fn cells_split_the_frame_evenly_inside_the_margin() {
+        let layout = layout(2, 2, Vec::new());
+
+        assert_eq!(
+            area(&layout, cell(0, 0, 1, 1), (1200, 1600), 20),
+            Area {
+                x: 20,
+                y: 20,
+                width: 570,
+                height: 770
+            }
+        );
+        assert_eq!(
+            area(&layout, cell(1, 1, 1, 1), (1200, 1600), 20),
+            Area {
+                x: 610,
+                y: 810,
+                width: 570,
+                height: 770
+            }
+        );
+        assert_eq!(
+            area(&layout, cell(0, 0, 2, 1), (1200, 1600), 20),
+            Area {
+                x: 20,
+                y: 20,
+                width: 1160,
+                height: 770
+            }
+        );
+    }
+
+    #[test]
+    fn every_widget_draws_inside_its_own_cell() {
+        let layout = layout(
+            2,
+            3,
+            vec![
+                (cell(0, 0, 2, 1), Widget::Clock),
+                (
+                    cell(0, 1, 1, 1),
+                    Widget::Weather {
+                        location: "perth".to_owned(),
+                        days: 4,
+                    },
+                ),
+                (cell(1, 1, 1, 1), Widget::Solar),
+                (cell(0, 2, 1, 1), Widget::Agenda { limit: 4 }),
+                (
+                    cell(1, 2, 1, 1),
+                    Widget::Sensors {
+                        sensors: vec!["living_room".to_owned()],
+                    },
+                ),
+            ],
+        );
+        let mut data = data();
+        data.sensors.insert(
+            "living_room".to_owned(),
+            SensorReading {
+                name: "Living room".to_owned(),
+                temperature: 22.4,
+                humidity: Some(55.0),
+            },
+        );
+
+        let img = render(&layout, &data, (1200, 1600)).unwrap();
+
+        assert_eq!(img.dimensions(), (1200, 1600));
+        for placed in &layout.widgets {
+            assert!(
+                inked(&img, area(&layout, placed.cell, (1200, 1600), 20)),
+                "{} drew nothing",
+                placed.widget.as_str()
+            );
+        }
+        // the gutter between the second and third rows stays blank
+        assert!(!inked(
+            &img,
+            Area {
+                x: 0,
+                y: 1055,
+                width: 1200,
+                height: 15
+            }
+        ));
+    }
+
+    #[test]
+    fn an_empty_layout_renders_a_blank_frame() {
+        let img = render(&layout(1, 1, Vec::new()), &data(), (800, 480)).unwrap();
+
+        assert!(img.pixels().all(|pixel| *pixel == WHITE));
+    }
+
+    #[test]
+    fn labels_are_short_enough_for_a_line() {
+        let now = data().now;
+
+        assert_eq!(day_label("2026-10-21T00:00:00+08:00", 0), "Today");
+        assert_eq!(day_label("2026-10-21T00:00:00+08:00", 1), "Wed");
+        assert_eq!(day_label("2026-10-22", 2), "Thu");
+        assert_eq!(when_label(now + chrono::TimeDelta::hours(3), now), "17:05");
+        assert_eq!(
+            when_label(now + chrono::TimeDelta::days(2), now),
+            "Thu 14:05"
+        );
+        assert_eq!(
+            battery_label(&BatteryLevel {
+                name: "hall".to_owned(),
+                percent: Some(7.6),
+                voltage: Some(3.4)
+            }),
+            "8%"
+        );
+        assert_eq!(
+            battery_label(&BatteryLevel {
+                name: "hall".to_owned(),
+                percent: None,
+                voltage: Some(3.4)
+            }),
+            "3.40 V"
+        );
+    }
+}
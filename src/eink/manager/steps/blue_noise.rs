@@ -0,0 +1,163 @@
+//! A tileable blue-noise threshold map, built once by void-and-cluster.
+//! Thresholds from it dither without the visible grid of a Bayer matrix.
+
+use std::sync::OnceLock;
+
+const SIZE: usize = 64;
+const SIGMA: f32 = 1.5;
+const SEED_DENSITY: usize = 10;
+
+/// Threshold in `0..1` for the pixel at `x`, `y`.
+pub(super) fn threshold(x: u32, y: u32) -> f32 {
+    static MAP: OnceLock<Vec<f32>> = OnceLock::new();
+
+    let map = MAP.get_or_init(void_and_cluster);
+    map[(y as usize % SIZE) * SIZE + x as usize % SIZE]
+}
+
+/// Each pixel's Gaussian-weighted closeness to the set ones, wrapping at the
+/// edges so the map tiles.
+#[derive(Clone)]
+struct Energy {
+    kernel: Vec<f32>,
+    set: Vec<bool>,
+    energy: Vec<f32>,
+}
+
+impl Energy {
+    fn new() -> Self {
+        let mut kernel = vec![0.0; SIZE * SIZE];
+        for dy in 0..SIZE {
+            for dx in 0..SIZE {
+                let fx = dx.min(SIZE - dx) as f32;
+                let fy = dy.min(SIZE - dy) as f32;
+                kernel[dy * SIZE + dx] = (-(fx * fx + fy * fy) / (2.0 * SIGMA * SIGMA)).exp();
+            }
+        }
+
+        Self {
+            kernel,
+            set: vec![false; SIZE * SIZE],
+            energy: vec![0.0; SIZE * SIZE],
+        }
+    }
+
+    fn toggle(&mut self, index: usize) {
+        self.set[index] = !self.set[index];
+        let sign = if self.set[index] { 1.0 } else { -1.0 };
+        let (x, y) = (index % SIZE, index / SIZE);
+
+        for (other, energy) in self.energy.iter_mut().enumerate() {
+            let dx = (other % SIZE + SIZE - x) % SIZE;
+            let dy = (other / SIZE + SIZE - y) % SIZE;
+            *energy += sign * self.kernel[dy * SIZE + dx];
+        }
+    }
+
+    /// The set pixel with the most set neighbours.
+    fn tightest_cluster(&self) -> usize {
+        self.extreme(true, |a, b| a > b)
+    }
+
+    /// The unset pixel furthest from any set one.
+    fn largest_void(&self) -> usize {
+        self.extreme(false, |a, b| a < b)
+    }
+
+    fn extreme(&self, set: bool, better: impl Fn(f32, f32) -> bool) -> usize {
+        let mut best = None;
+
+        for (index, &energy) in self.energy.iter().enumerate() {
+            if self.set[index] != set {
+                continue;
+            }
+            if best.is_none_or(|(_, best_energy)| better(energy, best_energy)) {
+                best = Some((index, energy));
+            }
+        }
+
+        best.map_or(0, |(index, _)| index)
+    }
+}
+
+fn void_and_cluster() -> Vec<f32> {
+    let pixels = SIZE * SIZE;
+    let mut pattern = Energy::new();
+
+    // a fixed seed, so the map and every frame dithered with it are stable
+    let mut state = 0x2545_f491_u32;
+    let mut placed = 0;
+    while placed < pixels / SEED_DENSITY {
+        state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        let index = (state >> 8) as usize % pixels;
+        if !pattern.set[index] {
+            pattern.toggle(index);
+            placed += 1;
+        }
+    }
+
+    // spread the seed out until moving its tightest point cannot help
+    for _ in 0..pixels {
+        let cluster = pattern.tightest_cluster();
+        pattern.toggle(cluster);
+        let void = pattern.largest_void();
+        pattern.toggle(void);
+
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut rank = vec![0usize; pixels];
+
+    let mut removing = pattern.clone();
+    for r in (0..placed).rev() {
+        let cluster = removing.tightest_cluster();
+        removing.toggle(cluster);
+        rank[cluster] = r;
+    }
+
+    for r in placed..pixels {
+        let void = pattern.largest_void();
+        pattern.toggle(void);
+        rank[void] = r;
+    }
+
+    rank.into_iter()
+        .map(|r| (r as f32 + 0.5) / pixels as f32)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_threshold_appears_once_per_tile() {
+        let mut thresholds: Vec<f32> = (0..SIZE as u32)
+            .flat_map(|y| (0..SIZE as u32).map(move |x| threshold(x, y)))
+            .collect();
+        thresholds.sort_by(f32::total_cmp);
+
+        for (rank, threshold) in thresholds.iter().enumerate() {
+            assert_eq!(*threshold, (rank as f32 + 0.5) / (SIZE * SIZE) as f32);
+        }
+        assert_eq!(threshold(3, 5), threshold(3 + SIZE as u32, 5 + SIZE as u32));
+    }
+
+    #[test]
+    fn low_thresholds_are_spread_out() {
+        let lowest: Vec<(usize, usize)> = (0..SIZE)
+            .flat_map(|y| (0..SIZE).map(move |x| (x, y)))
+            .filter(|&(x, y)| threshold(x as u32, y as u32) < 0.05)
+            .collect();
+
+        for (i, &(ax, ay)) in lowest.iter().enumerate() {
+            for &(bx, by) in &lowest[i + 1..] {
+                let dx = ax.abs_diff(bx).min(SIZE - ax.abs_diff(bx));
+                let dy = ay.abs_diff(by).min(SIZE - ay.abs_diff(by));
+                assert!(dx + dy > 1, "({ax}, {ay}) and ({bx}, {by}) touch");
+            }
+        }
+    }
+}
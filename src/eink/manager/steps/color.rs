@@ -0,0 +1,91 @@
+use crate::settings::ColorSpace;
+
+/// `rgb` in `space`, where squared distance tracks how far apart two colours
+/// look.
+pub(super) fn to_space(space: ColorSpace, [r, g, b]: [f32; 3]) -> [f32; 3] {
+    match space {
+        ColorSpace::Rgb => [r, g, b],
+        ColorSpace::Cielab => cielab(linear(r), linear(g), linear(b)),
+        ColorSpace::Oklab => oklab(linear(r), linear(g), linear(b)),
+    }
+}
+
+fn linear(channel: f32) -> f32 {
+    let c = channel.clamp(0.0, 255.0) / 255.0;
+
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// CIELAB under D65, so distance is CIE76 delta E.
+fn cielab(r: f32, g: f32, b: f32) -> [f32; 3] {
+    let x = (0.412_456_4 * r + 0.357_576_1 * g + 0.180_437_5 * b) / 0.950_47;
+    let y = 0.212_672_9 * r + 0.715_152_2 * g + 0.072_175 * b;
+    let z = (0.019_333_9 * r + 0.119_192 * g + 0.950_304_1 * b) / 1.088_83;
+
+    let f = |t: f32| {
+        if t > 216.0 / 24389.0 {
+            t.cbrt()
+        } else {
+            (24389.0 / 27.0 * t + 16.0) / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+fn oklab(r: f32, g: f32, b: f32) -> [f32; 3] {
+    let l = (0.412_221_46 * r + 0.536_332_55 * g + 0.051_445_995 * b).cbrt();
+    let m = (0.211_903_5 * r + 0.680_699_5 * g + 0.107_396_96 * b).cbrt();
+    let s = (0.088_302_46 * r + 0.281_718_85 * g + 0.629_978_7 * b).cbrt();
+
+    [
+        0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+        1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+        0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(actual: [f32; 3], expected: [f32; 3]) -> bool {
+        actual
+            .iter()
+            .zip(expected)
+            .all(|(a, e)| (a - e).abs() < 0.01)
+    }
+
+    #[test]
+    fn white_and_black_sit_on_the_lightness_axis() {
+        let white = [255.0, 255.0, 255.0];
+        let black = [0.0, 0.0, 0.0];
+
+        assert!(close(
+            to_space(ColorSpace::Cielab, white),
+            [100.0, 0.0, 0.0]
+        ));
+        assert!(close(to_space(ColorSpace::Cielab, black), [0.0, 0.0, 0.0]));
+        assert!(close(to_space(ColorSpace::Oklab, white), [1.0, 0.0, 0.0]));
+        assert!(close(to_space(ColorSpace::Oklab, black), [0.0, 0.0, 0.0]));
+    }
+
+    #[test]
+    fn red_lands_on_its_published_coordinates() {
+        let red = [255.0, 0.0, 0.0];
+
+        assert!(close(
+            to_space(ColorSpace::Cielab, red).map(|c| (c * 10.0).round() / 10.0),
+            [53.2, 80.1, 67.2]
+        ));
+        assert!(close(
+            to_space(ColorSpace::Oklab, red).map(|c| (c * 1000.0).round() / 1000.0),
+            [0.628, 0.225, 0.126]
+        ));
+    }
+}
@@ -0,0 +1,320 @@
+use super::blue_noise;
+use super::color::to_space;
+use crate::eink::manager::frame::FrameContext;
+use crate::settings::{ColorSpace, Dither, DitherAlgorithm};
+use image::RgbImage;
+use image::imageops::ColorMap;
+
+/// Error diffusion weights as `(dx, dy, weight)` and the divisor they share.
+type Kernel = (&'static [(i32, i32, f32)], f32);
+
+/// Diffuses only three quarters of the error, trading shadow detail for
+/// cleaner flat areas.
+const ATKINSON: Kernel = (
+    &[
+        (1, 0, 1.0),
+        (2, 0, 1.0),
+        (-1, 1, 1.0),
+        (0, 1, 1.0),
+        (1, 1, 1.0),
+        (0, 2, 1.0),
+    ],
+    8.0,
+);
+
+const STUCKI: Kernel = (
+    &[
+        (1, 0, 8.0),
+        (2, 0, 4.0),
+        (-2, 1, 2.0),
+        (-1, 1, 4.0),
+        (0, 1, 8.0),
+        (1, 1, 4.0),
+        (2, 1, 2.0),
+        (-2, 2, 1.0),
+        (-1, 2, 2.0),
+        (0, 2, 4.0),
+        (1, 2, 2.0),
+        (2, 2, 1.0),
+    ],
+    42.0,
+);
+
+const JARVIS_JUDICE_NINKE: Kernel = (
+    &[
+        (1, 0, 7.0),
+        (2, 0, 5.0),
+        (-2, 1, 3.0),
+        (-1, 1, 5.0),
+        (0, 1, 7.0),
+        (1, 1, 5.0),
+        (2, 1, 3.0),
+        (-2, 2, 1.0),
+        (-1, 2, 3.0),
+        (0, 2, 5.0),
+        (1, 2, 3.0),
+        (2, 2, 1.0),
+    ],
+    48.0,
+);
+
+struct PanelPalette {
+    colors: Vec<(f32, f32, f32, u8)>,
+    space: ColorSpace,
+    /// `colors` in `space`.
+    matched: Vec<[f32; 3]>,
+}
+
+impl PanelPalette {
+    fn new(colors: &[(f32, f32, f32, u8)], space: ColorSpace) -> Self {
+        Self {
+            colors: colors.to_vec(),
+            space,
+            matched: colors
+                .iter()
+                .map(|&(r, g, b, _)| to_space(space, [r, g, b]))
+                .collect(),
+        }
+    }
+
+    fn nearest(&self, rgb: [f32; 3]) -> usize {
+        let [x, y, z] = to_space(self.space, rgb);
+
+        let mut closest = 0;
+        let mut min_dist = f32::MAX;
+
+        for (i, &[px, py, pz]) in self.matched.iter().enumerate() {
+            let dist = (x - px).powi(2) + (y - py).powi(2) + (z - pz).powi(2);
+            if dist < min_dist {
+                min_dist = dist;
+                closest = i;
+            }
+        }
+
+        closest
+    }
+
+    fn rgb(&self, index: usize) -> image::Rgb<u8> {
+        let (r, g, b, _) = self.colors[index];
+        image::Rgb([r as u8, g as u8, b as u8])
+    }
+}
+
+impl ColorMap for PanelPalette {
+    type Color = image::Rgb<u8>;
+
+    fn index_of(&self, color: &Self::Color) -> usize {
+        self.nearest(color.0.map(f32::from))
+    }
+
+    fn lookup(&self, index: usize) -> Option<Self::Color> {
+        (index < self.colors.len()).then(|| self.rgb(index))
+    }
+
+    fn has_lookup(&self) -> bool {
+        true
+    }
+
+    fn map_color(&self, color: &mut Self::Color) {
+        let index = self.index_of(color);
+        if let Some(mapped) = self.lookup(index) {
+            *color = mapped;
+        }
+    }
+}
+
+/// Dither `img` to `palette` in place, returning each pixel's palette index.
+pub(super) fn dither_to_palette(
+    img: &mut RgbImage,
+    palette: &[(f32, f32, f32, u8)],
+    dither: &Dither,
+) -> Vec<u8> {
+    let map = PanelPalette::new(palette, dither.color_space);
+
+    let indices = match dither.algorithm {
+        DitherAlgorithm::FloydSteinberg => {
+            image::imageops::dither(img, &map);
+            img.pixels().map(|pixel| map.index_of(pixel)).collect()
+        }
+        DitherAlgorithm::Atkinson => diffuse(img, &map, ATKINSON),
+        DitherAlgorithm::Stucki => diffuse(img, &map, STUCKI),
+        DitherAlgorithm::JarvisJudiceNinke => diffuse(img, &map, JARVIS_JUDICE_NINKE),
+        DitherAlgorithm::Bayer => ordered(img, &map, bayer),
+        DitherAlgorithm::BlueNoise => ordered(img, &map, blue_noise::threshold),
+    };
+
+    indices.into_iter().map(|i| map.colors[i].3).collect()
+}
+
+/// What an encoder's output depends on. Floyd–Steinberg in RGB fingerprints
+/// as the palette alone, as it did before dithering was selectable, so those
+/// frames keep their cached hashes.
+pub(super) fn palette_fingerprint(ctx: &FrameContext) -> String {
+    let palette = ctx
+        .palette
+        .iter()
+        .map(|(r, g, b, index)| format!("{r}:{g}:{b}:{index}"))
+        .collect::<Vec<_>>()
+        .join(",");
+
+    match (ctx.dither.algorithm, ctx.dither.color_space) {
+        (DitherAlgorithm::FloydSteinberg, ColorSpace::Rgb) => palette,
+        (algorithm, space) => format!("{palette};{}:{}", algorithm.as_str(), space.as_str()),
+    }
+}
+
+fn diffuse(img: &mut RgbImage, map: &PanelPalette, (kernel, divisor): Kernel) -> Vec<usize> {
+    let (width, height) = img.dimensions();
+    let (w, h) = (width as i32, height as i32);
+
+    let mut pending: Vec<[f32; 3]> = img.pixels().map(|pixel| pixel.0.map(f32::from)).collect();
+    let mut indices = Vec::with_capacity(pending.len());
+
+    for y in 0..h {
+        for x in 0..w {
+            let old = pending[(y * w + x) as usize].map(|c| c.clamp(0.0, 255.0));
+            let index = map.nearest(old);
+            let (r, g, b, _) = map.colors[index];
+            let error = [old[0] - r, old[1] - g, old[2] - b];
+
+            for &(dx, dy, weight) in kernel {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || nx >= w || ny >= h {
+                    continue;
+                }
+
+                let neighbour = &mut pending[(ny * w + nx) as usize];
+                for (channel, e) in neighbour.iter_mut().zip(error) {
+                    *channel += e * weight / divisor;
+                }
+            }
+
+            img.put_pixel(x as u32, y as u32, map.rgb(index));
+            indices.push(index);
+        }
+    }
+
+    indices
+}
+
+/// Offset each pixel by a threshold in `0..1` before matching it; the fewer
+/// colours the panel has, the wider the offset.
+fn ordered(
+    img: &mut RgbImage,
+    map: &PanelPalette,
+    threshold: impl Fn(u32, u32) -> f32,
+) -> Vec<usize> {
+    let spread = 255.0 / (map.colors.len().max(1) as f32).cbrt();
+    let mut indices = Vec::with_capacity((img.width() * img.height()) as usize);
+
+    for (x, y, pixel) in img.enumerate_pixels_mut() {
+        let offset = (threshold(x, y) - 0.5) * spread;
+        let index = map.nearest(pixel.0.map(|c| (f32::from(c) + offset).clamp(0.0, 255.0)));
+
+        *pixel = map.rgb(index);
+        indices.push(index);
+    }
+
+    indices
+}
+
+/// The 8x8 Bayer matrix, as a threshold in `0..1`.
+fn bayer(x: u32, y: u32) -> f32 {
+    let mut rank = 0;
+    for bit in 0..3 {
+        let (xb, yb) = ((x >> bit) & 1, (y >> bit) & 1);
+        rank |= (((xb ^ yb) << 1) | yb) << (2 * (2 - bit));
+    }
+
+    (rank as f32 + 0.5) / 64.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    const ALGORITHMS: [DitherAlgorithm; 6] = [
+        DitherAlgorithm::FloydSteinberg,
+        DitherAlgorithm::Atkinson,
+        DitherAlgorithm::Stucki,
+        DitherAlgorithm::JarvisJudiceNinke,
+        DitherAlgorithm::Bayer,
+        DitherAlgorithm::BlueNoise,
+    ];
+
+    fn dither(algorithm: DitherAlgorithm, color_space: ColorSpace) -> Dither {
+        Dither {
+            algorithm,
+            color_space,
+            ..Dither::default()
+        }
+    }
+
+    #[rstest]
+    fn dither_maps_flat_colours_to_their_palette_index(
+        #[values(ColorSpace::Rgb, ColorSpace::Cielab, ColorSpace::Oklab)] space: ColorSpace,
+    ) {
+        let palette = vec![
+            (0.0, 0.0, 0.0, 0u8),
+            (255.0, 255.0, 255.0, 1u8),
+            (255.0, 0.0, 0.0, 3u8),
+        ];
+
+        for algorithm in ALGORITHMS {
+            let mut img = RgbImage::from_pixel(8, 8, image::Rgb([250, 10, 8]));
+            let indices = dither_to_palette(&mut img, &palette, &dither(algorithm, space));
+
+            assert_eq!(indices.len(), 64);
+            assert!(indices.iter().all(|&index| index == 3), "{algorithm:?}");
+            assert!(img.pixels().all(|pixel| pixel.0 == [255, 0, 0]));
+        }
+    }
+
+    #[rstest]
+    fn dither_only_emits_palette_indices(
+        #[values(ColorSpace::Rgb, ColorSpace::Cielab, ColorSpace::Oklab)] space: ColorSpace,
+    ) {
+        let palette = vec![(0.0, 0.0, 0.0, 0u8), (255.0, 255.0, 255.0, 1u8)];
+
+        for algorithm in ALGORITHMS {
+            let mut img = RgbImage::from_pixel(16, 16, image::Rgb([128, 128, 128]));
+            let indices = dither_to_palette(&mut img, &palette, &dither(algorithm, space));
+
+            assert!(indices.iter().all(|index| [0, 1].contains(index)));
+            assert!(indices.contains(&0), "{algorithm:?}");
+            assert!(indices.contains(&1), "{algorithm:?}");
+        }
+    }
+
+    #[test]
+    fn the_bayer_matrix_holds_every_threshold_once() {
+        let mut ranks: Vec<u32> = (0..8)
+            .flat_map(|y| (0..8).map(move |x| (bayer(x, y) * 64.0) as u32))
+            .collect();
+        ranks.sort_unstable();
+
+        assert_eq!(ranks, (0..64).collect::<Vec<_>>());
+        assert_eq!(bayer(0, 0), bayer(8, 8));
+    }
+
+    #[test]
+    fn the_default_dither_fingerprints_as_the_palette_alone() {
+        let ctx = |dither: Dither| FrameContext {
+            crop_to: None,
+            sleep_label: None,
+            palette: vec![(0.0, 0.0, 0.0, 0), (255.0, 255.0, 255.0, 1)],
+            panel: &crate::eink::panel::SPECTRA6_13IN3,
+            dither,
+        };
+
+        assert_eq!(
+            palette_fingerprint(&ctx(Dither::default())),
+            "0:0:0:0,255:255:255:1"
+        );
+        assert_eq!(
+            palette_fingerprint(&ctx(dither(DitherAlgorithm::Bayer, ColorSpace::Oklab))),
+            "0:0:0:0,255:255:255:1;bayer:oklab"
+        );
+    }
+}
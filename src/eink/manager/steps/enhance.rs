@@ -0,0 +1,90 @@
+use crate::eink::manager::frame::{FrameContext, FrameStep};
+use image::RgbImage;
+
+/// Saturation then contrast, ahead of dithering; e-paper inks are duller
+/// than the screen a photo was graded on.
+pub struct Enhance;
+
+impl FrameStep for Enhance {
+    fn name(&self) -> &'static str {
+        "enhance"
+    }
+
+    fn fingerprint(&self, ctx: &FrameContext) -> Option<String> {
+        let (contrast, saturation) = (ctx.dither.contrast, ctx.dither.saturation);
+
+        (contrast != 1.0 || saturation != 1.0)
+            .then(|| format!("contrast={contrast}:saturation={saturation}"))
+    }
+
+    fn apply(&self, ctx: &FrameContext, img: &mut RgbImage) -> anyhow::Result<()> {
+        let (contrast, saturation) = (ctx.dither.contrast, ctx.dither.saturation);
+
+        for pixel in img.pixels_mut() {
+            let [r, g, b] = pixel.0.map(f32::from);
+            let luma = 0.299 * r + 0.587 * g + 0.114 * b;
+
+            pixel.0 = [r, g, b].map(|c| {
+                let saturated = luma + (c - luma) * saturation;
+                ((saturated - 128.0) * contrast + 128.0)
+                    .round()
+                    .clamp(0.0, 255.0) as u8
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Dither;
+
+    fn ctx(contrast: f32, saturation: f32) -> FrameContext {
+        FrameContext {
+            crop_to: None,
+            sleep_label: None,
+            palette: Vec::new(),
+            panel: &crate::eink::panel::SPECTRA6_13IN3,
+            dither: Dither {
+                contrast,
+                saturation,
+                ..Dither::default()
+            },
+        }
+    }
+
+    #[test]
+    fn no_boost_is_inactive() {
+        assert_eq!(Enhance.fingerprint(&ctx(1.0, 1.0)), None);
+        assert!(Enhance.fingerprint(&ctx(1.1, 1.0)).is_some());
+    }
+
+    #[test]
+    fn saturation_pushes_colour_away_from_grey_and_leaves_grey_alone() {
+        let mut img = RgbImage::from_fn(2, 1, |x, _| match x {
+            0 => image::Rgb([200, 120, 100]),
+            _ => image::Rgb([90, 90, 90]),
+        });
+
+        Enhance.apply(&ctx(1.0, 1.5), &mut img).unwrap();
+
+        let [r, g, b] = img.get_pixel(0, 0).0;
+        assert!(r > 200 && g < 120 && b < 100);
+        assert_eq!(img.get_pixel(1, 0).0, [90, 90, 90]);
+    }
+
+    #[test]
+    fn contrast_spreads_tones_around_the_midpoint() {
+        let mut img = RgbImage::from_fn(2, 1, |x, _| match x {
+            0 => image::Rgb([100, 100, 100]),
+            _ => image::Rgb([160, 160, 160]),
+        });
+
+        Enhance.apply(&ctx(2.0, 1.0), &mut img).unwrap();
+
+        assert_eq!(img.get_pixel(0, 0).0, [72, 72, 72]);
+        assert_eq!(img.get_pixel(1, 0).0, [192, 192, 192]);
+    }
+}
@@ -0,0 +1,95 @@
+use super::dither::{dither_to_palette, palette_fingerprint};
+use crate::eink::manager::frame::{FrameContext, FrameEncoder};
+use crate::eink::panel::PixelOrder;
+use image::RgbImage;
+
+/// A greyscale PNG at 1 or 2 bits per pixel, for TRMNL panels. The palette's
+/// indices are the grey levels (`0` black, highest white) and the panel's bits
+/// per pixel picks the depth.
+pub struct GreyscalePng;
+
+impl FrameEncoder for GreyscalePng {
+    fn name(&self) -> &'static str {
+        "greyscale_png"
+    }
+
+    fn fingerprint(&self, ctx: &FrameContext) -> String {
+        palette_fingerprint(ctx)
+    }
+
+    fn encode(&self, ctx: &FrameContext, img: &mut RgbImage) -> anyhow::Result<Vec<u8>> {
+        let depth = match ctx.panel.bits_per_pixel {
+            1 => png::BitDepth::One,
+            2 => png::BitDepth::Two,
+            bits => anyhow::bail!(
+                "a {bits}-bit {} panel is not a greyscale panel",
+                ctx.panel.name
+            ),
+        };
+        if ctx.panel.pixel_order != PixelOrder::MsbFirst {
+            anyhow::bail!("png rows are packed most significant pixel first");
+        }
+
+        let levels = dither_to_palette(img, &ctx.palette, &ctx.dither);
+        let (width, height) = img.dimensions();
+        let packed = ctx.panel.pack(&levels, width);
+
+        let mut out = Vec::new();
+        let mut encoder = png::Encoder::new(&mut out, width, height);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(depth);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&packed)?;
+        writer.finish()?;
+
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eink::panel::{PanelProfile, TRMNL_1BIT, TRMNL_2BIT};
+    use crate::settings::Dither;
+
+    fn ctx(panel: &'static PanelProfile) -> FrameContext {
+        FrameContext {
+            crop_to: None,
+            sleep_label: None,
+            palette: panel
+                .palette
+                .iter()
+                .map(|&(_, r, g, b, index)| (r, g, b, index))
+                .collect(),
+            panel,
+            dither: Dither::default(),
+        }
+    }
+
+    #[test]
+    fn one_bit_frames_decode_to_black_and_white() {
+        let mut img = RgbImage::from_fn(20, 4, |x, _| match x < 10 {
+            true => image::Rgb([10, 10, 10]),
+            false => image::Rgb([245, 245, 245]),
+        });
+
+        let encoded = GreyscalePng.encode(&ctx(&TRMNL_1BIT), &mut img).unwrap();
+        let decoded = image::load_from_memory(&encoded).unwrap().to_luma8();
+
+        assert_eq!(decoded.dimensions(), (20, 4));
+        assert_eq!(decoded.get_pixel(0, 0).0, [0]);
+        assert_eq!(decoded.get_pixel(19, 3).0, [255]);
+    }
+
+    #[test]
+    fn two_bit_frames_keep_the_middle_greys() {
+        let mut img = RgbImage::from_pixel(7, 3, image::Rgb([170, 170, 170]));
+
+        let encoded = GreyscalePng.encode(&ctx(&TRMNL_2BIT), &mut img).unwrap();
+        let decoded = image::load_from_memory(&encoded).unwrap().to_luma8();
+
+        assert_eq!(decoded.dimensions(), (7, 3));
+        assert!(decoded.pixels().all(|pixel| pixel.0 == [170]));
+    }
+}
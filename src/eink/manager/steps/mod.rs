@@ -1,9 +1,16 @@
+mod blue_noise;
+mod color;
 mod crop;
+mod dither;
+mod enhance;
+mod greyscale;
 mod pack;
 mod rotate;
 mod sleep_label;
 
 pub use crop::CropCover;
-pub use pack::FloydSteinbergPacked;
-pub use rotate::RotateToPortrait;
+pub use enhance::Enhance;
+pub use greyscale::GreyscalePng;
+pub use pack::DitheredPacked;
+pub use rotate::RotateToPanel;
 pub use sleep_label::SleepLabel;
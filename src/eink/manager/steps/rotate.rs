@@ -1,28 +1,30 @@
 use crate::eink::manager::frame::{FrameContext, FrameStep};
-use crate::eink::panel::{PANEL_HEIGHT, PANEL_WIDTH};
 use image::RgbImage;
 
-pub struct RotateToPortrait;
+pub struct RotateToPanel;
 
-impl FrameStep for RotateToPortrait {
+impl FrameStep for RotateToPanel {
     fn name(&self) -> &'static str {
         "rotate"
     }
 
-    fn fingerprint(&self, _ctx: &FrameContext) -> Option<String> {
-        Some(format!("{PANEL_WIDTH}x{PANEL_HEIGHT}"))
+    fn fingerprint(&self, ctx: &FrameContext) -> Option<String> {
+        Some(format!("{}x{}", ctx.panel.width, ctx.panel.height))
     }
 
-    fn apply(&self, _ctx: &FrameContext, img: &mut RgbImage) -> anyhow::Result<()> {
+    fn apply(&self, ctx: &FrameContext, img: &mut RgbImage) -> anyhow::Result<()> {
+        let (width, height) = (ctx.panel.width, ctx.panel.height);
+
         match img.dimensions() {
-            (PANEL_HEIGHT, PANEL_WIDTH) => {
+            (w, h) if (w, h) == (width, height) => Ok(()),
+            (w, h) if (w, h) == (height, width) => {
                 *img = image::imageops::rotate90(img);
                 Ok(())
             }
-            (PANEL_WIDTH, PANEL_HEIGHT) => Ok(()),
             (w, h) => Err(anyhow::anyhow!(
-                "image dimensions must be {PANEL_HEIGHT}x{PANEL_WIDTH} (will be rotated) or \
-                 {PANEL_WIDTH}x{PANEL_HEIGHT}, got {w}x{h}"
+                "image dimensions must be {height}x{width} (will be rotated) or \
+                 {width}x{height} for a {} panel, got {w}x{h}",
+                ctx.panel.name
             )),
         }
     }
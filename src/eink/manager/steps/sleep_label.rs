@@ -1,9 +1,9 @@
+use crate::eink::image::FONT;
 use crate::eink::manager::frame::{FrameContext, FrameStep};
 use ab_glyph::{FontRef, PxScale};
 use image::RgbImage;
 use imageproc::drawing::{draw_text_mut, text_size};
 
-const LABEL_FONT: &[u8] = include_bytes!("../../../../assets/LiberationSans-Bold.ttf");
 const LABEL_SCALE: f32 = 120.0;
 const LABEL_MARGIN: i32 = 48;
 
@@ -23,7 +23,7 @@ impl FrameStep for SleepLabel {
             return Ok(());
         };
 
-        let Ok(font) = FontRef::try_from_slice(LABEL_FONT) else {
+        let Ok(font) = FontRef::try_from_slice(FONT) else {
             tracing::warn!("failed to load label font, skipping sleep label");
             return Ok(());
         };
@@ -0,0 +1,67 @@
+use super::EinkDisplayManager;
+use super::config::refresh_secs;
+use super::resolve::ResolvedDisplay;
+use crate::eink::panel::is_frame_hash;
+
+const TRMNL_CACHE_PREFIX: &str = "eink-display/trmnl/";
+
+pub fn trmnl_cache_key(hash: &str) -> Option<String> {
+    is_frame_hash(hash).then(|| format!("{TRMNL_CACHE_PREFIX}{hash}.png"))
+}
+
+pub struct TrmnlDisplay {
+    pub refresh_secs: u32,
+    /// Hash of the frame to show, cached under [`trmnl_cache_key`].
+    pub hash: Option<String>,
+}
+
+impl EinkDisplayManager {
+    pub async fn trmnl_display(&self, resolved: &ResolvedDisplay) -> TrmnlDisplay {
+        let mut display = TrmnlDisplay {
+            refresh_secs: refresh_secs(resolved),
+            hash: None,
+        };
+
+        let Some(plan) = self
+            .plan_frame(
+                resolved,
+                &self.trmnl_frames,
+                Some(resolved.target_dims()),
+                resolved.palette.clone(),
+            )
+            .await
+        else {
+            tracing::warn!(
+                device_id = resolved.device_id,
+                "no image to serve, skipping this cycle"
+            );
+            return display;
+        };
+
+        let Some(key) = trmnl_cache_key(&plan.hash) else {
+            tracing::error!(hash = %plan.hash, "render plan hash is not a frame hash");
+            return display;
+        };
+
+        if self
+            .ensure_rendered(&plan, &key, self.trmnl_frames.clone())
+            .await
+            .is_some()
+        {
+            display.hash = Some(plan.hash);
+        }
+
+        display
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trmnl_cache_key_only_accepts_frame_hashes() {
+        assert!(trmnl_cache_key(&"a".repeat(64)).is_some_and(|key| key.ends_with(".png")));
+        assert_eq!(trmnl_cache_key("../packed/x"), None);
+    }
+}
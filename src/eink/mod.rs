@@ -3,6 +3,8 @@ pub mod image;
 pub mod manager;
 pub mod panel;
 pub mod partial;
+pub mod preview;
+pub mod upload;
 
 pub use manager::EinkDisplayManager;
 pub use panel::PartialWindow;
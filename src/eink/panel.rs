@@ -1,15 +1,202 @@
+use crate::settings::{Orientation, Panel};
 use serde::{Deserialize, Serialize};
 
-pub const PANEL_WIDTH: u32 = 1200;
-pub const PANEL_HEIGHT: u32 = 1600;
-const PANEL_HALF_WIDTH: u32 = PANEL_WIDTH / 2;
-const WINDOW_X_ALIGN: u32 = 4;
-pub const WINDOW_MIN_WIDTH: u32 = 16;
-
 const PACKED_CACHE_PREFIX: &str = "eink-display/packed/";
-pub const PACKED_ROW_BYTES: usize = 600;
-pub const PACKED_FRAME_HEIGHT: usize = 1600;
-pub const PACKED_FRAME_SIZE: usize = PACKED_ROW_BYTES * PACKED_FRAME_HEIGHT;
+
+/// Palette entries as `(name, r, g, b, index)`; the index is what the panel's
+/// controller is sent for that colour.
+pub type PanelPalette = &'static [(&'static str, f32, f32, f32, u8)];
+
+/// Which end of a packed byte holds the leftmost pixel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelOrder {
+    MsbFirst,
+    LsbFirst,
+}
+
+/// Alignment a partial refresh window must keep for the panel's controller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowRules {
+    pub x_align: u32,
+    pub y_align: u32,
+    pub min_width: u32,
+    /// Column where two controllers meet; a window crossing it must extend
+    /// at least `min_width` into both halves.
+    pub split: Option<u32>,
+}
+
+/// Geometry and encoding of an e-paper panel. `width` x `height` is the
+/// controller's native scan order, which every packed frame is laid out in.
+#[derive(Debug, PartialEq)]
+pub struct PanelProfile {
+    pub name: &'static str,
+    pub width: u32,
+    pub height: u32,
+    /// One of 1, 2, 4 or 8.
+    pub bits_per_pixel: u32,
+    pub pixel_order: PixelOrder,
+    pub palette: PanelPalette,
+    pub window: WindowRules,
+}
+
+/// 13.3" Spectra 6, driven by two controllers side by side.
+pub const SPECTRA6_13IN3: PanelProfile = PanelProfile {
+    name: "spectra6_13in3",
+    width: 1200,
+    height: 1600,
+    bits_per_pixel: 4,
+    pixel_order: PixelOrder::MsbFirst,
+    palette: &[
+        ("black", 0.0, 0.0, 0.0, 0),
+        ("white", 255.0, 255.0, 255.0, 1),
+        ("yellow", 255.0, 255.0, 0.0, 2),
+        ("red", 255.0, 0.0, 0.0, 3),
+        ("blue", 0.0, 0.0, 255.0, 5),
+        ("green", 0.0, 255.0, 0.0, 6),
+    ],
+    window: WindowRules {
+        x_align: 4,
+        y_align: 2,
+        min_width: 16,
+        split: Some(600),
+    },
+};
+
+/// 7.5" black, white and red.
+pub const BWR_7IN5: PanelProfile = PanelProfile {
+    name: "bwr_7in5",
+    width: 800,
+    height: 480,
+    bits_per_pixel: 2,
+    pixel_order: PixelOrder::MsbFirst,
+    palette: &[
+        ("black", 0.0, 0.0, 0.0, 0),
+        ("white", 255.0, 255.0, 255.0, 1),
+        ("red", 255.0, 0.0, 0.0, 2),
+    ],
+    window: WindowRules {
+        x_align: 8,
+        y_align: 1,
+        min_width: 8,
+        split: None,
+    },
+};
+
+/// A TRMNL on the stock firmware, which shows black and white.
+pub const TRMNL_1BIT: PanelProfile = PanelProfile {
+    name: "trmnl_1bit",
+    width: 800,
+    height: 480,
+    bits_per_pixel: 1,
+    pixel_order: PixelOrder::MsbFirst,
+    palette: &[
+        ("black", 0.0, 0.0, 0.0, 0),
+        ("white", 255.0, 255.0, 255.0, 1),
+    ],
+    window: WindowRules {
+        x_align: 8,
+        y_align: 1,
+        min_width: 8,
+        split: None,
+    },
+};
+
+/// A TRMNL on firmware that can show four greys.
+pub const TRMNL_2BIT: PanelProfile = PanelProfile {
+    name: "trmnl_2bit",
+    palette: &[
+        ("black", 0.0, 0.0, 0.0, 0),
+        ("dark_grey", 85.0, 85.0, 85.0, 1),
+        ("light_grey", 170.0, 170.0, 170.0, 2),
+        ("white", 255.0, 255.0, 255.0, 3),
+    ],
+    bits_per_pixel: 2,
+    ..TRMNL_1BIT
+};
+
+impl Panel {
+    pub fn profile(self) -> &'static PanelProfile {
+        match self {
+            Panel::Spectra6 => &SPECTRA6_13IN3,
+            Panel::Bwr7in5 => &BWR_7IN5,
+            Panel::Trmnl1Bit => &TRMNL_1BIT,
+            Panel::Trmnl2Bit => &TRMNL_2BIT,
+        }
+    }
+}
+
+impl PanelProfile {
+    pub fn row_bytes(&self) -> usize {
+        (self.width * self.bits_per_pixel).div_ceil(8) as usize
+    }
+
+    pub fn frame_size(&self) -> usize {
+        self.row_bytes() * self.height as usize
+    }
+
+    /// The size an image is rendered at before it is rotated into the
+    /// panel's scan order.
+    pub fn target_dims(&self, orientation: Orientation) -> (u32, u32) {
+        let (short, long) = (self.width.min(self.height), self.width.max(self.height));
+
+        match orientation {
+            Orientation::Portrait => (short, long),
+            Orientation::Landscape => (long, short),
+        }
+    }
+
+    /// Pack palette indices, `width` to a row, at the panel's depth; each row
+    /// starts on a fresh byte.
+    pub fn pack(&self, indices: &[u8], width: u32) -> Vec<u8> {
+        let bits = self.bits_per_pixel as usize;
+        let per_byte = 8 / bits;
+        let mask = ((1u16 << bits) - 1) as u8;
+        let width = width as usize;
+        let row_bytes = width.div_ceil(per_byte);
+
+        let mut packed = vec![0u8; row_bytes * (indices.len() / width.max(1))];
+        for (i, &index) in indices.iter().enumerate() {
+            let (x, y) = (i % width, i / width);
+            let slot = x % per_byte;
+            let shift = match self.pixel_order {
+                PixelOrder::MsbFirst => 8 - bits * (slot + 1),
+                PixelOrder::LsbFirst => bits * slot,
+            };
+            packed[y * row_bytes + x / per_byte] |= (index & mask) << shift;
+        }
+
+        packed
+    }
+
+    /// The palette indices of a full packed frame, a row at a time; the
+    /// inverse of [`PanelProfile::pack`] at the panel's own width.
+    pub fn unpack(&self, packed: &[u8]) -> Vec<u8> {
+        let bits = self.bits_per_pixel as usize;
+        let per_byte = 8 / bits;
+        let mask = ((1u16 << bits) - 1) as u8;
+        let (width, height) = (self.width as usize, self.height as usize);
+        let row_bytes = self.row_bytes();
+
+        let mut indices = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let slot = x % per_byte;
+                let shift = match self.pixel_order {
+                    PixelOrder::MsbFirst => 8 - bits * (slot + 1),
+                    PixelOrder::LsbFirst => bits * slot,
+                };
+                let byte = packed.get(y * row_bytes + x / per_byte).copied();
+                indices.push(byte.map_or(0, |byte| (byte >> shift) & mask));
+            }
+        }
+
+        indices
+    }
+
+    fn byte_of(&self, x: u32) -> usize {
+        (x * self.bits_per_pixel / 8) as usize
+    }
+}
 
 #[derive(
     Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, async_graphql::SimpleObject,
@@ -23,19 +210,21 @@ pub struct PartialWindow {
 }
 
 impl PartialWindow {
-    pub fn is_valid(&self) -> bool {
-        self.width >= WINDOW_MIN_WIDTH
-            && self.height >= 2
-            && self.x.is_multiple_of(WINDOW_X_ALIGN)
-            && self.width.is_multiple_of(WINDOW_X_ALIGN)
-            && self.y.is_multiple_of(2)
-            && self.height.is_multiple_of(2)
-            && self.x + self.width <= PANEL_WIDTH
-            && self.y + self.height <= PANEL_HEIGHT
+    pub fn is_valid(&self, panel: &PanelProfile) -> bool {
+        let rules = &panel.window;
+
+        self.width >= rules.min_width
+            && self.height >= rules.y_align
+            && self.x.is_multiple_of(rules.x_align)
+            && self.width.is_multiple_of(rules.x_align)
+            && self.y.is_multiple_of(rules.y_align)
+            && self.height.is_multiple_of(rules.y_align)
+            && self.x + self.width <= panel.width
+            && self.y + self.height <= panel.height
     }
 
-    pub fn area_pct(&self) -> u32 {
-        (self.width as u64 * self.height as u64 * 100 / (PANEL_WIDTH as u64 * PANEL_HEIGHT as u64))
+    pub fn area_pct(&self, panel: &PanelProfile) -> u32 {
+        (self.width as u64 * self.height as u64 * 100 / (panel.width as u64 * panel.height as u64))
             as u32
     }
 }
@@ -53,20 +242,24 @@ pub fn packed_cache_key(hash: &str) -> Option<String> {
     is_frame_hash(hash).then(|| format!("{PACKED_CACHE_PREFIX}{hash}.bin"))
 }
 
-pub fn dirty_window(previous: &[u8], next: &[u8]) -> Option<PartialWindow> {
-    if previous.len() != PACKED_FRAME_SIZE || next.len() != PACKED_FRAME_SIZE {
+pub fn dirty_window(panel: &PanelProfile, previous: &[u8], next: &[u8]) -> Option<PartialWindow> {
+    let frame_size = panel.frame_size();
+    if previous.len() != frame_size || next.len() != frame_size {
         return None;
     }
 
-    let mut min_byte = PACKED_ROW_BYTES;
+    let row_bytes = panel.row_bytes();
+    let rows = panel.height as usize;
+
+    let mut min_byte = row_bytes;
     let mut max_byte = 0usize;
-    let mut min_row = PACKED_FRAME_HEIGHT;
+    let mut min_row = rows;
     let mut max_row = 0usize;
 
-    for row in 0..PACKED_FRAME_HEIGHT {
-        let start = row * PACKED_ROW_BYTES;
-        let previous_row = &previous[start..start + PACKED_ROW_BYTES];
-        let next_row = &next[start..start + PACKED_ROW_BYTES];
+    for row in 0..rows {
+        let start = row * row_bytes;
+        let previous_row = &previous[start..start + row_bytes];
+        let next_row = &next[start..start + row_bytes];
 
         if previous_row == next_row {
             continue;
@@ -81,7 +274,7 @@ pub fn dirty_window(previous: &[u8], next: &[u8]) -> Option<PartialWindow> {
             .iter()
             .zip(next_row)
             .rposition(|(a, b)| a != b)
-            .unwrap_or(PACKED_ROW_BYTES - 1);
+            .unwrap_or(row_bytes - 1);
 
         min_byte = min_byte.min(first);
         max_byte = max_byte.max(last);
@@ -93,47 +286,54 @@ pub fn dirty_window(previous: &[u8], next: &[u8]) -> Option<PartialWindow> {
         return None;
     }
 
-    let x0 = (min_byte as u32 * 2) / WINDOW_X_ALIGN * WINDOW_X_ALIGN;
-    let x1 = ((max_byte as u32 + 1) * 2).div_ceil(WINDOW_X_ALIGN) * WINDOW_X_ALIGN;
-    let y0 = min_row as u32 & !1;
-    let y1 = (max_row as u32 + 1).div_ceil(2) * 2;
+    let rules = &panel.window;
+    let pixels_per_byte = 8 / panel.bits_per_pixel;
+
+    let x0 = (min_byte as u32 * pixels_per_byte) / rules.x_align * rules.x_align;
+    let x1 = ((max_byte as u32 + 1) * pixels_per_byte).div_ceil(rules.x_align) * rules.x_align;
+    let y0 = min_row as u32 / rules.y_align * rules.y_align;
+    let y1 = (max_row as u32 + 1).div_ceil(rules.y_align) * rules.y_align;
 
     let mut window = PartialWindow {
         x: x0,
         y: y0,
-        width: x1 - x0,
-        height: y1 - y0,
+        width: x1.min(panel.width) - x0,
+        height: y1.min(panel.height) - y0,
     };
 
-    if window.width < WINDOW_MIN_WIDTH {
-        window.width = WINDOW_MIN_WIDTH;
-        window.x = window.x.min(PANEL_WIDTH - WINDOW_MIN_WIDTH);
+    if window.width < rules.min_width {
+        window.width = rules.min_width;
+        window.x = window.x.min(panel.width - rules.min_width);
     }
 
-    if window.x < PANEL_HALF_WIDTH && window.x + window.width > PANEL_HALF_WIDTH {
-        let left = PANEL_HALF_WIDTH - window.x;
-        let right = window.x + window.width - PANEL_HALF_WIDTH;
+    if let Some(split) = rules.split
+        && window.x < split
+        && window.x + window.width > split
+    {
+        let left = split - window.x;
+        let right = window.x + window.width - split;
 
-        if left < WINDOW_MIN_WIDTH {
-            window.x = PANEL_HALF_WIDTH - WINDOW_MIN_WIDTH;
-            window.width = right + WINDOW_MIN_WIDTH;
+        if left < rules.min_width {
+            window.x = split - rules.min_width;
+            window.width = right + rules.min_width;
         }
 
-        if right < WINDOW_MIN_WIDTH {
-            window.width = PANEL_HALF_WIDTH - window.x + WINDOW_MIN_WIDTH;
+        if right < rules.min_width {
+            window.width = split - window.x + rules.min_width;
         }
     }
 
-    window.is_valid().then_some(window)
+    window.is_valid(panel).then_some(window)
 }
 
-pub fn crop_packed(packed: &[u8], window: PartialWindow) -> Vec<u8> {
-    let row_offset = (window.x / 2) as usize;
-    let row_count = (window.width / 2) as usize;
+pub fn crop_packed(panel: &PanelProfile, packed: &[u8], window: PartialWindow) -> Vec<u8> {
+    let row_bytes = panel.row_bytes();
+    let row_offset = panel.byte_of(window.x);
+    let row_count = panel.byte_of(window.width);
 
     let mut cropped = Vec::with_capacity(row_count * window.height as usize);
     for row in window.y..window.y + window.height {
-        let start = row as usize * PACKED_ROW_BYTES + row_offset;
+        let start = row as usize * row_bytes + row_offset;
         cropped.extend_from_slice(&packed[start..start + row_count]);
     }
 
@@ -144,8 +344,10 @@ pub fn crop_packed(packed: &[u8], window: PartialWindow) -> Vec<u8> {
 mod tests {
     use super::*;
 
-    fn blank_frame() -> Vec<u8> {
-        vec![0x11; PACKED_FRAME_SIZE]
+    const SPECTRA: &PanelProfile = &SPECTRA6_13IN3;
+
+    fn blank_frame(panel: &PanelProfile) -> Vec<u8> {
+        vec![0x11; panel.frame_size()]
     }
 
     #[test]
@@ -174,30 +376,77 @@ mod tests {
         }
     }
 
-    fn set_pixel(frame: &mut [u8], x: usize, y: usize) {
-        frame[y * PACKED_ROW_BYTES + x / 2] = 0x00;
+    fn set_pixel(panel: &PanelProfile, frame: &mut [u8], x: u32, y: u32) {
+        frame[y as usize * panel.row_bytes() + panel.byte_of(x)] ^= 0xff;
+    }
+
+    #[test]
+    fn the_spectra_profile_keeps_the_original_geometry() {
+        assert_eq!(SPECTRA.row_bytes(), 600);
+        assert_eq!(SPECTRA.frame_size(), 600 * 1600);
+        assert_eq!(SPECTRA.target_dims(Orientation::Portrait), (1200, 1600));
+        assert_eq!(SPECTRA.target_dims(Orientation::Landscape), (1600, 1200));
+        assert_eq!(SPECTRA.pack(&[1, 3, 6, 0], 4), vec![0x13, 0x60]);
+    }
+
+    #[test]
+    fn a_two_bit_panel_packs_four_pixels_to_a_byte() {
+        assert_eq!(BWR_7IN5.row_bytes(), 200);
+        assert_eq!(BWR_7IN5.target_dims(Orientation::Portrait), (480, 800));
+        assert_eq!(
+            BWR_7IN5.pack(&[0, 1, 2, 1, 2, 2, 2, 2], 8),
+            vec![0x19, 0xaa]
+        );
+
+        let lsb_first = PanelProfile {
+            pixel_order: PixelOrder::LsbFirst,
+            ..BWR_7IN5
+        };
+        assert_eq!(lsb_first.pack(&[0, 1, 2, 1], 4), vec![0x64]);
+    }
+
+    #[test]
+    fn unpacking_a_frame_recovers_its_indices() {
+        for panel in [SPECTRA, &BWR_7IN5, &TRMNL_1BIT] {
+            let colours = panel.palette.len();
+            let indices: Vec<u8> = (0..panel.width * panel.height)
+                .map(|i| panel.palette[i as usize * 7 % colours].4)
+                .collect();
+
+            let packed = panel.pack(&indices, panel.width);
+
+            assert_eq!(packed.len(), panel.frame_size(), "{}", panel.name);
+            assert_eq!(panel.unpack(&packed), indices, "{}", panel.name);
+        }
     }
 
     #[test]
     fn an_unchanged_frame_has_no_dirty_window() {
-        assert_eq!(dirty_window(&blank_frame(), &blank_frame()), None);
+        assert_eq!(
+            dirty_window(SPECTRA, &blank_frame(SPECTRA), &blank_frame(SPECTRA)),
+            None
+        );
     }
 
     #[test]
     fn a_wrongly_sized_frame_has_no_dirty_window() {
-        assert_eq!(dirty_window(&[0u8; 8], &[1u8; 8]), None);
+        assert_eq!(dirty_window(SPECTRA, &[0u8; 8], &[1u8; 8]), None);
+        assert_eq!(
+            dirty_window(&BWR_7IN5, &blank_frame(SPECTRA), &blank_frame(SPECTRA)),
+            None
+        );
     }
 
     #[test]
     fn a_single_changed_pixel_yields_the_minimum_window() {
-        let previous = blank_frame();
+        let previous = blank_frame(SPECTRA);
         let mut next = previous.clone();
-        set_pixel(&mut next, 400, 500);
+        set_pixel(SPECTRA, &mut next, 400, 500);
 
-        let window = dirty_window(&previous, &next).expect("a dirty window");
+        let window = dirty_window(SPECTRA, &previous, &next).expect("a dirty window");
 
-        assert!(window.is_valid());
-        assert_eq!(window.width, WINDOW_MIN_WIDTH);
+        assert!(window.is_valid(SPECTRA));
+        assert_eq!(window.width, SPECTRA.window.min_width);
         assert_eq!(window.height, 2);
         assert_eq!(window.y, 500);
         assert!(window.x <= 400 && 400 < window.x + window.width);
@@ -205,57 +454,94 @@ mod tests {
 
     #[test]
     fn a_change_straddling_the_controller_split_covers_both_halves() {
-        let previous = blank_frame();
+        let previous = blank_frame(SPECTRA);
         let mut next = previous.clone();
-        set_pixel(&mut next, 598, 100);
-        set_pixel(&mut next, 602, 100);
+        set_pixel(SPECTRA, &mut next, 598, 100);
+        set_pixel(SPECTRA, &mut next, 602, 100);
 
-        let window = dirty_window(&previous, &next).expect("a dirty window");
+        let window = dirty_window(SPECTRA, &previous, &next).expect("a dirty window");
+        let (split, min_width) = (600, SPECTRA.window.min_width);
 
-        assert!(window.is_valid());
-        assert!(window.x + WINDOW_MIN_WIDTH <= PANEL_HALF_WIDTH);
-        assert!(window.x + window.width >= PANEL_HALF_WIDTH + WINDOW_MIN_WIDTH);
+        assert!(window.is_valid(SPECTRA));
+        assert!(window.x + min_width <= split);
+        assert!(window.x + window.width >= split + min_width);
     }
 
     #[test]
     fn a_full_frame_change_covers_the_whole_panel() {
-        let previous = blank_frame();
-        let next = vec![0x00; PACKED_FRAME_SIZE];
+        let previous = blank_frame(SPECTRA);
+        let next = vec![0x00; SPECTRA.frame_size()];
 
-        let window = dirty_window(&previous, &next).expect("a dirty window");
+        let window = dirty_window(SPECTRA, &previous, &next).expect("a dirty window");
 
         assert_eq!(
             window,
             PartialWindow {
                 x: 0,
                 y: 0,
-                width: PANEL_WIDTH,
-                height: PANEL_HEIGHT,
+                width: 1200,
+                height: 1600,
             }
         );
-        assert_eq!(window.area_pct(), 100);
+        assert_eq!(window.area_pct(SPECTRA), 100);
     }
 
     #[test]
-    fn a_crop_matches_the_same_region_of_the_full_frame() {
-        let mut packed = blank_frame();
-        for (index, byte) in packed.iter_mut().enumerate() {
-            *byte = index as u8;
-        }
+    fn a_small_panel_aligns_its_window_to_its_own_rules() {
+        let panel = &BWR_7IN5;
+        let previous = blank_frame(panel);
+        let mut next = previous.clone();
+        set_pixel(panel, &mut next, 795, 479);
 
-        let window = PartialWindow {
-            x: 400,
-            y: 500,
-            width: 64,
-            height: 4,
-        };
-        let cropped = crop_packed(&packed, window);
+        let window = dirty_window(panel, &previous, &next).expect("a dirty window");
+
+        assert_eq!(
+            window,
+            PartialWindow {
+                x: 792,
+                y: 479,
+                width: 8,
+                height: 1,
+            }
+        );
+        assert!(!window.is_valid(SPECTRA));
+        assert!(!PartialWindow { x: 4, ..window }.is_valid(panel));
+    }
 
-        assert_eq!(cropped.len(), window.width as usize / 2 * 4);
-        for row in 0..window.height as usize {
-            let source = (500 + row) * PACKED_ROW_BYTES + 200;
-            let target = row * 32;
-            assert_eq!(&cropped[target..target + 32], &packed[source..source + 32]);
+    #[test]
+    fn a_crop_matches_the_same_region_of_the_full_frame() {
+        for (panel, window) in [
+            (
+                SPECTRA,
+                PartialWindow {
+                    x: 400,
+                    y: 500,
+                    width: 64,
+                    height: 4,
+                },
+            ),
+            (
+                &BWR_7IN5,
+                PartialWindow {
+                    x: 160,
+                    y: 100,
+                    width: 64,
+                    height: 3,
+                },
+            ),
+        ] {
+            let packed: Vec<u8> = (0..panel.frame_size()).map(|i| i as u8).collect();
+            let cropped = crop_packed(panel, &packed, window);
+
+            let row = panel.byte_of(window.width);
+            assert_eq!(cropped.len(), row * window.height as usize);
+            for y in 0..window.height as usize {
+                let source = (window.y as usize + y) * panel.row_bytes() + panel.byte_of(window.x);
+                assert_eq!(
+                    &cropped[y * row..(y + 1) * row],
+                    &packed[source..source + row]
+                );
+            }
         }
     }
 }
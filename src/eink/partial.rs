@@ -39,7 +39,7 @@ pub async fn resolve_partial_window(
     let next = s3.get_object(&packed_cache_key(new_hash)?).await.ok();
 
     let window = match (previous.as_deref(), next.as_deref()) {
-        (Some(previous), Some(next)) => dirty_window(previous, next),
+        (Some(previous), Some(next)) => dirty_window(display.profile(), previous, next),
         _ => None,
     };
 
@@ -48,7 +48,7 @@ pub async fn resolve_partial_window(
         return None;
     };
 
-    let area_pct = window.area_pct();
+    let area_pct = window.area_pct(display.profile());
 
     if area_pct > policy.max_area_pct {
         tracing::info!(
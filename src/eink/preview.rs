@@ -0,0 +1,117 @@
+use crate::eink::panel::{PanelProfile, PartialWindow};
+use image::{Rgb, RgbImage};
+use imageproc::drawing::draw_hollow_rect_mut;
+use imageproc::rect::Rect;
+
+/// Drawn around the dirty window, in a colour no panel can show.
+const OVERLAY: Rgb<u8> = Rgb([255, 0, 255]);
+const OVERLAY_STROKE: u32 = 3;
+
+/// An index the palette has no colour for; a frame should never carry one.
+const UNKNOWN: Rgb<u8> = Rgb([128, 128, 128]);
+
+/// A packed frame as the panel shows it, in its scan order and coloured with
+/// `palette`, with `window` outlined when given.
+pub fn decode_frame(
+    panel: &PanelProfile,
+    palette: &[(f32, f32, f32, u8)],
+    packed: &[u8],
+    window: Option<PartialWindow>,
+) -> anyhow::Result<Vec<u8>> {
+    if packed.len() != panel.frame_size() {
+        anyhow::bail!(
+            "a {} frame is {} bytes, not {}",
+            panel.name,
+            panel.frame_size(),
+            packed.len()
+        );
+    }
+
+    let indices = panel.unpack(packed);
+    let mut img = RgbImage::from_fn(panel.width, panel.height, |x, y| {
+        let index = indices[(y * panel.width + x) as usize];
+
+        palette
+            .iter()
+            .find(|&&(.., i)| i == index)
+            .map_or(UNKNOWN, |&(r, g, b, _)| Rgb([r as u8, g as u8, b as u8]))
+    });
+
+    if let Some(window) = window {
+        for inset in 0..OVERLAY_STROKE.min(window.width / 2).min(window.height / 2) {
+            draw_hollow_rect_mut(
+                &mut img,
+                Rect::at((window.x + inset) as i32, (window.y + inset) as i32)
+                    .of_size(window.width - inset * 2, window.height - inset * 2),
+                OVERLAY,
+            );
+        }
+    }
+
+    let mut png = Vec::new();
+    image::DynamicImage::ImageRgb8(img)
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)?;
+
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eink::panel::BWR_7IN5;
+
+    fn palette() -> Vec<(f32, f32, f32, u8)> {
+        BWR_7IN5
+            .palette
+            .iter()
+            .map(|&(_, r, g, b, index)| (r, g, b, index))
+            .collect()
+    }
+
+    fn decode(packed: &[u8], window: Option<PartialWindow>) -> RgbImage {
+        let png = decode_frame(&BWR_7IN5, &palette(), packed, window).unwrap();
+
+        image::load_from_memory(&png).unwrap().to_rgb8()
+    }
+
+    #[test]
+    fn a_frame_decodes_to_its_palette_colours() {
+        let mut indices = vec![1u8; (BWR_7IN5.width * BWR_7IN5.height) as usize];
+        indices[0] = 0;
+        indices[1] = 2;
+
+        let img = decode(&BWR_7IN5.pack(&indices, BWR_7IN5.width), None);
+
+        assert_eq!(img.dimensions(), (BWR_7IN5.width, BWR_7IN5.height));
+        assert_eq!(img.get_pixel(0, 0), &Rgb([0, 0, 0]));
+        assert_eq!(img.get_pixel(1, 0), &Rgb([255, 0, 0]));
+        assert_eq!(img.get_pixel(2, 0), &Rgb([255, 255, 255]));
+    }
+
+    #[test]
+    fn the_dirty_window_is_outlined() {
+        let white = BWR_7IN5.pack(
+            &vec![1u8; (BWR_7IN5.width * BWR_7IN5.height) as usize],
+            BWR_7IN5.width,
+        );
+        let window = PartialWindow {
+            x: 80,
+            y: 40,
+            width: 64,
+            height: 32,
+        };
+
+        let img = decode(&white, Some(window));
+
+        assert_eq!(img.get_pixel(80, 40), &OVERLAY);
+        assert_eq!(img.get_pixel(143, 71), &OVERLAY);
+        assert_eq!(img.get_pixel(82, 60), &OVERLAY);
+        assert_eq!(img.get_pixel(100, 60), &Rgb([255, 255, 255]));
+        assert_eq!(img.get_pixel(79, 40), &Rgb([255, 255, 255]));
+    }
+
+    #[test]
+    fn a_truncated_frame_is_rejected() {
+        assert!(decode_frame(&BWR_7IN5, &palette(), &[0u8; 8], None).is_err());
+    }
+}
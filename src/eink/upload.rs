@@ -0,0 +1,134 @@
+use crate::eink::image::crop_to_aspect;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use std::io::Cursor;
+use std::path::Path;
+
+/// Ships with libheif's examples; the image crate has no HEIC decoder.
+const HEIF_CONVERT: &str = "heif-convert";
+
+/// ISO-BMFF brands a phone writes for a HEIC still.
+const HEIC_BRANDS: [&[u8; 4]; 8] = [
+    b"heic", b"heix", b"heim", b"heis", b"hevc", b"hevx", b"mif1", b"msf1",
+];
+
+#[derive(thiserror::Error, Debug)]
+pub enum UploadError {
+    #[error("not a JPEG, PNG or HEIC image")]
+    Unsupported,
+    #[error("could not decode the image: {0}")]
+    Decode(#[from] image::ImageError),
+    #[error("could not convert the HEIC image: {0}")]
+    Heic(String),
+}
+
+/// An uploaded photo as the album stores it: turned upright per its EXIF
+/// orientation, cropped to the `crop_to` aspect ratio when one is given,
+/// and re-encoded as a PNG, which drops the EXIF block along with any
+/// location it carried.
+pub fn normalize(bytes: &[u8], crop_to: Option<(u32, u32)>) -> Result<Vec<u8>, UploadError> {
+    let converted;
+    let (bytes, format) = match image::guess_format(bytes) {
+        Ok(format @ (ImageFormat::Jpeg | ImageFormat::Png)) => (bytes, format),
+        _ if is_heic(bytes) => {
+            converted = heic_to_png(bytes)?;
+            (converted.as_slice(), ImageFormat::Png)
+        }
+        _ => return Err(UploadError::Unsupported),
+    };
+
+    let mut decoder = ImageReader::with_format(Cursor::new(bytes), format).into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut img = DynamicImage::from_decoder(decoder)?;
+    img.apply_orientation(orientation);
+
+    let mut img = img.to_rgb8();
+    if let Some((width, height)) = crop_to {
+        img = crop_to_aspect(&img, width, height);
+    }
+
+    let mut out = Vec::new();
+    DynamicImage::ImageRgb8(img).write_to(&mut Cursor::new(&mut out), ImageFormat::Png)?;
+
+    Ok(out)
+}
+
+fn is_heic(bytes: &[u8]) -> bool {
+    bytes.len() >= 12 && &bytes[4..8] == b"ftyp" && HEIC_BRANDS.iter().any(|b| &bytes[8..12] == *b)
+}
+
+fn heic_to_png(bytes: &[u8]) -> Result<Vec<u8>, UploadError> {
+    let stem = std::env::temp_dir().join(format!("hg-upload-{}", uuid::Uuid::new_v4()));
+    let input = stem.with_extension("heic");
+    let output = stem.with_extension("png");
+
+    let converted = convert(bytes, &input, &output);
+
+    let _ = std::fs::remove_file(&input);
+    let _ = std::fs::remove_file(&output);
+
+    converted
+}
+
+fn convert(bytes: &[u8], input: &Path, output: &Path) -> Result<Vec<u8>, UploadError> {
+    std::fs::write(input, bytes).map_err(|e| UploadError::Heic(e.to_string()))?;
+
+    let status = std::process::Command::new(HEIF_CONVERT)
+        .arg(input)
+        .arg(output)
+        .stdout(std::process::Stdio::null())
+        .status()
+        .map_err(|e| UploadError::Heic(format!("running {HEIF_CONVERT}: {e}")))?;
+    if !status.success() {
+        return Err(UploadError::Heic(format!(
+            "{HEIF_CONVERT} exited with {status}"
+        )));
+    }
+
+    std::fs::read(output).map_err(|e| UploadError::Heic(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jpeg(width: u32, height: u32) -> Vec<u8> {
+        let img = image::RgbImage::from_pixel(width, height, image::Rgb([200, 40, 40]));
+        let mut out = Vec::new();
+        DynamicImage::ImageRgb8(img)
+            .write_to(&mut Cursor::new(&mut out), ImageFormat::Jpeg)
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn an_upload_is_cropped_to_the_display_and_stored_as_png() {
+        let png = normalize(&jpeg(900, 600), Some((480, 800))).unwrap();
+
+        assert_eq!(image::guess_format(&png).unwrap(), ImageFormat::Png);
+        let img = image::load_from_memory(&png).unwrap();
+        assert_eq!((img.width(), img.height()), (360, 600));
+    }
+
+    #[test]
+    fn without_a_display_the_framing_is_kept() {
+        let png = normalize(&jpeg(900, 600), None).unwrap();
+
+        let img = image::load_from_memory(&png).unwrap();
+        assert_eq!((img.width(), img.height()), (900, 600));
+    }
+
+    #[test]
+    fn anything_but_a_photo_is_rejected() {
+        assert!(matches!(
+            normalize(b"GIF89a not really", None),
+            Err(UploadError::Unsupported)
+        ));
+    }
+
+    #[test]
+    fn heic_is_recognised_by_its_brand() {
+        assert!(is_heic(b"\0\0\0\x18ftypheic\0\0\0\0"));
+        assert!(!is_heic(b"\0\0\0\x18ftypavif\0\0\0\0"));
+        assert!(!is_heic(b"ftyp"));
+    }
+}
@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use std::collections::HashMap;
 use uuid::Uuid;
 
@@ -51,15 +52,15 @@ pub enum EventBusMessage {
         transition: SunTransition,
         offset: chrono::TimeDelta,
     },
-    /// A light reported a power-state change (`on`/off), published for
-    /// subscribers; the dispatcher does not currently trigger on it.
+    /// A light's power state changed (`on`/off). Only the edges are published;
+    /// a repeated report of the same state is not.
     Light {
         event_id: Uuid,
         ieee_addr: IEEEAddress,
         on: bool,
     },
     /// A UniFi WiFi client connected or disconnected. `client` is the mapped
-    /// friendly name (or `unknown`); published for subscribers, not triggered on.
+    /// friendly name (or `unknown`).
     Unifi {
         event_id: Uuid,
         mac_address: String,
@@ -153,6 +154,47 @@ pub enum EventBusMessage {
     /// Threshold + rising-edge handling lives in the dispatcher, as it does for
     /// [`EventBusMessage::Environment`].
     Solar { event_id: Uuid, current_wh: f64 },
+    /// A workflow variable changed value. `previous` is `None` when it had
+    /// never been set and has no default.
+    Variable {
+        event_id: Uuid,
+        name: String,
+        value: String,
+        previous: Option<String>,
+    },
+    /// A robot vacuum's state changed (e.g. `cleaning` -> `docked`), published by
+    /// the [`crate::actors::devices::robot_vacuum`] handler. `state` is as the
+    /// vacuum reports it (Valetudo's status, or the Roborock status entity's
+    /// state); `previous` is `None` the first time a vacuum reports.
+    RobotVacuum {
+        event_id: Uuid,
+        device_id: String,
+        name: String,
+        state: String,
+        previous: Option<String>,
+    },
+    /// A device stopped reporting for longer than its watchdog timeout
+    /// (`available: false`), or started again after that. Published by the
+    /// [`crate::actors::system::watchdog::WatchdogActor`] on its check, so an
+    /// edge lags by up to `watchdog.check_interval`.
+    DeviceAvailability {
+        event_id: Uuid,
+        device_id: String,
+        address: String,
+        available: bool,
+        last_seen: DateTime<Utc>,
+    },
+    /// A button on an actionable notification was tapped, reported by the app
+    /// to `/v1/ingest/home/notification-action`. `origin_event_id` is that of
+    /// the event the notification was sent for; `source` names the sender
+    /// (e.g. `workflow:bins`, `door:front`, `watchdog:hallway`).
+    NotificationAction {
+        event_id: Uuid,
+        origin_event_id: Uuid,
+        notification_id: Uuid,
+        source: String,
+        action: String,
+    },
 }
 
 impl EventBusMessage {
@@ -174,7 +216,11 @@ impl EventBusMessage {
             | EventBusMessage::DeviceBattery { event_id, .. }
             | EventBusMessage::Jellyfin { event_id, .. }
             | EventBusMessage::MediaPlayer { event_id, .. }
-            | EventBusMessage::Solar { event_id, .. } => *event_id,
+            | EventBusMessage::Solar { event_id, .. }
+            | EventBusMessage::Variable { event_id, .. }
+            | EventBusMessage::RobotVacuum { event_id, .. }
+            | EventBusMessage::DeviceAvailability { event_id, .. }
+            | EventBusMessage::NotificationAction { event_id, .. } => *event_id,
         }
     }
 
@@ -196,6 +242,10 @@ impl EventBusMessage {
             EventBusMessage::Jellyfin { .. } => "jellyfin",
             EventBusMessage::MediaPlayer { .. } => "media_player",
             EventBusMessage::Solar { .. } => "solar",
+            EventBusMessage::Variable { .. } => "variable",
+            EventBusMessage::RobotVacuum { .. } => "robot_vacuum",
+            EventBusMessage::DeviceAvailability { .. } => "device_availability",
+            EventBusMessage::NotificationAction { .. } => "notification_action",
         }
     }
 
@@ -215,6 +265,10 @@ impl EventBusMessage {
         "jellyfin",
         "media_player",
         "solar",
+        "variable",
+        "robot_vacuum",
+        "device_availability",
+        "notification_action",
     ];
 
     pub fn entity(&self) -> String {
@@ -237,6 +291,10 @@ impl EventBusMessage {
             EventBusMessage::Jellyfin { user, .. } => user.clone(),
             EventBusMessage::MediaPlayer { device_id, .. } => device_id.clone(),
             EventBusMessage::Solar { .. } => "solar".to_string(),
+            EventBusMessage::Variable { name, .. } => name.clone(),
+            EventBusMessage::RobotVacuum { device_id, .. }
+            | EventBusMessage::DeviceAvailability { device_id, .. } => device_id.clone(),
+            EventBusMessage::NotificationAction { source, .. } => source.clone(),
         }
     }
 
@@ -428,6 +486,52 @@ impl EventBusMessage {
             EventBusMessage::Solar { current_wh, .. } => {
                 HashMap::from([("current".to_owned(), format!("{current_wh:.0}"))])
             }
+            EventBusMessage::Variable {
+                name,
+                value,
+                previous,
+                ..
+            } => HashMap::from([
+                ("variable".to_owned(), name.clone()),
+                ("value".to_owned(), value.clone()),
+                ("previous".to_owned(), previous.clone().unwrap_or_default()),
+            ]),
+            EventBusMessage::RobotVacuum {
+                device_id,
+                name,
+                state,
+                previous,
+                ..
+            } => HashMap::from([
+                ("device".to_owned(), device_id.clone()),
+                ("name".to_owned(), name.clone()),
+                ("state".to_owned(), state.clone()),
+                ("previous".to_owned(), previous.clone().unwrap_or_default()),
+            ]),
+            EventBusMessage::DeviceAvailability {
+                device_id,
+                address,
+                available,
+                last_seen,
+                ..
+            } => HashMap::from([
+                ("device".to_owned(), device_id.clone()),
+                ("address".to_owned(), address.clone()),
+                ("available".to_owned(), available.to_string()),
+                ("last_seen".to_owned(), last_seen.to_rfc3339()),
+            ]),
+            EventBusMessage::NotificationAction {
+                origin_event_id,
+                notification_id,
+                source,
+                action,
+                ..
+            } => HashMap::from([
+                ("origin_event_id".to_owned(), origin_event_id.to_string()),
+                ("notification".to_owned(), notification_id.to_string()),
+                ("source".to_owned(), source.clone()),
+                ("action".to_owned(), action.clone()),
+            ]),
         }
     }
 }
@@ -1,11 +1,13 @@
 use async_graphql::{MergedObject, Schema};
 use queries::{
-    adhoc_query::AdhocQuery, auth_query::AuthQuery, energy_query::EnergyQuery,
-    entities_query::EntitiesQuery, home_assistant_query::HomeAssistantQuery,
-    jellyfin_query::JellyfinQuery, solar_query::SolarQuery, weather_query::WeatherQuery,
+    adhoc_query::AdhocQuery, auth_query::AuthQuery, eink_album_query::EinkAlbumQuery,
+    energy_query::EnergyQuery, entities_query::EntitiesQuery,
+    home_assistant_query::HomeAssistantQuery, jellyfin_query::JellyfinQuery,
+    notifications_query::NotificationsQuery, solar_query::SolarQuery, weather_query::WeatherQuery,
 };
 
 use crate::graphql::mutations::MutationRoot;
+use crate::graphql::queries::history_query::HistoryQuery;
 use crate::graphql::queries::woolworths_query::WoolworthsQuery;
 use crate::graphql::queries::workflows_query::WorkflowsQuery;
 use crate::graphql::subscription::SubscriptionRoot;
@@ -23,6 +25,7 @@ pub struct QueryRoot(
     AuthQuery,
     HomeAssistantQuery,
     EntitiesQuery,
+    HistoryQuery,
     SolarQuery,
     EnergyQuery,
     WeatherQuery,
@@ -30,6 +33,8 @@ pub struct QueryRoot(
     WorkflowsQuery,
     JellyfinQuery,
     AdhocQuery,
+    NotificationsQuery,
+    EinkAlbumQuery,
 );
 
 pub type FinalSchema = Schema<QueryRoot, MutationRoot, SubscriptionRoot>;
@@ -0,0 +1,27 @@
+use async_graphql::Object;
+
+use crate::actors::system::config_reload::{ConfigReloadActor, ConfigReloadMessage};
+use crate::actors::system::rpc;
+use crate::auth::scope::required;
+use crate::graphql::guard::ScopeGuard;
+use crate::settings::ConfigChange;
+
+const RELOAD_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+#[derive(Default)]
+pub struct ConfigMutation;
+
+#[Object]
+impl ConfigMutation {
+    /// Re-read the config dir and swap it in. An invalid config is rejected
+    /// with the validation error and the running config is kept.
+    #[graphql(guard = ScopeGuard(required::GRAPHQL_CONFIG_WRITE))]
+    async fn reload_config(&self) -> async_graphql::Result<ConfigChange> {
+        let result = rpc::query(ConfigReloadActor::NAME, RELOAD_TIMEOUT, |reply| {
+            ConfigReloadMessage::Reload { reply }
+        })
+        .await?;
+
+        result.map_err(async_graphql::Error::new)
+    }
+}
@@ -0,0 +1,63 @@
+use async_graphql::{Context, Object, Upload};
+use std::io::Read;
+
+use crate::auth::scope::required;
+use crate::eink::EinkDisplayManager;
+use crate::eink::manager::album::EinkAlbumItem;
+use crate::graphql::guard::ScopeGuard;
+
+#[derive(Default)]
+pub struct EinkAlbumMutation;
+
+#[Object]
+impl EinkAlbumMutation {
+    /// Store a JPEG, PNG or HEIC photo in `album`: turned upright, cropped
+    /// for `deviceId` (or the first display showing the album) and with its
+    /// EXIF stripped.
+    #[graphql(guard = ScopeGuard(required::GRAPHQL_EPD_WRITE))]
+    async fn upload_eink_album_image(
+        &self,
+        ctx: &Context<'_>,
+        album: String,
+        image: Upload,
+        device_id: Option<String>,
+    ) -> async_graphql::Result<EinkAlbumItem> {
+        let eink = ctx.data::<EinkDisplayManager>()?;
+
+        let mut bytes = Vec::new();
+        image.value(ctx)?.into_read().read_to_end(&mut bytes)?;
+
+        Ok(eink
+            .upload_to_album(&album, bytes, device_id.as_deref())
+            .await?)
+    }
+
+    #[graphql(guard = ScopeGuard(required::GRAPHQL_EPD_WRITE))]
+    async fn delete_eink_album_image(
+        &self,
+        ctx: &Context<'_>,
+        album: String,
+        name: String,
+    ) -> async_graphql::Result<bool> {
+        let eink = ctx.data::<EinkDisplayManager>()?;
+
+        eink.delete_album_item(&album, &name).await?;
+
+        Ok(true)
+    }
+
+    /// While any of an album's photos are pinned, its displays only show
+    /// those.
+    #[graphql(guard = ScopeGuard(required::GRAPHQL_EPD_WRITE))]
+    async fn pin_eink_album_image(
+        &self,
+        ctx: &Context<'_>,
+        album: String,
+        name: String,
+        pinned: bool,
+    ) -> async_graphql::Result<EinkAlbumItem> {
+        let eink = ctx.data::<EinkDisplayManager>()?;
+
+        Ok(eink.pin_album_item(&album, &name, pinned).await?)
+    }
+}
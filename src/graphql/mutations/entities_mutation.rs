@@ -1,10 +1,11 @@
 use async_graphql::Object;
 
-use crate::device_registry::DeviceRegistry;
 use crate::graphql::mutations::eink_display_mutation::EinkDisplayMutation;
 use crate::graphql::mutations::light_mutation::LightMutation;
 use crate::graphql::mutations::media_player_mutation::MediaPlayerMutation;
 use crate::graphql::mutations::robot_vacuum_mutation::RobotVacuumMutation;
+use crate::graphql::mutations::smart_switch_mutation::SmartSwitchMutation;
+use crate::settings::LiveConfig;
 
 #[derive(Default)]
 pub struct EntitiesMutation;
@@ -16,7 +17,7 @@ impl EntitiesMutation {
         ctx: &async_graphql::Context<'_>,
         id: String,
     ) -> async_graphql::Result<LightMutation> {
-        let registry = ctx.data::<DeviceRegistry>()?;
+        let registry = &ctx.data::<LiveConfig>()?.devices();
         let address = registry.address_or_self(&id).to_owned();
         let capabilities = registry.capabilities(&address).to_vec();
         Ok(LightMutation {
@@ -25,12 +26,29 @@ impl EntitiesMutation {
         })
     }
 
+    async fn smart_switch(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+        id: String,
+    ) -> async_graphql::Result<SmartSwitchMutation> {
+        let registry = &ctx.data::<LiveConfig>()?.devices();
+        let address = registry.address_or_self(&id).to_owned();
+
+        if registry.smart_switch(&address).is_none() {
+            return Err(async_graphql::Error::new(format!(
+                "unknown smart switch `{id}`"
+            )));
+        }
+
+        Ok(SmartSwitchMutation { address })
+    }
+
     async fn robot_vacuum(
         &self,
         ctx: &async_graphql::Context<'_>,
         id: String,
     ) -> async_graphql::Result<RobotVacuumMutation> {
-        let registry = ctx.data::<DeviceRegistry>()?;
+        let registry = &ctx.data::<LiveConfig>()?.devices();
         let address = registry.address_or_self(&id).to_owned();
 
         if let Some(settings) = registry.roborock(&address) {
@@ -51,7 +69,7 @@ impl EntitiesMutation {
         ctx: &async_graphql::Context<'_>,
         id: String,
     ) -> async_graphql::Result<MediaPlayerMutation> {
-        let registry = ctx.data::<DeviceRegistry>()?;
+        let registry = &ctx.data::<LiveConfig>()?.devices();
         let address = registry.address_or_self(&id).to_owned();
 
         let Some(settings) = registry.media_player(&address) else {
@@ -68,7 +86,7 @@ impl EntitiesMutation {
         ctx: &async_graphql::Context<'_>,
         id: String,
     ) -> async_graphql::Result<EinkDisplayMutation> {
-        let registry = ctx.data::<DeviceRegistry>()?;
+        let registry = &ctx.data::<LiveConfig>()?.devices();
         let address = registry.address_or_self(&id).to_owned();
 
         if registry.eink_display(&address).is_none() {
@@ -1,16 +1,27 @@
 use async_graphql::MergedObject;
 
 use crate::graphql::mutations::adhoc_mutation::AdhocMutation;
+use crate::graphql::mutations::config_mutation::ConfigMutation;
+use crate::graphql::mutations::eink_album_mutation::EinkAlbumMutation;
 use crate::graphql::mutations::entities_mutation::EntitiesMutation;
 use crate::graphql::mutations::workflows_mutation::WorkflowsMutation;
 
 pub mod adhoc_mutation;
+pub mod config_mutation;
+pub mod eink_album_mutation;
 pub mod eink_display_mutation;
 pub mod entities_mutation;
 pub mod light_mutation;
 pub mod media_player_mutation;
 pub mod robot_vacuum_mutation;
+pub mod smart_switch_mutation;
 pub mod workflows_mutation;
 
 #[derive(Default, MergedObject)]
-pub struct MutationRoot(EntitiesMutation, WorkflowsMutation, AdhocMutation);
+pub struct MutationRoot(
+    EntitiesMutation,
+    WorkflowsMutation,
+    AdhocMutation,
+    ConfigMutation,
+    EinkAlbumMutation,
+);
@@ -0,0 +1,51 @@
+use async_graphql::Object;
+use ractor::factory::{FactoryMessage, Job, JobOptions};
+
+use crate::actors::devices::smart_switch::{Message, SmartSwitchHandler};
+use crate::auth::scope::required;
+use crate::graphql::guard::ScopeGuard;
+
+pub struct SmartSwitchMutation {
+    pub address: String,
+}
+
+fn dispatch(message: Message) -> async_graphql::Result<bool> {
+    let Some(actor) = ractor::registry::where_is(SmartSwitchHandler::NAME) else {
+        return Err(async_graphql::Error::new("smart switch actor unavailable"));
+    };
+
+    let message = FactoryMessage::Dispatch(Job {
+        key: (),
+        msg: message,
+        options: JobOptions::default(),
+        accepted: None,
+    });
+
+    actor.send_message(message)?;
+
+    Ok(true)
+}
+
+#[Object]
+impl SmartSwitchMutation {
+    #[graphql(guard = ScopeGuard(required::GRAPHQL_SMART_SWITCH_WRITE))]
+    async fn on(&self) -> async_graphql::Result<bool> {
+        dispatch(Message::TurnOn {
+            address: self.address.clone(),
+        })
+    }
+
+    #[graphql(guard = ScopeGuard(required::GRAPHQL_SMART_SWITCH_WRITE))]
+    async fn off(&self) -> async_graphql::Result<bool> {
+        dispatch(Message::TurnOff {
+            address: self.address.clone(),
+        })
+    }
+
+    #[graphql(guard = ScopeGuard(required::GRAPHQL_SMART_SWITCH_WRITE))]
+    async fn toggle(&self) -> async_graphql::Result<bool> {
+        dispatch(Message::Toggle {
+            address: self.address.clone(),
+        })
+    }
+}
@@ -1,12 +1,14 @@
 use async_graphql::Object;
 use uuid::Uuid;
 
+use crate::actors::workflows::invoke::{Invocation, WorkflowExecution, invoke};
 use crate::actors::workflows::manager::WorkflowManager;
-use crate::auth::scope::required;
+use crate::auth::{AuthContext, scope::required};
 use crate::event_bus::{EventBus, EventBusMessage};
 use crate::graphql::guard::ScopeGuard;
+use crate::graphql::objects::workflow_object::{WorkflowInput, WorkflowVariable};
 use crate::mode::Mode;
-use crate::settings::SettingsContainer;
+use crate::settings::LiveConfig;
 
 #[derive(Default)]
 pub struct WorkflowsMutation;
@@ -20,7 +22,7 @@ impl WorkflowsMutation {
         slug: String,
         enabled: bool,
     ) -> async_graphql::Result<bool> {
-        let settings = ctx.data::<SettingsContainer>()?;
+        let settings = &ctx.data::<LiveConfig>()?.settings();
         let known = settings.workflows.values().any(|w| w.slug == slug);
         if !known {
             return Err(async_graphql::Error::new(format!(
@@ -33,6 +35,40 @@ impl WorkflowsMutation {
         Ok(enabled)
     }
 
+    /// Run the configured workflow `slug` now, with `inputs` as its template
+    /// vars. With `wait`, returns once the run ends with its outcome and
+    /// trace; otherwise returns the queued run's id, to look up through
+    /// `workflowRuns(eventId:)`. A `graphql:workflow:execute:<tag>` scope
+    /// covers workflows carrying that tag.
+    async fn execute_workflow(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+        slug: String,
+        #[graphql(default)] inputs: Vec<WorkflowInput>,
+        #[graphql(default)] dry_run: bool,
+        #[graphql(default)] wait: bool,
+    ) -> async_graphql::Result<WorkflowExecution> {
+        let settings = &ctx.data::<LiveConfig>()?.settings();
+        let auth = ctx.data::<AuthContext>()?;
+        let invocation = Invocation {
+            slug,
+            inputs: inputs
+                .into_iter()
+                .map(|input| (input.name, input.value))
+                .collect(),
+            dry_run,
+            wait,
+        };
+
+        Ok(invoke(
+            settings,
+            auth,
+            &required::GRAPHQL_WORKFLOW_EXECUTE,
+            invocation,
+        )
+        .await?)
+    }
+
     #[graphql(guard = ScopeGuard(required::GRAPHQL_WORKFLOW_WRITE))]
     async fn set_mode(
         &self,
@@ -55,6 +91,38 @@ impl WorkflowsMutation {
         Ok(manager.active_modes().await)
     }
 
+    /// Set a declared workflow variable. `value` is parsed as the variable's
+    /// type, and a change publishes a `variable` event like a `set_variable`
+    /// step does.
+    #[graphql(guard = ScopeGuard(required::GRAPHQL_VARIABLE_WRITE))]
+    async fn set_variable(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+        name: String,
+        value: String,
+    ) -> async_graphql::Result<WorkflowVariable> {
+        let settings = &ctx.data::<LiveConfig>()?.settings();
+        let Some(variable) = settings.variables.get(&name) else {
+            return Err(async_graphql::Error::new(format!(
+                "unknown variable: {name}"
+            )));
+        };
+        let value = variable.parse(&value).map_err(async_graphql::Error::new)?;
+
+        let manager = ctx.data::<WorkflowManager>()?;
+        let event_bus = ctx.data::<EventBus>()?;
+
+        if let Some(change) = manager.set_variable(&name, variable, value.clone()).await? {
+            event_bus.publish(change.into_event());
+        }
+
+        Ok(WorkflowVariable {
+            name,
+            kind: variable.into(),
+            value: Some(value),
+        })
+    }
+
     #[graphql(
         guard = ScopeGuard(required::GRAPHQL_WORKFLOW_WRITE),
         deprecation = "use setMode(mode: GUEST, active: ...) instead"
@@ -0,0 +1,106 @@
+use async_graphql::{Object, SimpleObject};
+use chrono::{DateTime, Utc};
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    actors::devices::control_switch::ControlSwitchHandler,
+    device_registry::{Capability, DeviceRegistry},
+    graphql::objects::entity_object::{available_for, last_seen_for},
+};
+
+#[derive(SimpleObject)]
+pub struct ControlSwitchAction {
+    /// The action as the switch reported it, e.g. `single` or `brightness_move_up`.
+    pub action: String,
+    pub time: DateTime<Utc>,
+}
+
+pub struct ControlSwitchEntity {
+    /// machine slug (configured sensor id), also its name.
+    pub id: String,
+    /// ieee address, the `device_metric` address.
+    pub address: String,
+    pub capabilities: Vec<Capability>,
+    pub room: Option<String>,
+}
+
+impl ControlSwitchEntity {
+    pub fn from_registry(registry: &DeviceRegistry, address: &str) -> Option<Self> {
+        if !registry.control_switch(address) {
+            return None;
+        }
+        let id = registry
+            .id_for_address(address)
+            .unwrap_or(address)
+            .to_owned();
+        Some(Self {
+            id,
+            address: address.to_owned(),
+            capabilities: registry.capabilities(address).to_vec(),
+            room: registry.room(address).map(str::to_owned),
+        })
+    }
+}
+
+#[Object]
+impl ControlSwitchEntity {
+    async fn category(&self) -> super::EntityCategory {
+        super::EntityCategory::Controls
+    }
+
+    async fn id(&self) -> &str {
+        &self.id
+    }
+
+    async fn name(&self) -> &str {
+        &self.id
+    }
+
+    async fn capabilities(&self) -> &[Capability] {
+        &self.capabilities
+    }
+
+    async fn room(&self) -> Option<&str> {
+        self.room.as_deref()
+    }
+
+    async fn battery(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+    ) -> async_graphql::Result<Option<super::DeviceBattery>> {
+        super::battery_for(ctx, &self.id).await
+    }
+
+    /// The most recent press, null until the switch has been used.
+    async fn last_action(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+    ) -> async_graphql::Result<Option<ControlSwitchAction>> {
+        let db = ctx.data::<Pool<Postgres>>()?;
+
+        Ok(sqlx::query_as!(
+            ControlSwitchAction,
+            r#"SELECT text_value AS "action!", updated_at AS "time"
+               FROM latest_device_metric
+               WHERE address = $1 AND metric = $2 AND text_value IS NOT NULL"#,
+            self.address,
+            ControlSwitchHandler::ACTION_METRIC
+        )
+        .fetch_optional(db)
+        .await?)
+    }
+
+    async fn last_seen(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+    ) -> async_graphql::Result<Option<DateTime<Utc>>> {
+        last_seen_for(ctx, &self.address).await
+    }
+
+    async fn available(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+    ) -> async_graphql::Result<Option<bool>> {
+        available_for(ctx, &self.address).await
+    }
+}
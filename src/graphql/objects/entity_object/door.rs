@@ -8,7 +8,7 @@ use crate::{
     },
     db::DoorState,
     device_registry::{Capability, DeviceRegistry},
-    graphql::objects::entity_object::{QUERY_TIMEOUT, last_seen_for},
+    graphql::objects::entity_object::{QUERY_TIMEOUT, available_for, last_seen_for},
 };
 
 pub struct DoorEntity {
@@ -83,4 +83,11 @@ impl DoorEntity {
     ) -> async_graphql::Result<Option<DateTime<Utc>>> {
         last_seen_for(ctx, &self.address).await
     }
+
+    async fn available(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+    ) -> async_graphql::Result<Option<bool>> {
+        available_for(ctx, &self.address).await
+    }
 }
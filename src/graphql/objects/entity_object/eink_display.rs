@@ -5,13 +5,14 @@ use itertools::Itertools;
 use super::BatteryPoint;
 use crate::{
     device_registry::{Capability, DeviceRegistry},
-    eink::EinkDisplayManager,
+    eink::{EinkDisplayManager, manager::EinkRender},
     graphql::dataloader::{
         device_battery_history::{DeviceBatteryHistoryDataLoader, clamp_since},
         eink_battery::EinkDisplayDataLoader,
     },
     routes::epd::{DeviceReport, EpdConfig},
     settings::EinkDisplaySettings,
+    settings::LiveConfig,
     settings::{EinkMode, Orientation, RedditTimespan},
     timedelta_format::humanize,
 };
@@ -176,7 +177,7 @@ impl EinkDisplayEntity {
         &self,
         ctx: &async_graphql::Context<'_>,
     ) -> async_graphql::Result<Option<EinkDisplayConfig>> {
-        let registry = ctx.data::<DeviceRegistry>()?;
+        let registry = &ctx.data::<LiveConfig>()?.devices();
         Ok(registry
             .eink_display(&self.address)
             .map(EinkDisplayConfig::from))
@@ -198,6 +199,18 @@ impl EinkDisplayEntity {
         Ok(eink.epd_config(&display, DeviceReport::default()).await)
     }
 
+    /// Frames last served to the display, newest first. TRMNLs fetch theirs
+    /// as PNGs and are not recorded.
+    async fn renders(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+        #[graphql(default = 20)] limit: i64,
+    ) -> async_graphql::Result<Vec<EinkRender>> {
+        let eink = ctx.data::<EinkDisplayManager>()?;
+
+        Ok(eink.render_history(&self.address, limit).await?)
+    }
+
     async fn battery(
         &self,
         ctx: &async_graphql::Context<'_>,
@@ -5,7 +5,7 @@ use crate::{
     device_registry::{Capability, DeviceRegistry},
     graphql::{
         dataloader::temperature::{LatestTemperatureDataLoader, TemperatureModel},
-        objects::entity_object::last_seen_for,
+        objects::entity_object::{available_for, last_seen_for},
     },
 };
 
@@ -32,6 +32,18 @@ impl EnvironmentEntity {
         })
     }
 
+    /// The latest temperature, `None` when the sensor hasn't reported one.
+    pub async fn latest_temperature(
+        &self,
+        context: &async_graphql::Context<'_>,
+    ) -> async_graphql::Result<Option<f64>> {
+        let loader = context.data::<DataLoader<LatestTemperatureDataLoader>>()?;
+        Ok(loader
+            .load_one(self.id.clone())
+            .await?
+            .map(|t| t.temperature))
+    }
+
     async fn load<T, F>(
         &self,
         context: &async_graphql::Context<'_>,
@@ -127,4 +139,11 @@ impl EnvironmentEntity {
     ) -> async_graphql::Result<Option<DateTime<Utc>>> {
         last_seen_for(ctx, &self.address).await
     }
+
+    async fn available(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+    ) -> async_graphql::Result<Option<bool>> {
+        available_for(ctx, &self.address).await
+    }
 }
@@ -7,7 +7,7 @@ use crate::{
         system::rpc,
     },
     device_registry::{Capability, DeviceRegistry},
-    graphql::objects::entity_object::{QUERY_TIMEOUT, last_seen_for},
+    graphql::objects::entity_object::{QUERY_TIMEOUT, available_for, last_seen_for},
 };
 
 pub struct LightEntity {
@@ -36,6 +36,19 @@ impl LightEntity {
             room: registry.room(address).map(str::to_owned),
         })
     }
+
+    /// `None` until the light has reported a state.
+    pub async fn is_on(&self) -> async_graphql::Result<Option<bool>> {
+        Ok(
+            rpc::query_factory(LightHandler::NAME, QUERY_TIMEOUT, |reply| {
+                LightHandlerMessage::QueryPowerState {
+                    ieee_addr: self.address.clone(),
+                    reply,
+                }
+            })
+            .await?,
+        )
+    }
 }
 
 #[Object]
@@ -67,18 +80,11 @@ impl LightEntity {
         super::battery_for(ctx, &self.id).await
     }
 
-    /// Current power state. Nullable so an unreachable light actor reports the
-    /// error against this field without nulling the whole entity.
+    /// Current power state, null until the light has reported one. Nullable
+    /// also so an unreachable light actor reports the error against this field
+    /// without nulling the whole entity.
     async fn on(&self) -> async_graphql::Result<Option<bool>> {
-        Ok(Some(
-            rpc::query_factory(LightHandler::NAME, QUERY_TIMEOUT, |reply| {
-                LightHandlerMessage::QueryPowerState {
-                    ieee_addr: self.address.clone(),
-                    reply,
-                }
-            })
-            .await?,
-        ))
+        self.is_on().await
     }
 
     async fn last_seen(
@@ -87,4 +93,11 @@ impl LightEntity {
     ) -> async_graphql::Result<Option<DateTime<Utc>>> {
         last_seen_for(ctx, &self.address).await
     }
+
+    async fn available(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+    ) -> async_graphql::Result<Option<bool>> {
+        available_for(ctx, &self.address).await
+    }
 }
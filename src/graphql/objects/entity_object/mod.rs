@@ -3,7 +3,9 @@
 //! Mirrors the stateful domains of the `EventUpdate` subscription (light, door,
 //! presence, environment). Current state is read the same way workflow
 //! conditions read it — named-actor RPC (see [`crate::actors::workflows::conditions`])
-//! — except environment, which reuses the existing temperature dataloader.
+//! — except environment, which reuses the existing temperature dataloader, and
+//! smart switches, plants and control switches, which read what their actors
+//! store.
 
 use std::time::Duration;
 
@@ -11,27 +13,37 @@ use async_graphql::{Enum, SimpleObject, Union, dataloader::DataLoader};
 use chrono::{DateTime, Utc};
 
 use crate::graphql::dataloader::last_seen::LastSeenDataLoader;
+use crate::settings::LiveConfig;
 
 pub mod battery;
+pub mod control_switch;
 pub mod door;
 pub mod eink_display;
 pub mod environment;
 pub mod light;
 pub mod media_player;
+pub mod plant;
 pub mod presence;
 pub mod robot_vacuum;
+pub mod smart_switch;
 
 pub use battery::{BatteryPoint, DeviceBattery, battery_for};
+pub use control_switch::ControlSwitchEntity;
 pub use door::DoorEntity;
 pub use eink_display::EinkDisplayEntity;
 pub use environment::EnvironmentEntity;
 pub use light::LightEntity;
 pub use media_player::MediaPlayerEntity;
+pub use plant::PlantEntity;
 pub use presence::PresenceEntity;
 pub use robot_vacuum::RobotVacuumEntity;
+pub use smart_switch::SmartSwitchEntity;
 
 pub(super) const QUERY_TIMEOUT: Duration = Duration::from_secs(10);
 
+/// The most readings an entity's `history` returns, keeping the newest.
+pub(super) const HISTORY_LIMIT: i64 = 5000;
+
 /// Most recent time the device behind `address` was heard from, resolved via the
 /// batched `device_last_seen` dataloader. Nullable when the device has never been
 /// recorded.
@@ -43,6 +55,28 @@ pub(super) async fn last_seen_for(
     Ok(loader.load_one(address.to_owned()).await?)
 }
 
+/// Whether the device behind `address` has been heard from within its watchdog
+/// timeout, the same test the `available` workflow condition uses. Nullable
+/// when the device has never been recorded.
+pub(super) async fn available_for(
+    ctx: &async_graphql::Context<'_>,
+    address: &str,
+) -> async_graphql::Result<Option<bool>> {
+    let Some(last_seen) = last_seen_for(ctx, address).await? else {
+        return Ok(None);
+    };
+
+    let config = ctx.data::<LiveConfig>()?;
+    let watchdog = &config.settings().watchdog;
+    let devices = config.devices();
+
+    Ok(Some(watchdog.available(
+        devices.watchdog(address),
+        last_seen,
+        Utc::now(),
+    )))
+}
+
 /// The dashboard section a device kind is displayed under. Owned by the backend
 /// so categorising a kind never requires a frontend change. Variants are declared
 /// in display order — see [`EntityCategory::ORDERED`].
@@ -55,11 +89,14 @@ pub enum EntityCategory {
     Displays,
     Vacuums,
     Media,
+    Switches,
+    Plants,
+    Controls,
 }
 
 impl EntityCategory {
     /// Every category in the order sections should render.
-    pub const ORDERED: [EntityCategory; 7] = [
+    pub const ORDERED: [EntityCategory; 10] = [
         EntityCategory::Lights,
         EntityCategory::Doors,
         EntityCategory::Presence,
@@ -67,6 +104,9 @@ impl EntityCategory {
         EntityCategory::Displays,
         EntityCategory::Vacuums,
         EntityCategory::Media,
+        EntityCategory::Switches,
+        EntityCategory::Plants,
+        EntityCategory::Controls,
     ];
 }
 
@@ -80,6 +120,9 @@ impl std::fmt::Display for EntityCategory {
             EntityCategory::Displays => "Displays",
             EntityCategory::Vacuums => "Vacuums",
             EntityCategory::Media => "Media",
+            EntityCategory::Switches => "Switches",
+            EntityCategory::Plants => "Plants",
+            EntityCategory::Controls => "Controls",
         })
     }
 }
@@ -111,4 +154,25 @@ pub enum Entity {
     EinkDisplay(EinkDisplayEntity),
     RobotVacuum(RobotVacuumEntity),
     MediaPlayer(MediaPlayerEntity),
+    SmartSwitch(SmartSwitchEntity),
+    Plant(PlantEntity),
+    ControlSwitch(ControlSwitchEntity),
+}
+
+impl Entity {
+    /// The room the entity's device is placed in.
+    pub fn room(&self) -> Option<&str> {
+        match self {
+            Entity::Light(light) => light.room.as_deref(),
+            Entity::Environment(environment) => environment.room.as_deref(),
+            Entity::Door(door) => door.room.as_deref(),
+            Entity::Presence(presence) => presence.room.as_deref(),
+            Entity::EinkDisplay(display) => display.room.as_deref(),
+            Entity::RobotVacuum(vacuum) => vacuum.room.as_deref(),
+            Entity::MediaPlayer(player) => player.room.as_deref(),
+            Entity::SmartSwitch(switch) => switch.room.as_deref(),
+            Entity::Plant(plant) => plant.room.as_deref(),
+            Entity::ControlSwitch(switch) => switch.room.as_deref(),
+        }
+    }
 }
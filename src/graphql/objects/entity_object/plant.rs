@@ -0,0 +1,170 @@
+use async_graphql::{Object, SimpleObject};
+use chrono::{DateTime, Utc};
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    device_registry::{Capability, DeviceRegistry},
+    graphql::{
+        dataloader::device_battery_history::clamp_since,
+        objects::entity_object::{HISTORY_LIMIT, available_for, last_seen_for},
+    },
+};
+
+const SOIL_MOISTURE: &str = "soil_moisture";
+
+#[derive(SimpleObject)]
+pub struct PlantReading {
+    /// esphome sensor object_id, e.g. `soil_moisture`.
+    pub metric: String,
+    pub value: f64,
+    pub time: DateTime<Utc>,
+}
+
+pub struct PlantEntity {
+    /// machine slug (configured sensor id).
+    pub id: String,
+    /// the plant's configured id, e.g. `hallway-plant`.
+    pub name: String,
+    /// esphome node, the `device_metric` address.
+    pub address: String,
+    /// the esphome sensor object_ids the plant reports.
+    pub metrics: Vec<String>,
+    pub capabilities: Vec<Capability>,
+    pub room: Option<String>,
+}
+
+impl PlantEntity {
+    pub fn from_registry(registry: &DeviceRegistry, address: &str) -> Option<Self> {
+        let settings = registry.plant(address)?;
+        let id = registry
+            .id_for_address(address)
+            .unwrap_or(address)
+            .to_owned();
+        Some(Self {
+            id,
+            name: settings.id.clone(),
+            address: address.to_owned(),
+            metrics: settings.entities.clone(),
+            capabilities: registry.capabilities(address).to_vec(),
+            room: registry.room(address).map(str::to_owned),
+        })
+    }
+}
+
+#[Object]
+impl PlantEntity {
+    async fn category(&self) -> super::EntityCategory {
+        super::EntityCategory::Plants
+    }
+
+    async fn id(&self) -> &str {
+        &self.id
+    }
+
+    async fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn capabilities(&self) -> &[Capability] {
+        &self.capabilities
+    }
+
+    async fn room(&self) -> Option<&str> {
+        self.room.as_deref()
+    }
+
+    async fn battery(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+    ) -> async_graphql::Result<Option<super::DeviceBattery>> {
+        super::battery_for(ctx, &self.id).await
+    }
+
+    /// The latest soil moisture percentage, null until the sensor has reported one.
+    async fn moisture(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+    ) -> async_graphql::Result<Option<f64>> {
+        let db = ctx.data::<Pool<Postgres>>()?;
+
+        Ok(sqlx::query_scalar!(
+            "SELECT value FROM latest_device_metric WHERE address = $1 AND metric = $2",
+            self.address,
+            SOIL_MOISTURE
+        )
+        .fetch_optional(db)
+        .await?
+        .flatten())
+    }
+
+    /// The latest reading of each sensor the plant is configured with.
+    async fn readings(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+    ) -> async_graphql::Result<Vec<PlantReading>> {
+        let db = ctx.data::<Pool<Postgres>>()?;
+
+        Ok(sqlx::query_as!(
+            PlantReading,
+            r#"SELECT metric, value AS "value!", updated_at AS "time"
+               FROM latest_device_metric
+               WHERE address = $1 AND metric = ANY($2) AND value IS NOT NULL
+               ORDER BY metric"#,
+            self.address,
+            &self.metrics
+        )
+        .fetch_all(db)
+        .await?)
+    }
+
+    /// Readings of `metric` since `since`, oldest first. The window is clamped
+    /// to the last 90 days and the most recent readings are kept.
+    async fn history(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+        since: DateTime<Utc>,
+        #[graphql(default_with = "SOIL_MOISTURE.to_owned()")] metric: String,
+    ) -> async_graphql::Result<Vec<PlantReading>> {
+        if !self.metrics.contains(&metric) {
+            return Err(async_graphql::Error::new(format!(
+                "plant `{}` does not report `{metric}`",
+                self.name
+            )));
+        }
+
+        let db = ctx.data::<Pool<Postgres>>()?;
+        let since = clamp_since(since, Utc::now());
+
+        let mut readings = sqlx::query_as!(
+            PlantReading,
+            r#"SELECT metric, value AS "value!", "time"
+               FROM device_metric
+               WHERE address = $1 AND metric = $2 AND "time" >= $3 AND value IS NOT NULL
+               ORDER BY "time" DESC
+               LIMIT $4"#,
+            self.address,
+            metric,
+            since,
+            HISTORY_LIMIT
+        )
+        .fetch_all(db)
+        .await?;
+        readings.reverse();
+
+        Ok(readings)
+    }
+
+    async fn last_seen(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+    ) -> async_graphql::Result<Option<DateTime<Utc>>> {
+        last_seen_for(ctx, &self.address).await
+    }
+
+    async fn available(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+    ) -> async_graphql::Result<Option<bool>> {
+        available_for(ctx, &self.address).await
+    }
+}
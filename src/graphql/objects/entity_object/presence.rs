@@ -7,7 +7,7 @@ use crate::{
         system::rpc,
     },
     device_registry::{Capability, DeviceRegistry},
-    graphql::objects::entity_object::{QUERY_TIMEOUT, last_seen_for},
+    graphql::objects::entity_object::{QUERY_TIMEOUT, available_for, last_seen_for},
 };
 
 pub struct PresenceEntity {
@@ -41,6 +41,19 @@ impl PresenceEntity {
             room: registry.room(address).map(str::to_owned),
         })
     }
+
+    /// A sensor that hasn't reported counts as not present.
+    pub async fn is_present(&self) -> async_graphql::Result<bool> {
+        let present: Option<bool> =
+            rpc::query_factory(PresenceSensorHandler::NAME, QUERY_TIMEOUT, |reply| {
+                PresenceMessage::QueryLatest {
+                    sensor: self.address.clone(),
+                    reply,
+                }
+            })
+            .await?;
+        Ok(present.unwrap_or(false))
+    }
 }
 
 #[Object]
@@ -75,15 +88,7 @@ impl PresenceEntity {
     /// Whether presence is detected. Nullable so an unreachable presence actor
     /// reports the error against this field without nulling the whole entity.
     async fn present(&self) -> async_graphql::Result<Option<bool>> {
-        let present: Option<bool> =
-            rpc::query_factory(PresenceSensorHandler::NAME, QUERY_TIMEOUT, |reply| {
-                PresenceMessage::QueryLatest {
-                    sensor: self.address.clone(),
-                    reply,
-                }
-            })
-            .await?;
-        Ok(Some(present.unwrap_or(false)))
+        Ok(Some(self.is_present().await?))
     }
 
     async fn last_seen(
@@ -92,4 +97,11 @@ impl PresenceEntity {
     ) -> async_graphql::Result<Option<DateTime<Utc>>> {
         last_seen_for(ctx, &self.address).await
     }
+
+    async fn available(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+    ) -> async_graphql::Result<Option<bool>> {
+        available_for(ctx, &self.address).await
+    }
 }
@@ -0,0 +1,158 @@
+use async_graphql::{Object, SimpleObject};
+use chrono::{DateTime, Utc};
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    device_registry::{Capability, DeviceRegistry},
+    graphql::{
+        dataloader::device_battery_history::clamp_since,
+        objects::entity_object::{HISTORY_LIMIT, available_for, last_seen_for},
+    },
+};
+
+#[derive(SimpleObject)]
+pub struct SmartSwitchReading {
+    /// Power draw in watts.
+    pub power: i64,
+    /// Cumulative energy in kWh, as the plug reports it.
+    pub energy: f64,
+    pub voltage: i64,
+    pub current: f64,
+    pub time: DateTime<Utc>,
+}
+
+pub struct SmartSwitchEntity {
+    /// machine slug (configured sensor id).
+    pub id: String,
+    /// human-friendly name.
+    pub name: String,
+    /// ieee address or home assistant entity id, the `smart_switch` table key.
+    pub address: String,
+    pub capabilities: Vec<Capability>,
+    pub room: Option<String>,
+}
+
+impl SmartSwitchEntity {
+    pub fn from_registry(registry: &DeviceRegistry, address: &str) -> Option<Self> {
+        let settings = registry.smart_switch(address)?;
+        let id = registry
+            .id_for_address(address)
+            .unwrap_or(address)
+            .to_owned();
+        Some(Self {
+            id,
+            name: settings.name.clone(),
+            address: address.to_owned(),
+            capabilities: registry.capabilities(address).to_vec(),
+            room: registry.room(address).map(str::to_owned),
+        })
+    }
+}
+
+#[Object]
+impl SmartSwitchEntity {
+    async fn category(&self) -> super::EntityCategory {
+        super::EntityCategory::Switches
+    }
+
+    async fn id(&self) -> &str {
+        &self.id
+    }
+
+    async fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn capabilities(&self) -> &[Capability] {
+        &self.capabilities
+    }
+
+    async fn room(&self) -> Option<&str> {
+        self.room.as_deref()
+    }
+
+    async fn battery(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+    ) -> async_graphql::Result<Option<super::DeviceBattery>> {
+        super::battery_for(ctx, &self.id).await
+    }
+
+    /// The last reported power state. zigbee2mqtt reports `ON`/`OFF` while Home
+    /// Assistant uses `on`/`off`, so it is compared case-insensitively. Null
+    /// until the switch has reported a state.
+    async fn on(&self, ctx: &async_graphql::Context<'_>) -> async_graphql::Result<Option<bool>> {
+        let db = ctx.data::<Pool<Postgres>>()?;
+
+        Ok(sqlx::query!(
+            "SELECT state FROM smart_switch_state WHERE address = $1",
+            self.address
+        )
+        .fetch_optional(db)
+        .await?
+        .map(|row| row.state.eq_ignore_ascii_case("on")))
+    }
+
+    /// The latest power and energy reading. Null for switches that don't meter,
+    /// such as those controlled through Home Assistant.
+    async fn reading(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+    ) -> async_graphql::Result<Option<SmartSwitchReading>> {
+        let db = ctx.data::<Pool<Postgres>>()?;
+
+        Ok(sqlx::query_as!(
+            SmartSwitchReading,
+            r#"SELECT power, energy, voltage, current_f64 AS current, "time"
+               FROM smart_switch
+               WHERE ieee_addr = $1
+               ORDER BY "time" DESC
+               LIMIT 1"#,
+            self.address
+        )
+        .fetch_optional(db)
+        .await?)
+    }
+
+    /// Power and energy readings since `since`, oldest first. The window is
+    /// clamped to the last 90 days and the most recent readings are kept.
+    async fn history(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+        since: DateTime<Utc>,
+    ) -> async_graphql::Result<Vec<SmartSwitchReading>> {
+        let db = ctx.data::<Pool<Postgres>>()?;
+        let since = clamp_since(since, Utc::now());
+
+        let mut readings = sqlx::query_as!(
+            SmartSwitchReading,
+            r#"SELECT power, energy, voltage, current_f64 AS current, "time"
+               FROM smart_switch
+               WHERE ieee_addr = $1 AND "time" >= $2
+               ORDER BY "time" DESC
+               LIMIT $3"#,
+            self.address,
+            since,
+            HISTORY_LIMIT
+        )
+        .fetch_all(db)
+        .await?;
+        readings.reverse();
+
+        Ok(readings)
+    }
+
+    async fn last_seen(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+    ) -> async_graphql::Result<Option<DateTime<Utc>>> {
+        last_seen_for(ctx, &self.address).await
+    }
+
+    async fn available(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+    ) -> async_graphql::Result<Option<bool>> {
+        available_for(ctx, &self.address).await
+    }
+}
@@ -0,0 +1,48 @@
+use async_graphql::SimpleObject;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+#[derive(async_graphql::Enum, Clone, Copy, PartialEq, Eq)]
+pub enum SuppressionReason {
+    QuietHours,
+    Duplicate,
+    RateLimited,
+}
+
+impl SuppressionReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SuppressionReason::QuietHours => "quiet_hours",
+            SuppressionReason::Duplicate => "duplicate",
+            SuppressionReason::RateLimited => "rate_limited",
+        }
+    }
+
+    pub fn parse(reason: &str) -> Option<Self> {
+        Some(match reason {
+            "quiet_hours" => SuppressionReason::QuietHours,
+            "duplicate" => SuppressionReason::Duplicate,
+            "rate_limited" => SuppressionReason::RateLimited,
+            _ => return None,
+        })
+    }
+}
+
+/// A notification the policy held back from one target.
+#[derive(SimpleObject)]
+pub struct NotificationSuppression {
+    pub id: async_graphql::ID,
+    /// The target, e.g. `android_app` or `ntfy:https://ntfy.sh/house`.
+    pub target: String,
+    pub channel: String,
+    /// The sender, e.g. `workflow:bins` or `door:front`.
+    pub source: String,
+    pub event_id: Uuid,
+    pub message: String,
+    pub reason: SuppressionReason,
+    /// Held for a digest rather than dropped.
+    pub digest: bool,
+    pub suppressed_at: DateTime<Utc>,
+    /// When the digest carrying it went out.
+    pub digested_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,91 @@
+use async_graphql::Object;
+
+use crate::graphql::objects::entity_object::Entity;
+use crate::settings::Room;
+
+/// A declared room and the entities placed in it. The aggregates cover only
+/// the entities listed, so they respect the caller's scopes the same way.
+pub struct RoomObject {
+    pub id: String,
+    pub room: Room,
+    pub entities: Vec<Entity>,
+}
+
+#[Object]
+impl RoomObject {
+    async fn id(&self) -> &str {
+        &self.id
+    }
+
+    async fn name(&self) -> &str {
+        &self.room.name
+    }
+
+    async fn floor(&self) -> i32 {
+        self.room.floor
+    }
+
+    async fn icon(&self) -> Option<&str> {
+        self.room.icon.as_deref()
+    }
+
+    async fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    /// Whether any presence sensor in the room detects someone. Null when the
+    /// room has no presence sensors.
+    async fn occupied(&self) -> async_graphql::Result<Option<bool>> {
+        let mut occupied = None;
+
+        for entity in &self.entities {
+            if let Entity::Presence(presence) = entity {
+                if presence.is_present().await? {
+                    return Ok(Some(true));
+                }
+                occupied = Some(false);
+            }
+        }
+
+        Ok(occupied)
+    }
+
+    /// The mean of the room's latest temperature readings. Null when none of
+    /// its environment sensors has reported.
+    async fn average_temperature(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+    ) -> async_graphql::Result<Option<f64>> {
+        let mut readings = Vec::new();
+
+        for entity in &self.entities {
+            if let Entity::Environment(environment) = entity
+                && let Some(temperature) = environment.latest_temperature(ctx).await?
+            {
+                readings.push(temperature);
+            }
+        }
+
+        if readings.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(readings.iter().sum::<f64>() / readings.len() as f64))
+    }
+
+    /// How many of the room's lights are on. Nullable so an unreachable light
+    /// actor reports the error against this field without nulling the room.
+    async fn lights_on(&self) -> async_graphql::Result<Option<i32>> {
+        let mut on = 0;
+
+        for entity in &self.entities {
+            if let Entity::Light(light) = entity
+                && light.is_on().await? == Some(true)
+            {
+                on += 1;
+            }
+        }
+
+        Ok(Some(on))
+    }
+}
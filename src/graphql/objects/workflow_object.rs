@@ -1,6 +1,9 @@
-use async_graphql::SimpleObject;
+use async_graphql::{InputObject, SimpleObject};
 use chrono::{DateTime, Utc};
 
+use crate::actors::workflows::trace::RunTrace;
+use crate::settings::Variable;
+
 #[derive(SimpleObject)]
 pub struct WorkflowRun {
     pub id: async_graphql::ID,
@@ -12,6 +15,9 @@ pub struct WorkflowRun {
     pub duration_ms: i64,
     pub error: Option<String>,
     pub started_at: DateTime<Utc>,
+    /// What the run did step by step. Null for runs recorded before traces
+    /// were kept, or whose trace has been trimmed.
+    pub trace: Option<RunTrace>,
 }
 
 #[derive(SimpleObject)]
@@ -26,3 +32,38 @@ pub struct WorkflowStatus {
     pub dry_run: bool,
     pub reusable: bool,
 }
+
+#[derive(async_graphql::Enum, Clone, Copy, PartialEq, Eq)]
+pub enum VariableType {
+    Number,
+    Boolean,
+    String,
+    Timestamp,
+}
+
+impl From<&Variable> for VariableType {
+    fn from(variable: &Variable) -> Self {
+        match variable {
+            Variable::Number { .. } => VariableType::Number,
+            Variable::Boolean { .. } => VariableType::Boolean,
+            Variable::Text { .. } => VariableType::String,
+            Variable::Timestamp => VariableType::Timestamp,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct WorkflowVariable {
+    pub name: String,
+    #[graphql(name = "type")]
+    pub kind: VariableType,
+    /// Stored as text; `null` for a timestamp that was never set.
+    pub value: Option<String>,
+}
+
+/// A template var passed to `executeWorkflow`.
+#[derive(InputObject)]
+pub struct WorkflowInput {
+    pub name: String,
+    pub value: String,
+}
@@ -0,0 +1,23 @@
+use crate::auth::scope::required;
+use crate::eink::EinkDisplayManager;
+use crate::eink::manager::album::EinkAlbumItem;
+use crate::graphql::guard::ScopeGuard;
+use async_graphql::{Context, Object};
+
+#[derive(Default)]
+pub struct EinkAlbumQuery;
+
+#[Object]
+impl EinkAlbumQuery {
+    /// Every photo in the album, pinned or not.
+    #[graphql(guard = ScopeGuard(required::GRAPHQL_EPD_READ))]
+    async fn eink_album_items(
+        &self,
+        ctx: &Context<'_>,
+        album: String,
+    ) -> async_graphql::Result<Vec<EinkAlbumItem>> {
+        let eink = ctx.data::<EinkDisplayManager>()?;
+
+        Ok(eink.album_items(&album).await?)
+    }
+}
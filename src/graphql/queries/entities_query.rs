@@ -5,9 +5,12 @@ use crate::auth::scope::required;
 use crate::device_registry::DeviceRegistry;
 use crate::graphql::guard::ScopeGuard;
 use crate::graphql::objects::entity_object::{
-    DoorEntity, EinkDisplayEntity, Entity, EntitySection, EnvironmentEntity, LightEntity,
-    MediaPlayerEntity, PresenceEntity, RobotVacuumEntity,
+    ControlSwitchEntity, DoorEntity, EinkDisplayEntity, Entity, EntitySection, EnvironmentEntity,
+    LightEntity, MediaPlayerEntity, PlantEntity, PresenceEntity, RobotVacuumEntity,
+    SmartSwitchEntity,
 };
+use crate::graphql::objects::room_object::RoomObject;
+use crate::settings::LiveConfig;
 
 #[derive(Default)]
 pub struct EntitiesQuery;
@@ -28,89 +31,46 @@ impl EntitiesQuery {
         &self,
         ctx: &async_graphql::Context<'_>,
     ) -> async_graphql::Result<Vec<Entity>> {
-        let registry = ctx.data::<DeviceRegistry>()?;
+        let registry = &ctx.data::<LiveConfig>()?.devices();
         let auth = ctx.data::<AuthContext>()?;
 
-        let mut out = Vec::new();
-
-        if auth.has(&required::GRAPHQL_LIGHT_READ) {
-            out.extend(
-                registry
-                    .lights()
-                    .filter_map(|(address, _)| LightEntity::from_registry(registry, address))
-                    .map(Entity::Light),
-            );
-        }
-
-        if auth.has(&required::GRAPHQL_DOOR_READ) {
-            out.extend(
-                registry
-                    .doors()
-                    .filter_map(|(address, _)| DoorEntity::from_registry(registry, address))
-                    .map(Entity::Door),
-            );
-        }
-
-        if auth.has(&required::GRAPHQL_PRESENCE_READ) {
-            out.extend(
-                registry
-                    .presence_devices()
-                    .filter_map(|(address, _)| PresenceEntity::from_registry(registry, address))
-                    .map(Entity::Presence),
-            );
-        }
-
-        if auth.has(&required::GRAPHQL_ENVIRONMENT_READ) {
-            out.extend(
-                registry
-                    .environment_devices()
-                    .filter_map(|(address, _)| EnvironmentEntity::from_registry(registry, address))
-                    .map(Entity::Environment),
-            );
-        }
+        Ok(visible_entities(registry, auth))
+    }
 
-        if auth.has(&required::GRAPHQL_EPD_READ) {
-            out.extend(
-                registry
-                    .eink_displays()
-                    .keys()
-                    .filter_map(|address| EinkDisplayEntity::from_firmware(registry, address))
-                    .map(Entity::EinkDisplay),
-            );
-            out.extend(
-                registry
-                    .trmnl_devices()
-                    .keys()
-                    .filter_map(|address| EinkDisplayEntity::from_trmnl(registry, address))
-                    .map(Entity::EinkDisplay),
-            );
-        }
+    /// Every declared room, floor by floor, with the entities placed in it.
+    /// Entities are omitted by scope exactly as in `entities`.
+    async fn rooms(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+    ) -> async_graphql::Result<Vec<RoomObject>> {
+        let config = ctx.data::<LiveConfig>()?;
+        let auth = ctx.data::<AuthContext>()?;
 
-        if auth.has(&required::GRAPHQL_ROBOT_VACUUM_READ) {
-            out.extend(
-                registry
-                    .roborocks()
-                    .filter_map(|(address, _)| RobotVacuumEntity::from_roborock(registry, address))
-                    .map(Entity::RobotVacuum),
-            );
-            out.extend(
-                registry
-                    .valetudos()
-                    .filter_map(|(address, _)| RobotVacuumEntity::from_valetudo(registry, address))
-                    .map(Entity::RobotVacuum),
-            );
-        }
+        let mut rooms: Vec<RoomObject> = config
+            .settings()
+            .rooms
+            .iter()
+            .map(|(id, room)| RoomObject {
+                id: id.clone(),
+                room: room.clone(),
+                entities: Vec::new(),
+            })
+            .collect();
+        rooms.sort_by(|a, b| {
+            (a.room.floor, &a.room.name, &a.id).cmp(&(b.room.floor, &b.room.name, &b.id))
+        });
 
-        if auth.has(&required::GRAPHQL_MEDIA_PLAYER_READ) {
-            out.extend(
-                registry
-                    .media_players()
-                    .filter_map(|(address, _)| MediaPlayerEntity::from_registry(registry, address))
-                    .map(Entity::MediaPlayer),
-            );
+        for entity in visible_entities(&config.devices(), auth) {
+            let Some(index) = entity
+                .room()
+                .and_then(|room| rooms.iter().position(|r| r.id == room))
+            else {
+                continue;
+            };
+            rooms[index].entities.push(entity);
         }
 
-        Ok(out)
+        Ok(rooms)
     }
 
     #[graphql(guard = ScopeGuard(required::GRAPHQL_MEDIA_PLAYER_READ))]
@@ -119,7 +79,7 @@ impl EntitiesQuery {
         ctx: &async_graphql::Context<'_>,
         id: String,
     ) -> async_graphql::Result<MediaPlayerEntity> {
-        let registry = ctx.data::<DeviceRegistry>()?;
+        let registry = &ctx.data::<LiveConfig>()?.devices();
         let address = registry.address_or_self(&id).to_owned();
         MediaPlayerEntity::from_registry(registry, &address)
             .ok_or_else(|| async_graphql::Error::new(format!("unknown media player `{id}`")))
@@ -131,7 +91,7 @@ impl EntitiesQuery {
         ctx: &async_graphql::Context<'_>,
         id: String,
     ) -> async_graphql::Result<LightEntity> {
-        let registry = ctx.data::<DeviceRegistry>()?;
+        let registry = &ctx.data::<LiveConfig>()?.devices();
         let address = registry.address_or_self(&id).to_owned();
         LightEntity::from_registry(registry, &address)
             .ok_or_else(|| async_graphql::Error::new(format!("unknown light `{id}`")))
@@ -143,7 +103,7 @@ impl EntitiesQuery {
         ctx: &async_graphql::Context<'_>,
         id: String,
     ) -> async_graphql::Result<DoorEntity> {
-        let registry = ctx.data::<DeviceRegistry>()?;
+        let registry = &ctx.data::<LiveConfig>()?.devices();
         let address = registry.address_or_self(&id).to_owned();
         DoorEntity::from_registry(registry, &address)
             .ok_or_else(|| async_graphql::Error::new(format!("unknown door `{id}`")))
@@ -155,7 +115,7 @@ impl EntitiesQuery {
         ctx: &async_graphql::Context<'_>,
         id: String,
     ) -> async_graphql::Result<PresenceEntity> {
-        let registry = ctx.data::<DeviceRegistry>()?;
+        let registry = &ctx.data::<LiveConfig>()?.devices();
         let address = registry.address_or_self(&id).to_owned();
         PresenceEntity::from_registry(registry, &address)
             .ok_or_else(|| async_graphql::Error::new(format!("unknown presence sensor `{id}`")))
@@ -167,7 +127,7 @@ impl EntitiesQuery {
         ctx: &async_graphql::Context<'_>,
         id: String,
     ) -> async_graphql::Result<EnvironmentEntity> {
-        let registry = ctx.data::<DeviceRegistry>()?;
+        let registry = &ctx.data::<LiveConfig>()?.devices();
         let address = registry.address_or_self(&id).to_owned();
         EnvironmentEntity::from_registry(registry, &address)
             .ok_or_else(|| async_graphql::Error::new(format!("unknown environment sensor `{id}`")))
@@ -179,7 +139,7 @@ impl EntitiesQuery {
         ctx: &async_graphql::Context<'_>,
         id: String,
     ) -> async_graphql::Result<EinkDisplayEntity> {
-        let registry = ctx.data::<DeviceRegistry>()?;
+        let registry = &ctx.data::<LiveConfig>()?.devices();
         let address = registry.address_or_self(&id).to_owned();
         EinkDisplayEntity::from_firmware(registry, &address)
             .or_else(|| EinkDisplayEntity::from_trmnl(registry, &address))
@@ -192,10 +152,157 @@ impl EntitiesQuery {
         ctx: &async_graphql::Context<'_>,
         id: String,
     ) -> async_graphql::Result<RobotVacuumEntity> {
-        let registry = ctx.data::<DeviceRegistry>()?;
+        let registry = &ctx.data::<LiveConfig>()?.devices();
         let address = registry.address_or_self(&id).to_owned();
         RobotVacuumEntity::from_roborock(registry, &address)
             .or_else(|| RobotVacuumEntity::from_valetudo(registry, &address))
             .ok_or_else(|| async_graphql::Error::new(format!("unknown robot vacuum `{id}`")))
     }
+
+    #[graphql(guard = ScopeGuard(required::GRAPHQL_SMART_SWITCH_READ))]
+    async fn smart_switch(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+        id: String,
+    ) -> async_graphql::Result<SmartSwitchEntity> {
+        let registry = &ctx.data::<LiveConfig>()?.devices();
+        let address = registry.address_or_self(&id).to_owned();
+        SmartSwitchEntity::from_registry(registry, &address)
+            .ok_or_else(|| async_graphql::Error::new(format!("unknown smart switch `{id}`")))
+    }
+
+    #[graphql(guard = ScopeGuard(required::GRAPHQL_PLANT_READ))]
+    async fn plant(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+        id: String,
+    ) -> async_graphql::Result<PlantEntity> {
+        let registry = &ctx.data::<LiveConfig>()?.devices();
+        let address = registry.address_or_self(&id).to_owned();
+        PlantEntity::from_registry(registry, &address)
+            .ok_or_else(|| async_graphql::Error::new(format!("unknown plant `{id}`")))
+    }
+
+    #[graphql(guard = ScopeGuard(required::GRAPHQL_SWITCH_READ))]
+    async fn control_switch(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+        id: String,
+    ) -> async_graphql::Result<ControlSwitchEntity> {
+        let registry = &ctx.data::<LiveConfig>()?.devices();
+        let address = registry.address_or_self(&id).to_owned();
+        ControlSwitchEntity::from_registry(registry, &address)
+            .ok_or_else(|| async_graphql::Error::new(format!("unknown control switch `{id}`")))
+    }
+}
+
+/// Every configured entity the caller holds the read scope for.
+fn visible_entities(registry: &DeviceRegistry, auth: &AuthContext) -> Vec<Entity> {
+    let mut out = Vec::new();
+
+    if auth.has(&required::GRAPHQL_LIGHT_READ) {
+        out.extend(
+            registry
+                .lights()
+                .filter_map(|(address, _)| LightEntity::from_registry(registry, address))
+                .map(Entity::Light),
+        );
+    }
+
+    if auth.has(&required::GRAPHQL_DOOR_READ) {
+        out.extend(
+            registry
+                .doors()
+                .filter_map(|(address, _)| DoorEntity::from_registry(registry, address))
+                .map(Entity::Door),
+        );
+    }
+
+    if auth.has(&required::GRAPHQL_PRESENCE_READ) {
+        out.extend(
+            registry
+                .presence_devices()
+                .filter_map(|(address, _)| PresenceEntity::from_registry(registry, address))
+                .map(Entity::Presence),
+        );
+    }
+
+    if auth.has(&required::GRAPHQL_ENVIRONMENT_READ) {
+        out.extend(
+            registry
+                .environment_devices()
+                .filter_map(|(address, _)| EnvironmentEntity::from_registry(registry, address))
+                .map(Entity::Environment),
+        );
+    }
+
+    if auth.has(&required::GRAPHQL_EPD_READ) {
+        out.extend(
+            registry
+                .eink_displays()
+                .keys()
+                .filter_map(|address| EinkDisplayEntity::from_firmware(registry, address))
+                .map(Entity::EinkDisplay),
+        );
+        out.extend(
+            registry
+                .trmnl_devices()
+                .keys()
+                .filter_map(|address| EinkDisplayEntity::from_trmnl(registry, address))
+                .map(Entity::EinkDisplay),
+        );
+    }
+
+    if auth.has(&required::GRAPHQL_ROBOT_VACUUM_READ) {
+        out.extend(
+            registry
+                .roborocks()
+                .filter_map(|(address, _)| RobotVacuumEntity::from_roborock(registry, address))
+                .map(Entity::RobotVacuum),
+        );
+        out.extend(
+            registry
+                .valetudos()
+                .filter_map(|(address, _)| RobotVacuumEntity::from_valetudo(registry, address))
+                .map(Entity::RobotVacuum),
+        );
+    }
+
+    if auth.has(&required::GRAPHQL_MEDIA_PLAYER_READ) {
+        out.extend(
+            registry
+                .media_players()
+                .filter_map(|(address, _)| MediaPlayerEntity::from_registry(registry, address))
+                .map(Entity::MediaPlayer),
+        );
+    }
+
+    if auth.has(&required::GRAPHQL_SMART_SWITCH_READ) {
+        out.extend(
+            registry
+                .smart_switches()
+                .filter_map(|(address, _)| SmartSwitchEntity::from_registry(registry, address))
+                .map(Entity::SmartSwitch),
+        );
+    }
+
+    if auth.has(&required::GRAPHQL_PLANT_READ) {
+        out.extend(
+            registry
+                .plants()
+                .filter_map(|(address, _)| PlantEntity::from_registry(registry, address))
+                .map(Entity::Plant),
+        );
+    }
+
+    if auth.has(&required::GRAPHQL_SWITCH_READ) {
+        out.extend(
+            registry
+                .control_switches()
+                .filter_map(|address| ControlSwitchEntity::from_registry(registry, address))
+                .map(Entity::ControlSwitch),
+        );
+    }
+
+    out
 }
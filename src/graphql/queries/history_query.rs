@@ -0,0 +1,48 @@
+use async_graphql::Object;
+use chrono::{DateTime, Utc};
+use sqlx::{Pool, Postgres};
+
+use crate::auth::context::AuthContext;
+use crate::history::{Aggregate, HistoryPoint, Series, Window, fetch};
+use crate::settings::LiveConfig;
+use crate::timedelta_format::parse_datetime_str_with_ms;
+
+#[derive(Default)]
+pub struct HistoryQuery;
+
+#[Object]
+impl HistoryQuery {
+    /// A sensor metric bucketed over time, oldest first. `entity` is a device
+    /// id or address and `metric` a reading it records, e.g. `temperature` or
+    /// `power`. `to` defaults to now and `bucket` (e.g. `15m`, `1h`) to the
+    /// narrowest width that keeps the series within 1000 points. Reading it
+    /// needs the read scope of the device's kind.
+    async fn history(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+        entity: String,
+        metric: String,
+        from: DateTime<Utc>,
+        to: Option<DateTime<Utc>>,
+        bucket: Option<String>,
+        #[graphql(default_with = "Aggregate::Avg")] aggregate: Aggregate,
+    ) -> async_graphql::Result<Vec<HistoryPoint>> {
+        let registry = &ctx.data::<LiveConfig>()?.devices();
+        let auth = ctx.data::<AuthContext>()?;
+        let db = ctx.data::<Pool<Postgres>>()?;
+
+        let series =
+            Series::resolve(registry, &entity, &metric).map_err(async_graphql::Error::new)?;
+        if !auth.has(&series.scope) {
+            return Err("insufficient scope".into());
+        }
+
+        let bucket = bucket
+            .map(|bucket| parse_datetime_str_with_ms(&bucket))
+            .transpose()?;
+        let window = Window::new(from, to.unwrap_or_else(Utc::now), bucket)
+            .map_err(async_graphql::Error::new)?;
+
+        Ok(fetch(db, &series, window, aggregate).await?)
+    }
+}
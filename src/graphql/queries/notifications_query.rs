@@ -0,0 +1,57 @@
+use async_graphql::Object;
+use sqlx::{Pool, Postgres};
+
+use crate::auth::scope::required;
+use crate::graphql::guard::ScopeGuard;
+use crate::graphql::objects::notification_object::{NotificationSuppression, SuppressionReason};
+
+#[derive(Default)]
+pub struct NotificationsQuery;
+
+#[Object]
+impl NotificationsQuery {
+    /// Notifications held back by a target's policy, newest first.
+    #[graphql(guard = ScopeGuard(required::GRAPHQL_PUSH_READ))]
+    async fn notification_suppressions(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+        target: Option<String>,
+        reason: Option<SuppressionReason>,
+        limit: Option<i64>,
+    ) -> async_graphql::Result<Vec<NotificationSuppression>> {
+        let db = ctx.data::<Pool<Postgres>>()?;
+        let limit = limit.unwrap_or(50).clamp(1, 500);
+
+        let rows = sqlx::query!(
+            "SELECT id, target, channel, source, event_id, message, reason, digest, \
+             suppressed_at, digested_at \
+             FROM notification_suppressions \
+             WHERE ($1::text IS NULL OR target = $1) AND ($2::text IS NULL OR reason = $2) \
+             ORDER BY suppressed_at DESC \
+             LIMIT $3",
+            target,
+            reason.map(|r| r.as_str()),
+            limit,
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|r| {
+                Some(NotificationSuppression {
+                    id: async_graphql::ID(r.id.to_string()),
+                    target: r.target,
+                    channel: r.channel,
+                    source: r.source,
+                    event_id: r.event_id,
+                    message: r.message,
+                    reason: SuppressionReason::parse(&r.reason)?,
+                    digest: r.digest,
+                    suppressed_at: r.suppressed_at,
+                    digested_at: r.digested_at,
+                })
+            })
+            .collect())
+    }
+}
@@ -1,12 +1,13 @@
 use async_graphql::Object;
 use sqlx::{Pool, Postgres};
+use uuid::Uuid;
 
 use crate::actors::workflows::manager::WorkflowManager;
 use crate::auth::scope::required;
 use crate::graphql::guard::ScopeGuard;
-use crate::graphql::objects::workflow_object::{WorkflowRun, WorkflowStatus};
+use crate::graphql::objects::workflow_object::{WorkflowRun, WorkflowStatus, WorkflowVariable};
 use crate::mode::Mode;
-use crate::settings::SettingsContainer;
+use crate::settings::LiveConfig;
 
 #[derive(Default)]
 pub struct WorkflowsQuery;
@@ -18,7 +19,7 @@ impl WorkflowsQuery {
         &self,
         ctx: &async_graphql::Context<'_>,
     ) -> async_graphql::Result<Vec<WorkflowStatus>> {
-        let settings = ctx.data::<SettingsContainer>()?;
+        let settings = &ctx.data::<LiveConfig>()?.settings();
         let manager = ctx.data::<WorkflowManager>()?;
 
         let mut statuses = Vec::with_capacity(settings.workflows.len());
@@ -58,24 +59,50 @@ impl WorkflowsQuery {
         Ok(manager.mode_active(Mode::Guest).await)
     }
 
+    /// Every declared workflow variable and its current value.
+    #[graphql(guard = ScopeGuard(required::GRAPHQL_VARIABLE_READ))]
+    async fn variables(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+    ) -> async_graphql::Result<Vec<WorkflowVariable>> {
+        let settings = &ctx.data::<LiveConfig>()?.settings();
+        let manager = ctx.data::<WorkflowManager>()?;
+
+        let mut variables = Vec::with_capacity(settings.variables.len());
+        for (name, variable) in &settings.variables {
+            variables.push(WorkflowVariable {
+                name: name.clone(),
+                kind: variable.into(),
+                value: manager.variable(name, variable).await?,
+            });
+        }
+        variables.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(variables)
+    }
+
+    /// Recent runs, newest first. `eventId` narrows to one run, e.g. the
+    /// `runId` of an `executeWorkflow` call, which has no entry until it ends.
     #[graphql(guard = ScopeGuard(required::GRAPHQL_WORKFLOW_READ))]
     async fn workflow_runs(
         &self,
         ctx: &async_graphql::Context<'_>,
         slug: Option<String>,
         limit: Option<i64>,
+        event_id: Option<Uuid>,
     ) -> async_graphql::Result<Vec<WorkflowRun>> {
         let db = ctx.data::<Pool<Postgres>>()?;
         let limit = limit.unwrap_or(50).clamp(1, 500);
 
         let rows = sqlx::query!(
-            "SELECT id, slug, name, event_id, outcome, dry_run, duration_ms, error, started_at \
+            "SELECT id, slug, name, event_id, outcome, dry_run, duration_ms, error, started_at, trace \
              FROM workflow_runs \
              WHERE ($1::text IS NULL OR slug = $1) \
+             AND ($3::uuid IS NULL OR event_id = $3) \
              ORDER BY started_at DESC \
              LIMIT $2",
             slug,
             limit,
+            event_id,
         )
         .fetch_all(db)
         .await?;
@@ -92,6 +119,7 @@ impl WorkflowsQuery {
                 duration_ms: r.duration_ms,
                 error: r.error,
                 started_at: r.started_at,
+                trace: r.trace.and_then(|trace| serde_json::from_value(trace).ok()),
             })
             .collect())
     }
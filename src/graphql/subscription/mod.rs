@@ -4,8 +4,8 @@ use tokio::sync::broadcast;
 
 use crate::auth::AuthContext;
 use crate::auth::scope::{Action, Domain, Resource, Scope};
-use crate::device_registry::DeviceRegistry;
 use crate::event_bus::{EventBus, EventBusMessage, EventFilter};
+use crate::settings::LiveConfig;
 
 pub mod types;
 
@@ -32,17 +32,17 @@ impl SubscriptionRoot {
         }
 
         let rx = ctx.data::<EventBus>()?.subscribe();
-        // Cheap Arc clone; moved into the stream so updates can resolve device
-        // addresses to the same slug/name the `entities` query exposes.
-        let registry = ctx.data::<DeviceRegistry>()?.clone();
-        Ok(event_stream(rx, filter, registry))
+        // Moved into the stream so updates can resolve device addresses to the
+        // same slug/name the `entities` query exposes, as of the latest reload.
+        let config = ctx.data::<LiveConfig>()?.clone();
+        Ok(event_stream(rx, filter, config))
     }
 }
 
 fn event_stream(
     rx: broadcast::Receiver<EventBusMessage>,
     filter: EventFilter,
-    registry: DeviceRegistry,
+    config: LiveConfig,
 ) -> impl Stream<Item = EventUpdate> {
     futures::stream::unfold(rx, |mut rx| async move {
         loop {
@@ -55,7 +55,7 @@ fn event_stream(
     })
     .filter_map(move |msg| {
         let keep = filter.matches(&msg);
-        let registry = registry.clone();
+        let registry = config.devices();
         async move { keep.then(|| EventUpdate::from_message(msg, &registry)) }
     })
 }
@@ -1,4 +1,5 @@
 use async_graphql::{ComplexObject, ID, SimpleObject, Union};
+use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
 use crate::device_registry::DeviceRegistry;
@@ -123,6 +124,45 @@ pub struct SolarUpdate {
     pub current_wh: f64,
 }
 
+#[derive(SimpleObject)]
+pub struct VariableUpdate {
+    pub event_id: Uuid,
+    pub name: String,
+    pub value: String,
+    pub previous: Option<String>,
+}
+
+#[derive(SimpleObject)]
+pub struct RobotVacuumUpdate {
+    pub event_id: Uuid,
+    /// Config slug, matching the `id` from the `entities` query.
+    pub id: ID,
+    pub name: String,
+    pub state: String,
+    pub previous: Option<String>,
+}
+
+#[derive(SimpleObject)]
+pub struct DeviceAvailabilityUpdate {
+    pub event_id: Uuid,
+    /// Config slug, matching the `id` from the `entities` query.
+    pub id: ID,
+    /// Raw device address the event was emitted for.
+    pub device: String,
+    pub available: bool,
+    pub last_seen: DateTime<Utc>,
+}
+
+#[derive(SimpleObject)]
+pub struct NotificationActionUpdate {
+    pub event_id: Uuid,
+    /// The event the notification was sent for.
+    pub origin_event_id: Uuid,
+    pub notification_id: Uuid,
+    pub source: String,
+    pub action: String,
+}
+
 #[derive(SimpleObject)]
 #[graphql(complex)]
 pub struct DeviceBatteryUpdate {
@@ -221,6 +261,10 @@ pub enum EventUpdate {
     Jellyfin(JellyfinUpdate),
     MediaPlayer(MediaPlayerUpdate),
     Solar(SolarUpdate),
+    Variable(VariableUpdate),
+    RobotVacuum(RobotVacuumUpdate),
+    DeviceAvailability(DeviceAvailabilityUpdate),
+    NotificationAction(NotificationActionUpdate),
 }
 
 impl EventUpdate {
@@ -393,6 +437,56 @@ impl EventUpdate {
                 event_id,
                 current_wh,
             }),
+            EventBusMessage::Variable {
+                event_id,
+                name,
+                value,
+                previous,
+            } => EventUpdate::Variable(VariableUpdate {
+                event_id,
+                name,
+                value,
+                previous,
+            }),
+            EventBusMessage::RobotVacuum {
+                event_id,
+                device_id,
+                name,
+                state,
+                previous,
+            } => EventUpdate::RobotVacuum(RobotVacuumUpdate {
+                event_id,
+                id: ID(device_id),
+                name,
+                state,
+                previous,
+            }),
+            EventBusMessage::DeviceAvailability {
+                event_id,
+                device_id,
+                address,
+                available,
+                last_seen,
+            } => EventUpdate::DeviceAvailability(DeviceAvailabilityUpdate {
+                event_id,
+                id: ID(device_id),
+                device: address,
+                available,
+                last_seen,
+            }),
+            EventBusMessage::NotificationAction {
+                event_id,
+                origin_event_id,
+                notification_id,
+                source,
+                action,
+            } => EventUpdate::NotificationAction(NotificationActionUpdate {
+                event_id,
+                origin_event_id,
+                notification_id,
+                source,
+                action,
+            }),
             EventBusMessage::DeviceBattery {
                 event_id,
                 device_id,
@@ -0,0 +1,348 @@
+//! Bucketed time series for any recorded sensor metric, read from whichever
+//! table the metric's device writes to. Buckets are computed in the database
+//! with `date_bin`, so a chart never pulls raw rows.
+
+use async_graphql::{Enum, SimpleObject};
+use chrono::{DateTime, TimeDelta, Utc};
+use sqlx::PgPool;
+
+use crate::{
+    auth::scope::{Scope, required},
+    device_registry::DeviceRegistry,
+};
+
+/// The most buckets one series returns.
+pub const MAX_POINTS: i64 = 1000;
+
+/// The widest window a series can cover.
+pub const MAX_WINDOW: TimeDelta = TimeDelta::days(366);
+
+/// Buckets tried, narrowest first, when the caller doesn't pick one.
+const BUCKET_LADDER: [TimeDelta; 9] = [
+    TimeDelta::minutes(1),
+    TimeDelta::minutes(5),
+    TimeDelta::minutes(15),
+    TimeDelta::minutes(30),
+    TimeDelta::hours(1),
+    TimeDelta::hours(3),
+    TimeDelta::hours(6),
+    TimeDelta::hours(12),
+    TimeDelta::hours(24),
+];
+
+const ENVIRONMENT_METRICS: [&str; 7] = [
+    "temperature",
+    "humidity",
+    "pressure",
+    "lux",
+    "uv_index",
+    "pm25",
+    "voc_index",
+];
+
+const SMART_SWITCH_METRICS: [&str; 4] = ["power", "energy", "voltage", "current"];
+
+/// The metric presence transitions are recorded under, `1` present and `0` clear.
+pub const PRESENCE_METRIC: &str = "presence";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum)]
+pub enum Aggregate {
+    Min,
+    Max,
+    Avg,
+    /// The final reading in each bucket.
+    Last,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Source {
+    /// A column of `temperature_sensor`.
+    Environment,
+    /// A column of `smart_switch`.
+    SmartSwitch,
+    /// Rows of `device_metric` named by the metric.
+    DeviceMetric,
+}
+
+/// Where a metric is stored and the scope needed to read it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Series {
+    source: Source,
+    address: String,
+    metric: String,
+    pub scope: Scope,
+}
+
+impl Series {
+    /// Environment readings and smart switch power are read from their own
+    /// tables, presence and plant readings from the metric sink under their
+    /// kind's scope, and any other device's metrics under the entity scope.
+    pub fn resolve(registry: &DeviceRegistry, entity: &str, metric: &str) -> Result<Self, String> {
+        let address = registry.address_or_self(entity);
+
+        let (source, scope) = if registry.environment(address).is_some()
+            && ENVIRONMENT_METRICS.contains(&metric)
+        {
+            (Source::Environment, required::GRAPHQL_ENVIRONMENT_READ)
+        } else if registry.smart_switch(address).is_some() && SMART_SWITCH_METRICS.contains(&metric)
+        {
+            (Source::SmartSwitch, required::GRAPHQL_SMART_SWITCH_READ)
+        } else if registry.presence(address).is_some() && metric == PRESENCE_METRIC {
+            (Source::DeviceMetric, required::GRAPHQL_PRESENCE_READ)
+        } else if registry
+            .plant(address)
+            .is_some_and(|plant| plant.entities.iter().any(|e| e == metric))
+        {
+            (Source::DeviceMetric, required::GRAPHQL_PLANT_READ)
+        } else if registry.id_for_address(address).is_some() {
+            (Source::DeviceMetric, required::GRAPHQL_ENTITY_READ)
+        } else {
+            return Err(format!("unknown entity `{entity}`"));
+        };
+
+        Ok(Self {
+            source,
+            address: address.to_owned(),
+            metric: metric.to_owned(),
+            scope,
+        })
+    }
+}
+
+/// The time range of a series and the width of its buckets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Window {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub bucket: TimeDelta,
+}
+
+impl Window {
+    /// Without a `bucket`, the narrowest one that keeps the window within
+    /// [`MAX_POINTS`] is picked; an explicit one that would exceed it is an error.
+    pub fn new(
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        bucket: Option<TimeDelta>,
+    ) -> Result<Self, String> {
+        if to <= from {
+            return Err("`to` must be after `from`".to_owned());
+        }
+
+        let span = to - from;
+        if span > MAX_WINDOW {
+            return Err(format!(
+                "a series covers at most {} days",
+                MAX_WINDOW.num_days()
+            ));
+        }
+
+        let points = |bucket: TimeDelta| {
+            (span.num_seconds() + bucket.num_seconds() - 1) / bucket.num_seconds()
+        };
+
+        let bucket = match bucket {
+            Some(bucket) if bucket < TimeDelta::seconds(1) => {
+                return Err("`bucket` must be at least a second".to_owned());
+            }
+            Some(bucket) if points(bucket) > MAX_POINTS => {
+                return Err(format!(
+                    "a {}s bucket splits the window into more than {MAX_POINTS} points",
+                    bucket.num_seconds()
+                ));
+            }
+            Some(bucket) => bucket,
+            None => BUCKET_LADDER
+                .into_iter()
+                .find(|bucket| points(*bucket) <= MAX_POINTS)
+                .unwrap_or(TimeDelta::hours(24)),
+        };
+
+        Ok(Self { from, to, bucket })
+    }
+}
+
+#[derive(SimpleObject, Debug, Clone, PartialEq)]
+pub struct HistoryPoint {
+    /// The start of the bucket.
+    pub time: DateTime<Utc>,
+    pub value: f64,
+}
+
+struct Bucket {
+    bucket: DateTime<Utc>,
+    min: Option<f64>,
+    max: Option<f64>,
+    avg: Option<f64>,
+    last: Option<f64>,
+}
+
+impl Bucket {
+    fn point(self, aggregate: Aggregate) -> Option<HistoryPoint> {
+        let value = match aggregate {
+            Aggregate::Min => self.min,
+            Aggregate::Max => self.max,
+            Aggregate::Avg => self.avg,
+            Aggregate::Last => self.last,
+        }?;
+
+        Some(HistoryPoint {
+            time: self.bucket,
+            value,
+        })
+    }
+}
+
+/// The series bucketed over `window`, oldest first. Buckets without a reading
+/// are omitted, so presence, which is only recorded when it changes, has a
+/// point only where it flipped.
+pub async fn fetch(
+    db: &PgPool,
+    series: &Series,
+    window: Window,
+    aggregate: Aggregate,
+) -> Result<Vec<HistoryPoint>, sqlx::Error> {
+    let bucket_secs = window.bucket.num_seconds() as f64;
+
+    let buckets = match series.source {
+        Source::Environment => {
+            sqlx::query_as!(
+                Bucket,
+                r#"SELECT date_bin(make_interval(secs => $1), "time", $2) AS "bucket!",
+                          min(value) AS min, max(value) AS max, avg(value) AS avg,
+                          (array_agg(value ORDER BY "time" DESC))[1] AS last
+                   FROM (
+                       SELECT "time", CASE $5
+                           WHEN 'temperature' THEN temperature
+                           WHEN 'humidity' THEN humidity
+                           WHEN 'pressure' THEN pressure
+                           WHEN 'lux' THEN lux
+                           WHEN 'uv_index' THEN uv_index
+                           WHEN 'pm25' THEN pm25::float8
+                           WHEN 'voc_index' THEN voc_index::float8
+                       END AS value
+                       FROM temperature_sensor
+                       WHERE ieee_addr = $4 AND "time" >= $2 AND "time" < $3
+                   ) readings
+                   WHERE value IS NOT NULL
+                   GROUP BY 1
+                   ORDER BY 1"#,
+                bucket_secs,
+                window.from,
+                window.to,
+                series.address,
+                series.metric
+            )
+            .fetch_all(db)
+            .await?
+        }
+        Source::SmartSwitch => {
+            sqlx::query_as!(
+                Bucket,
+                r#"SELECT date_bin(make_interval(secs => $1), "time", $2) AS "bucket!",
+                          min(value) AS min, max(value) AS max, avg(value) AS avg,
+                          (array_agg(value ORDER BY "time" DESC))[1] AS last
+                   FROM (
+                       SELECT "time", CASE $5
+                           WHEN 'power' THEN power::float8
+                           WHEN 'energy' THEN energy
+                           WHEN 'voltage' THEN voltage::float8
+                           WHEN 'current' THEN current_f64
+                       END AS value
+                       FROM smart_switch
+                       WHERE ieee_addr = $4 AND "time" >= $2 AND "time" < $3
+                   ) readings
+                   WHERE value IS NOT NULL
+                   GROUP BY 1
+                   ORDER BY 1"#,
+                bucket_secs,
+                window.from,
+                window.to,
+                series.address,
+                series.metric
+            )
+            .fetch_all(db)
+            .await?
+        }
+        Source::DeviceMetric => {
+            sqlx::query_as!(
+                Bucket,
+                r#"SELECT date_bin(make_interval(secs => $1), "time", $2) AS "bucket!",
+                          min(value) AS min, max(value) AS max, avg(value) AS avg,
+                          (array_agg(value ORDER BY "time" DESC))[1] AS last
+                   FROM device_metric
+                   WHERE address = $4 AND metric = $5 AND "time" >= $2 AND "time" < $3
+                     AND value IS NOT NULL
+                   GROUP BY 1
+                   ORDER BY 1"#,
+                bucket_secs,
+                window.from,
+                window.to,
+                series.address,
+                series.metric
+            )
+            .fetch_all(db)
+            .await?
+        }
+    };
+
+    Ok(buckets
+        .into_iter()
+        .filter_map(|bucket| bucket.point(aggregate))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn at(hour: i64) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2026-07-28T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+            + TimeDelta::hours(hour)
+    }
+
+    #[test]
+    fn the_narrowest_bucket_within_the_point_limit_is_picked() {
+        let day = Window::new(at(0), at(24), None).unwrap();
+        assert_eq!(day.bucket, TimeDelta::minutes(5));
+
+        let month = Window::new(at(0), at(24 * 30), None).unwrap();
+        assert_eq!(month.bucket, TimeDelta::hours(1));
+    }
+
+    #[test]
+    fn an_explicit_bucket_over_the_point_limit_is_rejected() {
+        assert!(Window::new(at(0), at(24), Some(TimeDelta::seconds(30))).is_err());
+        assert_eq!(
+            Window::new(at(0), at(24), Some(TimeDelta::hours(1)))
+                .unwrap()
+                .bucket,
+            TimeDelta::hours(1)
+        );
+    }
+
+    #[test]
+    fn windows_must_run_forwards_and_stay_within_the_limit() {
+        assert!(Window::new(at(5), at(5), None).is_err());
+        assert!(Window::new(at(5), at(1), None).is_err());
+        assert!(Window::new(at(0), at(24 * 400), None).is_err());
+    }
+
+    #[test]
+    fn a_bucket_yields_the_requested_aggregate() {
+        let bucket = || Bucket {
+            bucket: at(0),
+            min: Some(1.0),
+            max: Some(3.0),
+            avg: Some(2.0),
+            last: None,
+        };
+
+        assert_eq!(bucket().point(Aggregate::Max).unwrap().value, 3.0);
+        assert_eq!(bucket().point(Aggregate::Avg).unwrap().value, 2.0);
+        assert_eq!(bucket().point(Aggregate::Last), None);
+    }
+}
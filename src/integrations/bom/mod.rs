@@ -0,0 +1,47 @@
+use async_graphql::SimpleObject;
+use reqwest_middleware::ClientWithMiddleware;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+#[derive(thiserror::Error, Debug)]
+pub enum BomError {
+    #[error("a http error occurred: {0}")]
+    HttpMiddleware(#[from] reqwest_middleware::Error),
+    #[error("a http error occurred: {0}")]
+    Request(#[from] reqwest::Error),
+}
+
+#[derive(Serialize, Deserialize, SimpleObject, Debug, Clone)]
+pub struct ForecastDetails {
+    pub date_time: String,
+    pub code: String,
+    pub description: String,
+    pub emoji: String,
+    pub min: i64,
+    pub max: i64,
+    pub uv: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, SimpleObject, Debug, Clone)]
+pub struct Forecast {
+    pub days: Vec<ForecastDetails>,
+}
+
+/// The daily forecast for `location` from the BOM service at `url`.
+#[instrument(skip(client))]
+pub async fn forecast(
+    client: &ClientWithMiddleware,
+    url: &str,
+    location: &str,
+) -> Result<Forecast, BomError> {
+    let forecast = client
+        .get(format!("{url}/forecast"))
+        .query(&[("location", location)])
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Forecast>()
+        .await?;
+
+    Ok(forecast)
+}
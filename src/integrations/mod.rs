@@ -1,3 +1,4 @@
+pub mod bom;
 pub mod esphome;
 pub mod feature_flag;
 pub mod home_assistant;
@@ -1,5 +1,5 @@
 use crate::actors::system::mqtt_ingest;
-use crate::device_registry::DeviceRegistry;
+use crate::settings::LiveConfig;
 use ractor::{
     ActorRef,
     factory::{FactoryMessage, Job, JobOptions},
@@ -65,6 +65,13 @@ impl MqttClient {
             .map_err(MqttError::from)
     }
 
+    pub async fn unsubscribe(&self, topic: String) -> Result<(), MqttError> {
+        self.client
+            .unsubscribe(topic)
+            .await
+            .map_err(MqttError::from)
+    }
+
     pub async fn send_event_raw(&self, topic: String, payload: &str) -> Result<(), MqttError> {
         self.client
             .publish(topic, rumqttc::QoS::ExactlyOnce, false, payload)
@@ -120,7 +127,7 @@ impl Mqtt {
     pub async fn process_events(
         &mut self,
         cancellation_token: CancellationToken,
-        devices: DeviceRegistry,
+        config: LiveConfig,
     ) -> Result<(), MqttError> {
         let mut backoff = RECONNECT_BACKOFF_MIN;
 
@@ -132,6 +139,7 @@ impl Mqtt {
                             rumqttc::Event::Incoming(rumqttc::Packet::ConnAck(_)) => {
                                 backoff = RECONNECT_BACKOFF_MIN;
 
+                                let devices = config.devices();
                                 let topics: Vec<(&'static str, String)> = STATIC_TOPICS
                                     .iter()
                                     .map(|topic| ("topic", (*topic).to_owned()))
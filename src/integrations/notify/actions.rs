@@ -0,0 +1,77 @@
+//! Reply buttons on notifications. Each actionable notification is recorded
+//! under a fresh id before it goes out; the app echoes that id back with the
+//! tapped action, which is accepted once and turned into a
+//! `notification_action` event of its own, carrying the originating
+//! `event_id` as `origin_event_id`.
+
+use sqlx::{Pool, Postgres};
+use uuid::Uuid;
+
+use super::Actionable;
+
+/// How a callback for a recorded notification was received.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Response {
+    Accepted {
+        /// The event the notification was sent for.
+        origin_event_id: Uuid,
+        source: String,
+    },
+    /// Another action (or a double tap) got there first.
+    AlreadyAnswered,
+    /// No such notification, or it never offered that action.
+    Unknown,
+}
+
+pub async fn record(
+    db: &Pool<Postgres>,
+    id: Uuid,
+    actionable: &Actionable,
+) -> Result<(), sqlx::Error> {
+    let ids: Vec<String> = actionable.actions.iter().map(|a| a.id.clone()).collect();
+
+    sqlx::query!(
+        "INSERT INTO notification_actions (id, event_id, source, actions) \
+         VALUES ($1, $2, $3, $4)",
+        id,
+        actionable.event_id,
+        actionable.source,
+        &ids
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn respond(db: &Pool<Postgres>, id: Uuid, action: &str) -> Result<Response, sqlx::Error> {
+    let accepted = sqlx::query!(
+        "UPDATE notification_actions SET action = $2, responded_at = now() \
+         WHERE id = $1 AND $2 = ANY(actions) AND responded_at IS NULL \
+         RETURNING event_id, source",
+        id,
+        action
+    )
+    .fetch_optional(db)
+    .await?;
+
+    if let Some(row) = accepted {
+        return Ok(Response::Accepted {
+            origin_event_id: row.event_id,
+            source: row.source,
+        });
+    }
+
+    let answered = sqlx::query_scalar!(
+        r#"SELECT responded_at IS NOT NULL AS "answered!" FROM notification_actions WHERE id = $1 AND $2 = ANY(actions)"#,
+        id,
+        action
+    )
+    .fetch_optional(db)
+    .await?;
+
+    Ok(match answered {
+        Some(true) => Response::AlreadyAnswered,
+        _ => Response::Unknown,
+    })
+}
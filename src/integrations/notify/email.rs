@@ -0,0 +1,72 @@
+use lettre::{
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+    message::header::{ContentType, Header, HeaderName, HeaderValue},
+    transport::smtp::authentication::Credentials,
+};
+
+use super::NotifyError;
+use crate::settings::notify::{NotifyOptions, NotifyPriority, SmtpSettings, SmtpTls};
+
+/// `X-Priority`, from `1` (highest) to `5` (lowest).
+#[derive(Clone)]
+struct XPriority(u8);
+
+impl XPriority {
+    fn from_priority(priority: NotifyPriority) -> Self {
+        Self(match priority {
+            NotifyPriority::Urgent => 1,
+            NotifyPriority::High => 2,
+            NotifyPriority::Default => 3,
+            NotifyPriority::Low => 4,
+            NotifyPriority::Min => 5,
+        })
+    }
+}
+
+impl Header for XPriority {
+    fn name() -> HeaderName {
+        HeaderName::new_from_ascii_str("X-Priority")
+    }
+
+    fn parse(s: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Self(s.trim().parse()?))
+    }
+
+    fn display(&self) -> HeaderValue {
+        HeaderValue::new(Self::name(), self.0.to_string())
+    }
+}
+
+pub async fn send(
+    smtp: &SmtpSettings,
+    from: &str,
+    to: &[String],
+    options: &NotifyOptions,
+    message: &str,
+) -> Result<(), NotifyError> {
+    let mut builder = Message::builder()
+        .from(from.parse()?)
+        .subject(options.title())
+        .header(ContentType::TEXT_PLAIN)
+        .header(XPriority::from_priority(options.priority));
+    for address in to {
+        builder = builder.to(address.parse()?);
+    }
+    let email = builder.body(message.to_owned())?;
+
+    let mut transport = match smtp.tls {
+        SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&smtp.host)?,
+        SmtpTls::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&smtp.host)?,
+        SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&smtp.host),
+    };
+    if let Some(port) = smtp.port {
+        transport = transport.port(port);
+    }
+    if let (Some(username), Some(password)) = (&smtp.username, &smtp.password) {
+        transport = transport.credentials(Credentials::new(username.clone(), password.clone()));
+    }
+
+    transport.build().send(email).await?;
+
+    Ok(())
+}
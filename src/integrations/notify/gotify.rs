@@ -0,0 +1,33 @@
+use reqwest_middleware::ClientWithMiddleware;
+use serde::Serialize;
+
+use super::{NotifyError, ensure_success};
+use crate::settings::notify::NotifyOptions;
+
+#[derive(Serialize)]
+struct GotifyMessage<'a> {
+    title: &'a str,
+    message: &'a str,
+    priority: u8,
+}
+
+pub async fn send(
+    client: &ClientWithMiddleware,
+    url: &str,
+    token: &str,
+    options: &NotifyOptions,
+    message: &str,
+) -> Result<(), NotifyError> {
+    let response = client
+        .post(format!("{}/message", url.trim_end_matches('/')))
+        .header("X-Gotify-Key", token)
+        .json(&GotifyMessage {
+            title: options.title(),
+            message,
+            priority: options.priority.gotify(),
+        })
+        .send()
+        .await?;
+
+    ensure_success(response).await
+}
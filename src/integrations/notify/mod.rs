@@ -1,35 +1,487 @@
+use http::StatusCode;
 use ractor::factory::{FactoryMessage, Job, JobOptions};
+use reqwest_middleware::ClientWithMiddleware;
+use serde::Serialize;
 use tracing::instrument;
+use uuid::Uuid;
 
 use crate::{
-    actors::system::push::{self, PushWorker},
-    settings::NotifySource,
+    actors::system::{
+        notify::{
+            NotifyMessage, NotifyWorker,
+            policy::{NotifyPolicyActor, NotifyPolicyMessage},
+        },
+        push::{self, PushWorker},
+    },
+    integrations::mqtt::{MqttClient, MqttError},
+    settings::{NotifySource, notify::NotifyAction},
 };
 
+pub mod actions;
+pub mod email;
+pub mod gotify;
+pub mod ntfy;
+pub mod policy;
+pub mod webhook;
+
+#[derive(thiserror::Error, Debug)]
+pub enum NotifyError {
+    #[error("a http error occurred: {0}")]
+    HttpMiddleware(#[from] reqwest_middleware::Error),
+    #[error("a http error occurred: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("target responded with {0}: {1}")]
+    Status(StatusCode, String),
+    #[error("invalid email address: {0}")]
+    Address(#[from] lettre::address::AddressError),
+    #[error("could not build email: {0}")]
+    Email(#[from] lettre::error::Error),
+    #[error("smtp error: {0}")]
+    Smtp(#[from] lettre::transport::smtp::Error),
+    #[error(transparent)]
+    Mqtt(#[from] MqttError),
+    #[error("{0} is delivered by the push worker")]
+    Unsupported(&'static str),
+}
+
+/// A message with its target's presentation applied, as sent in the default
+/// webhook body and on MQTT.
+#[derive(Serialize, Debug)]
+pub struct Notification<'a> {
+    pub title: &'a str,
+    pub message: &'a str,
+    pub priority: &'static str,
+    pub tags: &'a [String],
+}
+
+impl<'a> Notification<'a> {
+    pub fn new(source: &'a NotifySource, message: &'a str) -> Self {
+        let options = source.options();
+        Self {
+            title: options.title(),
+            message,
+            priority: options.priority.as_str(),
+            tags: &options.tags,
+        }
+    }
+}
+
+/// Where a notification comes from: the event behind it, and a sender name
+/// such as `workflow:bins` or `door:front`. Rate limits count per sender, and
+/// a tapped action is published under the event's id.
+#[derive(Debug, Clone)]
+pub struct Origin {
+    pub event_id: Uuid,
+    pub source: String,
+}
+
+impl Origin {
+    pub fn new(event_id: Uuid, source: impl Into<String>) -> Self {
+        Self {
+            event_id,
+            source: source.into(),
+        }
+    }
+}
+
+/// Reply buttons for one notification, and what a tap answers to.
+#[derive(Debug, Clone)]
+pub struct Actionable {
+    /// The event that led to the notification. A tap is published as a
+    /// `notification_action` event under the same id.
+    pub event_id: Uuid,
+    /// Who sent it, e.g. `workflow:bins` or `door:front`, for triggers to
+    /// match on.
+    pub source: String,
+    pub actions: Vec<NotifyAction>,
+}
+
+impl Actionable {
+    /// `None` when there are no actions, so the notification stays plain.
+    pub fn new(origin: &Origin, actions: &[NotifyAction]) -> Option<Self> {
+        (!actions.is_empty()).then(|| Self {
+            event_id: origin.event_id,
+            source: origin.source.clone(),
+            actions: actions.to_vec(),
+        })
+    }
+}
+
+/// Send `message` to every source, subject to each target's policy. Only the
+/// Android app shows `actions`; other channels get the message alone.
 #[instrument]
-pub fn notify(notify_sources: &[NotifySource], message: String) {
-    for notify in notify_sources {
-        match notify {
-            NotifySource::AndroidApp => {
-                let Some(actor) = ractor::registry::where_is(PushWorker::NAME) else {
-                    tracing::warn!("push worker not found, skipping android app notification");
+pub fn notify(
+    notify_sources: &[NotifySource],
+    message: String,
+    origin: Origin,
+    actions: &[NotifyAction],
+) {
+    let Some(actor) = ractor::registry::where_is(NotifyPolicyActor::NAME) else {
+        tracing::warn!("notify policy not running, sending without it");
+        let actionable = Actionable::new(&origin, actions);
+        for source in notify_sources {
+            dispatch(source, message.clone(), actionable.clone());
+        }
+        return;
+    };
+
+    if let Err(e) = actor.send_message(NotifyPolicyMessage::Notify {
+        targets: notify_sources.to_vec(),
+        message,
+        origin,
+        actions: actions.to_vec(),
+    }) {
+        tracing::error!("error sending to notify policy: {e}");
+    }
+}
+
+/// Hand one message to the worker for its target: the Android app through the
+/// FCM push worker, everything else through the notify worker.
+pub(crate) fn dispatch(source: &NotifySource, message: String, actionable: Option<Actionable>) {
+    match source {
+        NotifySource::AndroidApp { options } => {
+            let Some(actor) = ractor::registry::where_is(PushWorker::NAME) else {
+                tracing::warn!("push worker not found, skipping android app notification");
+                crate::metrics::record_notification(source.channel(), "skipped");
+                return;
+            };
+
+            tracing::info!("notifying android app with \"{}\"", message);
+
+            if let Err(e) = actor.send_message(FactoryMessage::Dispatch(Job {
+                key: (),
+                msg: push::PushMessage::Send {
+                    title: options.title().to_owned(),
+                    body: message,
+                    actionable,
+                },
+                options: JobOptions::default(),
+                accepted: None,
+            })) {
+                tracing::error!("error sending to push worker: {e}");
+            };
+        }
+        source => {
+            let Some(actor) = ractor::registry::where_is(NotifyWorker::NAME) else {
+                tracing::warn!(
+                    "notify worker not found, skipping {} notification",
+                    source.channel()
+                );
+                crate::metrics::record_notification(source.channel(), "skipped");
+                return;
+            };
+
+            tracing::info!("notifying {} with \"{}\"", source.channel(), message);
+
+            if let Err(e) = actor.send_message(FactoryMessage::Dispatch(Job {
+                key: (),
+                msg: NotifyMessage::Send {
+                    source: source.clone(),
+                    message,
+                },
+                options: JobOptions::default(),
+                accepted: None,
+            })) {
+                tracing::error!("error sending to notify worker: {e}");
+            };
+        }
+    }
+}
+
+/// Deliver one message to one non-Android target.
+pub async fn deliver(
+    client: &ClientWithMiddleware,
+    mqtt: &MqttClient,
+    source: &NotifySource,
+    message: &str,
+) -> Result<(), NotifyError> {
+    match source {
+        NotifySource::AndroidApp { .. } => Err(NotifyError::Unsupported(source.channel())),
+        NotifySource::Ntfy {
+            url,
+            topic,
+            token,
+            options,
+        } => ntfy::send(client, url, topic, token.as_deref(), options, message).await,
+        NotifySource::Gotify {
+            url,
+            token,
+            options,
+        } => gotify::send(client, url, token, options, message).await,
+        NotifySource::Webhook {
+            url,
+            method,
+            headers,
+            body,
+            ..
+        } => {
+            let notification = Notification::new(source, message);
+            webhook::send(client, url, *method, headers, body.as_ref(), &notification).await
+        }
+        NotifySource::Email {
+            smtp,
+            from,
+            to,
+            options,
+        } => email::send(smtp, from, to, options, message).await,
+        NotifySource::Mqtt { topic, .. } => {
+            mqtt.send_event(topic.clone(), Notification::new(source, message))
+                .await?;
+            Ok(())
+        }
+    }
+}
+
+/// Treat anything but a 2xx as a failed delivery, keeping the body for the log.
+async fn ensure_success(response: reqwest::Response) -> Result<(), NotifyError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+
+    let text = response.text().await.unwrap_or_default();
+    Err(NotifyError::Status(status, text))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use axum::{Router, body::Bytes};
+    use http::{HeaderMap, Method, Uri};
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::TcpListener,
+        sync::{mpsc, oneshot},
+    };
+
+    use super::*;
+    use crate::settings::notify::{
+        NotifyOptions, NotifyPriority, SmtpSettings, SmtpTls, WebhookMethod,
+    };
+
+    struct Captured {
+        method: Method,
+        path: String,
+        headers: HeaderMap,
+        body: serde_json::Value,
+    }
+
+    /// An HTTP server that answers every request with `status` and hands it
+    /// back for inspection.
+    async fn stand_in(status: StatusCode) -> (String, mpsc::UnboundedReceiver<Captured>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let app = Router::new().fallback(
+            move |method: Method, uri: Uri, headers: HeaderMap, body: Bytes| async move {
+                let body = serde_json::from_slice(&body).unwrap_or_default();
+                let path = uri.path().to_owned();
+                tx.send(Captured {
+                    method,
+                    path,
+                    headers,
+                    body,
+                })
+                .unwrap();
+                status
+            },
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        (url, rx)
+    }
+
+    /// Just enough SMTP to accept one message, returning its DATA.
+    async fn smtp_stand_in() -> (u16, oneshot::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (tx, rx) = oneshot::channel();
+
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (read, mut write) = stream.into_split();
+            let mut lines = BufReader::new(read).lines();
+            write.write_all(b"220 stand-in ESMTP\r\n").await.unwrap();
+
+            let mut data = String::new();
+            let mut in_data = false;
+            while let Some(line) = lines.next_line().await.unwrap() {
+                if in_data {
+                    if line == "." {
+                        in_data = false;
+                        write.write_all(b"250 queued\r\n").await.unwrap();
+                    } else {
+                        data.push_str(&line);
+                        data.push('\n');
+                    }
                     continue;
-                };
+                }
 
-                tracing::info!("notifying android app with \"{}\"", message);
-
-                if let Err(e) = actor.send_message(FactoryMessage::Dispatch(Job {
-                    key: (),
-                    msg: push::PushMessage::Send {
-                        title: "Home Gateway".to_string(),
-                        body: message.clone(),
-                    },
-                    options: JobOptions::default(),
-                    accepted: None,
-                })) {
-                    tracing::error!("error sending to push worker: {e}");
+                let verb = line.get(..4).unwrap_or_default().to_ascii_uppercase();
+                let reply: &[u8] = match verb.as_str() {
+                    "DATA" => {
+                        in_data = true;
+                        b"354 go ahead\r\n"
+                    }
+                    "QUIT" => {
+                        write.write_all(b"221 bye\r\n").await.unwrap();
+                        break;
+                    }
+                    _ => b"250 ok\r\n",
                 };
+                write.write_all(reply).await.unwrap();
             }
+
+            let _ = tx.send(data);
+        });
+
+        (port, rx)
+    }
+
+    fn options(title: &str, priority: NotifyPriority, tags: &[&str]) -> NotifyOptions {
+        NotifyOptions {
+            title: Some(title.to_owned()),
+            priority,
+            tags: tags.iter().map(|t| (*t).to_owned()).collect(),
         }
     }
+
+    fn client() -> ClientWithMiddleware {
+        crate::http::get_traced_http_client().unwrap()
+    }
+
+    #[tokio::test]
+    async fn ntfy_publishes_json_to_the_server_root() {
+        let (url, mut rx) = stand_in(StatusCode::OK).await;
+        let options = options("Doorbell", NotifyPriority::High, &["bell"]);
+
+        ntfy::send(
+            &client(),
+            &url,
+            "house",
+            Some("tk_1"),
+            &options,
+            "Someone's here",
+        )
+        .await
+        .unwrap();
+
+        let request = rx.recv().await.unwrap();
+        assert_eq!(request.path, "/");
+        assert_eq!(request.headers["authorization"], "Bearer tk_1");
+        assert_eq!(
+            request.body,
+            serde_json::json!({
+                "topic": "house",
+                "title": "Doorbell",
+                "message": "Someone's here",
+                "priority": 4,
+                "tags": ["bell"],
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn gotify_sends_its_own_priority_scale() {
+        let (url, mut rx) = stand_in(StatusCode::OK).await;
+        let options = options("Leak", NotifyPriority::Urgent, &[]);
+
+        gotify::send(
+            &client(),
+            &format!("{url}/"),
+            "app-token",
+            &options,
+            "Water!",
+        )
+        .await
+        .unwrap();
+
+        let request = rx.recv().await.unwrap();
+        assert_eq!(request.path, "/message");
+        assert_eq!(request.headers["x-gotify-key"], "app-token");
+        assert_eq!(request.body["priority"], 10);
+        assert_eq!(request.body["title"], "Leak");
+    }
+
+    #[tokio::test]
+    async fn webhook_renders_its_body_template() {
+        let (url, mut rx) = stand_in(StatusCode::NO_CONTENT).await;
+        let headers = HashMap::from([("x-api-key".to_owned(), "secret".to_owned())]);
+        let body = serde_json::json!({ "text": "${title}: ${message}", "labels": "${tags}" });
+        let tags = ["red".to_owned(), "yellow".to_owned()];
+        let notification = Notification {
+            title: "Bins",
+            message: "Put them out",
+            priority: "default",
+            tags: &tags,
+        };
+
+        webhook::send(
+            &client(),
+            &url,
+            WebhookMethod::Put,
+            &headers,
+            Some(&body),
+            &notification,
+        )
+        .await
+        .unwrap();
+
+        let request = rx.recv().await.unwrap();
+        assert_eq!(request.method, Method::PUT);
+        assert_eq!(request.headers["x-api-key"], "secret");
+        assert_eq!(
+            request.body,
+            serde_json::json!({ "text": "Bins: Put them out", "labels": "red,yellow" })
+        );
+    }
+
+    #[tokio::test]
+    async fn a_rejected_request_is_a_failed_delivery() {
+        let (url, _rx) = stand_in(StatusCode::INTERNAL_SERVER_ERROR).await;
+        let options = NotifyOptions::default();
+
+        let error = ntfy::send(&client(), &url, "house", None, &options, "hi")
+            .await
+            .unwrap_err();
+
+        assert!(
+            matches!(
+                error,
+                NotifyError::Status(StatusCode::INTERNAL_SERVER_ERROR, _)
+            ),
+            "{error}"
+        );
+    }
+
+    #[tokio::test]
+    async fn email_goes_out_over_smtp() {
+        let (port, rx) = smtp_stand_in().await;
+        let smtp = SmtpSettings {
+            host: "127.0.0.1".to_owned(),
+            port: Some(port),
+            username: None,
+            password: None,
+            tls: SmtpTls::None,
+        };
+        let options = options("Back door", NotifyPriority::Urgent, &[]);
+
+        email::send(
+            &smtp,
+            "gateway@home.test",
+            &["me@home.test".to_owned()],
+            &options,
+            "The back door is open",
+        )
+        .await
+        .unwrap();
+
+        let data = rx.await.unwrap();
+        assert!(data.contains("Subject: Back door"), "{data}");
+        assert!(data.contains("X-Priority: 1"), "{data}");
+        assert!(data.contains("To: me@home.test"), "{data}");
+        assert!(data.contains("The back door is open"), "{data}");
+    }
 }
@@ -0,0 +1,38 @@
+use reqwest_middleware::ClientWithMiddleware;
+use serde::Serialize;
+
+use super::{NotifyError, ensure_success};
+use crate::settings::notify::NotifyOptions;
+
+/// ntfy's JSON publish body, posted to the server root so the title and tags
+/// don't have to survive being sent as headers.
+#[derive(Serialize)]
+struct NtfyPublish<'a> {
+    topic: &'a str,
+    title: &'a str,
+    message: &'a str,
+    priority: u8,
+    tags: &'a [String],
+}
+
+pub async fn send(
+    client: &ClientWithMiddleware,
+    url: &str,
+    topic: &str,
+    token: Option<&str>,
+    options: &NotifyOptions,
+    message: &str,
+) -> Result<(), NotifyError> {
+    let mut request = client.post(url).json(&NtfyPublish {
+        topic,
+        title: options.title(),
+        message,
+        priority: options.priority.ntfy(),
+        tags: &options.tags,
+    });
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+
+    ensure_success(request.send().await?).await
+}
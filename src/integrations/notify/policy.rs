@@ -0,0 +1,361 @@
+//! The notification policy: per-target quiet hours, duplicate collapsing and
+//! per-source rate limits, applied by the
+//! [`NotifyPolicyActor`](crate::actors::system::notify::policy::NotifyPolicyActor)
+//! before anything reaches a delivery worker. Suppressions are recorded here,
+//! and those held for a digest are read back and summarised once the target
+//! can be reached again.
+
+use std::collections::{HashMap, VecDeque};
+
+use chrono::{DateTime, NaiveTime, TimeDelta, Utc};
+use sqlx::{Pool, Postgres};
+use uuid::Uuid;
+
+use super::Origin;
+use crate::settings::notify::NotifyPolicy;
+
+/// Most held messages listed in one digest; the rest are counted.
+const DIGEST_LINES: usize = 10;
+
+/// How far back held messages are read for a digest; anything older has
+/// missed its moment and is left out.
+const HELD_WITHIN: TimeDelta = TimeDelta::days(1);
+
+/// Most held messages read back per flush.
+const HELD_LIMIT: i64 = 500;
+
+/// Why a message was not sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Suppression {
+    QuietHours,
+    Duplicate,
+    RateLimited,
+}
+
+impl Suppression {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Suppression::QuietHours => "quiet_hours",
+            Suppression::Duplicate => "duplicate",
+            Suppression::RateLimited => "rate_limited",
+        }
+    }
+
+    /// A duplicate repeats something already sent, so it never goes in a
+    /// digest.
+    pub fn digestible(&self) -> bool {
+        !matches!(self, Suppression::Duplicate)
+    }
+}
+
+/// The moment a decision is made: the time, the local (Perth) time of day
+/// quiet hours are judged by, and whether night mode is on.
+#[derive(Debug, Clone, Copy)]
+pub struct Now {
+    pub at: DateTime<Utc>,
+    pub local: NaiveTime,
+    pub night: bool,
+}
+
+/// What has recently been sent, per target. Kept in memory, so a restart
+/// forgets it and at worst lets one extra message through.
+#[derive(Default)]
+pub struct PolicyState {
+    /// `(target, message)` to when it was last sent.
+    last_sent: HashMap<(String, String), DateTime<Utc>>,
+    /// `(target, source)` to its send times within the rate-limit window.
+    recent: HashMap<(String, String), VecDeque<DateTime<Utc>>>,
+}
+
+impl PolicyState {
+    /// Decide whether `message` from `source` may go to `target` now. A
+    /// message that may go is counted as sent.
+    pub fn check(
+        &mut self,
+        policy: &NotifyPolicy,
+        target: &str,
+        source: &str,
+        message: &str,
+        now: Now,
+    ) -> Option<Suppression> {
+        if policy
+            .quiet_hours
+            .as_ref()
+            .is_some_and(|quiet| quiet.contains(now.local, now.night))
+        {
+            return Some(Suppression::QuietHours);
+        }
+
+        if let Some(window) = policy.dedup {
+            self.last_sent
+                .retain(|(t, _), sent_at| t != target || now.at - *sent_at < window);
+            if self
+                .last_sent
+                .contains_key(&(target.to_owned(), message.to_owned()))
+            {
+                return Some(Suppression::Duplicate);
+            }
+        }
+
+        if let Some(limit) = &policy.rate_limit {
+            let sent = self
+                .recent
+                .entry((target.to_owned(), source.to_owned()))
+                .or_default();
+            while sent.front().is_some_and(|at| now.at - *at >= limit.per) {
+                sent.pop_front();
+            }
+            if sent.len() >= limit.count as usize {
+                return Some(Suppression::RateLimited);
+            }
+            sent.push_back(now.at);
+        }
+
+        if policy.dedup.is_some() {
+            self.last_sent
+                .insert((target.to_owned(), message.to_owned()), now.at);
+        }
+
+        None
+    }
+
+    /// Whether a digest can go to `target`: outside quiet hours, and, with a
+    /// rate limit, once a full period has passed since the oldest held message.
+    pub fn digest_due(policy: &NotifyPolicy, oldest: DateTime<Utc>, now: Now) -> bool {
+        let quiet = policy
+            .quiet_hours
+            .as_ref()
+            .is_some_and(|quiet| quiet.contains(now.local, now.night));
+        let waited = policy
+            .rate_limit
+            .as_ref()
+            .is_none_or(|limit| now.at - oldest >= limit.per);
+
+        !quiet && waited
+    }
+}
+
+/// One summary of everything held for a target.
+pub fn digest(messages: &[String]) -> String {
+    let mut summary = format!(
+        "{} notification{} held back:",
+        messages.len(),
+        if messages.len() == 1 { "" } else { "s" }
+    );
+    for message in messages.iter().take(DIGEST_LINES) {
+        summary.push_str("\n- ");
+        summary.push_str(message);
+    }
+    if messages.len() > DIGEST_LINES {
+        summary.push_str(&format!("\n…and {} more", messages.len() - DIGEST_LINES));
+    }
+
+    summary
+}
+
+pub struct Suppressed<'a> {
+    pub target: &'a str,
+    pub channel: &'static str,
+    pub origin: &'a Origin,
+    pub message: &'a str,
+    pub reason: Suppression,
+    pub digest: bool,
+}
+
+pub async fn record(db: &Pool<Postgres>, suppressed: Suppressed<'_>) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "INSERT INTO notification_suppressions \
+         (id, target, channel, source, event_id, message, reason, digest) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+        Uuid::new_v4(),
+        suppressed.target,
+        suppressed.channel,
+        suppressed.origin.source,
+        suppressed.origin.event_id,
+        suppressed.message,
+        suppressed.reason.as_str(),
+        suppressed.digest
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+pub struct Held {
+    pub id: Uuid,
+    pub target: String,
+    pub message: String,
+    pub suppressed_at: DateTime<Utc>,
+}
+
+/// Messages waiting for a digest, oldest first, from within [`HELD_WITHIN`]
+/// and at most [`HELD_LIMIT`] of them.
+pub async fn held(db: &Pool<Postgres>) -> Result<Vec<Held>, sqlx::Error> {
+    sqlx::query_as!(
+        Held,
+        "SELECT id, target, message, suppressed_at FROM notification_suppressions \
+         WHERE digest AND digested_at IS NULL AND suppressed_at > $1 \
+         ORDER BY suppressed_at LIMIT $2",
+        Utc::now() - HELD_WITHIN,
+        HELD_LIMIT
+    )
+    .fetch_all(db)
+    .await
+}
+
+pub async fn mark_digested(db: &Pool<Postgres>, ids: &[Uuid]) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE notification_suppressions SET digested_at = now() WHERE id = ANY($1)",
+        ids
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+    use crate::settings::notify::{QuietHours, RateLimit};
+
+    fn at(minutes: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_760_000_000, 0).unwrap() + TimeDelta::minutes(minutes)
+    }
+
+    fn noon() -> NaiveTime {
+        NaiveTime::from_hms_opt(12, 0, 0).unwrap()
+    }
+
+    fn now(minutes: i64, local: NaiveTime, night: bool) -> Now {
+        Now {
+            at: at(minutes),
+            local,
+            night,
+        }
+    }
+
+    #[test]
+    fn quiet_hours_hold_everything_and_night_mode_counts() {
+        let policy = NotifyPolicy {
+            quiet_hours: Some(QuietHours {
+                start: NaiveTime::from_hms_opt(22, 0, 0),
+                end: NaiveTime::from_hms_opt(7, 0, 0),
+                night_mode: true,
+            }),
+            ..Default::default()
+        };
+        let mut state = PolicyState::default();
+        let three_am = NaiveTime::from_hms_opt(3, 0, 0).unwrap();
+
+        let check = |state: &mut PolicyState, local, night| {
+            state.check(
+                &policy,
+                "android_app",
+                "door:front",
+                "open",
+                now(0, local, night),
+            )
+        };
+        assert_eq!(
+            check(&mut state, three_am, false),
+            Some(Suppression::QuietHours)
+        );
+        assert_eq!(
+            check(&mut state, noon(), true),
+            Some(Suppression::QuietHours)
+        );
+        assert_eq!(check(&mut state, noon(), false), None);
+    }
+
+    #[test]
+    fn identical_messages_collapse_within_the_window() {
+        let policy = NotifyPolicy {
+            dedup: Some(TimeDelta::minutes(15)),
+            ..Default::default()
+        };
+        let mut state = PolicyState::default();
+        let mut check = |message, minutes| {
+            state.check(
+                &policy,
+                "android_app",
+                "watchdog:a",
+                message,
+                now(minutes, noon(), false),
+            )
+        };
+
+        assert_eq!(check("offline", 0), None);
+        assert_eq!(check("offline", 10), Some(Suppression::Duplicate));
+        assert_eq!(check("online", 11), None);
+        assert_eq!(check("offline", 16), None);
+    }
+
+    #[test]
+    fn the_rate_limit_is_per_source() {
+        let policy = NotifyPolicy {
+            rate_limit: Some(RateLimit {
+                count: 2,
+                per: TimeDelta::hours(1),
+            }),
+            ..Default::default()
+        };
+        let mut state = PolicyState::default();
+        let mut check = |source, minutes| {
+            state.check(
+                &policy,
+                "android_app",
+                source,
+                "low battery",
+                now(minutes, noon(), false),
+            )
+        };
+
+        assert_eq!(check("workflow:battery", 0), None);
+        assert_eq!(check("workflow:battery", 1), None);
+        assert_eq!(check("workflow:battery", 2), Some(Suppression::RateLimited));
+        assert_eq!(check("door:front", 3), None);
+        assert_eq!(check("workflow:battery", 60), None);
+    }
+
+    #[test]
+    fn a_digest_waits_out_quiet_hours_and_the_rate_limit() {
+        let policy = NotifyPolicy {
+            quiet_hours: Some(QuietHours {
+                start: None,
+                end: None,
+                night_mode: true,
+            }),
+            rate_limit: Some(RateLimit {
+                count: 1,
+                per: TimeDelta::minutes(30),
+            }),
+            digest: true,
+            ..Default::default()
+        };
+
+        assert!(!PolicyState::digest_due(
+            &policy,
+            at(0),
+            now(60, noon(), true)
+        ));
+        assert!(!PolicyState::digest_due(
+            &policy,
+            at(0),
+            now(10, noon(), false)
+        ));
+        assert!(PolicyState::digest_due(
+            &policy,
+            at(0),
+            now(30, noon(), false)
+        ));
+
+        let messages: Vec<String> = (1..=12).map(|i| format!("message {i}")).collect();
+        let summary = digest(&messages);
+        assert!(summary.starts_with("12 notifications held back:\n- message 1\n"));
+        assert!(summary.ends_with("…and 2 more"));
+    }
+}
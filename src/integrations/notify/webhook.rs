@@ -0,0 +1,43 @@
+use std::collections::HashMap;
+
+use http::Method;
+use reqwest_middleware::ClientWithMiddleware;
+
+use super::{Notification, NotifyError, ensure_success};
+use crate::settings::{notify::WebhookMethod, template::render_json};
+
+pub async fn send(
+    client: &ClientWithMiddleware,
+    url: &str,
+    method: WebhookMethod,
+    headers: &HashMap<String, String>,
+    body: Option<&serde_json::Value>,
+    notification: &Notification<'_>,
+) -> Result<(), NotifyError> {
+    let body = match body {
+        Some(template) => render_json(template, &vars(notification)),
+        None => serde_json::to_value(notification).unwrap_or_default(),
+    };
+
+    let method = match method {
+        WebhookMethod::Post => Method::POST,
+        WebhookMethod::Put => Method::PUT,
+    };
+
+    let mut request = client.request(method, url).json(&body);
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+
+    ensure_success(request.send().await?).await
+}
+
+/// Template vars for a custom body. Tags are comma separated.
+fn vars(notification: &Notification<'_>) -> HashMap<String, String> {
+    HashMap::from([
+        ("title".to_owned(), notification.title.to_owned()),
+        ("message".to_owned(), notification.message.to_owned()),
+        ("priority".to_owned(), notification.priority.to_owned()),
+        ("tags".to_owned(), notification.tags.join(",")),
+    ])
+}
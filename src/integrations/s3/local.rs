@@ -0,0 +1,240 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+/// Sidecars live under this directory at the root, out of the way of every
+/// listing.
+const METADATA_DIR: &str = ".metadata";
+
+/// What S3 would keep alongside the object: its content type and user
+/// metadata.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Sidecar {
+    content_type: String,
+    #[serde(default)]
+    metadata: HashMap<String, String>,
+}
+
+/// Objects as plain files under `root`, a key's `/`s becoming directories.
+#[derive(Debug, Clone)]
+pub struct LocalStore {
+    root: PathBuf,
+}
+
+impl LocalStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn path(&self, key: &str) -> anyhow::Result<PathBuf> {
+        let relative = Path::new(key);
+        let plain = relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+
+        if key.is_empty() || !plain || key.starts_with(METADATA_DIR) {
+            anyhow::bail!("invalid object key `{key}`");
+        }
+
+        Ok(self.root.join(relative))
+    }
+
+    fn sidecar_path(&self, key: &str) -> anyhow::Result<PathBuf> {
+        self.path(key)?;
+        Ok(self.root.join(METADATA_DIR).join(format!("{key}.json")))
+    }
+
+    pub async fn get_object(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        match tokio::fs::read(self.path(key)?).await {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("reading object {key}")),
+        }
+    }
+
+    pub async fn list_objects(&self, prefix: &str) -> anyhow::Result<Vec<String>> {
+        // Only the directory the prefix names can hold a match, so the walk
+        // starts there rather than at the root.
+        let start = match prefix.rfind('/') {
+            Some(end) => self.root.join(&prefix[..end]),
+            None => self.root.clone(),
+        };
+
+        let mut keys = Vec::new();
+        let mut pending = vec![start];
+        while let Some(dir) = pending.pop() {
+            let mut entries = match tokio::fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e).with_context(|| format!("listing {}", dir.display())),
+            };
+
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                let Ok(relative) = path.strip_prefix(&self.root) else {
+                    continue;
+                };
+                let key = relative
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+
+                if key.starts_with(METADATA_DIR) || key.contains(".tmp-") {
+                    continue;
+                }
+
+                if entry.file_type().await?.is_dir() {
+                    pending.push(path);
+                } else if key.starts_with(prefix) {
+                    keys.push(key);
+                }
+            }
+        }
+
+        keys.sort();
+        Ok(keys)
+    }
+
+    pub async fn put_object(
+        &self,
+        key: &str,
+        payload: &[u8],
+        content_type: &str,
+        metadata: &HashMap<String, String>,
+    ) -> anyhow::Result<()> {
+        let sidecar = Sidecar {
+            content_type: content_type.to_owned(),
+            metadata: metadata.clone(),
+        };
+
+        write_atomically(&self.path(key)?, payload).await?;
+        write_atomically(&self.sidecar_path(key)?, &serde_json::to_vec(&sidecar)?).await
+    }
+
+    /// The object's user metadata, or `None` when it does not exist. An
+    /// object dropped into the directory by hand has none.
+    pub async fn get_object_metadata(
+        &self,
+        key: &str,
+    ) -> anyhow::Result<Option<HashMap<String, String>>> {
+        if !tokio::fs::try_exists(self.path(key)?).await? {
+            return Ok(None);
+        }
+
+        let sidecar = match tokio::fs::read(self.sidecar_path(key)?).await {
+            Ok(bytes) => serde_json::from_slice::<Sidecar>(&bytes)
+                .with_context(|| format!("reading metadata for {key}"))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Sidecar::default(),
+            Err(e) => return Err(e).with_context(|| format!("reading metadata for {key}")),
+        };
+
+        Ok(Some(sidecar.metadata))
+    }
+
+    pub async fn delete_object(&self, key: &str) -> anyhow::Result<()> {
+        for path in [self.path(key)?, self.sidecar_path(key)?] {
+            match tokio::fs::remove_file(&path).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e).with_context(|| format!("deleting {}", path.display())),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Write to a sibling and rename over, so a reader never sees half a file.
+async fn write_atomically(path: &Path, payload: &[u8]) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .with_context(|| format!("creating {}", parent.display()))?;
+    }
+
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(format!(".tmp-{}", uuid::Uuid::new_v4()));
+
+    tokio::fs::write(&temp, payload)
+        .await
+        .with_context(|| format!("writing {}", path.display()))?;
+    tokio::fs::rename(&temp, path)
+        .await
+        .with_context(|| format!("writing {}", path.display()))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn store() -> LocalStore {
+        LocalStore::new(std::env::temp_dir().join(format!("hg-store-{}", uuid::Uuid::new_v4())))
+    }
+
+    #[tokio::test]
+    async fn objects_round_trip_with_their_metadata() {
+        let store = store();
+        let metadata = HashMap::from([("hash".to_owned(), "abc".to_owned())]);
+
+        store
+            .put_object("album/cats/tom.png", b"tom", "image/png", &metadata)
+            .await
+            .unwrap();
+        store
+            .put_object("album/dogs/rex.png", b"rex", "image/png", &HashMap::new())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.get_object("album/cats/tom.png").await.unwrap(),
+            Some(b"tom".to_vec())
+        );
+        assert_eq!(
+            store
+                .get_object_metadata("album/cats/tom.png")
+                .await
+                .unwrap(),
+            Some(metadata)
+        );
+        assert_eq!(
+            store.list_objects("album/").await.unwrap(),
+            vec!["album/cats/tom.png", "album/dogs/rex.png"]
+        );
+        assert_eq!(
+            store.list_objects("album/cats/").await.unwrap(),
+            vec!["album/cats/tom.png"]
+        );
+
+        store.delete_object("album/cats/tom.png").await.unwrap();
+
+        assert_eq!(store.get_object("album/cats/tom.png").await.unwrap(), None);
+        assert_eq!(
+            store
+                .get_object_metadata("album/cats/tom.png")
+                .await
+                .unwrap(),
+            None
+        );
+        assert!(store.list_objects("album/cats/").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn keys_cannot_escape_the_root() {
+        let store = store();
+
+        for key in [
+            "../escape.png",
+            "/etc/passwd",
+            "a/../../b",
+            ".metadata/x",
+            "",
+        ] {
+            assert!(store.get_object(key).await.is_err(), "{key}");
+        }
+    }
+}
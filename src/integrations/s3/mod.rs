@@ -1,11 +1,23 @@
+mod local;
+
+use crate::settings::S3Settings;
 use anyhow::Context;
 use http::{HeaderMap, HeaderName, HeaderValue};
+use local::LocalStore;
 use s3::{Bucket, Region, creds::Credentials};
 use std::collections::HashMap;
 
+/// The gateway's object store: an S3 bucket, or a directory on local disk
+/// behind the same calls.
 #[derive(Clone)]
 pub struct S3 {
-    bucket: Box<Bucket>,
+    backend: Backend,
+}
+
+#[derive(Clone)]
+enum Backend {
+    Bucket(Box<Bucket>),
+    Local(LocalStore),
 }
 
 pub struct ObjectResponse {
@@ -21,6 +33,23 @@ pub enum OptionalObjectResponse {
 }
 
 impl S3 {
+    pub fn from_settings(settings: &S3Settings) -> anyhow::Result<Self> {
+        match settings {
+            S3Settings::Bucket {
+                bucket,
+                region,
+                endpoint,
+            } => Self::new(bucket, region, endpoint.clone()),
+            S3Settings::Local { path } => Ok(Self::local(path.clone())),
+        }
+    }
+
+    pub fn local(root: std::path::PathBuf) -> Self {
+        Self {
+            backend: Backend::Local(LocalStore::new(root)),
+        }
+    }
+
     /// Credentials are read from the standard AWS environment
     /// (`AWS_ACCESS_KEY_ID` / `AWS_SECRET_ACCESS_KEY` / `AWS_SESSION_TOKEN` / …).
     /// When `endpoint` is set, an S3-compatible store is assumed and path-style
@@ -45,11 +74,23 @@ impl S3 {
             bucket = bucket.with_path_style();
         }
 
-        Ok(Self { bucket })
+        Ok(Self {
+            backend: Backend::Bucket(bucket),
+        })
     }
 
     pub async fn get_object(&self, key: &str) -> anyhow::Result<Vec<u8>> {
-        let response = self.bucket.get_object(key).await?;
+        let bucket = match &self.backend {
+            Backend::Bucket(bucket) => bucket,
+            Backend::Local(store) => {
+                return store
+                    .get_object(key)
+                    .await?
+                    .with_context(|| format!("object {key} not found"));
+            }
+        };
+
+        let response = bucket.get_object(key).await?;
         let status = response.status_code();
         if status != 200 {
             anyhow::bail!("unexpected status {status} fetching object {key}");
@@ -59,19 +100,40 @@ impl S3 {
 
     /// Conditional GET: if the object's etag matches `etag`, the server returns
     /// 304 and we report [`OptionalObjectResponse::ExistingObjectIsValid`]
-    /// without re-downloading the body.
+    /// without re-downloading the body. On local disk the etag is the
+    /// content hash.
     pub async fn get_object_optional(
         &self,
         key: &str,
         etag: Option<&str>,
     ) -> anyhow::Result<OptionalObjectResponse> {
+        let bucket = match &self.backend {
+            Backend::Bucket(bucket) => bucket,
+            Backend::Local(store) => {
+                let payload = store
+                    .get_object(key)
+                    .await?
+                    .with_context(|| format!("object {key} not found"))?;
+                let current = format!("\"{}\"", crate::eink::image::content_hash(&payload));
+
+                return Ok(if etag == Some(current.as_str()) {
+                    OptionalObjectResponse::ExistingObjectIsValid
+                } else {
+                    OptionalObjectResponse::ObjectUpdated(ObjectResponse {
+                        payload,
+                        etag: current,
+                    })
+                });
+            }
+        };
+
         let bucket = match etag {
             Some(etag) => {
                 let mut headers = HeaderMap::new();
                 headers.insert(http::header::IF_NONE_MATCH, etag.parse()?);
-                self.bucket.with_extra_headers(headers)?
+                bucket.with_extra_headers(headers)?
             }
-            None => *self.bucket.clone(),
+            None => *bucket.clone(),
         };
 
         let response = bucket.get_object(key).await?;
@@ -89,7 +151,12 @@ impl S3 {
     }
 
     pub async fn list_objects(&self, prefix: &str) -> anyhow::Result<Vec<String>> {
-        let results = self.bucket.list(prefix.to_owned(), None).await?;
+        let bucket = match &self.backend {
+            Backend::Bucket(bucket) => bucket,
+            Backend::Local(store) => return store.list_objects(prefix).await,
+        };
+
+        let results = bucket.list(prefix.to_owned(), None).await?;
         Ok(results
             .into_iter()
             .flat_map(|page| page.contents)
@@ -105,8 +172,16 @@ impl S3 {
         content_type: Option<&str>,
     ) -> anyhow::Result<()> {
         let content_type = content_type.unwrap_or("application/octet-stream");
-        let response = self
-            .bucket
+        let bucket = match &self.backend {
+            Backend::Bucket(bucket) => bucket,
+            Backend::Local(store) => {
+                return store
+                    .put_object(key, payload, content_type, &HashMap::new())
+                    .await;
+            }
+        };
+
+        let response = bucket
             .put_object_with_content_type(key, payload, content_type)
             .await?;
         let status = response.status_code();
@@ -122,7 +197,12 @@ impl S3 {
         &self,
         key: &str,
     ) -> anyhow::Result<Option<HashMap<String, String>>> {
-        let (result, status) = self.bucket.head_object(key).await?;
+        let bucket = match &self.backend {
+            Backend::Bucket(bucket) => bucket,
+            Backend::Local(store) => return store.get_object_metadata(key).await,
+        };
+
+        let (result, status) = bucket.head_object(key).await?;
         match status {
             200 => Ok(Some(result.metadata.unwrap_or_default())),
             404 => Ok(None),
@@ -138,6 +218,13 @@ impl S3 {
         metadata: &HashMap<String, String>,
     ) -> anyhow::Result<()> {
         let content_type = content_type.unwrap_or("application/octet-stream");
+        let bucket = match &self.backend {
+            Backend::Bucket(bucket) => bucket,
+            Backend::Local(store) => {
+                return store.put_object(key, payload, content_type, metadata).await;
+            }
+        };
+
         let mut headers = HeaderMap::new();
         for (name, value) in metadata {
             let header_name = HeaderName::try_from(format!("x-amz-meta-{name}"))
@@ -146,8 +233,7 @@ impl S3 {
                 .with_context(|| format!("invalid metadata value for `{name}`"))?;
             headers.insert(header_name, header_value);
         }
-        let response = self
-            .bucket
+        let response = bucket
             .put_object_with_content_type_and_headers(key, payload, content_type, Some(headers))
             .await?;
         let status = response.status_code();
@@ -156,4 +242,18 @@ impl S3 {
         }
         Ok(())
     }
+
+    pub async fn delete_object(&self, key: &str) -> anyhow::Result<()> {
+        let bucket = match &self.backend {
+            Backend::Bucket(bucket) => bucket,
+            Backend::Local(store) => return store.delete_object(key).await,
+        };
+
+        let response = bucket.delete_object(key).await?;
+        let status = response.status_code();
+        if !(200..300).contains(&status) {
+            anyhow::bail!("unexpected status {status} deleting object {key}");
+        }
+        Ok(())
+    }
 }
@@ -11,6 +11,7 @@ pub mod error;
 pub mod event_bus;
 pub mod graphql;
 pub mod graphql_tracing;
+pub mod history;
 pub mod http;
 pub mod integrations;
 pub mod metrics;
@@ -15,7 +15,7 @@ use mqtt::Mqtt;
 use ractor::Actor;
 use rustls::crypto::aws_lc_rs;
 use s3::S3;
-use settings::SettingsContainer;
+use settings::LiveConfig;
 use state::{ApiState, SharedActorState};
 use std::net::SocketAddr;
 use tokio::task::JoinSet;
@@ -45,8 +45,10 @@ async fn main() -> anyhow::Result<()> {
     tracing_setup::init();
     let metrics_registry = tracing_setup::init_metrics();
 
-    let (settings_container, device_registry) = SettingsContainer::new()?;
-    let settings = settings_container.clone();
+    let config = LiveConfig::load()?;
+    // Connection-level settings (database, mqtt, s3, integrations) are read once
+    // here; a reload only swaps what the actors read per message.
+    let settings = config.settings();
 
     let pool = db::connect(&settings.database_url).await?;
     db::migrate(&pool).await?;
@@ -66,11 +68,7 @@ async fn main() -> anyhow::Result<()> {
     let cancellation_token = CancellationToken::new();
     handle_cancellation(cancellation_token.clone());
 
-    let s3 = S3::new(
-        &settings.s3.bucket,
-        &settings.s3.region,
-        settings.s3.endpoint.clone(),
-    )?;
+    let s3 = S3::from_settings(&settings.s3)?;
 
     let event_bus = EventBus::default();
 
@@ -78,29 +76,23 @@ async fn main() -> anyhow::Result<()> {
         pool.clone(),
         s3.clone(),
         feature_flag_client.clone(),
-        device_registry.clone(),
-        settings_container.clone(),
+        config.clone(),
         home_gateway::integrations::reddit::Reddit::new(),
     );
 
-    let home_assistant =
-        home_assistant::HomeAssistant::from_settings(&settings_container.home_assistant);
+    let home_assistant = home_assistant::HomeAssistant::from_settings(&settings.home_assistant);
 
-    let jellyfin = settings_container
-        .jellyfin
-        .as_ref()
-        .and_then(jellyfin::Jellyfin::new);
+    let jellyfin = settings.jellyfin.as_ref().and_then(jellyfin::Jellyfin::new);
 
     let http_client = home_gateway::http::get_traced_http_client()?;
 
     let shared_actor_state = SharedActorState {
-        settings: settings_container.clone(),
-        devices: device_registry.clone(),
+        config: config.clone(),
         db: pool.clone(),
         mqtt: mqtt_client,
         feature_flag_client: feature_flag_client.clone(),
         s3: s3.clone(),
-        event_bus,
+        event_bus: event_bus.clone(),
         workflows: workflow_manager,
         home_assistant,
         jellyfin,
@@ -119,12 +111,12 @@ async fn main() -> anyhow::Result<()> {
     let api_state = ApiState {
         feature_flag_client,
         schema,
-        settings: settings_container.clone(),
+        config: config.clone(),
         db: pool.clone(),
         s3,
         auth: AuthManager::new(pool.clone(), oauth),
-        devices: device_registry.clone(),
         eink,
+        event_bus,
     };
 
     for key in &settings.api_keys {
@@ -159,9 +151,9 @@ async fn main() -> anyhow::Result<()> {
     });
 
     let mqtt_cancellation_token = cancellation_token.child_token();
-    let mqtt_devices = device_registry.clone();
+    let mqtt_config = config.clone();
     task_set.spawn(async move {
-        mqtt.process_events(mqtt_cancellation_token, mqtt_devices)
+        mqtt.process_events(mqtt_cancellation_token, mqtt_config)
             .await?;
         Ok::<(), MainError>(())
     });
@@ -17,8 +17,11 @@ struct Instruments {
     events_total: Counter<u64>,
     /// Trigger firings, labelled by trigger name and outcome.
     triggers_total: Counter<u64>,
-    /// Workflow executions, labelled by outcome (`success` / `error` / `disabled`).
+    /// Workflow executions, labelled by outcome (`success` / `error` /
+    /// `delayed` / `disabled` / `cancelled` / `dropped`).
     workflows_total: Counter<u64>,
+    /// New runs admitted by a workflow's run mode, labelled by mode and decision.
+    workflow_admissions_total: Counter<u64>,
     /// Wall-clock time to execute a whole workflow.
     workflow_duration: Histogram<f64>,
     /// Individual workflow steps, labelled by step kind and outcome.
@@ -30,8 +33,12 @@ struct Instruments {
     device_battery_percent: Gauge<f64>,
     /// Signed seconds between a display's intended wake and its actual poll.
     eink_wake_drift: Histogram<f64>,
+    /// Latest WiFi signal strength reported by a display, labelled by device id.
+    eink_rssi: Gauge<f64>,
     /// Ad-hoc task outcomes, labelled by task name and outcome.
     adhoc_tasks_total: Counter<u64>,
+    /// Notification deliveries, labelled by channel and outcome.
+    notifications_total: Counter<u64>,
 }
 
 static INSTRUMENTS: LazyLock<Instruments> = LazyLock::new(|| {
@@ -49,6 +56,10 @@ static INSTRUMENTS: LazyLock<Instruments> = LazyLock::new(|| {
             .u64_counter("home_gateway_workflows_total")
             .with_description("Workflow executions by outcome")
             .build(),
+        workflow_admissions_total: meter
+            .u64_counter("home_gateway_workflow_admissions_total")
+            .with_description("New workflow runs by run mode and admission decision")
+            .build(),
         workflow_duration: meter
             .f64_histogram("home_gateway_workflow_duration_seconds")
             .with_description("Workflow execution duration in seconds")
@@ -73,10 +84,18 @@ static INSTRUMENTS: LazyLock<Instruments> = LazyLock::new(|| {
             .f64_histogram("home_gateway_eink_wake_drift_seconds")
             .with_description("Seconds between an eink display's intended wake and its actual poll")
             .build(),
+        eink_rssi: meter
+            .f64_gauge("home_gateway_eink_rssi_dbm")
+            .with_description("Latest WiFi signal strength reported by an eink display")
+            .build(),
         adhoc_tasks_total: meter
             .u64_counter("home_gateway_adhoc_tasks_total")
             .with_description("Ad-hoc task outcomes by task name and outcome")
             .build(),
+        notifications_total: meter
+            .u64_counter("home_gateway_notifications_total")
+            .with_description("Notification deliveries by channel and outcome")
+            .build(),
     }
 });
 
@@ -90,12 +109,30 @@ pub fn record_adhoc_task(name: &'static str, outcome: &'static str) {
     );
 }
 
+/// Outcome (`delivered` / `failed` / `skipped` / `suppressed`) of one
+/// notification on one channel, e.g. `ntfy` or `android_app`.
+pub fn record_notification(channel: &'static str, outcome: &'static str) {
+    INSTRUMENTS.notifications_total.add(
+        1,
+        &[
+            KeyValue::new("channel", channel),
+            KeyValue::new("outcome", outcome),
+        ],
+    );
+}
+
 pub fn record_eink_wake_drift(device_id: String, drift_secs: f64) {
     INSTRUMENTS
         .eink_wake_drift
         .record(drift_secs, &[KeyValue::new("device_id", device_id)]);
 }
 
+pub fn record_eink_rssi(device_id: String, rssi_dbm: f64) {
+    INSTRUMENTS
+        .eink_rssi
+        .record(rssi_dbm, &[KeyValue::new("device_id", device_id)]);
+}
+
 pub fn record_device_battery_percent(device_id: String, kind: String, percent: f64) {
     INSTRUMENTS.device_battery_percent.record(
         percent,
@@ -163,6 +200,17 @@ pub fn record_workflow(outcome: &'static str, elapsed: Duration) {
         .record(elapsed.as_secs_f64(), &[KeyValue::new("outcome", outcome)]);
 }
 
+/// A new run was started, queued or dropped by its workflow's run mode.
+pub fn record_admission(mode: &'static str, decision: &'static str) {
+    INSTRUMENTS.workflow_admissions_total.add(
+        1,
+        &[
+            KeyValue::new("mode", mode),
+            KeyValue::new("decision", decision),
+        ],
+    );
+}
+
 /// A single workflow step finished executing.
 pub fn record_step(kind: &'static str, success: bool, elapsed: Duration) {
     let outcome = if success { "success" } else { "error" };
@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+use axum::{
+    Json,
+    response::{IntoResponse, Response},
+};
+use http::StatusCode;
+
+use crate::{
+    actors::system::{
+        config_reload::{ConfigReloadActor, ConfigReloadMessage},
+        rpc,
+    },
+    auth::{Auth, scope::required},
+    error::AppError,
+};
+
+const RELOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Re-read the config dir and swap it in. A config that fails validation is
+/// rejected with 422 and the error, and the running config is kept.
+pub async fn reload_config(Auth(auth): Auth) -> Result<Response, AppError> {
+    auth.require(&required::ADMIN_CONFIG_WRITE)
+        .map_err(AppError::StatusCode)?;
+
+    let result = rpc::query(ConfigReloadActor::NAME, RELOAD_TIMEOUT, |reply| {
+        ConfigReloadMessage::Reload { reply }
+    })
+    .await?;
+
+    Ok(match result {
+        Ok(change) => Json(change).into_response(),
+        Err(e) => (StatusCode::UNPROCESSABLE_ENTITY, e).into_response(),
+    })
+}
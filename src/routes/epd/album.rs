@@ -0,0 +1,110 @@
+use crate::{
+    auth::{Auth, scope::required},
+    eink::manager::album::{AlbumError, EinkAlbumItem},
+    error::AppError,
+    state::ApiState,
+};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+use bytes::Bytes;
+use http::StatusCode;
+use serde::Deserialize;
+
+/// Phone photos run well past axum's 2 MiB default.
+pub const MAX_UPLOAD_BYTES: usize = 32 * 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+pub struct UploadParams {
+    /// Crop for this display rather than the first one showing the album.
+    pub device_id: Option<String>,
+}
+
+fn status(err: AlbumError) -> AppError {
+    match err {
+        AlbumError::UnknownAlbum(_)
+        | AlbumError::UnknownItem { .. }
+        | AlbumError::UnknownDisplay(_) => AppError::StatusCode(StatusCode::NOT_FOUND),
+        AlbumError::Upload(e) => {
+            tracing::warn!("rejected an album upload: {e}");
+            AppError::StatusCode(StatusCode::UNSUPPORTED_MEDIA_TYPE)
+        }
+        e => AppError::Error(e.into()),
+    }
+}
+
+pub async fn list(
+    State(ApiState { eink, .. }): State<ApiState>,
+    Auth(auth): Auth,
+    Path(album): Path<String>,
+) -> Result<Json<Vec<EinkAlbumItem>>, AppError> {
+    auth.require(&required::REST_EPD_READ)
+        .map_err(AppError::StatusCode)?;
+
+    Ok(Json(eink.album_items(&album).await.map_err(status)?))
+}
+
+/// The body is the image itself: a JPEG, PNG or HEIC.
+pub async fn upload(
+    State(ApiState { eink, .. }): State<ApiState>,
+    Auth(auth): Auth,
+    Path(album): Path<String>,
+    Query(params): Query<UploadParams>,
+    body: Bytes,
+) -> Result<(StatusCode, Json<EinkAlbumItem>), AppError> {
+    auth.require(&required::REST_EPD_WRITE)
+        .map_err(AppError::StatusCode)?;
+
+    let item = eink
+        .upload_to_album(&album, body.to_vec(), params.device_id.as_deref())
+        .await
+        .map_err(status)?;
+
+    Ok((StatusCode::CREATED, Json(item)))
+}
+
+pub async fn delete(
+    State(ApiState { eink, .. }): State<ApiState>,
+    Auth(auth): Auth,
+    Path((album, name)): Path<(String, String)>,
+) -> Result<StatusCode, AppError> {
+    auth.require(&required::REST_EPD_WRITE)
+        .map_err(AppError::StatusCode)?;
+
+    eink.delete_album_item(&album, &name)
+        .await
+        .map_err(status)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn pin(
+    State(ApiState { eink, .. }): State<ApiState>,
+    Auth(auth): Auth,
+    Path((album, name)): Path<(String, String)>,
+) -> Result<Json<EinkAlbumItem>, AppError> {
+    auth.require(&required::REST_EPD_WRITE)
+        .map_err(AppError::StatusCode)?;
+
+    Ok(Json(
+        eink.pin_album_item(&album, &name, true)
+            .await
+            .map_err(status)?,
+    ))
+}
+
+pub async fn unpin(
+    State(ApiState { eink, .. }): State<ApiState>,
+    Auth(auth): Auth,
+    Path((album, name)): Path<(String, String)>,
+) -> Result<Json<EinkAlbumItem>, AppError> {
+    auth.require(&required::REST_EPD_WRITE)
+        .map_err(AppError::StatusCode)?;
+
+    Ok(Json(
+        eink.pin_album_item(&album, &name, false)
+            .await
+            .map_err(status)?,
+    ))
+}
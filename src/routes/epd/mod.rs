@@ -1,3 +1,5 @@
+pub mod album;
+
 use crate::{
     actors::eink_display::{EInkDisplayActor, EInkDisplayMessage},
     auth::{Auth, scope::required},
@@ -8,12 +10,14 @@ use crate::{
 use axum::{
     Json,
     extract::{Query, State},
+    response::IntoResponse,
 };
-use http::StatusCode;
+use http::{StatusCode, header};
 use serde::{Deserialize, Serialize};
 
 use crate::eink::EinkDisplayManager;
-use crate::eink::panel::{PACKED_FRAME_SIZE, crop_packed, packed_cache_key};
+use crate::eink::panel::{PanelProfile, crop_packed, packed_cache_key};
+use crate::eink::preview::decode_frame;
 
 pub use crate::eink::panel::PartialWindow;
 
@@ -66,7 +70,7 @@ pub struct ImageParams {
 }
 
 impl ImageParams {
-    fn window(&self) -> Result<Option<PartialWindow>, AppError> {
+    fn window(&self, panel: &PanelProfile) -> Result<Option<PartialWindow>, AppError> {
         let (Some(x), Some(y), Some(width), Some(height)) =
             (self.x, self.y, self.width, self.height)
         else {
@@ -80,9 +84,10 @@ impl ImageParams {
             height,
         };
 
-        if !window.is_valid() {
+        if !window.is_valid(panel) {
             tracing::warn!(
                 device_id = %self.device_id,
+                panel = panel.name,
                 "rejecting misaligned partial window x={x} y={y} w={width} h={height}"
             );
             return Err(AppError::StatusCode(StatusCode::BAD_REQUEST));
@@ -93,7 +98,7 @@ impl ImageParams {
 }
 
 pub async fn image(
-    State(ApiState { s3, .. }): State<ApiState>,
+    State(ApiState { s3, config, .. }): State<ApiState>,
     Auth(auth): Auth,
     axum::extract::Path(hash): axum::extract::Path<String>,
     Query(params): Query<ImageParams>,
@@ -101,7 +106,13 @@ pub async fn image(
     auth.require(&required::REST_EPD_READ)
         .map_err(AppError::StatusCode)?;
 
-    let window = params.window()?;
+    let panel = config
+        .devices()
+        .eink_display(&params.device_id)
+        .map(|display| display.panel)
+        .unwrap_or_default()
+        .profile();
+    let window = params.window(panel)?;
 
     let Some(key) = packed_cache_key(&hash) else {
         tracing::warn!(
@@ -120,30 +131,71 @@ pub async fn image(
         return Err(AppError::StatusCode(StatusCode::NOT_FOUND));
     };
 
-    if packed.len() != PACKED_FRAME_SIZE {
+    if packed.len() != panel.frame_size() {
         tracing::error!(
             device_id = %params.device_id,
             key = %key,
             len = packed.len(),
-            "pinned frame is not {PACKED_FRAME_SIZE} bytes"
+            "pinned frame is not the {} bytes of a {} frame",
+            panel.frame_size(),
+            panel.name
         );
         return Err(AppError::StatusCode(StatusCode::INTERNAL_SERVER_ERROR));
     }
 
     Ok(match window {
-        Some(window) => crop_packed(&packed, window),
+        Some(window) => crop_packed(panel, &packed, window),
         None => packed,
     })
 }
 
+/// A packed frame decoded to a PNG in the display's palette, as the panel was
+/// sent it; a window in the query is outlined rather than cropped to.
+pub async fn frame_preview(
+    State(ApiState { s3, eink, .. }): State<ApiState>,
+    Auth(auth): Auth,
+    axum::extract::Path(hash): axum::extract::Path<String>,
+    Query(params): Query<ImageParams>,
+) -> Result<impl IntoResponse, AppError> {
+    auth.require(&required::REST_EPD_READ)
+        .map_err(AppError::StatusCode)?;
+
+    let Some(display) = eink.resolve(&params.device_id).await else {
+        return Err(AppError::StatusCode(StatusCode::NOT_FOUND));
+    };
+    let panel = display.profile();
+    let window = params.window(panel)?;
+
+    let Some(key) = packed_cache_key(&hash) else {
+        return Err(AppError::StatusCode(StatusCode::BAD_REQUEST));
+    };
+
+    let Ok(packed) = s3.get_object(&key).await else {
+        tracing::warn!(
+            device_id = %params.device_id,
+            key = %key,
+            "previewed frame is not in the packed cache"
+        );
+        return Err(AppError::StatusCode(StatusCode::NOT_FOUND));
+    };
+
+    let png =
+        tokio::task::spawn_blocking(move || decode_frame(panel, &display.palette, &packed, window))
+            .await
+            .map_err(|e| anyhow::anyhow!("join error: {e}"))??;
+
+    Ok(([(header::CONTENT_TYPE, "image/png")], png))
+}
+
 pub async fn config(
-    State(ApiState { eink, devices, .. }): State<ApiState>,
+    State(ApiState { eink, config, .. }): State<ApiState>,
     Auth(auth): Auth,
     Json(request): Json<EpdConfigRequest>,
 ) -> Result<Json<EpdConfig>, AppError> {
     auth.require(&required::REST_EPD_READ)
         .map_err(AppError::StatusCode)?;
 
+    let devices = config.devices();
     let display = devices.eink_display(&request.device_id);
     let registered = display.is_some();
     let configured_mode = display.map(|display| display.mode.name());
@@ -171,30 +223,14 @@ pub async fn config(
     }
 
     report_to_actor(&request)?;
-
-    let prepared = crate::actors::system::rpc::query(
-        EInkDisplayActor::NAME,
-        PREPARE_RENDER_TIMEOUT,
-        |reply| EInkDisplayMessage::PrepareRender {
-            device_id: request.device_id.clone(),
-            reply,
-        },
-    )
-    .await;
-
-    if let Err(e) = prepared {
-        tracing::warn!(
-            device_id = %request.device_id,
-            "could not prepare a fresh render ({e}), serving the last one"
-        );
-    }
+    prepare_render(&request.device_id).await;
 
     let Some(resolved) = eink.resolve(&request.device_id).await else {
         return Err(AppError::StatusCode(StatusCode::NOT_FOUND));
     };
 
     let config = eink
-        .epd_config(
+        .serve_epd_config(
             &resolved,
             DeviceReport {
                 running_firmware_version: request.firmware_version.as_deref(),
@@ -225,7 +261,28 @@ async fn wake_drift_secs(eink: &EinkDisplayManager, device_id: &str) -> Option<i
     Some(drift)
 }
 
-fn schedule_next_render(device_id: &str, wake_in_secs: u32) -> Result<(), AppError> {
+/// Give the display's source a chance to render something fresh before a frame
+/// is planned from whatever is stored.
+pub(crate) async fn prepare_render(device_id: &str) {
+    let prepared = crate::actors::system::rpc::query(
+        EInkDisplayActor::NAME,
+        PREPARE_RENDER_TIMEOUT,
+        |reply| EInkDisplayMessage::PrepareRender {
+            device_id: device_id.to_owned(),
+            reply,
+        },
+    )
+    .await;
+
+    if let Err(e) = prepared {
+        tracing::warn!(
+            device_id,
+            "could not prepare a fresh render ({e}), serving the last one"
+        );
+    }
+}
+
+pub(crate) fn schedule_next_render(device_id: &str, wake_in_secs: u32) -> Result<(), AppError> {
     let Some(actor) = ractor::registry::where_is(EInkDisplayActor::NAME) else {
         tracing::warn!("eink display actor not found, not scheduling the next render");
         return Ok(());
@@ -239,7 +296,7 @@ fn schedule_next_render(device_id: &str, wake_in_secs: u32) -> Result<(), AppErr
     Ok(())
 }
 
-fn report_to_actor(request: &EpdConfigRequest) -> Result<(), AppError> {
+pub(crate) fn report_to_actor(request: &EpdConfigRequest) -> Result<(), AppError> {
     let Some(actor) = ractor::registry::where_is(EInkDisplayActor::NAME) else {
         tracing::warn!("eink display actor not found, dropping config request");
         return Ok(());
@@ -13,8 +13,8 @@ use crate::actors::{
         solar::SolarActor, synergy::SynergyActor, unifi::UnifiConnectedClientHandler,
         woolworths::WoolworthsActor,
     },
-    system::{cron::CronActor, push},
-    workflows::{WorkflowWorker, dispatcher::WorkflowDispatcher},
+    system::{cron::CronActor, notify, push},
+    workflows::{WorkflowWorker, delayed::DelayedRunActor, dispatcher::WorkflowDispatcher},
 };
 
 pub async fn health() -> StatusCode {
@@ -23,6 +23,7 @@ pub async fn health() -> StatusCode {
 
 const EXPECTED_ACTORS: &[&str] = &[
     WorkflowDispatcher::NAME,
+    DelayedRunActor::NAME,
     UnifiConnectedClientHandler::NAME,
     CronActor::NAME,
     SynergyActor::NAME,
@@ -33,6 +34,8 @@ const EXPECTED_ACTORS: &[&str] = &[
     DoorEventsSupervisor::NAME,
     WorkflowWorker::NAME,
     push::PushWorker::NAME,
+    notify::NotifyWorker::NAME,
+    notify::policy::NotifyPolicyActor::NAME,
     light::LightHandler::NAME,
     control_switch::ControlSwitchHandler::NAME,
     smart_switch::SmartSwitchHandler::NAME,
@@ -1,2 +1,3 @@
 pub mod alarm;
+pub mod notification_action;
 pub mod push_token;
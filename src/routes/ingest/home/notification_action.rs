@@ -0,0 +1,56 @@
+use crate::{
+    auth::{Auth, scope::required},
+    event_bus::EventBusMessage,
+    integrations::notify::actions::{self, Response},
+    state::ApiState,
+};
+use axum::{Json, extract::State};
+use http::StatusCode;
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct NotificationActionPayload {
+    pub notification_id: Uuid,
+    pub action: String,
+}
+
+/// Called by the app when a notification button is tapped. Each notification
+/// takes one answer: a repeat (or a second button) gets a 409.
+pub async fn notification_action(
+    State(ApiState {
+        ref db,
+        ref event_bus,
+        ..
+    }): State<ApiState>,
+    Auth(auth): Auth,
+    Json(payload): Json<NotificationActionPayload>,
+) -> StatusCode {
+    if auth.require(&required::INGEST_HOME_WRITE).is_err() {
+        return StatusCode::FORBIDDEN;
+    }
+
+    match actions::respond(db, payload.notification_id, &payload.action).await {
+        Ok(Response::Accepted {
+            origin_event_id,
+            source,
+        }) => {
+            // a fresh event, so a run waiting on the reply can't pass for
+            // the run that sent the notification
+            event_bus.publish(EventBusMessage::NotificationAction {
+                event_id: Uuid::new_v4(),
+                origin_event_id,
+                notification_id: payload.notification_id,
+                source,
+                action: payload.action,
+            });
+            StatusCode::NO_CONTENT
+        }
+        Ok(Response::AlreadyAnswered) => StatusCode::CONFLICT,
+        Ok(Response::Unknown) => StatusCode::NOT_FOUND,
+        Err(e) => {
+            tracing::error!("failed to record notification action: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
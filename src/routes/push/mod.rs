@@ -32,6 +32,7 @@ pub async fn notify(Auth(auth): Auth, Json(payload): Json<PushNotifyPayload>) ->
         msg: push::PushMessage::Send {
             title: payload.title,
             body: payload.body,
+            actionable: None,
         },
         options: JobOptions::default(),
         accepted: None,
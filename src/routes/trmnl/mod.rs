@@ -0,0 +1,266 @@
+//! The TRMNL "bring your own server" device protocol, so TRMNL hardware can be
+//! pointed at the gateway (`{base}/v1/trmnl`) and shown frames from the same
+//! sources as the eink firmware. The device cannot send `X-Api-Key`, so these
+//! routes sit outside the auth middleware: `/api/setup` hands a registered
+//! device an api key, which it returns as `Access-Token` from then on. Since
+//! the only thing identifying the device there is its MAC, it is only paired,
+//! or its key replaced, while its `byos` block sets `allow_setup`.
+
+use crate::{
+    auth::{resolve_device_token, scope::required},
+    battery::BatteryChemistry,
+    eink::manager::trmnl_cache_key,
+    error::AppError,
+    routes::epd::{EpdConfigRequest, prepare_render, report_to_actor, schedule_next_render},
+    settings::TrmnlDeviceSettings,
+    state::ApiState,
+};
+use axum::{
+    Json,
+    extract::{Path, State},
+    response::IntoResponse,
+};
+use http::{HeaderMap, StatusCode, header};
+use serde::Serialize;
+
+/// What a TRMNL api key may do: fetch its own display.
+const DEVICE_SCOPES: &[&str] = &["rest:epd:read"];
+
+#[cfg(debug_assertions)]
+const HOST: &str = "http://192.168.0.149:8000/v1/trmnl";
+#[cfg(not(debug_assertions))]
+const HOST: &str = "https://home.anurag.sh/v1/trmnl";
+
+#[derive(Debug, Serialize)]
+pub struct SetupResponse {
+    pub status: u16,
+    pub api_key: String,
+    pub friendly_id: String,
+    pub image_url: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DisplayResponse {
+    pub status: u16,
+    pub image_url: Option<String>,
+    pub filename: Option<String>,
+    pub refresh_rate: u32,
+    pub reset_firmware: bool,
+    pub update_firmware: bool,
+    pub firmware_url: Option<String>,
+    pub special_function: &'static str,
+}
+
+fn header_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+fn numeric_header<T: std::str::FromStr>(headers: &HeaderMap, name: &str) -> Option<T> {
+    header_value(headers, name).and_then(|value| value.parse().ok())
+}
+
+/// The self-served TRMNL named by the `ID` (MAC) header.
+fn device(state: &ApiState, headers: &HeaderMap) -> Result<TrmnlDeviceSettings, AppError> {
+    let Some(mac) = header_value(headers, "ID") else {
+        return Err(AppError::StatusCode(StatusCode::BAD_REQUEST));
+    };
+
+    let devices = state.config.devices();
+    let Some(device) = devices.byos_trmnl(mac) else {
+        tracing::warn!(
+            mac,
+            "trmnl request from an unregistered device, add it to devices.yaml with a `byos` block"
+        );
+        return Err(AppError::StatusCode(StatusCode::NOT_FOUND));
+    };
+
+    Ok(device.clone())
+}
+
+/// The device's own key, or a broader one; never another TRMNL's.
+async fn authorize(
+    state: &ApiState,
+    headers: &HeaderMap,
+    device: &TrmnlDeviceSettings,
+) -> Result<(), AppError> {
+    let auth = resolve_device_token(header_value(headers, "Access-Token"), state)
+        .await
+        .map_err(AppError::StatusCode)?;
+
+    auth.require(&required::REST_EPD_READ)
+        .map_err(AppError::StatusCode)?;
+
+    if let Some(name) = &auth.name
+        && name.starts_with("trmnl:")
+        && *name != device.key_name()
+    {
+        tracing::warn!(
+            device_id = %device.id,
+            key = %name,
+            "trmnl presented another device's access token"
+        );
+        return Err(AppError::StatusCode(StatusCode::FORBIDDEN));
+    }
+
+    Ok(())
+}
+
+/// Called by a freshly reset device. While `allow_setup` is on, a registered
+/// one is given a key, revoking any it already held. Otherwise it is refused,
+/// with a conflict if it already has a key.
+pub async fn setup(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+) -> Result<Json<SetupResponse>, AppError> {
+    let device = device(&state, &headers)?;
+    let name = device.key_name();
+
+    let existing = state
+        .auth
+        .list()
+        .await?
+        .into_iter()
+        .find(|key| key.name == name && key.revoked_at.is_none());
+
+    let allow_setup = device.byos.as_ref().is_some_and(|byos| byos.allow_setup);
+    let created = match existing {
+        Some(_) if !allow_setup => {
+            tracing::warn!(
+                device_id = %device.id,
+                "trmnl setup refused, the device already has a key; set `allow_setup` to pair it again"
+            );
+            return Err(AppError::StatusCode(StatusCode::CONFLICT));
+        }
+        Some(key) => state.auth.regenerate(key.id).await?,
+        None if !allow_setup => {
+            tracing::warn!(
+                device_id = %device.id,
+                "trmnl setup refused, pairing is off; set `allow_setup` to pair it"
+            );
+            return Err(AppError::StatusCode(StatusCode::FORBIDDEN));
+        }
+        None => None,
+    };
+    let created = match created {
+        Some(created) => created,
+        None => {
+            let scopes: Vec<String> = DEVICE_SCOPES.iter().map(|s| (*s).to_owned()).collect();
+            state.auth.create(&name, &scopes, None).await?
+        }
+    };
+
+    tracing::info!(device_id = %device.id, key = %created.key_prefix, "trmnl set up");
+
+    Ok(Json(SetupResponse {
+        status: 200,
+        api_key: created.key,
+        friendly_id: device.id.clone(),
+        image_url: None,
+        message: format!("{} registered with home-gateway", device.name),
+    }))
+}
+
+pub async fn display(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+) -> Result<Json<DisplayResponse>, AppError> {
+    let device = device(&state, &headers)?;
+    authorize(&state, &headers, &device).await?;
+
+    if device.byos.is_none() {
+        return Err(AppError::StatusCode(StatusCode::NOT_FOUND));
+    }
+
+    let battery_voltage = numeric_header::<f32>(&headers, "Battery-Voltage");
+    let rssi = numeric_header::<f64>(&headers, "RSSI");
+    let firmware_version = header_value(&headers, "FW-Version").map(str::to_owned);
+
+    tracing::info!(
+        device_id = %device.id,
+        ?battery_voltage,
+        ?rssi,
+        ?firmware_version,
+        "trmnl display requested"
+    );
+
+    if let Some(rssi) = rssi {
+        crate::metrics::record_eink_rssi(device.id.clone(), rssi);
+    }
+
+    report_to_actor(&EpdConfigRequest {
+        device_id: device.id.clone(),
+        battery_voltage,
+        is_charging: None,
+        battery_chemistry: Some(BatteryChemistry::Lipo),
+        battery_kind: Some("trmnl".to_owned()),
+        firmware_version,
+        current_image_hash: None,
+    })?;
+    prepare_render(&device.id).await;
+
+    let Some(resolved) = state.eink.resolve(&device.id).await else {
+        return Err(AppError::StatusCode(StatusCode::NOT_FOUND));
+    };
+
+    let frame = state.eink.trmnl_display(&resolved).await;
+    schedule_next_render(&device.id, frame.refresh_secs)?;
+
+    Ok(Json(DisplayResponse {
+        status: 0,
+        image_url: frame
+            .hash
+            .as_ref()
+            .map(|hash| format!("{HOST}/image/{hash}.png")),
+        filename: frame.hash,
+        refresh_rate: frame.refresh_secs,
+        reset_firmware: false,
+        update_firmware: false,
+        firmware_url: None,
+        special_function: "sleep",
+    }))
+}
+
+/// The device's own diagnostics, posted after a failed cycle.
+pub async fn log(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    Json(body): Json<serde_json::Value>,
+) -> Result<StatusCode, AppError> {
+    let device = device(&state, &headers)?;
+    authorize(&state, &headers, &device).await?;
+
+    let entries = body
+        .pointer("/log/logs_array")
+        .and_then(|logs| logs.as_array())
+        .cloned()
+        .unwrap_or_else(|| vec![body]);
+
+    for entry in entries {
+        tracing::info!(device_id = %device.id, %entry, "trmnl log");
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Frames are fetched without the access token, so they are addressed only by
+/// their content hash.
+pub async fn image(
+    State(ApiState { s3, .. }): State<ApiState>,
+    Path(file): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let Some(key) = file.strip_suffix(".png").and_then(trmnl_cache_key) else {
+        return Err(AppError::StatusCode(StatusCode::BAD_REQUEST));
+    };
+
+    let Ok(png) = s3.get_object(&key).await else {
+        tracing::warn!(key = %key, "trmnl frame is not in the frame cache");
+        return Err(AppError::StatusCode(StatusCode::NOT_FOUND));
+    };
+
+    Ok(([(header::CONTENT_TYPE, "image/png")], png))
+}
@@ -33,6 +33,7 @@ pub async fn workflow_execute(
         event_id: uuid::Uuid::new_v4(),
         workflow: payload.workflow,
         vars: HashMap::new(),
+        reply: None,
     };
 
     let message = FactoryMessage::Dispatch(Job {
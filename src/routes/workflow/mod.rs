@@ -1 +1,2 @@
 pub mod execute;
+pub mod run;
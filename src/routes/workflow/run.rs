@@ -0,0 +1,109 @@
+use crate::{
+    actors::workflows::invoke::{Invocation, InvokeError, WorkflowExecution, invoke},
+    auth::{Auth, scope::required},
+    error::AppError,
+    state::ApiState,
+};
+use axum::{
+    Json,
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+};
+use http::StatusCode;
+use serde::Deserialize;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct WorkflowRunPayload {
+    #[serde(default)]
+    pub inputs: HashMap<String, String>,
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default)]
+    pub wait: bool,
+}
+
+/// Run the configured workflow `slug`. With `wait` the finished run comes
+/// back with its trace; otherwise, or if it outlasts the wait, 202 with the
+/// `run_id` to poll.
+pub async fn workflow_run(
+    State(ApiState { config, .. }): State<ApiState>,
+    Auth(auth): Auth,
+    Path(slug): Path<String>,
+    Json(payload): Json<WorkflowRunPayload>,
+) -> Result<Response, AppError> {
+    let invocation = Invocation {
+        slug,
+        inputs: payload.inputs,
+        dry_run: payload.dry_run,
+        wait: payload.wait,
+    };
+
+    let execution = invoke(
+        &config.settings(),
+        &auth,
+        &required::REST_WORKFLOW_EXECUTE,
+        invocation,
+    )
+    .await;
+
+    Ok(match execution {
+        Ok(execution) if execution.outcome.is_none() => {
+            (StatusCode::ACCEPTED, Json(execution)).into_response()
+        }
+        Ok(execution) => Json(execution).into_response(),
+        Err(InvokeError::UnknownWorkflow(_)) => StatusCode::NOT_FOUND.into_response(),
+        Err(InvokeError::Forbidden) => StatusCode::FORBIDDEN.into_response(),
+        Err(e @ InvokeError::InvalidInputs(_)) => {
+            (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()).into_response()
+        }
+        Err(InvokeError::Rpc(e)) => return Err(e.into()),
+    })
+}
+
+/// The latest recorded run for `run_id`. 404 until the run has ended or
+/// parked, since nothing is recorded before then.
+pub async fn workflow_run_status(
+    State(ApiState { config, db, .. }): State<ApiState>,
+    Auth(auth): Auth,
+    Path(run_id): Path<Uuid>,
+) -> Result<Response, AppError> {
+    let Some(row) = sqlx::query!(
+        "SELECT slug, outcome, dry_run, duration_ms, error, trace \
+         FROM workflow_runs \
+         WHERE event_id = $1 \
+         ORDER BY started_at DESC \
+         LIMIT 1",
+        run_id,
+    )
+    .fetch_optional(&db)
+    .await?
+    else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+
+    // a workflow since removed from the config only answers to untagged scopes
+    let settings = config.settings();
+    let tags = settings
+        .workflows
+        .values()
+        .find(|w| w.slug == row.slug)
+        .map(|w| w.tags.as_slice())
+        .unwrap_or_default();
+    if !auth.has_for_tags(&required::REST_WORKFLOW_READ, tags) {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+
+    Ok(Json(WorkflowExecution {
+        run_id,
+        outcome: Some(row.outcome),
+        dry_run: row.dry_run,
+        duration_ms: Some(row.duration_ms),
+        error: row.error,
+        trace: row
+            .trace
+            .and_then(|trace| serde_json::from_value(trace).ok()),
+    })
+    .into_response())
+}
@@ -2,7 +2,9 @@ use chrono::TimeDelta;
 use schemars::JsonSchema;
 use serde::Deserialize;
 
-use super::notify::{NotifyRef, NotifySource, NotifyTargets, resolve_notify};
+use super::notify::{
+    NotifyAction, NotifyRef, NotifySource, NotifyTargets, resolve_notify, validate_actions,
+};
 use crate::timedelta_format::option_time_delta_from_str;
 
 #[derive(Debug, Clone)]
@@ -17,13 +19,19 @@ pub struct RawDeviceWatchdog {
     timeout: Option<TimeDelta>,
     #[serde(default)]
     notify: Vec<NotifyRef>,
+    /// Buttons on the offline alert.
+    #[serde(default)]
+    actions: Vec<NotifyAction>,
 }
 
 impl RawDeviceWatchdog {
     pub(crate) fn resolve(self, targets: &NotifyTargets) -> Result<DeviceWatchdog, String> {
+        validate_actions(&self.actions)?;
+
         Ok(DeviceWatchdog {
             timeout: self.timeout,
             notify: resolve_notify(self.notify, targets)?,
+            actions: self.actions,
         })
     }
 }
@@ -32,4 +40,5 @@ impl RawDeviceWatchdog {
 pub struct DeviceWatchdog {
     pub timeout: Option<TimeDelta>,
     pub notify: Vec<NotifySource>,
+    pub actions: Vec<NotifyAction>,
 }
@@ -3,7 +3,9 @@ use chrono::TimeDelta;
 use schemars::JsonSchema;
 use serde::Deserialize;
 
-use super::notify::{NotifyRef, NotifySource, NotifyTargets, resolve_notify};
+use super::notify::{
+    NotifyAction, NotifyRef, NotifySource, NotifyTargets, resolve_notify, validate_actions,
+};
 
 #[derive(Debug, Deserialize, Clone, JsonSchema)]
 #[serde(tag = "state", rename_all = "snake_case")]
@@ -22,6 +24,7 @@ pub struct DoorSettings {
     pub id: String,
     pub armed: ArmedDoorStates,
     pub notify: Vec<NotifySource>,
+    pub actions: Vec<NotifyAction>,
 }
 
 #[derive(Debug, Deserialize, Clone, JsonSchema)]
@@ -32,15 +35,21 @@ pub struct RawDoorSettings {
     armed: ArmedDoorStates,
     #[serde(default)]
     notify: Vec<NotifyRef>,
+    /// Buttons on the left-open alert, e.g. to snooze it.
+    #[serde(default)]
+    actions: Vec<NotifyAction>,
 }
 
 impl RawDoorSettings {
     pub(crate) fn resolve(self, targets: &NotifyTargets) -> Result<DoorSettings, String> {
+        validate_actions(&self.actions)?;
+
         Ok(DoorSettings {
             name: self.name,
             id: self.id,
             armed: self.armed,
             notify: resolve_notify(self.notify, targets)?,
+            actions: self.actions,
         })
     }
 }
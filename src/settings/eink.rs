@@ -5,6 +5,9 @@ use schemars::JsonSchema;
 use serde::Deserialize;
 
 use crate::actors::system::cron::schedule::CronSchedule;
+use crate::eink::panel::PanelProfile;
+use crate::settings::eink_layout::{DashboardLayout, RawDashboardLayout};
+use crate::settings::eink_playlist::{PlaylistEntry, RawPlaylistEntry};
 use crate::timedelta_format::{humanize, time_delta_from_str};
 
 pub const DEFAULT_ALBUM_PREFIX: &str = "eink-display/album/";
@@ -15,6 +18,8 @@ pub enum EinkMode {
     Dashboard,
     Album,
     Reddit,
+    Native,
+    Playlist,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema, async_graphql::Enum)]
@@ -37,6 +42,29 @@ pub enum EinkModeConfig {
     Reddit {
         feed: RedditFeed,
     },
+    Native {
+        layout: Option<String>,
+    },
+    /// Entries in order; the one to show is picked per wake.
+    Playlist {
+        entries: Vec<PlaylistEntry>,
+    },
+}
+
+/// The e-paper panel a display drives; its geometry and encoding are its
+/// [`PanelProfile`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+pub enum Panel {
+    #[default]
+    #[serde(rename = "spectra6_13in3")]
+    Spectra6,
+    #[serde(rename = "bwr_7in5")]
+    Bwr7in5,
+    /// Picked from a TRMNL's `bit_depth` rather than named in config.
+    #[serde(skip)]
+    Trmnl1Bit,
+    #[serde(skip)]
+    Trmnl2Bit,
 }
 
 impl EinkModeConfig {
@@ -45,53 +73,83 @@ impl EinkModeConfig {
             EinkModeConfig::Dashboard { .. } => EinkMode::Dashboard,
             EinkModeConfig::Album { .. } => EinkMode::Album,
             EinkModeConfig::Reddit { .. } => EinkMode::Reddit,
+            EinkModeConfig::Native { .. } => EinkMode::Native,
+            EinkModeConfig::Playlist { .. } => EinkMode::Playlist,
         }
     }
 
     pub fn view(&self) -> Option<&str> {
         match self {
             EinkModeConfig::Dashboard { view, .. } => view.as_deref(),
-            EinkModeConfig::Album { .. } | EinkModeConfig::Reddit { .. } => None,
+            EinkModeConfig::Album { .. }
+            | EinkModeConfig::Reddit { .. }
+            | EinkModeConfig::Native { .. }
+            | EinkModeConfig::Playlist { .. } => None,
         }
     }
 
     pub fn settle(&self) -> Option<TimeDelta> {
         match self {
             EinkModeConfig::Dashboard { settle, .. } => Some(*settle),
-            EinkModeConfig::Album { .. } | EinkModeConfig::Reddit { .. } => None,
+            EinkModeConfig::Album { .. }
+            | EinkModeConfig::Reddit { .. }
+            | EinkModeConfig::Native { .. }
+            | EinkModeConfig::Playlist { .. } => None,
         }
     }
 
     pub fn lead(&self) -> Option<TimeDelta> {
         match self {
             EinkModeConfig::Dashboard { lead, .. } => Some(*lead),
-            EinkModeConfig::Album { .. } | EinkModeConfig::Reddit { .. } => None,
+            EinkModeConfig::Album { .. }
+            | EinkModeConfig::Reddit { .. }
+            | EinkModeConfig::Native { .. }
+            | EinkModeConfig::Playlist { .. } => None,
         }
     }
 
     pub fn album(&self) -> Option<&str> {
         match self {
             EinkModeConfig::Album { album } => album.as_deref(),
-            EinkModeConfig::Dashboard { .. } | EinkModeConfig::Reddit { .. } => None,
+            EinkModeConfig::Dashboard { .. }
+            | EinkModeConfig::Reddit { .. }
+            | EinkModeConfig::Native { .. }
+            | EinkModeConfig::Playlist { .. } => None,
         }
     }
 
     pub fn feed(&self) -> Option<&RedditFeed> {
         match self {
             EinkModeConfig::Reddit { feed } => Some(feed),
-            EinkModeConfig::Dashboard { .. } | EinkModeConfig::Album { .. } => None,
+            EinkModeConfig::Dashboard { .. }
+            | EinkModeConfig::Album { .. }
+            | EinkModeConfig::Native { .. }
+            | EinkModeConfig::Playlist { .. } => None,
         }
     }
-}
 
-impl Orientation {
-    pub fn target_dims(self) -> (u32, u32) {
+    pub fn layout(&self) -> Option<&str> {
+        match self {
+            EinkModeConfig::Native { layout } => layout.as_deref(),
+            EinkModeConfig::Dashboard { .. }
+            | EinkModeConfig::Album { .. }
+            | EinkModeConfig::Reddit { .. }
+            | EinkModeConfig::Playlist { .. } => None,
+        }
+    }
+
+    /// Every source the mode can show: a playlist's entries, otherwise itself.
+    pub fn sources(&self) -> Vec<&EinkModeConfig> {
         match self {
-            Orientation::Portrait => (1200, 1600),
-            Orientation::Landscape => (1600, 1200),
+            EinkModeConfig::Playlist { entries } => {
+                entries.iter().map(|entry| &entry.source).collect()
+            }
+            mode => vec![mode],
         }
     }
+}
 
+impl Orientation {
     pub fn as_str(self) -> &'static str {
         match self {
             Orientation::Portrait => "portrait",
@@ -143,15 +201,6 @@ pub struct RedditFeed {
     pub limit: u32,
 }
 
-pub const PALETTE_COLORS: [(&str, f32, f32, f32, u8); 6] = [
-    ("black", 0.0, 0.0, 0.0, 0),
-    ("white", 255.0, 255.0, 255.0, 1),
-    ("yellow", 255.0, 255.0, 0.0, 2),
-    ("red", 255.0, 0.0, 0.0, 3),
-    ("blue", 0.0, 0.0, 255.0, 5),
-    ("green", 0.0, 255.0, 0.0, 6),
-];
-
 #[derive(Debug, Clone, Copy)]
 pub struct PaletteColor {
     pub name: &'static str,
@@ -161,17 +210,139 @@ pub struct PaletteColor {
     pub index: u8,
 }
 
-fn default_palette() -> Vec<PaletteColor> {
-    PALETTE_COLORS
-        .iter()
-        .map(|&(name, r, g, b, index)| PaletteColor {
-            name,
-            r,
-            g,
-            b,
-            index,
-        })
-        .collect()
+/// How a frame is reduced to the panel's palette. `bayer` and `blue_noise`
+/// are ordered, which keeps the flat colours of a dashboard free of worms.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DitherAlgorithm {
+    #[default]
+    FloydSteinberg,
+    Atkinson,
+    Stucki,
+    JarvisJudiceNinke,
+    Bayer,
+    BlueNoise,
+}
+
+impl DitherAlgorithm {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DitherAlgorithm::FloydSteinberg => "floyd_steinberg",
+            DitherAlgorithm::Atkinson => "atkinson",
+            DitherAlgorithm::Stucki => "stucki",
+            DitherAlgorithm::JarvisJudiceNinke => "jarvis_judice_ninke",
+            DitherAlgorithm::Bayer => "bayer",
+            DitherAlgorithm::BlueNoise => "blue_noise",
+        }
+    }
+}
+
+/// Space pixels are matched to the nearest palette colour in. The perceptual
+/// `cielab` and `oklab` keep skin tones from going muddy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorSpace {
+    #[default]
+    Rgb,
+    Cielab,
+    Oklab,
+}
+
+impl ColorSpace {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ColorSpace::Rgb => "rgb",
+            ColorSpace::Cielab => "cielab",
+            ColorSpace::Oklab => "oklab",
+        }
+    }
+}
+
+const MAX_BOOST: f32 = 3.0;
+
+/// Dithering for a display or mode; anything left unset falls through to the
+/// next layer. `contrast` and `saturation` scale the image before it is
+/// dithered, `1` leaving it as is; Spectra panels show photos best at about
+/// `1.1` and `1.3`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+pub struct DitherConfig {
+    #[serde(default)]
+    pub algorithm: Option<DitherAlgorithm>,
+    #[serde(default)]
+    pub color_space: Option<ColorSpace>,
+    #[serde(default)]
+    pub contrast: Option<f32>,
+    #[serde(default)]
+    pub saturation: Option<f32>,
+}
+
+impl DitherConfig {
+    /// Fields set here, the rest from `fallback`.
+    pub fn or(self, fallback: DitherConfig) -> DitherConfig {
+        DitherConfig {
+            algorithm: self.algorithm.or(fallback.algorithm),
+            color_space: self.color_space.or(fallback.color_space),
+            contrast: self.contrast.or(fallback.contrast),
+            saturation: self.saturation.or(fallback.saturation),
+        }
+    }
+
+    pub fn resolve(self) -> Dither {
+        let default = Dither::default();
+        let boost = |value: Option<f32>| value.map_or(1.0, |v| v.clamp(0.0, MAX_BOOST));
+
+        Dither {
+            algorithm: self.algorithm.unwrap_or(default.algorithm),
+            color_space: self.color_space.unwrap_or(default.color_space),
+            contrast: boost(self.contrast),
+            saturation: boost(self.saturation),
+        }
+    }
+}
+
+/// Dithering defaults for every display in a mode.
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema)]
+pub struct ModeDither {
+    #[serde(default)]
+    pub dashboard: DitherConfig,
+    #[serde(default)]
+    pub album: DitherConfig,
+    #[serde(default)]
+    pub reddit: DitherConfig,
+    #[serde(default)]
+    pub native: DitherConfig,
+}
+
+impl ModeDither {
+    pub fn for_mode(&self, mode: EinkMode) -> DitherConfig {
+        match mode {
+            EinkMode::Dashboard => self.dashboard,
+            EinkMode::Album => self.album,
+            EinkMode::Reddit => self.reddit,
+            EinkMode::Native => self.native,
+            // a playlist is dithered as the entry it is showing
+            EinkMode::Playlist => DitherConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dither {
+    pub algorithm: DitherAlgorithm,
+    pub color_space: ColorSpace,
+    pub contrast: f32,
+    pub saturation: f32,
+}
+
+impl Default for Dither {
+    fn default() -> Self {
+        Self {
+            algorithm: DitherAlgorithm::FloydSteinberg,
+            color_space: ColorSpace::Rgb,
+            contrast: 1.0,
+            saturation: 1.0,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
@@ -182,6 +353,10 @@ pub struct RawEinkGlobal {
     albums: HashMap<String, RawAlbum>,
     #[serde(default)]
     palette: HashMap<String, RawPaletteColor>,
+    #[serde(default)]
+    dither: ModeDither,
+    #[serde(default)]
+    layouts: HashMap<String, RawDashboardLayout>,
 }
 
 #[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
@@ -203,25 +378,18 @@ struct RawAlbum {
     prefix: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct EinkGlobalSettings {
     pub views: HashMap<String, DashboardView>,
     pub albums: HashMap<String, Album>,
-    pub palette: Vec<PaletteColor>,
-}
-
-impl Default for EinkGlobalSettings {
-    fn default() -> Self {
-        Self {
-            views: HashMap::new(),
-            albums: HashMap::new(),
-            palette: default_palette(),
-        }
-    }
+    /// Calibrated colours by palette name, for every panel that has them.
+    pub palette_overrides: HashMap<String, (f32, f32, f32)>,
+    pub dither: ModeDither,
+    pub layouts: HashMap<String, DashboardLayout>,
 }
 
 impl RawEinkGlobal {
-    pub fn resolve(self) -> EinkGlobalSettings {
+    pub fn resolve(self) -> Result<EinkGlobalSettings, String> {
         let views = self
             .views
             .into_iter()
@@ -245,34 +413,32 @@ impl RawEinkGlobal {
                 (name.clone(), Album { name, prefix })
             })
             .collect();
-        let palette = PALETTE_COLORS
-            .iter()
-            .map(|&(name, dr, dg, db, index)| {
-                let (r, g, b) = self
-                    .palette
-                    .get(name)
-                    .map(|c| {
-                        (
-                            c.r.clamp(0.0, 255.0),
-                            c.g.clamp(0.0, 255.0),
-                            c.b.clamp(0.0, 255.0),
-                        )
-                    })
-                    .unwrap_or((dr, dg, db));
-                PaletteColor {
+        let palette_overrides = self
+            .palette
+            .into_iter()
+            .map(|(name, c)| {
+                (
                     name,
-                    r,
-                    g,
-                    b,
-                    index,
-                }
+                    (
+                        c.r.clamp(0.0, 255.0),
+                        c.g.clamp(0.0, 255.0),
+                        c.b.clamp(0.0, 255.0),
+                    ),
+                )
             })
             .collect();
-        EinkGlobalSettings {
+        let layouts = self
+            .layouts
+            .into_iter()
+            .map(|(name, layout)| Ok((name.clone(), layout.resolve(&name)?)))
+            .collect::<Result<_, String>>()?;
+        Ok(EinkGlobalSettings {
             views,
             albums,
-            palette,
-        }
+            palette_overrides,
+            dither: self.dither,
+            layouts,
+        })
     }
 }
 
@@ -285,6 +451,31 @@ impl EinkGlobalSettings {
         self.albums.get(name)
     }
 
+    pub fn layout(&self, name: &str) -> Option<&DashboardLayout> {
+        self.layouts.get(name)
+    }
+
+    pub fn palette(&self, panel: &PanelProfile) -> Vec<PaletteColor> {
+        panel
+            .palette
+            .iter()
+            .map(|&(name, dr, dg, db, index)| {
+                let (r, g, b) = self
+                    .palette_overrides
+                    .get(name)
+                    .copied()
+                    .unwrap_or((dr, dg, db));
+                PaletteColor {
+                    name,
+                    r,
+                    g,
+                    b,
+                    index,
+                }
+            })
+            .collect()
+    }
+
     pub fn default_album(&self) -> Album {
         Album {
             name: "default".to_owned(),
@@ -315,11 +506,18 @@ pub enum RawEinkMode {
         timespan: RedditTimespan,
         limit: u32,
     },
+    Native {
+        #[serde(default)]
+        layout: Option<String>,
+    },
+    Playlist {
+        entries: Vec<RawPlaylistEntry>,
+    },
 }
 
 impl RawEinkMode {
-    fn resolve(self) -> EinkModeConfig {
-        match self {
+    pub(crate) fn resolve(self, id: &str) -> Result<EinkModeConfig, String> {
+        Ok(match self {
             RawEinkMode::Dashboard { view, settle, lead } => {
                 EinkModeConfig::Dashboard { view, settle, lead }
             }
@@ -335,7 +533,20 @@ impl RawEinkMode {
                     limit,
                 },
             },
-        }
+            RawEinkMode::Native { layout } => EinkModeConfig::Native { layout },
+            RawEinkMode::Playlist { entries } => {
+                if entries.is_empty() {
+                    return Err(format!("eink display {id}: a playlist needs an entry"));
+                }
+
+                EinkModeConfig::Playlist {
+                    entries: entries
+                        .into_iter()
+                        .map(|entry| entry.resolve(id))
+                        .collect::<Result<_, _>>()?,
+                }
+            }
+        })
     }
 }
 
@@ -384,17 +595,10 @@ pub struct RawSleepWindow {
 }
 
 impl RawSleepWindow {
-    fn resolve(self, id: &str, grace: TimeDelta) -> Result<SleepWindow, String> {
-        let parse = |value: &str| {
-            NaiveTime::parse_from_str(value, "%H:%M:%S")
-                .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M"))
-                .map_err(|_| {
-                    format!("eink display {id}: invalid sleep time `{value}`, expected HH:MM")
-                })
-        };
+    pub(crate) fn resolve(self, id: &str, grace: TimeDelta) -> Result<SleepWindow, String> {
         let window = SleepWindow {
-            start: parse(&self.start)?,
-            end: parse(&self.end)?,
+            start: parse_clock_time(id, &self.start)?,
+            end: parse_clock_time(id, &self.end)?,
         };
 
         if grace >= window.duration() {
@@ -409,6 +613,12 @@ impl RawSleepWindow {
     }
 }
 
+pub(crate) fn parse_clock_time(id: &str, value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M"))
+        .map_err(|_| format!("eink display {id}: invalid time `{value}`, expected HH:MM"))
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct SleepWindow {
     pub start: NaiveTime,
@@ -445,6 +655,27 @@ impl SleepWindow {
     }
 }
 
+pub(crate) fn check_lead(id: &str, grace: TimeDelta, mode: &EinkModeConfig) -> Result<(), String> {
+    if let EinkModeConfig::Playlist { entries } = mode {
+        return entries
+            .iter()
+            .try_for_each(|entry| check_lead(id, grace, &entry.source));
+    }
+
+    if let Some(lead) = mode.lead()
+        && grace >= lead
+    {
+        return Err(format!(
+            "eink display {id}: grace `{}` must be shorter than the dashboard lead `{}`, \
+             or a display waking inside the grace is served a pre-render that has not run yet",
+            humanize(grace),
+            humanize(lead)
+        ));
+    }
+
+    Ok(())
+}
+
 #[derive(Debug, Clone, Deserialize, JsonSchema)]
 pub struct RawEinkDisplayBlock {
     name: String,
@@ -452,6 +683,8 @@ pub struct RawEinkDisplayBlock {
     mode: RawEinkMode,
     #[serde(default)]
     orientation: Option<Orientation>,
+    #[serde(default)]
+    panel: Panel,
     refresh: CronSchedule,
     #[serde(with = "time_delta_from_str")]
     #[schemars(with = "String")]
@@ -459,34 +692,28 @@ pub struct RawEinkDisplayBlock {
     #[serde(default)]
     sleep: Option<RawSleepWindow>,
     partial: RawPartialRefresh,
+    #[serde(default)]
+    dither: DitherConfig,
 }
 
 impl RawEinkDisplayBlock {
     pub(crate) fn resolve(self, id: &str) -> Result<EinkDisplaySettings, String> {
         let sleep = self.sleep.map(|s| s.resolve(id, self.grace)).transpose()?;
         let partial = self.partial.resolve(id)?;
-        let mode = self.mode.resolve();
-
-        if let Some(lead) = mode.lead()
-            && self.grace >= lead
-        {
-            return Err(format!(
-                "eink display {id}: grace `{}` must be shorter than the dashboard lead `{}`, \
-                 or a display waking inside the grace is served a pre-render that has not run yet",
-                humanize(self.grace),
-                humanize(lead)
-            ));
-        }
+        let mode = self.mode.resolve(id)?;
+        check_lead(id, self.grace, &mode)?;
 
         Ok(EinkDisplaySettings {
             name: self.name,
             firmware_version: self.firmware_version,
             mode,
             orientation: self.orientation.unwrap_or(Orientation::Portrait),
+            panel: self.panel,
             refresh: self.refresh,
             grace: self.grace,
             sleep,
             partial,
+            dither: self.dither,
         })
     }
 }
@@ -497,15 +724,17 @@ pub struct EinkDisplaySettings {
     pub firmware_version: String,
     pub mode: EinkModeConfig,
     pub orientation: Orientation,
+    pub panel: Panel,
     pub refresh: CronSchedule,
     pub grace: TimeDelta,
     pub sleep: Option<SleepWindow>,
     pub partial: PartialRefresh,
+    pub dither: DitherConfig,
 }
 
 impl EinkDisplaySettings {
     pub fn target_dims(&self) -> (u32, u32) {
-        self.orientation.target_dims()
+        self.panel.profile().target_dims(self.orientation)
     }
 
     pub fn orientation_str(&self) -> &'static str {
@@ -598,6 +827,29 @@ mod tests {
         assert!(block.resolve("hallway-epd").is_err());
     }
 
+    #[test]
+    fn a_display_picks_its_panel_and_renders_at_its_size() {
+        let default = display_block(&dashboard_mode("15m"), "5m", false)
+            .resolve("hallway-epd")
+            .unwrap();
+        let bedroom: RawEinkDisplayBlock = serde_yaml::from_str(&format!(
+            "name: Bedroom Display\nfirmware_version: v0.1.0\nrefresh: \"0 * * * *\"\n\
+             grace: 5m\norientation: landscape\npanel: bwr_7in5\n{}{PARTIAL}",
+            dashboard_mode("15m")
+        ))
+        .unwrap();
+        let bedroom = bedroom.resolve("bedroom-epd").unwrap();
+
+        assert_eq!(default.panel, Panel::Spectra6);
+        assert_eq!(default.target_dims(), (1200, 1600));
+        assert_eq!(bedroom.panel, Panel::Bwr7in5);
+        assert_eq!(bedroom.target_dims(), (800, 480));
+        assert!(
+            serde_yaml::from_str::<Panel>("trmnl_1bit").is_err(),
+            "trmnl panels come from a trmnl's bit depth"
+        );
+    }
+
     #[test]
     fn a_grace_is_parsed_from_a_duration_string() {
         let display = display_block(&dashboard_mode("15m"), "5m", true)
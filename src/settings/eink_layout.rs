@@ -0,0 +1,271 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+const MAX_GRID: u32 = 12;
+const DEFAULT_FORECAST_DAYS: usize = 4;
+const DEFAULT_AGENDA_LIMIT: usize = 6;
+const DEFAULT_BATTERY_LIMIT: usize = 6;
+
+/// A `native` dashboard: widgets placed on a grid of `columns` × `rows`
+/// equal cells and drawn in-process, with no browser in the loop.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct RawDashboardLayout {
+    columns: u32,
+    rows: u32,
+    widgets: Vec<RawPlacedWidget>,
+}
+
+/// A widget and the cells it covers: `column` and `row` are its top-left
+/// cell, counted from 0, and it spans `width` columns and `height` rows.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+struct RawPlacedWidget {
+    column: u32,
+    row: u32,
+    #[serde(default = "one")]
+    width: u32,
+    #[serde(default = "one")]
+    height: u32,
+    #[serde(flatten)]
+    widget: Widget,
+}
+
+fn one() -> u32 {
+    1
+}
+
+fn forecast_days() -> usize {
+    DEFAULT_FORECAST_DAYS
+}
+
+fn agenda_limit() -> usize {
+    DEFAULT_AGENDA_LIMIT
+}
+
+fn battery_limit() -> usize {
+    DEFAULT_BATTERY_LIMIT
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Widget {
+    /// The local time, with the date under it.
+    Clock,
+    /// The BOM forecast for `location`, a row per day.
+    Weather {
+        location: String,
+        #[serde(default = "forecast_days")]
+        days: usize,
+    },
+    /// Live solar generation and the day's, yesterday's and month's totals.
+    Solar,
+    /// The next firing of every enabled workflow on a `cron` or `sun` trigger.
+    Agenda {
+        #[serde(default = "agenda_limit")]
+        limit: usize,
+    },
+    /// The latest reading of each environment sensor, by entity id.
+    Sensors { sensors: Vec<String> },
+    /// The emptiest batteries first; those under `below` percent are
+    /// highlighted.
+    Batteries {
+        #[serde(default = "battery_limit")]
+        limit: usize,
+        #[serde(default)]
+        below: Option<f64>,
+    },
+}
+
+impl Widget {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Widget::Clock => "clock",
+            Widget::Weather { .. } => "weather",
+            Widget::Solar => "solar",
+            Widget::Agenda { .. } => "agenda",
+            Widget::Sensors { .. } => "sensors",
+            Widget::Batteries { .. } => "batteries",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GridCell {
+    pub column: u32,
+    pub row: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl GridCell {
+    fn overlaps(&self, other: &GridCell) -> bool {
+        self.column < other.column + other.width
+            && other.column < self.column + self.width
+            && self.row < other.row + other.height
+            && other.row < self.row + self.height
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlacedWidget {
+    pub cell: GridCell,
+    pub widget: Widget,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DashboardLayout {
+    pub name: String,
+    pub columns: u32,
+    pub rows: u32,
+    pub widgets: Vec<PlacedWidget>,
+}
+
+impl RawDashboardLayout {
+    pub(crate) fn resolve(self, name: &str) -> Result<DashboardLayout, String> {
+        for (axis, cells) in [("columns", self.columns), ("rows", self.rows)] {
+            if cells == 0 || cells > MAX_GRID {
+                return Err(format!(
+                    "eink layout {name}: {axis} must be between 1 and {MAX_GRID}, got {cells}"
+                ));
+            }
+        }
+
+        let mut widgets: Vec<PlacedWidget> = Vec::with_capacity(self.widgets.len());
+        for raw in self.widgets {
+            let kind = raw.widget.as_str();
+            let cell = GridCell {
+                column: raw.column,
+                row: raw.row,
+                width: raw.width,
+                height: raw.height,
+            };
+
+            if cell.width == 0 || cell.height == 0 {
+                return Err(format!(
+                    "eink layout {name}: {kind} widget must span at least one cell"
+                ));
+            }
+
+            if cell.column + cell.width > self.columns || cell.row + cell.height > self.rows {
+                return Err(format!(
+                    "eink layout {name}: {kind} widget at column {}, row {} runs off the \
+                     {}x{} grid",
+                    cell.column, cell.row, self.columns, self.rows
+                ));
+            }
+
+            if let Some(other) = widgets.iter().find(|placed| placed.cell.overlaps(&cell)) {
+                return Err(format!(
+                    "eink layout {name}: {kind} widget overlaps the {} widget",
+                    other.widget.as_str()
+                ));
+            }
+
+            widgets.push(PlacedWidget {
+                cell,
+                widget: raw.widget,
+            });
+        }
+
+        Ok(DashboardLayout {
+            name: name.to_owned(),
+            columns: self.columns,
+            rows: self.rows,
+            widgets,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn layout(yaml: &str) -> Result<DashboardLayout, String> {
+        serde_yaml::from_str::<RawDashboardLayout>(yaml)
+            .unwrap()
+            .resolve("kitchen")
+    }
+
+    #[test]
+    fn widgets_are_placed_on_the_grid_with_their_defaults() {
+        let layout = layout(
+            r#"
+columns: 2
+rows: 3
+widgets:
+  - { type: clock, column: 0, row: 0, width: 2 }
+  - { type: weather, column: 0, row: 1, location: perth }
+  - { type: agenda, column: 1, row: 1, height: 2, limit: 3 }
+  - { type: batteries, column: 0, row: 2, below: 20 }
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(layout.widgets.len(), 4);
+        assert_eq!(
+            layout.widgets[0].cell,
+            GridCell {
+                column: 0,
+                row: 0,
+                width: 2,
+                height: 1
+            }
+        );
+        assert_eq!(
+            layout.widgets[1].widget,
+            Widget::Weather {
+                location: "perth".to_owned(),
+                days: DEFAULT_FORECAST_DAYS
+            }
+        );
+        assert_eq!(layout.widgets[2].widget, Widget::Agenda { limit: 3 });
+        assert_eq!(
+            layout.widgets[3].widget,
+            Widget::Batteries {
+                limit: DEFAULT_BATTERY_LIMIT,
+                below: Some(20.0)
+            }
+        );
+    }
+
+    #[test]
+    fn a_widget_off_the_grid_is_rejected() {
+        let err = layout(
+            r#"
+columns: 2
+rows: 2
+widgets:
+  - { type: solar, column: 1, row: 0, width: 2 }
+"#,
+        )
+        .unwrap_err();
+
+        assert!(err.contains("runs off the 2x2 grid"), "{err}");
+    }
+
+    #[test]
+    fn overlapping_widgets_are_rejected() {
+        let err = layout(
+            r#"
+columns: 2
+rows: 2
+widgets:
+  - { type: clock, column: 0, row: 0, width: 2 }
+  - { type: solar, column: 1, row: 0, height: 2 }
+"#,
+        )
+        .unwrap_err();
+
+        assert!(
+            err.contains("solar widget overlaps the clock widget"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn an_empty_grid_is_rejected() {
+        let err = layout("columns: 0\nrows: 2\nwidgets: []\n").unwrap_err();
+
+        assert!(err.contains("columns must be between 1 and 12"), "{err}");
+    }
+}
@@ -0,0 +1,216 @@
+use chrono::{Datelike, NaiveDateTime, NaiveTime, TimeDelta, Weekday};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::device_registry::DeviceRegistry;
+use crate::mode::Mode;
+use crate::settings::eink::{EinkModeConfig, RawEinkMode, parse_clock_time};
+use crate::timedelta_format::time_delta_from_str;
+
+fn default_duration() -> TimeDelta {
+    TimeDelta::hours(1)
+}
+
+/// One source in a `playlist` and when it may play. An entry with no
+/// `between`, `days` or `when` is the fallback: it only plays while no
+/// scheduled entry does.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct RawPlaylistEntry {
+    source: RawEinkMode,
+    /// A local time window, e.g. `06:00`–`09:00`; it may wrap midnight.
+    #[serde(default)]
+    between: Option<RawTimeWindow>,
+    /// Days of the week the entry plays on, e.g. `[sat, sun]`.
+    #[serde(default)]
+    #[schemars(with = "Vec<String>")]
+    days: Vec<Weekday>,
+    /// How long the entry holds the display each time round when it shares
+    /// the rotation with others; a weight, in effect.
+    #[serde(default = "default_duration", with = "time_delta_from_str")]
+    #[schemars(with = "String")]
+    duration: TimeDelta,
+    /// House state that must all hold for the entry to play.
+    #[serde(default)]
+    when: Vec<PlaylistCondition>,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+struct RawTimeWindow {
+    start: String,
+    end: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PlaylistCondition {
+    Mode { mode: Mode, active: bool },
+    Presence { sensor: String, present: bool },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl TimeWindow {
+    pub fn contains(&self, now: NaiveTime) -> bool {
+        if self.start <= self.end {
+            now >= self.start && now < self.end
+        } else {
+            now >= self.start || now < self.end
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PlaylistEntry {
+    pub source: EinkModeConfig,
+    pub between: Option<TimeWindow>,
+    pub days: Vec<Weekday>,
+    pub duration: TimeDelta,
+    pub when: Vec<PlaylistCondition>,
+}
+
+impl PlaylistEntry {
+    /// Whether the entry only plays some of the time.
+    pub fn is_scheduled(&self) -> bool {
+        self.between.is_some() || !self.days.is_empty() || !self.when.is_empty()
+    }
+
+    /// Whether `now` (local) falls inside the entry's window and days; its
+    /// `when` conditions are the caller's to check.
+    pub fn in_schedule(&self, now: NaiveDateTime) -> bool {
+        self.between
+            .is_none_or(|window| window.contains(now.time()))
+            && (self.days.is_empty() || self.days.contains(&now.weekday()))
+    }
+}
+
+/// A playlist's `presence` conditions must name a registered presence sensor,
+/// by id or address, or their entry would quietly never play.
+pub(super) fn validate_sensors(registry: &DeviceRegistry) -> Result<(), String> {
+    let displays = registry
+        .eink_displays()
+        .iter()
+        .chain(registry.trmnl_displays());
+    for (address, display) in displays {
+        let EinkModeConfig::Playlist { entries } = &display.mode else {
+            continue;
+        };
+        for condition in entries.iter().flat_map(|entry| &entry.when) {
+            if let PlaylistCondition::Presence { sensor, .. } = condition
+                && registry
+                    .presence(registry.address_or_self(sensor))
+                    .is_none()
+            {
+                return Err(format!(
+                    "eink display {}: playlist names unknown presence sensor `{sensor}`",
+                    registry.id_for_address(address).unwrap_or(address)
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+impl RawPlaylistEntry {
+    pub(crate) fn resolve(self, id: &str) -> Result<PlaylistEntry, String> {
+        if matches!(self.source, RawEinkMode::Playlist { .. }) {
+            return Err(format!(
+                "eink display {id}: a playlist cannot play a playlist"
+            ));
+        }
+
+        if self.duration <= TimeDelta::zero() {
+            return Err(format!(
+                "eink display {id}: playlist entry duration must be positive"
+            ));
+        }
+
+        let between = self
+            .between
+            .map(|window| {
+                Ok::<_, String>(TimeWindow {
+                    start: parse_clock_time(id, &window.start)?,
+                    end: parse_clock_time(id, &window.end)?,
+                })
+            })
+            .transpose()?;
+
+        Ok(PlaylistEntry {
+            source: self.source.resolve(id)?,
+            between,
+            days: self.days,
+            duration: self.duration,
+            when: self.when,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+
+    fn entry(yaml: &str) -> Result<PlaylistEntry, String> {
+        serde_yaml::from_str::<RawPlaylistEntry>(yaml)
+            .unwrap()
+            .resolve("kitchen")
+    }
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        // 2026-10-19 is a Monday
+        NaiveDate::from_ymd_opt(2026, 10, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn an_entry_plays_inside_its_window_and_days() {
+        let weekend_mornings = entry(
+            r#"
+source: { name: album, album: cats }
+between: { start: "06:00", end: "09:00" }
+days: [sat, sun]
+duration: 30m
+"#,
+        )
+        .unwrap();
+
+        assert!(weekend_mornings.is_scheduled());
+        assert_eq!(weekend_mornings.duration, TimeDelta::minutes(30));
+        assert!(weekend_mornings.in_schedule(at(24, 6, 0)));
+        assert!(!weekend_mornings.in_schedule(at(24, 9, 0)));
+        assert!(!weekend_mornings.in_schedule(at(19, 7, 0)));
+    }
+
+    #[test]
+    fn a_window_may_wrap_midnight() {
+        let late =
+            entry("source: { name: album }\nbetween: { start: \"22:00\", end: \"02:00\" }\n")
+                .unwrap();
+
+        assert!(late.in_schedule(at(19, 23, 0)));
+        assert!(late.in_schedule(at(19, 1, 59)));
+        assert!(!late.in_schedule(at(19, 12, 0)));
+    }
+
+    #[test]
+    fn an_unconditional_entry_is_the_fallback() {
+        let fallback = entry("source: { name: album }\n").unwrap();
+
+        assert!(!fallback.is_scheduled());
+        assert!(fallback.in_schedule(at(19, 12, 0)));
+        assert_eq!(fallback.duration, TimeDelta::hours(1));
+    }
+
+    #[test]
+    fn a_nested_playlist_is_rejected() {
+        let err = entry("source: { name: playlist, entries: [] }\n").unwrap_err();
+
+        assert!(err.contains("cannot play a playlist"), "{err}");
+    }
+}
@@ -0,0 +1,152 @@
+//! Hot-swappable view of the resolved config.
+//!
+//! Actors, the GraphQL schema and the HTTP routes all hold the same
+//! [`LiveConfig`] and take a snapshot per message or request, so a reload is
+//! picked up by the next one without restarting anything. A snapshot is two
+//! `Arc` clones, cheap enough for every hot path.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, PoisonError, RwLock};
+
+use config::ConfigError;
+
+use crate::device_registry::DeviceRegistry;
+use crate::settings::SettingsContainer;
+
+#[derive(Clone)]
+struct Snapshot {
+    settings: SettingsContainer,
+    devices: DeviceRegistry,
+}
+
+#[derive(Clone)]
+pub struct LiveConfig {
+    current: Arc<RwLock<Snapshot>>,
+    dir: Arc<PathBuf>,
+}
+
+/// What a reload changed, for logging and for re-arming the schedules and
+/// subscriptions derived from the config.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, async_graphql::SimpleObject)]
+pub struct ConfigChange {
+    pub version: String,
+    pub added_workflows: Vec<String>,
+    pub removed_workflows: Vec<String>,
+    pub added_topics: Vec<String>,
+    pub removed_topics: Vec<String>,
+}
+
+fn diff<'a>(
+    before: impl Iterator<Item = &'a String>,
+    after: impl Iterator<Item = &'a String>,
+) -> (Vec<String>, Vec<String>) {
+    let before: HashSet<&String> = before.collect();
+    let after: HashSet<&String> = after.collect();
+
+    let mut added: Vec<String> = after.difference(&before).map(|s| (*s).clone()).collect();
+    let mut removed: Vec<String> = before.difference(&after).map(|s| (*s).clone()).collect();
+    added.sort_unstable();
+    removed.sort_unstable();
+
+    (added, removed)
+}
+
+impl ConfigChange {
+    fn between(before: &Snapshot, settings: &SettingsContainer, devices: &DeviceRegistry) -> Self {
+        let (added_workflows, removed_workflows) = diff(
+            before.settings.workflows.values().map(|w| &w.slug),
+            settings.workflows.values().map(|w| &w.slug),
+        );
+        let (added_topics, removed_topics) = diff(
+            before.devices.esphome_all_topics(),
+            devices.esphome_all_topics(),
+        );
+
+        Self {
+            version: settings.version.clone(),
+            added_workflows,
+            removed_workflows,
+            added_topics,
+            removed_topics,
+        }
+    }
+}
+
+impl LiveConfig {
+    /// `dir` is where the running config was loaded from, and where a reload
+    /// reads it again.
+    pub fn new(dir: PathBuf, settings: SettingsContainer, devices: DeviceRegistry) -> Self {
+        Self {
+            current: Arc::new(RwLock::new(Snapshot { settings, devices })),
+            dir: Arc::new(dir),
+        }
+    }
+
+    /// Load from `CONFIG_DIR`, falling back to the baked-in `./config` when the
+    /// override dir doesn't resolve.
+    pub fn load() -> Result<Self, ConfigError> {
+        let override_dir = PathBuf::from(
+            std::env::var("CONFIG_DIR").unwrap_or_else(|_| "/etc/home-gateway/config".to_string()),
+        );
+        let baked_dir = PathBuf::from("./config");
+
+        let (source, dir, (settings, registry)) =
+            match SettingsContainer::load_from_dir(&override_dir) {
+                Ok(loaded) => ("override", override_dir, loaded),
+                Err(e) => {
+                    tracing::warn!(
+                        config_dir = %override_dir.display(),
+                        error = %e,
+                        "failed to load config from override dir, falling back to baked-in config"
+                    );
+                    (
+                        "baked-in",
+                        baked_dir.clone(),
+                        SettingsContainer::load_from_dir(&baked_dir)?,
+                    )
+                }
+            };
+
+        tracing::info!(
+            source,
+            version = %settings.version,
+            "loaded config"
+        );
+
+        Ok(Self::new(dir, settings.into(), registry))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn settings(&self) -> SettingsContainer {
+        self.snapshot().settings
+    }
+
+    pub fn devices(&self) -> DeviceRegistry {
+        self.snapshot().devices
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        self.current
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Re-read the config dir through the same `RawSettings::resolve` path as
+    /// startup and swap it in. On any error the running config is kept as is.
+    pub fn reload(&self) -> Result<ConfigChange, ConfigError> {
+        let (settings, devices) = SettingsContainer::load_from_dir(&self.dir)?;
+        let settings = SettingsContainer::from(settings);
+
+        let mut current = self.current.write().unwrap_or_else(PoisonError::into_inner);
+        let devices = devices.keep_friendly_names(&current.devices);
+        let change = ConfigChange::between(&current, &settings, &devices);
+        *current = Snapshot { settings, devices };
+
+        Ok(change)
+    }
+}
@@ -2,12 +2,9 @@ use config::builder::{ConfigBuilder, DefaultState};
 use config::{Config, ConfigError, Environment, File, FileFormat};
 use schemars::JsonSchema;
 use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 use std::sync::Arc;
-use std::{
-    collections::{HashMap, HashSet},
-    path::PathBuf,
-};
 
 pub mod adhoc;
 pub mod alarm;
@@ -17,16 +14,20 @@ pub mod de;
 pub mod device;
 pub mod door;
 pub mod eink;
+pub mod eink_layout;
+pub mod eink_playlist;
 pub mod environment;
 pub mod home_assistant;
 pub mod jellyfin;
 pub mod light;
+pub mod live;
 pub mod location;
 pub mod media_player;
 pub mod notify;
 pub mod plant;
 pub mod presence;
 pub mod roborock;
+pub mod room;
 pub mod s3;
 pub mod solar;
 pub mod sun;
@@ -35,6 +36,7 @@ pub mod template;
 pub mod trigger;
 pub mod trmnl;
 pub mod valetudo;
+pub mod variable;
 pub mod watchdog;
 pub mod woolworths;
 pub mod workflow;
@@ -47,30 +49,35 @@ pub use bom::BomSettings;
 pub use device::{BatterySettings, DeviceWatchdog, RawDeviceWatchdog};
 pub use door::{ArmedDoorStates, DoorSettings};
 pub use eink::{
-    Album, DashboardView, EinkDisplaySettings, EinkGlobalSettings, EinkMode, EinkModeConfig,
-    Orientation, PaletteColor, PartialRefresh, RawEinkDisplayBlock, RedditFeed, RedditTimespan,
-    SleepWindow,
+    Album, ColorSpace, DashboardView, Dither, DitherAlgorithm, DitherConfig, EinkDisplaySettings,
+    EinkGlobalSettings, EinkMode, EinkModeConfig, Orientation, PaletteColor, Panel, PartialRefresh,
+    RawEinkDisplayBlock, RedditFeed, RedditTimespan, SleepWindow,
 };
+pub use eink_layout::{DashboardLayout, GridCell, PlacedWidget, Widget};
+pub use eink_playlist::{PlaylistCondition, PlaylistEntry, TimeWindow};
 pub use environment::{
     EnvironmentSensorSettings, EnvironmentSensorType, Metric, RawEnvironmentBlock,
 };
 pub use home_assistant::{EntitySettings, HomeAssistantSettings};
 pub use jellyfin::JellyfinSettings;
 pub use light::RawLightBlock;
+pub use live::{ConfigChange, LiveConfig};
 pub use location::LocationSettings;
 pub use media_player::{MediaPlayerSettings, RawMediaPlayerBlock};
-pub use notify::{NotifySource, NotifyTargets};
+pub use notify::{NotifyRef, NotifySource, NotifyTargets};
 pub use plant::{PlantSensorSettings, RawPlantBlock};
 pub use presence::{PresenceSensorType, PresenceSettings, RawPresenceBlock};
 pub use roborock::{RawRoborockBlock, RoborockField, RoborockSettings};
+pub use room::{Room, Rooms};
 pub use s3::S3Settings;
 pub use solar::SolarSettings;
 pub use sun::SunSettings;
-pub use switch::{RawSmartSwitchBlock, SwitchRole};
+pub use switch::{RawSmartSwitchBlock, SmartSwitchSettings, SwitchControl, SwitchRole};
 pub use template::TemplateString;
 pub use trigger::TriggerMatcher;
-pub use trmnl::{RawTrmnlBlock, TrmnlDeviceSettings, TrmnlSettings};
+pub use trmnl::{RawTrmnlBlock, TrmnlBitDepth, TrmnlByos, TrmnlDeviceSettings, TrmnlSettings};
 pub use valetudo::{RawValetudoBlock, ValetudoSettings};
+pub use variable::Variable;
 pub use watchdog::WatchdogSettings;
 pub use woolworths::WoolworthsSettings;
 pub use workflow::{Workflow, WorkflowSettings};
@@ -113,8 +120,11 @@ pub struct Settings {
     pub mqtt_password: String,
     pub unifi_webhook_secret: String,
     pub android_app_webhook_secret: String,
+    pub notify_targets: NotifyTargets,
+    pub rooms: Rooms,
     pub workflows: HashMap<String, Workflow>,
     pub workflow: WorkflowSettings,
+    pub variables: HashMap<String, Variable>,
     pub s3: S3Settings,
     pub watchdog: WatchdogSettings,
     pub oauth: Option<OAuthSettings>,
@@ -154,10 +164,14 @@ pub struct RawSettings {
     #[serde(default)]
     notify_targets: NotifyTargets,
     #[serde(default)]
+    rooms: Rooms,
+    #[serde(default)]
     devices: Vec<RawSensor>,
     zigbee_models: HashMap<String, RawZigbeeModelProfile>,
     #[serde(default)]
     workflows: Vec<Vec<Workflow>>,
+    #[serde(default)]
+    variables: HashMap<String, Variable>,
     s3: S3Settings,
     watchdog: WatchdogSettings,
     workflow: WorkflowSettings,
@@ -201,9 +215,11 @@ impl RawSettings {
             unifi_webhook_secret,
             android_app_webhook_secret,
             notify_targets,
+            rooms,
             devices,
             zigbee_models,
             workflows,
+            variables,
             s3,
             watchdog,
             workflow,
@@ -235,15 +251,41 @@ impl RawSettings {
             }
         }
 
+        for name in variables.keys() {
+            let valid = !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+            if !valid {
+                return Err(format!(
+                    "variable name `{name}` may only use letters, digits, `_` and `-`"
+                ));
+            }
+        }
+
+        notify::validate_targets(&notify_targets)?;
+
         let registry = DeviceRegistry::build(devices, &notify_targets, zigbee_models)?;
         let aliases = registry.aliases();
+        room::validate_devices(&rooms, &registry)?;
+        eink_playlist::validate_sensors(&registry)?;
 
         let mut resolved = HashMap::new();
         let mut slugs = HashSet::new();
         for mut workflow in workflows.into_iter().flatten() {
             workflow.resolve_devices(aliases)?;
             workflow.validate_capabilities(&registry)?;
+            workflow.validate_rooms(&rooms)?;
+            workflow.validate_params()?;
+            workflow.validate_templates(&registry)?;
+            workflow.validate_variables(&variables)?;
+            workflow.validate_notify(&notify_targets)?;
+            workflow.validate_control_flow()?;
+            workflow.validate_run_mode()?;
             if let Some(trigger) = workflow.on() {
+                trigger
+                    .validate_held()
+                    .map_err(|e| format!("workflow '{}': {e}", workflow.name))?;
                 let available = trigger.available_vars();
                 for var in workflow.template_placeholders() {
                     if !available.contains(&var) {
@@ -291,7 +333,10 @@ impl RawSettings {
                 mqtt_password,
                 unifi_webhook_secret,
                 android_app_webhook_secret,
+                notify_targets,
+                rooms,
                 workflows: resolved,
+                variables,
                 s3,
                 watchdog,
                 workflow,
@@ -307,7 +352,7 @@ impl RawSettings {
                 jellyfin,
                 solar,
                 bom,
-                eink_display: eink_display.resolve(),
+                eink_display: eink_display.resolve()?,
                 adhoc,
             },
             registry,
@@ -357,37 +402,6 @@ impl SettingsContainer {
 
         Self::build(config)
     }
-
-    pub fn new() -> Result<(Self, DeviceRegistry), ConfigError> {
-        let override_dir =
-            std::env::var("CONFIG_DIR").unwrap_or_else(|_| "/etc/home-gateway/config".to_string());
-        let baked_dir = PathBuf::from("./config");
-
-        let (source, (settings, registry)) = match Self::load_from_dir(Path::new(&override_dir)) {
-            Ok(loaded) => ("override", loaded),
-            Err(e) => {
-                tracing::warn!(
-                    config_dir = %override_dir,
-                    error = %e,
-                    "failed to load config from override dir, falling back to baked-in config"
-                );
-                ("baked-in", Self::load_from_dir(&baked_dir)?)
-            }
-        };
-
-        tracing::info!(
-            source,
-            version = %settings.version,
-            "loaded config"
-        );
-
-        Ok((
-            Self {
-                inner: Arc::new(settings),
-            },
-            registry,
-        ))
-    }
 }
 
 impl From<Settings> for SettingsContainer {
@@ -478,6 +492,47 @@ ts011f_plug:
         assert!(err.contains("`home_assistant` transport"), "{err}");
     }
 
+    #[test]
+    fn a_home_assistant_smart_switch_is_controlled_in_its_entity_domain() {
+        let registry = build_devices(
+            r#"
+- id: heater
+  transport: home_assistant
+  address: switch.bedroom_heater
+  roles:
+    - type: smart_switch
+      config: { name: Bedroom Heater }
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            registry
+                .smart_switch("switch.bedroom_heater")
+                .map(|s| s.control.clone()),
+            Some(SwitchControl::HomeAssistant {
+                domain: "switch".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn a_home_assistant_smart_switch_cannot_act_as_a_light() {
+        let err = build_devices(
+            r#"
+- id: heater
+  transport: home_assistant
+  address: switch.bedroom_heater
+  roles:
+    - type: smart_switch
+      config: { name: Bedroom Heater, as: light }
+"#,
+        )
+        .unwrap_err();
+
+        assert!(err.contains("cannot be declared `as:`"), "{err}");
+    }
+
     #[test]
     fn a_media_player_address_that_is_not_an_entity_id_is_rejected() {
         let err = build_devices(
@@ -601,6 +656,41 @@ ts011f_plug:
         assert!(err.contains("does not support ColourTemp"), "{err}");
     }
 
+    #[test]
+    fn switch_step_only_targets_smart_switches() {
+        let registry = build_devices(
+            r#"
+- id: living-room-table-lamp
+  transport: zigbee
+  model: ts011f_plug
+  address: "0xa4c1389fe5cea26e"
+  roles:
+    - type: smart_switch
+      config: { name: Living Room Table Lamp }
+- id: small-switch
+  transport: esphome
+  address: small-switch
+  roles:
+    - type: control_switch
+"#,
+        )
+        .unwrap();
+
+        let step = |device: &str| -> workflow::Step {
+            serde_yaml::from_str(&format!("type: switch\ndevice: {device}\nstate: TOGGLE\n"))
+                .unwrap()
+        };
+
+        step("living-room-table-lamp")
+            .validate_capabilities(&registry)
+            .unwrap();
+
+        let err = step("small-switch")
+            .validate_capabilities(&registry)
+            .unwrap_err();
+        assert!(err.contains("is not a smart switch"), "{err}");
+    }
+
     #[test]
     fn tmp_hallway_epd_registers_battery() {
         let secrets = r#"
@@ -763,7 +853,7 @@ android_app_webhook_secret: x
         let lamp = "0xa4c1389fe5cea26e";
         assert_eq!(registry.address_or_self("living-room-table-lamp"), lamp);
         assert_eq!(
-            registry.smart_switch(lamp).map(String::as_str),
+            registry.smart_switch(lamp).map(|s| s.name.as_str()),
             Some("Living Room Table Lamp")
         );
         assert_eq!(
@@ -968,6 +1058,335 @@ workflows:
         assert!(err.contains("does-not-exist"), "{err}");
     }
 
+    #[test]
+    fn a_malformed_template_is_rejected() {
+        let raw: RawSettings = serde_yaml::from_str(
+            r#"
+api_key: x
+database_url: x
+zigbee_models: {}
+mqtt_url: x
+mqtt_username: x
+mqtt_password: x
+unifi_webhook_secret: x
+android_app_webhook_secret: x
+s3: { bucket: b, region: r }
+watchdog: { enabled: false, timeout: 30m, check_interval: 5m, realert_after: 6h }
+workflow: { workers: 12 }
+location: { latitude: 0.0, longitude: 0.0 }
+sun: { catch_up_within: 2h }
+bom: { url: "http://bom" }
+adhoc: { recheck_interval: 15m }
+workflows:
+  - - name: Greeting
+      slug: greeting
+      run:
+        - type: home_assistant
+          call_service: notify.notify
+          data:
+            message: "It's ${sensor.outside.temperature | round(} out"
+"#,
+        )
+        .unwrap();
+
+        let err = raw.resolve().unwrap_err();
+        assert!(err.contains("invalid template"), "{err}");
+    }
+
+    #[test]
+    fn a_template_lookup_must_name_a_registered_device() {
+        let registry = build_devices(
+            r#"
+- id: study-node
+  transport: esphome
+  address: apollo-study
+  roles:
+    - type: environment
+      config:
+        id: study
+        name: Study
+        entities:
+          temperature: study_node_temperature
+      capabilities: [temperature]
+"#,
+        )
+        .unwrap();
+        let workflow = |message: &str| {
+            serde_yaml::from_str::<Workflow>(&format!(
+                r#"
+name: Study
+slug: study
+run:
+  - type: home_assistant
+    call_service: notify.notify
+    data: {{ message: "{message}" }}
+"#
+            ))
+            .unwrap()
+        };
+
+        assert!(
+            workflow("${sensor.study-node.temperature}")
+                .validate_templates(&registry)
+                .is_ok()
+        );
+
+        let err = workflow("${sensor.study-nod.temperature}")
+            .validate_templates(&registry)
+            .unwrap_err();
+        assert!(
+            err.contains("unknown environment sensor `study-nod`"),
+            "{err}"
+        );
+
+        // the right device, but not a door
+        assert!(
+            workflow("${door.study-node}")
+                .validate_templates(&registry)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn notify_targets_are_validated_and_workflow_references_checked() {
+        let config = |targets: &str, notify: &str| {
+            format!(
+                r#"
+api_key: x
+database_url: x
+zigbee_models: {{}}
+mqtt_url: x
+mqtt_username: x
+mqtt_password: x
+unifi_webhook_secret: x
+android_app_webhook_secret: x
+s3: {{ bucket: b, region: r }}
+watchdog: {{ enabled: false, timeout: 30m, check_interval: 5m, realert_after: 6h }}
+workflow: {{ workers: 12 }}
+location: {{ latitude: 0.0, longitude: 0.0 }}
+sun: {{ catch_up_within: 2h }}
+bom: {{ url: "http://bom" }}
+adhoc: {{ recheck_interval: 15m }}
+notify_targets: {targets}
+workflows:
+  - - name: Alert
+      slug: alert
+      run:
+        - type: notify
+          notify: {notify}
+          message: hello
+"#
+            )
+        };
+        let resolve = |targets: &str, notify: &str| {
+            serde_yaml::from_str::<RawSettings>(&config(targets, notify))
+                .unwrap()
+                .resolve()
+        };
+
+        let phone = "{ phone: { type: ntfy, topic: house } }";
+        assert!(resolve(phone, "phone").is_ok());
+
+        let err = resolve(phone, "pager").unwrap_err();
+        assert!(err.contains("unknown notify target: pager"), "{err}");
+
+        let err = resolve(
+            "{ phone: { type: ntfy, url: 'ntfy.local', topic: house } }",
+            "phone",
+        )
+        .unwrap_err();
+        assert!(err.contains("notify target 'phone'"), "{err}");
+    }
+
+    #[test]
+    fn a_workflow_using_an_undeclared_variable_is_rejected() {
+        let raw: RawSettings = serde_yaml::from_str(
+            r#"
+api_key: x
+database_url: x
+zigbee_models: {}
+mqtt_url: x
+mqtt_username: x
+mqtt_password: x
+unifi_webhook_secret: x
+android_app_webhook_secret: x
+s3: { bucket: b, region: r }
+watchdog: { enabled: false, timeout: 30m, check_interval: 5m, realert_after: 6h }
+workflow: { workers: 12 }
+location: { latitude: 0.0, longitude: 0.0 }
+sun: { catch_up_within: 2h }
+bom: { url: "http://bom" }
+adhoc: { recheck_interval: 15m }
+variables:
+  door_opens: { type: number }
+workflows:
+  - - name: Counter
+      slug: counter
+      run:
+        - type: increment_variable
+          variable: door_openings
+"#,
+        )
+        .unwrap();
+
+        let err = raw.resolve().unwrap_err();
+        assert!(err.contains("unknown variable `door_openings`"), "{err}");
+    }
+
+    #[test]
+    fn only_number_variables_increment() {
+        let raw: RawSettings = serde_yaml::from_str(
+            r#"
+api_key: x
+database_url: x
+zigbee_models: {}
+mqtt_url: x
+mqtt_username: x
+mqtt_password: x
+unifi_webhook_secret: x
+android_app_webhook_secret: x
+s3: { bucket: b, region: r }
+watchdog: { enabled: false, timeout: 30m, check_interval: 5m, realert_after: 6h }
+workflow: { workers: 12 }
+location: { latitude: 0.0, longitude: 0.0 }
+sun: { catch_up_within: 2h }
+bom: { url: "http://bom" }
+adhoc: { recheck_interval: 15m }
+variables:
+  bins_acknowledged: { type: timestamp }
+workflows:
+  - - name: Counter
+      slug: counter
+      run:
+        - type: increment_variable
+          variable: bins_acknowledged
+"#,
+        )
+        .unwrap();
+
+        let err = raw.resolve().unwrap_err();
+        assert!(err.contains("needs a number"), "{err}");
+    }
+
+    #[test]
+    fn a_device_in_an_undeclared_room_is_rejected() {
+        let raw: RawSettings = serde_yaml::from_str(
+            r#"
+api_key: x
+database_url: x
+zigbee_models: {}
+mqtt_url: x
+mqtt_username: x
+mqtt_password: x
+unifi_webhook_secret: x
+android_app_webhook_secret: x
+s3: { bucket: b, region: r }
+watchdog: { enabled: false, timeout: 30m, check_interval: 5m, realert_after: 6h }
+workflow: { workers: 12 }
+location: { latitude: 0.0, longitude: 0.0 }
+sun: { catch_up_within: 2h }
+bom: { url: "http://bom" }
+adhoc: { recheck_interval: 15m }
+rooms:
+  living-room: { name: Living Room }
+devices:
+  - id: bedroom-mtr-1
+    room: bedrom
+    transport: esphome
+    address: apollo-mtr-1-bedroom
+    roles:
+      - type: presence
+        config: { name: Bedroom Motion, motion_entity: [ld2450_presence] }
+"#,
+        )
+        .unwrap();
+
+        let err = raw.resolve().unwrap_err();
+        assert!(
+            err.contains("bedroom-mtr-1 is in undeclared room `bedrom`"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn a_workflow_in_an_undeclared_room_is_rejected() {
+        let raw: RawSettings = serde_yaml::from_str(
+            r#"
+api_key: x
+database_url: x
+zigbee_models: {}
+mqtt_url: x
+mqtt_username: x
+mqtt_password: x
+unifi_webhook_secret: x
+android_app_webhook_secret: x
+s3: { bucket: b, region: r }
+watchdog: { enabled: false, timeout: 30m, check_interval: 5m, realert_after: 6h }
+workflow: { workers: 12 }
+location: { latitude: 0.0, longitude: 0.0 }
+sun: { catch_up_within: 2h }
+bom: { url: "http://bom" }
+adhoc: { recheck_interval: 15m }
+rooms:
+  living-room: { name: Living Room }
+workflows:
+  - - name: Lights out
+      slug: lights-out
+      on: { type: room, room: living-room, occupied: false }
+      run:
+        - type: scene
+          run:
+            - type: room_lights
+              room: living-room
+              state: OFF
+              when: { type: room_occupied, room: bedroom, occupied: false }
+"#,
+        )
+        .unwrap();
+
+        let err = raw.resolve().unwrap_err();
+        assert!(err.contains("unknown room: bedroom"), "{err}");
+    }
+
+    #[test]
+    fn room_lights_checks_every_light_in_the_room() {
+        let registry = build_devices(
+            r#"
+- id: living-room-table-lamp
+  room: living-room
+  transport: zigbee
+  model: ts011f_plug
+  address: "0xa4c1389fe5cea26e"
+  roles:
+    - type: smart_switch
+      config: { name: Living Room Table Lamp, as: light }
+"#,
+        )
+        .unwrap();
+
+        let step = |room: &str, state: &str| -> workflow::Step {
+            serde_yaml::from_str(&format!(
+                "type: room_lights\nroom: {room}\nstate: {state}\nvalue: 50\n"
+            ))
+            .unwrap()
+        };
+
+        step("living-room", "OFF")
+            .validate_capabilities(&registry)
+            .unwrap();
+        step("bedroom", "SET_BRIGHTNESS")
+            .validate_capabilities(&registry)
+            .unwrap();
+
+        let err = step("living-room", "SET_BRIGHTNESS")
+            .validate_capabilities(&registry)
+            .unwrap_err();
+        assert!(
+            err.contains("living-room-table-lamp in room living-room does not support Brightness"),
+            "{err}"
+        );
+    }
+
     #[test]
     fn run_workflow_accepts_a_known_target() {
         let raw: RawSettings = serde_yaml::from_str(
@@ -1134,6 +1553,205 @@ devices:
         assert_eq!(display.mode.view(), None);
     }
 
+    #[test]
+    fn eink_display_native_mode_resolves() {
+        let raw: RawSettings = serde_yaml::from_str(
+            r#"
+api_key: x
+database_url: x
+zigbee_models: {}
+mqtt_url: x
+mqtt_username: x
+mqtt_password: x
+unifi_webhook_secret: x
+android_app_webhook_secret: x
+s3: { bucket: b, region: r }
+watchdog: { enabled: false, timeout: 30m, check_interval: 5m, realert_after: 6h }
+workflow: { workers: 12 }
+location: { latitude: 0.0, longitude: 0.0 }
+sun: { catch_up_within: 2h }
+bom: { url: "http://bom" }
+adhoc: { recheck_interval: 15m }
+eink_display:
+  layouts:
+    kitchen:
+      columns: 2
+      rows: 2
+      widgets:
+        - { type: clock, column: 0, row: 0, width: 2 }
+        - { type: sensors, column: 0, row: 1, sensors: [living_room] }
+        - { type: batteries, column: 1, row: 1 }
+devices:
+  - id: epd
+    transport: eink_display_firmware
+    address: "abc123"
+    roles:
+      - type: eink_display_firmware
+        config:
+          name: Test Display
+          firmware_version: v0.1.0
+          refresh: "0 * * * *"
+          grace: 10m
+          partial:
+            enabled: false
+            max_area_pct: 30
+            max_consecutive: 5
+          mode:
+            name: native
+            layout: kitchen
+"#,
+        )
+        .unwrap();
+
+        let (settings, registry) = raw.resolve().unwrap();
+        let display = registry.eink_display("abc123").expect("display resolved");
+
+        assert_eq!(display.mode.name(), crate::settings::EinkMode::Native);
+        assert_eq!(display.mode.layout(), Some("kitchen"));
+        assert_eq!(display.mode.lead(), None);
+
+        let layout = settings
+            .eink_display
+            .layout("kitchen")
+            .expect("layout resolved");
+        assert_eq!(layout.widgets.len(), 3);
+        assert_eq!(
+            layout.widgets[1].widget,
+            crate::settings::Widget::Sensors {
+                sensors: vec!["living_room".to_owned()]
+            }
+        );
+    }
+
+    #[test]
+    fn eink_display_playlist_mode_resolves() {
+        let raw: RawSettings = serde_yaml::from_str(
+            r#"
+api_key: x
+database_url: x
+zigbee_models: {}
+mqtt_url: x
+mqtt_username: x
+mqtt_password: x
+unifi_webhook_secret: x
+android_app_webhook_secret: x
+s3: { bucket: b, region: r }
+watchdog: { enabled: false, timeout: 30m, check_interval: 5m, realert_after: 6h }
+workflow: { workers: 12 }
+location: { latitude: 0.0, longitude: 0.0 }
+sun: { catch_up_within: 2h }
+bom: { url: "http://bom" }
+adhoc: { recheck_interval: 15m }
+devices:
+  - id: epd
+    transport: eink_display_firmware
+    address: "abc123"
+    roles:
+      - type: eink_display_firmware
+        config:
+          name: Test Display
+          firmware_version: v0.1.0
+          refresh: "0 * * * *"
+          grace: 10m
+          partial:
+            enabled: false
+            max_area_pct: 30
+            max_consecutive: 5
+          mode:
+            name: playlist
+            entries:
+              - source: { name: dashboard, view: weather, settle: 10s, lead: 15m }
+                between: { start: "06:00", end: "09:00" }
+              - source: { name: reddit, subreddit: EarthPorn, timespan: day, limit: 25 }
+                days: [sat, sun]
+                when: [{ type: mode, mode: away, active: false }]
+              - source: { name: album, album: cats }
+                duration: 2h
+"#,
+        )
+        .unwrap();
+
+        let (_, registry) = raw.resolve().unwrap();
+        let display = registry.eink_display("abc123").expect("display resolved");
+
+        assert_eq!(display.mode.name(), crate::settings::EinkMode::Playlist);
+        let crate::settings::EinkModeConfig::Playlist { entries } = &display.mode else {
+            panic!("expected a playlist, got {:?}", display.mode);
+        };
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].source.view(), Some("weather"));
+        assert_eq!(
+            entries[1].days,
+            vec![chrono::Weekday::Sat, chrono::Weekday::Sun]
+        );
+        assert_eq!(
+            entries[1].when,
+            vec![crate::settings::PlaylistCondition::Mode {
+                mode: crate::mode::Mode::Away,
+                active: false
+            }]
+        );
+        assert_eq!(entries[2].source.album(), Some("cats"));
+        assert_eq!(entries[2].duration, chrono::TimeDelta::hours(2));
+        assert!(!entries[2].is_scheduled());
+    }
+
+    #[test]
+    fn eink_display_playlist_rejects_unknown_presence_sensors() {
+        let playlist = |sensor: &str| {
+            serde_yaml::from_str::<RawSettings>(&format!(
+                r#"
+api_key: x
+database_url: x
+zigbee_models: {{}}
+mqtt_url: x
+mqtt_username: x
+mqtt_password: x
+unifi_webhook_secret: x
+android_app_webhook_secret: x
+s3: {{ bucket: b, region: r }}
+watchdog: {{ enabled: false, timeout: 30m, check_interval: 5m, realert_after: 6h }}
+workflow: {{ workers: 12 }}
+location: {{ latitude: 0.0, longitude: 0.0 }}
+sun: {{ catch_up_within: 2h }}
+bom: {{ url: "http://bom" }}
+adhoc: {{ recheck_interval: 15m }}
+devices:
+  - id: epd
+    transport: eink_display_firmware
+    address: "abc123"
+    roles:
+      - type: eink_display_firmware
+        config:
+          name: Test Display
+          firmware_version: v0.1.0
+          refresh: "0 * * * *"
+          grace: 10m
+          partial:
+            enabled: false
+            max_area_pct: 30
+            max_consecutive: 5
+          mode:
+            name: playlist
+            entries:
+              - source: {{ name: album, album: cats }}
+                when: [{{ type: presence, sensor: {sensor}, present: true }}]
+"#
+            ))
+            .unwrap()
+        };
+
+        let err = playlist("hallway-motion").resolve().unwrap_err();
+        assert!(
+            err.contains("unknown presence sensor `hallway-motion`"),
+            "{err}"
+        );
+
+        // registered, but not as a presence sensor
+        let err = playlist("epd").resolve().unwrap_err();
+        assert!(err.contains("unknown presence sensor `epd`"), "{err}");
+    }
+
     #[test]
     fn eink_display_defaults_resolve() {
         let raw: RawSettings = serde_yaml::from_str(
@@ -1188,6 +1806,67 @@ devices:
         );
     }
 
+    #[test]
+    fn a_byos_trmnl_is_served_like_an_eink_display() {
+        let raw = |address: &str, bit_depth: u8| {
+            serde_yaml::from_str::<RawSettings>(&format!(
+                r#"
+api_key: x
+database_url: x
+zigbee_models: {{}}
+mqtt_url: x
+mqtt_username: x
+mqtt_password: x
+unifi_webhook_secret: x
+android_app_webhook_secret: x
+s3: {{ bucket: b, region: r }}
+watchdog: {{ enabled: false, timeout: 30m, check_interval: 5m, realert_after: 6h }}
+workflow: {{ workers: 12 }}
+location: {{ latitude: 0.0, longitude: 0.0 }}
+sun: {{ catch_up_within: 2h }}
+bom: {{ url: "http://bom" }}
+adhoc: {{ recheck_interval: 15m }}
+devices:
+  - id: desk-trmnl
+    transport: trmnl
+    address: "{address}"
+    roles:
+      - type: trmnl
+        config:
+          name: Desk TRMNL
+          byos:
+            refresh: "*/15 * * * *"
+            grace: 2m
+            bit_depth: {bit_depth}
+            mode:
+              name: album
+"#
+            ))
+        };
+
+        let (_, registry) = raw("94:A9:90:CF:83:84", 2).unwrap().resolve().unwrap();
+        let trmnl = registry
+            .byos_trmnl("94a990cf8384")
+            .expect("found by its MAC however it is spelled");
+        let display = registry
+            .eink_display("desk-trmnl")
+            .expect("display resolved");
+
+        assert_eq!(trmnl.id, "desk-trmnl");
+        assert!(
+            !trmnl.byos.as_ref().unwrap().allow_setup,
+            "re-pairing is opt-in"
+        );
+        assert_eq!(display.panel, crate::settings::Panel::Trmnl2Bit);
+        assert_eq!(display.target_dims(), (800, 480));
+        assert_eq!(display.mode.name(), crate::settings::EinkMode::Album);
+        assert_eq!(display.orientation_str(), "landscape");
+        assert!(!display.partial.enabled);
+
+        assert!(raw("653VZN", 1).unwrap().resolve().is_err());
+        assert!(raw("94:A9:90:CF:83:84", 8).is_err());
+    }
+
     #[test]
     fn load_from_dir_errors_on_missing_dir() {
         let result = SettingsContainer::load_from_dir(Path::new("./does-not-exist"));
@@ -1,15 +1,394 @@
+use chrono::{NaiveTime, TimeDelta};
+use lettre::message::Mailbox;
+use reqwest::Url;
 use schemars::JsonSchema;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
 
+use super::SleepWindow;
+use crate::timedelta_format::{option_time_delta_from_str, time_delta_from_str};
+
 /// Named notify targets (`name -> source`) declared under `notify_targets:`.
 pub type NotifyTargets = HashMap<String, NotifySource>;
 
+pub const DEFAULT_TITLE: &str = "Home Gateway";
+
 #[derive(Debug, Deserialize, Clone, JsonSchema)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum NotifySource {
-    /// Push notification to all registered HomeGateway Android devices.
-    AndroidApp,
+    /// Push notification to all registered HomeGateway Android devices. Only
+    /// the title applies; FCM has no priority or tags here.
+    AndroidApp {
+        #[serde(flatten)]
+        options: NotifyOptions,
+    },
+    /// A message on an [ntfy](https://ntfy.sh) topic, on `ntfy.sh` unless
+    /// `url` points at a self-hosted server.
+    Ntfy {
+        #[serde(default = "ntfy_url")]
+        url: String,
+        topic: String,
+        /// Access token, for protected topics.
+        #[serde(default)]
+        token: Option<String>,
+        #[serde(flatten)]
+        options: NotifyOptions,
+    },
+    /// A message to a Gotify server, authenticated with an application
+    /// `token`. Gotify has no tags.
+    Gotify {
+        url: String,
+        token: String,
+        #[serde(flatten)]
+        options: NotifyOptions,
+    },
+    /// An HTTP request to any endpoint. `body` is a JSON template rendered
+    /// with `${title}`, `${message}`, `${priority}` and `${tags}`; without
+    /// one, those four are sent as a JSON object.
+    Webhook {
+        url: String,
+        #[serde(default)]
+        method: WebhookMethod,
+        #[serde(default)]
+        headers: HashMap<String, String>,
+        #[serde(default)]
+        body: Option<serde_json::Value>,
+        #[serde(flatten)]
+        options: NotifyOptions,
+    },
+    /// A plain-text email, with the title as its subject and the priority as
+    /// its `X-Priority` header. Tags are not sent.
+    Email {
+        smtp: SmtpSettings,
+        from: String,
+        to: Vec<String>,
+        #[serde(flatten)]
+        options: NotifyOptions,
+    },
+    /// A JSON object of `title`, `message`, `priority` and `tags` published to
+    /// an MQTT topic on the gateway's broker, for a display or another
+    /// automation system to pick up.
+    Mqtt {
+        topic: String,
+        #[serde(flatten)]
+        options: NotifyOptions,
+    },
+}
+
+fn ntfy_url() -> String {
+    "https://ntfy.sh".to_owned()
+}
+
+/// Presentation and delivery policy shared by every target.
+#[derive(Debug, Deserialize, Clone, Default, JsonSchema)]
+pub struct NotifyOptions {
+    /// Defaults to `Home Gateway`.
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub priority: NotifyPriority,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// When to hold back or collapse messages. Everything is sent by default.
+    #[serde(default)]
+    pub policy: NotifyPolicy,
+}
+
+/// Filters applied before a message reaches the target, in order: quiet
+/// hours, then duplicates, then the rate limit. Suppressed messages are
+/// recorded either way.
+#[derive(Debug, Deserialize, Clone, Default, JsonSchema)]
+pub struct NotifyPolicy {
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHours>,
+    /// Drop a message identical to one sent to this target within the window,
+    /// e.g. `15m`.
+    #[serde(default, with = "option_time_delta_from_str")]
+    #[schemars(with = "Option<String>")]
+    pub dedup: Option<TimeDelta>,
+    #[serde(default)]
+    pub rate_limit: Option<RateLimit>,
+    /// Hold messages suppressed by quiet hours or the rate limit and send them
+    /// as one summary once the target can be reached again.
+    #[serde(default)]
+    pub digest: bool,
+}
+
+/// Hours (Perth time) during which nothing is sent, e.g. `22:00` to `07:00`,
+/// and/or whenever night mode is on.
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
+pub struct QuietHours {
+    #[serde(default, deserialize_with = "clock_time")]
+    #[schemars(with = "Option<String>")]
+    pub start: Option<NaiveTime>,
+    #[serde(default, deserialize_with = "clock_time")]
+    #[schemars(with = "Option<String>")]
+    pub end: Option<NaiveTime>,
+    /// Also quiet while `Mode::Night` is active.
+    #[serde(default)]
+    pub night_mode: bool,
+}
+
+impl QuietHours {
+    pub fn window(&self) -> Option<SleepWindow> {
+        Some(SleepWindow {
+            start: self.start?,
+            end: self.end?,
+        })
+    }
+
+    pub fn contains(&self, now: NaiveTime, night: bool) -> bool {
+        (self.night_mode && night) || self.window().is_some_and(|w| w.contains(now))
+    }
+}
+
+/// At most `count` messages from one source per `per`, e.g. 3 an hour for a
+/// flapping sensor's workflow.
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
+pub struct RateLimit {
+    pub count: u32,
+    #[serde(with = "time_delta_from_str")]
+    #[schemars(with = "String")]
+    pub per: TimeDelta,
+}
+
+impl NotifyPolicy {
+    fn validate(&self) -> Result<(), String> {
+        if let Some(quiet) = &self.quiet_hours {
+            match (quiet.start, quiet.end) {
+                (Some(start), Some(end)) if start == end => {
+                    return Err("quiet hours start and end are the same".to_owned());
+                }
+                (Some(_), Some(_)) => {}
+                (None, None) if quiet.night_mode => {}
+                (None, None) => {
+                    return Err("quiet hours need a start and end, or night_mode".to_owned());
+                }
+                _ => return Err("quiet hours need both a start and an end".to_owned()),
+            }
+        }
+        if self.dedup.is_some_and(|window| window <= TimeDelta::zero()) {
+            return Err("dedup window must be positive".to_owned());
+        }
+        if let Some(limit) = &self.rate_limit
+            && (limit.count == 0 || limit.per <= TimeDelta::zero())
+        {
+            return Err("rate limit needs a positive count and period".to_owned());
+        }
+
+        Ok(())
+    }
+}
+
+/// `HH:MM` or `HH:MM:SS`.
+fn clock_time<'de, D>(deserializer: D) -> Result<Option<NaiveTime>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let Some(value) = Option::<String>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+
+    NaiveTime::parse_from_str(&value, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(&value, "%H:%M"))
+        .map(Some)
+        .map_err(|_| serde::de::Error::custom(format!("invalid time `{value}`, expected HH:MM")))
+}
+
+impl NotifyOptions {
+    pub fn title(&self) -> &str {
+        self.title.as_deref().unwrap_or(DEFAULT_TITLE)
+    }
+}
+
+/// ntfy's five priority levels, mapped onto each channel's own scale.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifyPriority {
+    Min,
+    Low,
+    #[default]
+    Default,
+    High,
+    Urgent,
+}
+
+impl NotifyPriority {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotifyPriority::Min => "min",
+            NotifyPriority::Low => "low",
+            NotifyPriority::Default => "default",
+            NotifyPriority::High => "high",
+            NotifyPriority::Urgent => "urgent",
+        }
+    }
+
+    /// ntfy's `1`-`5`.
+    pub fn ntfy(&self) -> u8 {
+        match self {
+            NotifyPriority::Min => 1,
+            NotifyPriority::Low => 2,
+            NotifyPriority::Default => 3,
+            NotifyPriority::High => 4,
+            NotifyPriority::Urgent => 5,
+        }
+    }
+
+    /// Gotify's `0`-`10`, where Android clients pop up from `8`.
+    pub fn gotify(&self) -> u8 {
+        match self {
+            NotifyPriority::Min => 0,
+            NotifyPriority::Low => 2,
+            NotifyPriority::Default => 5,
+            NotifyPriority::High => 8,
+            NotifyPriority::Urgent => 10,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, JsonSchema)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum WebhookMethod {
+    #[default]
+    Post,
+    Put,
+}
+
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
+pub struct SmtpSettings {
+    pub host: String,
+    /// Defaults to the port for `tls`: 465, 587 or 25.
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub tls: SmtpTls,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpTls {
+    /// Implicit TLS from the first byte.
+    Tls,
+    /// Plain connection upgraded with `STARTTLS`, which must succeed.
+    #[default]
+    Starttls,
+    /// No encryption, for a relay on the local network.
+    None,
+}
+
+impl NotifySource {
+    pub fn android_app() -> Self {
+        NotifySource::AndroidApp {
+            options: NotifyOptions::default(),
+        }
+    }
+
+    /// Static channel name, used as a label in logs and metrics.
+    pub fn channel(&self) -> &'static str {
+        match self {
+            NotifySource::AndroidApp { .. } => "android_app",
+            NotifySource::Ntfy { .. } => "ntfy",
+            NotifySource::Gotify { .. } => "gotify",
+            NotifySource::Webhook { .. } => "webhook",
+            NotifySource::Email { .. } => "email",
+            NotifySource::Mqtt { .. } => "mqtt",
+        }
+    }
+
+    /// Identifies a target across named and inline references, for policy
+    /// state and recorded suppressions, e.g. `ntfy:https://ntfy.sh/house`.
+    pub fn key(&self) -> String {
+        match self {
+            NotifySource::AndroidApp { .. } => "android_app".to_owned(),
+            NotifySource::Ntfy { url, topic, .. } => {
+                format!("ntfy:{}/{topic}", url.trim_end_matches('/'))
+            }
+            NotifySource::Gotify { url, .. } | NotifySource::Webhook { url, .. } => {
+                format!("{}:{url}", self.channel())
+            }
+            NotifySource::Email { to, .. } => format!("email:{}", to.join(",")),
+            NotifySource::Mqtt { topic, .. } => format!("mqtt:{topic}"),
+        }
+    }
+
+    pub fn options(&self) -> &NotifyOptions {
+        match self {
+            NotifySource::AndroidApp { options }
+            | NotifySource::Ntfy { options, .. }
+            | NotifySource::Gotify { options, .. }
+            | NotifySource::Webhook { options, .. }
+            | NotifySource::Email { options, .. }
+            | NotifySource::Mqtt { options, .. } => options,
+        }
+    }
+
+    /// Reject a target that could never deliver, so a typo fails at load
+    /// rather than when the first notification goes missing.
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        self.options().policy.validate()?;
+
+        match self {
+            NotifySource::AndroidApp { .. } => {}
+            NotifySource::Ntfy { url, topic, .. } => {
+                validate_url(url)?;
+                if topic.is_empty() || topic.contains('/') {
+                    return Err(format!("invalid ntfy topic `{topic}`"));
+                }
+            }
+            NotifySource::Gotify { url, token, .. } => {
+                validate_url(url)?;
+                if token.is_empty() {
+                    return Err("gotify needs an application token".to_owned());
+                }
+            }
+            NotifySource::Webhook { url, headers, .. } => {
+                validate_url(url)?;
+                for (name, value) in headers {
+                    if http::HeaderName::from_bytes(name.as_bytes()).is_err()
+                        || http::HeaderValue::from_str(value).is_err()
+                    {
+                        return Err(format!("invalid webhook header `{name}`"));
+                    }
+                }
+            }
+            NotifySource::Email { smtp, from, to, .. } => {
+                if smtp.host.is_empty() {
+                    return Err("email needs an smtp host".to_owned());
+                }
+                if smtp.username.is_some() != smtp.password.is_some() {
+                    return Err("smtp username and password go together".to_owned());
+                }
+                if to.is_empty() {
+                    return Err("email needs at least one `to` address".to_owned());
+                }
+                for address in std::iter::once(from).chain(to) {
+                    address
+                        .parse::<Mailbox>()
+                        .map_err(|e| format!("invalid email address `{address}`: {e}"))?;
+                }
+            }
+            NotifySource::Mqtt { topic, .. } => {
+                if topic.is_empty() || topic.contains(['+', '#']) {
+                    return Err(format!("invalid mqtt topic `{topic}`"));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn validate_url(url: &str) -> Result<(), String> {
+    match Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => Ok(()),
+        Ok(_) => Err(format!("`{url}` is not an http(s) url")),
+        Err(e) => Err(format!("invalid url `{url}`: {e}")),
+    }
 }
 
 /// A reference to a notify destination: either the name of a target declared
@@ -22,20 +401,187 @@ pub enum NotifyRef {
 }
 
 impl NotifyRef {
-    fn resolve(self, targets: &NotifyTargets) -> Result<NotifySource, String> {
+    pub(crate) fn resolve(&self, targets: &NotifyTargets) -> Result<NotifySource, String> {
         match self {
-            NotifyRef::Inline(source) => Ok(source),
+            NotifyRef::Inline(source) => {
+                source.validate()?;
+                Ok(source.clone())
+            }
             NotifyRef::Named(name) => targets
-                .get(&name)
+                .get(name)
                 .cloned()
                 .ok_or_else(|| format!("unknown notify target: {name}")),
         }
     }
 }
 
+impl std::fmt::Display for NotifyRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NotifyRef::Named(name) => f.write_str(name),
+            NotifyRef::Inline(source) => f.write_str(source.channel()),
+        }
+    }
+}
+
 pub(crate) fn resolve_notify(
     refs: Vec<NotifyRef>,
     targets: &NotifyTargets,
 ) -> Result<Vec<NotifySource>, String> {
-    refs.into_iter().map(|r| r.resolve(targets)).collect()
+    refs.iter().map(|r| r.resolve(targets)).collect()
+}
+
+/// Check every declared target, naming the bad one.
+pub(crate) fn validate_targets(targets: &NotifyTargets) -> Result<(), String> {
+    for (name, target) in targets {
+        target
+            .validate()
+            .map_err(|e| format!("notify target '{name}': {e}"))?;
+    }
+
+    Ok(())
+}
+
+/// Android shows at most three buttons on a notification.
+pub const MAX_ACTIONS: usize = 3;
+
+/// A reply button on a notification. Tapping it calls back into the gateway,
+/// which publishes a `notification_action` event carrying `id`.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, JsonSchema)]
+pub struct NotifyAction {
+    /// What `notification_action` triggers match on, e.g. `snooze`.
+    pub id: String,
+    /// The button label, e.g. `Snooze 30m`.
+    pub title: String,
+}
+
+/// Actions are delivered to the Android app only; other channels send the
+/// message alone.
+pub(crate) fn validate_actions(actions: &[NotifyAction]) -> Result<(), String> {
+    if actions.len() > MAX_ACTIONS {
+        return Err(format!("at most {MAX_ACTIONS} notification actions"));
+    }
+
+    for (i, action) in actions.iter().enumerate() {
+        let valid = !action.id.is_empty()
+            && action
+                .id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid {
+            return Err(format!(
+                "notification action id `{}` may only use letters, digits, `_` and `-`",
+                action.id
+            ));
+        }
+        if action.title.trim().is_empty() {
+            return Err(format!("notification action `{}` needs a title", action.id));
+        }
+        if actions[..i].iter().any(|a| a.id == action.id) {
+            return Err(format!("duplicate notification action `{}`", action.id));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(yaml: &str) -> NotifySource {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn targets_parse_with_their_options() {
+        let ntfy = parse("{ type: ntfy, topic: house, priority: high, tags: [door] }");
+        let NotifySource::Ntfy { url, options, .. } = &ntfy else {
+            panic!("expected ntfy, got {ntfy:?}");
+        };
+        assert_eq!(url, "https://ntfy.sh");
+        assert_eq!(options.priority, NotifyPriority::High);
+        assert_eq!(options.title(), DEFAULT_TITLE);
+        assert!(ntfy.validate().is_ok());
+
+        let android = parse("{ type: android_app, title: Doorbell }");
+        assert_eq!(android.options().title(), "Doorbell");
+    }
+
+    #[test]
+    fn targets_that_could_never_deliver_are_rejected() {
+        let invalid = [
+            "{ type: ntfy, topic: a/b }",
+            "{ type: gotify, url: 'ftp://gotify.local', token: abc }",
+            "{ type: webhook, url: 'http://hooks.local', headers: { 'bad header': x } }",
+            "{ type: email, smtp: { host: mail.local }, from: gateway@home, to: [] }",
+            "{ type: email, smtp: { host: mail.local, username: me }, from: a@home, to: [b@home] }",
+            "{ type: mqtt, topic: 'home/#' }",
+        ];
+
+        for yaml in invalid {
+            assert!(
+                parse(yaml).validate().is_err(),
+                "expected {yaml} to be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn policies_parse_and_nonsense_is_rejected() {
+        let ntfy = parse(
+            "{ type: ntfy, topic: house, policy: { quiet_hours: { start: '22:00', end: '07:00' }, \
+             dedup: 15m, rate_limit: { count: 3, per: 1h }, digest: true } }",
+        );
+        assert!(ntfy.validate().is_ok());
+        let policy = &ntfy.options().policy;
+        let quiet = policy.quiet_hours.as_ref().unwrap();
+        let at = |h| NaiveTime::from_hms_opt(h, 0, 0).unwrap();
+        assert!(quiet.contains(at(23), false));
+        assert!(quiet.contains(at(3), false));
+        assert!(!quiet.contains(at(12), false));
+        assert_eq!(policy.dedup, Some(TimeDelta::minutes(15)));
+        assert!(policy.digest);
+
+        let night = parse("{ type: android_app, policy: { quiet_hours: { night_mode: true } } }");
+        assert!(night.validate().is_ok());
+        let quiet = night.options().policy.quiet_hours.as_ref().unwrap();
+        assert!(quiet.contains(at(12), true));
+        assert!(!quiet.contains(at(3), false));
+
+        let invalid = [
+            "{ type: android_app, policy: { quiet_hours: { start: '22:00' } } }",
+            "{ type: android_app, policy: { quiet_hours: { start: '22:00', end: '22:00' } } }",
+            "{ type: android_app, policy: { quiet_hours: {} } }",
+            "{ type: android_app, policy: { rate_limit: { count: 0, per: 1h } } }",
+        ];
+        for yaml in invalid {
+            assert!(
+                parse(yaml).validate().is_err(),
+                "expected {yaml} to be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn actions_need_distinct_ids_and_a_title() {
+        let action = |id: &str, title: &str| NotifyAction {
+            id: id.to_owned(),
+            title: title.to_owned(),
+        };
+
+        assert!(validate_actions(&[action("snooze", "Snooze 30m"), action("arm", "Arm")]).is_ok());
+        assert!(validate_actions(&[action("snooze", "A"), action("snooze", "B")]).is_err());
+        assert!(validate_actions(&[action("turn off", "Lights off")]).is_err());
+        assert!(validate_actions(&[action("off", " ")]).is_err());
+        assert!(
+            validate_actions(&[
+                action("a", "A"),
+                action("b", "B"),
+                action("c", "C"),
+                action("d", "D")
+            ])
+            .is_err()
+        );
+    }
 }
@@ -9,9 +9,7 @@ pub(crate) fn default_plant_entities() -> Vec<String> {
 
 #[derive(Debug, Clone)]
 pub struct PlantSensorSettings {
-    #[allow(unused)]
     pub id: String,
-    #[allow(unused)]
     pub entities: Vec<String>,
 }
 
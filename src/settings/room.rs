@@ -0,0 +1,44 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::device_registry::DeviceRegistry;
+
+/// A room declared under the top-level `rooms:` key, keyed by the id a device
+/// names in its `room:`. Groups entities for the `rooms` query and scopes the
+/// `room` trigger, `room_occupied` condition and `room_lights` step.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct Room {
+    pub name: String,
+    /// Ground is `0`, below ground negative; rooms are listed floor by floor.
+    #[serde(default)]
+    pub floor: i32,
+    /// An icon name for the frontend, e.g. `mdi:sofa`.
+    #[serde(default)]
+    pub icon: Option<String>,
+}
+
+pub type Rooms = HashMap<String, Room>;
+
+/// Every device's `room:` must be declared, so a typo can't quietly leave a
+/// device out of its room's queries and actions.
+pub(super) fn validate_devices(rooms: &Rooms, registry: &DeviceRegistry) -> Result<(), String> {
+    for (address, room) in registry.device_rooms() {
+        if !rooms.contains_key(room) {
+            return Err(format!(
+                "device {} is in undeclared room `{room}`",
+                registry.id_for_address(address).unwrap_or(address)
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// A workflow's room reference must be declared.
+pub(super) fn validate_room(room: &str, rooms: &Rooms) -> Result<(), String> {
+    if rooms.contains_key(room) {
+        Ok(())
+    } else {
+        Err(format!("unknown room: {room}"))
+    }
+}
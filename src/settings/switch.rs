@@ -13,3 +13,19 @@ pub struct RawSmartSwitchBlock {
     #[serde(default, rename = "as")]
     pub(crate) role: Option<SwitchRole>,
 }
+
+/// How commands reach a smart switch; mirrors the device's transport.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SwitchControl {
+    /// zigbee2mqtt `/set` publishes addressed by friendly name.
+    Zigbee,
+    /// `turn_on`/`turn_off` service calls in the entity's own domain, so a
+    /// `switch.` and an `input_boolean.` entity both work.
+    HomeAssistant { domain: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct SmartSwitchSettings {
+    pub name: String,
+    pub control: SwitchControl,
+}
@@ -0,0 +1,512 @@
+//! The expression language inside `${…}`.
+//!
+//! ```text
+//! expr    := compare ('?' expr ':' expr)?
+//! compare := pipe (('==' | '!=' | '<' | '<=' | '>' | '>=') pipe)?
+//! pipe    := unary ('|' filter)*
+//! unary   := '!' unary | primary
+//! primary := path | 'string' | "string" | number | '(' expr ')'
+//! filter  := round | round(digits) | unit('W') | default('n/a')
+//! ```
+//!
+//! Values are strings in the scope and only read as numbers where a filter or
+//! comparison needs one, so `${temperature | round(1)}` works on the
+//! `"21.46"` an environment event carries.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Expr {
+    /// An event var, or a dotted live-state path like `sensor.bedroom.humidity`.
+    Path(String),
+    Str(String),
+    Num(f64),
+    Not(Box<Expr>),
+    Compare(Box<Expr>, CmpOp, Box<Expr>),
+    Ternary(Box<Expr>, Box<Expr>, Box<Expr>),
+    Filter(Box<Expr>, Filter),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum Filter {
+    /// Fixed number of decimal places.
+    Round(usize),
+    /// Append a unit, scaled to k/M/G once it reaches a thousand.
+    Unit(String),
+    /// Fallback for a missing or empty value.
+    Default(String),
+}
+
+/// What an expression evaluates to. `Missing` is an unknown var or lookup,
+/// which renders as the original `${…}` unless a `default` catches it.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Value {
+    Missing,
+    Str(String),
+    Num(f64),
+    Bool(bool),
+}
+
+impl Value {
+    fn as_num(&self) -> Option<f64> {
+        match self {
+            Value::Num(n) => Some(*n),
+            Value::Str(s) => s.trim().parse().ok(),
+            Value::Missing | Value::Bool(_) => None,
+        }
+    }
+
+    fn truthy(&self) -> bool {
+        match self {
+            Value::Missing => false,
+            Value::Bool(b) => *b,
+            Value::Num(n) => *n != 0.0,
+            Value::Str(s) => !matches!(
+                s.trim().to_ascii_lowercase().as_str(),
+                "" | "false" | "0" | "off" | "no"
+            ),
+        }
+    }
+
+    pub(super) fn render(&self) -> Option<String> {
+        match self {
+            Value::Missing => None,
+            Value::Str(s) => Some(s.clone()),
+            Value::Num(n) => Some(format_number(*n)),
+            Value::Bool(b) => Some(b.to_string()),
+        }
+    }
+}
+
+/// Whole numbers without a trailing `.0`, everything else as is.
+fn format_number(n: f64) -> String {
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        format!("{}", n as i64)
+    } else {
+        n.to_string()
+    }
+}
+
+fn with_unit(n: f64, unit: &str) -> String {
+    let (scaled, prefix) = match n.abs() {
+        a if a >= 1e9 => (n / 1e9, "G"),
+        a if a >= 1e6 => (n / 1e6, "M"),
+        a if a >= 1e3 => (n / 1e3, "k"),
+        _ => (n, ""),
+    };
+    let rounded = (scaled * 100.0).round() / 100.0;
+
+    format!("{} {prefix}{unit}", format_number(rounded))
+}
+
+impl CmpOp {
+    fn apply(self, a: &Value, b: &Value) -> bool {
+        let ord = match (a.as_num(), b.as_num()) {
+            (Some(x), Some(y)) => x.partial_cmp(&y),
+            _ => Some(a.render().cmp(&b.render())),
+        };
+
+        match self {
+            CmpOp::Eq => ord == Some(Ordering::Equal),
+            CmpOp::Ne => ord != Some(Ordering::Equal),
+            CmpOp::Lt => ord == Some(Ordering::Less),
+            CmpOp::Le => matches!(ord, Some(Ordering::Less | Ordering::Equal)),
+            CmpOp::Gt => ord == Some(Ordering::Greater),
+            CmpOp::Ge => matches!(ord, Some(Ordering::Greater | Ordering::Equal)),
+        }
+    }
+}
+
+impl Filter {
+    fn apply(&self, value: Value) -> Value {
+        match self {
+            Filter::Default(fallback) => match value {
+                Value::Missing => Value::Str(fallback.clone()),
+                Value::Str(s) if s.is_empty() => Value::Str(fallback.clone()),
+                value => value,
+            },
+            Filter::Round(digits) => match value.as_num() {
+                Some(n) => Value::Str(format!("{n:.digits$}")),
+                None => value,
+            },
+            Filter::Unit(unit) => match value.as_num() {
+                Some(n) => Value::Str(with_unit(n, unit)),
+                None => value,
+            },
+        }
+    }
+}
+
+impl Expr {
+    pub(super) fn eval(&self, vars: &HashMap<String, String>) -> Value {
+        match self {
+            Expr::Path(path) => vars
+                .get(path)
+                .map_or(Value::Missing, |v| Value::Str(v.clone())),
+            Expr::Str(s) => Value::Str(s.clone()),
+            Expr::Num(n) => Value::Num(*n),
+            Expr::Not(inner) => match inner.eval(vars) {
+                Value::Missing => Value::Missing,
+                value => Value::Bool(!value.truthy()),
+            },
+            Expr::Compare(lhs, op, rhs) => match (lhs.eval(vars), rhs.eval(vars)) {
+                (Value::Missing, _) | (_, Value::Missing) => Value::Missing,
+                (a, b) => Value::Bool(op.apply(&a, &b)),
+            },
+            Expr::Ternary(cond, then, otherwise) => match cond.eval(vars) {
+                Value::Missing => Value::Missing,
+                value if value.truthy() => then.eval(vars),
+                _ => otherwise.eval(vars),
+            },
+            Expr::Filter(inner, filter) => filter.apply(inner.eval(vars)),
+        }
+    }
+
+    /// Every path the expression reads, in order.
+    pub(super) fn paths<'a>(&'a self, out: &mut Vec<&'a str>) {
+        match self {
+            Expr::Path(path) => out.push(path),
+            Expr::Str(_) | Expr::Num(_) => {}
+            Expr::Not(inner) | Expr::Filter(inner, _) => inner.paths(out),
+            Expr::Compare(lhs, _, rhs) => {
+                lhs.paths(out);
+                rhs.paths(out);
+            }
+            Expr::Ternary(cond, then, otherwise) => {
+                cond.paths(out);
+                then.paths(out);
+                otherwise.paths(out);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Num(f64),
+    Cmp(CmpOp),
+    Not,
+    Pipe,
+    Question,
+    Colon,
+    LParen,
+    RParen,
+    Comma,
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        i += 1;
+
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '|' => Token::Pipe,
+            '?' => Token::Question,
+            ':' => Token::Colon,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            ',' => Token::Comma,
+            '=' if next == Some('=') => {
+                i += 1;
+                Token::Cmp(CmpOp::Eq)
+            }
+            '!' if next == Some('=') => {
+                i += 1;
+                Token::Cmp(CmpOp::Ne)
+            }
+            '!' => Token::Not,
+            '<' | '>' => {
+                let or_equal = next == Some('=');
+                if or_equal {
+                    i += 1;
+                }
+                Token::Cmp(match (c, or_equal) {
+                    ('<', false) => CmpOp::Lt,
+                    ('<', true) => CmpOp::Le,
+                    (_, false) => CmpOp::Gt,
+                    (_, true) => CmpOp::Ge,
+                })
+            }
+            '\'' | '"' => {
+                let start = i;
+                while i < chars.len() && chars[i] != c {
+                    i += 1;
+                }
+                if i == chars.len() {
+                    return Err("unterminated string".to_owned());
+                }
+                i += 1;
+                Token::Str(chars[start..i - 1].iter().collect())
+            }
+            c if c.is_ascii_digit() || (c == '-' && next.is_some_and(|n| n.is_ascii_digit())) => {
+                let start = i - 1;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let literal: String = chars[start..i].iter().collect();
+                Token::Num(
+                    literal
+                        .parse()
+                        .map_err(|_| format!("invalid number `{literal}`"))?,
+                )
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i - 1;
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '.' | '-'))
+                {
+                    i += 1;
+                }
+                Token::Ident(chars[start..i].iter().collect())
+            }
+            other => return Err(format!("unexpected `{other}`")),
+        };
+
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+pub(super) fn parse(src: &str) -> Result<Expr, String> {
+    let mut parser = Parser {
+        tokens: tokenize(src)?,
+        pos: 0,
+    };
+
+    let expr = parser.expr()?;
+    match parser.next() {
+        None => Ok(expr),
+        Some(token) => Err(format!("unexpected {token:?} after the expression")),
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &Token) -> Result<(), String> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(format!("expected {token:?}, found {:?}", self.peek()))
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        let cond = self.compare()?;
+        if !self.eat(&Token::Question) {
+            return Ok(cond);
+        }
+
+        let then = self.expr()?;
+        self.expect(&Token::Colon)?;
+        let otherwise = self.expr()?;
+
+        Ok(Expr::Ternary(
+            Box::new(cond),
+            Box::new(then),
+            Box::new(otherwise),
+        ))
+    }
+
+    fn compare(&mut self) -> Result<Expr, String> {
+        let lhs = self.pipe()?;
+        let Some(Token::Cmp(op)) = self.peek().cloned() else {
+            return Ok(lhs);
+        };
+
+        self.pos += 1;
+        let rhs = self.pipe()?;
+
+        Ok(Expr::Compare(Box::new(lhs), op, Box::new(rhs)))
+    }
+
+    fn pipe(&mut self) -> Result<Expr, String> {
+        let mut expr = self.unary()?;
+        while self.eat(&Token::Pipe) {
+            expr = Expr::Filter(Box::new(expr), self.filter()?);
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat(&Token::Not) {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Ident(path)) => Ok(Expr::Path(path)),
+            Some(Token::Str(s)) => Ok(Expr::Str(s)),
+            Some(Token::Num(n)) => Ok(Expr::Num(n)),
+            Some(Token::LParen) => {
+                let expr = self.expr()?;
+                self.expect(&Token::RParen)?;
+                Ok(expr)
+            }
+            Some(token) => Err(format!("unexpected {token:?}")),
+            None => Err("unexpected end of expression".to_owned()),
+        }
+    }
+
+    fn filter(&mut self) -> Result<Filter, String> {
+        let Some(Token::Ident(name)) = self.next() else {
+            return Err("expected a filter name after `|`".to_owned());
+        };
+
+        let mut args = Vec::new();
+        if self.eat(&Token::LParen) && !self.eat(&Token::RParen) {
+            loop {
+                match self.next() {
+                    Some(token @ (Token::Str(_) | Token::Num(_))) => args.push(token),
+                    other => {
+                        return Err(format!("`{name}` expects literal arguments, got {other:?}"));
+                    }
+                }
+                if self.eat(&Token::RParen) {
+                    break;
+                }
+                self.expect(&Token::Comma)?;
+            }
+        }
+
+        match (name.as_str(), args.as_slice()) {
+            ("round", []) => Ok(Filter::Round(0)),
+            ("round", [Token::Num(digits)])
+                if *digits >= 0.0 && digits.fract() == 0.0 && *digits <= 10.0 =>
+            {
+                Ok(Filter::Round(*digits as usize))
+            }
+            ("unit", [Token::Str(unit)]) => Ok(Filter::Unit(unit.clone())),
+            ("default", [Token::Str(fallback)]) => Ok(Filter::Default(fallback.clone())),
+            ("default", [Token::Num(fallback)]) => Ok(Filter::Default(format_number(*fallback))),
+            ("round" | "unit" | "default", _) => Err(format!("bad arguments to `{name}`")),
+            _ => Err(format!("unknown filter `{name}`")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(src: &str, vars: &[(&str, &str)]) -> Option<String> {
+        let vars = vars
+            .iter()
+            .map(|(k, v)| ((*k).to_owned(), (*v).to_owned()))
+            .collect();
+        parse(src).unwrap().eval(&vars).render()
+    }
+
+    #[test]
+    fn round_and_unit_filters_chain() {
+        let vars = [("temperature", "21.46"), ("current", "3456")];
+
+        assert_eq!(eval("temperature | round(1)", &vars).unwrap(), "21.5");
+        assert_eq!(eval("temperature | round", &vars).unwrap(), "21");
+        assert_eq!(eval("current | unit('W')", &vars).unwrap(), "3.46 kW");
+        assert_eq!(eval("temperature | unit('°C')", &vars).unwrap(), "21.46 °C");
+    }
+
+    #[test]
+    fn default_catches_missing_and_empty_values() {
+        assert_eq!(eval("series | default('n/a')", &[]).unwrap(), "n/a");
+        assert_eq!(
+            eval("series | default('n/a')", &[("series", "")]).unwrap(),
+            "n/a"
+        );
+        assert_eq!(eval("series", &[]), None);
+    }
+
+    #[test]
+    fn ternary_branches_on_truthiness_and_comparisons() {
+        let away = [("present", "false")];
+        let hot = [("temperature", "31.2")];
+
+        assert_eq!(eval("present ? 'home' : 'away'", &away).unwrap(), "away");
+        assert_eq!(eval("!present ? 'away' : 'home'", &away).unwrap(), "away");
+        assert_eq!(
+            eval("temperature >= 30 ? 'hot' : 'fine'", &hot).unwrap(),
+            "hot"
+        );
+        assert_eq!(
+            eval(
+                "mode.night == 'true' ? 'quietly' : 'loudly'",
+                &[("mode.night", "true")]
+            )
+            .unwrap(),
+            "quietly"
+        );
+    }
+
+    #[test]
+    fn paths_lists_every_read_in_order() {
+        let expr = parse("present ? sensor.bedroom.temperature | round(1) : fallback").unwrap();
+        let mut paths = Vec::new();
+        expr.paths(&mut paths);
+
+        assert_eq!(
+            paths,
+            vec!["present", "sensor.bedroom.temperature", "fallback"]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_expressions() {
+        for src in [
+            "",
+            "temperature |",
+            "temperature | shout",
+            "round(1",
+            "present ? 'home'",
+            "'unterminated",
+            "a b",
+            "temperature | round('x')",
+        ] {
+            assert!(parse(src).is_err(), "expected `{src}` to be rejected");
+        }
+    }
+}
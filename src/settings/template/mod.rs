@@ -0,0 +1,487 @@
+//! `${…}` templates in workflow strings.
+//!
+//! A placeholder holds an expression (see [`expr`]) over the triggering
+//! event's vars and, by dotted path, live state ([`Lookup`]). Rendering is
+//! sync over a flat `name -> value` map; the workflow worker resolves the
+//! lookups a step's templates use and adds them to that map first.
+//!
+//! Anything that doesn't evaluate, an unknown var or a malformed expression,
+//! renders as the original `${…}` so it stands out in the message. Config load
+//! rejects malformed expressions and unknown lookups up front, including ones
+//! naming a device the registry doesn't have.
+
+use crate::actors::sun::calc::SunTransition;
+use crate::device_registry::DeviceRegistry;
+use crate::mode::Mode;
+use crate::settings::workflow::EnvMetric;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+mod expr;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(from = "String")]
+#[schemars(with = "String")]
+pub enum TemplateString {
+    Plain(String),
+    Template(String),
+}
+
+impl TemplateString {
+    pub fn render(&self, vars: &HashMap<String, String>) -> String {
+        match self {
+            TemplateString::Plain(s) => s.clone(),
+            TemplateString::Template(s) => render_template(s, vars),
+        }
+    }
+
+    pub fn raw(&self) -> &str {
+        match self {
+            TemplateString::Plain(s) | TemplateString::Template(s) => s,
+        }
+    }
+
+    /// Event vars the template reads, in order, repeats included.
+    pub fn placeholders(&self) -> Vec<String> {
+        self.paths()
+            .into_iter()
+            .filter(|path| !path.contains('.'))
+            .collect()
+    }
+
+    /// Dotted live-state paths the template reads, e.g. `sensor.bedroom.humidity`.
+    pub fn lookups(&self) -> Vec<String> {
+        self.paths()
+            .into_iter()
+            .filter(|path| path.contains('.'))
+            .collect()
+    }
+
+    /// Config-time check that every expression parses and every lookup names
+    /// live state that exists, on a device in `registry`.
+    pub fn validate(&self, registry: &DeviceRegistry) -> Result<(), String> {
+        let TemplateString::Template(s) = self else {
+            return Ok(());
+        };
+
+        for segment in segments(s) {
+            if let Segment::Expr { source, parsed } = segment {
+                parsed.map_err(|e| format!("invalid template `${{{source}}}`: {e}"))?;
+            }
+        }
+
+        for path in self.lookups() {
+            Lookup::parse(&path)?.validate_device(registry)?;
+        }
+
+        Ok(())
+    }
+
+    fn paths(&self) -> Vec<String> {
+        let TemplateString::Template(s) = self else {
+            return Vec::new();
+        };
+
+        let mut paths = Vec::new();
+        for segment in segments(s) {
+            if let Segment::Expr {
+                parsed: Ok(expr), ..
+            } = segment
+            {
+                let mut found = Vec::new();
+                expr.paths(&mut found);
+                paths.extend(found.into_iter().map(str::to_owned));
+            }
+        }
+
+        paths
+    }
+}
+
+impl From<String> for TemplateString {
+    fn from(s: String) -> Self {
+        if s.contains("${") {
+            TemplateString::Template(s)
+        } else {
+            TemplateString::Plain(s)
+        }
+    }
+}
+
+impl std::fmt::Display for TemplateString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.raw())
+    }
+}
+
+/// A whole number that can also be given as a template, e.g.
+/// `value: "${level | round}"`, rendered and parsed when the step runs.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum TemplateNumber {
+    Literal(u64),
+    Template(TemplateString),
+}
+
+impl TemplateNumber {
+    pub fn resolve(&self, vars: &HashMap<String, String>) -> Result<u64, String> {
+        let template = match self {
+            TemplateNumber::Literal(n) => return Ok(*n),
+            TemplateNumber::Template(template) => template,
+        };
+
+        let rendered = template.render(vars);
+        match rendered.trim().parse::<f64>() {
+            Ok(n) if n >= 0.0 && n.is_finite() => Ok(n.round() as u64),
+            _ => Err(format!(
+                "`{template}` rendered to `{rendered}`, not a whole number"
+            )),
+        }
+    }
+
+    pub fn template(&self) -> Option<&TemplateString> {
+        match self {
+            TemplateNumber::Literal(_) => None,
+            TemplateNumber::Template(template) => Some(template),
+        }
+    }
+}
+
+/// Every string in a JSON value that holds a `${…}`, as a template.
+pub fn json_templates(value: &serde_json::Value) -> Vec<TemplateString> {
+    fn collect(value: &serde_json::Value, out: &mut Vec<TemplateString>) {
+        match value {
+            serde_json::Value::String(s) if s.contains("${") => out.push(s.clone().into()),
+            serde_json::Value::Array(items) => items.iter().for_each(|v| collect(v, out)),
+            serde_json::Value::Object(fields) => fields.values().for_each(|v| collect(v, out)),
+            _ => {}
+        }
+    }
+
+    let mut out = Vec::new();
+    collect(value, &mut out);
+    out
+}
+
+/// Render every template string in a JSON value. A string that is nothing but
+/// one placeholder and renders to a number or bool becomes that JSON type, so
+/// `brightness_pct: "${level}"` reaches Home Assistant as a number.
+pub fn render_json(value: &serde_json::Value, vars: &HashMap<String, String>) -> serde_json::Value {
+    match value {
+        serde_json::Value::String(s) if s.contains("${") => {
+            let rendered = render_template(s, vars);
+            let whole = s.starts_with("${") && s.ends_with('}') && segments(s).len() == 1;
+            if whole && !rendered.starts_with("${") {
+                if let Ok(n) = rendered.parse::<serde_json::Number>() {
+                    return serde_json::Value::Number(n);
+                }
+                if let Ok(b) = rendered.parse::<bool>() {
+                    return serde_json::Value::Bool(b);
+                }
+            }
+            serde_json::Value::String(rendered)
+        }
+        serde_json::Value::Array(items) => {
+            serde_json::Value::Array(items.iter().map(|v| render_json(v, vars)).collect())
+        }
+        serde_json::Value::Object(fields) => serde_json::Value::Object(
+            fields
+                .iter()
+                .map(|(k, v)| (k.clone(), render_json(v, vars)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Live state a template reads by dotted path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lookup<'a> {
+    /// `sensor.<id>.<metric>`: an environment sensor's latest reading.
+    Sensor { sensor: &'a str, metric: EnvMetric },
+    /// `presence.<id>`: `true` while the sensor sees someone.
+    Presence { sensor: &'a str },
+    /// `door.<id>`: `true` while the door is open.
+    Door { device: &'a str },
+    /// `mode.<mode>`: `true` while the mode is active.
+    Mode(Mode),
+    /// `sun.sunrise` / `sun.sunset`: today's local time as `HH:MM`.
+    Sun(SunTransition),
+    /// `var.<name>`: a declared workflow variable.
+    Variable(&'a str),
+}
+
+impl<'a> Lookup<'a> {
+    pub fn parse(path: &'a str) -> Result<Self, String> {
+        let unknown = || format!("unknown template lookup `{path}`");
+        let (namespace, rest) = path.split_once('.').ok_or_else(unknown)?;
+
+        match namespace {
+            "sensor" => {
+                let (sensor, metric) = rest.rsplit_once('.').ok_or_else(unknown)?;
+                let metric = match metric {
+                    "temperature" => EnvMetric::Temperature,
+                    "humidity" => EnvMetric::Humidity,
+                    "pressure" => EnvMetric::Pressure,
+                    "lux" => EnvMetric::Lux,
+                    "uv_index" => EnvMetric::UvIndex,
+                    _ => return Err(unknown()),
+                };
+                Ok(Lookup::Sensor { sensor, metric })
+            }
+            "presence" => Ok(Lookup::Presence { sensor: rest }),
+            "door" => Ok(Lookup::Door { device: rest }),
+            "mode" => Mode::ALL
+                .iter()
+                .find(|mode| mode.as_str() == rest)
+                .map(|mode| Lookup::Mode(*mode))
+                .ok_or_else(unknown),
+            "sun" => match rest {
+                "sunrise" => Ok(Lookup::Sun(SunTransition::Sunrise)),
+                "sunset" => Ok(Lookup::Sun(SunTransition::Sunset)),
+                _ => Err(unknown()),
+            },
+            "var" => Ok(Lookup::Variable(rest)),
+            _ => Err(unknown()),
+        }
+    }
+
+    /// The device a sensor, presence or door lookup reads must be registered
+    /// as one, by id or address.
+    fn validate_device(&self, registry: &DeviceRegistry) -> Result<(), String> {
+        let (kind, id, registered) = match self {
+            Lookup::Sensor { sensor, .. } => (
+                "environment sensor",
+                sensor,
+                registry
+                    .environment(registry.address_or_self(sensor))
+                    .is_some(),
+            ),
+            Lookup::Presence { sensor } => (
+                "presence sensor",
+                sensor,
+                registry
+                    .presence(registry.address_or_self(sensor))
+                    .is_some(),
+            ),
+            Lookup::Door { device } => (
+                "door",
+                device,
+                registry.door(registry.address_or_self(device)).is_some(),
+            ),
+            Lookup::Mode(_) | Lookup::Sun(_) | Lookup::Variable(_) => return Ok(()),
+        };
+
+        if registered {
+            Ok(())
+        } else {
+            Err(format!("template lookup names unknown {kind} `{id}`"))
+        }
+    }
+}
+
+enum Segment<'a> {
+    Text(&'a str),
+    Expr {
+        source: &'a str,
+        parsed: Result<expr::Expr, String>,
+    },
+}
+
+/// Split into literal text and `${…}` placeholders. The closing `}` is the
+/// first one outside a quoted string; with none, the rest is literal text.
+fn segments(input: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut rest = input;
+
+    while let Some(start) = rest.find("${") {
+        if start > 0 {
+            segments.push(Segment::Text(&rest[..start]));
+        }
+
+        let after = &rest[start + 2..];
+        let mut quote = None;
+        let end = after.char_indices().find_map(|(i, c)| {
+            match (quote, c) {
+                (None, '}') => return Some(i),
+                (None, '\'' | '"') => quote = Some(c),
+                (Some(q), c) if q == c => quote = None,
+                _ => {}
+            }
+            None
+        });
+
+        let Some(end) = end else {
+            segments.push(Segment::Text(&rest[start..]));
+            return segments;
+        };
+
+        let source = &after[..end];
+        segments.push(Segment::Expr {
+            source,
+            parsed: expr::parse(source),
+        });
+        rest = &after[end + 1..];
+    }
+
+    if !rest.is_empty() {
+        segments.push(Segment::Text(rest));
+    }
+    segments
+}
+
+fn render_template(input: &str, vars: &HashMap<String, String>) -> String {
+    let mut out = String::with_capacity(input.len());
+
+    for segment in segments(input) {
+        match segment {
+            Segment::Text(text) => out.push_str(text),
+            Segment::Expr { source, parsed } => {
+                match parsed.ok().and_then(|expr| expr.eval(vars).render()) {
+                    Some(value) => out.push_str(&value),
+                    None => {
+                        out.push_str("${");
+                        out.push_str(source);
+                        out.push('}');
+                    }
+                }
+            }
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> HashMap<String, String> {
+        HashMap::from([
+            ("name".to_owned(), "Milk".to_owned()),
+            ("old_price".to_owned(), "4.50".to_owned()),
+            ("new_price".to_owned(), "3.00".to_owned()),
+        ])
+    }
+
+    #[test]
+    fn plain_string_has_no_placeholders() {
+        let t: TemplateString = "just text".to_owned().into();
+        assert_eq!(t, TemplateString::Plain("just text".to_owned()));
+        assert_eq!(t.render(&vars()), "just text");
+    }
+
+    #[test]
+    fn substitutes_known_vars() {
+        let t: TemplateString = "${name}: ${old_price} -> ${new_price}".to_owned().into();
+        assert!(matches!(t, TemplateString::Template(_)));
+        assert_eq!(t.render(&vars()), "Milk: 4.50 -> 3.00");
+    }
+
+    #[test]
+    fn unknown_var_left_literal() {
+        let t: TemplateString = "${name} ${bogus}".to_owned().into();
+        assert_eq!(t.render(&vars()), "Milk ${bogus}");
+    }
+
+    #[test]
+    fn unterminated_placeholder_left_literal() {
+        let t: TemplateString = "drop ${name".to_owned().into();
+        assert_eq!(t.render(&vars()), "drop ${name");
+    }
+
+    #[test]
+    fn placeholders_lists_referenced_vars() {
+        let t: TemplateString = "${name}: ${old_price} ${name}".to_owned().into();
+        assert_eq!(t.placeholders(), vec!["name", "old_price", "name"]);
+
+        let plain: TemplateString = "no vars".to_owned().into();
+        assert!(plain.placeholders().is_empty());
+    }
+
+    #[test]
+    fn expressions_render_with_filters_and_lookups() {
+        let mut vars = vars();
+        vars.insert("temperature".to_owned(), "21.46".to_owned());
+        vars.insert("present".to_owned(), "true".to_owned());
+        vars.insert("sensor.bedroom.humidity".to_owned(), "48.2".to_owned());
+
+        let t: TemplateString =
+            "${temperature | round(1)}°C, ${present ? 'home' : 'away'}, ${sensor.bedroom.humidity | round}% ${series | default('-')}"
+                .to_owned()
+                .into();
+
+        assert_eq!(t.render(&vars), "21.5°C, home, 48% -");
+        assert_eq!(t.placeholders(), vec!["temperature", "present", "series"]);
+        assert_eq!(t.lookups(), vec!["sensor.bedroom.humidity"]);
+    }
+
+    #[test]
+    fn a_closing_brace_inside_quotes_does_not_end_the_placeholder() {
+        let t: TemplateString = "${bogus | default('}')}!".to_owned().into();
+        assert_eq!(t.render(&vars()), "}!");
+    }
+
+    #[test]
+    fn validate_rejects_bad_syntax_and_unknown_lookups() {
+        let registry = DeviceRegistry::default();
+        let ok: TemplateString = "${mode.night ? 'shh' : sun.sunset} ${var.visits}"
+            .to_owned()
+            .into();
+        assert!(ok.validate(&registry).is_ok());
+
+        for bad in [
+            "${name |}",
+            "${mode.disco}",
+            "${weather.today}",
+            "${sensor.x.colour}",
+            "${sensor.0x01.uv_index}",
+            "${presence.hallway}",
+            "${door.front}",
+        ] {
+            let t: TemplateString = bad.to_owned().into();
+            assert!(
+                t.validate(&registry).is_err(),
+                "expected `{bad}` to be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn template_numbers_resolve_literals_and_templates() {
+        let vars = HashMap::from([("level".to_owned(), "62.6".to_owned())]);
+
+        let literal: TemplateNumber = serde_json::from_value(serde_json::json!(120)).unwrap();
+        let template: TemplateNumber =
+            serde_json::from_value(serde_json::json!("${level | round}")).unwrap();
+        let missing: TemplateNumber =
+            serde_json::from_value(serde_json::json!("${bogus}")).unwrap();
+
+        assert_eq!(literal.resolve(&vars), Ok(120));
+        assert_eq!(template.resolve(&vars), Ok(63));
+        assert!(missing.resolve(&vars).is_err());
+    }
+
+    #[test]
+    fn json_placeholders_keep_their_type_when_they_are_the_whole_string() {
+        let vars = HashMap::from([
+            ("level".to_owned(), "40".to_owned()),
+            ("name".to_owned(), "Milk".to_owned()),
+        ]);
+        let data = serde_json::json!({
+            "entity_id": "light.hall",
+            "brightness_pct": "${level}",
+            "message": ["${name} is ${level}%"],
+        });
+
+        assert_eq!(
+            render_json(&data, &vars),
+            serde_json::json!({
+                "entity_id": "light.hall",
+                "brightness_pct": 40,
+                "message": ["Milk is 40%"],
+            })
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use chrono::TimeDelta;
 use schemars::JsonSchema;
 use serde::Deserialize;
 
@@ -7,20 +8,36 @@ use crate::actors::sun::calc::SunTransition;
 use crate::actors::system::cron::schedule::CronSchedule;
 use crate::event_bus::{PlaybackState, SensorMetric, SolarMetric};
 use crate::mode::Mode;
+use crate::timedelta_format::{humanize, option_time_delta_from_str};
 
 /// Which event a trigger fires on. Mirrors the [`crate::event_bus::EventBusMessage`]
 /// variants; the dispatcher matches messages against these.
+///
+/// Presence, door, environment, light, `home_assistant` and `media_player`
+/// triggers take an optional `for:` duration, e.g. `for: 10m`. With one, the
+/// trigger fires once its state has held that long rather than on the event
+/// that entered it, and not again until the state has flipped back.
 #[derive(Debug, Deserialize, Clone, JsonSchema)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum TriggerMatcher {
     Presence {
         sensor: String,
         present: bool,
+        #[serde(default, rename = "for", with = "option_time_delta_from_str")]
+        #[schemars(with = "Option<String>")]
+        held_for: Option<TimeDelta>,
     },
+    /// Fires when a declared room becomes occupied (`occupied: true`), on the
+    /// first of its presence sensors detecting someone, or empty, once the
+    /// last one clears.
+    Room { room: String, occupied: bool },
     Door {
         #[serde(rename = "device", alias = "ieeeAddr")]
         ieee_addr: IEEEAddress,
         open: bool,
+        #[serde(default, rename = "for", with = "option_time_delta_from_str")]
+        #[schemars(with = "Option<String>")]
+        held_for: Option<TimeDelta>,
     },
     Switch {
         #[serde(rename = "device", alias = "ieeeAddr")]
@@ -35,13 +52,14 @@ pub enum TriggerMatcher {
         metric: SensorMetric,
         #[serde(flatten)]
         cmp: Comparison,
+        #[serde(default, rename = "for", with = "option_time_delta_from_str")]
+        #[schemars(with = "Option<String>")]
+        held_for: Option<TimeDelta>,
     },
     /// Fires on a recurring schedule. `schedule` is a standard 5-field cron
     /// expression (e.g. `"0 20 * * THU"`), evaluated in local time. Driven by the
     /// [`crate::actors::system::cron::CronActor`] producer, which matches by trigger name.
-    Cron {
-        schedule: Box<CronSchedule>,
-    },
+    Cron { schedule: Box<CronSchedule> },
     /// Fires at a sun transition (`sunrise`/`sunset`), driven by the
     /// [`crate::actors::sun::SunActor`] producer.
     Sun {
@@ -55,17 +73,17 @@ pub enum TriggerMatcher {
     },
     /// Fires when a house mode is entered (`active: true`) or exited
     /// (`active: false`), driven by `set_mode`.
-    Mode {
-        mode: Mode,
-        active: bool,
-    },
+    Mode { mode: Mode, active: bool },
     /// Fires when a Home Assistant entity changes state, driven by the
     /// [`crate::actors::integrations::home_assistant`] producer. Optionally gate on the entity
-    /// reaching a specific `state`.
+    /// reaching a specific `state`, which a `for:` needs.
     HomeAssistant {
         entity_id: String,
         #[serde(default)]
         state: Option<String>,
+        #[serde(default, rename = "for", with = "option_time_delta_from_str")]
+        #[schemars(with = "Option<String>")]
+        held_for: Option<TimeDelta>,
     },
     /// Fires when a tracked Woolworths product drops in price, driven by the
     /// [`crate::actors::integrations::woolworths`] producer. Optionally gate on a specific
@@ -106,7 +124,8 @@ pub enum TriggerMatcher {
     /// [`crate::actors::devices::media_player`] handler. Every field is an optional
     /// gate: the configured `device` id, `state`
     /// (`started`/`stopped`/`paused`/`resumed`), and the casting `app` (e.g.
-    /// `Jellyfin`, `YouTube`).
+    /// `Jellyfin`, `YouTube`). A `for:` needs `device` and `state`, and holds
+    /// until the player's next edge.
     MediaPlayer {
         #[serde(default)]
         device: Option<String>,
@@ -114,6 +133,9 @@ pub enum TriggerMatcher {
         state: Option<PlaybackState>,
         #[serde(default)]
         app: Option<String>,
+        #[serde(default, rename = "for", with = "option_time_delta_from_str")]
+        #[schemars(with = "Option<String>")]
+        held_for: Option<TimeDelta>,
     },
     /// Fires on a solar generation reading, driven by the
     /// [`crate::actors::integrations::solar`] producer's poll. `metric` picks the
@@ -125,17 +147,99 @@ pub enum TriggerMatcher {
         #[serde(flatten)]
         cmp: Comparison,
     },
+    /// Fires whenever a declared workflow variable changes value, from a
+    /// `set_variable` / `increment_variable` step or the GraphQL API.
+    Variable { variable: String },
+    /// Fires when a light is switched on (`on: true`) or off (`on: false`),
+    /// however that happened: a workflow, the wall switch, or the app.
+    Light {
+        #[serde(rename = "device", alias = "ieeeAddr")]
+        ieee_addr: IEEEAddress,
+        on: bool,
+        #[serde(default, rename = "for", with = "option_time_delta_from_str")]
+        #[schemars(with = "Option<String>")]
+        held_for: Option<TimeDelta>,
+    },
+    /// Fires when a UniFi WiFi client connects (`connected: true`) or
+    /// disconnects. `client` gates on the mapped client name and `mac_address`
+    /// on the MAC (case-insensitive); with neither, any client matches.
+    Unifi {
+        #[serde(default)]
+        client: Option<String>,
+        #[serde(default)]
+        mac_address: Option<String>,
+        connected: bool,
+    },
+    /// Fires on a robot vacuum state change, driven by the
+    /// [`crate::actors::devices::robot_vacuum`] handler. Every field is an
+    /// optional gate: the vacuum's registry id as `device`, the state it left
+    /// (`from`) and the state it entered (`to`), e.g. `from: cleaning` +
+    /// `to: docked` for a finished clean, or `to: error`.
+    RobotVacuum {
+        #[serde(default)]
+        device: Option<String>,
+        #[serde(default)]
+        from: Option<String>,
+        #[serde(default)]
+        to: Option<String>,
+    },
+    /// Fires when a device goes offline (`available: false`) or comes back,
+    /// as judged by the watchdog against its `timeout`. Optionally gate on a
+    /// specific `device`; without one, any watched device matches.
+    DeviceAvailability {
+        #[serde(default)]
+        device: Option<String>,
+        available: bool,
+    },
+    /// Fires when a button on an actionable notification is tapped. `action`
+    /// is the button's id; `source` optionally narrows to one sender, e.g.
+    /// `workflow:bins` or `door:front`.
+    NotificationAction {
+        action: String,
+        #[serde(default)]
+        source: Option<String>,
+    },
 }
 
 impl TriggerMatcher {
-    // used by the workflow `plan` renderer, currently exercised only in tests
-    #[allow(dead_code)]
+    /// How long the trigger's state must hold before it fires, if it takes a
+    /// `for:`.
+    pub fn held_for(&self) -> Option<TimeDelta> {
+        match self {
+            TriggerMatcher::Presence { held_for, .. }
+            | TriggerMatcher::Door { held_for, .. }
+            | TriggerMatcher::Environment { held_for, .. }
+            | TriggerMatcher::HomeAssistant { held_for, .. }
+            | TriggerMatcher::MediaPlayer { held_for, .. }
+            | TriggerMatcher::Light { held_for, .. } => *held_for,
+            _ => None,
+        }
+    }
+
     pub fn describe(&self) -> String {
+        let described = self.describe_event();
+        match self.held_for() {
+            Some(held_for) => format!("{described} for {}", humanize(held_for)),
+            None => described,
+        }
+    }
+
+    fn describe_event(&self) -> String {
         match self {
-            TriggerMatcher::Presence { sensor, present } => {
+            TriggerMatcher::Presence {
+                sensor, present, ..
+            } => {
                 format!("presence({sensor}) -> {present}")
             }
-            TriggerMatcher::Door { ieee_addr, open } => {
+            TriggerMatcher::Room { room, occupied } => {
+                format!(
+                    "room({room}) -> {}",
+                    if *occupied { "occupied" } else { "empty" }
+                )
+            }
+            TriggerMatcher::Door {
+                ieee_addr, open, ..
+            } => {
                 format!(
                     "door({ieee_addr}) -> {}",
                     if *open { "open" } else { "closed" }
@@ -148,6 +252,7 @@ impl TriggerMatcher {
                 sensor,
                 metric,
                 cmp,
+                ..
             } => {
                 format!(
                     "environment({sensor}).{metric:?} {:?} {}",
@@ -157,7 +262,9 @@ impl TriggerMatcher {
             TriggerMatcher::Mode { mode, active } => {
                 format!("mode({}) -> {active}", mode.as_str())
             }
-            TriggerMatcher::HomeAssistant { entity_id, state } => match state {
+            TriggerMatcher::HomeAssistant {
+                entity_id, state, ..
+            } => match state {
                 Some(state) => format!("home_assistant({entity_id}) -> {state}"),
                 None => format!("home_assistant({entity_id})"),
             },
@@ -203,7 +310,9 @@ impl TriggerMatcher {
                     None => format!("jellyfin({subject})"),
                 }
             }
-            TriggerMatcher::MediaPlayer { device, state, app } => {
+            TriggerMatcher::MediaPlayer {
+                device, state, app, ..
+            } => {
                 let subject = device
                     .clone()
                     .or_else(|| app.clone())
@@ -216,6 +325,50 @@ impl TriggerMatcher {
             TriggerMatcher::Solar { metric, cmp } => {
                 format!("solar.{} {:?} {}", metric.var_name(), cmp.op, cmp.value)
             }
+            TriggerMatcher::Variable { variable } => format!("variable({variable}) changed"),
+            TriggerMatcher::Light { ieee_addr, on, .. } => {
+                format!("light({ieee_addr}) -> {}", if *on { "on" } else { "off" })
+            }
+            TriggerMatcher::Unifi {
+                client,
+                mac_address,
+                connected,
+            } => {
+                let subject = client
+                    .clone()
+                    .or_else(|| mac_address.clone())
+                    .unwrap_or_else(|| "*".to_owned());
+                format!(
+                    "unifi({subject}) -> {}",
+                    if *connected {
+                        "connected"
+                    } else {
+                        "disconnected"
+                    }
+                )
+            }
+            TriggerMatcher::DeviceAvailability { device, available } => {
+                format!(
+                    "device({}) -> {}",
+                    device.as_deref().unwrap_or("*"),
+                    if *available { "online" } else { "offline" }
+                )
+            }
+            TriggerMatcher::NotificationAction { action, source } => {
+                format!(
+                    "notification({}) -> {action}",
+                    source.as_deref().unwrap_or("*")
+                )
+            }
+            TriggerMatcher::RobotVacuum { device, from, to } => {
+                let any = || "*".to_owned();
+                format!(
+                    "robot_vacuum({}) {} -> {}",
+                    device.clone().unwrap_or_else(any),
+                    from.clone().unwrap_or_else(any),
+                    to.clone().unwrap_or_else(any),
+                )
+            }
             TriggerMatcher::Cron { schedule } => format!("cron({})", schedule.expression()),
             TriggerMatcher::Sun { transition, offset } => {
                 if offset.is_zero() {
@@ -237,6 +390,7 @@ impl TriggerMatcher {
         let strs = |v: &[&str]| v.iter().map(|s| (*s).to_owned()).collect();
         match self {
             TriggerMatcher::Presence { .. } => strs(&["sensor", "present"]),
+            TriggerMatcher::Room { .. } => strs(&["sensor", "present", "room", "occupied"]),
             TriggerMatcher::Door { .. } => strs(&["device", "open"]),
             TriggerMatcher::Switch { .. } => strs(&["device", "action"]),
             TriggerMatcher::Environment { metric, .. } => {
@@ -293,19 +447,67 @@ impl TriggerMatcher {
                 "muted",
             ]),
             TriggerMatcher::Solar { .. } => strs(&["current", "avg_15m", "avg_1h", "avg_3h"]),
+            TriggerMatcher::Variable { .. } => strs(&["variable", "value", "previous"]),
+            TriggerMatcher::Light { .. } => strs(&["device", "on"]),
+            TriggerMatcher::Unifi { .. } => strs(&["mac_address", "client", "connected"]),
+            TriggerMatcher::RobotVacuum { .. } => strs(&["device", "name", "state", "previous"]),
+            TriggerMatcher::DeviceAvailability { .. } => {
+                strs(&["device", "address", "available", "last_seen"])
+            }
+            TriggerMatcher::NotificationAction { .. } => {
+                strs(&["origin_event_id", "notification", "source", "action"])
+            }
+        }
+    }
+
+    /// A `for:` must be positive, and needs the gates that pin down a state to
+    /// hold: a `home_assistant` trigger's `state`, and a `media_player`
+    /// trigger's `device` and `state`.
+    pub(super) fn validate_held(&self) -> Result<(), String> {
+        let Some(held_for) = self.held_for() else {
+            return Ok(());
+        };
+
+        if held_for <= TimeDelta::zero() {
+            return Err("trigger `for` must be at least 1s".to_owned());
+        }
+
+        match self {
+            TriggerMatcher::HomeAssistant { state: None, .. } => {
+                Err("a home_assistant trigger needs a `state` to hold `for`".to_owned())
+            }
+            TriggerMatcher::MediaPlayer { device, state, .. }
+                if device.is_none() || state.is_none() =>
+            {
+                Err("a media_player trigger needs a `device` and `state` to hold `for`".to_owned())
+            }
+            _ => Ok(()),
         }
     }
 
     pub(super) fn resolve_devices(&mut self, devices: &DeviceAliases) -> Result<(), String> {
         match self {
-            TriggerMatcher::Door { ieee_addr, .. } | TriggerMatcher::Switch { ieee_addr, .. } => {
+            TriggerMatcher::Door { ieee_addr, .. }
+            | TriggerMatcher::Switch { ieee_addr, .. }
+            | TriggerMatcher::Light { ieee_addr, .. } => {
                 validate_device(ieee_addr, devices)?;
             }
+            TriggerMatcher::RobotVacuum {
+                device: Some(device),
+                ..
+            }
+            | TriggerMatcher::DeviceAvailability {
+                device: Some(device),
+                ..
+            } => {
+                validate_device(device, devices)?;
+            }
             TriggerMatcher::Presence { sensor, .. }
             | TriggerMatcher::Environment { sensor, .. } => {
                 validate_device(sensor, devices)?;
             }
-            TriggerMatcher::Cron { .. }
+            TriggerMatcher::Room { .. }
+            | TriggerMatcher::Cron { .. }
             | TriggerMatcher::Sun { .. }
             | TriggerMatcher::Mode { .. }
             | TriggerMatcher::HomeAssistant { .. }
@@ -313,7 +515,12 @@ impl TriggerMatcher {
             | TriggerMatcher::DeviceBattery { .. }
             | TriggerMatcher::Jellyfin { .. }
             | TriggerMatcher::MediaPlayer { .. }
-            | TriggerMatcher::Solar { .. } => {}
+            | TriggerMatcher::Solar { .. }
+            | TriggerMatcher::Variable { .. }
+            | TriggerMatcher::Unifi { .. }
+            | TriggerMatcher::RobotVacuum { device: None, .. }
+            | TriggerMatcher::DeviceAvailability { device: None, .. }
+            | TriggerMatcher::NotificationAction { .. } => {}
         }
         Ok(())
     }
@@ -1,3 +1,8 @@
+use crate::actors::system::cron::schedule::CronSchedule;
+use crate::settings::eink::{
+    DitherConfig, EinkDisplaySettings, Orientation, Panel, PartialRefresh, RawEinkMode,
+    RawSleepWindow, check_lead,
+};
 use crate::timedelta_format::time_delta_from_str;
 use chrono::TimeDelta;
 use schemars::JsonSchema;
@@ -29,13 +34,142 @@ impl Default for TrmnlSettings {
     }
 }
 
+/// Lowercase alphanumerics only, so `94:A9:90:CF:83:84`, `94a990cf8384` and a
+/// friendly id compare the way the device and the cloud spell them.
+pub fn normalize_address(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
 #[derive(Debug, Clone, Deserialize, JsonSchema)]
 pub struct RawTrmnlBlock {
     pub(crate) name: String,
+    #[serde(default)]
+    pub(crate) byos: Option<RawTrmnlByos>,
+}
+
+/// Serve this device ourselves over the TRMNL "bring your own server"
+/// protocol instead of leaving it on the TRMNL cloud.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct RawTrmnlByos {
+    mode: RawEinkMode,
+    refresh: CronSchedule,
+    #[serde(with = "time_delta_from_str")]
+    #[schemars(with = "String")]
+    grace: TimeDelta,
+    #[serde(default)]
+    sleep: Option<RawSleepWindow>,
+    #[serde(default)]
+    #[schemars(with = "u8")]
+    bit_depth: TrmnlBitDepth,
+    #[serde(default)]
+    dither: DitherConfig,
+    /// Let `/api/setup` pair this device, handing it a key or replacing the one
+    /// it already holds after a reset. Switch it back off once the device has
+    /// its key.
+    #[serde(default)]
+    allow_setup: bool,
+}
+
+impl RawTrmnlByos {
+    pub(crate) fn resolve(
+        self,
+        id: &str,
+        name: &str,
+        address: &str,
+    ) -> Result<(EinkDisplaySettings, TrmnlByos), String> {
+        let mac = normalize_address(address);
+        if mac.len() != 12 || !mac.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!(
+                "device {id}: a `byos` trmnl is found by the MAC it sends, so its address \
+                 must be that MAC, got `{address}`"
+            ));
+        }
+
+        let sleep = self.sleep.map(|s| s.resolve(id, self.grace)).transpose()?;
+        let mode = self.mode.resolve(id)?;
+        check_lead(id, self.grace, &mode)?;
+
+        let display = EinkDisplaySettings {
+            name: name.to_owned(),
+            // the device updates itself from the TRMNL cloud, never from us
+            firmware_version: String::new(),
+            mode,
+            orientation: Orientation::Landscape,
+            panel: self.bit_depth.panel(),
+            refresh: self.refresh,
+            grace: self.grace,
+            sleep,
+            partial: PartialRefresh {
+                enabled: false,
+                max_area_pct: 100,
+                max_consecutive: 1,
+            },
+            dither: self.dither,
+        };
+
+        Ok((
+            display,
+            TrmnlByos {
+                mac,
+                allow_setup: self.allow_setup,
+            },
+        ))
+    }
+}
+
+/// Grey levels the panel is dithered to: 1-bit for the stock firmware, 2-bit
+/// for firmware that can show four greys.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "u8")]
+pub enum TrmnlBitDepth {
+    #[default]
+    One,
+    Two,
+}
+
+impl TrmnlBitDepth {
+    pub fn panel(self) -> Panel {
+        match self {
+            TrmnlBitDepth::One => Panel::Trmnl1Bit,
+            TrmnlBitDepth::Two => Panel::Trmnl2Bit,
+        }
+    }
+}
+
+impl TryFrom<u8> for TrmnlBitDepth {
+    type Error = String;
+
+    fn try_from(bits: u8) -> Result<Self, Self::Error> {
+        match bits {
+            1 => Ok(TrmnlBitDepth::One),
+            2 => Ok(TrmnlBitDepth::Two),
+            other => Err(format!("trmnl bit_depth must be 1 or 2, got {other}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TrmnlByos {
+    /// Normalised with [`normalize_address`].
+    pub mac: String,
+    /// Whether `/api/setup` may pair the device or replace its existing key.
+    pub allow_setup: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct TrmnlDeviceSettings {
     pub id: String,
     pub name: String,
+    pub byos: Option<TrmnlByos>,
+}
+
+impl TrmnlDeviceSettings {
+    /// Name of the api key handed to this device by `/api/setup`.
+    pub fn key_name(&self) -> String {
+        format!("trmnl:{}", self.id)
+    }
 }
@@ -0,0 +1,130 @@
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// A workflow variable declared under the top-level `variables:` key, e.g. a
+/// counter of front door openings or when the bins reminder was last
+/// acknowledged. Values live in the `state` table under `var:<name>`, stored as
+/// text in the normalised form [`Variable::parse`] produces.
+#[derive(Debug, Deserialize, Clone, PartialEq, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Variable {
+    Number {
+        #[serde(default)]
+        default: f64,
+    },
+    Boolean {
+        #[serde(default)]
+        default: bool,
+    },
+    #[serde(rename = "string")]
+    Text {
+        #[serde(default)]
+        default: String,
+    },
+    /// RFC 3339, or `now` when set. Unset until first written.
+    Timestamp,
+}
+
+impl Variable {
+    pub fn state_key(name: &str) -> String {
+        format!("var:{name}")
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Variable::Number { .. } => "number",
+            Variable::Boolean { .. } => "boolean",
+            Variable::Text { .. } => "string",
+            Variable::Timestamp => "timestamp",
+        }
+    }
+
+    /// The value before anything has been written.
+    pub fn initial(&self) -> Option<String> {
+        match self {
+            Variable::Number { default } => Some(default.to_string()),
+            Variable::Boolean { default } => Some(default.to_string()),
+            Variable::Text { default } => Some(default.clone()),
+            Variable::Timestamp => None,
+        }
+    }
+
+    /// Parse `raw` as this variable's type, into the form it is stored in.
+    pub fn parse(&self, raw: &str) -> Result<String, String> {
+        let trimmed = raw.trim();
+        let invalid = || format!("`{raw}` is not a valid {}", self.type_name());
+
+        match self {
+            Variable::Number { .. } => match trimmed.parse::<f64>() {
+                Ok(n) if n.is_finite() => Ok(n.to_string()),
+                _ => Err(invalid()),
+            },
+            Variable::Boolean { .. } => match trimmed.to_ascii_lowercase().as_str() {
+                "true" | "on" | "yes" | "1" => Ok("true".to_owned()),
+                "false" | "off" | "no" | "0" => Ok("false".to_owned()),
+                _ => Err(invalid()),
+            },
+            Variable::Text { .. } => Ok(raw.to_owned()),
+            Variable::Timestamp if trimmed.eq_ignore_ascii_case("now") => {
+                Ok(Utc::now().to_rfc3339())
+            }
+            Variable::Timestamp => DateTime::parse_from_rfc3339(trimmed)
+                .map(|at| at.to_utc().to_rfc3339())
+                .map_err(|_| invalid()),
+        }
+    }
+
+    /// A stored value as a number for a `variable` condition: numbers as is,
+    /// booleans as `1`/`0`.
+    pub fn number(&self, stored: &str) -> Option<f64> {
+        match self {
+            Variable::Number { .. } => stored.parse().ok(),
+            Variable::Boolean { .. } => Some(if stored == "true" { 1.0 } else { 0.0 }),
+            Variable::Text { .. } | Variable::Timestamp => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_parse_into_their_stored_form() {
+        let number = Variable::Number { default: 0.0 };
+        assert_eq!(number.parse(" 3.0 ").unwrap(), "3");
+        assert_eq!(number.parse("2.5").unwrap(), "2.5");
+        assert!(number.parse("three").is_err());
+        assert!(number.parse("NaN").is_err());
+
+        let boolean = Variable::Boolean { default: false };
+        assert_eq!(boolean.parse("ON").unwrap(), "true");
+        assert_eq!(boolean.parse("0").unwrap(), "false");
+        assert!(boolean.parse("maybe").is_err());
+
+        let timestamp = Variable::Timestamp;
+        assert_eq!(
+            timestamp.parse("2026-10-18T08:00:00+08:00").unwrap(),
+            "2026-10-18T00:00:00+00:00"
+        );
+        assert!(timestamp.parse("now").is_ok());
+        assert!(timestamp.parse("yesterday").is_err());
+    }
+
+    #[test]
+    fn only_numbers_and_booleans_compare_as_numbers() {
+        assert_eq!(Variable::Number { default: 0.0 }.number("4"), Some(4.0));
+        assert_eq!(
+            Variable::Boolean { default: false }.number("true"),
+            Some(1.0)
+        );
+        assert_eq!(
+            Variable::Text {
+                default: String::new()
+            }
+            .number("4"),
+            None
+        );
+    }
+}
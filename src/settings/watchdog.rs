@@ -1,5 +1,6 @@
+use super::device::DeviceWatchdog;
 use crate::timedelta_format::time_delta_from_str;
-use chrono::TimeDelta;
+use chrono::{DateTime, TimeDelta, Utc};
 use schemars::JsonSchema;
 use serde::Deserialize;
 
@@ -17,3 +18,43 @@ pub struct WatchdogSettings {
     #[schemars(with = "String")]
     pub realert_after: TimeDelta,
 }
+
+impl WatchdogSettings {
+    /// Whether a device last heard from at `last_seen` still counts as
+    /// available: within its own `watchdog.timeout`, else the global one.
+    pub fn available(
+        &self,
+        device: Option<&DeviceWatchdog>,
+        last_seen: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> bool {
+        let timeout = device.and_then(|d| d.timeout).unwrap_or(self.timeout);
+        last_seen >= now - timeout
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_device_timeout_overrides_the_global_one() {
+        let watchdog = WatchdogSettings {
+            enabled: true,
+            timeout: TimeDelta::minutes(30),
+            check_interval: TimeDelta::minutes(5),
+            realert_after: TimeDelta::hours(6),
+        };
+        let device = DeviceWatchdog {
+            timeout: Some(TimeDelta::hours(2)),
+            notify: Vec::new(),
+            actions: Vec::new(),
+        };
+        let now = Utc::now();
+        let last_seen = now - TimeDelta::hours(1);
+
+        assert!(!watchdog.available(None, last_seen, now));
+        assert!(watchdog.available(Some(&device), last_seen, now));
+        assert!(!watchdog.available(Some(&device), now - TimeDelta::hours(3), now));
+    }
+}
@@ -1,8 +1,11 @@
 use crate::device_registry::{Capability, DeviceRegistry};
-use crate::settings::NotifySource;
 use crate::settings::TemplateString;
+use crate::settings::Variable;
+use crate::settings::notify::{NotifyAction, NotifyRef, NotifyTargets, validate_actions};
+use crate::settings::room::{Rooms, validate_room};
+use crate::settings::template::{Lookup, TemplateNumber, json_templates};
 use crate::settings::trigger::TriggerMatcher;
-use crate::timedelta_format::option_time_delta_from_str;
+use crate::timedelta_format::{option_time_delta_from_str, time_delta_from_str};
 
 use super::{DeviceAliases, IEEEAddress, validate_device, yes};
 use crate::actors::sun::calc::SunPeriod;
@@ -10,6 +13,8 @@ use crate::mode::Mode;
 use chrono::{NaiveTime, TimeDelta};
 use schemars::JsonSchema;
 use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
 
 /// Brightness / colour-temperature mutations applied to a light. Kept in
 /// `SCREAMING_SNAKE_CASE` to match the long-standing on-disk config.
@@ -20,15 +25,15 @@ pub enum LightState {
     Off,
     Toggle,
     SetBrightness {
-        value: u64,
+        value: TemplateNumber,
     },
     IncreaseBrightness {
-        value: u64,
+        value: TemplateNumber,
         #[serde(default)]
         on_off: bool,
     },
     DecreaseBrightness {
-        value: u64,
+        value: TemplateNumber,
         #[serde(default)]
         on_off: bool,
     },
@@ -55,6 +60,17 @@ impl LightState {
             | LightState::StopColourTemperature => Some(Capability::ColourTemp),
         }
     }
+
+    fn templates(&self) -> Vec<TemplateString> {
+        match self {
+            LightState::SetBrightness { value }
+            | LightState::IncreaseBrightness { value, .. }
+            | LightState::DecreaseBrightness { value, .. } => {
+                value.template().cloned().into_iter().collect()
+            }
+            _ => Vec::new(),
+        }
+    }
 }
 
 /// Target enablement for a `set_workflows_enabled` step. `Toggle` flips the
@@ -77,7 +93,7 @@ pub enum SwitchState {
 }
 
 /// Which reading of an environment sensor a condition compares against.
-#[derive(Debug, Deserialize, Clone, Copy, JsonSchema)]
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum EnvMetric {
     Temperature,
@@ -180,6 +196,25 @@ pub enum LeafCondition {
         mode: Mode,
         active: bool,
     },
+    /// Compares a declared number or boolean variable; booleans read as
+    /// `1`/`0`.
+    Variable {
+        variable: String,
+        #[serde(flatten)]
+        cmp: Comparison,
+    },
+    /// Whether a device has been heard from within its watchdog timeout,
+    /// e.g. to fall back to a timer while a presence sensor is offline.
+    Available {
+        device: String,
+        available: bool,
+    },
+    /// Whether any presence sensor in a declared room currently detects
+    /// someone.
+    RoomOccupied {
+        room: String,
+        occupied: bool,
+    },
 }
 
 impl Condition {
@@ -196,6 +231,30 @@ impl Condition {
             Condition::Leaf(l) => l.describe(),
         }
     }
+
+    /// Variables the condition compares, through any combinators.
+    fn variables<'a>(&'a self, out: &mut Vec<&'a str>) {
+        match self {
+            Condition::Combinator(Combinator::All(conditions) | Combinator::Any(conditions)) => {
+                conditions.iter().for_each(|c| c.variables(out));
+            }
+            Condition::Combinator(Combinator::Not(condition)) => condition.variables(out),
+            Condition::Leaf(LeafCondition::Variable { variable, .. }) => out.push(variable),
+            Condition::Leaf(_) => {}
+        }
+    }
+
+    /// Rooms the condition tests, through any combinators.
+    fn rooms<'a>(&'a self, out: &mut Vec<&'a str>) {
+        match self {
+            Condition::Combinator(Combinator::All(conditions) | Combinator::Any(conditions)) => {
+                conditions.iter().for_each(|c| c.rooms(out));
+            }
+            Condition::Combinator(Combinator::Not(condition)) => condition.rooms(out),
+            Condition::Leaf(LeafCondition::RoomOccupied { room, .. }) => out.push(room),
+            Condition::Leaf(_) => {}
+        }
+    }
 }
 
 impl Combinator {
@@ -223,13 +282,19 @@ impl Combinator {
 impl LeafCondition {
     fn resolve_devices(&mut self, devices: &DeviceAliases) -> Result<(), String> {
         match self {
-            LeafCondition::Light { ieee_addr, .. } | LeafCondition::Door { ieee_addr, .. } => {
+            LeafCondition::Light { ieee_addr, .. }
+            | LeafCondition::Door { ieee_addr, .. }
+            | LeafCondition::Available {
+                device: ieee_addr, ..
+            } => {
                 validate_device(ieee_addr, devices)?;
             }
             LeafCondition::Environment { .. }
             | LeafCondition::Presence { .. }
             | LeafCondition::TimeOfDay { .. }
             | LeafCondition::Mode { .. }
+            | LeafCondition::Variable { .. }
+            | LeafCondition::RoomOccupied { .. }
             | LeafCondition::Sun { .. } => {}
         }
         Ok(())
@@ -273,6 +338,21 @@ impl LeafCondition {
             LeafCondition::Mode { mode, active } => {
                 format!("mode({}) is {active}", mode.as_str())
             }
+            LeafCondition::Variable { variable, cmp } => {
+                format!("var({variable}) {:?} {}", cmp.op, cmp.value)
+            }
+            LeafCondition::Available { device, available } => {
+                format!(
+                    "device({device}) is {}",
+                    if *available { "online" } else { "offline" }
+                )
+            }
+            LeafCondition::RoomOccupied { room, occupied } => {
+                format!(
+                    "room({room}) is {}",
+                    if *occupied { "occupied" } else { "empty" }
+                )
+            }
         }
     }
 }
@@ -300,10 +380,18 @@ pub enum Step {
         #[serde(default)]
         when: Option<Condition>,
     },
+    /// Applies `state` to every light in a declared room, expanded over the
+    /// registry when the step runs.
+    RoomLights {
+        room: String,
+        #[serde(flatten)]
+        state: LightState,
+        #[serde(default)]
+        when: Option<Condition>,
+    },
     Switch {
         #[serde(rename = "device", alias = "ieeeAddr")]
         ieee_addr: IEEEAddress,
-        #[allow(unused)]
         state: SwitchState,
         #[serde(default)]
         when: Option<Condition>,
@@ -314,8 +402,12 @@ pub enum Step {
         when: Option<Condition>,
     },
     Notify {
-        notify: NotifySource,
+        /// A `notify_targets:` name, or an inline target.
+        notify: NotifyRef,
         message: TemplateString,
+        /// Reply buttons; tapping one publishes a `notification_action` event.
+        #[serde(default)]
+        actions: Vec<NotifyAction>,
         #[serde(default)]
         when: Option<Condition>,
     },
@@ -341,6 +433,22 @@ pub enum Step {
         #[serde(default)]
         when: Option<Condition>,
     },
+    /// Write a declared variable. `value` is rendered, then parsed as the
+    /// variable's type.
+    SetVariable {
+        variable: String,
+        value: TemplateString,
+        #[serde(default)]
+        when: Option<Condition>,
+    },
+    /// Add `by` to a number variable; negative counts down.
+    IncrementVariable {
+        variable: String,
+        #[serde(default = "one")]
+        by: f64,
+        #[serde(default)]
+        when: Option<Condition>,
+    },
     HomeAssistant {
         #[serde(rename = "call_service")]
         call_service: String,
@@ -349,6 +457,80 @@ pub enum Step {
         #[serde(default)]
         when: Option<Condition>,
     },
+    /// Runs every branch at once and carries on when they have all finished.
+    /// A `delay` inside sleeps on the worker rather than parking, so it isn't
+    /// durable: a restart loses the rest of the step. Everything inside may
+    /// hold the worker for at most [`MAX_WAIT`].
+    Parallel {
+        branches: Vec<Vec<Step>>,
+        #[serde(default)]
+        when: Option<Condition>,
+    },
+    /// Runs the first option whose `if` holds, or `else` when none does.
+    Choose {
+        options: Vec<ChooseOption>,
+        #[serde(default, rename = "else")]
+        otherwise: Vec<Step>,
+        #[serde(default)]
+        when: Option<Condition>,
+    },
+    /// Runs `run` `count` times, while a condition holds (checked before each
+    /// pass) or until one does (checked after). Stops at [`MAX_REPEAT`]
+    /// passes. As in `parallel`, a `delay` inside sleeps on the worker and
+    /// doesn't survive a restart, and every pass together may hold the worker
+    /// for at most [`MAX_WAIT`].
+    Repeat {
+        #[serde(default)]
+        count: Option<u32>,
+        #[serde(default, rename = "while")]
+        repeat_while: Option<Condition>,
+        #[serde(default)]
+        until: Option<Condition>,
+        run: Vec<Step>,
+        #[serde(default)]
+        when: Option<Condition>,
+    },
+    /// Holds the workflow until `condition` holds or an `event` matching the
+    /// trigger-style matcher arrives, for at most `timeout` (capped at
+    /// [`MAX_WAIT`]). A timeout fails the step unless `continue_on_timeout`.
+    WaitFor {
+        #[serde(default)]
+        condition: Option<Condition>,
+        #[serde(default)]
+        event: Option<TriggerMatcher>,
+        #[serde(with = "time_delta_from_str")]
+        #[schemars(with = "String")]
+        timeout: TimeDelta,
+        #[serde(default = "yes")]
+        continue_on_timeout: bool,
+        #[serde(default)]
+        when: Option<Condition>,
+    },
+}
+
+/// One branch of a `choose`.
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
+pub struct ChooseOption {
+    #[serde(rename = "if")]
+    pub condition: Condition,
+    pub run: Vec<Step>,
+}
+
+/// The most passes a `repeat` makes before giving up.
+pub const MAX_REPEAT: u32 = 100;
+
+/// The longest a step may hold a worker: a `wait_for`, or everything a
+/// `parallel` or `repeat` runs in place. Anything longer is a `delay`.
+pub const MAX_WAIT: TimeDelta = TimeDelta::hours(1);
+
+/// The longest `steps` could hold a worker run in place, one after another.
+fn held_in_place(steps: &[Step]) -> TimeDelta {
+    steps
+        .iter()
+        .map(Step::held_in_place)
+        .fold(TimeDelta::zero(), |total, held| {
+            total.checked_add(&held).unwrap_or(TimeDelta::MAX)
+        })
 }
 
 impl Step {
@@ -356,6 +538,7 @@ impl Step {
     pub fn kind(&self) -> &'static str {
         match self {
             Step::Light { .. } => "light",
+            Step::RoomLights { .. } => "room_lights",
             Step::Switch { .. } => "switch",
             Step::Scene { .. } => "scene",
             Step::Notify { .. } => "notify",
@@ -363,7 +546,13 @@ impl Step {
             Step::RunWorkflow { .. } => "run_workflow",
             Step::SetMode { .. } => "set_mode",
             Step::SetWorkflowsEnabled { .. } => "set_workflows_enabled",
+            Step::SetVariable { .. } => "set_variable",
+            Step::IncrementVariable { .. } => "increment_variable",
             Step::HomeAssistant { .. } => "home_assistant",
+            Step::Parallel { .. } => "parallel",
+            Step::Choose { .. } => "choose",
+            Step::Repeat { .. } => "repeat",
+            Step::WaitFor { .. } => "wait_for",
         }
     }
 
@@ -371,6 +560,7 @@ impl Step {
     pub fn guard(&self) -> Option<&Condition> {
         match self {
             Step::Light { when, .. }
+            | Step::RoomLights { when, .. }
             | Step::Switch { when, .. }
             | Step::Scene { when, .. }
             | Step::Notify { when, .. }
@@ -378,7 +568,83 @@ impl Step {
             | Step::RunWorkflow { when, .. }
             | Step::SetMode { when, .. }
             | Step::SetWorkflowsEnabled { when, .. }
-            | Step::HomeAssistant { when, .. } => when.as_ref(),
+            | Step::SetVariable { when, .. }
+            | Step::IncrementVariable { when, .. }
+            | Step::HomeAssistant { when, .. }
+            | Step::Parallel { when, .. }
+            | Step::Choose { when, .. }
+            | Step::Repeat { when, .. }
+            | Step::WaitFor { when, .. } => when.as_ref(),
+        }
+    }
+
+    /// The guard and any conditions the step itself tests: a `choose`'s
+    /// options, a `repeat`'s `while`/`until` and a `wait_for`'s `condition`.
+    fn conditions(&self) -> Vec<&Condition> {
+        let mut out: Vec<&Condition> = self.guard().into_iter().collect();
+        match self {
+            Step::Choose { options, .. } => out.extend(options.iter().map(|o| &o.condition)),
+            Step::Repeat {
+                repeat_while,
+                until,
+                ..
+            } => out.extend(repeat_while.iter().chain(until)),
+            Step::WaitFor { condition, .. } => out.extend(condition),
+            _ => {}
+        }
+        out
+    }
+
+    /// The longest the step could hold a worker when run in place, as inside
+    /// `parallel` or `repeat`: its `delay`s and `wait_for` timeouts along the
+    /// slowest path, over every pass of a `repeat`. A `run_workflow` counts as
+    /// nothing, as its steps aren't known here; the executor caps those.
+    pub fn held_in_place(&self) -> TimeDelta {
+        match self {
+            Step::Delay { seconds, .. } => i64::try_from(*seconds)
+                .ok()
+                .and_then(TimeDelta::try_seconds)
+                .unwrap_or(TimeDelta::MAX),
+            Step::WaitFor { timeout, .. } => *timeout,
+            Step::Scene { run, .. } => held_in_place(run),
+            Step::Parallel { .. } | Step::Choose { .. } => self
+                .children()
+                .into_iter()
+                .map(held_in_place)
+                .max()
+                .unwrap_or_default(),
+            Step::Repeat { count, run, .. } => {
+                let passes = count.unwrap_or(MAX_REPEAT);
+                i32::try_from(passes)
+                    .ok()
+                    .and_then(|passes| held_in_place(run).checked_mul(passes))
+                    .unwrap_or(TimeDelta::MAX)
+            }
+            _ => TimeDelta::zero(),
+        }
+    }
+
+    /// A digest of `steps` as configured, which a parked `delay` keeps so an
+    /// edit to them while it waits is caught before it resumes.
+    pub fn digest(steps: &[Step]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(format!("{steps:?}").as_bytes());
+        hex::encode(hasher.finalize())
+    }
+
+    /// The step lists nested directly in this one.
+    pub fn children(&self) -> Vec<&[Step]> {
+        match self {
+            Step::Scene { run, .. } | Step::Repeat { run, .. } => vec![run.as_slice()],
+            Step::Parallel { branches, .. } => branches.iter().map(Vec::as_slice).collect(),
+            Step::Choose {
+                options, otherwise, ..
+            } => options
+                .iter()
+                .map(|o| o.run.as_slice())
+                .chain([otherwise.as_slice()])
+                .collect(),
+            _ => Vec::new(),
         }
     }
 
@@ -387,12 +653,27 @@ impl Step {
             Step::Light {
                 ieee_addr, state, ..
             } => Some(format!("light({ieee_addr}) -> {state:?}")),
+            Step::RoomLights { room, state, .. } => {
+                Some(format!("room_lights({room}) -> {state:?}"))
+            }
             Step::Switch {
                 ieee_addr, state, ..
             } => Some(format!("switch({ieee_addr}) -> {state:?}")),
+            Step::Notify {
+                notify,
+                message,
+                actions,
+                ..
+            } if !actions.is_empty() => {
+                let ids: Vec<&str> = actions.iter().map(|a| a.id.as_str()).collect();
+                Some(format!(
+                    "notify({notify}): {message} [actions: {}]",
+                    ids.join(", ")
+                ))
+            }
             Step::Notify {
                 notify, message, ..
-            } => Some(format!("notify({notify:?}): {message}")),
+            } => Some(format!("notify({notify}): {message}")),
             Step::Delay { seconds, .. } => Some(format!("delay {seconds}s")),
             Step::SetMode { mode, active, .. } => {
                 Some(format!("set_mode({}) -> {active}", mode.as_str()))
@@ -400,10 +681,44 @@ impl Step {
             Step::SetWorkflowsEnabled { tag, state, .. } => {
                 Some(format!("set_workflows_enabled(#{tag}) -> {state:?}"))
             }
+            Step::SetVariable {
+                variable, value, ..
+            } => Some(format!("set_variable({variable}) -> {value}")),
+            Step::IncrementVariable { variable, by, .. } => {
+                Some(format!("increment_variable({variable}) by {by}"))
+            }
             Step::HomeAssistant {
                 call_service, data, ..
             } => Some(format!("home_assistant({call_service}) {data}")),
-            Step::Scene { .. } | Step::RunWorkflow { .. } => None,
+            Step::WaitFor {
+                condition,
+                event,
+                timeout,
+                ..
+            } => {
+                let on = match (condition, event) {
+                    (Some(condition), _) => condition.describe(),
+                    (None, Some(event)) => event.describe(),
+                    (None, None) => String::new(),
+                };
+                Some(format!("wait_for({on}) up to {}s", timeout.num_seconds()))
+            }
+            Step::Scene { .. }
+            | Step::RunWorkflow { .. }
+            | Step::Parallel { .. }
+            | Step::Choose { .. }
+            | Step::Repeat { .. } => None,
+        }
+    }
+
+    /// Templates in this step's own fields, not those of nested steps.
+    pub fn templates(&self) -> Vec<TemplateString> {
+        match self {
+            Step::Notify { message, .. } => vec![message.clone()],
+            Step::SetVariable { value, .. } => vec![value.clone()],
+            Step::HomeAssistant { data, .. } => json_templates(data),
+            Step::Light { state, .. } | Step::RoomLights { state, .. } => state.templates(),
+            _ => Vec::new(),
         }
     }
 
@@ -424,11 +739,62 @@ impl Step {
                 }
                 resolve_opt(when, devices)?;
             }
-            Step::Notify { when, .. }
+            Step::Repeat {
+                repeat_while,
+                until,
+                run,
+                when,
+                ..
+            } => {
+                for step in run {
+                    step.resolve_devices(devices)?;
+                }
+                resolve_opt(repeat_while, devices)?;
+                resolve_opt(until, devices)?;
+                resolve_opt(when, devices)?;
+            }
+            Step::Parallel { branches, when } => {
+                for step in branches.iter_mut().flatten() {
+                    step.resolve_devices(devices)?;
+                }
+                resolve_opt(when, devices)?;
+            }
+            Step::Choose {
+                options,
+                otherwise,
+                when,
+            } => {
+                for option in options {
+                    option.condition.resolve_devices(devices)?;
+                    for step in &mut option.run {
+                        step.resolve_devices(devices)?;
+                    }
+                }
+                for step in otherwise {
+                    step.resolve_devices(devices)?;
+                }
+                resolve_opt(when, devices)?;
+            }
+            Step::WaitFor {
+                condition,
+                event,
+                when,
+                ..
+            } => {
+                resolve_opt(condition, devices)?;
+                if let Some(event) = event {
+                    event.resolve_devices(devices)?;
+                }
+                resolve_opt(when, devices)?;
+            }
+            Step::RoomLights { when, .. }
+            | Step::Notify { when, .. }
             | Step::Delay { when, .. }
             | Step::RunWorkflow { when, .. }
             | Step::SetMode { when, .. }
             | Step::SetWorkflowsEnabled { when, .. }
+            | Step::SetVariable { when, .. }
+            | Step::IncrementVariable { when, .. }
             | Step::HomeAssistant { when, .. } => resolve_opt(when, devices)?,
         }
         Ok(())
@@ -448,17 +814,44 @@ impl Step {
                     ));
                 }
             }
-            Step::Scene { run, .. } => {
-                for step in run {
+            Step::RoomLights { room, state, .. } => {
+                if let Some(required) = state.required_capability() {
+                    for address in registry.room_members(room) {
+                        if registry.light(address).is_some()
+                            && !registry.capabilities(address).contains(&required)
+                        {
+                            return Err(format!(
+                                "light {} in room {room} does not support {required:?}: {state:?}",
+                                registry.id_for_address(address).unwrap_or(address)
+                            ));
+                        }
+                    }
+                }
+            }
+            Step::Switch { ieee_addr, .. } => {
+                if registry
+                    .smart_switch(registry.address_or_self(ieee_addr))
+                    .is_none()
+                {
+                    return Err(format!(
+                        "switch step targets {ieee_addr}, which is not a smart switch"
+                    ));
+                }
+            }
+            step => {
+                for step in step.children().into_iter().flatten() {
                     step.validate_capabilities(registry)?;
                 }
             }
-            _ => {}
         }
         Ok(())
     }
 }
 
+fn one() -> f64 {
+    1.0
+}
+
 fn resolve_opt(when: &mut Option<Condition>, devices: &DeviceAliases) -> Result<(), String> {
     if let Some(when) = when {
         when.resolve_devices(devices)?;
@@ -466,6 +859,41 @@ fn resolve_opt(when: &mut Option<Condition>, devices: &DeviceAliases) -> Result<
     Ok(())
 }
 
+/// An input a reusable workflow takes when it is run by slug, rendered into
+/// its templates as `${name}` like a trigger's vars.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct WorkflowParam {
+    #[serde(rename = "type", default)]
+    pub kind: ParamKind,
+    /// Used when the input is left out; without one the input is required.
+    #[serde(default)]
+    pub default: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ParamKind {
+    #[default]
+    String,
+    Number,
+    Boolean,
+}
+
+impl ParamKind {
+    /// Parse `raw` the way a variable of the same type is, so `on`/`yes`
+    /// pass as `true` and numbers are normalised.
+    pub fn parse(self, raw: &str) -> Result<String, String> {
+        let variable = match self {
+            ParamKind::String => Variable::Text {
+                default: String::new(),
+            },
+            ParamKind::Number => Variable::Number { default: 0.0 },
+            ParamKind::Boolean => Variable::Boolean { default: false },
+        };
+        variable.parse(raw)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum WorkflowTrigger {
     Triggered {
@@ -477,6 +905,38 @@ pub enum WorkflowTrigger {
     Reusable,
 }
 
+/// What starting a workflow does while a run of it is still in flight,
+/// parked behind a `delay` included.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RunMode {
+    /// Drop the new run.
+    Single,
+    /// Cancel the runs in flight and start the new one.
+    Restart,
+    /// Start the new run once the ones in flight finish, holding at most
+    /// `max` waiting. The queue doesn't survive a restart.
+    Queued,
+    /// Start the new run alongside, up to `max` at once.
+    #[default]
+    Parallel,
+}
+
+impl RunMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RunMode::Single => "single",
+            RunMode::Restart => "restart",
+            RunMode::Queued => "queued",
+            RunMode::Parallel => "parallel",
+        }
+    }
+}
+
+fn default_max_runs() -> u32 {
+    10
+}
+
 #[derive(Debug, Clone, Deserialize, JsonSchema)]
 #[serde(from = "RawWorkflow")]
 #[schemars(with = "RawWorkflow")]
@@ -488,6 +948,12 @@ pub struct Workflow {
     pub enabled: bool,
     pub dry_run: bool,
     pub trigger: WorkflowTrigger,
+    pub mode: RunMode,
+    /// How many runs a `queued` workflow holds waiting, or a `parallel` one
+    /// runs at once.
+    pub max: u32,
+    /// Inputs taken when run by slug; only reusable workflows declare any.
+    pub params: BTreeMap<String, WorkflowParam>,
     pub run: Vec<Step>,
 }
 
@@ -515,6 +981,12 @@ pub struct RawWorkflow {
     #[serde(default, deserialize_with = "option_time_delta_from_str::deserialize")]
     #[schemars(with = "Option<String>")]
     delay: Option<TimeDelta>,
+    #[serde(default)]
+    mode: RunMode,
+    #[serde(default = "default_max_runs")]
+    max: u32,
+    #[serde(default)]
+    params: BTreeMap<String, WorkflowParam>,
     run: Vec<Step>,
 }
 
@@ -537,14 +1009,27 @@ impl From<RawWorkflow> for Workflow {
             enabled: raw.enabled,
             dry_run: raw.dry_run,
             trigger,
+            mode: raw.mode,
+            max: raw.max,
+            params: raw.params,
             run: raw.run,
         }
     }
 }
 
+fn default_resume_within() -> TimeDelta {
+    TimeDelta::hours(2)
+}
+
 #[derive(Debug, Clone, Deserialize, JsonSchema)]
 pub struct WorkflowSettings {
     pub workers: usize,
+    /// How late a parked delayed trigger or `delay` continuation may still run,
+    /// e.g. after coming back from downtime. Anything overdue by more is
+    /// dropped rather than firing long after the event that armed it.
+    #[serde(default = "default_resume_within", with = "time_delta_from_str")]
+    #[schemars(with = "String")]
+    pub resume_within: TimeDelta,
 }
 
 impl Workflow {
@@ -555,15 +1040,13 @@ impl Workflow {
         }
     }
 
-    pub fn template_placeholders(&self) -> Vec<String> {
-        fn collect(steps: &[Step], out: &mut Vec<String>) {
+    /// Every template in the workflow's steps, nested ones included.
+    fn templates(&self) -> Vec<TemplateString> {
+        fn collect(steps: &[Step], out: &mut Vec<TemplateString>) {
             for step in steps {
-                match step {
-                    Step::Notify { message, .. } => {
-                        out.extend(message.placeholders().into_iter().map(str::to_owned));
-                    }
-                    Step::Scene { run, .. } => collect(run, out),
-                    _ => {}
+                out.extend(step.templates());
+                for run in step.children() {
+                    collect(run, out);
                 }
             }
         }
@@ -572,13 +1055,205 @@ impl Workflow {
         out
     }
 
+    pub fn template_placeholders(&self) -> Vec<String> {
+        self.templates()
+            .iter()
+            .flat_map(TemplateString::placeholders)
+            .collect()
+    }
+
+    /// Params are for reusable workflows, whose vars otherwise come from
+    /// whichever workflow runs them; a triggered one gets its trigger's.
+    pub(super) fn validate_params(&self) -> Result<(), String> {
+        let context = |e: String| format!("workflow '{}': {e}", self.name);
+        if self.on().is_some() && !self.params.is_empty() {
+            return Err(context(
+                "only reusable workflows (without `on:`) declare params".to_owned(),
+            ));
+        }
+        for (name, param) in &self.params {
+            if let Some(default) = &param.default {
+                param
+                    .kind
+                    .parse(default)
+                    .map_err(|e| context(format!("param `{name}` default: {e}")))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The template vars for a run by slug. A reusable workflow's `inputs`
+    /// are checked against its params, parsed as their types and filled in
+    /// with defaults; a triggered one takes only the vars its trigger sets.
+    pub fn inputs(
+        &self,
+        mut inputs: HashMap<String, String>,
+    ) -> Result<HashMap<String, String>, String> {
+        if let Some(trigger) = self.on() {
+            let available = trigger.available_vars();
+            if let Some(unknown) = inputs.keys().find(|name| !available.contains(name)) {
+                return Err(format!(
+                    "`{unknown}` is not set by this workflow's trigger, which provides: [{}]",
+                    available.join(", ")
+                ));
+            }
+            return Ok(inputs);
+        }
+
+        if let Some(unknown) = inputs.keys().find(|name| !self.params.contains_key(*name)) {
+            return Err(format!("`{unknown}` is not a param of this workflow"));
+        }
+
+        let mut vars = HashMap::new();
+        for (name, param) in &self.params {
+            let value = match inputs.remove(name) {
+                Some(raw) => param
+                    .kind
+                    .parse(&raw)
+                    .map_err(|e| format!("input `{name}`: {e}"))?,
+                None => param
+                    .default
+                    .clone()
+                    .ok_or_else(|| format!("missing required input `{name}`"))?,
+            };
+            vars.insert(name.clone(), value);
+        }
+        Ok(vars)
+    }
+
+    pub(super) fn validate_templates(&self, registry: &DeviceRegistry) -> Result<(), String> {
+        for template in self.templates() {
+            template
+                .validate(registry)
+                .map_err(|e| format!("workflow '{}': {e}", self.name))?;
+        }
+        Ok(())
+    }
+
+    /// Every variable the workflow triggers on, compares, writes or looks up
+    /// must be declared, and suit how it's used: conditions compare numbers
+    /// and booleans only, and only numbers increment.
+    pub(super) fn validate_variables(
+        &self,
+        variables: &HashMap<String, Variable>,
+    ) -> Result<(), String> {
+        fn collect<'a>(
+            steps: &'a [Step],
+            compared: &mut Vec<&'a str>,
+            written: &mut Vec<&'a Step>,
+        ) {
+            for step in steps {
+                for condition in step.conditions() {
+                    condition.variables(compared);
+                }
+                if matches!(
+                    step,
+                    Step::SetVariable { .. } | Step::IncrementVariable { .. }
+                ) {
+                    written.push(step);
+                }
+                for run in step.children() {
+                    collect(run, compared, written);
+                }
+            }
+        }
+
+        let context = |e: String| format!("workflow '{}': {e}", self.name);
+        let declared = |name: &str| {
+            variables
+                .get(name)
+                .ok_or_else(|| context(format!("unknown variable `{name}`")))
+        };
+
+        if let Some(TriggerMatcher::Variable { variable }) = self.on() {
+            declared(variable)?;
+        }
+
+        let mut compared = Vec::new();
+        let mut written = Vec::new();
+        if let Some(when) = self.when() {
+            when.variables(&mut compared);
+        }
+        collect(&self.run, &mut compared, &mut written);
+
+        for name in compared {
+            let variable = declared(name)?;
+            if matches!(variable, Variable::Text { .. } | Variable::Timestamp) {
+                return Err(context(format!(
+                    "variable `{name}` is a {}, which a condition can't compare",
+                    variable.type_name()
+                )));
+            }
+        }
+
+        for step in written {
+            match step {
+                Step::SetVariable { variable, .. } => {
+                    declared(variable)?;
+                }
+                Step::IncrementVariable { variable, .. } => {
+                    let declaration = declared(variable)?;
+                    if !matches!(declaration, Variable::Number { .. }) {
+                        return Err(context(format!(
+                            "increment_variable needs a number, but `{variable}` is a {}",
+                            declaration.type_name()
+                        )));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for template in self.templates() {
+            for path in template.lookups() {
+                if let Ok(Lookup::Variable(name)) = Lookup::parse(&path) {
+                    declared(name)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Every room the workflow triggers on, tests or acts on must be declared.
+    pub(super) fn validate_rooms(&self, rooms: &Rooms) -> Result<(), String> {
+        fn collect<'a>(steps: &'a [Step], out: &mut Vec<&'a str>) {
+            for step in steps {
+                for condition in step.conditions() {
+                    condition.rooms(out);
+                }
+                if let Step::RoomLights { room, .. } = step {
+                    out.push(room);
+                }
+                for run in step.children() {
+                    collect(run, out);
+                }
+            }
+        }
+
+        let mut referenced = Vec::new();
+        if let Some(TriggerMatcher::Room { room, .. }) = self.on() {
+            referenced.push(room.as_str());
+        }
+        if let Some(when) = self.when() {
+            when.rooms(&mut referenced);
+        }
+        collect(&self.run, &mut referenced);
+
+        for room in referenced {
+            validate_room(room, rooms).map_err(|e| format!("workflow '{}': {e}", self.name))?;
+        }
+        Ok(())
+    }
+
     pub fn run_workflow_targets(&self) -> Vec<&str> {
         fn collect<'a>(steps: &'a [Step], out: &mut Vec<&'a str>) {
             for step in steps {
-                match step {
-                    Step::RunWorkflow { workflow, .. } => out.push(workflow.as_str()),
-                    Step::Scene { run, .. } => collect(run, out),
-                    _ => {}
+                if let Step::RunWorkflow { workflow, .. } = step {
+                    out.push(workflow.as_str());
+                }
+                for run in step.children() {
+                    collect(run, out);
                 }
             }
         }
@@ -587,6 +1262,134 @@ impl Workflow {
         out
     }
 
+    /// Every `notify` step must name a declared target and offer valid actions.
+    pub(super) fn validate_notify(&self, targets: &NotifyTargets) -> Result<(), String> {
+        fn check(steps: &[Step], targets: &NotifyTargets) -> Result<(), String> {
+            for step in steps {
+                if let Step::Notify {
+                    notify, actions, ..
+                } = step
+                {
+                    notify.resolve(targets)?;
+                    validate_actions(actions)?;
+                }
+                for run in step.children() {
+                    check(run, targets)?;
+                }
+            }
+            Ok(())
+        }
+
+        check(&self.run, targets).map_err(|e| format!("workflow '{}': {e}", self.name))
+    }
+
+    /// A `queued` or `parallel` workflow with a `max` of 0 would drop every run.
+    pub(super) fn validate_run_mode(&self) -> Result<(), String> {
+        match self.mode {
+            RunMode::Queued | RunMode::Parallel if self.max == 0 => Err(format!(
+                "workflow '{}': `max` must be at least 1 for mode {}",
+                self.name,
+                self.mode.as_str()
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// `repeat` takes exactly one of `count`, `while` or `until`, and
+    /// `wait_for` one of `condition` or `event`. A `wait_for` event can't be
+    /// one the dispatcher matches with its own state (rooms, cron, the
+    /// rising-edge environment and solar triggers, and anything held `for:` a
+    /// duration); a condition covers those.
+    pub(super) fn validate_control_flow(&self) -> Result<(), String> {
+        fn check(steps: &[Step]) -> Result<(), String> {
+            for step in steps {
+                match step {
+                    Step::Parallel { branches, .. } if branches.is_empty() => {
+                        return Err("parallel needs at least one branch".to_owned());
+                    }
+                    Step::Choose { options, .. } if options.is_empty() => {
+                        return Err("choose needs at least one option".to_owned());
+                    }
+                    Step::Repeat {
+                        count,
+                        repeat_while,
+                        until,
+                        ..
+                    } => {
+                        let modes = [count.is_some(), repeat_while.is_some(), until.is_some()];
+                        if modes.iter().filter(|set| **set).count() != 1 {
+                            return Err("repeat takes exactly one of `count`, `while` or `until`"
+                                .to_owned());
+                        }
+                        if let Some(count) = count
+                            && !(1..=MAX_REPEAT).contains(count)
+                        {
+                            return Err(format!(
+                                "repeat `count` must be between 1 and {MAX_REPEAT}"
+                            ));
+                        }
+                    }
+                    Step::WaitFor {
+                        condition,
+                        event,
+                        timeout,
+                        ..
+                    } => {
+                        if condition.is_some() == event.is_some() {
+                            return Err(
+                                "wait_for takes exactly one of `condition` or `event`".to_owned()
+                            );
+                        }
+                        if let Some(
+                            event @ (TriggerMatcher::Room { .. }
+                            | TriggerMatcher::Cron { .. }
+                            | TriggerMatcher::Environment { .. }
+                            | TriggerMatcher::Solar { .. }),
+                        ) = event
+                        {
+                            return Err(format!(
+                                "wait_for can't wait on the event {}, use a `condition`",
+                                event.describe()
+                            ));
+                        }
+                        if event
+                            .as_ref()
+                            .is_some_and(|event| event.held_for().is_some())
+                        {
+                            return Err("wait_for can't wait on an event held `for` a duration, \
+                                 use a `condition`"
+                                .to_owned());
+                        }
+                        if *timeout <= TimeDelta::zero() || *timeout > MAX_WAIT {
+                            return Err(format!(
+                                "wait_for `timeout` must be between 1s and {}m",
+                                MAX_WAIT.num_minutes()
+                            ));
+                        }
+                    }
+                    _ => {}
+                }
+                if matches!(step, Step::Parallel { .. } | Step::Repeat { .. })
+                    && step.held_in_place() > MAX_WAIT
+                {
+                    return Err(format!(
+                        "{} could hold a worker for {}, over {}m; the `delay`s and \
+                         `wait_for`s inside it run in place rather than parking",
+                        step.kind(),
+                        crate::timedelta_format::humanize(step.held_in_place()),
+                        MAX_WAIT.num_minutes()
+                    ));
+                }
+                for run in step.children() {
+                    check(run)?;
+                }
+            }
+            Ok(())
+        }
+
+        check(&self.run).map_err(|e| format!("workflow '{}': {e}", self.name))
+    }
+
     pub fn when(&self) -> Option<&Condition> {
         match &self.trigger {
             WorkflowTrigger::Triggered { when, .. } => when.as_ref(),
@@ -715,7 +1518,7 @@ run:
 
         assert!(matches!(
             workflow.on(),
-            Some(TriggerMatcher::HomeAssistant { entity_id, state })
+            Some(TriggerMatcher::HomeAssistant { entity_id, state, .. })
                 if entity_id == "binary_sensor.front_door" && state.as_deref() == Some("on")
         ));
 
@@ -725,3 +1528,336 @@ run:
         ));
     }
 }
+
+#[cfg(test)]
+mod params_tests {
+    use super::*;
+    use config::{Config, File, FileFormat};
+
+    fn parse(yaml: &str) -> Workflow {
+        Config::builder()
+            .add_source(File::from_str(yaml, FileFormat::Yaml))
+            .build()
+            .unwrap()
+            .try_deserialize::<Workflow>()
+            .unwrap()
+    }
+
+    fn inputs(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| ((*name).to_owned(), (*value).to_owned()))
+            .collect()
+    }
+
+    fn reusable() -> Workflow {
+        parse(
+            r#"
+name: Dim lounge
+slug: dim-lounge
+params:
+  level: { type: number }
+  announce: { type: boolean, default: "false" }
+run:
+  - type: delay
+    seconds: 1
+"#,
+        )
+    }
+
+    #[test]
+    fn inputs_are_parsed_and_defaults_filled_in() {
+        let vars = reusable()
+            .inputs(inputs(&[("level", " 40 ")]))
+            .expect("valid inputs");
+        assert_eq!(vars, inputs(&[("level", "40"), ("announce", "false")]));
+
+        let vars = reusable()
+            .inputs(inputs(&[("level", "5"), ("announce", "yes")]))
+            .unwrap();
+        assert_eq!(vars["announce"], "true");
+    }
+
+    #[test]
+    fn missing_unknown_and_mistyped_inputs_are_rejected() {
+        let workflow = reusable();
+        assert!(workflow.inputs(HashMap::new()).is_err());
+        assert!(
+            workflow
+                .inputs(inputs(&[("level", "5"), ("colour", "red")]))
+                .is_err()
+        );
+        assert!(workflow.inputs(inputs(&[("level", "dim")])).is_err());
+    }
+
+    #[test]
+    fn triggered_workflows_take_only_their_trigger_vars() {
+        let workflow = parse(
+            r#"
+name: Door
+slug: door
+on: { type: door, device: front-door, open: true }
+run: []
+"#,
+        );
+        assert!(workflow.inputs(inputs(&[("open", "true")])).is_ok());
+        assert!(workflow.inputs(inputs(&[("level", "5")])).is_err());
+        assert!(workflow.validate_params().is_ok());
+    }
+
+    #[test]
+    fn params_are_only_declared_on_reusable_workflows_with_valid_defaults() {
+        let triggered = parse(
+            r#"
+name: Door
+slug: door
+on: { type: door, device: front-door, open: true }
+params:
+  level: { type: number }
+run: []
+"#,
+        );
+        assert!(triggered.validate_params().is_err());
+
+        let bad_default = parse(
+            r#"
+name: Dim
+slug: dim
+params:
+  level: { type: number, default: bright }
+run: []
+"#,
+        );
+        assert!(bad_default.validate_params().is_err());
+        assert!(reusable().validate_params().is_ok());
+    }
+}
+
+#[cfg(test)]
+mod control_flow_tests {
+    use super::*;
+    use config::{Config, File, FileFormat};
+
+    fn parse(yaml: &str) -> Workflow {
+        Config::builder()
+            .add_source(File::from_str(yaml, FileFormat::Yaml))
+            .build()
+            .unwrap()
+            .try_deserialize::<Workflow>()
+            .unwrap()
+    }
+
+    fn with_step(step: &str) -> Workflow {
+        parse(&format!("name: Hall\nslug: hall\nrun:\n{step}"))
+    }
+
+    #[test]
+    fn nested_steps_are_walked_through_every_control_flow_step() {
+        let workflow = parse(
+            r#"
+name: Evening
+slug: evening
+run:
+  - type: parallel
+    branches:
+      - - type: run_workflow
+          workflow: lamps
+      - - type: repeat
+          count: 2
+          run:
+            - type: run_workflow
+              workflow: chime
+  - type: choose
+    options:
+      - if: { type: variable, variable: visits, op: gt, value: 3 }
+        run:
+          - type: run_workflow
+            workflow: welcome
+    else:
+      - type: run_workflow
+        workflow: quiet
+"#,
+        );
+
+        assert_eq!(
+            workflow.run_workflow_targets(),
+            vec!["lamps", "chime", "welcome", "quiet"]
+        );
+        assert!(workflow.validate_control_flow().is_ok());
+
+        let mut compared = Vec::new();
+        for condition in workflow.run[1].conditions() {
+            condition.variables(&mut compared);
+        }
+        assert_eq!(compared, vec!["visits"]);
+    }
+
+    #[test]
+    fn repeat_takes_exactly_one_mode_within_the_cap() {
+        let both = with_step(
+            r#"
+  - type: repeat
+    count: 2
+    until: { type: mode, mode: night, active: true }
+    run: []
+"#,
+        );
+        assert!(both.validate_control_flow().is_err());
+
+        let over_cap = with_step(&format!(
+            "  - type: repeat\n    count: {}\n    run: []\n",
+            MAX_REPEAT + 1
+        ));
+        assert!(over_cap.validate_control_flow().is_err());
+    }
+
+    #[test]
+    fn editing_the_steps_changes_their_digest() {
+        let flash = |seconds: u64| {
+            with_step(&format!(
+                "  - type: delay\n    seconds: {seconds}\n  - type: set_mode\n    mode: night\n    active: true\n"
+            ))
+        };
+
+        assert_eq!(Step::digest(&flash(5).run), Step::digest(&flash(5).run));
+        assert_ne!(Step::digest(&flash(5).run), Step::digest(&flash(10).run));
+        assert_ne!(
+            Step::digest(&flash(5).run),
+            Step::digest(&flash(5).run[..1])
+        );
+    }
+
+    #[test]
+    fn parallel_and_repeat_hold_a_worker_within_the_cap() {
+        let blink = with_step(
+            r#"
+  - type: repeat
+    count: 10
+    run:
+      - type: delay
+        seconds: 60
+"#,
+        );
+        assert_eq!(blink.run[0].held_in_place(), TimeDelta::minutes(10));
+        assert!(blink.validate_control_flow().is_ok());
+
+        let hourly = with_step(
+            r#"
+  - type: repeat
+    count: 100
+    run:
+      - type: delay
+        seconds: 3600
+"#,
+        );
+        assert!(hourly.validate_control_flow().is_err());
+
+        let slowest_branch = with_step(
+            r#"
+  - type: parallel
+    branches:
+      - - type: delay
+          seconds: 30
+      - - type: delay
+          seconds: 60
+        - type: wait_for
+          condition: { type: mode, mode: night, active: true }
+          timeout: 59m
+"#,
+        );
+        assert_eq!(slowest_branch.run[0].held_in_place(), TimeDelta::hours(1));
+        assert!(slowest_branch.validate_control_flow().is_ok());
+
+        let unbounded = with_step(
+            r#"
+  - type: repeat
+    until: { type: mode, mode: night, active: true }
+    run:
+      - type: delay
+        seconds: 60
+"#,
+        );
+        assert!(unbounded.validate_control_flow().is_err());
+    }
+
+    #[test]
+    fn wait_for_takes_a_stateless_event_or_a_condition_within_the_cap() {
+        let door = with_step(
+            r#"
+  - type: wait_for
+    event: { type: door, device: "0x2", open: false }
+    timeout: 10m
+"#,
+        );
+        assert!(door.validate_control_flow().is_ok());
+
+        let room = with_step(
+            r#"
+  - type: wait_for
+    event: { type: room, room: lounge, occupied: false }
+    timeout: 10m
+"#,
+        );
+        assert!(room.validate_control_flow().is_err());
+
+        let neither = with_step("  - type: wait_for\n    timeout: 10m\n");
+        assert!(neither.validate_control_flow().is_err());
+
+        let too_long = with_step(
+            r#"
+  - type: wait_for
+    condition: { type: mode, mode: night, active: true }
+    timeout: 2h
+"#,
+        );
+        assert!(too_long.validate_control_flow().is_err());
+    }
+
+    #[test]
+    fn a_held_trigger_pins_down_its_state_and_cant_be_waited_on() {
+        let door = parse(
+            r#"
+name: Door left open
+slug: door-left-open
+on: { type: door, device: "0x2", open: true, for: 10m }
+run: []
+"#,
+        );
+        let on = door.on().expect("trigger");
+        assert_eq!(on.held_for(), Some(TimeDelta::minutes(10)));
+        assert_eq!(on.describe(), "door(0x2) -> open for 10m");
+        assert!(on.validate_held().is_ok());
+
+        let any_state = parse(
+            r#"
+name: Heater
+slug: heater
+on: { type: home_assistant, entity_id: climate.lounge, for: 1h }
+run: []
+"#,
+        );
+        assert!(any_state.on().expect("trigger").validate_held().is_err());
+
+        let waited = with_step(
+            r#"
+  - type: wait_for
+    event: { type: door, device: "0x2", open: false, for: 1m }
+    timeout: 10m
+"#,
+        );
+        assert!(waited.validate_control_flow().is_err());
+    }
+
+    #[test]
+    fn run_mode_defaults_to_parallel_and_needs_a_max() {
+        let default = with_step("  - type: delay\n    seconds: 5\n");
+        assert_eq!(default.mode, RunMode::Parallel);
+        assert_eq!(default.max, 10);
+
+        let none_queued = parse("name: Hall\nslug: hall\nmode: queued\nmax: 0\nrun: []\n");
+        assert!(none_queued.validate_run_mode().is_err());
+
+        let single = parse("name: Hall\nslug: hall\nmode: single\nmax: 0\nrun: []\n");
+        assert!(single.validate_run_mode().is_ok());
+    }
+}
@@ -11,14 +11,13 @@ use crate::integrations::home_assistant::HomeAssistant;
 use crate::integrations::jellyfin::Jellyfin;
 use crate::integrations::mqtt::MqttClient;
 use crate::integrations::s3::S3;
-use crate::settings::SettingsContainer;
+use crate::settings::{LiveConfig, SettingsContainer};
 
 #[derive(Clone)]
 pub struct SharedActorState {
     pub db: Pool<Postgres>,
     pub mqtt: MqttClient,
-    pub settings: SettingsContainer,
-    pub devices: DeviceRegistry,
+    pub config: LiveConfig,
     pub feature_flag_client: FeatureFlagClient,
     pub s3: S3,
     pub event_bus: EventBus,
@@ -32,10 +31,32 @@ pub struct SharedActorState {
 pub struct ApiState {
     pub feature_flag_client: FeatureFlagClient,
     pub schema: FinalSchema,
-    pub settings: SettingsContainer,
+    pub config: LiveConfig,
     pub db: Pool<Postgres>,
     pub s3: S3,
     pub auth: AuthManager,
-    pub devices: DeviceRegistry,
     pub eink: EinkDisplayManager,
+    pub event_bus: EventBus,
+}
+
+impl SharedActorState {
+    /// The settings as of now. Taken per message rather than held, so a config
+    /// reload reaches long-lived actors without a restart.
+    pub fn settings(&self) -> SettingsContainer {
+        self.config.settings()
+    }
+
+    pub fn devices(&self) -> DeviceRegistry {
+        self.config.devices()
+    }
+}
+
+impl ApiState {
+    pub fn settings(&self) -> SettingsContainer {
+        self.config.settings()
+    }
+
+    pub fn devices(&self) -> DeviceRegistry {
+        self.config.devices()
+    }
 }
@@ -51,27 +51,6 @@ impl Client {
     }
 }
 
-/// Mints a key through the same path the admin API uses, so the test exercises
-/// real hashed-key lookup rather than the static config key.
-async fn mint_key(harness: &Harness, name: &str, scopes: &[&str]) -> String {
-    let key = format!("test-key-{}", uuid::Uuid::new_v4().simple());
-    let hashed = home_gateway::auth::hash_key(&key);
-    let scopes: Vec<String> = scopes.iter().map(|s| (*s).to_owned()).collect();
-
-    sqlx::query(
-        "INSERT INTO api_keys (name, key_prefix, key_hash, scopes) VALUES ($1, $2, $3, $4)",
-    )
-    .bind(name)
-    .bind(&key[..8])
-    .bind(&hashed)
-    .bind(&scopes)
-    .execute(&harness.db)
-    .await
-    .expect("failed to insert the test api key");
-
-    key
-}
-
 #[tokio::test]
 #[serial]
 async fn health_is_unauthenticated() {
@@ -110,7 +89,7 @@ async fn requests_without_credentials_are_rejected() {
 async fn a_valid_api_key_is_accepted() {
     let harness = Harness::start().await;
     let client = Client::new(&harness);
-    let key = mint_key(&harness, "test-admin", &["*"]).await;
+    let key = harness.mint_key("test-admin", &["*"]).await;
 
     let (status, body) = client.graphql(Some(&key), "{ __typename }").await;
 
@@ -123,7 +102,7 @@ async fn a_valid_api_key_is_accepted() {
 async fn a_revoked_api_key_is_rejected() {
     let harness = Harness::start().await;
     let client = Client::new(&harness);
-    let key = mint_key(&harness, "test-revoked", &["*"]).await;
+    let key = harness.mint_key("test-revoked", &["*"]).await;
 
     sqlx::query("UPDATE api_keys SET revoked_at = now() WHERE name = $1")
         .bind("test-revoked")
@@ -141,7 +120,7 @@ async fn a_revoked_api_key_is_rejected() {
 async fn temperature_readings_are_queryable_over_graphql() {
     let harness = Harness::start().await;
     let client = Client::new(&harness);
-    let key = mint_key(&harness, "test-reader", &["*"]).await;
+    let key = harness.mint_key("test-reader", &["*"]).await;
 
     sqlx::query(
         "INSERT INTO latest_temperature_sensor (name, entity_id, ieee_addr, temperature, humidity)
@@ -179,7 +158,7 @@ async fn temperature_readings_are_queryable_over_graphql() {
 async fn an_insufficient_scope_is_denied() {
     let harness = Harness::start().await;
     let client = Client::new(&harness);
-    let key = mint_key(&harness, "test-narrow", &["rest:epd:read"]).await;
+    let key = harness.mint_key("test-narrow", &["rest:epd:read"]).await;
 
     let (status, body) = client
         .graphql(
@@ -1,4 +1,4 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use home_gateway::actors::root::RootMessage;
@@ -10,7 +10,7 @@ use home_gateway::event_bus::{EventBus, EventBusMessage};
 use home_gateway::integrations::feature_flag::FeatureFlagClient;
 use home_gateway::integrations::reddit::Reddit;
 use home_gateway::integrations::s3::S3;
-use home_gateway::settings::SettingsContainer;
+use home_gateway::settings::{LiveConfig, SettingsContainer};
 use home_gateway::state::{ApiState, SharedActorState};
 use ractor::{Actor, ActorProcessingErr, ActorRef};
 use sqlx::{Pool, Postgres};
@@ -76,28 +76,30 @@ impl Harness {
         let (settings, devices) = SettingsContainer::load_from_dir(Path::new(FIXTURE_CONFIG))
             .expect("failed to load the fixture config");
         let settings: SettingsContainer = settings.into();
+        let config = LiveConfig::new(
+            PathBuf::from(FIXTURE_CONFIG),
+            settings.clone(),
+            devices.clone(),
+        );
 
         let test_broker = broker::start(devices.clone()).await;
         let recorder = test_broker.recorder.clone();
 
         let feature_flag_client = FeatureFlagClient::new().await;
-        let s3 = S3::new(&settings.s3.bucket, &settings.s3.region, None)
-            .expect("failed to build the s3 client");
+        let s3 = S3::from_settings(&settings.s3).expect("failed to build the s3 client");
 
         let eink = home_gateway::eink::EinkDisplayManager::new(
             db.clone(),
             s3.clone(),
             feature_flag_client.clone(),
-            devices.clone(),
-            settings.clone(),
+            config.clone(),
             Reddit::new(),
         );
 
         let event_bus = EventBus::default();
 
         let state = SharedActorState {
-            settings: settings.clone(),
-            devices: devices.clone(),
+            config,
             db: db.clone(),
             mqtt: test_broker.client.clone(),
             feature_flag_client,
@@ -133,12 +135,12 @@ impl Harness {
         ApiState {
             feature_flag_client: self.state.feature_flag_client.clone(),
             schema: build_schema(&self.state, http_client),
-            settings: self.settings.clone(),
+            config: self.state.config.clone(),
             db: self.db.clone(),
             s3: self.state.s3.clone(),
             auth: AuthManager::new(self.db.clone(), None),
-            devices: self.devices.clone(),
             eink: self.state.eink.clone(),
+            event_bus: self.event_bus.clone(),
         }
     }
 
@@ -146,6 +148,27 @@ impl Harness {
         build_router(self.api_state(), prometheus::Registry::new())
     }
 
+    /// Mints a key through the same path the admin API uses, so the test exercises
+    /// real hashed-key lookup rather than the static config key.
+    pub async fn mint_key(&self, name: &str, scopes: &[&str]) -> String {
+        let key = format!("test-key-{}", uuid::Uuid::new_v4().simple());
+        let hashed = home_gateway::auth::hash_key(&key);
+        let scopes: Vec<String> = scopes.iter().map(|s| (*s).to_owned()).collect();
+
+        sqlx::query(
+            "INSERT INTO api_keys (name, key_prefix, key_hash, scopes) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(name)
+        .bind(&key[..8])
+        .bind(&hashed)
+        .bind(&scopes)
+        .execute(&self.db)
+        .await
+        .expect("failed to insert the test api key");
+
+        key
+    }
+
     pub fn subscribe(&self) -> EventStream {
         EventStream {
             receiver: self.event_bus.subscribe(),
@@ -1,5 +1,5 @@
 use home_gateway::device_registry::DeviceRegistry;
-use home_gateway::settings::SettingsContainer;
+use home_gateway::settings::{LiveConfig, SettingsContainer};
 use pretty_assertions::assert_eq;
 use std::path::Path;
 
@@ -131,6 +131,69 @@ fn esphome_environment_without_entities_is_rejected() {
     );
 }
 
+#[test]
+fn reload_swaps_in_the_edited_config() {
+    let dir = copy_fixture();
+    let (settings, devices) = SettingsContainer::load_from_dir(&dir).unwrap();
+    let config = LiveConfig::new(dir.clone(), settings.into(), devices);
+
+    std::fs::write(dir.join("workflows/index.yaml"), "[]\n").unwrap();
+    let change = config.reload().expect("expected the edited config to load");
+
+    assert_eq!(
+        change.removed_workflows,
+        vec![
+            "test-door-opens-lamp-on",
+            "test-lamp-flash",
+            "test-lamp-on",
+            "test-motion-clear-lamp-off",
+            "test-phone-home-lamp-on",
+            "test-second-visit-lamp-on",
+        ]
+    );
+    assert!(change.added_workflows.is_empty());
+    assert!(config.settings().workflows.is_empty());
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn reload_rejects_an_invalid_config_and_keeps_the_running_one() {
+    let dir = copy_fixture();
+    let (settings, devices) = SettingsContainer::load_from_dir(&dir).unwrap();
+    let config = LiveConfig::new(dir.clone(), settings.into(), devices);
+
+    std::fs::write(dir.join("devices.yaml"), "- id: [not, a, sensor]\n").unwrap();
+
+    assert!(config.reload().is_err());
+    assert!(config.settings().workflows.contains_key("test-lamp-on"));
+    assert!(
+        config
+            .devices()
+            .zigbee_device("0x0000000000000001")
+            .is_some()
+    );
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+fn copy_fixture() -> std::path::PathBuf {
+    let dir = tempdir();
+    std::fs::create_dir_all(dir.join("workflows")).unwrap();
+
+    for file in [
+        "base.yaml",
+        "devices.yaml",
+        "zigbee_models.yaml",
+        "workflows/index.yaml",
+        "workflows/lamp.yaml",
+    ] {
+        std::fs::copy(Path::new(FIXTURE_CONFIG).join(file), dir.join(file)).unwrap();
+    }
+
+    dir
+}
+
 /// Rebuilds the fixture config with `devices.yaml` swapped out, so the
 /// startup validation errors can be asserted without a whole config tree.
 fn build_with_devices(devices_yaml: &str) -> String {
@@ -6,5 +6,7 @@ mod auth;
 mod config;
 mod cron_tasks;
 mod ingest;
+mod notify;
 mod solar;
+mod trmnl;
 mod workflows;
@@ -0,0 +1,173 @@
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use home_gateway::actors::system::notify::spawn::{spawn_notify, spawn_notify_policy};
+use home_gateway::event_bus::EventBusMessage;
+use home_gateway::integrations::notify::{Actionable, Origin, actions, notify};
+use home_gateway::settings::notify::NotifyAction;
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use serial_test::serial;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use crate::common::{Harness, wait_for};
+
+const DB_TIMEOUT: Duration = Duration::from_secs(10);
+const HALLWAY_TOPIC: &str = "test/notify/hallway";
+const ANDROID_SECRET: &str = "test-android-secret";
+
+#[tokio::test]
+#[serial]
+async fn an_mqtt_target_publishes_through_the_notify_worker() {
+    let harness = Harness::start().await;
+    spawn_notify(&harness.root, harness.state.clone())
+        .await
+        .unwrap();
+
+    let target = harness.settings.notify_targets["hallway_display"].clone();
+    notify(
+        &[target],
+        "Front door left open".to_owned(),
+        Origin::new(Uuid::new_v4(), "door:front"),
+        &[],
+    );
+
+    let payload = harness.recorder.expect_publish(HALLWAY_TOPIC).await;
+    assert_eq!(
+        payload,
+        json!({
+            "title": "Hallway",
+            "message": "Front door left open",
+            "priority": "high",
+            "tags": ["door"],
+        })
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn a_repeated_message_is_collapsed_and_recorded() {
+    let harness = Harness::start().await;
+    spawn_notify(&harness.root, harness.state.clone())
+        .await
+        .unwrap();
+    spawn_notify_policy(&harness.root, harness.state.clone())
+        .await
+        .unwrap();
+
+    let target = harness.settings.notify_targets["hallway_display"].clone();
+    for _ in 0..2 {
+        notify(
+            std::slice::from_ref(&target),
+            "Front door left open".to_owned(),
+            Origin::new(Uuid::new_v4(), "door:front"),
+            &[],
+        );
+    }
+
+    harness.recorder.expect_publish(HALLWAY_TOPIC).await;
+
+    let (target, source, reason) = wait_for(DB_TIMEOUT, "the suppression row", || async {
+        sqlx::query_as::<_, (String, String, String)>(
+            "SELECT target, source, reason FROM notification_suppressions",
+        )
+        .fetch_optional(&harness.db)
+        .await
+        .unwrap()
+    })
+    .await;
+    assert_eq!(target, "mqtt:test/notify/hallway");
+    assert_eq!(source, "door:front");
+    assert_eq!(reason, "duplicate");
+
+    let published = harness
+        .recorder
+        .published()
+        .into_iter()
+        .filter(|(topic, _)| topic == HALLWAY_TOPIC)
+        .count();
+    assert_eq!(published, 1);
+}
+
+async fn tap(harness: &Harness, notification_id: Uuid, action: &str) -> StatusCode {
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/ingest/home/notification-action")
+        .header("content-type", "application/json")
+        .header("X-Webhook-Secret", ANDROID_SECRET)
+        .body(Body::from(
+            json!({ "notification_id": notification_id, "action": action }).to_string(),
+        ))
+        .unwrap();
+
+    harness
+        .router()
+        .oneshot(request)
+        .await
+        .expect("the router should not fail")
+        .status()
+}
+
+#[tokio::test]
+#[serial]
+async fn a_tapped_action_is_published_once_under_the_originating_event() {
+    let harness = Harness::start().await;
+    let mut events = harness.subscribe();
+
+    let event_id = Uuid::new_v4();
+    let notification_id = Uuid::new_v4();
+    let actionable = Actionable::new(
+        &Origin::new(event_id, "door:front"),
+        &[
+            NotifyAction {
+                id: "snooze".to_owned(),
+                title: "Snooze".to_owned(),
+            },
+            NotifyAction {
+                id: "dismiss".to_owned(),
+                title: "Dismiss".to_owned(),
+            },
+        ],
+    )
+    .unwrap();
+    actions::record(&harness.db, notification_id, &actionable)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        tap(&harness, notification_id, "unlock").await,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        tap(&harness, notification_id, "snooze").await,
+        StatusCode::NO_CONTENT
+    );
+
+    let event = events
+        .next_matching("the notification action", |m| {
+            matches!(m, EventBusMessage::NotificationAction { .. })
+        })
+        .await;
+    let EventBusMessage::NotificationAction {
+        event_id: published,
+        origin_event_id,
+        notification_id: published_notification,
+        source,
+        action,
+    } = event
+    else {
+        unreachable!()
+    };
+    assert_ne!(published, event_id, "the action is an event of its own");
+    assert_eq!(origin_event_id, event_id);
+    assert_eq!(published_notification, notification_id);
+    assert_eq!(source, "door:front");
+    assert_eq!(action, "snooze");
+
+    assert_eq!(
+        tap(&harness, notification_id, "dismiss").await,
+        StatusCode::CONFLICT
+    );
+}
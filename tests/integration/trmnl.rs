@@ -0,0 +1,101 @@
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use http_body_util::BodyExt;
+use pretty_assertions::assert_eq;
+use serial_test::serial;
+use tower::ServiceExt;
+
+use crate::common::Harness;
+
+const TRMNL_MAC: &str = "94:A9:90:CF:83:84";
+/// A second device, whose `byos` block sets `allow_setup`.
+const PAIRING_MAC: &str = "94:A9:90:CF:83:85";
+
+async fn send(harness: &Harness, request: Request<Body>) -> (StatusCode, serde_json::Value) {
+    let response = harness
+        .router()
+        .oneshot(request)
+        .await
+        .expect("the router should not fail");
+
+    let status = response.status();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let body = serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null);
+
+    (status, body)
+}
+
+fn setup(mac: &str) -> Request<Body> {
+    Request::builder()
+        .uri("/v1/trmnl/api/setup")
+        .header("ID", mac)
+        .body(Body::empty())
+        .unwrap()
+}
+
+fn log(mac: &str, token: &str) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri("/v1/trmnl/api/log")
+        .header("ID", mac)
+        .header("Access-Token", token)
+        .header("content-type", "application/json")
+        .body(Body::from(
+            r#"{"log":{"logs_array":[{"message":"wifi connect failed"}]}}"#,
+        ))
+        .unwrap()
+}
+
+#[tokio::test]
+#[serial]
+async fn setup_issues_a_key_only_the_device_can_use() {
+    let harness = Harness::start().await;
+
+    let (status, _) = send(&harness, setup("00:00:00:00:00:00")).await;
+    assert_eq!(
+        status,
+        StatusCode::NOT_FOUND,
+        "unregistered devices get nothing"
+    );
+
+    let (status, body) = send(&harness, setup(PAIRING_MAC)).await;
+    assert_eq!(status, StatusCode::OK, "got {body}");
+    assert_eq!(body["friendly_id"], "test-trmnl-pairing");
+    let first = body["api_key"].as_str().expect("an api key").to_owned();
+
+    let (status, _) = send(&harness, log(PAIRING_MAC, &first)).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (status, _) = send(&harness, log(PAIRING_MAC, "not-a-key")).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // pairing again replaces the key
+    let (status, body) = send(&harness, setup(PAIRING_MAC)).await;
+    assert_eq!(status, StatusCode::OK, "got {body}");
+    let second = body["api_key"].as_str().expect("an api key").to_owned();
+
+    let (status, _) = send(&harness, log(PAIRING_MAC, &first)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = send(&harness, log(PAIRING_MAC, &second)).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+#[serial]
+async fn setup_is_refused_while_pairing_is_off() {
+    let harness = Harness::start().await;
+
+    // anyone can send the MAC, so without `allow_setup` a device is not paired
+    let (status, _) = send(&harness, setup(TRMNL_MAC)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // nor is a key it already holds replaced
+    let key = harness
+        .mint_key("trmnl:test-trmnl", &["rest:epd:read"])
+        .await;
+    let (status, _) = send(&harness, setup(TRMNL_MAC)).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, _) = send(&harness, log(TRMNL_MAC, &key)).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+}
@@ -1,11 +1,20 @@
+use std::collections::HashMap;
 use std::time::Duration;
 
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use http_body_util::BodyExt;
+
 use home_gateway::actors::devices::light::spawn::spawn_light_handler;
+use home_gateway::actors::workflows::delayed::{
+    self, DelayedRun, DelayedRunActor, EventSubject, Resume,
+};
 use home_gateway::actors::workflows::{dispatcher::WorkflowDispatcher, spawn::spawn_workflows};
 use home_gateway::event_bus::EventBusMessage;
 use pretty_assertions::assert_eq;
 use ractor::Actor;
 use serial_test::serial;
+use tower::ServiceExt;
 use uuid::Uuid;
 
 use crate::common::{Harness, wait_for};
@@ -24,7 +33,12 @@ async fn start() -> Harness {
     spawn_light_handler(&harness.root, harness.state.clone())
         .await
         .unwrap();
+    start_dispatcher(&harness).await;
+
+    harness
+}
 
+async fn start_dispatcher(harness: &Harness) {
     Actor::spawn(
         Some(WorkflowDispatcher::NAME.to_owned()),
         WorkflowDispatcher {
@@ -34,8 +48,59 @@ async fn start() -> Harness {
     )
     .await
     .expect("failed to spawn the workflow dispatcher");
+}
 
-    harness
+async fn start_delayed(harness: &Harness) {
+    Actor::spawn(
+        Some(DelayedRunActor::NAME.to_owned()),
+        DelayedRunActor {
+            shared_actor_state: harness.state.clone(),
+        },
+        (),
+    )
+    .await
+    .expect("failed to spawn the delayed run actor");
+}
+
+fn delayed_trigger(slug: &str, subject: &EventSubject, overdue: chrono::TimeDelta) -> DelayedRun {
+    DelayedRun {
+        event_id: Uuid::new_v4(),
+        slug: slug.to_owned(),
+        name: slug.to_owned(),
+        due_at: chrono::Utc::now() - overdue,
+        vars: HashMap::new(),
+        resume: Resume::Trigger {
+            subject_kind: subject.0.clone(),
+            subject_entity: subject.1.clone(),
+        },
+    }
+}
+
+async fn parked(harness: &Harness) -> Vec<String> {
+    sqlx::query_scalar("SELECT message->>'name' FROM pgmq.q_workflow_delays ORDER BY msg_id")
+        .fetch_all(&harness.db)
+        .await
+        .unwrap()
+}
+
+async fn assert_outcome(harness: &Harness, slug: &str, outcome: &str) {
+    wait_for(
+        DB_TIMEOUT,
+        &format!("a {outcome} run for {slug}"),
+        || async {
+            let count: i64 = sqlx::query_scalar(
+                "SELECT count(*) FROM workflow_runs WHERE slug = $1 AND outcome = $2",
+            )
+            .bind(slug)
+            .bind(outcome)
+            .fetch_one(&harness.db)
+            .await
+            .unwrap();
+
+            (count > 0).then_some(count)
+        },
+    )
+    .await;
 }
 
 async fn assert_ran(harness: &Harness, slug: &str) {
@@ -56,6 +121,34 @@ async fn assert_ran(harness: &Harness, slug: &str) {
     .await;
 }
 
+async fn run_by_slug(
+    harness: &Harness,
+    api_key: &str,
+    slug: &str,
+    body: serde_json::Value,
+) -> (StatusCode, serde_json::Value) {
+    let request = Request::builder()
+        .method("POST")
+        .uri(format!("/v1/workflow/{slug}/run"))
+        .header("content-type", "application/json")
+        .header("X-Api-Key", api_key)
+        .body(Body::from(body.to_string()))
+        .unwrap();
+
+    let response = harness
+        .router()
+        .oneshot(request)
+        .await
+        .expect("the router should not fail");
+    let status = response.status();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null),
+    )
+}
+
 #[tokio::test]
 #[serial]
 async fn a_door_event_runs_the_workflow_and_publishes_to_mqtt() {
@@ -117,3 +210,316 @@ async fn a_non_matching_trigger_runs_nothing() {
 
     assert_eq!(runs, 0, "no workflow should have run");
 }
+
+#[tokio::test]
+#[serial]
+async fn a_matching_subject_cancels_every_delay_armed_for_it() {
+    let harness = Harness::start().await;
+    let presence: EventSubject = ("presence".to_owned(), "livingroom-motion".to_owned());
+    let door: EventSubject = ("door".to_owned(), "front-door".to_owned());
+    let hour = Duration::from_secs(3600);
+
+    for (name, subject) in [
+        ("lamp off", &presence),
+        ("heater off", &presence),
+        ("porch light off", &door),
+    ] {
+        let run = delayed_trigger(name, subject, chrono::TimeDelta::zero());
+        delayed::park(&harness.db, &run, hour).await.unwrap();
+    }
+
+    let mut cancelled = delayed::cancel_for(&harness.db, &presence).await.unwrap();
+    cancelled.sort();
+
+    assert_eq!(cancelled, vec!["heater off", "lamp off"]);
+    assert_eq!(
+        parked(&harness).await,
+        vec!["porch light off"],
+        "a delay armed for a different subject survives"
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn cancelling_an_unarmed_subject_is_a_no_op() {
+    let harness = Harness::start().await;
+    let presence: EventSubject = ("presence".to_owned(), "livingroom-motion".to_owned());
+    let run = delayed_trigger("lamp off", &presence, chrono::TimeDelta::zero());
+    delayed::park(&harness.db, &run, Duration::from_secs(3600))
+        .await
+        .unwrap();
+
+    let other: EventSubject = ("presence".to_owned(), "closet-presence".to_owned());
+
+    assert!(
+        delayed::cancel_for(&harness.db, &other)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(parked(&harness).await, vec!["lamp off"]);
+}
+
+#[tokio::test]
+#[serial]
+async fn an_event_cancels_a_delay_armed_before_startup() {
+    let harness = Harness::start().await;
+
+    // parked by a previous process, so only the queue knows the door is armed
+    let door: EventSubject = ("door".to_owned(), DOOR_ADDRESS.to_owned());
+    let run = delayed_trigger("porch light off", &door, chrono::TimeDelta::zero());
+    delayed::park(&harness.db, &run, Duration::from_secs(3600))
+        .await
+        .unwrap();
+
+    start_dispatcher(&harness).await;
+    harness.event_bus.publish(EventBusMessage::Door {
+        event_id: Uuid::new_v4(),
+        ieee_addr: DOOR_ADDRESS.to_owned(),
+        open: false,
+    });
+
+    wait_for(DB_TIMEOUT, "the delay to be cancelled", || async {
+        parked(&harness).await.is_empty().then_some(())
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn a_delayed_trigger_that_came_due_while_down_runs_on_startup() {
+    let harness = start().await;
+
+    // parked by a previous process, due a minute ago
+    let door: EventSubject = ("door".to_owned(), DOOR_ADDRESS.to_owned());
+    let run = delayed_trigger("test-lamp-on", &door, chrono::TimeDelta::minutes(1));
+    delayed::park(&harness.db, &run, Duration::ZERO)
+        .await
+        .unwrap();
+
+    start_delayed(&harness).await;
+
+    let payload = harness.recorder.expect_publish(LAMP_TOPIC).await;
+    assert_eq!(payload, serde_json::json!({ "state": "ON" }));
+
+    assert_outcome(&harness, "test-lamp-on", "success").await;
+    assert!(parked(&harness).await.is_empty());
+}
+
+#[tokio::test]
+#[serial]
+async fn a_delayed_trigger_overdue_past_resume_within_expires() {
+    let harness = start().await;
+
+    // the fixture keeps the default `workflow.resume_within` of 2h
+    let door: EventSubject = ("door".to_owned(), DOOR_ADDRESS.to_owned());
+    let run = delayed_trigger("test-lamp-on", &door, chrono::TimeDelta::hours(3));
+    delayed::park(&harness.db, &run, Duration::ZERO)
+        .await
+        .unwrap();
+
+    start_delayed(&harness).await;
+
+    assert_outcome(&harness, "test-lamp-on", "expired").await;
+    assert!(parked(&harness).await.is_empty());
+    harness.recorder.assert_no_publish(LAMP_TOPIC);
+}
+
+#[tokio::test]
+#[serial]
+async fn a_delay_step_parks_the_rest_of_the_workflow_and_resumes_it() {
+    let harness = start().await;
+    start_delayed(&harness).await;
+
+    // `test-lamp-flash` turns the lamp on, waits a second, then turns it off
+    let subject: EventSubject = ("test".to_owned(), "flash".to_owned());
+    let run = delayed_trigger("test-lamp-flash", &subject, chrono::TimeDelta::zero());
+    delayed::park(&harness.db, &run, Duration::ZERO)
+        .await
+        .unwrap();
+
+    assert_outcome(&harness, "test-lamp-flash", "delayed").await;
+    assert_outcome(&harness, "test-lamp-flash", "success").await;
+
+    let states = wait_for(DB_TIMEOUT, "the lamp on then off", || async {
+        let states: Vec<serde_json::Value> = harness
+            .recorder
+            .published()
+            .into_iter()
+            .filter(|(topic, _)| topic == LAMP_TOPIC)
+            .map(|(_, payload)| serde_json::from_slice(&payload).unwrap())
+            .collect();
+
+        (states.len() >= 2).then_some(states)
+    })
+    .await;
+
+    assert_eq!(
+        states,
+        vec![
+            serde_json::json!({ "state": "ON" }),
+            serde_json::json!({ "state": "OFF" }),
+        ]
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn a_parked_delay_step_expires_once_the_workflow_is_edited() {
+    let harness = start().await;
+
+    // parked at `test-lamp-flash`'s delay by steps that have since changed
+    let run = DelayedRun {
+        resume: Resume::Step {
+            path: vec![1],
+            digest: Some("steps before the edit".to_owned()),
+        },
+        ..delayed_trigger(
+            "test-lamp-flash",
+            &("test".to_owned(), "flash".to_owned()),
+            chrono::TimeDelta::zero(),
+        )
+    };
+    delayed::park(&harness.db, &run, Duration::ZERO)
+        .await
+        .unwrap();
+
+    start_delayed(&harness).await;
+
+    assert_outcome(&harness, "test-lamp-flash", "expired").await;
+    assert!(parked(&harness).await.is_empty());
+    harness.recorder.assert_no_publish(LAMP_TOPIC);
+}
+
+#[tokio::test]
+#[serial]
+async fn a_variable_trigger_fires_once_its_condition_holds() {
+    let harness = start().await;
+    let settings = harness.state.settings();
+    let visits = &settings.variables["test_visits"];
+    let workflows = &harness.state.workflows;
+
+    let change = workflows
+        .increment_variable("test_visits", visits, 1.0)
+        .await
+        .unwrap();
+    harness.event_bus.publish(change.into_event());
+
+    tokio::time::sleep(Duration::from_millis(750)).await;
+    harness.recorder.assert_no_publish(LAMP_TOPIC);
+
+    let change = workflows
+        .increment_variable("test_visits", visits, 1.0)
+        .await
+        .unwrap();
+    assert_eq!(change.value, "2");
+    harness.event_bus.publish(change.into_event());
+
+    let payload = harness.recorder.expect_publish(LAMP_TOPIC).await;
+    assert_eq!(payload, serde_json::json!({ "state": "ON" }));
+
+    // the workflow's `set_variable` step resets the count once it has run
+    wait_for(DB_TIMEOUT, "the visit count to reset", || async {
+        let value = workflows.variable("test_visits", visits).await.unwrap();
+        (value.as_deref() == Some("0")).then_some(())
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn a_unifi_trigger_fires_only_for_its_client() {
+    let harness = start().await;
+
+    harness.event_bus.publish(EventBusMessage::Unifi {
+        event_id: Uuid::new_v4(),
+        mac_address: "aa:bb:cc:dd:ee:01".to_owned(),
+        client: "someone-else".to_owned(),
+        connected: true,
+    });
+    harness.event_bus.publish(EventBusMessage::Unifi {
+        event_id: Uuid::new_v4(),
+        mac_address: "aa:bb:cc:dd:ee:02".to_owned(),
+        client: "test-phone".to_owned(),
+        connected: false,
+    });
+
+    tokio::time::sleep(Duration::from_millis(750)).await;
+    harness.recorder.assert_no_publish(LAMP_TOPIC);
+
+    harness.event_bus.publish(EventBusMessage::Unifi {
+        event_id: Uuid::new_v4(),
+        mac_address: "aa:bb:cc:dd:ee:02".to_owned(),
+        client: "test-phone".to_owned(),
+        connected: true,
+    });
+
+    let payload = harness.recorder.expect_publish(LAMP_TOPIC).await;
+    assert_eq!(payload, serde_json::json!({ "state": "ON" }));
+}
+
+#[tokio::test]
+#[serial]
+async fn a_workflow_run_by_slug_takes_its_inputs_and_returns_the_trace() {
+    let harness = start().await;
+    let key = harness
+        .mint_key("test-runner", &["rest:workflow:execute:test-visits"])
+        .await;
+
+    let (status, run) = run_by_slug(
+        &harness,
+        &key,
+        "test-set-visits",
+        serde_json::json!({ "inputs": { "visits": "1" }, "wait": true }),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(run["outcome"], "success");
+    assert_eq!(run["trace"]["steps"][0]["kind"], "set_variable");
+    assert_eq!(run["trace"]["vars"][0]["value"], "1");
+
+    let settings = harness.state.settings();
+    let visits = harness
+        .state
+        .workflows
+        .variable("test_visits", &settings.variables["test_visits"])
+        .await
+        .unwrap();
+    assert_eq!(visits.as_deref(), Some("1"));
+}
+
+#[tokio::test]
+#[serial]
+async fn a_workflow_run_by_slug_checks_inputs_and_tag_scopes() {
+    let harness = start().await;
+    let key = harness
+        .mint_key("test-runner", &["rest:workflow:execute:test-visits"])
+        .await;
+
+    let (status, _) = run_by_slug(
+        &harness,
+        &key,
+        "test-set-visits",
+        serde_json::json!({ "inputs": { "visits": "lots" } }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (status, _) = run_by_slug(&harness, &key, "test-lamp-on", serde_json::json!({})).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = run_by_slug(&harness, &key, "no-such-workflow", serde_json::json!({})).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, queued) = run_by_slug(
+        &harness,
+        &key,
+        "test-set-visits",
+        serde_json::json!({ "inputs": { "visits": "1" }, "dry_run": true }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(queued["dry_run"], true);
+    assert_outcome(&harness, "test-set-visits", "success").await;
+}